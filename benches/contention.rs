@@ -0,0 +1,58 @@
+//! Threads logging through `LOG` at once, 1 to 16 of them, one line each
+//! per iteration into a sink that drops them: lines below the level, which
+//! don't take the global lock, then lines written. The throughput is in
+//! lines across the threads.
+//!
+//! ```text
+//! cargo bench --bench contention
+//! ```
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tklog::{debug, info, logsink::LogSink, Format, LEVEL, LOG, PRINTMODE};
+
+struct Discard;
+
+impl LogSink for Discard {
+    fn write(&mut self, _: LEVEL, _: &str) {}
+
+    fn flush(&mut self) {}
+}
+
+/// The time `threads` threads take to run `line` `iters` times each.
+fn at_once(threads: usize, iters: u64, line: fn(usize, u64)) -> Duration {
+    let start = Instant::now();
+    thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                for i in 0..iters {
+                    line(t, i);
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn contention(c: &mut Criterion) {
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_level(LEVEL::Info).set_format(Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName);
+    LOG.set_custom_sink(Box::new(Discard)).set_custom_sink_only(true);
+    let mut group = c.benchmark_group("contention");
+    for threads in [1, 4, 16] {
+        group.throughput(Throughput::Elements(threads as u64));
+        group.bench_with_input(BenchmarkId::new("below_level", threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| at_once(threads, iters, |t, i| debug!("t", t, " ", i)))
+        });
+        group.bench_with_input(BenchmarkId::new("written", threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| at_once(threads, iters, |t, i| info!("t", t, " ", i)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, contention);
+criterion_main!(benches);
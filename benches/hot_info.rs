@@ -0,0 +1,95 @@
+//! A hot loop of Info lines, one record per iteration, through `LOG` and
+//! through a logger of the `*s!` macros, into a sink that drops them: the
+//! time is that of the macro, the layout and the lock. Before its times,
+//! each benchmark prints how many allocations a record takes.
+//!
+//! ```text
+//! cargo bench --bench hot_info
+//! ```
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tklog::{info, infos, logsink::LogSink, sync::Logger, Format, LEVEL, LOG, PRINTMODE};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Discard;
+
+impl LogSink for Discard {
+    fn write(&mut self, _: LEVEL, _: &str) {}
+
+    fn flush(&mut self) {}
+}
+
+const FORMAT: u8 = Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName;
+
+/// Prints the allocations of a record of `line`, once its buffers are warm.
+fn allocations(name: &str, mut line: impl FnMut(u64)) {
+    const RECORDS: u64 = 10_000;
+    for i in 0..100 {
+        line(i);
+    }
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for i in 0..RECORDS {
+        line(i);
+    }
+    let n = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{}: {:.2} allocations per record", name, n as f64 / RECORDS as f64);
+}
+
+fn hot_info(c: &mut Criterion) {
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_level(LEVEL::Info).set_format(FORMAT);
+    LOG.set_custom_sink(Box::new(Discard)).set_custom_sink_only(true);
+    allocations("hot_info/global", |i| info!("request ", i, " served in ", 12, "ms"));
+    c.bench_function("hot_info/global", |b| {
+        let mut i = 0u64;
+        b.iter(|| {
+            info!("request ", i, " served in ", 12, "ms");
+            i += 1;
+        })
+    });
+
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(FORMAT);
+    log.set_custom_sink(Box::new(Discard)).set_custom_sink_only(true);
+    let mut log = Arc::new(Mutex::new(log));
+    allocations("hot_info/multi", |i| infos!(&mut log, "request ", i, " served in ", 12, "ms"));
+    c.bench_function("hot_info/multi", |b| {
+        let mut i = 0u64;
+        b.iter(|| {
+            infos!(&mut log, "request ", i, " served in ", 12, "ms");
+            i += 1;
+        })
+    });
+}
+
+criterion_group!(benches, hot_info);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+//! Logs from many tasks through one shared `Async::Logger` and flushes the
+//! queue before the runtime shuts down, so no line is lost on exit.
+//!
+//! ```text
+//! cargo run --example async_shutdown
+//! ```
+
+use std::{fs, sync::Arc};
+
+use tklog::{async_infos, Async::Logger, Format, LEVEL};
+
+#[tokio::main]
+async fn main() {
+    let dir = std::env::temp_dir().join(format!("tklog_example_async_shutdown_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("app.log");
+
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false).await;
+    let log = Arc::new(log);
+
+    let tasks: Vec<_> = (0..8)
+        .map(|t| {
+            let log = log.clone();
+            tokio::spawn(async move {
+                for i in 0..100 {
+                    async_infos!(&log, format!("task {} line {}\n", t, i));
+                }
+            })
+        })
+        .collect();
+    for t in tasks {
+        t.await.unwrap();
+    }
+    // The lines are still queued when the tasks finish; wait for the writer.
+    log.flush().await;
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(content.lines().count(), 800);
+    for t in 0..8 {
+        assert!(content.contains(&format!("task {} line 99\n", t)));
+    }
+    println!("{} lines written", content.lines().count());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn example() {
+    main();
+}
@@ -0,0 +1,76 @@
+//! Writes one JSON object per line by rewriting the file body, while the
+//! console keeps the plain layout. The time is frozen with a test mode so
+//! the output can be compared verbatim.
+//!
+//! ```text
+//! cargo run --example json_output
+//! ```
+
+use std::fs;
+
+use chrono::{Local, TimeZone};
+use tklog::{sync::Logger, Format, TestMode, LEVEL};
+
+/// Quotes `s` as a JSON string.
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("tklog_example_json_output_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("app.json");
+
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_format(Format::LevelFlag | Format::Date | Format::Time)
+        .set_formatter("{level}|{time}|{message}")
+        .set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    log.set_attr_format(|fmt| {
+        fmt.set_level_fmt(|level| format!("{:?}", level).to_lowercase());
+        fmt.set_file_body_fmt(|_, body| {
+            let mut parts = body.splitn(3, '|');
+            let (level, time, message) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            format!("{{\"level\":{},\"time\":{},\"message\":{}}}\n", quote(level), quote(time.trim()), quote(message))
+        });
+    });
+    log.set_test_mode(TestMode {
+        fixed_time: Local.with_ymd_and_hms(2024, 5, 1, 12, 30, 45).unwrap(),
+        fixed_seq_start: 1,
+    })
+    .unwrap();
+
+    for (level, message) in [(LEVEL::Info, "user \"ann\" logged in"), (LEVEL::Warn, "disk 91% full")] {
+        let s = log.fmt(module_path!(), level, "", 0, message.to_string());
+        log.print(level, module_path!(), s);
+    }
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        concat!(
+            r#"{"level":"info","time":"2024-05-01 12:30:45","message":"user \"ann\" logged in"}"#,
+            "\n",
+            r#"{"level":"warn","time":"2024-05-01 12:30:45","message":"disk 91% full"}"#,
+            "\n",
+        )
+    );
+    print!("{}", content);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn example() {
+    main();
+}
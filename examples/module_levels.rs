@@ -0,0 +1,50 @@
+//! Per-module levels: a noisy dependency is held at Warn while the
+//! application logs at Debug, then the override is cleared at runtime.
+//!
+//! ```text
+//! cargo run --example module_levels
+//! ```
+
+use std::fs;
+
+use tklog::{sync::Logger, Format, LogOption, LEVEL};
+
+fn log_all(log: &mut Logger, module: &str) {
+    for level in [LEVEL::Debug, LEVEL::Info, LEVEL::Warn] {
+        if log.get_level(module) <= level {
+            let s = log.fmt(module, level, "", 0, format!("{} {:?}\n", module, level));
+            log.print(level, module, s);
+        }
+    }
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("tklog_example_module_levels_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("app.log");
+
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Debug).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    log.set_mod_option("hyper::*", LogOption { level: Some(LEVEL::Warn), ..LogOption::new() });
+    assert_eq!(log.module_levels(), [("hyper::*".to_string(), LEVEL::Warn)]);
+
+    log_all(&mut log, "app::server");
+    log_all(&mut log, "hyper::proto");
+    assert!(log.clear_module_level("hyper::*"));
+    log_all(&mut log, "hyper::proto");
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        "app::server Debug\napp::server Info\napp::server Warn\n\
+         hyper::proto Warn\n\
+         hyper::proto Debug\nhyper::proto Info\nhyper::proto Warn\n"
+    );
+    print!("{}", content);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn example() {
+    main();
+}
@@ -0,0 +1,64 @@
+//! Two independent loggers shared between threads with the `*s!` macros:
+//! an access log taking everything from Info up, and an error log taking
+//! only Error and above.
+//!
+//! ```text
+//! cargo run --example multi_logger
+//! ```
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use tklog::{errors, infos, sync::Logger, Format, LEVEL};
+
+fn logger(path: &std::path::Path, level: LEVEL) -> Arc<Mutex<Logger>> {
+    let mut log = Logger::new();
+    log.set_console(false).set_level(level).set_format(Format::LevelFlag).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    Arc::new(Mutex::new(log))
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("tklog_example_multi_logger_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let access = logger(&dir.join("access.log"), LEVEL::Info);
+    let error = logger(&dir.join("error.log"), LEVEL::Error);
+
+    let workers: Vec<_> = (0..4)
+        .map(|w| {
+            let (mut access, mut error) = (access.clone(), error.clone());
+            thread::spawn(move || {
+                for i in 0..25 {
+                    infos!(&mut access, "worker", w, "request", i);
+                    if i % 5 == 0 {
+                        errors!(&mut error, "worker", w, "request", i, "failed");
+                    }
+                    // Below the error log's level: dropped.
+                    infos!(&mut error, "worker", w, "request", i);
+                }
+            })
+        })
+        .collect();
+    for w in workers {
+        w.join().unwrap();
+    }
+
+    let access = fs::read_to_string(dir.join("access.log")).unwrap();
+    let error = fs::read_to_string(dir.join("error.log")).unwrap();
+    assert_eq!(access.lines().count(), 100);
+    assert!(access.lines().all(|l| l.starts_with("[INFO]")));
+    assert_eq!(error.lines().count(), 20);
+    assert!(error.lines().all(|l| l.starts_with("[ERROR]") && l.ends_with("failed")));
+    for w in 0..4 {
+        assert!(error.contains(&format!("worker{}request20failed", w)));
+    }
+    println!("{} access lines, {} error lines", access.lines().count(), error.lines().count());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn example() {
+    main();
+}
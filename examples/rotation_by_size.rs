@@ -0,0 +1,39 @@
+//! Rotates `app.log` every 1 KiB, keeping two backups.
+//!
+//! ```text
+//! cargo run --example rotation_by_size
+//! ```
+
+use std::fs;
+
+use tklog::{sync::Logger, Format, LEVEL};
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("tklog_example_rotation_by_size_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("app.log");
+
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 1024, 2, false);
+    for i in 0..100 {
+        let s = log.fmt(module_path!(), LEVEL::Info, "", 0, format!("request {:03} served in 12ms\n", i));
+        log.print(LEVEL::Info, module_path!(), s);
+    }
+
+    // 100 lines of 27 bytes fill two 1 KiB files, cut into app_1.log and
+    // app_2.log; app.log holds the lines written since the last cut.
+    let mut files: Vec<String> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+    files.sort();
+    assert_eq!(files, ["app.log", "app_1.log", "app_2.log"]);
+    for f in &files {
+        assert!(fs::metadata(dir.join(f)).unwrap().len() <= 1024, "{} is over the max size", f);
+    }
+    assert!(fs::read_to_string(&path).unwrap().ends_with("request 099 served in 12ms\n"));
+    println!("rotated into {:?}", files);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn example() {
+    main();
+}
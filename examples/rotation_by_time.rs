@@ -0,0 +1,43 @@
+//! Rotates `app.log` hourly. The file is back-dated by two hours, so the
+//! first write cuts it into a backup stamped with the hour it was written in.
+//!
+//! ```text
+//! cargo run --example rotation_by_time
+//! ```
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use tklog::{sync::Logger, Format, LEVEL, MODE};
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("tklog_example_rotation_by_time_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.log");
+    let written = SystemTime::now() - Duration::from_secs(7200);
+    let mut f = File::create(&path).unwrap();
+    f.write_all(b"from two hours ago\n").unwrap();
+    f.set_modified(written).unwrap();
+    drop(f);
+
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_cutmode_by_time(path.to_str().unwrap(), MODE::HOUR, 10, false);
+    let s = log.fmt(module_path!(), LEVEL::Info, "", 0, "this hour\n".to_string());
+    log.print(LEVEL::Info, module_path!(), s);
+
+    let backup = dir.join(format!("app_{}_1.log", DateTime::<Local>::from(written).format("%Y%m%d%H")));
+    assert_eq!(fs::read_to_string(&backup).unwrap(), "from two hours ago\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "this hour\n");
+    println!("cut into {}", backup.display());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn example() {
+    main();
+}
@@ -0,0 +1,31 @@
+/* The C ABI of tklog, built with the `ffi` feature; see src/ffi.rs. */
+
+#ifndef TKLOG_H
+#define TKLOG_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TKLOG_TRACE 1
+#define TKLOG_DEBUG 2
+#define TKLOG_INFO 3
+#define TKLOG_WARN 4
+#define TKLOG_ERROR 5
+#define TKLOG_FATAL 6
+
+/* Logs `msg` from `module` at `level` through the global logger. NULL
+ * strings read as empty ones; invalid UTF-8 is replaced. */
+void tklog_log(uint8_t level, const char *module, const char *file, uint32_t line, const char *msg);
+
+/* True if a line of `module` at `level` would be logged. */
+bool tklog_enabled(uint8_t level, const char *module);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TKLOG_H */
@@ -14,17 +14,76 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::future::Future;
+use std::io;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::asyncfile::FileHandler;
-use crate::handle::{FHandler, FileOptionType, FmtHandler};
-use crate::tklog::asynclog;
+use crate::boot;
+use crate::bridge::{self, LogBridge};
+use crate::budget::{self, AdaptiveBudget};
+use crate::builder::AsyncLoggerBuilder;
+use crate::capture::Captures;
+use crate::channel::{self, ChannelBound};
+use crate::events::Events;
+use crate::fields::{self, DynamicFields, FieldLimits, FieldMap};
+use crate::filter::{Filter, Filters, LogRecord};
+use crate::hook::{Hook, Hooks};
+use crate::guard::{Guarded, PanicCount};
+use crate::output::{Gate, OutputLevels, OutputMode, OutputModes, OutputSink};
+use crate::paths::{Claim, Paths, DEFAULT_FILE};
+use crate::handle::{self, FHandler, FileOption, FileOptionType, FileSettings, FmtHandler};
+use crate::health::{Degradation, Health};
+use crate::config::{self, describe_changes, ConfigWatch, FileConfig, LogConfig};
+use crate::cut::{CutConfig, CutMixed, CutSize, CutTime};
+use crate::diagnostics::{self, Category};
+use crate::directory::{DirLayout, Directory};
+use crate::memory::{self, Held};
+use crate::metrics::LogMetrics;
+use crate::{fd_exhausted, global_async, global_async_blocking, Inside};
+use crate::batch::{Batch, BatchPolicy, Wait};
+use crate::block::{self, BlockWriter};
+use crate::callers::{self, CallerTrace};
+use crate::clock::{Clock, SystemClock};
+use crate::color::{ColorOptions, ConsoleColors};
+use crate::json::Schema;
+use crate::levelspec::{self, LevelSpec};
+use crate::logerror::{self, ErrorHandler, LogError};
+use crate::logsink::LogSink;
+use crate::persist::{Pending, Persisting};
+use crate::preset::{K8sPreset, Preset};
+use crate::quota::{Admission, Quota};
+use crate::record::{FormatStage, LogFormatter, RecordFormatter, RecordSnapshot, Render};
+use crate::rotation::RotationGroup;
+use crate::routing::{self, LevelSet, Routes, RoutingTable, Sink};
+use crate::remote::{Remote, RemoteConfig};
+use crate::runtime::{Rt, Runtime};
+use crate::scheduler::Scheduler;
+use crate::writebuf::Flusher;
+use crate::stats::{LogStats, StatsCollector};
+use crate::storm::{StormConfig, StormControl};
+#[cfg(feature = "journald")]
+use crate::journald::{Journald, JOURNALD_SOCKET};
+#[cfg(feature = "syslog")]
+use crate::syslog::{Syslog, SyslogConfig};
+use crate::tee::{self, TeeLayout};
 use crate::trie::Trie;
+use crate::backupname::BackupTemplate;
+use crate::verify::TamperKey;
 use crate::{
-    arguments_to_string, l2tk, log_fmt, AttrFormat, Format, LogContent, LogContext, LogOption,
-    LogOptionConst, OptionTrait, LEVEL, MODE, PRINTMODE, TKLOG2ASYNC_LOG,
+    check_column_order, check_file_pattern, check_time_format, init_time_zone, now, places, subseq, thread_label, AttrFormat, CompressType, ConsoleStream, Error, Format, LogContent, LogContext,
+    LocationStrategy, LogOption, LogOptionConst, OptionTrait, OverflowPolicy, ParseSizeError, PrunePolicy, RotationEvent, TestMode, COLUMN, CUTMODE, LEVEL, MODE, PRINTMODE, RFC3339_TIME_FORMAT,
 };
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
 /// this is the tklog encapsulated Logger whose File operations
 /// are based on tokio, Therefore, it supports asynchronous scenarios
@@ -48,544 +107,3666 @@ use tokio::sync::mpsc;
 /// };
 /// ```
 pub struct Logger {
-    sender: mpsc::UnboundedSender<(LEVEL, String, LogContent)>,
+    sender: mpsc::UnboundedSender<Job>,
+    /// The receiving end of `sender`, kept here so that the lines queued
+    /// outlive a consumer dropped with its runtime.
+    queue: SharedQueue,
+    /// `WORKER_RUNNING`, `WORKER_STOPPED` or `WORKER_PANICKED`.
+    worker: Arc<AtomicU8>,
+    /// The batches the consumer writes, see `set_delay_batch`.
+    batch: Arc<BatchPolicy>,
+    /// See `set_channel_capacity`.
+    channel: Arc<ChannelBound>,
+    /// Set when leaving `PRINTMODE::DELAY`: the next direct write waits for
+    /// the queue first.
+    drain_queue: AtomicBool,
+    /// The runtime the consumer was spawned on, to restart it there.
+    runtime: Mutex<Option<tokio::runtime::Handle>>,
+    started: AtomicBool,
+    prestart: AtomicUsize,
+    prestart_lines: usize,
     fmthandle: FmtHandler,
-    filehandle: (String, FHandler),
+    filehandle: (String, SharedHandler),
     mutex: tokio::sync::Mutex<u32>,
     pub mode: PRINTMODE,
     modmap: Trie<(LogOptionConst, String)>,
-    fmap: HashMap<String, FHandler>,
+    /// The level option of each module looked up, per generation of
+    /// `modmap`.
+    level_cache: RwLock<(u64, HashMap<String, Option<LEVEL>>)>,
+    /// The module patterns of the last `set_level_from_env`.
+    env_modules: Vec<String>,
+    fmap: HashMap<String, SharedHandler>,
+    paths: Paths,
+    /// The handlers of `fmap`, for the queue consumer to close when file
+    /// descriptors run out.
+    module_files: ModuleFiles,
     custom_handler: Option<fn(&LogContext) -> bool>,
+    custom_panics: PanicCount,
+    filters: Filters,
+    hooks: Hooks,
+    captures: Captures,
     separator: String,
     levels: Option<[Option<(LogOption, String)>; 7]>,
     // levelfmt: Option<Box<dyn Fn(LEVEL) -> String + Send + Sync>>,
     // timefmt: Option<Box<dyn Fn() -> (String, String, String) + Send + Sync>>,
-    attrfmt: AttrFormat,
+    render: Arc<Render>,
+    format_stage: FormatStage,
+    output: OutputModes,
+    output_levels: OutputLevels,
+    testmode: Option<TestMode>,
+    seq: AtomicU64,
+    subseq: bool,
+    boot_id: bool,
+    /// See `set_function_with_module`.
+    function_module: bool,
+    /// See `set_error_backtraces`.
+    error_backtraces: bool,
+    location_strategy: LocationStrategy,
+    filesettings: FileSettings,
+    /// The SIGHUPs seen, see `enable_reopen_on_sighup`.
+    #[cfg(all(unix, feature = "signal"))]
+    hangups: Option<AtomicU64>,
+    stats: Arc<StatsCollector>,
+    latency_sampling: u64,
+    sampled: AtomicU64,
+    storm: Option<Mutex<StormControl>>,
+    callers: Mutex<Option<CallerTrace>>,
+    persisting: Mutex<Persisting>,
+    clock: Arc<dyn Clock>,
+    budget: Option<Mutex<AdaptiveBudget>>,
+    events: Mutex<Events>,
+    quotas: Mutex<BTreeMap<String, Quota>>,
+    scheduler: Scheduler,
+    flusher: Flusher,
+    pending: Mutex<Vec<(LEVEL, LogContent)>>,
+    routing: Option<RoutingTable>,
+    sinks: HashMap<String, String>,
+    /// The files of `add_level_file`, with their levels.
+    level_files: Vec<(Vec<LEVEL>, String)>,
+    directory: Option<Mutex<Directory<Arc<tokio::sync::Mutex<FHandler>>>>>,
+    /// The tee files by name, in the order of `Render::tees`.
+    tees: Vec<(String, SharedHandler)>,
+    groups: Mutex<Vec<RotationGroup>>,
+    dynamic_fields: Option<Guarded<DynamicFields>>,
+    context_fields: bool,
+    field_limits: FieldLimits,
+    custom_sink: Option<SharedSink>,
+    custom_sink_only: bool,
+    #[cfg(feature = "otel")]
+    auto_trace_ids: bool,
+    remote: Option<Remote>,
+    #[cfg(feature = "journald")]
+    journald: Option<Mutex<Journald>>,
+    #[cfg(feature = "syslog")]
+    syslog: Option<Mutex<Syslog>>,
+    console: Arc<tokio::sync::Mutex<FHandler>>,
+}
+
+/// A handler shared with the queue consumer. What the synchronous accessors
+/// need to know about its file is kept beside it, so they never wait for a write.
+#[derive(Clone)]
+struct SharedHandler {
+    inner: Arc<tokio::sync::Mutex<FHandler>>,
+    file: Option<FileConfig>,
+    rotating: bool,
+}
+
+impl SharedHandler {
+    fn new(fhandler: FHandler) -> Self {
+        SharedHandler {
+            file: fhandler.file_config(),
+            rotating: fhandler.rotating_file().is_some(),
+            inner: Arc::new(tokio::sync::Mutex::new(fhandler)),
+        }
+    }
+
+    async fn set_async_file_handler(&mut self, filehandler: FileHandler) {
+        let mut h = self.inner.lock().await;
+        h.set_async_file_handler(filehandler);
+        self.file = h.file_config();
+        self.rotating = h.rotating_file().is_some();
+    }
+}
+
+type ModuleFiles = Arc<Mutex<Vec<Arc<tokio::sync::Mutex<FHandler>>>>>;
+
+/// The custom sink, shared with the queue consumer.
+type SharedSink = Arc<Mutex<Box<dyn LogSink>>>;
+
+/// The queue consumer, to spawn on a runtime.
+type Consumer = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// The queue, locked by the consumer that runs.
+type SharedQueue = Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<Job>>>;
+
+/// The consumer is spawned, or not started yet.
+const WORKER_RUNNING: u8 = 0;
+/// The consumer was dropped without panicking, with its runtime; the next
+/// line queued spawns it again.
+const WORKER_STOPPED: u8 = 1;
+/// The consumer panicked and closed the queue, see `Logger::try_recover`.
+const WORKER_PANICKED: u8 = 2;
+
+/// Owned by the consumer, to tell the logger when it is dropped. A panic
+/// also closes and drains the queue: its lines are shed and `flush`
+/// returns, rather than wait for a consumer that may panic again.
+struct WorkerGuard {
+    state: Arc<AtomicU8>,
+    queue: SharedQueue,
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.state.store(WORKER_STOPPED, Ordering::Release);
+            return;
+        }
+        if let Ok(mut receiver) = self.queue.try_lock() {
+            receiver.close();
+            while receiver.try_recv().is_ok() {}
+        }
+        self.state.store(WORKER_PANICKED, Ordering::Release);
+    }
+}
+
+/// The lines queued before the consumer runs, by default.
+const PRESTART_LINES: usize = 65536;
+
+/// What the queue consumer is handed. A line carries the handler it was
+/// routed to, so the consumer writes it without going back to the logger;
+/// settings go through the queue too, to apply in order with the lines.
+enum Job {
+    Line(Target, Held<Payload>, Option<Instant>),
+    Settings(Arc<tokio::sync::Mutex<FHandler>>, FileSettings),
+    Flush(Vec<Arc<tokio::sync::Mutex<FHandler>>>, oneshot::Sender<()>),
+    Rotate(GroupRotation),
+    Reopen(Vec<(String, Arc<tokio::sync::Mutex<FHandler>>)>, oneshot::Sender<io::Result<()>>),
+    Custom(SharedSink, LEVEL, Payload),
+}
+
+/// Reopens the files of `handlers`, named as they are reported; errs with
+/// the first that can't be opened, after trying every one.
+async fn reopen_files(handlers: Vec<(String, Arc<tokio::sync::Mutex<FHandler>>)>) -> io::Result<()> {
+    let mut result = Ok(());
+    for (filename, handler) in handlers {
+        if let Err(e) = handler.lock().await.async_reopen_file().await {
+            diagnostics::report(Category::Reopen, Some(Path::new(&filename)), format!("cannot reopen {}: {}", filename, e));
+            result = result.and(Err(e));
+        }
+    }
+    result
+}
+
+/// The members of a rotation group to rotate at once, see `rotation`.
+struct GroupRotation {
+    members: Vec<(String, Arc<tokio::sync::Mutex<FHandler>>)>,
+    stamp: u64,
+    startsec: u64,
+    empty_backups: bool,
+}
+
+impl GroupRotation {
+    async fn run(self) {
+        for (id, handler) in self.members {
+            if let Err(e) = handler.lock().await.async_rotate_as(self.stamp, self.startsec, self.empty_backups).await {
+                diagnostics::report(Category::Reopen, Some(Path::new(&id)), format!("cannot rotate {} with its group: {}", id, e));
+            }
+        }
+    }
+}
+
+/// A queued line: laid out already, or a snapshot the consumer lays out
+/// once for all its targets under `FormatStage::Worker`.
+#[derive(Clone)]
+enum Payload {
+    Rendered(LogContent),
+    Deferred(Arc<Deferred>),
+}
+
+struct Deferred {
+    record: RecordSnapshot<'static>,
+    fmat: u8,
+    formatter: Option<String>,
+    render: Arc<Render>,
+    content: OnceLock<LogContent>,
+}
+
+impl Payload {
+    /// The bytes a queued line holds, for the memory budget.
+    fn size(&self) -> usize {
+        match self {
+            Payload::Rendered(content) => content.size(),
+            Payload::Deferred(d) => d.record.message.len() + d.record.module.len() + d.record.file.len() + d.record.fields.iter().map(|(k, v)| k.len() + v.len()).sum::<usize>(),
+        }
+    }
+
+    fn content(&self) -> &LogContent {
+        match self {
+            Payload::Rendered(content) => content,
+            Payload::Deferred(d) => d.content.get_or_init(|| d.render.content(&d.record, d.fmat, d.formatter.as_ref())),
+        }
+    }
+}
+
+/// Where one line goes, resolved by `Logger::target`.
+struct Target {
+    sink: String,
+    handler: Arc<tokio::sync::Mutex<FHandler>>,
+    console: bool,
+    /// The default handler, when `handler` is another one.
+    fallback: Option<Arc<tokio::sync::Mutex<FHandler>>>,
+    /// The index of the tee file `handler` writes, which takes its own body.
+    tee: Option<usize>,
+}
+
+/// Writes one line to its target. Out of file descriptors, the other
+/// module files are closed to make room and the write is retried once; if
+/// it still fails the line goes to the default file instead.
+async fn write_line(target: &Target, module_files: &ModuleFiles, stats: &StatsCollector, message: &LogContent) {
+    if let Some(i) = target.tee {
+        if let Some(body) = message.tees.get(i) {
+            if target.handler.lock().await.async_write_body(body).await.is_ok() {
+                stats.tee_written(&target.sink, body.len());
+            }
+        }
+        return;
+    }
+    match target.handler.lock().await.async_write_line(target.console, message).await {
+        Err(e) if fd_exhausted(&e) => {}
+        _ => return,
+    }
+    reclaim_descriptors(module_files, Some(&target.handler)).await;
+    let mut handler = target.handler.lock().await;
+    if let Err(e) = handler.async_write_line(false, message).await {
+        if let Some(fallback) = &target.fallback {
+            if handler.degrade() {
+                diagnostics::report(Category::Reopen, Some(Path::new(&target.sink)), format!("cannot reopen {}: {}; its lines go to the default file", target.sink, e));
+            }
+            drop(handler);
+            let _ = fallback.lock().await.async_write_line(false, message).await;
+        }
+    }
+}
+
+/// Closes every module file but `keep`'s; each reopens on its next write.
+/// Handlers busy elsewhere are skipped.
+async fn reclaim_descriptors(module_files: &ModuleFiles, keep: Option<&Arc<tokio::sync::Mutex<FHandler>>>) {
+    let handlers = module_files.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    for handler in handlers {
+        if keep.is_some_and(|k| Arc::ptr_eq(k, &handler)) {
+            continue;
+        }
+        if let Ok(mut h) = handler.try_lock() {
+            h.async_release_file().await;
+        }
+    }
+}
+
+/// A line of the queue held in a batch.
+type BatchedLine = (Target, Held<Payload>, Option<Instant>);
+
+/// The queue consumer, writing the jobs of `queue` and its lines in the
+/// batches of `policy`.
+fn queue_consumer(queue: SharedQueue, state: Arc<AtomicU8>, stats: Arc<StatsCollector>, module_files: ModuleFiles, policy: Arc<BatchPolicy>, bound: Arc<ChannelBound>) -> Consumer {
+    let guard = WorkerGuard { state, queue };
+    Box::pin(async move {
+        let guard = guard;
+        let mut receiver = guard.queue.clone().lock_owned().await;
+        // The custom sink written since its last flush.
+        let mut unflushed: Option<SharedSink> = None;
+        let mut batch: Batch<BatchedLine> = Batch::default();
+        loop {
+            let next = match batch.wait(&policy) {
+                Wait::Idle => match receiver.recv().await {
+                    Some(job) => Some(job),
+                    None => break,
+                },
+                Wait::Queued => receiver.try_recv().ok(),
+                Wait::Until(deadline) => Rt::timeout_at(deadline, receiver.recv()).await.flatten(),
+                Wait::Due => None,
+            };
+            let Some(job) = next else {
+                write_batch(&stats, &module_files, batch.take()).await;
+                bound.taken();
+                flush_drained(&mut unflushed, receiver.is_empty());
+                continue;
+            };
+            if let Job::Line(target, msg, enqueued_at) = job {
+                batch.push((target, msg, enqueued_at));
+                continue;
+            }
+            // A job comes after the lines queued before it.
+            write_batch(&stats, &module_files, batch.take()).await;
+            bound.taken();
+            match job {
+                Job::Line(..) => {}
+                Job::Settings(handler, settings) => {
+                    // Nothing in flight, for a live compressed file to be closed in place.
+                    let mut handler = handler.lock().await;
+                    let _ = handler.async_flush().await;
+                    handler.set_file_settings(&settings);
+                }
+                Job::Rotate(rotation) => rotation.run().await,
+                Job::Reopen(handlers, done) => {
+                    let _ = done.send(reopen_files(handlers).await);
+                }
+                Job::Flush(handlers, done) => {
+                    for handler in handlers {
+                        let _ = handler.lock().await.async_flush().await;
+                    }
+                    flush_drained(&mut unflushed, true);
+                    let _ = done.send(());
+                }
+                Job::Custom(sink, level, msg) => {
+                    sink.lock().unwrap_or_else(|e| e.into_inner()).write(level, &msg.content().file_body);
+                    if let Some(previous) = unflushed.replace(sink.clone()).filter(|s| !Arc::ptr_eq(s, &sink)) {
+                        previous.lock().unwrap_or_else(|e| e.into_inner()).flush();
+                    }
+                }
+            }
+            flush_drained(&mut unflushed, receiver.is_empty());
+        }
+    })
+}
+
+async fn write_batch(stats: &StatsCollector, module_files: &ModuleFiles, lines: Vec<BatchedLine>) {
+    for (target, msg, enqueued_at) in lines {
+        let Some(msg) = msg.take() else {
+            stats.shed(&target.sink);
+            continue;
+        };
+        write_line(&target, module_files, stats, msg.content()).await;
+        stats.written(&target.sink, enqueued_at);
+    }
+}
+
+/// Flushes the custom sink once the queue is drained: one flush for the batch.
+fn flush_drained(unflushed: &mut Option<SharedSink>, drained: bool) {
+    if drained {
+        if let Some(sink) = unflushed.take() {
+            sink.lock().unwrap_or_else(|e| e.into_inner()).flush();
+        }
+    }
 }
 
 impl Logger {
+    /// The logger behind `ASYNC_LOG`, whose files are checked against
+    /// those of the global sync logger.
+    pub(crate) fn new_global() -> Self {
+        Logger { paths: Paths::global("global async logger"), ..Logger::new() }
+    }
+
+    /// A logger with the levels of `TKLOG_LEVEL`, see `levelspec`.
+    pub fn from_env() -> Self {
+        let mut log = Self::new();
+        log.set_level_from_env(levelspec::TKLOG_LEVEL);
+        log
+    }
+
+    /// A logger configured in one expression, see `builder`.
+    pub fn builder() -> AsyncLoggerBuilder {
+        AsyncLoggerBuilder::default()
+    }
+
     pub fn new() -> Self {
-        let (sender, mut receiver) = mpsc::unbounded_channel();
-        tokio::spawn(async move {
-            while let Some(message) = receiver.recv().await {
-                let (level, module, msg) = message;
-                let m1: String = module;
-                let m2: LogContent = msg;
-                crate::async_log!(level, m1.as_str(), m2);
-            }
-        });
+        let log = Logger::unstarted();
+        log.start();
+        log
+    }
+
+    /// A logger whose queue consumer runs on `handle`, whichever runtime
+    /// it is made or used in, such as the one of an application's
+    /// background work. Should that runtime shut down first, the next line
+    /// queued spawns the consumer again: on the current runtime, or on a
+    /// thread of its own outside any. The lines queued meanwhile are kept.
+    pub fn with_runtime(handle: &tokio::runtime::Handle) -> Self {
+        let log = Logger::unstarted();
+        log.attach_runtime(handle);
+        log
+    }
+
+    fn unstarted() -> Self {
+        init_time_zone();
+        let (sender, receiver) = mpsc::unbounded_channel::<Job>();
         Logger {
             sender,
+            queue: Arc::new(tokio::sync::Mutex::new(receiver)),
+            worker: Arc::new(AtomicU8::new(WORKER_RUNNING)),
+            batch: Arc::default(),
+            channel: Arc::default(),
+            drain_queue: AtomicBool::new(false),
+            runtime: Mutex::new(None),
+            started: AtomicBool::new(false),
+            prestart: AtomicUsize::new(0),
+            prestart_lines: PRESTART_LINES,
             fmthandle: FmtHandler::new(),
-            filehandle: ("".to_string(), FHandler::new()),
+            filehandle: ("".to_string(), SharedHandler::new(FHandler::new())),
             mutex: tokio::sync::Mutex::new(0),
             mode: PRINTMODE::DELAY,
             modmap: Trie::new(),
+            level_cache: RwLock::new((0, HashMap::new())),
+            env_modules: Vec::new(),
             fmap: HashMap::new(),
+            paths: Paths::default(),
+            module_files: ModuleFiles::default(),
             custom_handler: None,
+            custom_panics: PanicCount::default(),
+            filters: Filters::default(),
+            hooks: Hooks::default(),
+            captures: Captures::default(),
             separator: "".to_string(),
             levels: None,
             // levelfmt: None,
             // timefmt: None,
-            attrfmt: AttrFormat::new(),
+            render: Arc::default(),
+            format_stage: FormatStage::CallSite,
+            output: OutputModes::default(),
+            output_levels: OutputLevels::default(),
+            testmode: None,
+            seq: AtomicU64::new(1),
+            subseq: false,
+            boot_id: false,
+            function_module: false,
+            error_backtraces: false,
+            location_strategy: LocationStrategy::default(),
+            filesettings: FileSettings::default(),
+            #[cfg(all(unix, feature = "signal"))]
+            hangups: None,
+            stats: Arc::new(StatsCollector::new()),
+            latency_sampling: 64,
+            sampled: AtomicU64::new(0),
+            storm: None,
+            callers: Mutex::new(None),
+            persisting: Mutex::new(Persisting::default()),
+            clock: Arc::new(SystemClock),
+            budget: None,
+            events: Mutex::default(),
+            quotas: Mutex::new(BTreeMap::new()),
+            scheduler: Scheduler::new_task(),
+            flusher: Flusher::new_task(),
+            pending: Mutex::new(Vec::new()),
+            routing: None,
+            sinks: HashMap::new(),
+            level_files: Vec::new(),
+            directory: None,
+            tees: Vec::new(),
+            groups: Mutex::new(Vec::new()),
+            dynamic_fields: None,
+            context_fields: true,
+            field_limits: FieldLimits::default(),
+            custom_sink: None,
+            custom_sink_only: false,
+            #[cfg(feature = "otel")]
+            auto_trace_ids: false,
+            remote: None,
+            #[cfg(feature = "journald")]
+            journald: None,
+            #[cfg(feature = "syslog")]
+            syslog: None,
+            console: Arc::new(tokio::sync::Mutex::new(FHandler::new())),
         }
     }
 
-    pub async fn print(&mut self, level: LEVEL, module: &str, message: LogContent) {
-        let mut console = self.fmthandle.get_console();
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, filename) = mm;
-                if let Some(cs) = lo.console {
-                    console = cs
-                }
-                if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.async_print(console, message).await;
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.async_print(console, message).await;
-                        }
-                    }
-                    return;
-                }
+    /// Spawns the queue consumer on `handle`, for a logger made outside a
+    /// runtime that is fed from threads outside it too. The lines queued
+    /// so far are written first. Does nothing once the consumer runs.
+    pub fn attach_runtime(&self, handle: &tokio::runtime::Handle) -> &Self {
+        let mut runtime = self.runtime.lock().unwrap_or_else(|e| e.into_inner());
+        if !self.started.load(Ordering::Acquire) && self.spawn_on(handle) {
+            *runtime = Some(handle.clone());
+            self.started.store(true, Ordering::Release);
+        }
+        self
+    }
+
+    fn consumer(&self) -> Consumer {
+        queue_consumer(self.queue.clone(), self.worker.clone(), self.stats.clone(), self.module_files.clone(), self.batch.clone(), self.channel.clone())
+    }
+
+    /// Spawns the consumer on `handle`; false if the runtime is shutting
+    /// down and dropped it.
+    fn spawn_on(&self, handle: &tokio::runtime::Handle) -> bool {
+        self.worker.store(WORKER_RUNNING, Ordering::Release);
+        handle.spawn(self.consumer());
+        self.worker.load(Ordering::Acquire) != WORKER_STOPPED
+    }
+
+    /// Spawns the consumer again once its runtime has shut down: on that
+    /// runtime if it takes it, else on the current one, else on a thread
+    /// of its own with a runtime of its own.
+    fn revive(&self) {
+        let mut runtime = self.runtime.lock().unwrap_or_else(|e| e.into_inner());
+        if self.worker.load(Ordering::Acquire) != WORKER_STOPPED {
+            return;
+        }
+        let candidates: Vec<tokio::runtime::Handle> = runtime.iter().cloned().chain(tokio::runtime::Handle::try_current().ok()).collect();
+        for handle in candidates {
+            if self.spawn_on(&handle) {
+                *runtime = Some(handle);
+                return;
             }
         }
+        let consumer = self.consumer();
+        self.worker.store(WORKER_RUNNING, Ordering::Release);
+        // Without a thread the consumer is dropped, and the next line tries again.
+        let _ = thread::Builder::new().name("tklog-async".to_string()).spawn(move || {
+            if let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                rt.block_on(consumer);
+            }
+        });
+    }
 
-        if let Some(levels) = &self.levels {
-            if let Some(lp) = &levels[level as usize - 1] {
-                let (lo, filename) = lp;
-                if let Some(cs) = lo.console {
-                    console = cs
-                }
-                if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.async_print(console, message).await;
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.async_print(console, message).await;
-                        }
-                    }
-                    return;
-                }
+    /// Whether the queue consumer runs; a logger made outside a runtime
+    /// starts it on the first line queued within one, or on `attach_runtime`.
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::Acquire)
+    }
+
+    /// The lines kept until the queue consumer runs; those past it are
+    /// dropped, see `LogStats::prestart_dropped`. Default: 65536.
+    pub fn set_prestart_buffer(&mut self, lines: usize) -> &mut Self {
+        self.prestart_lines = lines;
+        self
+    }
+
+    /// Spawns the queue consumer on the current runtime, if any, or again
+    /// when its runtime has shut down.
+    fn start(&self) -> bool {
+        if self.started.load(Ordering::Acquire) {
+            if self.worker.load(Ordering::Acquire) == WORKER_STOPPED {
+                self.revive();
             }
+            return true;
+        }
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            self.attach_runtime(&handle);
         }
+        self.started.load(Ordering::Acquire)
+    }
 
-        let _ = self.filehandle.1.async_print(console, message).await;
+    pub async fn print(&self, level: LEVEL, module: &str, message: LogContent) {
+        self.drain_queue().await;
+        self.reopen_on_hangup().await;
+        self.rotate_groups().await;
+        self.print_pending().await;
+        self.route(level, module, message).await;
     }
 
-    pub async fn safeprint(&mut self, level: LEVEL, module: &str, message: LogContent) {
+    pub async fn safeprint(&self, level: LEVEL, module: &str, message: LogContent) {
+        self.drain_queue().await;
+        self.reopen_on_hangup().await;
+        self.print_pending().await;
         let _mutex_guard = self.mutex.lock().await;
-        let mut console = self.fmthandle.get_console();
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, filename) = mm;
-                if let Some(cs) = lo.console {
-                    console = cs
-                }
-                if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.async_print(console, message).await;
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.async_print(console, message).await;
-                        }
-                    }
-                    return;
-                }
+        self.rotate_groups().await;
+        self.route(level, module, message).await;
+    }
+
+    /// Waits for the lines of `PRINTMODE::DELAY` once it was left, so that
+    /// they are written before the lines written directly.
+    async fn drain_queue(&self) {
+        if self.drain_queue.load(Ordering::Relaxed) && self.drain_queue.swap(false, Ordering::AcqRel) && self.start() {
+            let (done, wait) = oneshot::channel();
+            if self.sender.send(Job::Flush(Vec::new(), done)).is_ok() {
+                let _ = wait.await;
             }
         }
+    }
 
-        if let Some(levels) = &self.levels {
-            if let Some(lp) = &levels[level as usize - 1] {
-                let (lo, filename) = lp;
-                if let Some(cs) = lo.console {
-                    console = cs
-                }
-                if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.async_print(console, message).await;
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.async_print(console, message).await;
-                        }
-                    }
-                    return;
-                }
-            }
+    async fn rotate_groups(&self) {
+        for rotation in self.due_rotations() {
+            rotation.run().await;
         }
+    }
 
-        let _ = self.filehandle.1.async_print(console, message).await;
+    /// The members of every rotation group whose period is over.
+    fn due_rotations(&self) -> Vec<GroupRotation> {
+        let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+        if groups.is_empty() {
+            return Vec::new();
+        }
+        let now = self.clock.wall();
+        groups
+            .iter_mut()
+            .filter_map(|group| {
+                let (stamp, startsec) = group.check(now)?;
+                let members = group.members.iter().filter_map(|id| Some((id.clone(), self.group_member(id)?.inner.clone()))).collect();
+                Some(GroupRotation { members, stamp, startsec, empty_backups: group.empty_backups })
+            })
+            .collect()
     }
 
-    pub fn log(&self, level: LEVEL, module: String, message: LogContent) {
-        self.sender
-            .send((level, module, message))
-            .expect("send error");
+    fn group_member(&self, id: &str) -> Option<&SharedHandler> {
+        if !id.is_empty() && id == self.filehandle.0 {
+            return Some(&self.filehandle.1);
+        }
+        self.fmap.get(id)
     }
 
-    pub fn get_level(&mut self, module: &str) -> LEVEL {
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, _) = mm;
-                if let Some(level) = lo.level {
-                    return level;
-                }
+    /// Writes the lines tklog produced itself while formatting, e.g. storm
+    /// notices, and those of `log_if_persists` that are due.
+    async fn print_pending(&self) {
+        for (level, message) in self.take_pending() {
+            self.route(level, "tklog", message).await;
+        }
+        for (level, module, message) in self.take_persisted() {
+            self.route(level, &module, message).await;
+        }
+    }
+
+    fn take_pending(&self) -> Vec<(LEVEL, LogContent)> {
+        std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    async fn route(&self, level: LEVEL, module: &str, message: LogContent) {
+        if !message.is_empty() {
+            self.filesettings.metrics.printed(level);
+        }
+        if let Some(sink) = &self.custom_sink {
+            let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+            sink.write(level, &message.file_body);
+            sink.flush();
+            if self.custom_sink_only {
+                return;
             }
         }
-        self.fmthandle.get_level()
+        if let Some(remote) = &self.remote {
+            remote.push(&message.file_body);
+        }
+        #[cfg(feature = "syslog")]
+        if self.send_syslog(level, &message) {
+            return;
+        }
+        #[cfg(feature = "journald")]
+        if self.send_journald(level, module, &message) {
+            return;
+        }
+        let mut targets = self.targets(module, level);
+        let last = targets.pop();
+        for target in targets {
+            self.deliver(target, message.clone()).await;
+        }
+        if let Some(target) = last {
+            self.deliver(target, message).await;
+        }
     }
 
-    pub fn is_file_line(&mut self, level: LEVEL, module: &str) -> bool {
-        if let Some(levels) = &self.levels {
-            if let Some(lp) = &levels[level as usize - 1] {
-                let (lo, _) = lp;
-                if let Some(v) = lo.format {
-                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
-                }
+    async fn deliver(&self, target: Target, message: LogContent) {
+        let (target, message) = match self.charge_quota(&target.sink, &message) {
+            Ok(()) => (target, message),
+            Err(Some(warning)) => (self.default_target(), warning),
+            Err(None) => return,
+        };
+        write_line(&target, &self.module_files, &self.stats, &message).await;
+    }
+
+    /// Formats and writes one line unless `module` filters it out. Without
+    /// an explicit `location` the caller's file and line are used.
+    #[track_caller]
+    pub fn log_record<'a>(&'a self, level: LEVEL, module: &'a str, location: Option<(&'a str, u32)>, message: String) -> impl Future<Output = ()> + 'a {
+        let caller = Location::caller();
+        async move {
+            if self.get_level(module) > level {
+                return;
+            }
+            let (file, line) = match location {
+                Some(l) if self.is_file_line(level, module) => l,
+                None if self.is_file_line(level, module) => (caller.file(), caller.line()),
+                _ => ("", 0),
+            };
+            let s = self.fmt(module, level, file, line, message);
+            if s.is_empty() {
+                return;
+            }
+            if self.mode == PRINTMODE::DELAY {
+                self.log(level, module, s);
+            } else {
+                self.safeprint(level, module, s).await;
             }
         }
+    }
 
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, _) = mm;
-                if let Some(v) = lo.format {
-                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
-                }
+    /// Filters, formats and queues one line; `file` and `line` are kept only
+    /// when the format shows them. This is the hot path behind the macros and
+    /// takes `&self`, so an `Arc<Logger>` can be cloned into tasks as is.
+    pub fn enqueue(&self, level: LEVEL, module: &str, file: &str, line: u32, message: String) {
+        self.enqueue_with(level, module, (file, line), None, message, FieldMap::new(), RecordSnapshot::into_owned);
+    }
+
+    /// `enqueue` for the macros, whose names outlive the snapshot, so
+    /// `FormatStage::Worker` needn't copy them.
+    #[doc(hidden)]
+    pub fn enqueue_static(&self, level: LEVEL, module: &'static str, file: &'static str, line: u32, message: String) {
+        self.enqueue_with(level, module, (file, line), None, message, FieldMap::new(), |record| record);
+    }
+
+    /// `enqueue_static` with an event ID.
+    #[doc(hidden)]
+    pub fn enqueue_static_event(&self, level: LEVEL, module: &'static str, file: &'static str, line: u32, event: Option<&'static str>, message: String) {
+        self.enqueue_with(level, module, (file, line), event, message, FieldMap::new(), |record| record);
+    }
+
+    /// `enqueue_static_event` with the fields given to the macro.
+    #[doc(hidden)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn enqueue_static_fields(&self, level: LEVEL, module: &'static str, file: &'static str, line: u32, event: Option<&'static str>, message: String, fields: FieldMap) {
+        self.enqueue_with(level, module, (file, line), event, message, fields, |record| record);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn enqueue_with<'a>(&self, level: LEVEL, module: &'a str, (file, line): (&'a str, u32), event: Option<&'static str>, message: String, fields: FieldMap, owned: fn(RecordSnapshot<'a>) -> RecordSnapshot<'static>) {
+        if self.get_level(module) > level {
+            return;
+        }
+        let (file, line) = if self.is_file_line(level, module) { (file, line) } else { ("", 0) };
+        let Some((record, fmat, formatter)) = self.capture(module, level, file, line, event, message, fields) else {
+            return;
+        };
+        if self.format_stage == FormatStage::Worker && self.budget.is_none() && self.hooks.is_empty() && self.quotas.lock().unwrap_or_else(|e| e.into_inner()).is_empty() {
+            let deferred = Deferred {
+                record: owned(record),
+                fmat,
+                formatter: formatter.cloned(),
+                render: self.render.clone(),
+                content: OnceLock::new(),
+            };
+            self.dispatch(level, module, Payload::Deferred(Arc::new(deferred)));
+        } else {
+            let s = self.content(&record, fmat, formatter);
+            if !s.is_empty() {
+                self.dispatch(level, module, Payload::Rendered(s));
             }
         }
-        self.fmthandle.is_file_line()
     }
 
-    pub fn fmt(
-        &mut self,
-        module: &str,
-        level: LEVEL,
-        filename: &str,
-        line: u32,
-        message: String,
-    ) -> LogContent {
-        if self.custom_handler.is_some() {
-            if let Some(ch) = &self.custom_handler {
-                if !ch(&LogContext {
-                    level: level,
-                    filename: filename.to_string(),
-                    line: line,
-                    log_body: message.clone(),
-                    modname: module.to_string(),
-                }) {
-                    return LogContent::new(String::new(), None);
-                }
+    /// What keeps this logger from working as configured, see `health`.
+    pub async fn health(&self) -> Health {
+        let mut reasons = Vec::new();
+        if self.sender.is_closed() {
+            reasons.push(Degradation::QueueStopped);
+        }
+        let mut names: Vec<&String> = self.fmap.keys().collect();
+        names.sort();
+        for filename in names {
+            if self.fmap[filename].inner.lock().await.is_degraded() {
+                reasons.push(Degradation::Diverted(filename.clone()));
             }
         }
-        let mut fmat = self.fmthandle.get_format();
-        let mut formatter = self.fmthandle.get_formatter();
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, _) = mm;
-                if let Some(v) = lo.format {
-                    fmat = v;
-                }
-                if lo.formatter.is_some() {
-                    formatter = lo.formatter.as_ref();
-                }
+        if self.custom_panics.is_disabled() {
+            reasons.push(Degradation::CallbackDisabled("custom handler"));
+        }
+        if let Some(name) = self.filters.disabled() {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        if let Some(name) = self.hooks.disabled() {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        if let Some(name) = self.filesettings.errors.disabled() {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        reasons.extend(self.render.disabled_callbacks().into_iter().map(Degradation::CallbackDisabled));
+        if let Some(name) = self.dynamic_fields.as_ref().and_then(|g| g.disabled()) {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        for h in self.file_handlers() {
+            if h.inner.lock().await.rotation_handler_disabled() {
+                reasons.push(Degradation::CallbackDisabled("rotation handler"));
+                break;
             }
         }
+        Health::new(reasons)
+    }
 
-        if let Some(levels) = &self.levels {
-            if let Some(lp) = &levels[level as usize - 1] {
-                let (lo, _) = lp;
-                if let Some(v) = lo.format {
-                    fmat = v;
-                }
-                if lo.formatter.is_some() {
-                    formatter = lo.formatter.as_ref();
-                }
-            }
+    /// Restarts a stopped queue consumer, on the runtime it ran on or the
+    /// current one, reopens the files whose lines go to the default file
+    /// and enables the disabled callbacks again. Errs with the reasons of
+    /// `health` left, such as a file that still can't be opened. The lines
+    /// queued when the consumer stopped are lost.
+    pub async fn try_recover(&mut self) -> Result<(), Error> {
+        if self.sender.is_closed() {
+            let (sender, receiver) = mpsc::unbounded_channel::<Job>();
+            self.sender = sender;
+            self.queue = Arc::new(tokio::sync::Mutex::new(receiver));
+            self.worker.store(WORKER_STOPPED, Ordering::Release);
+            self.start();
+        }
+        for h in self.file_handlers() {
+            let _ = h.inner.lock().await.async_recover().await;
+        }
+        self.custom_panics.reset();
+        self.filters.reset();
+        self.hooks.reset();
+        self.filesettings.errors.reset();
+        self.render.reset_callbacks();
+        if let Some(g) = &self.dynamic_fields {
+            g.reset();
+        }
+        let health = self.health().await;
+        if health.ok {
+            Ok(())
+        } else {
+            Err(Error::NotRecovered(health.degraded_reasons))
+        }
+    }
+
+    /// The default file, the module and level files and the tee files.
+    fn file_handlers(&self) -> impl Iterator<Item = &SharedHandler> {
+        std::iter::once(&self.filehandle.1).chain(self.fmap.values()).chain(self.tees.iter().map(|(_, h)| h))
+    }
+
+    /// Waits until every line queued so far is written and its file synced
+    /// to disk, e.g. before the runtime shuts down. Safe to call from many
+    /// tasks at once: each waits for the lines queued before its call.
+    pub async fn flush(&self) {
+        if let Some(wait) = self.queue_flush() {
+            let _ = wait.await;
+        }
+        if let Some(remote) = &self.remote {
+            remote.flush().await;
         }
+    }
+
+    /// `flush` from synchronous code, giving up after `timeout`; true when
+    /// everything was flushed. False at once on a current-thread runtime,
+    /// where the worker can't run while this waits. On a worker thread of a
+    /// multi-thread runtime the wait is in `block_in_place`, for the worker
+    /// woken on this thread to run elsewhere.
+    pub(crate) fn flush_blocking(&self, timeout: Duration) -> bool {
+        let runtime = tokio::runtime::Handle::try_current().ok().map(|h| h.runtime_flavor());
+        if runtime == Some(tokio::runtime::RuntimeFlavor::CurrentThread) {
+            return false;
+        }
+        let Some(mut wait) = self.queue_flush() else {
+            return false;
+        };
+        let deadline = Instant::now() + timeout;
+        let mut flushed = || loop {
+            match wait.try_recv() {
+                Ok(()) => return true,
+                Err(oneshot::error::TryRecvError::Closed) => return false,
+                Err(oneshot::error::TryRecvError::Empty) if Instant::now() >= deadline => return false,
+                Err(oneshot::error::TryRecvError::Empty) => thread::sleep(Duration::from_millis(1)),
+            }
+        };
+        let flushed = if runtime.is_some() { Rt::block_in_place(flushed) } else { flushed() };
+        flushed && self.remote.as_ref().is_none_or(|r| r.flush_blocking())
+    }
+
+    /// Queues a flush of every file, answered once the lines before it are
+    /// written; None with the worker stopped.
+    fn queue_flush(&self) -> Option<oneshot::Receiver<()>> {
+        let mut handlers: Vec<_> = std::iter::once(&self.filehandle.1).chain(self.fmap.values()).chain(self.tees.iter().map(|(_, h)| h)).map(|h| h.inner.clone()).collect();
+        if let Some(d) = &self.directory {
+            handlers.extend(d.lock().unwrap_or_else(|e| e.into_inner()).handlers().cloned());
+        }
+        let (done, wait) = oneshot::channel();
+        self.start();
+        self.sender.send(Job::Flush(handlers, done)).ok().map(|_| wait)
+    }
+
+    pub fn log(&self, level: LEVEL, module: impl AsRef<str>, message: LogContent) {
+        self.dispatch(level, module.as_ref(), Payload::Rendered(message));
+    }
+
+    /// Moves the default file to `path` for the lines after this call, once
+    /// those queued so far are written. What the old file holds is written
+    /// out and, with `rotate_old`, renamed to its next backup as a cut
+    /// would; the file of `path` is opened with the same cut settings, its
+    /// directories created, and the cuts count from it. The path the default
+    /// file has is a no-op. Errs with `Error::NoLogFile` without a default
+    /// file, and with `Error::PathConflict` or `Error::FileUnopenable`
+    /// leaving the old file as it was.
+    pub async fn set_log_path(&mut self, path: &str, rotate_old: bool) -> Result<&mut Self, Error> {
+        if path == self.filehandle.0 {
+            return Ok(self);
+        }
+        let Some(old) = self.filehandle.1.file.clone() else {
+            return Err(Error::NoLogFile);
+        };
+        let option = FileOptionType::from_config(&FileConfig { filename: path.to_string(), ..old.clone() });
+        self.paths.claim_alone(DEFAULT_FILE, &option)?;
+        let f = match self.new_filehandler(Box::new(option)).await {
+            Ok(f) => f,
+            Err(e) => {
+                self.paths.claim(DEFAULT_FILE, &FileOptionType::from_config(&old));
+                return Err(Error::FileUnopenable(path.into(), e));
+            }
+        };
+        self.flush().await;
+        self.filehandle.1.inner.lock().await.async_retire_file(rotate_old).await;
+        self.filehandle.0 = f.get_file_name();
+        self.filehandle.1.set_async_file_handler(f).await;
+        Ok(self)
+    }
+
+    /// Writes out what the files hold and opens their paths again, for the
+    /// files renamed by logrotate to be written anew, once the lines
+    /// queued so far are written; the size cuts count from the files
+    /// opened. The files of `set_directory_mode` are closed and open again
+    /// on their next line. Errs with the first file that can't be opened,
+    /// after trying every one.
+    pub async fn reopen(&self) -> io::Result<()> {
+        match self.queue_reopen() {
+            // Without a consumer to answer, the files are reopened here.
+            Some(wait) => match wait.await {
+                Ok(result) => result,
+                Err(_) => reopen_files(self.named_handlers()).await,
+            },
+            None => reopen_files(self.named_handlers()).await,
+        }
+    }
+
+    /// Queues a `reopen`, answered once it is done; None with the worker
+    /// stopped.
+    fn queue_reopen(&self) -> Option<oneshot::Receiver<io::Result<()>>> {
+        if let Some(d) = &self.directory {
+            d.lock().unwrap_or_else(|e| e.into_inner()).close_all();
+        }
+        let (done, wait) = oneshot::channel();
+        self.start();
+        self.sender.send(Job::Reopen(self.named_handlers(), done)).ok().map(|_| wait)
+    }
+
+    /// The default file, the module and level files and the tee files, by
+    /// name.
+    fn named_handlers(&self) -> Vec<(String, Arc<tokio::sync::Mutex<FHandler>>)> {
+        let default = std::iter::once((&self.filehandle.0, &self.filehandle.1));
+        default.chain(&self.fmap).chain(self.tees.iter().map(|(f, h)| (f, h))).map(|(f, h)| (f.clone(), h.inner.clone())).collect()
+    }
+
+    /// Reopens the files, see `reopen`, on every SIGHUP, as logrotate's
+    /// `postrotate` sends; the signal no longer ends the process. Errs if
+    /// the signal can't be listened for.
+    #[cfg(all(unix, feature = "signal"))]
+    pub fn enable_reopen_on_sighup(&mut self) -> io::Result<&mut Self> {
+        crate::signal::listen()?;
+        self.hangups = Some(AtomicU64::new(crate::signal::hangups()));
+        Ok(self)
+    }
+
+    /// Whether a SIGHUP came since the last line.
+    fn hangup_seen(&self) -> bool {
+        #[cfg(all(unix, feature = "signal"))]
+        if let Some(seen) = &self.hangups {
+            let hangups = crate::signal::hangups();
+            return seen.swap(hangups, Ordering::Relaxed) != hangups;
+        }
+        false
+    }
+
+    async fn reopen_on_hangup(&self) {
+        if self.hangup_seen() {
+            let _ = self.reopen().await;
+        }
+    }
+
+    /// Writes the lines `block` adds as one, see `block`. `block` only runs
+    /// when `module` doesn't filter `level` out.
+    pub async fn log_block(&self, level: LEVEL, module: &str, block: impl FnOnce(&mut BlockWriter)) {
+        if self.get_level(module) > level {
+            return;
+        }
+        let mut writer = BlockWriter::default();
+        block(&mut writer);
+        let lines: Vec<LogContent> = writer.into_lines().into_iter().map(|message| self.fmt(module, level, "", 0, message)).collect();
+        let Some(s) = block::join(lines) else {
+            return;
+        };
+        if self.mode == PRINTMODE::DELAY {
+            self.log(level, module, s);
+        } else {
+            self.safeprint(level, module, s).await;
+        }
+    }
+
+    /// Logs a line after `after` unless `clear_persisting(key)` comes first,
+    /// see `persist`. `message` is only called when `key` isn't armed yet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_if_persists(&self, key: &str, after: Duration, level: LEVEL, module: &str, file: &str, line: u32, message: impl FnOnce() -> String) {
+        let (now, since) = (self.clock.now(), self.clock.wall());
+        let full = self.persisting.lock().unwrap_or_else(|e| e.into_inner()).arm(key, || Pending::new(level, module, (file, line), message(), since, now + after));
+        if let Some((level, module, s)) = full.and_then(|pending| self.render_persisted(pending)) {
+            self.log(level, module, s);
+        }
+    }
+
+    /// Cancels the line armed for `key`; false when none was.
+    pub fn clear_persisting(&self, key: &str) -> bool {
+        self.persisting.lock().unwrap_or_else(|e| e.into_inner()).clear(key)
+    }
+
+    /// Queues the lines of `log_if_persists` that are due, and returns how
+    /// long until the next one is.
+    pub fn expire_persisting(&self) -> Option<Duration> {
+        for (level, module, s) in self.take_persisted() {
+            self.log(level, module, s);
+        }
+        self.persisting.lock().unwrap_or_else(|e| e.into_inner()).wait(self.clock.now())
+    }
+
+    /// The lines of `log_if_persists` that are due, formatted.
+    fn take_persisted(&self) -> Vec<(LEVEL, String, LogContent)> {
+        let due = {
+            let mut persisting = self.persisting.lock().unwrap_or_else(|e| e.into_inner());
+            if !persisting.is_due(|| self.clock.now()) {
+                return Vec::new();
+            }
+            persisting.expired(self.clock.now())
+        };
+        due.into_iter().filter_map(|pending| self.render_persisted(pending)).collect()
+    }
+
+    fn render_persisted(&self, pending: Pending) -> Option<(LEVEL, String, LogContent)> {
+        let fields = pending.fields();
+        let Pending { level, module, file, line, message, .. } = pending;
+        let s = self.fmt_with_fields(&module, level, &file, line, None, message, fields);
+        (!s.is_empty()).then_some((level, module, s))
+    }
+
+    fn dispatch(&self, level: LEVEL, module: &str, message: Payload) {
+        self.filesettings.metrics.printed(level);
+        if self.hangup_seen() {
+            // Behind the lines queued so far, which go to the renamed files.
+            let _ = self.queue_reopen();
+        }
+        if let Some(notice) = self.channel.notice(self.clock.now()) {
+            self.queue_internal(LEVEL::Warn, notice);
+        }
+        for (level, module, s) in self.take_persisted() {
+            self.dispatch(level, &module, Payload::Rendered(s));
+        }
+        for rotation in self.due_rotations() {
+            let _ = self.sender.send(Job::Rotate(rotation));
+        }
+        for (level, message) in self.take_pending() {
+            self.filesettings.metrics.printed(level);
+            for target in self.targets("tklog", level) {
+                self.send_unbounded(level, target, Payload::Rendered(message.clone()));
+            }
+        }
+        if let Some(sink) = &self.custom_sink {
+            self.start();
+            let _ = self.sender.send(Job::Custom(sink.clone(), level, message.clone()));
+            if self.custom_sink_only {
+                return;
+            }
+        }
+        if let Some(remote) = &self.remote {
+            remote.push(&message.content().file_body);
+        }
+        #[cfg(feature = "syslog")]
+        if self.send_syslog(level, message.content()) {
+            return;
+        }
+        #[cfg(feature = "journald")]
+        if self.send_journald(level, module, message.content()) {
+            return;
+        }
+        let mut targets = self.targets(module, level);
+        let last = targets.pop();
+        for target in targets {
+            self.enqueue_to(level, target, message.clone());
+        }
+        if let Some(target) = last {
+            self.enqueue_to(level, target, message);
+        }
+    }
+
+    fn enqueue_to(&self, level: LEVEL, target: Target, message: Payload) {
+        let Payload::Rendered(content) = &message else {
+            return self.send(level, target, message);
+        };
+        match self.charge_quota(&target.sink, content) {
+            Ok(()) => self.send(level, target, message),
+            Err(Some(warning)) => self.send_unbounded(LEVEL::Warn, self.default_target(), Payload::Rendered(warning)),
+            Err(None) => {}
+        }
+    }
+
+    fn send(&self, level: LEVEL, target: Target, message: Payload) {
+        self.queue_line(level, target, message, true);
+    }
+
+    /// Queues a line of tklog itself, past the bound of the queue.
+    fn send_unbounded(&self, level: LEVEL, target: Target, message: Payload) {
+        self.queue_line(level, target, message, false);
+    }
+
+    fn queue_line(&self, level: LEVEL, target: Target, message: Payload, bounded: bool) {
+        if !self.start() && self.prestart.fetch_add(1, Ordering::Relaxed) >= self.prestart_lines {
+            self.stats.prestart_dropped();
+            return;
+        }
+        let Some(message) = memory::hold(level, message.size(), message) else {
+            return;
+        };
+        let message = if !bounded {
+            self.channel.push_unchecked(message)
+        } else {
+            match self.channel.admit(message, self.clock.now()) {
+                channel::Admission::Queue(message) => message,
+                channel::Admission::Wait(message) => {
+                    self.wait_room();
+                    self.channel.push_unchecked(message)
+                }
+                channel::Admission::Drop => return,
+            }
+        };
+        let mut enqueued_at = None;
+        if self.latency_sampling > 0 && self.sampled.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.latency_sampling) {
+            enqueued_at = Some(Instant::now());
+        }
+        self.stats.enqueued(&target.sink);
+        // With the consumer stopped the line is dropped, see `health`.
+        if let Err(mpsc::error::SendError(Job::Line(target, ..))) = self.sender.send(Job::Line(target, message, enqueued_at)) {
+            self.stats.shed(&target.sink);
+        }
+    }
+
+    /// Waits for the consumer to make room in the queue, unless it runs on
+    /// this thread's current-thread runtime or has stopped.
+    fn wait_room(&self) {
+        let running = || self.started.load(Ordering::Acquire) && self.worker.load(Ordering::Acquire) == WORKER_RUNNING;
+        match tokio::runtime::Handle::try_current().map(|h| h.runtime_flavor()) {
+            Ok(tokio::runtime::RuntimeFlavor::CurrentThread) => {}
+            Ok(_) => Rt::block_in_place(|| self.channel.wait_room(running)),
+            Err(_) => self.channel.wait_room(running),
+        }
+    }
+
+    /// Where a line of `module` at `level` goes: its destinations, the level
+    /// files, then the tee files.
+    fn targets(&self, module: &str, level: LEVEL) -> Vec<Target> {
+        let mut targets = self.destinations(module, level);
+        targets.extend(self.level_files.iter().filter(|(levels, _)| levels.contains(&level)).filter_map(|(_, filename)| self.handler(filename, false)));
+        targets.extend(self.tees.iter().enumerate().map(|(i, (filename, h))| Target {
+            sink: filename.clone(),
+            handler: h.inner.clone(),
+            console: false,
+            fallback: None,
+            tee: Some(i),
+        }));
+        if self.output_levels.is_empty() {
+            return targets;
+        }
+        let gate = self.output_levels.gate(level, self.set_level_of(module));
+        targets.into_iter().filter_map(|t| self.gated(t, gate)).collect()
+    }
+
+    /// `target` for the sinks of `gate`: without the console, or only the
+    /// console when the files don't take the line.
+    fn gated(&self, target: Target, gate: Gate) -> Option<Target> {
+        let console = target.console && gate.console;
+        if gate.file {
+            return Some(Target { console, ..target });
+        }
+        (console && target.tee.is_none()).then(|| Target {
+            sink: "console".to_string(),
+            handler: self.console.clone(),
+            console: true,
+            fallback: None,
+            tee: None,
+        })
+    }
+
+    /// The routing matrix when it is set and no module option names a
+    /// file, else `target`.
+    fn destinations(&self, module: &str, level: LEVEL) -> Vec<Target> {
+        if let Some(routing) = &self.routing {
+            let module_file = !module.is_empty() && self.modmap.len() > 0 && self.modmap.lookup(module).is_some_and(|(_, filename)| !filename.is_empty());
+            if !module_file {
+                return routing
+                    .route(level)
+                    .filter_map(|sink| match sink {
+                        Sink::Console => Some(Target {
+                            sink: "console".to_string(),
+                            handler: self.console.clone(),
+                            console: true,
+                            fallback: None,
+                            tee: None,
+                        }),
+                        Sink::File(filename) => self.handler(filename, false),
+                    })
+                    .collect();
+            }
+        }
+        self.target(module, level).into_iter().collect()
+    }
+
+    /// The handler a line of `module` at `level` goes to, following the
+    /// module options, then the level options; `None` when they name a file
+    /// without a handler.
+    fn target(&self, module: &str, level: LEVEL) -> Option<Target> {
+        let mut console = self.fmthandle.get_console();
+        if !module.is_empty() && self.modmap.len() > 0 {
+            if let Some((lo, filename)) = self.modmap.lookup(module) {
+                if let Some(cs) = lo.console {
+                    console = cs
+                }
+                if !filename.is_empty() {
+                    return self.handler(filename, console);
+                }
+            }
+        }
+        if let Some(levels) = &self.levels {
+            if let Some((lo, filename)) = &levels[level as usize - 1] {
+                if let Some(cs) = lo.console {
+                    console = cs
+                }
+                if !filename.is_empty() {
+                    return self.handler(filename, console);
+                }
+            }
+        }
+        if let Some(directory) = &self.directory {
+            return Some(self.directory_target(directory, module, console));
+        }
+        Some(Target { console, ..self.default_target() })
+    }
+
+    /// The file of `module` for the current day under the directory root.
+    fn directory_target(&self, directory: &Mutex<Directory<Arc<tokio::sync::Mutex<FHandler>>>>, module: &str, console: bool) -> Target {
+        let time = self.testmode.map_or_else(now, |t| t.fixed_time);
+        let mut directory = directory.lock().unwrap_or_else(|e| e.into_inner());
+        let handler = directory.handler(module, time, |path| {
+            let mut f = FileHandler::unopened(path.to_string_lossy().into_owned());
+            f.set_settings(self.filesettings.clone());
+            let mut fhandler = FHandler::new();
+            fhandler.set_async_file_handler(f);
+            Arc::new(tokio::sync::Mutex::new(fhandler))
+        }).clone();
+        Target {
+            sink: directory.root().display().to_string(),
+            handler,
+            console,
+            fallback: Some(self.filehandle.1.inner.clone()),
+            tee: None,
+        }
+    }
+
+    fn handler(&self, filename: &str, console: bool) -> Option<Target> {
+        if *filename == self.filehandle.0 {
+            return Some(Target { console, ..self.default_target() });
+        }
+        Some(Target {
+            sink: filename.to_string(),
+            handler: self.fmap.get(filename)?.inner.clone(),
+            console,
+            fallback: Some(self.filehandle.1.inner.clone()),
+            tee: None,
+        })
+    }
+
+    /// The default handler; its sink is `console` when it has no file.
+    fn default_target(&self) -> Target {
+        Target {
+            sink: if self.filehandle.0.is_empty() { "console".to_string() } else { self.filehandle.0.clone() },
+            handler: self.filehandle.1.inner.clone(),
+            console: self.fmthandle.get_console(),
+            fallback: None,
+            tee: None,
+        }
+    }
+
+    /// Replaces per-level destinations with a routing matrix over named
+    /// sinks, see `routing`. Module options that name a file still win.
+    /// Errs on a sink name the logger doesn't have; levels left without a
+    /// destination are warned about on stderr.
+    pub fn set_routing<F: FnOnce(&mut Routes)>(&mut self, f: F) -> Result<&mut Self, Error> {
+        let mut routes = Routes::default();
+        f(&mut routes);
+        let table = RoutingTable::compile(routes, |name| routing::resolve(name, &self.filehandle.0, &self.sinks, |f| self.fmap.contains_key(f)))?;
+        self.routing = Some(table);
+        Ok(self)
+    }
+
+    pub fn clear_routing(&mut self) -> &mut Self {
+        self.routing = None;
+        self
+    }
+
+    /// Adds a file handler that routing rules can name `file:<name>`.
+    pub async fn add_file_sink(&mut self, name: &str, option: impl FileOption + 'static) -> &mut Self {
+        if let Ok(filename) = self.module_file(&format!("file sink `{}`", name), Box::new(option)).await {
+            if !filename.is_empty() {
+                self.sinks.insert(name.to_string(), filename);
+            }
+        }
+        self
+    }
+
+    /// Writes the lines at `levels`, such as `LEVEL::Warn..`, to a file of
+    /// their own as well, rotated by `cut`; wherever else they go, they
+    /// still do. Each file has its own handler, so neither waits for the
+    /// other's writes or rotation. Adding its file again replaces its levels.
+    pub async fn add_level_file(&mut self, levels: impl LevelSet, cut: impl Into<CutConfig>) -> &mut Self {
+        let cut = cut.into();
+        if let Ok(filename) = self.module_file(&format!("level file {}", cut.filename()), Box::new(cut.option())).await {
+            self.insert_level_file(routing::level_list(&levels), filename);
+        }
+        self
+    }
+
+    /// Stops writing the files of `add_level_file`.
+    pub fn clear_level_files(&mut self) -> &mut Self {
+        self.level_files.clear();
+        self
+    }
+
+    fn insert_level_file(&mut self, levels: Vec<LEVEL>, filename: String) {
+        if filename.is_empty() || filename == self.filehandle.0 {
+            return;
+        }
+        match self.level_files.iter_mut().find(|(_, f)| *f == filename) {
+            Some(entry) => entry.0 = levels,
+            None => self.level_files.push((levels, filename)),
+        }
+    }
+
+    /// Makes the time-rotated handlers `handler_ids`, log file names, rotate
+    /// together, see `rotation`; a member with an empty file then gets an
+    /// empty backup. Errs with `Error::InvalidRotationGroup` for a handler
+    /// a group can't take.
+    pub async fn set_rotation_group(&mut self, handler_ids: &[&str]) -> Result<&mut Self, Error> {
+        self.set_rotation_group_with(handler_ids, true).await
+    }
+
+    /// `set_rotation_group`, leaving the members with an empty file as they
+    /// are unless `empty_backups`.
+    pub async fn set_rotation_group_with(&mut self, handler_ids: &[&str], empty_backups: bool) -> Result<&mut Self, Error> {
+        let mut starts = HashMap::new();
+        for id in handler_ids {
+            if let Some(h) = self.group_member(id) {
+                if let Some(start) = h.inner.lock().await.startsec() {
+                    starts.insert(*id, start);
+                }
+            }
+        }
+        let group = {
+            let groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+            RotationGroup::new(
+                handler_ids,
+                |id| Some((self.group_member(id)?.file.clone()?, *starts.get(id)?)),
+                |id| groups.iter().any(|g| g.members.iter().any(|m| m == id)),
+                self.clock.wall(),
+                empty_backups,
+            )?
+        };
+        self.set_grouped(&group.members, Some(group.startsec)).await;
+        self.groups.get_mut().unwrap_or_else(|e| e.into_inner()).push(group);
+        Ok(self)
+    }
+
+    /// Gives the members of the rotation groups their own time checks back.
+    pub async fn clear_rotation_groups(&mut self) -> &mut Self {
+        let groups = std::mem::take(self.groups.get_mut().unwrap_or_else(|e| e.into_inner()));
+        for group in groups {
+            self.set_grouped(&group.members, None).await;
+        }
+        self
+    }
+
+    async fn set_grouped(&self, members: &[String], group: Option<u64>) {
+        for id in members {
+            if let Some(h) = self.group_member(id) {
+                h.inner.lock().await.set_grouped(group);
+            }
+        }
+    }
+
+    /// Adds a tee file, written with every line in `mode` and rotated by
+    /// `cut`, see `tee`; adding its file again replaces it. Errs with
+    /// `Error::UnsupportedTeeMode` for logfmt and delimited lines, and with
+    /// `Error::PathConflict` for a file another handler writes.
+    pub async fn add_tee_file(&mut self, mode: OutputMode, cut: impl Into<CutConfig>) -> Result<&mut Self, Error> {
+        let layout = TeeLayout::new(mode)?;
+        let option = cut.into().option();
+        let owner = tee::owner(&option.filename());
+        self.paths.claim_alone(&owner, &option)?;
+        let f = match self.new_filehandler(Box::new(option)).await {
+            Ok(f) => f,
+            Err(e) => {
+                self.paths.release(&owner);
+                diagnostics::report(Category::Reopen, None, format!("cannot open the {}: {}", owner, e));
+                return Ok(self);
+            }
+        };
+        let filename = f.get_file_name();
+        let mut fh = FHandler::new();
+        fh.set_async_file_handler(f);
+        self.stats.add_tee(&filename, mode);
+        let render = Arc::make_mut(&mut self.render);
+        match self.tees.iter().position(|(name, _)| *name == filename) {
+            Some(i) => {
+                self.tees[i].1 = SharedHandler::new(fh);
+                render.tees[i] = layout;
+            }
+            None => {
+                self.tees.push((filename, SharedHandler::new(fh)));
+                render.tees.push(layout);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Stops writing the tee files.
+    pub fn clear_tee_files(&mut self) -> &mut Self {
+        for (filename, _) in self.tees.drain(..) {
+            self.paths.release(&tee::owner(&filename));
+            self.stats.remove_tee(&filename);
+        }
+        Arc::make_mut(&mut self.render).tees.clear();
+        self
+    }
+
+    /// Sends every line, once laid out, to `sink` as well, see `logsink`;
+    /// a later call replaces it. The queued lines reach it on the consumer
+    /// task.
+    pub fn set_custom_sink(&mut self, sink: Box<dyn LogSink>) -> &mut Self {
+        self.custom_sink = Some(Arc::new(Mutex::new(sink)));
+        self
+    }
+
+    /// Sends the lines to the custom sink alone, not to the console, the
+    /// files and the tee files. Default: false.
+    pub fn set_custom_sink_only(&mut self, on: bool) -> &mut Self {
+        self.custom_sink_only = on;
+        self
+    }
+
+    /// Drops the custom sink once the lines queued for it are written.
+    pub fn clear_custom_sink(&mut self) -> &mut Self {
+        self.custom_sink = None;
+        self
+    }
+
+    /// Caps the bytes written to `handler_id`, a log file name, per day. Lines
+    /// over the quota are dropped and counted, with a single warning to the
+    /// default handler; the quota resets at the next day boundary.
+    pub fn set_handler_quota(&mut self, handler_id: &str, bytes_per_day: u64) -> &mut Self {
+        self.quotas.get_mut().unwrap_or_else(|e| e.into_inner()).insert(handler_id.to_string(), Quota::new(bytes_per_day));
+        self
+    }
+
+    /// Charges `message` to the quota of `handler`. An error means it must be
+    /// dropped, carrying the warning for the default handler the first time.
+    fn charge_quota(&self, handler: &str, message: &LogContent) -> Result<(), Option<LogContent>> {
+        let notice = {
+            let mut quotas = self.quotas.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(quota) = quotas.get_mut(handler) else {
+                return Ok(());
+            };
+            match quota.admit(message.file_body.len() as u64) {
+                Admission::Admit => return Ok(()),
+                Admission::Drop => return Err(None),
+                Admission::Exceeded => quota.exceeded_notice(handler),
+            }
+        };
+        if self.get_level("tklog") > LEVEL::Warn {
+            return Err(None);
+        }
+        let s = self.fmt("tklog", LEVEL::Warn, "", 0, notice);
+        Err(if s.is_empty() { None } else { Some(s) })
+    }
+
+    /// Temporarily drops lines below Warn after an Error storm. Off by default.
+    pub fn set_storm_control(&mut self, config: StormConfig) -> &mut Self {
+        self.storm = Some(Mutex::new(StormControl::new(config)));
+        self
+    }
+
+    pub fn clear_storm_control(&mut self) -> &mut Self {
+        self.storm = None;
+        self
+    }
+
+    /// For `duration`, logs a backtrace with the match count for the first
+    /// and then every `sample_rate`-th line whose message contains
+    /// `message`, at the level of that line, to find where a flood of lines
+    /// comes from. The window then closes with a count of the matches.
+    pub fn trace_callers(&mut self, message: &str, sample_rate: u64, duration: Duration) -> &mut Self {
+        *self.callers.get_mut().unwrap_or_else(|e| e.into_inner()) = Some(CallerTrace::new(message, sample_rate, self.clock.now(), duration));
+        self
+    }
+
+    pub fn stop_trace_callers(&mut self) -> &mut Self {
+        *self.callers.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+        self
+    }
+
+    /// Calls `f` for every line that passes the level checks, before it is
+    /// formatted, to add fields such as the tenant of the current request;
+    /// they follow the message as ` key=value`. See `tklog::fields`.
+    pub fn set_dynamic_fields(&mut self, f: DynamicFields) -> &mut Self {
+        self.dynamic_fields = Some(Guarded::new("dynamic fields callback", f));
+        self
+    }
+
+    pub fn clear_dynamic_fields(&mut self) -> &mut Self {
+        self.dynamic_fields = None;
+        self
+    }
+
+    /// Whether lines get the fields of `tklog::context`, looked up per
+    /// line. Default: true.
+    pub fn set_context_fields(&mut self, on: bool) -> &mut Self {
+        self.context_fields = on;
+        self
+    }
+
+    /// Keeps the first `n` fields of a line, those of the macro first, and
+    /// drops the others, see `tklog::fields`. 0, the default, keeps them all.
+    pub fn set_max_fields(&mut self, n: usize) -> &mut Self {
+        self.field_limits.max_fields = n;
+        self
+    }
+
+    /// Cuts the field values of a line to `len` bytes, see `tklog::fields`.
+    /// 0, the default, leaves them whole.
+    pub fn set_max_field_value_len(&mut self, len: usize) -> &mut Self {
+        self.field_limits.max_value_len = len;
+        self
+    }
+
+    /// Lays every line out with `f` instead of the format flags and the
+    /// formatter; the body formats of `set_attr_format` still apply. See
+    /// `tklog::record`.
+    pub fn set_record_formatter(&mut self, f: RecordFormatter) -> &mut Self {
+        self.leave_format_json();
+        Arc::make_mut(&mut self.render).set_formatter(f);
+        self
+    }
+
+    /// Lays every line out as one JSON object, `{"level":…,"time":…,"file":…,
+    /// "line":…,"module":…,"message":…}`, on the console and in the files.
+    /// It replaces the `set_formatter` template and the record formatter,
+    /// and the next one set replaces it; `false` goes back to text lines.
+    pub fn set_format_json(&mut self, on: bool) -> &mut Self {
+        if !on {
+            self.leave_format_json();
+            return self;
+        }
+        self.fmthandle.clear_formatter();
+        let render = Arc::make_mut(&mut self.render);
+        render.formatter = None;
+        render.preset = Some(Preset::Json);
+        self.output.reset(OutputSink::Console, OutputMode::Json);
+        self.output.reset(OutputSink::File, OutputMode::Json);
+        self
+    }
+
+    fn leave_format_json(&mut self) {
+        if matches!(self.render.preset, Some(Preset::Json)) {
+            Arc::make_mut(&mut self.render).preset = None;
+            self.output.clear(OutputSink::Console);
+            self.output.clear(OutputSink::File);
+        }
+    }
+
+    pub fn clear_record_formatter(&mut self) -> &mut Self {
+        Arc::make_mut(&mut self.render).formatter = None;
+        self
+    }
+
+    /// Lays out the console lines with `f`, such as a
+    /// `badge::ConsoleBadgeFormatter`, instead of the format flags, the
+    /// formatter and the console body format. Files are unaffected.
+    pub fn set_console_formatter_impl(&mut self, f: impl LogFormatter + 'static) -> &mut Self {
+        Arc::make_mut(&mut self.render).set_console_formatter(Box::new(f));
+        self
+    }
+
+    pub fn clear_console_formatter(&mut self) -> &mut Self {
+        Arc::make_mut(&mut self.render).console_formatter = None;
+        self
+    }
+
+    /// Keeps ANSI escape sequences, such as the colors of a body format
+    /// shared with the console, in the lines written to files and custom
+    /// sinks. Default: false, they are stripped there.
+    /// Appends `#n` to the time of a line, n counting the lines before it
+    /// with the same rendered time, or gives it as `{subseq}` to a body
+    /// format that has it; ties of a coarse clock then keep their order.
+    /// Default: false.
+    pub fn set_time_subseq(&mut self, on: bool) -> &mut Self {
+        self.subseq = on;
+        self
+    }
+
+    /// Gives every line the boot ID of the process, see `boot`. A formatter
+    /// template shows it with `{bootid}` either way. Default: false.
+    pub fn set_include_boot_id(&mut self, on: bool) -> &mut Self {
+        self.boot_id = on;
+        self
+    }
+
+    /// Keeps the module path in the function of `Format::FuncName` and
+    /// `{function}`, `app::api::Server::handle` instead of `Server::handle`.
+    /// Default: false.
+    pub fn set_function_with_module(&mut self, on: bool) -> &mut Self {
+        self.function_module = on;
+        self
+    }
+
+    /// Writes the backtrace of an `ErrorChain` given one, on indented lines
+    /// after its message. Default: false, one line per record.
+    pub fn set_error_backtraces(&mut self, on: bool) -> &mut Self {
+        self.error_backtraces = on;
+        self
+    }
+
+    /// Where the macros take the file and line of a line from. The default,
+    /// `LocationStrategy::Caller`, reports the caller of a `#[track_caller]`
+    /// helper the macro is in; `MacroExpansion` the macro itself.
+    pub fn set_location_strategy(&mut self, strategy: LocationStrategy) -> &mut Self {
+        self.location_strategy = strategy;
+        self
+    }
+
+    pub fn location_strategy(&self) -> LocationStrategy {
+        self.location_strategy
+    }
+
+    pub fn allow_ansi_in_files(&mut self, allow: bool) -> &mut Self {
+        Arc::make_mut(&mut self.render).allow_ansi = allow;
+        self
+    }
+
+    /// Names `level` `label` in the level flag of text lines, `[label]`,
+    /// and in `{level}`; the other levels keep their names. A
+    /// `set_level_fmt` comes first, JSON lines keep the usual names.
+    pub fn set_level_label(&mut self, level: LEVEL, label: &str) -> &mut Self {
+        Arc::make_mut(&mut self.render).labels.set(level, label);
+        self
+    }
+
+    pub fn clear_level_labels(&mut self) -> &mut Self {
+        Arc::make_mut(&mut self.render).labels.clear();
+        self
+    }
+
+    /// Colors the level flag of the console lines, see `color`; left off
+    /// on a stream that is not a terminal. Default: false.
+    pub fn set_console_color(&mut self, on: bool) -> &mut Self {
+        match on {
+            true => self.set_console_color_options(ColorOptions::default()),
+            false => {
+                Arc::make_mut(&mut self.render).console_color = None;
+                self
+            }
+        }
+    }
+
+    /// Colors the console lines as `opts` says, see `color`.
+    pub fn set_console_color_options(&mut self, opts: ColorOptions) -> &mut Self {
+        Arc::make_mut(&mut self.render).console_color = ConsoleColors::new(opts);
+        self
+    }
+
+    /// Sends the console lines to stdout, the default, to stderr, or split
+    /// by level with `ConsoleStream::SplitAt`. Stdout is flushed before a
+    /// line goes to stderr, so the two keep their order on a shared terminal.
+    pub fn set_console_stream(&mut self, stream: ConsoleStream) -> &mut Self {
+        Arc::make_mut(&mut self.render).console_stream = stream;
+        self
+    }
+
+    /// Where queued lines are laid out, see `FormatStage`. Lines to a
+    /// handler with a quota are laid out at the call site either way, as
+    /// the quota is charged before they are queued.
+    pub fn set_format_stage(&mut self, stage: FormatStage) -> &mut Self {
+        self.format_stage = stage;
+        self
+    }
+
+    /// Sets up logging for a container: JSON lines on stdout with `v`, the
+    /// schema version of `json`, `ts` (RFC 3339, UTC), `level`, `msg`,
+    /// `caller` and `logger` keys, plus `pod` and `namespace` from
+    /// `POD_NAME` and `POD_NAMESPACE` when set. Level Info, no file, no body formatters; `describe()` shows the preset.
+    pub fn preset_k8s(&mut self) -> &mut Self {
+        self.fmthandle.set_console(true);
+        self.fmthandle.set_level(LEVEL::Info);
+        self.fmthandle.set_format(Format::LevelFlag | Format::Date | Format::Time | Format::Microseconds | Format::LongFileName);
+        self.fmthandle.clear_formatter();
+        let render = Arc::make_mut(&mut self.render);
+        render.attrfmt = AttrFormat::new();
+        render.formatter = None;
+        render.console_formatter = None;
+        render.preset = Some(Preset::K8s(K8sPreset::from_env()));
+        self.filehandle = ("".to_string(), SharedHandler::new(FHandler::new()));
+        self.routing = None;
+        self.output.reset(OutputSink::Console, OutputMode::Json);
+        self.output.reset(OutputSink::File, OutputMode::Json);
+        self
+    }
+
+    /// Pins the JSON lines to an older schema version, see `json`.
+    /// Errs with `Error::UnknownSchemaVersion` for a version there isn't.
+    pub fn set_json_schema_version(&mut self, version: u32) -> Result<&mut Self, Error> {
+        let schema = Schema::version(version).ok_or(Error::UnknownSchemaVersion(version))?;
+        Arc::make_mut(&mut self.render).json_schema = Some(schema);
+        Ok(self)
+    }
+
+    /// Sets up a service whose stdout goes to journald: each console line
+    /// starts with the `<N>` priority of its level (`<7>` for Trace and
+    /// Debug up to `<2>` for Fatal), ahead of any console body formatting,
+    /// and leaves out the time journald stamps itself. Files are unaffected.
+    pub fn preset_systemd(&mut self) -> &mut Self {
+        self.fmthandle.set_console(true);
+        Arc::make_mut(&mut self.render).preset = Some(Preset::Systemd);
+        self.output.reset(OutputSink::Console, OutputMode::Text);
+        self
+    }
+
+    /// Puts `sink` in `mode`, see `output`. Errs with
+    /// `Error::ConflictingMode` while it is in another mode.
+    pub fn set_output_mode(&mut self, sink: OutputSink, mode: OutputMode) -> Result<&mut Self, Error> {
+        self.output.claim(sink, mode)?;
+        Ok(self)
+    }
+
+    pub fn clear_output_mode(&mut self, sink: OutputSink) -> &mut Self {
+        self.output.clear(sink);
+        self
+    }
+
+    /// Writes to `sink` only the lines at `level` or above, whatever the
+    /// level of their module, see `output`. The macros let a line through
+    /// once one sink takes it.
+    pub fn set_output_level(&mut self, sink: OutputSink, level: LEVEL) -> &mut Self {
+        self.output_levels.set(sink, Some(level));
+        self
+    }
+
+    /// Lets `sink` follow the level of each line's module again.
+    pub fn clear_output_level(&mut self, sink: OutputSink) -> &mut Self {
+        self.output_levels.set(sink, None);
+        self
+    }
+
+    pub fn output_level(&self, sink: OutputSink) -> Option<LEVEL> {
+        self.output_levels.get(sink)
+    }
+
+    /// The output mode of each sink; errs with `Error::ConflictingMode` on
+    /// a sink not in text mode that has a formatter template or a body format.
+    pub fn validate(&self) -> Result<Vec<(OutputSink, OutputMode)>, Error> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).check();
+        self.output.validate(&self.render, self.fmthandle.get_formatter().is_some())
+    }
+
+    /// The event IDs lines are expected to carry, with what each means;
+    /// `validate` warns about the others, see `events`.
+    pub fn set_event_registry(&mut self, events: &[(&str, &str)]) -> &mut Self {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).set_registry(events);
+        self
+    }
+
+    pub fn event_description(&self, id: &str) -> Option<String> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).description(id).map(str::to_string)
+    }
+
+    /// Appends the trace and span IDs of the active OpenTelemetry span to
+    /// every line, see `otel`. Does nothing without the `otel` feature.
+    pub fn set_auto_trace_ids(&mut self, on: bool) -> &mut Self {
+        #[cfg(feature = "otel")]
+        {
+            self.auto_trace_ids = on;
+        }
+        #[cfg(not(feature = "otel"))]
+        let _ = on;
+        self
+    }
+
+    /// Sends the lines to journald as structured records instead of the
+    /// console and the files, falling back to those while its socket fails,
+    /// see `journald`.
+    #[cfg(feature = "journald")]
+    pub fn set_journald(&mut self, on: bool) -> &mut Self {
+        self.set_journald_socket(on.then(|| PathBuf::from(JOURNALD_SOCKET)))
+    }
+
+    /// `set_journald` with a socket other than `JOURNALD_SOCKET`, as in a
+    /// container with the journal mounted elsewhere; None turns it off.
+    #[cfg(feature = "journald")]
+    pub fn set_journald_socket(&mut self, socket: Option<PathBuf>) -> &mut Self {
+        Arc::make_mut(&mut self.render).source = socket.is_some();
+        self.journald = socket.map(|path| Mutex::new(Journald::new(path)));
+        self
+    }
+
+    /// Sends the lines to syslog instead of the console and the files,
+    /// falling back to those while the socket fails, see `syslog`.
+    #[cfg(feature = "syslog")]
+    pub fn set_syslog(&mut self, config: SyslogConfig) -> &mut Self {
+        self.syslog = Some(Mutex::new(Syslog::new(config)));
+        self
+    }
+
+    #[cfg(feature = "syslog")]
+    pub fn clear_syslog(&mut self) -> &mut Self {
+        self.syslog = None;
+        self
+    }
+
+    /// Whether the line went to syslog, see `set_syslog`.
+    #[cfg(feature = "syslog")]
+    fn send_syslog(&self, level: LEVEL, message: &LogContent) -> bool {
+        self.syslog.as_ref().is_some_and(|s| s.lock().unwrap_or_else(|e| e.into_inner()).send(level, &message.file_body))
+    }
+
+    /// Whether the line went to journald, see `set_journald`.
+    #[cfg(feature = "journald")]
+    fn send_journald(&self, level: LEVEL, module: &str, message: &LogContent) -> bool {
+        self.journald.as_ref().is_some_and(|j| j.lock().unwrap_or_else(|e| e.into_inner()).send(level, module, message.source.as_ref(), &message.file_body))
+    }
+
+    /// Ships the lines over TCP or UDP too, see `remote`. A remote end set
+    /// before is flushed first.
+    pub fn set_remote(&mut self, config: RemoteConfig) -> &mut Self {
+        self.remote = Some(Remote::new(config, true));
+        self
+    }
+
+    /// Stops shipping the lines, once those queued are sent or the flush
+    /// timeout is over.
+    pub fn clear_remote(&mut self) -> &mut Self {
+        self.remote = None;
+        self
+    }
+
+    /// The lines dropped from the full queue of the remote end.
+    pub fn remote_dropped(&self) -> u64 {
+        self.remote.as_ref().map_or(0, |r| r.dropped())
+    }
+
+    /// Replaces the time source of time-window logic such as storm control.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keeps the bytes laid out per day near `bytes_per_day` on long
+    /// unattended runs: when the day's volume projects over it, every
+    /// level is raised one step for the rest of the day, with a notice
+    /// from module `tklog`, and back as set the next day. 0 turns it off,
+    /// the default.
+    /// Lines are then laid out at the call site whatever the format stage.
+    pub fn set_adaptive_budget(&mut self, bytes_per_day: u64) -> &mut Self {
+        self.budget = (bytes_per_day > 0).then(|| Mutex::new(AdaptiveBudget::new(bytes_per_day, self.clock.wall())));
+        self
+    }
+
+    /// Measures the enqueue-to-write latency of one in `n` queued lines;
+    /// 0 turns latency sampling off. Default: 64.
+    pub fn set_latency_sampling(&mut self, n: u64) -> &mut Self {
+        self.latency_sampling = n;
+        self
+    }
+
+    /// Per-sink queue depth and latency statistics.
+    pub fn stats(&self) -> LogStats {
+        let mut stats = self.stats.snapshot();
+        stats.quotas = self.quotas.lock().unwrap_or_else(|e| e.into_inner()).iter().map(|(handler, q)| q.stats(handler)).collect();
+        stats
+    }
+
+    /// The lines printed at each level, and the bytes, rotations and
+    /// compressions of the files.
+    pub fn metrics(&self) -> LogMetrics {
+        self.filesettings.metrics.snapshot()
+    }
+
+    /// Sets every counter of `metrics` back to 0.
+    pub fn reset_metrics(&self) {
+        self.filesettings.metrics.reset();
+    }
+
+    /// A readable summary of the configuration, the extra handlers and the
+    /// consumption of each handler quota.
+    pub fn describe(&self) -> String {
+        let mut out = self.config().to_string();
+        let mut handlers: Vec<&String> = self.fmap.keys().collect();
+        handlers.sort();
+        for h in handlers {
+            let _ = writeln!(out, "handler: {}", h);
+        }
+        for (handler, q) in self.quotas.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let q = q.stats(handler);
+            let _ = writeln!(out, "quota: {} {}/{} bytes today, {} lines dropped", q.handler, q.used, q.bytes_per_day, q.dropped);
+        }
+        if let Some(routing) = &self.routing {
+            out.push_str(&routing.describe());
+        }
+        if let Some(preset) = &self.render.preset {
+            out.push_str(&format!("preset: {}\n", preset.name()));
+        }
+        if self.format_stage == FormatStage::Worker {
+            out.push_str("format stage: worker\n");
+        }
+        out.push_str(&self.output.describe());
+        if let Some(d) = &self.directory {
+            let d = d.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = writeln!(out, "directory: {} ({:?})", d.root().display(), d.layout());
+        }
+        out
+    }
+
+    pub fn get_level(&self, module: &str) -> LEVEL {
+        let level = self.output_levels.lowest(self.set_level_of(module), self.fmthandle.get_console());
+        let Some(budget) = &self.budget else {
+            return level;
+        };
+        let (raised, notice) = budget.lock().unwrap_or_else(|e| e.into_inner()).check(self.clock.wall());
+        if let Some(notice) = notice {
+            self.queue_internal(LEVEL::Info, notice);
+        }
+        if raised {
+            budget::raise(level)
+        } else {
+            level
+        }
+    }
+
+    /// The level of `module` as set, before the adaptive budget.
+    fn set_level_of(&self, module: &str) -> LEVEL {
+        if module != "" && self.modmap.len() > 0 {
+            if let Some(level) = self.module_level(module) {
+                return level;
+            }
+        }
+        self.fmthandle.get_level()
+    }
+
+    /// The level option of the pattern `module` matches, cached until the
+    /// module options change.
+    fn module_level(&self, module: &str) -> Option<LEVEL> {
+        let generation = self.modmap.generation();
+        {
+            let cache = self.level_cache.read().unwrap_or_else(|e| e.into_inner());
+            if cache.0 == generation {
+                if let Some(level) = cache.1.get(module) {
+                    return *level;
+                }
+            }
+        }
+        let level = self.modmap.lookup(module).and_then(|(lo, _)| lo.level);
+        let mut cache = self.level_cache.write().unwrap_or_else(|e| e.into_inner());
+        if cache.0 != generation {
+            *cache = (generation, HashMap::new());
+        }
+        cache.1.insert(module.to_string(), level);
+        level
+    }
+
+    /// Lays out a line of tklog itself to go out ahead of the next one.
+    fn queue_internal(&self, level: LEVEL, message: String) {
+        if self.get_level("tklog") <= level {
+            let s = self.fmt("tklog", level, "", 0, message);
+            if !s.is_empty() {
+                self.pending.lock().unwrap_or_else(|e| e.into_inner()).push((level, s));
+            }
+        }
+    }
+
+    /// The bodies of `record`, counted against the adaptive budget.
+    fn content(&self, record: &RecordSnapshot, fmat: u8, formatter: Option<&String>) -> LogContent {
+        let content = self.render.content(record, fmat, formatter);
+        if let Some(budget) = &self.budget {
+            let notice = budget.lock().unwrap_or_else(|e| e.into_inner()).charge(self.clock.wall(), content.size());
+            if let Some(notice) = notice {
+                self.queue_internal(LEVEL::Warn, notice);
+            }
+        }
+        if !self.hooks.is_empty() && !content.is_empty() {
+            let _inside = Inside::enter();
+            self.hooks.call(record.level, &record.module, &content.file_body);
+        }
+        content
+    }
+
+    /// Whether the macros pass the file and line of a line: when its format
+    /// shows them, or for the filters and the captures.
+    pub fn is_file_line(&self, level: LEVEL, module: &str) -> bool {
+        !self.filters.is_empty() || !self.captures.is_empty() || self.shows_file_line(level, module)
+    }
+
+    fn shows_file_line(&self, level: LEVEL, module: &str) -> bool {
+        if let Some(levels) = &self.levels {
+            if let Some(lp) = &levels[level as usize - 1] {
+                let (lo, _) = lp;
+                if let Some(v) = lo.format {
+                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
+                }
+            }
+        }
+
+        if module != "" && self.modmap.len() > 0 {
+            if let Some(mm) = self.modmap.lookup(module) {
+                let (lo, _) = mm;
+                if let Some(v) = lo.format {
+                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
+                }
+            }
+        }
+        self.fmthandle.is_file_line()
+    }
+
+    pub fn fmt(
+        &self,
+        module: &str,
+        level: LEVEL,
+        filename: &str,
+        line: u32,
+        message: String,
+    ) -> LogContent {
+        self.fmt_with_event(module, level, filename, line, None, message)
+    }
+
+    /// `fmt` for a line with an event ID, see `events`.
+    pub fn fmt_with_event(&self, module: &str, level: LEVEL, filename: &str, line: u32, event: Option<&'static str>, message: String) -> LogContent {
+        self.fmt_with_fields(module, level, filename, line, event, message, FieldMap::new())
+    }
+
+    /// `fmt_with_event` with the fields of the line, as given to a macro
+    /// after its `;`, see `fields`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn fmt_with_fields(&self, module: &str, level: LEVEL, filename: &str, line: u32, event: Option<&'static str>, message: String, fields: FieldMap) -> LogContent {
+        match self.capture(module, level, filename, line, event, message, fields) {
+            Some((record, fmat, formatter)) => self.content(&record, fmat, formatter),
+            None => LogContent::new(String::new(), None),
+        }
+    }
+
+    /// The snapshot of one line with its format and formatter, after the
+    /// storm control and the custom handler; `None` when they drop it.
+    #[allow(clippy::too_many_arguments)]
+    fn capture<'a>(&self, module: &'a str, level: LEVEL, filename: &'a str, line: u32, event: Option<&'static str>, message: String, fields: FieldMap) -> Option<(RecordSnapshot<'a>, u8, Option<&String>)> {
+        let _inside = Inside::enter();
+        if !self.filters.is_empty() && !self.filters.pass(&LogRecord { level, module, file: filename, line, message: &message }) {
+            self.stats.filtered();
+            return None;
+        }
+        let at = (filename, line);
+        let (filename, line) = match (self.filters.is_empty() && self.captures.is_empty()) || self.shows_file_line(level, module) {
+            true => (filename, line),
+            false => ("", 0),
+        };
+        if let Some(storm) = &self.storm {
+            let mut notices = Vec::new();
+            let pass = storm.lock().unwrap_or_else(|e| e.into_inner()).check(self.clock.now(), module, level, &mut notices);
+            for n in notices {
+                if self.get_level("tklog") <= LEVEL::Warn {
+                    let s = self.fmt("tklog", LEVEL::Warn, "", 0, n);
+                    if !s.is_empty() {
+                        self.pending.lock().unwrap_or_else(|e| e.into_inner()).push((LEVEL::Warn, s));
+                    }
+                }
+            }
+            if !pass {
+                self.stats.storm_suppressed();
+                return None;
+            }
+        }
+        if module != "tklog" {
+            let notice = callers::check(&mut self.callers.lock().unwrap_or_else(|e| e.into_inner()), self.clock.now(), &message);
+            if let Some(notice) = notice {
+                let s = self.fmt("tklog", level, "", 0, notice);
+                if !s.is_empty() {
+                    self.pending.lock().unwrap_or_else(|e| e.into_inner()).push((level, s));
+                }
+            }
+        }
+        if self.custom_handler.is_some() {
+            if let Some(ch) = &self.custom_handler {
+                let ctx = LogContext {
+                    level: level,
+                    filename: filename.to_string(),
+                    line: line,
+                    log_body: message.clone(),
+                    modname: module.to_string(),
+                };
+                if !self.custom_panics.call("custom handler", || ch(&ctx)).unwrap_or(true) {
+                    return None;
+                }
+            }
+        }
+        self.captures.record(level, module, at.0, at.1, &message);
+        let mut fmat = self.fmthandle.get_format();
+        let mut formatter = self.fmthandle.get_formatter();
+        if module != "" && self.modmap.len() > 0 {
+            if let Some(mm) = self.modmap.lookup(module) {
+                let (lo, _) = mm;
+                if let Some(v) = lo.format {
+                    fmat = v;
+                }
+                if lo.formatter.is_some() {
+                    formatter = lo.formatter.as_ref();
+                }
+            }
+        }
+
+        if let Some(levels) = &self.levels {
+            if let Some(lp) = &levels[level as usize - 1] {
+                let (lo, _) = lp;
+                if let Some(v) = lo.format {
+                    fmat = v;
+                }
+                if lo.formatter.is_some() {
+                    formatter = lo.formatter.as_ref();
+                }
+            }
+        }
+
+        #[cfg(feature = "otel")]
+        let message = if self.auto_trace_ids { crate::otel::with_trace_ids(message) } else { message };
+        let fields = self.field_limits.apply(fields::of_line(fields, self.context_fields, self.dynamic_fields.as_ref()));
+        let time = self.testmode.map_or_else(now, |t| t.fixed_time);
+        let record = RecordSnapshot {
+            level,
+            time,
+            module: Cow::Borrowed(module),
+            file: Cow::Borrowed(filename),
+            line,
+            message,
+            fields,
+            seq: self.seq.fetch_add(1, Ordering::Relaxed),
+            subseq: self.subseq.then(|| subseq(&time, fmat & Format::Microseconds != 0)),
+            event,
+            boot_id: self.boot_id.then(boot::boot_id),
+            thread: (fmat & Format::ThreadId != 0 || formatter.is_some_and(|f| places(f, "thread"))).then(thread_label),
+            task: (fmat & Format::ThreadId != 0 || formatter.is_some_and(|f| places(f, "task"))).then(tokio::task::try_id).flatten(),
+            function: (fmat & Format::FuncName != 0 || formatter.is_some_and(|f| places(f, "function"))).then(|| crate::function_of(module, self.function_module)).flatten(),
+        };
+        if let Some(id) = event {
+            self.events.lock().unwrap_or_else(|e| e.into_inner()).seen(id);
+        }
+        Some((record, fmat, formatter))
+    }
+
+    /// Leaving `PRINTMODE::DELAY`, the next line written waits for the
+    /// lines queued so far to be written first.
+    pub fn set_printmode(&mut self, mode: PRINTMODE) -> &mut Self {
+        if self.mode == PRINTMODE::DELAY && mode != PRINTMODE::DELAY {
+            self.drain_queue.store(true, Ordering::Release);
+        }
+        self.mode = mode;
+        self
+    }
+
+    /// Writes the lines of `PRINTMODE::DELAY` `lines` at a time, trading
+    /// latency for throughput; a partial batch is written once its oldest
+    /// line has waited `set_delay_max_latency`. 0, the default, writes
+    /// whatever is queued when the consumer gets to it.
+    pub fn set_delay_batch(&mut self, lines: usize) -> &mut Self {
+        self.batch.set_size(lines);
+        self
+    }
+
+    /// How long a line of `PRINTMODE::DELAY` waits at most for its batch to
+    /// fill, see `set_delay_batch`. Default: 100ms.
+    pub fn set_delay_max_latency(&mut self, latency: Duration) -> &mut Self {
+        self.batch.set_max_latency(latency);
+        self
+    }
+
+    /// The lines queued and not written yet, those held in a batch included.
+    pub fn pending_records(&self) -> u64 {
+        self.stats.pending()
+    }
+
+    /// The lines `PRINTMODE::DELAY` queues at most, past which a line
+    /// waits or is dropped as `set_overflow_policy` says; every 10s that
+    /// lines were dropped, a warning tells how many. 0, the default, is
+    /// unbounded.
+    pub fn set_channel_capacity(&mut self, lines: usize) -> &mut Self {
+        self.channel.set_capacity(lines);
+        self
+    }
+
+    /// What a line does when the queue is full, see `set_channel_capacity`.
+    /// Default: `OverflowPolicy::Block`.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) -> &mut Self {
+        self.channel.set_policy(policy);
+        self
+    }
+
+    /// The lines dropped because the queue was full.
+    pub fn dropped_records(&self) -> u64 {
+        self.channel.dropped()
+    }
+
+    pub fn set_level(&mut self, level: LEVEL) -> &mut Self {
+        self.fmthandle.set_level(level);
+        self
+    }
+
+    pub fn set_console(&mut self, console: bool) -> &mut Self {
+        self.fmthandle.set_console(console);
+        self
+    }
+
+    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
+    pub fn set_format(&mut self, format: u8) -> &mut Self {
+        self.fmthandle.set_format(format);
+        self
+    }
+
+    /// The template of the lines, default `"{level}{time} {file}:{message}\n"`.
+    /// It places `{level}`, `{time}`, `{file}`, `{line}`, `{module}`,
+    /// `{message}`, `{thread}` (the thread name, or its ID), `{pid}`, `{seq}`,
+    /// `{subseq}`, `{event}` and `{bootid}`. `{file}` includes the line unless
+    /// `{line}` is placed too; both need a file name `Format`. An unknown
+    /// placeholder is written as it is.
+    pub fn set_formatter(&mut self, formatter: &str) -> &mut Self {
+        self.leave_format_json();
+        self.fmthandle.set_formatter(formatter.to_string());
+        self
+    }
+
+    /// Lays out the lines of `level` with `formatter` instead of the one
+    /// of `set_formatter`, keeping the other options of `level`, as
+    /// `set_level_option` would set them. The format flags still decide
+    /// what the lines carry; JSON lines are unaffected.
+    pub fn set_formatter_for(&mut self, level: LEVEL, formatter: &str) -> &mut Self {
+        let levels = self.levels.get_or_insert_with(|| std::array::from_fn(|_| None));
+        let (lo, _) = levels[level as usize - 1].get_or_insert_with(|| (LogOption::new(), String::new()));
+        lo.formatter = Some(formatter.to_string());
+        self
+    }
+
+    /// Rotates the default file as `cut` says, see `cut::CutSize::builder`,
+    /// `cut::CutTime::builder` and `cut::CutMixed::builder`.
+    pub async fn set_cut(&mut self, cut: impl Into<CutConfig>) -> &mut Self {
+        // A file that cannot be opened went to the error handler.
+        let _ = self.set_default_file(Box::new(cut.into().option())).await;
+        self
+    }
+
+    pub async fn set_cutmode_by_size(
+        &mut self,
+        filename: &str,
+        maxsize: u64,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &mut Self {
+        self.set_cut(CutSize::unchecked(filename, maxsize, maxbackups, compress)).await
+    }
+
+    /// `set_cutmode_by_size` with a size such as `"128MB"`, see `parse_size`.
+    pub async fn set_cutmode_by_size_str(&mut self, filename: &str, maxsize: &str, maxbackups: u32, compress: bool) -> Result<&mut Self, ParseSizeError> {
+        let maxsize = crate::parse_size(maxsize)?;
+        Ok(self.set_cutmode_by_size(filename, maxsize, maxbackups, compress).await)
+    }
+
+    pub async fn set_cutmode_by_time(
+        &mut self,
+        filename: &str,
+        mode: MODE,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &mut Self {
+        self.set_cut(CutTime::unchecked(filename, mode, maxbackups, compress)).await
+    }
+
+    /// Rotates the default file every `interval`, e.g. every 6 hours, see
+    /// `MODE::INTERVAL`; backups carry the start of their period.
+    pub async fn set_cutmode_by_interval(&mut self, filename: &str, interval: Duration, maxbackups: u32, compress: bool) -> &mut Self {
+        self.set_cut(CutTime::unchecked(filename, MODE::INTERVAL(interval), maxbackups, compress)).await
+    }
+
+    /// Rotates the default file at the end of each `mode` period and
+    /// whenever it would pass `maxsize` bytes, see `CUTMODE::MIXED`.
+    pub async fn set_cutmode_by_mixed(&mut self, filename: &str, mode: MODE, maxsize: u64, maxbackups: u32, compress: bool) -> &mut Self {
+        self.set_cut(CutMixed::unchecked(filename, mode, maxsize, maxbackups, compress)).await
+    }
+
+    /// Rotates the default file at the end of each `mode` period by writing
+    /// to the file the strftime `pattern` names for the period, such as
+    /// `logs/app-%Y-%m-%d.log`, instead of renaming one file, see
+    /// `CUTMODE::PATTERN`. A restart appends to the file of its period; the
+    /// file of a period over is compressed in place, and `maxbackups`
+    /// counts the files the pattern names. Errs with
+    /// `Error::InvalidTimeFormat` or `Error::InvalidCut` for a pattern
+    /// without a date in its file name or with one in its directories, and
+    /// with `Error::TestModeWithRotation` in test mode.
+    pub async fn set_cutmode_by_time_pattern(&mut self, pattern: &str, mode: MODE, maxbackups: u32, compress: bool) -> Result<&mut Self, Error> {
+        check_file_pattern(pattern)?;
+        if self.testmode.is_some() {
+            return Err(Error::TestModeWithRotation(pattern.to_string()));
+        }
+        // A file that cannot be opened went to the error handler.
+        let _ = self.set_default_file(Box::new(FileOptionType::new(CUTMODE::PATTERN, mode, pattern, 0, maxbackups, compress))).await;
+        Ok(self)
+    }
+
+    /// Sets how `maxbackups` retention counts backups, for every file handler
+    /// configured so far and any configured later. Default: `PrunePolicy::ByFile`.
+    pub fn set_prune_policy(&mut self, policy: PrunePolicy) -> &mut Self {
+        self.filesettings.prune_policy = policy;
+        self.update_file_settings();
+        self
+    }
+
+    /// Keeps at most `n` backups of every file handler configured so far and
+    /// any configured later, time or size cut, compressed or not, in place
+    /// of their own `maxbackups`; the oldest go at rotation. 0 keeps them all.
+    pub fn set_max_backups(&mut self, n: u32) -> &mut Self {
+        self.filesettings.max_backups = Some(n);
+        self.update_file_settings();
+        self
+    }
+
+    /// Names the backups of every file handler configured so far and any
+    /// configured later after `template`, see `backupname`, in place of
+    /// `app_1.log` and `app_20240501_1.log`. Errs with
+    /// `Error::InvalidBackupTemplate` for a template `backupname` can't take
+    /// or whose backups could be named like a configured log file.
+    pub fn set_backup_name_template(&mut self, template: &str) -> Result<&mut Self, Error> {
+        let template = BackupTemplate::parse(template)?;
+        for filename in std::iter::once(&self.filehandle.0).chain(self.fmap.keys()) {
+            template.check_file(filename)?;
+        }
+        self.filesettings.backup_template = Some(template);
+        self.update_file_settings();
+        Ok(self)
+    }
+
+    /// At each rotation, also deletes the backups of the rotated file cut
+    /// more than `max_age` ago, by their modification time, for every file
+    /// handler configured so far and any configured later. A backup that
+    /// can't be deleted is reported as a `DeleteFailed` diagnostic.
+    pub fn set_backup_max_age(&mut self, max_age: Duration) -> &mut Self {
+        self.filesettings.backup_max_age = Some(max_age);
+        self.update_file_settings();
+        self
+    }
+
+    /// Sets the format and level (0-9, default 6) used for rotated backups
+    /// of handlers with compression enabled.
+    pub fn set_compression(&mut self, compress_type: CompressType, level: u32) -> &mut Self {
+        self.filesettings.compress_type = compress_type;
+        self.filesettings.compress_level = level.min(9);
+        self.update_file_settings();
+        self
+    }
+
+    /// Sets the level of `set_compression` alone: 0 (fastest) to 9
+    /// (smallest). Errs with `Error::InvalidCompressLevel` above 9.
+    pub fn set_compress_level(&mut self, level: u32) -> Result<&mut Self, Error> {
+        if level > 9 {
+            return Err(Error::InvalidCompressLevel(level));
+        }
+        self.filesettings.compress_level = level;
+        self.update_file_settings();
+        Ok(self)
+    }
+
+    /// Keeps each rotated backup as it is next to its `.gz`, for an
+    /// archiver to remove once it has it. The pair counts as one backup
+    /// for `maxbackups`, and a backup with its `.gz` is not compressed
+    /// again. Default: false.
+    pub fn set_compress_keep_original(&mut self, keep: bool) -> &mut Self {
+        self.filesettings.compress_keep_original = keep;
+        self.update_file_settings();
+        self
+    }
+
+    /// Leaves the newest `n` backups of a handler with compression raw,
+    /// for `grep` and `tail`; each rotation compresses those older, and
+    /// its event says `CompressDecision::Deferred`. 0, the default,
+    /// compresses every backup as it is cut.
+    pub fn set_compress_after(&mut self, n: u32) -> &mut Self {
+        self.filesettings.compress_after = n;
+        self.update_file_settings();
+        self
+    }
+
+    /// Gives the files created from now on, backups and their `.gz`
+    /// included, the unix permission bits `mode`, such as `0o640`, whatever
+    /// the umask. Files already there keep theirs. Does nothing off unix.
+    pub fn set_file_mode(&mut self, mode: u32) -> &mut Self {
+        self.filesettings.file_mode = Some(mode);
+        self.update_file_settings();
+        self
+    }
+
+    /// Lets several processes write the same files: each line is appended
+    /// under an exclusive advisory lock of the file (flock, LockFileEx),
+    /// whole, and a rotation first checks whether another process has
+    /// rotated the file already. The size cuts count the size of the file.
+    /// Every line then costs a lock, a stat and an unbuffered write, several
+    /// times a line of one process. `set_buffer_size` does not apply, and
+    /// a file with live compression is not locked.
+    pub fn set_shared_file(&mut self, shared: bool) -> &mut Self {
+        self.filesettings.shared = shared;
+        self.update_file_settings();
+        self
+    }
+
+    /// Writes the files compressed as they are logged, rather than raw and
+    /// then compressed at rotation, for captures too large to write twice.
+    /// A file `app.log` is written as `app.log.gz`, and each rotation ends
+    /// its gzip stream and moves it to a backup such as `app_1.log.gz`.
+    /// Lines reach the file at a sync flush, at the first write once
+    /// `flush_interval` has passed since the last one and at `flush`; a reader such
+    /// as `compress::open_any` gets them up to there. Rotation by size
+    /// counts the bytes before compression. Uses the level of
+    /// `set_compression`.
+    pub fn set_live_compression(&mut self, compress_type: CompressType, flush_interval: Duration) -> &mut Self {
+        self.filesettings.live_compression = Some((compress_type, flush_interval));
+        self.update_file_settings();
+        self
+    }
+
+    /// Writes raw files again, see `set_live_compression`.
+    pub fn clear_live_compression(&mut self) -> &mut Self {
+        self.filesettings.live_compression = None;
+        self.update_file_settings();
+        self
+    }
+
+    /// Gathers up to `size` bytes of lines per file before writing them, for
+    /// fewer writes under load; 0, the default, writes every line. What a
+    /// file holds is written out before a rotation, a release or a close, at
+    /// `flush` and, with `set_flush_interval`, once it is that old. Files
+    /// with live compression, which buffers on its own, write every line.
+    pub fn set_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.filesettings.buffer_size = size;
+        self.update_file_settings();
+        self
+    }
+
+    /// Writes out the buffer of a file once its oldest line has waited
+    /// `interval`, see `set_buffer_size`; zero turns it off. Takes effect at
+    /// once for the open files too.
+    pub fn set_flush_interval(&mut self, interval: Duration) -> &mut Self {
+        self.flusher.set_interval((!interval.is_zero()).then_some(interval));
+        self
+    }
+
+    /// Skips compressing a rotated backup whose first 64 KiB compress to more
+    /// than `ratio` of their size, e.g. 0.9; the raw backup is kept instead.
+    pub fn set_compression_skip_ratio(&mut self, ratio: f64) -> &mut Self {
+        self.filesettings.compress_skip_ratio = Some(ratio);
+        self.update_file_settings();
+        self
+    }
+
+    /// Compresses a rotated backup only with at least `factor` times its size
+    /// free on its filesystem; otherwise the raw backup is kept. Default: 1.0.
+    pub fn set_compression_space_factor(&mut self, factor: f64) -> &mut Self {
+        self.filesettings.compress_space_factor = factor;
+        self.update_file_settings();
+        self
+    }
+
+    /// Replaces the free-space probe of the compression preflight, e.g. in tests.
+    pub fn set_space_probe(&mut self, probe: fn(&Path) -> io::Result<u64>) -> &mut Self {
+        self.filesettings.space_probe = probe;
+        self.update_file_settings();
+        self
+    }
+
+    /// Called after each rotation, once compression and pruning are done.
+    pub fn set_rotation_handler(&mut self, handler: fn(&RotationEvent)) -> &mut Self {
+        self.filesettings.rotation_handler = Some(handler);
+        self.update_file_settings();
+        self
+    }
+
+    /// Called with every failed write, rotation, compression and backup
+    /// deletion of the files, instead of the notices on stderr, see
+    /// `logerror`.
+    pub fn set_error_handler(&mut self, handler: ErrorHandler) -> &mut Self {
+        self.filesettings.errors.set(Some(handler));
+        self
+    }
+
+    /// Goes back to the notices on stderr.
+    pub fn clear_error_handler(&mut self) -> &mut Self {
+        self.filesettings.errors.set(None);
+        self
+    }
+
+    /// Ends every line written to a file with an HMAC chained from the line
+    /// before, so edits can be detected with `tklog::verify::chain`. Lines
+    /// a file gets are one record each, terminated with a newline.
+    pub fn set_tamper_evidence(&mut self, key: &[u8]) -> &mut Self {
+        self.filesettings.tamper_key = Some(TamperKey::new(key));
+        self.update_file_settings();
+        self
+    }
+
+    fn update_file_settings(&mut self) {
+        for fh in std::iter::once(&self.filehandle.1).chain(self.fmap.values()) {
+            let _ = self.sender.send(Job::Settings(fh.inner.clone(), self.filesettings.clone()));
+        }
+        if let Some(d) = &self.directory {
+            d.lock().unwrap_or_else(|e| e.into_inner()).close_all();
+        }
+    }
+
+    /// Sends the lines that would go to the default file to one file per
+    /// module and day under `root`, see `directory`. Module and level
+    /// options that name a file still win.
+    pub fn set_directory_mode(&mut self, root: PathBuf, layout: DirLayout) -> &mut Self {
+        self.directory = Some(Mutex::new(Directory::new(root, layout)));
+        self
+    }
+
+    /// Keeps the directory files of the last `days` days, deleting older
+    /// ones across the tree; 0, the default, keeps everything. Applies to
+    /// the directory of `set_directory_mode`, which must be set first.
+    pub fn set_directory_retention(&mut self, days: u32) -> &mut Self {
+        match &self.directory {
+            Some(d) => d.lock().unwrap_or_else(|e| e.into_inner()).set_retention(days),
+            None => diagnostics::report(Category::Config, None, "set_directory_retention without set_directory_mode is ignored".to_string()),
+        }
+        self
+    }
+
+    pub fn clear_directory_mode(&mut self) -> &mut Self {
+        self.directory = None;
+        self
+    }
+
+    pub async fn set_option(&mut self, option: LogOption) -> &mut Self {
+        if let Some(v) = option.console {
+            self.fmthandle.set_console(v);
+        }
+        if let Some(v) = option.format {
+            self.fmthandle.set_format(v);
+        }
+        if let Some(v) = option.formatter {
+            self.fmthandle.set_formatter(v);
+        }
+        if let Some(v) = option.level {
+            self.fmthandle.set_level(v);
+        }
+        if let Some(v) = option.fileoption {
+            let _ = self.set_default_file(v).await;
+        }
+        self
+    }
+
+    /// The options of the lines of `module` and of the modules under it:
+    /// the longest pattern matching a module path wins, `app::db` matching
+    /// `app::db::pool` too and `app::db::*` only the modules under
+    /// `app::db`. The separator of `set_mod_separator` is kept.
+    pub async fn set_mod_option(&mut self, module: &str, option: LogOption) -> &mut Self {
+        let mut filename = "".to_string();
+        if let Some(v) = option.fileoption {
+            match self.module_file(&format!("module `{}`", module), v).await {
+                Ok(f) => filename = f,
+                Err(e) if fd_exhausted(&e) => diagnostics::report(Category::FdExhausted, None, format!("out of file descriptors ({}); module {} logs to the default file", e, module)),
+                Err(_) => {}
+            }
+        }
+        let separator = self.modmap.get_pattern_mut(module).and_then(|(lo, _)| lo.separator.take());
+        self.modmap.insert(
+            module,
+            (
+                LogOptionConst {
+                    level: option.level,
+                    format: option.format,
+                    formatter: option.formatter,
+                    console: option.console,
+                    separator,
+                },
+                filename.clone(),
+            ),
+        );
+        self
+    }
+
+    /// The level overrides set with `set_mod_option`, sorted by pattern.
+    pub fn module_levels(&self) -> Vec<(String, LEVEL)> {
+        self.modmap.entries().into_iter().filter_map(|(pattern, (lo, _))| lo.level.map(|l| (pattern, l))).collect()
+    }
+
+    /// The lowest level of any module, the one the `log` facade filters by.
+    pub(crate) fn lowest_level(&self) -> LEVEL {
+        let lowest = self.module_levels().into_iter().fold(self.fmthandle.get_level(), |lowest, (_, l)| if l < lowest { l } else { lowest });
+        self.output_levels.lowest(lowest, self.fmthandle.get_console())
+    }
+
+    /// Sets the level of the modules `pattern` matches, as `set_mod_option`,
+    /// keeping the other options of `pattern`.
+    pub fn set_mod_level(&mut self, pattern: &str, level: LEVEL) -> &mut Self {
+        levelspec::set_level(&mut self.modmap, pattern, level);
+        self
+    }
+
+    /// Sets the separator the macros put between their arguments on the
+    /// lines of the modules `pattern` matches, as `set_mod_option`, keeping
+    /// the other options of `pattern`.
+    pub fn set_mod_separator(&mut self, pattern: &str, separator: &str) -> &mut Self {
+        levelspec::set_separator(&mut self.modmap, pattern, separator);
+        self
+    }
+
+    /// Drops the level override of `pattern`, keeping its other options;
+    /// false if it had none.
+    pub fn clear_module_level(&mut self, pattern: &str) -> bool {
+        levelspec::clear_level(&mut self.modmap, pattern)
+    }
+
+    /// Sets the levels of the spec in the variable `var`, such as
+    /// `info,my_crate::db=debug`, see `levelspec`. Call again to read it
+    /// anew.
+    pub fn set_level_from_env(&mut self, var: &str) -> &mut Self {
+        if let Some(level) = LevelSpec::from_env(var).and_then(|spec| spec.apply(&mut self.modmap, &mut self.env_modules)) {
+            self.set_level(level);
+        }
+        self
+    }
+
+    /// Adds a filter: a line is written only when every filter passes it,
+    /// see `filter`.
+    pub fn set_filter(&mut self, filter: Filter) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn clear_filters(&mut self) -> &mut Self {
+        self.filters.clear();
+        self
+    }
+
+    /// Adds a hook called with the level, module and body of every line,
+    /// see `hook`.
+    pub fn add_hook(&mut self, hook: Hook) -> &mut Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// The captures of this logger, see `capture`.
+    pub(crate) fn captures(&self) -> &Captures {
+        &self.captures
+    }
+
+    pub fn remove_hooks(&mut self) -> &mut Self {
+        self.hooks.clear();
+        self
+    }
+
+    pub fn set_custom_handler(&mut self, handler: fn(&LogContext) -> bool) {
+        self.custom_handler = Some(handler);
+        self.custom_panics.reset();
+    }
+
+    pub async fn set_level_option(&mut self, level: LEVEL, option: &dyn OptionTrait) -> &mut Self {
+        let mut filename = "".to_string();
+        if let Some(v) = option.get_fileoption() {
+            filename = self.module_file(&format!("level {:?}", level), v).await.unwrap_or_default();
+        }
+        let lo = LogOption {
+            level: None,
+            format: option.get_format(),
+            formatter: option.get_formatter(),
+            console: option.get_console(),
+            fileoption: option.get_fileoption(),
+        };
+
+        if self.levels.is_none() {
+            self.levels = Some(std::array::from_fn(|_| None));
+        }
+
+        if let Some(levels) = &mut self.levels {
+            levels[level as usize - 1] = Some((lo, filename));
+        }
+
+        self
+    }
+
+    pub fn set_separator(&mut self, separator: &str) -> &mut Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    pub fn get_separator(&self) -> String {
+        self.separator.clone()
+    }
+
+    /// The message of the macros, `args` joined with the separator of
+    /// `module`, see `crate::message_of`.
+    #[doc(hidden)]
+    pub fn message_of(&self, module: &str, args: &[&dyn std::fmt::Display]) -> String {
+        crate::with_error_backtraces(self.error_backtraces, || crate::message_of(self.separator(module), args))
+    }
+
+    /// Whether the messages show the backtraces of their `ErrorChain`s.
+    pub(crate) fn error_backtraces(&self) -> bool {
+        self.error_backtraces
+    }
+
+    /// The separator of the lines of `module`, for the macros.
+    #[doc(hidden)]
+    pub fn separator(&self, module: &str) -> &str {
+        if self.modmap.len() > 0 {
+            if let Some(separator) = self.modmap.lookup(module).and_then(|(lo, _)| lo.separator.as_deref()) {
+                return separator;
+            }
+        }
+        &self.separator
+    }
+
+    pub fn set_attr_format<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut AttrFormat) + Send + Sync + 'static,
+    {
+        f(&mut Arc::make_mut(&mut self.render).attrfmt);
+    }
+
+    /// Renders the time of text lines, in `{time}` and the default layout,
+    /// as the chrono strftime `format` whenever `Format` asks for any of
+    /// the date, time or microseconds. A `set_time_fmt` comes first; backup
+    /// names keep their own stamps. Errs with `Error::InvalidTimeFormat`
+    /// for a format chrono can't read.
+    pub fn set_time_format(&mut self, format: &str) -> Result<&mut Self, Error> {
+        Arc::make_mut(&mut self.render).time_format = Some(check_time_format(format)?);
+        Ok(self)
+    }
+
+    /// `set_time_format` with RFC 3339 to the microsecond, such as
+    /// `2024-05-01T12:00:00.123456+08:00`.
+    pub fn set_time_rfc3339(&mut self) -> &mut Self {
+        Arc::make_mut(&mut self.render).time_format = Some(RFC3339_TIME_FORMAT.to_string());
+        self
+    }
+
+    /// Lays the default layout out as `columns` in their order, leaving
+    /// out the columns not in it, such as `[TIME, LOGFLAG, MESSAGE]`. The
+    /// `Format` flags still say what each column holds, and a column they
+    /// leave empty is skipped along with its space; `COLON` is written
+    /// only after a column that was. A formatter or preset lays lines out
+    /// instead. Errs with `Error::InvalidColumnOrder` unless `MESSAGE` is
+    /// in `columns` once and no column twice.
+    pub fn set_column_order(&mut self, columns: &[COLUMN]) -> Result<&mut Self, Error> {
+        Arc::make_mut(&mut self.render).columns = Some(check_column_order(columns)?);
+        Ok(self)
+    }
+
+    /// Lays lines out in the default column order again.
+    pub fn clear_column_order(&mut self) -> &mut Self {
+        Arc::make_mut(&mut self.render).columns = None;
+        self
+    }
+
+    /// Returns a comparable snapshot of the effective configuration.
+    pub fn config(&self) -> LogConfig {
+        LogConfig {
+            level: self.fmthandle.get_level(),
+            console: self.fmthandle.get_console(),
+            format: self.fmthandle.get_format(),
+            formatter: self.fmthandle.get_formatter().cloned(),
+            separator: self.separator.clone(),
+            printmode: self.mode,
+            modules: self.module_levels(),
+            file: self.filehandle.1.file.clone(),
+            remote: self.remote.as_ref().map(|r| r.config().transport.to_string()),
+        }
+    }
+
+    /// Applies `config` and logs one Info line describing what changed,
+    /// e.g. `level: Info→Debug, backups: 7→30`. A no-op apply logs nothing.
+    pub async fn apply_config(&mut self, config: LogConfig) -> &mut Self {
+        let current = self.config();
+        let changes = current.diff(&config);
+        if changes.is_empty() {
+            return self;
+        }
+        self.fmthandle.set_level(config.level);
+        self.fmthandle.set_console(config.console);
+        self.fmthandle.set_format(config.format);
+        match config.formatter {
+            Some(f) => self.fmthandle.set_formatter(f),
+            None => self.fmthandle.clear_formatter(),
+        }
+        self.separator = config.separator;
+        self.mode = config.printmode;
+        if current.file != config.file {
+            match &config.file {
+                Some(fc) => {
+                    let _ = self.set_default_file(Box::new(FileOptionType::from_config(fc))).await;
+                }
+                None => self.filehandle = ("".to_string(), SharedHandler::new(FHandler::new())),
+            }
+        }
+        if current.modules != config.modules {
+            for (pattern, _) in &current.modules {
+                levelspec::clear_level(&mut self.modmap, pattern);
+            }
+            for (pattern, level) in &config.modules {
+                levelspec::set_level(&mut self.modmap, pattern, *level);
+            }
+        }
+        if current.remote != config.remote {
+            match Remote::reconfigured(self.remote.as_ref(), config.remote.as_deref()) {
+                Some(remote) => self.set_remote(remote),
+                None => self.clear_remote(),
+            };
+        }
+        self.log_internal(LEVEL::Info, describe_changes(&changes)).await;
+        self
+    }
+
+    /// A logger set up by the config file at `path`, see
+    /// `LogConfig::apply_text`.
+    pub async fn from_config_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut log = Self::new();
+        log.load_config_file(path).await?;
+        Ok(log)
+    }
+
+    /// Applies the config file at `path`, see `LogConfig::apply_text`. A
+    /// file it can't take is refused whole, leaving the configuration as
+    /// it was.
+    pub async fn load_config_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, Error> {
+        let config = self.config().apply_file(path)?;
+        Ok(self.apply_config(config).await)
+    }
+
+    /// Emits a line produced by tklog itself, attributed to module `tklog`.
+    async fn log_internal(&self, level: LEVEL, message: String) {
+        if self.get_level("tklog") <= level {
+            let s = self.fmt("tklog", level, "", 0, message);
+            if !s.is_empty() {
+                self.print(level, "tklog", s).await;
+            }
+        }
+    }
+
+    /// Keeps `f` as the handler of `filename` unless the logger has one.
+    /// Makes `option` the default file; a module file already open on its
+    /// path with the same settings is taken over instead. A conflicting
+    /// one leaves the default file as it is, see `check_paths`.
+    async fn set_default_file(&mut self, option: Box<dyn FileOption>) -> io::Result<()> {
+        match self.paths.claim(DEFAULT_FILE, &*option) {
+            Claim::Conflict => return Ok(()),
+            Claim::Shared(filename) => {
+                if let Some(fh) = self.fmap.remove(&filename) {
+                    self.filehandle = (filename, fh);
+                    return Ok(());
+                }
+            }
+            Claim::New => {}
+        }
+        let f = self.new_filehandler(option).await.inspect_err(|_| self.paths.release(DEFAULT_FILE))?;
+        self.filehandle.0 = f.get_file_name();
+        self.filehandle.1.set_async_file_handler(f).await;
+        Ok(())
+    }
+
+    /// The file name `owner` writes under: the handler open on the path of
+    /// `option` when the settings match, else a new one. Empty, which is
+    /// the default file, when it conflicts with another handler.
+    async fn module_file(&mut self, owner: &str, option: Box<dyn FileOption>) -> io::Result<String> {
+        match self.paths.claim(owner, &*option) {
+            Claim::Conflict => return Ok(String::new()),
+            Claim::Shared(filename) => return Ok(filename),
+            Claim::New => {}
+        }
+        let f = self.new_filehandler(option).await.inspect_err(|_| self.paths.release(owner))?;
+        let filename = f.get_file_name();
+        self.add_module_file(filename.clone(), f);
+        Ok(filename)
+    }
+
+    /// `Error::PathConflict` for the first file handler refused because
+    /// its path is already written with other cut settings, see `paths`.
+    pub fn check_paths(&self) -> Result<(), Error> {
+        self.paths.check()
+    }
+
+    fn add_module_file(&mut self, filename: String, f: FileHandler) {
+        if filename != self.filehandle.0 && !self.fmap.contains_key(&filename) {
+            let mut fhandler = FHandler::new();
+            fhandler.set_async_file_handler(f);
+            let shared = SharedHandler::new(fhandler);
+            self.module_files.lock().unwrap_or_else(|e| e.into_inner()).push(shared.inner.clone());
+            self.fmap.insert(filename, shared);
+        }
+    }
+
+    /// Opens the file of `option`. Out of file descriptors, the module files
+    /// are closed to make room and the open is retried once. A failure goes
+    /// to the error handler.
+    async fn new_filehandler(&self, option: Box<dyn FileOption>) -> io::Result<FileHandler> {
+        if self.testmode.is_some() && handle::rotates(&*option) {
+            let e = io::Error::other("refused in test mode: the file rotates");
+            self.filesettings.errors.report(LogError::OpenFailed(option.filename().into(), logerror::copy(&e)));
+            return Err(e);
+        }
+        let opened = match FileHandler::open(&*option, self.filesettings.file_mode).await {
+            Err(e) if fd_exhausted(&e) => {
+                reclaim_descriptors(&self.module_files, None).await;
+                FileHandler::open(&*option, self.filesettings.file_mode).await
+            }
+            r => r,
+        };
+        let mut f = opened.inspect_err(|e| self.filesettings.errors.report(LogError::OpenFailed(option.filename().into(), logerror::copy(e))))?;
+        f.set_settings(self.filesettings.clone());
+        f.schedule(&self.scheduler);
+        f.flush_with(&self.flusher);
+        Ok(f)
+    }
+
+    /// Freezes the rendered time and restarts `{seq}` at `fixed_seq_start`.
+    /// **Test only**: refused while any file handler with rotation is active,
+    /// and while it is on, a rotating file is refused in turn: it goes to the
+    /// error handler as `LogError::OpenFailed` and the logger keeps its files.
+    pub fn set_test_mode(&mut self, mode: TestMode) -> Result<&mut Self, Error> {
+        for fh in std::iter::once(&self.filehandle.1).chain(self.fmap.values()).chain(self.tees.iter().map(|(_, h)| h)) {
+            if let (true, Some(file)) = (fh.rotating, &fh.file) {
+                return Err(Error::TestModeWithRotation(file.filename.clone()));
+            }
+        }
+        self.seq.store(mode.fixed_seq_start, Ordering::Relaxed);
+        self.testmode = Some(mode);
+        Ok(self)
+    }
+
+    pub fn clear_test_mode(&mut self) -> &mut Self {
+        self.testmode = None;
+        self
+    }
+}
+
+pub struct Log;
+
+impl Log {
+    pub fn new() -> Self {
+        Log {}
+    }
+    pub fn set_printmode(&self, mode: PRINTMODE) -> &Self {
+        global_async_blocking().set_printmode(mode);
+        self
+    }
+
+    pub fn set_delay_batch(&self, lines: usize) -> &Self {
+        global_async_blocking().set_delay_batch(lines);
+        self
+    }
+
+    pub fn set_delay_max_latency(&self, latency: Duration) -> &Self {
+        global_async_blocking().set_delay_max_latency(latency);
+        self
+    }
+
+    pub fn pending_records(&self) -> u64 {
+        global_async_blocking().pending_records()
+    }
+
+    pub fn set_channel_capacity(&self, lines: usize) -> &Self {
+        global_async_blocking().set_channel_capacity(lines);
+        self
+    }
+
+    pub fn set_overflow_policy(&self, policy: OverflowPolicy) -> &Self {
+        global_async_blocking().set_overflow_policy(policy);
+        self
+    }
+
+    pub fn dropped_records(&self) -> u64 {
+        global_async_blocking().dropped_records()
+    }
+
+    pub fn set_level(&self, level: LEVEL) -> &Self {
+        let mut log = global_async_blocking();
+        log.set_level(level);
+        bridge::refresh(LogBridge::Async, log.lowest_level());
+        self
+    }
+
+    pub fn set_console(&self, console: bool) -> &Self {
+        let mut log = global_async_blocking();
+        log.set_console(console);
+        bridge::refresh(LogBridge::Async, log.lowest_level());
+        self
+    }
+
+    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
+    pub fn set_format(&self, format: u8) -> &Self {
+        global_async_blocking().set_format(format);
+        self
+    }
+
+    /** default: "{level}{time} {file}:{message}\n" */
+    pub fn set_formatter(&self, formatter: &str) -> &Self {
+        global_async_blocking().set_formatter(formatter);
+        self
+    }
+
+    pub fn set_formatter_for(&self, level: LEVEL, formatter: &str) -> &Self {
+        global_async_blocking().set_formatter_for(level, formatter);
+        self
+    }
+
+    pub async fn set_cut(&self, cut: impl Into<CutConfig>) -> &Self {
+        global_async().await.set_cut(cut).await;
+        self
+    }
+
+    pub async fn set_cutmode_by_size(
+        &self,
+        filename: &str,
+        maxsize: u64,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &Self {
+        global_async().await.set_cutmode_by_size(filename, maxsize, maxbackups, compress).await;
+        self
+    }
+
+    pub async fn set_cutmode_by_size_str(&self, filename: &str, maxsize: &str, maxbackups: u32, compress: bool) -> Result<&Self, ParseSizeError> {
+        global_async().await.set_cutmode_by_size_str(filename, maxsize, maxbackups, compress).await?;
+        Ok(self)
+    }
+
+    pub async fn set_cutmode_by_time(
+        &self,
+        filename: &str,
+        mode: MODE,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &Self {
+        global_async().await.set_cutmode_by_time(filename, mode, maxbackups, compress).await;
+        self
+    }
+
+    pub async fn set_cutmode_by_interval(&self, filename: &str, interval: Duration, maxbackups: u32, compress: bool) -> &Self {
+        global_async().await.set_cutmode_by_interval(filename, interval, maxbackups, compress).await;
+        self
+    }
+
+    pub async fn set_cutmode_by_mixed(&self, filename: &str, mode: MODE, maxsize: u64, maxbackups: u32, compress: bool) -> &Self {
+        global_async().await.set_cutmode_by_mixed(filename, mode, maxsize, maxbackups, compress).await;
+        self
+    }
+
+    pub async fn set_cutmode_by_time_pattern(&self, pattern: &str, mode: MODE, maxbackups: u32, compress: bool) -> Result<&Self, Error> {
+        global_async().await.set_cutmode_by_time_pattern(pattern, mode, maxbackups, compress).await?;
+        Ok(self)
+    }
+
+    pub fn set_prune_policy(&self, policy: PrunePolicy) -> &Self {
+        global_async_blocking().set_prune_policy(policy);
+        self
+    }
+
+    pub fn set_max_backups(&self, n: u32) -> &Self {
+        global_async_blocking().set_max_backups(n);
+        self
+    }
+
+    pub fn set_backup_name_template(&self, template: &str) -> Result<&Self, Error> {
+        global_async_blocking().set_backup_name_template(template)?;
+        Ok(self)
+    }
+
+    pub fn set_backup_max_age(&self, max_age: Duration) -> &Self {
+        global_async_blocking().set_backup_max_age(max_age);
+        self
+    }
+
+    pub async fn health(&self) -> Health {
+        global_async().await.health().await
+    }
+
+    pub async fn try_recover(&self) -> Result<(), Error> {
+        global_async().await.try_recover().await
+    }
+
+    pub fn set_compression(&self, compress_type: CompressType, level: u32) -> &Self {
+        global_async_blocking().set_compression(compress_type, level);
+        self
+    }
+
+    pub fn set_compress_level(&self, level: u32) -> Result<&Self, Error> {
+        global_async_blocking().set_compress_level(level)?;
+        Ok(self)
+    }
+
+    pub fn set_compress_keep_original(&self, keep: bool) -> &Self {
+        global_async_blocking().set_compress_keep_original(keep);
+        self
+    }
+
+    pub fn set_compress_after(&self, n: u32) -> &Self {
+        global_async_blocking().set_compress_after(n);
+        self
+    }
+
+    pub fn set_file_mode(&self, mode: u32) -> &Self {
+        global_async_blocking().set_file_mode(mode);
+        self
+    }
+
+    pub fn set_shared_file(&self, shared: bool) -> &Self {
+        global_async_blocking().set_shared_file(shared);
+        self
+    }
+
+    pub fn set_live_compression(&self, compress_type: CompressType, flush_interval: Duration) -> &Self {
+        global_async_blocking().set_live_compression(compress_type, flush_interval);
+        self
+    }
+
+    pub fn clear_live_compression(&self) -> &Self {
+        global_async_blocking().clear_live_compression();
+        self
+    }
+
+    pub fn set_buffer_size(&self, size: usize) -> &Self {
+        global_async_blocking().set_buffer_size(size);
+        self
+    }
+
+    pub fn set_flush_interval(&self, interval: Duration) -> &Self {
+        global_async_blocking().set_flush_interval(interval);
+        self
+    }
+
+    pub fn set_compression_skip_ratio(&self, ratio: f64) -> &Self {
+        global_async_blocking().set_compression_skip_ratio(ratio);
+        self
+    }
+
+    pub fn set_compression_space_factor(&self, factor: f64) -> &Self {
+        global_async_blocking().set_compression_space_factor(factor);
+        self
+    }
+
+    pub fn set_space_probe(&self, probe: fn(&Path) -> io::Result<u64>) -> &Self {
+        global_async_blocking().set_space_probe(probe);
+        self
+    }
+
+    pub fn set_tamper_evidence(&self, key: &[u8]) -> &Self {
+        global_async_blocking().set_tamper_evidence(key);
+        self
+    }
+
+    pub fn set_rotation_handler(&self, handler: fn(&RotationEvent)) -> &Self {
+        global_async_blocking().set_rotation_handler(handler);
+        self
+    }
+
+    pub fn set_error_handler(&self, handler: ErrorHandler) -> &Self {
+        global_async_blocking().set_error_handler(handler);
+        self
+    }
+
+    pub fn clear_error_handler(&self) -> &Self {
+        global_async_blocking().clear_error_handler();
+        self
+    }
+
+    pub fn set_latency_sampling(&self, n: u64) -> &Self {
+        global_async_blocking().set_latency_sampling(n);
+        self
+    }
+
+    pub fn attach_runtime(&self, handle: &tokio::runtime::Handle) -> &Self {
+        global_async_blocking().attach_runtime(handle);
+        self
+    }
+
+    pub fn set_prestart_buffer(&self, lines: usize) -> &Self {
+        global_async_blocking().set_prestart_buffer(lines);
+        self
+    }
+
+    pub fn stats(&self) -> LogStats {
+        global_async_blocking().stats()
+    }
+
+    pub fn metrics(&self) -> LogMetrics {
+        global_async_blocking().metrics()
+    }
+
+    pub fn reset_metrics(&self) {
+        global_async_blocking().reset_metrics();
+    }
+
+    pub fn describe(&self) -> String {
+        global_async_blocking().describe()
+    }
+
+    pub async fn flush(&self) {
+        global_async().await.flush().await;
+    }
+
+    pub async fn reopen(&self) -> io::Result<()> {
+        global_async().await.reopen().await
+    }
+
+    pub async fn set_log_path(&self, path: &str, rotate_old: bool) -> Result<&Self, Error> {
+        global_async().await.set_log_path(path, rotate_old).await?;
+        Ok(self)
+    }
+
+    #[cfg(all(unix, feature = "signal"))]
+    pub fn enable_reopen_on_sighup(&self) -> io::Result<&Self> {
+        global_async_blocking().enable_reopen_on_sighup()?;
+        Ok(self)
+    }
+
+    pub async fn log_block(&self, level: LEVEL, module: &str, block: impl FnOnce(&mut BlockWriter)) {
+        if crate::reentrant(level, module, || "a block of lines".to_string()) {
+            return;
+        }
+        global_async().await.log_block(level, module, block).await;
+    }
+
+    pub fn set_routing<F: FnOnce(&mut Routes)>(&self, f: F) -> Result<&Self, Error> {
+        global_async_blocking().set_routing(f)?;
+        Ok(self)
+    }
+
+    pub fn clear_routing(&self) -> &Self {
+        global_async_blocking().clear_routing();
+        self
+    }
+
+    pub fn set_directory_mode(&self, root: PathBuf, layout: DirLayout) -> &Self {
+        global_async_blocking().set_directory_mode(root, layout);
+        self
+    }
+
+    pub fn set_directory_retention(&self, days: u32) -> &Self {
+        global_async_blocking().set_directory_retention(days);
+        self
+    }
+
+    pub fn clear_directory_mode(&self) -> &Self {
+        global_async_blocking().clear_directory_mode();
+        self
+    }
+
+    pub fn set_output_mode(&self, sink: OutputSink, mode: OutputMode) -> Result<&Self, Error> {
+        global_async_blocking().set_output_mode(sink, mode)?;
+        Ok(self)
+    }
+
+    pub fn clear_output_mode(&self, sink: OutputSink) -> &Self {
+        global_async_blocking().clear_output_mode(sink);
+        self
+    }
+
+    pub fn set_output_level(&self, sink: OutputSink, level: LEVEL) -> &Self {
+        let mut log = global_async_blocking();
+        log.set_output_level(sink, level);
+        bridge::refresh(LogBridge::Async, log.lowest_level());
+        self
+    }
+
+    pub fn clear_output_level(&self, sink: OutputSink) -> &Self {
+        let mut log = global_async_blocking();
+        log.clear_output_level(sink);
+        bridge::refresh(LogBridge::Async, log.lowest_level());
+        self
+    }
+
+    pub fn output_level(&self, sink: OutputSink) -> Option<LEVEL> {
+        global_async_blocking().output_level(sink)
+    }
+
+    pub fn validate(&self) -> Result<Vec<(OutputSink, OutputMode)>, Error> {
+        global_async_blocking().validate()
+    }
+
+    pub fn set_event_registry(&self, events: &[(&str, &str)]) -> &Self {
+        global_async_blocking().set_event_registry(events);
+        self
+    }
+
+    pub fn event_description(&self, id: &str) -> Option<String> {
+        global_async_blocking().event_description(id)
+    }
+
+    pub async fn add_file_sink(&self, name: &str, option: impl FileOption + 'static) -> &Self {
+        global_async().await.add_file_sink(name, option).await;
+        self
+    }
+
+    pub async fn set_rotation_group(&self, handler_ids: &[&str]) -> Result<&Self, Error> {
+        global_async().await.set_rotation_group(handler_ids).await?;
+        Ok(self)
+    }
+
+    pub async fn set_rotation_group_with(&self, handler_ids: &[&str], empty_backups: bool) -> Result<&Self, Error> {
+        global_async().await.set_rotation_group_with(handler_ids, empty_backups).await?;
+        Ok(self)
+    }
+
+    pub async fn clear_rotation_groups(&self) -> &Self {
+        global_async().await.clear_rotation_groups().await;
+        self
+    }
+
+    pub async fn add_tee_file(&self, mode: OutputMode, cut: impl Into<CutConfig>) -> Result<&Self, Error> {
+        global_async().await.add_tee_file(mode, cut).await?;
+        Ok(self)
+    }
+
+    pub fn clear_tee_files(&self) -> &Self {
+        global_async_blocking().clear_tee_files();
+        self
+    }
+
+    pub async fn add_level_file(&self, levels: impl LevelSet, cut: impl Into<CutConfig>) -> &Self {
+        global_async().await.add_level_file(levels, cut).await;
+        self
+    }
+
+    pub fn clear_level_files(&self) -> &Self {
+        global_async_blocking().clear_level_files();
+        self
+    }
+
+    pub fn set_custom_sink(&self, sink: Box<dyn LogSink>) -> &Self {
+        global_async_blocking().set_custom_sink(sink);
+        self
+    }
+
+    pub fn set_custom_sink_only(&self, on: bool) -> &Self {
+        global_async_blocking().set_custom_sink_only(on);
+        self
+    }
+
+    pub fn clear_custom_sink(&self) -> &Self {
+        global_async_blocking().clear_custom_sink();
+        self
+    }
+
+    pub fn set_handler_quota(&self, handler_id: &str, bytes_per_day: u64) -> &Self {
+        global_async_blocking().set_handler_quota(handler_id, bytes_per_day);
+        self
+    }
+
+    pub fn set_storm_control(&self, config: StormConfig) -> &Self {
+        global_async_blocking().set_storm_control(config);
+        self
+    }
+
+    pub fn trace_callers(&self, message: &str, sample_rate: u64, duration: Duration) -> &Self {
+        global_async_blocking().trace_callers(message, sample_rate, duration);
+        self
+    }
+
+    pub fn stop_trace_callers(&self) -> &Self {
+        global_async_blocking().stop_trace_callers();
+        self
+    }
+
+    pub fn set_dynamic_fields(&self, f: DynamicFields) -> &Self {
+        global_async_blocking().set_dynamic_fields(f);
+        self
+    }
+
+    pub fn clear_dynamic_fields(&self) -> &Self {
+        global_async_blocking().clear_dynamic_fields();
+        self
+    }
+
+    pub fn set_context_fields(&self, on: bool) -> &Self {
+        global_async_blocking().set_context_fields(on);
+        self
+    }
+
+    pub fn set_max_fields(&self, n: usize) -> &Self {
+        global_async_blocking().set_max_fields(n);
+        self
+    }
+
+    pub fn set_max_field_value_len(&self, len: usize) -> &Self {
+        global_async_blocking().set_max_field_value_len(len);
+        self
+    }
+
+    pub fn set_record_formatter(&self, f: RecordFormatter) -> &Self {
+        global_async_blocking().set_record_formatter(f);
+        self
+    }
+
+    pub fn set_format_json(&self, on: bool) -> &Self {
+        global_async_blocking().set_format_json(on);
+        self
+    }
+
+    pub fn clear_record_formatter(&self) -> &Self {
+        global_async_blocking().clear_record_formatter();
+        self
+    }
 
-        let s = log_fmt(
-            self.attrfmt.levelfmt.as_ref(),
-            self.attrfmt.timefmt.as_ref(),
-            fmat,
-            formatter,
-            level,
-            filename,
-            line,
-            message.as_str(),
-        );
-        let console_s = if let Some(f) = &self.attrfmt.consolebodyfmt {
-            Some(f(level, s.clone()))
-        } else {
-            None
-        };
+    pub fn set_console_formatter_impl(&self, f: impl LogFormatter + 'static) -> &Self {
+        global_async_blocking().set_console_formatter_impl(f);
+        self
+    }
 
-        let content = LogContent::new(
-            if let Some(f) = &self.attrfmt.filebodyfmt {
-                f(level, s)
-            } else {
-                s
-            },
-            console_s,
-        );
+    pub fn clear_console_formatter(&self) -> &Self {
+        global_async_blocking().clear_console_formatter();
+        self
+    }
 
-        content
+    pub fn set_time_subseq(&self, on: bool) -> &Self {
+        global_async_blocking().set_time_subseq(on);
+        self
     }
 
-    pub fn set_printmode(&mut self, mode: PRINTMODE) -> &mut Self {
-        self.mode = mode;
+    pub fn set_include_boot_id(&self, on: bool) -> &Self {
+        global_async_blocking().set_include_boot_id(on);
         self
     }
 
-    pub fn set_level(&mut self, level: LEVEL) -> &mut Self {
-        self.fmthandle.set_level(level);
+    pub fn set_function_with_module(&self, on: bool) -> &Self {
+        global_async_blocking().set_function_with_module(on);
         self
     }
 
-    pub fn set_console(&mut self, console: bool) -> &mut Self {
-        self.fmthandle.set_console(console);
+    pub fn set_error_backtraces(&self, on: bool) -> &Self {
+        global_async_blocking().set_error_backtraces(on);
         self
     }
 
-    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
-    pub fn set_format(&mut self, format: u8) -> &mut Self {
-        self.fmthandle.set_format(format);
+    pub fn set_location_strategy(&self, strategy: LocationStrategy) -> &Self {
+        global_async_blocking().set_location_strategy(strategy);
         self
     }
 
-    /** default: "{level}{time} {file}:{message}\n" */
-    pub fn set_formatter(&mut self, formatter: &str) -> &mut Self {
-        self.fmthandle.set_formatter(formatter.to_string());
+    pub fn allow_ansi_in_files(&self, allow: bool) -> &Self {
+        global_async_blocking().allow_ansi_in_files(allow);
         self
     }
 
-    pub async fn set_cutmode_by_size(
-        &mut self,
-        filename: &str,
-        maxsize: u64,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &mut Self {
-        let fsm = FileOptionType::new(
-            crate::CUTMODE::SIZE,
-            MODE::DAY,
-            filename,
-            maxsize,
-            maxbackups,
-            compress,
-        );
-        let fh = FileHandler::new(Box::new(fsm)).await;
-        self.filehandle.0 = filename.to_string();
-        self.filehandle.1.set_async_file_handler(fh.unwrap());
+    pub fn set_level_label(&self, level: LEVEL, label: &str) -> &Self {
+        global_async_blocking().set_level_label(level, label);
         self
     }
 
-    pub async fn set_cutmode_by_time(
-        &mut self,
-        filename: &str,
-        mode: MODE,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &mut Self {
-        let ftm = FileOptionType::new(
-            crate::CUTMODE::TIME,
-            mode,
-            filename,
-            0,
-            maxbackups,
-            compress,
-        );
-        let fh = FileHandler::new(Box::new(ftm)).await;
-        self.filehandle.0 = filename.to_string();
-        self.filehandle.1.set_async_file_handler(fh.unwrap());
+    pub fn clear_level_labels(&self) -> &Self {
+        global_async_blocking().clear_level_labels();
         self
     }
 
-    pub async fn set_option(&mut self, option: LogOption) -> &mut Self {
-        if let Some(v) = option.console {
-            self.fmthandle.set_console(v);
-        }
-        if let Some(v) = option.format {
-            self.fmthandle.set_format(v);
-        }
-        if let Some(v) = option.formatter {
-            self.fmthandle.set_formatter(v);
-        }
-        if let Some(v) = option.level {
-            self.fmthandle.set_level(v);
-        }
-        if let Some(v) = option.fileoption {
-            match FileHandler::new(v).await {
-                Ok(f) => {
-                    self.filehandle.0 = f.get_file_name();
-                    self.filehandle.1.set_async_file_handler(f);
-                }
-                Err(_) => {}
-            }
-        }
+    pub fn set_console_color(&self, on: bool) -> &Self {
+        global_async_blocking().set_console_color(on);
         self
     }
 
-    pub async fn set_mod_option(&mut self, module: &str, option: LogOption) -> &mut Self {
-        let mut filename = "".to_string();
-        if let Some(v) = option.fileoption {
-            match FileHandler::new(v).await {
-                Ok(f) => {
-                    filename = f.get_file_name();
-                    if filename != self.filehandle.0 && !self.fmap.contains_key(&filename) {
-                        let mut fhandler = FHandler::new();
-                        fhandler.set_async_file_handler(f);
-                        self.fmap.insert(filename.clone(), fhandler);
-                    }
-                }
-                Err(_) => {}
-            }
-        }
-        self.modmap.insert(
-            module,
-            (
-                LogOptionConst {
-                    level: option.level,
-                    format: option.format,
-                    formatter: option.formatter,
-                    console: option.console,
-                },
-                filename.clone(),
-            ),
-        );
+    pub fn set_console_color_options(&self, opts: ColorOptions) -> &Self {
+        global_async_blocking().set_console_color_options(opts);
         self
     }
 
-    pub fn set_custom_handler(&mut self, handler: fn(&LogContext) -> bool) {
-        self.custom_handler = Some(handler);
+    pub fn set_console_stream(&self, stream: ConsoleStream) -> &Self {
+        global_async_blocking().set_console_stream(stream);
+        self
     }
 
-    pub async fn set_level_option(&mut self, level: LEVEL, option: &dyn OptionTrait) -> &mut Self {
-        let mut filename = "".to_string();
-        if let Some(v) = option.get_fileoption() {
-            match FileHandler::new(v).await {
-                Ok(f) => {
-                    filename = f.get_file_name();
-                    if filename != self.filehandle.0 && !self.fmap.contains_key(&filename) {
-                        let mut fhandler = FHandler::new();
-                        fhandler.set_async_file_handler(f);
-                        self.fmap.insert(filename.clone(), fhandler);
-                    }
-                }
-                Err(_) => {}
-            }
-        }
-        let lo = LogOption {
-            level: None,
-            format: option.get_format(),
-            formatter: option.get_formatter(),
-            console: option.get_console(),
-            fileoption: option.get_fileoption(),
-        };
+    pub fn check_paths(&self) -> Result<(), Error> {
+        global_async_blocking().check_paths()
+    }
 
-        if self.levels.is_none() {
-            self.levels = Some(std::array::from_fn(|_| None));
-        }
+    pub fn set_format_stage(&self, stage: FormatStage) -> &Self {
+        global_async_blocking().set_format_stage(stage);
+        self
+    }
 
-        if let Some(levels) = &mut self.levels {
-            levels[level as usize - 1] = Some((lo, filename));
-        }
+    pub fn set_json_schema_version(&self, version: u32) -> Result<&Self, Error> {
+        global_async_blocking().set_json_schema_version(version)?;
+        Ok(self)
+    }
 
+    pub fn preset_k8s(&self) -> &Self {
+        global_async_blocking().preset_k8s();
         self
     }
 
-    pub fn set_separator(&mut self, separator: &str) -> &mut Self {
-        self.separator = separator.to_string();
+    pub fn preset_systemd(&self) -> &Self {
+        global_async_blocking().preset_systemd();
         self
     }
 
-    pub fn get_separator(&self) -> String {
-        self.separator.clone()
+    pub fn set_auto_trace_ids(&self, on: bool) -> &Self {
+        global_async_blocking().set_auto_trace_ids(on);
+        self
     }
 
-    pub fn set_attr_format<F>(&mut self, mut f: F)
-    where
-        F: FnMut(&mut AttrFormat) + Send + Sync + 'static,
-    {
-        f(&mut self.attrfmt);
+    pub fn set_remote(&self, config: RemoteConfig) -> &Self {
+        global_async_blocking().set_remote(config);
+        self
     }
-}
 
-pub struct Log;
+    pub fn clear_remote(&self) -> &Self {
+        global_async_blocking().clear_remote();
+        self
+    }
 
-impl Log {
-    pub fn new() -> Self {
-        Log {}
+    pub fn remote_dropped(&self) -> u64 {
+        global_async_blocking().remote_dropped()
     }
-    pub fn set_printmode(&self, mode: PRINTMODE) -> &Self {
-        unsafe {
-            asynclog.set_printmode(mode);
-        }
+
+    #[cfg(feature = "journald")]
+    pub fn set_journald(&self, on: bool) -> &Self {
+        global_async_blocking().set_journald(on);
         self
     }
 
-    pub fn set_level(&self, level: LEVEL) -> &Self {
-        unsafe {
-            asynclog.set_level(level);
-        }
+    #[cfg(feature = "journald")]
+    pub fn set_journald_socket(&self, socket: Option<PathBuf>) -> &Self {
+        global_async_blocking().set_journald_socket(socket);
         self
     }
 
-    pub fn set_console(&self, console: bool) -> &Self {
-        unsafe {
-            asynclog.set_console(console);
-        }
+    #[cfg(feature = "syslog")]
+    pub fn set_syslog(&self, config: SyslogConfig) -> &Self {
+        global_async_blocking().set_syslog(config);
         self
     }
 
-    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
-    pub fn set_format(&self, format: u8) -> &Self {
-        unsafe {
-            asynclog.set_format(format);
-        }
+    #[cfg(feature = "syslog")]
+    pub fn clear_syslog(&self) -> &Self {
+        global_async_blocking().clear_syslog();
         self
     }
 
-    /** default: "{level}{time} {file}:{message}\n" */
-    pub fn set_formatter(&self, formatter: &str) -> &Self {
-        unsafe {
-            asynclog.set_formatter(formatter);
-        }
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) -> &Self {
+        global_async_blocking().set_clock(clock);
         self
     }
 
-    pub async fn set_cutmode_by_size(
-        &self,
-        filename: &str,
-        maxsize: u64,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &Self {
-        unsafe {
-            asynclog
-                .set_cutmode_by_size(filename, maxsize, maxbackups, compress)
-                .await;
-        }
+    pub fn set_adaptive_budget(&self, bytes_per_day: u64) -> &Self {
+        global_async_blocking().set_adaptive_budget(bytes_per_day);
         self
     }
 
-    pub async fn set_cutmode_by_time(
-        &self,
-        filename: &str,
-        mode: MODE,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &Self {
-        unsafe {
-            asynclog
-                .set_cutmode_by_time(filename, mode, maxbackups, compress)
-                .await;
-        }
+    pub fn module_levels(&self) -> Vec<(String, LEVEL)> {
+        global_async_blocking().module_levels()
+    }
+
+    pub fn set_mod_separator(&self, pattern: &str, separator: &str) -> &Self {
+        global_async_blocking().set_mod_separator(pattern, separator);
         self
     }
 
-    pub fn set_custom_handler(&self, handler: fn(&LogContext) -> bool) -> &Self {
-        unsafe { asynclog.set_custom_handler(handler) }
+    pub fn set_mod_level(&self, pattern: &str, level: LEVEL) -> &Self {
+        let mut log = global_async_blocking();
+        log.set_mod_level(pattern, level);
+        bridge::refresh(LogBridge::Async, log.lowest_level());
         self
     }
 
-    fn is_file_line(&self, level: LEVEL, module: &str) -> bool {
-        unsafe {
-            return asynclog.is_file_line(level, module);
-        }
+    pub fn clear_module_level(&self, pattern: &str) -> bool {
+        let mut log = global_async_blocking();
+        let cleared = log.clear_module_level(pattern);
+        bridge::refresh(LogBridge::Async, log.lowest_level());
+        cleared
+    }
+
+    pub fn set_level_from_env(&self, var: &str) -> &Self {
+        let mut log = global_async_blocking();
+        log.set_level_from_env(var);
+        bridge::refresh(LogBridge::Async, log.lowest_level());
+        self
+    }
+
+    pub fn set_filter(&self, filter: Filter) -> &Self {
+        global_async_blocking().set_filter(filter);
+        self
+    }
+
+    pub fn clear_filters(&self) -> &Self {
+        global_async_blocking().clear_filters();
+        self
+    }
+
+    pub fn add_hook(&self, hook: Hook) -> &Self {
+        global_async_blocking().add_hook(hook);
+        self
+    }
+
+    pub fn remove_hooks(&self) -> &Self {
+        global_async_blocking().remove_hooks();
+        self
+    }
+
+    pub fn set_custom_handler(&self, handler: fn(&LogContext) -> bool) -> &Self {
+        global_async_blocking().set_custom_handler(handler);
+        self
     }
 
     pub async fn set_option(&self, option: LogOption) -> &Self {
-        unsafe {
-            asynclog.set_option(option).await;
-        }
+        let mut log = global_async().await;
+        log.set_option(option).await;
+        bridge::refresh(LogBridge::Async, log.lowest_level());
         self
     }
 
     pub async fn set_mod_option(&self, module: &str, option: LogOption) -> &Self {
-        unsafe {
-            asynclog.set_mod_option(module, option).await;
-        }
+        let mut log = global_async().await;
+        log.set_mod_option(module, option).await;
+        bridge::refresh(LogBridge::Async, log.lowest_level());
         self
     }
 
     pub async fn set_level_option(&self, level: LEVEL, option: impl OptionTrait) -> &Self {
-        unsafe {
-            asynclog.set_level_option(level, &option).await;
-        }
+        global_async().await.set_level_option(level, &option).await;
         self
     }
 
     pub fn set_separator(&self, separator: &str) -> &Self {
-        unsafe {
-            asynclog.set_separator(separator);
-        };
+        global_async_blocking().set_separator(separator);
         self
     }
 
+    /// Installs `LogBridge::Async` as the logger of the `log` facade,
+    /// unless it has one already, see `bridge`.
     pub fn uselog(&self) -> &Self {
-        let _ = log::set_logger(&TKLOG2ASYNC_LOG);
-        log::set_max_level(log::LevelFilter::Trace);
+        let _ = bridge::install(LogBridge::Async);
         self
     }
 
@@ -593,59 +3774,106 @@ impl Log {
     where
         F: FnMut(&mut AttrFormat) + Send + Sync + 'static,
     {
-        unsafe {
-            asynclog.set_attr_format(f);
-        }
+        global_async_blocking().set_attr_format(f);
+    }
+
+    pub fn set_time_format(&self, format: &str) -> Result<&Self, Error> {
+        global_async_blocking().set_time_format(format)?;
+        Ok(self)
+    }
+
+    pub fn set_time_rfc3339(&self) -> &Self {
+        global_async_blocking().set_time_rfc3339();
+        self
+    }
+
+    pub fn set_column_order(&self, columns: &[COLUMN]) -> Result<&Self, Error> {
+        global_async_blocking().set_column_order(columns)?;
+        Ok(self)
+    }
+
+    pub fn clear_column_order(&self) -> &Self {
+        global_async_blocking().clear_column_order();
+        self
+    }
+
+    pub async fn load_config_file(&self, path: impl AsRef<Path>) -> Result<&Self, Error> {
+        let mut log = global_async().await;
+        log.load_config_file(path).await?;
+        bridge::refresh(LogBridge::Async, log.lowest_level());
+        Ok(self)
+    }
+
+    /// `LOG.watch_config_file`, checking the file on a task of the current
+    /// runtime.
+    pub async fn watch_config_file(&self, path: impl AsRef<Path>, interval: Duration) -> Result<ConfigWatch, Error> {
+        let path = path.as_ref().to_path_buf();
+        let last = config::stamp(&path);
+        self.load_config_file(&path).await?;
+        Ok(config::watch_task(path, last, interval, |path| async move {
+            if let Err(e) = crate::ASYNC_LOG.load_config_file(&path).await {
+                config::refused(&path, e);
+            }
+        }))
+    }
+
+    pub fn set_test_mode(&self, mode: TestMode) -> Result<&Self, Error> {
+        global_async_blocking().set_test_mode(mode)?;
+        Ok(self)
     }
 }
 
 impl log::Log for Log {
-    fn enabled(&self, _: &log::Metadata) -> bool {
-        return true;
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        LogBridge::Async.enabled(metadata)
     }
     fn log(&self, record: &log::Record) {
-        let level = l2tk(record.level());
-        let mut module = "";
-        if let Some(m) = record.module_path() {
-            module = m;
-            unsafe {
-                if asynclog.get_level(module) > level {
-                    return;
-                }
-            }
-        }
-
-        let args = record.args();
-        let mut file = "";
-        let mut line: u32 = 0;
-        if self.is_file_line(level, module) {
-            line = record.line().unwrap_or(0);
-            file = record.file().unwrap_or("");
-        }
-        unsafe {
-            let s = asynclog.fmt(module, level, file, line, arguments_to_string(args));
-            if !s.is_empty() {
-                asynclog.log(level, module.to_string(), s);
-            }
-        }
+        LogBridge::Async.log(record)
     }
     fn flush(&self) {}
 }
 
+/// `log!` on `ASYNC_LOG`.
 #[macro_export]
 macro_rules! async_log {
-    ($level:expr,$module:expr,$msg:expr) => {
-        let msg: LogContent = $msg;
-        let module: &str = $module;
-        unsafe {
-            crate::tklog::asynclog.print($level, module, msg).await;
-        }
-    };
+    ($level:expr, || $body:expr) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::async_log_common!(level, $crate::LazyMessage(|| $body));
+    }};
+    ($level:expr, $($arg:expr),+ ; $($fields:tt)*) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::async_log_common!(@fields None, level, ($($arg),*), $($fields)*);
+    }};
+    ($level:expr, $($arg:expr),+) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::async_log_common!(level, $($arg),*);
+    }};
+}
+
+/// `logf!` on `ASYNC_LOG`.
+#[macro_export]
+macro_rules! async_logf {
+    ($level:expr, $($arg:tt)+) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::async_log_common!(level, format_args!($($arg)+));
+    }};
 }
 
 #[macro_export]
 macro_rules! async_trace {
     () => {};
+    (|| $body:expr) => {
+        $crate::async_log_common!($crate::LEVEL::Trace, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields Some($event), $crate::LEVEL::Trace, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@event Some($event), $crate::LEVEL::Trace, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields None, $crate::LEVEL::Trace, ($($arg),*), $($fields)*);
+    };
     ($($arg:expr),*) => {
         $crate::async_log_common!($crate::LEVEL::Trace, $($arg),*);
     };
@@ -654,6 +3882,18 @@ macro_rules! async_trace {
 #[macro_export]
 macro_rules! async_debug {
     () => {};
+    (|| $body:expr) => {
+        $crate::async_log_common!($crate::LEVEL::Debug, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields Some($event), $crate::LEVEL::Debug, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@event Some($event), $crate::LEVEL::Debug, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields None, $crate::LEVEL::Debug, ($($arg),*), $($fields)*);
+    };
     ($($arg:expr),*) => {
         $crate::async_log_common!($crate::LEVEL::Debug, $($arg),*);
     };
@@ -662,6 +3902,18 @@ macro_rules! async_debug {
 #[macro_export]
 macro_rules! async_info {
     () => {};
+    (|| $body:expr) => {
+        $crate::async_log_common!($crate::LEVEL::Info, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields Some($event), $crate::LEVEL::Info, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@event Some($event), $crate::LEVEL::Info, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields None, $crate::LEVEL::Info, ($($arg),*), $($fields)*);
+    };
     ($($arg:expr),*) => {
         $crate::async_log_common!($crate::LEVEL::Info, $($arg),*);
     };
@@ -670,6 +3922,18 @@ macro_rules! async_info {
 #[macro_export]
 macro_rules! async_warn {
     () => {};
+    (|| $body:expr) => {
+        $crate::async_log_common!($crate::LEVEL::Warn, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields Some($event), $crate::LEVEL::Warn, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@event Some($event), $crate::LEVEL::Warn, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields None, $crate::LEVEL::Warn, ($($arg),*), $($fields)*);
+    };
     ($($arg:expr),*) => {
         $crate::async_log_common!($crate::LEVEL::Warn, $($arg),*);
     };
@@ -678,6 +3942,18 @@ macro_rules! async_warn {
 #[macro_export]
 macro_rules! async_error {
     () => {};
+    (|| $body:expr) => {
+        $crate::async_log_common!($crate::LEVEL::Error, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields Some($event), $crate::LEVEL::Error, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@event Some($event), $crate::LEVEL::Error, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields None, $crate::LEVEL::Error, ($($arg),*), $($fields)*);
+    };
     ($($arg:expr),*) => {
         $crate::async_log_common!($crate::LEVEL::Error, $($arg),*);
     };
@@ -686,6 +3962,18 @@ macro_rules! async_error {
 #[macro_export]
 macro_rules! async_fatal {
     () => {};
+    (|| $body:expr) => {
+        $crate::async_log_common!($crate::LEVEL::Fatal, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields Some($event), $crate::LEVEL::Fatal, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@event Some($event), $crate::LEVEL::Fatal, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_log_common!(@fields None, $crate::LEVEL::Fatal, ($($arg),*), $($fields)*);
+    };
     ($($arg:expr),*) => {
         $crate::async_log_common!($crate::LEVEL::Fatal, $($arg),*);
     };
@@ -693,31 +3981,63 @@ macro_rules! async_fatal {
 
 #[macro_export]
 macro_rules! async_log_common {
-    ($level:expr, $($arg:expr),*) => {
-        unsafe {
+    (@event $event:expr, $level:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@fields $event, $level, ($($arg),*),)
+    };
+    (@fields $event:expr, $level:expr, ($($arg:expr),*), $($fields:tt)*) => {
+        if $crate::compiled($level) {
             let module = module_path!();
-            if $crate::tklog::asynclog.get_level(module) <= $level {
-                let formatted_args: Vec<String> = vec![$(format!("{}", $arg)),*];
-                let mut file = "";
-                let mut line = 0;
-                if $crate::tklog::asynclog.is_file_line($level,module) {
-                    file = file!();
-                    line = line!();
-                }
-                let msg: String = formatted_args.join($crate::tklog::asynclog.get_separator().as_str());
-                if  $crate::tklog::asynclog.mode==$crate::PRINTMODE::DELAY {
-                    let s = $crate::tklog::asynclog.fmt(module,$level, file, line, msg);
-                    if !s.is_empty(){
-                        $crate::tklog::asynclog.log($level,module.to_string(),s);
-                    }
-                }else {
-                    let s = $crate::tklog::asynclog.fmt(module,$level, file, line, msg);
-                    if !s.is_empty(){
-                        $crate::tklog::asynclog.safeprint($level,module,s).await;
+            if !$crate::reentrant($level, module, || $crate::message_of("", &[$(&$arg),*])) {
+                let file_line = {
+                    let logger = $crate::global_async().await;
+                    if logger.get_level(module) <= $level { Some(logger.is_file_line($level, module)) } else { None }
+                };
+                if let Some(file_line) = file_line {
+                    let logger = $crate::global_async().await;
+                    let (file, line) = if file_line { $crate::CallSite::here(file!(), line!()).get(logger.location_strategy()) } else { ("", 0) };
+                    let msg = logger.message_of(module, &[$(&$arg),*]);
+                    let fields = $crate::fields_of!($($fields)*);
+                    if logger.mode==$crate::PRINTMODE::DELAY {
+                        $crate::in_function(Some($crate::function_name!()), || logger.enqueue_static_fields($level, module, file, line, $event, msg, fields));
+                    } else {
+                        let s = $crate::in_function(Some($crate::function_name!()), || logger.fmt_with_fields(module,$level, file, line, $event, msg, fields));
+                        if !s.is_empty(){
+                            logger.safeprint($level,module,s).await;
+                        }
                     }
                 }
             }
         }
     };
+    ($level:expr, $($arg:expr),*) => {
+        $crate::async_log_common!(@event None, $level, $($arg),*)
+    };
     () => {};
 }
+
+/// `warn_if_persists!` on `ASYNC_LOG`.
+#[macro_export]
+macro_rules! async_warn_if_persists {
+    ($after:expr, key: $key:expr, $($arg:tt)+) => {
+        {
+            let module = module_path!();
+            if $crate::compiled($crate::LEVEL::Warn) && !$crate::reentrant($crate::LEVEL::Warn, module, || format!($($arg)+)) {
+                let logger = $crate::global_async().await;
+                if logger.get_level(module) <= $crate::LEVEL::Warn {
+                    let (file, line) = if logger.is_file_line($crate::LEVEL::Warn, module) { $crate::CallSite::here(file!(), line!()).get(logger.location_strategy()) } else { ("", 0) };
+                    logger.log_if_persists($key, $after, $crate::LEVEL::Warn, module, file, line, || format!($($arg)+));
+                    drop(logger);
+                    $crate::persist::wake_async();
+                }
+            }
+        }
+    };
+}
+
+/// `clear!` on `ASYNC_LOG`.
+#[macro_export]
+macro_rules! async_clear {
+    ($key:expr) => {
+        !$crate::reentrant($crate::LEVEL::Off, module_path!(), String::new) && $crate::global_async().await.clear_persisting($key)
+    };
+}
@@ -14,9 +14,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::Async::Logger;
+use crate::{fields::FieldMap, inside_tklog, reentrant, CallSite, LEVEL, PRINTMODE};
+
 // Trace log macros, call secondary macro processing logic
 #[macro_export]
 macro_rules! async_traces {
+    ($logger:expr, || $body:expr) => {
+        $crate::async_logs_common!($logger, $crate::LEVEL::Trace, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_logs_common!(@fields $logger, $crate::LEVEL::Trace, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::async_logs_common!($logger, $crate::LEVEL::Trace, $($arg),*);
     };
@@ -26,6 +38,12 @@ macro_rules! async_traces {
 //Debug log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! async_debugs {
+    ($logger:expr, || $body:expr) => {
+        $crate::async_logs_common!($logger, $crate::LEVEL::Debug, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_logs_common!(@fields $logger, $crate::LEVEL::Debug, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::async_logs_common!($logger, $crate::LEVEL::Debug, $($arg),*);
     };
@@ -35,6 +53,12 @@ macro_rules! async_debugs {
 //Info log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! async_infos {
+    ($logger:expr, || $body:expr) => {
+        $crate::async_logs_common!($logger, $crate::LEVEL::Info, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_logs_common!(@fields $logger, $crate::LEVEL::Info, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::async_logs_common!($logger, $crate::LEVEL::Info, $($arg),*);
     };
@@ -44,6 +68,12 @@ macro_rules! async_infos {
 // warn log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! async_warns {
+    ($logger:expr, || $body:expr) => {
+        $crate::async_logs_common!($logger, $crate::LEVEL::Warn, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_logs_common!(@fields $logger, $crate::LEVEL::Warn, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::async_logs_common!($logger, $crate::LEVEL::Warn, $($arg),*);
     };
@@ -53,6 +83,12 @@ macro_rules! async_warns {
 // Error log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! async_errors {
+    ($logger:expr, || $body:expr) => {
+        $crate::async_logs_common!($logger, $crate::LEVEL::Error, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_logs_common!(@fields $logger, $crate::LEVEL::Error, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::async_logs_common!($logger, $crate::LEVEL::Error, $($arg),*);
     };
@@ -62,6 +98,12 @@ macro_rules! async_errors {
 // Fatal log macros, call secondary macro processing logic
 #[macro_export]
 macro_rules! async_fatals {
+    ($logger:expr, || $body:expr) => {
+        $crate::async_logs_common!($logger, $crate::LEVEL::Fatal, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::async_logs_common!(@fields $logger, $crate::LEVEL::Fatal, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::async_logs_common!($logger, $crate::LEVEL::Fatal, $($arg),*);
     };
@@ -71,22 +113,10 @@ macro_rules! async_fatals {
 #[macro_export]
 macro_rules! async_formats {
     ($logger:expr, $level:expr, $($arg:expr),*) => {
-        unsafe {
-            let logger_lock:&mut Arc<tokio::sync::Mutex<tklog::Async::Logger>> = $logger;
-            let mut logger = logger_lock.as_ref().lock().await;
-            let level:$crate::LEVEL = $level;
-            let module = module_path!();
-            if logger.get_level(module) <= level {
-                let mut file = "";
-                let mut line = 0;
-                if logger.is_file_line($level,module) {
-                    file = file!();
-                    line = line!();
-                }
-                let ss = logger.fmt(module,$level, file, line, format!($($arg),*));
-                if !ss.is_empty(){
-                    logger.print($level,module,ss).await;
-                }
+        {
+            let level: $crate::LEVEL = $level;
+            if $crate::compiled(level) {
+                $crate::asyncmulti::AsyncLogTarget::write_line(&*$logger, level, module_path!(), $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!()), |_| (format!($($arg),*), $crate::fields::FieldMap::new())).await;
             }
         }
     };
@@ -96,25 +126,73 @@ macro_rules! async_formats {
 #[macro_export]
 macro_rules! async_logs_common {
     ($logger:expr, $level:expr, $($arg:expr),*) => {
-        unsafe {
-            let logger_lock:&mut Arc<tokio::sync::Mutex<tklog::Async::Logger>> = $logger;
-            let mut logger = logger_lock.as_ref().lock().await;
-            let module = module_path!();
-            if logger.get_level(module) <= $level {
-                let formatted_args: Vec<String> = vec![$(format!("{}", $arg)),*];
-                let mut file = "";
-                let mut line = 0;
-                if logger.is_file_line($level,module) {
-                    file = file!();
-                    line = line!();
-                }
-                let msg: String = formatted_args.join(logger.get_separator().as_str());
-                let ss = logger.fmt(module,$level, file, line, msg);
-                if !ss.is_empty(){
-                    logger.print($level,module,ss).await;
-                }
-            }
+        $crate::async_logs_common!(@fields $logger, $level, ($($arg),*),)
+    };
+    (@fields $logger:expr, $level:expr, ($($arg:expr),*), $($fields:tt)*) => {
+        if $crate::compiled($level) {
+            $crate::asyncmulti::AsyncLogTarget::write_line(&*$logger, $level, module_path!(), $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!()), |separator| {
+                ($crate::message_of(separator, &[$(&$arg),*]), $crate::fields_of!($($fields)*))
+            })
+            .await;
         }
     };
     () => {};
 }
+
+/// What the `async_*s!` macros log through: an `Async::Logger`, shared with
+/// `&Logger` or `Arc<Logger>` as is, or an `Arc<tokio::sync::Mutex<Logger>>`.
+#[doc(hidden)]
+pub trait AsyncLogTarget {
+    /// Logs one line at `location`; `message` gets the separator, gives the
+    /// message with its fields, and is only called when `module` is enabled
+    /// at `level`.
+    fn write_line<F: FnOnce(&str) -> (String, FieldMap)>(&self, level: LEVEL, module: &'static str, location: CallSite, message: F) -> impl Future<Output = ()>;
+}
+
+impl AsyncLogTarget for Logger {
+    async fn write_line<F: FnOnce(&str) -> (String, FieldMap)>(&self, level: LEVEL, module: &'static str, location: CallSite, message: F) {
+        if inside_tklog() {
+            reentrant(level, module, || message("").0);
+            return;
+        }
+        if self.get_level(module) > level {
+            return;
+        }
+        let (file, line) = location.get(self.location_strategy());
+        let (msg, fields) = crate::with_error_backtraces(self.error_backtraces(), || message(self.separator(module)));
+        if self.mode == PRINTMODE::DELAY {
+            crate::in_function(location.function, || self.enqueue_static_fields(level, module, file, line, None, msg, fields));
+        } else {
+            let (file, line) = if self.is_file_line(level, module) { (file, line) } else { ("", 0) };
+            let s = crate::in_function(location.function, || self.fmt_with_fields(module, level, file, line, None, msg, fields));
+            if !s.is_empty() {
+                self.safeprint(level, module, s).await;
+            }
+        }
+    }
+}
+
+impl AsyncLogTarget for tokio::sync::Mutex<Logger> {
+    async fn write_line<F: FnOnce(&str) -> (String, FieldMap)>(&self, level: LEVEL, module: &'static str, location: CallSite, message: F) {
+        if inside_tklog() {
+            reentrant(level, module, || message("").0);
+            return;
+        }
+        let logger = self.lock().await;
+        if logger.get_level(module) > level {
+            return;
+        }
+        let (file, line) = if logger.is_file_line(level, module) { location.get(logger.location_strategy()) } else { ("", 0) };
+        let (msg, fields) = crate::with_error_backtraces(logger.error_backtraces(), || message(logger.separator(module)));
+        let s = crate::in_function(location.function, || logger.fmt_with_fields(module, level, file, line, None, msg, fields));
+        if !s.is_empty() {
+            logger.print(level, module, s).await;
+        }
+    }
+}
+
+impl<T: AsyncLogTarget> AsyncLogTarget for Arc<T> {
+    fn write_line<F: FnOnce(&str) -> (String, FieldMap)>(&self, level: LEVEL, module: &'static str, location: CallSite, message: F) -> impl Future<Output = ()> {
+        T::write_line(self, level, module, location, message)
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Backup names from a template, see `Logger::set_backup_name_template`.
+//!
+//! A template is the name of a backup with these tokens:
+//! - `{stem}`: the name of the log file up to its last dot, `app` for `app.log`;
+//! - `{ext}`: the name after that dot, `log`;
+//! - `{time:FORMAT}`: the start of the period cut, or the time of the cut
+//!   for size cuts, in the chrono strftime `FORMAT`;
+//! - `{index}`: 1 for the first backup of its time, then 2 and so on;
+//!   `{index:N}` pads it with zeros to `N` digits. Every template has one.
+//!
+//! Compressed backups get `.gz` after the name. Retention reads the names
+//! back with the same template, so that `maxbackups` counts the backups of
+//! this template only, oldest by their time first.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::sync::Logger;
+//! use tklog::MODE;
+//!
+//! let mut log = Logger::new();
+//! log.set_cutmode_by_time("app.log", MODE::DAY, 30, true);
+//! // app-2024-05-01_001.log.gz
+//! log.set_backup_name_template("{stem}-{time:%Y-%m-%d}_{index:3}.{ext}").unwrap();
+//! ```
+
+use std::path::Path;
+
+use chrono::{
+    format::{parse, Item, Parsed, StrftimeItems},
+    DateTime, NaiveDateTime,
+};
+use regex::Regex;
+
+use crate::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Stem,
+    Ext,
+    Time(String),
+    Index(usize),
+}
+
+/// A checked backup name template.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackupTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl BackupTemplate {
+    /// Errs with `Error::InvalidBackupTemplate` for an unknown token, a
+    /// time format chrono can't read, a `/` or no `{index}`.
+    pub fn parse(template: &str) -> Result<Self, Error> {
+        let invalid = |reason: String| Error::InvalidBackupTemplate { template: template.to_string(), reason };
+        if template.contains(['/', '\\']) {
+            return Err(invalid("a backup name has no directory".to_string()));
+        }
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..].find('}').ok_or_else(|| invalid("unclosed `{`".to_string()))? + open;
+            let token = &rest[open + 1..close];
+            parts.push(match token.split_once(':') {
+                None if token == "stem" => Part::Stem,
+                None if token == "ext" => Part::Ext,
+                None if token == "index" => Part::Index(0),
+                Some(("index", width)) => Part::Index(width.parse().map_err(|_| invalid(format!("`{{{}}}` needs a width", token)))?),
+                Some(("time", format)) => {
+                    if format.is_empty() || StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                        return Err(invalid(format!("`{}` is not a strftime format", format)));
+                    }
+                    Part::Time(format.to_string())
+                }
+                _ => return Err(invalid(format!("unknown token `{{{}}}`", token))),
+            });
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        if !parts.iter().any(|p| matches!(p, Part::Index(_))) {
+            return Err(invalid("`{index}` is missing".to_string()));
+        }
+        Ok(BackupTemplate { source: template.to_string(), parts })
+    }
+
+    /// Errs with `Error::InvalidBackupTemplate` if a backup of the log file
+    /// `filename` could have its name, which retention would then delete.
+    pub(crate) fn check_file(&self, filename: &str) -> Result<(), Error> {
+        let path = Path::new(filename);
+        let (Some(name), Some(stem)) = (path.file_name().and_then(|n| n.to_str()), path.file_stem()) else {
+            return Ok(());
+        };
+        let ext = path.extension().map_or(String::new(), |e| e.to_string_lossy().to_string());
+        match self.pattern(&stem.to_string_lossy(), &ext).is_match(name) {
+            true => Err(Error::InvalidBackupTemplate { template: self.source.clone(), reason: format!("backups could be named like the log file `{}`", name) }),
+            false => Ok(()),
+        }
+    }
+
+    /// The name of backup `index` of `stem.ext` cut at `secs`, seconds of
+    /// local wall-clock time.
+    pub(crate) fn name(&self, stem: &str, ext: &str, secs: u64, index: u64) -> String {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => name.push_str(s),
+                Part::Stem => name.push_str(stem),
+                Part::Ext => name.push_str(ext),
+                Part::Time(format) => name.push_str(&wall_clock(secs).format(format).to_string()),
+                Part::Index(width) => name.push_str(&format!("{:0width$}", index, width = *width)),
+            }
+        }
+        name
+    }
+
+    /// The times of the name at `secs`, which the collision index counts
+    /// within; `None` without `{time}`.
+    pub(crate) fn time_text(&self, secs: u64) -> Option<String> {
+        let times: Vec<String> = self.parts.iter().filter_map(|p| if let Part::Time(f) = p { Some(wall_clock(secs).format(f).to_string()) } else { None }).collect();
+        (!times.is_empty()).then(|| times.join("\u{0}"))
+    }
+
+    /// Matches the backups of `stem.ext`, with the times of the name in
+    /// the groups `t0`, `t1`… and the index in `index`.
+    fn pattern(&self, stem: &str, ext: &str) -> Regex {
+        let mut p = String::from("^");
+        let (mut times, mut index) = (0, false);
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => p.push_str(&regex::escape(s)),
+                Part::Stem => p.push_str(&regex::escape(stem)),
+                Part::Ext => p.push_str(&regex::escape(ext)),
+                Part::Time(format) => {
+                    p.push_str(&format!("(?P<t{}>", times));
+                    for item in StrftimeItems::new(format) {
+                        match item {
+                            Item::Literal(s) | Item::Space(s) => p.push_str(&regex::escape(s)),
+                            Item::OwnedLiteral(s) | Item::OwnedSpace(s) => p.push_str(&regex::escape(&s)),
+                            Item::Numeric(..) => p.push_str(r"\s*[+-]?\d+"),
+                            _ => p.push_str(".+?"),
+                        }
+                    }
+                    p.push(')');
+                    times += 1;
+                }
+                // Only the first index is captured, the others match it.
+                Part::Index(_) if index => p.push_str(r"\d+"),
+                Part::Index(_) => {
+                    p.push_str(r"(?P<index>\d+)");
+                    index = true;
+                }
+            }
+        }
+        p.push_str(r"(\.gz)?$");
+        Regex::new(&p).unwrap()
+    }
+
+    /// The time of a name from the text of its first `{time}`, in seconds
+    /// of local wall-clock time; parts the format leaves out count from
+    /// the start of the year.
+    fn secs(&self, text: &str) -> Option<u64> {
+        let format = self.parts.iter().find_map(|p| if let Part::Time(f) = p { Some(f) } else { None })?;
+        let mut parsed = Parsed::new();
+        parse(&mut parsed, text, StrftimeItems::new(format)).ok()?;
+        // Each setter fails if the name has the field already; it is kept.
+        let _ = parsed.set_month(1);
+        let _ = parsed.set_day(1);
+        let _ = parsed.set_hour(0);
+        let _ = parsed.set_minute(0);
+        let _ = parsed.set_second(0);
+        Some(parsed.to_naive_datetime_with_offset(0).ok()?.and_utc().timestamp().max(0) as u64)
+    }
+}
+
+fn wall_clock(secs: u64) -> NaiveDateTime {
+    DateTime::from_timestamp(secs as i64, 0).unwrap_or_default().naive_utc()
+}
+
+/// The backups of one log file: by default `stem_1.ext` and
+/// `stem_20240501_1.ext`, else the names of a template, either one
+/// optionally followed by `.gz`, or the files of a `CUTMODE::PATTERN`.
+/// The log file itself, compressed live or not, is never one.
+pub(crate) struct Backups {
+    re: Regex,
+    template: Option<BackupTemplate>,
+    own: String,
+}
+
+impl Backups {
+    pub(crate) fn new(stem: &str, ext: &str, template: Option<&BackupTemplate>) -> Self {
+        let own = if ext.is_empty() { stem.to_string() } else { format!("{}.{}", stem, ext) };
+        match template {
+            Some(t) => Backups { re: t.pattern(stem, ext), template: Some(t.clone()), own },
+            None => Backups { re: crate::backup_pattern(stem, ext), template: None, own },
+        }
+    }
+
+    /// The files named after the strftime file name `pattern` other than
+    /// `own`, the file of the current period, see `CUTMODE::PATTERN`.
+    pub(crate) fn dated(pattern: &str, own: &str) -> Self {
+        let template = BackupTemplate { source: pattern.to_string(), parts: vec![Part::Time(pattern.to_string())] };
+        Backups { re: template.pattern("", ""), template: Some(template), own: own.to_string() }
+    }
+
+    pub(crate) fn is_match(&self, name: &str) -> bool {
+        name != self.own && name.strip_suffix(".gz") != Some(&self.own) && self.re.is_match(name)
+    }
+
+    /// Where a backup sorts among its siblings: its period stamp (0 for size
+    /// backups without one), then its counter.
+    pub(crate) fn order(&self, path: &Path) -> Option<(u64, u64)> {
+        let caps = self.re.captures(path.file_name()?.to_str()?)?;
+        match &self.template {
+            // Padded to seconds so the stamps of every `MODE` compare.
+            None => {
+                let stamp = match caps.get(1)?.as_str().split('_').find(|s| !s.is_empty()) {
+                    Some(s) => format!("{:0<14}", s).parse().ok()?,
+                    None => 0,
+                };
+                Some((stamp, caps.get(2)?.as_str().parse().ok()?))
+            }
+            // The files of a pattern have no index.
+            Some(t) => Some((caps.name("t0").and_then(|m| t.secs(m.as_str())).unwrap_or(0), caps.name("index").map_or(Some(0), |m| m.as_str().parse().ok())?)),
+        }
+    }
+
+    /// The period stamp of a backup; `None` for size backups.
+    pub(crate) fn period(&self, path: &Path) -> Option<u64> {
+        let caps = self.re.captures(path.file_name()?.to_str()?)?;
+        match &self.template {
+            None => caps.get(1)?.as_str().split('_').find(|s| !s.is_empty())?.parse().ok(),
+            Some(t) => t.secs(caps.name("t0")?.as_str()),
+        }
+    }
+
+    /// The counter of the backup named `name` if it is one in period
+    /// `stamp`: the `getbackup_with_time` stamp by default, the
+    /// `time_text` of a template.
+    pub(crate) fn counter_in(&self, name: &str, stamp: Option<&str>) -> Option<u64> {
+        if !self.is_match(name) {
+            return None;
+        }
+        let caps = self.re.captures(name)?;
+        match &self.template {
+            None => (caps[1] == stamp.map_or(String::new(), |s| format!("_{}", s))).then(|| caps[2].parse().unwrap_or(0)),
+            Some(_) => {
+                let times: Vec<&str> = (0..).map_while(|i| caps.name(&format!("t{}", i)).map(|m| m.as_str())).collect();
+                let text = (!times.is_empty()).then(|| times.join("\u{0}"));
+                (text.as_deref() == stamp).then(|| caps["index"].parse().unwrap_or(0))
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A compact console layout for interactive tools: the level as a colored
+//! badge of fixed width, a dim time and the message.
+//!
+//! ```text
+//!  INFO  12:00:00 listening on :8080
+//!  WARN  12:00:01 config file missing, using defaults
+//! ```
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::badge::{BadgeOptions, ConsoleBadgeFormatter};
+//! use tklog::sync::Logger;
+//!
+//! let mut log = Logger::new();
+//! log.set_console_formatter_impl(ConsoleBadgeFormatter::new(BadgeOptions { icons: true, ..Default::default() }));
+//! ```
+
+use std::fmt::Write;
+
+use crate::{
+    record::{LogFormatter, RecordSnapshot},
+    LEVEL,
+};
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BadgeOptions {
+    /// Puts `✔`, `⚠` or `✖` ahead of the level name. Default: false.
+    pub icons: bool,
+    /// Shows the time as `HH:MM:SS`. Default: true.
+    pub time: bool,
+}
+
+impl Default for BadgeOptions {
+    fn default() -> Self {
+        BadgeOptions { icons: false, time: true }
+    }
+}
+
+/// The badge layout, for `Logger::set_console_formatter_impl`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConsoleBadgeFormatter {
+    opts: BadgeOptions,
+}
+
+impl ConsoleBadgeFormatter {
+    pub fn new(opts: BadgeOptions) -> Self {
+        ConsoleBadgeFormatter { opts }
+    }
+}
+
+/// The colors, name and icon of the badge of `level`.
+fn badge(level: LEVEL) -> (&'static str, &'static str, char) {
+    match level {
+        LEVEL::Trace => ("\x1b[97;44m", "TRACE", '·'),
+        LEVEL::Debug => ("\x1b[30;46m", "DEBUG", '·'),
+        LEVEL::Info => ("\x1b[30;42m", "INFO", '✔'),
+        LEVEL::Warn => ("\x1b[30;43m", "WARN", '⚠'),
+        LEVEL::Error => ("\x1b[97;41m", "ERROR", '✖'),
+        LEVEL::Fatal | LEVEL::Off => ("\x1b[1;97;41m", "FATAL", '✖'),
+    }
+}
+
+impl LogFormatter for ConsoleBadgeFormatter {
+    fn format(&self, record: &RecordSnapshot) -> String {
+        let (color, name, icon) = badge(record.level);
+        let message = record.fields.append_to(record.message.clone());
+        let message = message.strip_suffix('\n').unwrap_or(&message);
+        let mut out = String::with_capacity(48 + message.len());
+        out.push_str(color);
+        if self.opts.icons {
+            let _ = write!(out, " {}", icon);
+        }
+        let _ = write!(out, " {:<5} {} ", name, RESET);
+        if self.opts.time {
+            let _ = write!(out, "{}{}{} ", DIM, record.time.format("%H:%M:%S"), RESET);
+        }
+        out.push_str(message);
+        out.push('\n');
+        out
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The batches of the `PRINTMODE::DELAY` queue, see
+//! `Logger::set_delay_batch`.
+//!
+//! The queue consumer holds the lines it takes in a batch and writes the
+//! batch once it has `size` lines or its oldest line has waited the max
+//! latency, whichever comes first. Without a size, a batch is whatever is
+//! queued when the consumer gets to it, as before batches could be sized.
+
+use std::{
+    mem,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// How long the oldest line of a batch waits by default.
+pub(crate) const DEFAULT_MAX_LATENCY: Duration = Duration::from_millis(100);
+
+/// Shared between a logger and its queue consumer, which reads it for
+/// every line.
+pub(crate) struct BatchPolicy {
+    size: AtomicUsize,
+    latency: AtomicU64,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        BatchPolicy {
+            size: AtomicUsize::new(0),
+            latency: AtomicU64::new(DEFAULT_MAX_LATENCY.as_nanos() as u64),
+        }
+    }
+}
+
+impl BatchPolicy {
+    pub(crate) fn set_size(&self, size: usize) {
+        self.size.store(size, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_max_latency(&self, latency: Duration) {
+        self.latency.store(latency.as_nanos().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+    }
+
+    fn max_latency(&self) -> Duration {
+        Duration::from_nanos(self.latency.load(Ordering::Relaxed))
+    }
+}
+
+/// What the consumer does next with its batch.
+pub(crate) enum Wait {
+    /// Nothing is held: wait for the next line.
+    Idle,
+    /// Take the lines queued already, without waiting; write the batch
+    /// once there are none.
+    Queued,
+    /// Wait for the next line until then, and write the batch at that time.
+    Until(Instant),
+    /// Write the batch now.
+    Due,
+}
+
+/// The lines a queue consumer holds.
+pub(crate) struct Batch<T> {
+    items: Vec<T>,
+    since: Option<Instant>,
+}
+
+impl<T> Default for Batch<T> {
+    fn default() -> Self {
+        Batch { items: Vec::new(), since: None }
+    }
+}
+
+impl<T> Batch<T> {
+    pub(crate) fn push(&mut self, item: T) {
+        self.since.get_or_insert_with(Instant::now);
+        self.items.push(item);
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<T> {
+        self.since = None;
+        mem::take(&mut self.items)
+    }
+
+    pub(crate) fn wait(&self, policy: &BatchPolicy) -> Wait {
+        let Some(since) = self.since else {
+            return Wait::Idle;
+        };
+        let deadline = since + policy.max_latency();
+        match policy.size.load(Ordering::Relaxed) {
+            _ if Instant::now() >= deadline => Wait::Due,
+            0 => Wait::Queued,
+            size if self.items.len() >= size => Wait::Due,
+            _ => Wait::Until(deadline),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `tklog-check`, to debug a tklog config file; needs the `check` feature.
+//!
+//! ```text
+//! tklog-check <config>
+//! tklog-check simulate [--config <config>] --lines 100000 --size-cut 1MB --dir <dir> [--backups <n>] [--compress]
+//! ```
+//!
+//! The first form loads the config as an application would, validates it,
+//! and prints the `describe` output with a preview of its lines. The second
+//! writes fake lines into `<dir>/app.log` with size rotation and lists the
+//! files it ends with. The output is the same from run to run but for the
+//! timestamps, for bug reports.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use tklog::{parse_size, sync::Logger, Format, LogOption, LEVEL};
+
+const USAGE: &str = "usage:
+  tklog-check <config>
+  tklog-check simulate [--config <config>] --lines <n> --size-cut <size> --dir <dir> [--backups <n>] [--compress]";
+
+/// The levels previewed, each as the file and the console get it, and
+/// cycled through by `simulate`.
+const PREVIEW: [LEVEL; 5] = [LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("simulate") => simulate(&args[1..]),
+        Some(path) if args.len() == 1 && !path.starts_with('-') => check(Path::new(path)),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// A logger set up from the config file at `path`. Its own notice of what
+/// the config changed is left out, it would only repeat the file.
+fn load(path: &Path) -> Result<Logger, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let mut log = Logger::new();
+    log.set_mod_option("tklog", LogOption { level: Some(LEVEL::Off), format: None, formatter: None, console: None, fileoption: None });
+    let mut config = log.config().apply_text(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    // Kept through a `[modules]` of the file, which replaces the levels.
+    if !config.modules.iter().any(|(pattern, _)| pattern == "tklog") {
+        config.modules.push(("tklog".to_string(), LEVEL::Off));
+        config.modules.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+    log.apply_config(config);
+    log.check_paths().map_err(|e| e.to_string())?;
+    Ok(log)
+}
+
+fn check(path: &Path) -> Result<(), String> {
+    let mut log = load(path)?;
+    let modes = log.validate().map_err(|e| format!("invalid: {}", e))?;
+    println!("valid: {}", modes.iter().map(|(sink, mode)| format!("{} {}", sink, mode)).collect::<Vec<_>>().join(", "));
+    print!("{}", log.describe());
+    println!("preview:");
+    for level in PREVIEW {
+        if log.get_level("app::check") > level {
+            println!("  {:?} is below the level", level);
+            continue;
+        }
+        let s = log.fmt("app::check", level, "src/main.rs", 42, format!("{:?} preview", level));
+        print!("  file:    {}", s.file_body);
+        if let Some(console) = &s.console_body {
+            print!("  console: {}", console);
+        }
+    }
+    Ok(())
+}
+
+struct Simulation {
+    config: Option<PathBuf>,
+    lines: u64,
+    size_cut: u64,
+    dir: PathBuf,
+    backups: u32,
+    compress: bool,
+}
+
+impl Simulation {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let (mut config, mut lines, mut size_cut, mut dir, mut backups, mut compress) = (None, None, None, None, 0, false);
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value\n{}", arg, USAGE));
+            match arg.as_str() {
+                "--config" => config = Some(PathBuf::from(value()?)),
+                "--lines" => lines = Some(value()?.parse().map_err(|_| format!("--lines takes a count\n{}", USAGE))?),
+                "--size-cut" => size_cut = Some(parse_size(value()?).map_err(|e| format!("--size-cut takes a size such as 1MB, {}\n{}", e, USAGE))?),
+                "--dir" => dir = Some(PathBuf::from(value()?)),
+                "--backups" => backups = value()?.parse().map_err(|_| format!("--backups takes a count\n{}", USAGE))?,
+                "--compress" => compress = true,
+                _ => return Err(format!("unknown argument {}\n{}", arg, USAGE)),
+            }
+        }
+        let missing = |name: &str| format!("simulate needs {}\n{}", name, USAGE);
+        Ok(Simulation {
+            config,
+            lines: lines.ok_or_else(|| missing("--lines"))?,
+            size_cut: size_cut.ok_or_else(|| missing("--size-cut"))?,
+            dir: dir.ok_or_else(|| missing("--dir"))?,
+            backups,
+            compress,
+        })
+    }
+}
+
+fn simulate(args: &[String]) -> Result<(), String> {
+    let sim = Simulation::parse(args)?;
+    let mut log = match &sim.config {
+        Some(path) => load(path)?,
+        None => {
+            let mut log = Logger::new();
+            log.set_format(Format::LevelFlag | Format::Date | Format::Time);
+            log
+        }
+    };
+    fs::create_dir_all(&sim.dir).map_err(|e| format!("cannot create {}: {}", sim.dir.display(), e))?;
+    let file = sim.dir.join("app.log");
+    log.set_console(false).set_level(LEVEL::Trace).set_cutmode_by_size(&file.to_string_lossy(), sim.size_cut, sim.backups, sim.compress);
+    log.check_paths().map_err(|e| e.to_string())?;
+
+    for i in 0..sim.lines {
+        let level = PREVIEW[(i % PREVIEW.len() as u64) as usize];
+        let s = log.fmt("app::simulate", level, "src/main.rs", 42, format!("simulated request {} took {}ms", i, i * 7 % 1000));
+        log.print(level, "app::simulate", s);
+    }
+    // The backups are compressed and pruned on a thread of their own.
+    drop(tklog::flush_guard());
+
+    let mut files: Vec<(String, u64)> = fs::read_dir(&sim.dir)
+        .map_err(|e| format!("cannot list {}: {}", sim.dir.display(), e))?
+        .filter_map(|e| e.ok())
+        .map(|e| (e.file_name().to_string_lossy().into_owned(), e.metadata().map_or(0, |m| m.len())))
+        .collect();
+    files.sort();
+    println!("{} lines into {}, cut at {} bytes:", sim.lines, sim.dir.display(), sim.size_cut);
+    for (name, size) in files {
+        println!("  {} {}", name, size);
+    }
+    Ok(())
+}
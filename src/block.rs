@@ -0,0 +1,86 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Blocks of lines written as one, with `Logger::log_block`, for a table or
+//! a config dump that other threads must not interleave with.
+//!
+//! Each line of a block is formatted as a line of its own, so parsers read
+//! it as usual, and the block then goes to every file, sink and the console
+//! in one write, after one rotation check: it is never split by a cut. Past
+//! `MAX_BLOCK_BYTES` of messages its other lines are left out, and a last
+//! line tells how many.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{sync::Logger, LEVEL};
+//!
+//! let mut log = Logger::new();
+//! log.log_block(LEVEL::Info, "app", |block| {
+//!     block.line("config:");
+//!     for (key, value) in [("port", "8080"), ("workers", "4")] {
+//!         block.line(format!("  {} = {}", key, value));
+//!     }
+//! });
+//! ```
+
+use crate::LogContent;
+
+/// The most bytes of messages in a block.
+pub const MAX_BLOCK_BYTES: usize = 1 << 20;
+
+/// The lines of a block, see the module docs.
+#[derive(Default)]
+pub struct BlockWriter {
+    lines: Vec<String>,
+    bytes: usize,
+    truncated: usize,
+}
+
+impl BlockWriter {
+    /// Adds a line, left out once the block is full.
+    pub fn line(&mut self, message: impl Into<String>) -> &mut Self {
+        let message = message.into();
+        if self.truncated > 0 || self.bytes + message.len() > MAX_BLOCK_BYTES {
+            self.truncated += 1;
+        } else {
+            self.bytes += message.len();
+            self.lines.push(message);
+        }
+        self
+    }
+
+    /// The messages of the block, with the note on the lines left out.
+    pub(crate) fn into_lines(self) -> Vec<String> {
+        let mut lines = self.lines;
+        if self.truncated > 0 {
+            lines.push(format!("tklog: {} more lines of the block left out past {} bytes", self.truncated, MAX_BLOCK_BYTES));
+        }
+        lines
+    }
+}
+
+/// The formatted lines of a block as one line; `None` when all were
+/// dropped, e.g. by the custom handler.
+pub(crate) fn join(lines: impl IntoIterator<Item = LogContent>) -> Option<LogContent> {
+    let mut block: Option<LogContent> = None;
+    for line in lines.into_iter().filter(|line| !line.is_empty()) {
+        match &mut block {
+            Some(block) => block.append(line),
+            None => block = Some(line),
+        }
+    }
+    block
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The boot ID: eight random base32 characters drawn once per process, to
+//! tell the runs of a quickly restarting service apart in a merged stream.
+//!
+//! With `Logger::set_include_boot_id`, text lines end with
+//! `boot_id=k3v9qa2m`, unless the formatter places `{bootid}` itself, and
+//! JSON lines get a `boot_id` key. The ID stays the same for every logger
+//! of the process and across `force_reconfigure`; `postmortem::previous_boot_id`
+//! finds the one of the run before.
+//!
+//! ### Example
+//! ```no_run
+//! let mut log = tklog::sync::Logger::new();
+//! log.set_include_boot_id(true);
+//! println!("this run is {}", tklog::boot::boot_id());
+//! ```
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use once_cell::sync::Lazy;
+
+const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+static BOOT_ID: Lazy<String> = Lazy::new(|| {
+    // The keys of `RandomState` come from the OS, the time and pid only add
+    // to them.
+    let mut h = RandomState::new().build_hasher();
+    h.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos()));
+    h.write_u32(process::id());
+    let bits = h.finish();
+    (0..8).map(|i| ALPHABET[(bits >> (5 * i) & 31) as usize] as char).collect()
+});
+
+/// The boot ID of this process.
+pub fn boot_id() -> &'static str {
+    &BOOT_ID
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! tklog behind the `log` facade, so the `log::info!` … lines of the
+//! dependencies end up in the global logger.
+//!
+//! The target of a record is its module: the levels of `set_mod_option`
+//! apply to it, and its file and line show as the format has them. The
+//! facade's max level follows the lowest level set, global or per module;
+//! `set_level`, `set_option`, `set_mod_option` and `clear_module_level` on
+//! `LOG` or `ASYNC_LOG`, as well as `init_once` and `force_reconfigure`,
+//! keep it current. `LOG.uselog()` and `ASYNC_LOG.uselog()` install the
+//! bridge too.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{bridge::LogBridge, LEVEL, LOG};
+//!
+//! LOG.set_level(LEVEL::Info);
+//! tklog::install_log_bridge(LogBridge::Sync).unwrap();
+//! log::info!("from the facade");
+//! ```
+
+use std::{
+    borrow::Cow,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::{arguments_to_string, global, global_async_blocking, global_for_line, intern::intern, l2tk, reentrant, LEVEL, PRINTMODE};
+
+/// Which global logger the `log` records go to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogBridge {
+    /// `LOG`, the logger of `trace!` … `fatal!`.
+    Sync,
+    /// `ASYNC_LOG`; its lines are queued, never written in place.
+    Async,
+}
+
+static SYNC_BRIDGE: LogBridge = LogBridge::Sync;
+static ASYNC_BRIDGE: LogBridge = LogBridge::Async;
+
+/// The installed bridge: 0 for none, else `LogBridge as u8 + 1`.
+static INSTALLED: AtomicU8 = AtomicU8::new(0);
+
+pub(crate) fn install(bridge: LogBridge) -> Result<(), log::SetLoggerError> {
+    log::set_logger(match bridge {
+        LogBridge::Sync => &SYNC_BRIDGE,
+        LogBridge::Async => &ASYNC_BRIDGE,
+    })?;
+    INSTALLED.store(bridge as u8 + 1, Ordering::Release);
+    let lowest = match bridge {
+        LogBridge::Sync => global().lowest_level(),
+        LogBridge::Async => global_async_blocking().lowest_level(),
+    };
+    log::set_max_level(level_filter(lowest));
+    Ok(())
+}
+
+/// Sets the facade's max level to `lowest` when `bridge` is installed.
+pub(crate) fn refresh(bridge: LogBridge, lowest: LEVEL) {
+    if INSTALLED.load(Ordering::Acquire) == bridge as u8 + 1 {
+        log::set_max_level(level_filter(lowest));
+    }
+}
+
+fn level_filter(level: LEVEL) -> log::LevelFilter {
+    match level {
+        LEVEL::Trace => log::LevelFilter::Trace,
+        LEVEL::Debug => log::LevelFilter::Debug,
+        LEVEL::Info => log::LevelFilter::Info,
+        LEVEL::Warn => log::LevelFilter::Warn,
+        LEVEL::Error | LEVEL::Fatal => log::LevelFilter::Error,
+        LEVEL::Off => log::LevelFilter::Off,
+    }
+}
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        let level = l2tk(metadata.level());
+        match self {
+            LogBridge::Sync => crate::above_floor(level) && global_for_line().get_level(metadata.target()) <= level,
+            LogBridge::Async => global_async_blocking().get_level(metadata.target()) <= level,
+        }
+    }
+
+    fn log(&self, record: &log::Record) {
+        let level = l2tk(record.level());
+        let module = record.target();
+        if reentrant(level, module, || arguments_to_string(record.args())) {
+            return;
+        }
+        let (file, line) = (record.file().unwrap_or(""), record.line().unwrap_or(0));
+        match self {
+            LogBridge::Sync => {
+                if !crate::above_floor(level) {
+                    return;
+                }
+                let mut logger = global_for_line();
+                if logger.get_level(module) > level {
+                    return;
+                }
+                let (file, line) = if logger.is_file_line(level, module) { (file, line) } else { ("", 0) };
+                let s = logger.fmt(module, level, file, line, arguments_to_string(record.args()));
+                if s.is_empty() {
+                    return;
+                }
+                if logger.mode == PRINTMODE::DELAY {
+                    logger.log(level, record.module_path_static().filter(|m| *m == module).map_or_else(|| intern(module), Cow::Borrowed), s);
+                } else {
+                    logger.safeprint(level, module, s);
+                }
+            }
+            LogBridge::Async => {
+                let logger = global_async_blocking();
+                if logger.get_level(module) > level {
+                    return;
+                }
+                let (file, line) = if logger.is_file_line(level, module) { (file, line) } else { ("", 0) };
+                let s = logger.fmt(module, level, file, line, arguments_to_string(record.args()));
+                if !s.is_empty() {
+                    logger.log(level, module, s);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
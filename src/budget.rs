@@ -0,0 +1,90 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The adaptive minimum level of `Logger::set_adaptive_budget`.
+//!
+//! The bytes laid out today are projected to a full day from the time
+//! of day. Once the projection is over budget at noon or later, or the
+//! budget is used up earlier, every level is raised one step, Info to
+//! Warn and so on, for the rest of the day. The next day starts back at
+//! the levels as set.
+
+use chrono::{DateTime, Local, NaiveDate, Timelike};
+
+use crate::LEVEL;
+
+const DAY_SECS: u64 = 24 * 3600;
+const NOON_SECS: u64 = 12 * 3600;
+
+pub(crate) struct AdaptiveBudget {
+    bytes_per_day: u64,
+    day: NaiveDate,
+    used: u64,
+    raised: bool,
+}
+
+impl AdaptiveBudget {
+    pub(crate) fn new(bytes_per_day: u64, now: DateTime<Local>) -> Self {
+        AdaptiveBudget { bytes_per_day, day: now.date_naive(), used: 0, raised: false }
+    }
+
+    /// Whether levels are raised at `now`, with a notice when a new day
+    /// put them back.
+    pub(crate) fn check(&mut self, now: DateTime<Local>) -> (bool, Option<String>) {
+        if now.date_naive() == self.day {
+            return (self.raised, None);
+        }
+        let notice = self.raised.then(|| format!("adaptive budget: new day, levels back as set after {} bytes on {}", self.used, self.day));
+        *self = AdaptiveBudget::new(self.bytes_per_day, now);
+        (false, notice)
+    }
+
+    /// Counts `bytes` laid out at `now`, with a notice when that raises
+    /// the levels.
+    pub(crate) fn charge(&mut self, now: DateTime<Local>, bytes: usize) -> Option<String> {
+        self.check(now);
+        self.used += bytes as u64;
+        if self.raised {
+            return None;
+        }
+        let elapsed = now.num_seconds_from_midnight() as u64 + 1;
+        let projected = self.used.saturating_mul(DAY_SECS) / elapsed;
+        if self.used <= self.bytes_per_day && (elapsed < NOON_SECS || projected <= self.bytes_per_day) {
+            return None;
+        }
+        self.raised = true;
+        Some(format!(
+            "adaptive budget: {} bytes by {} project to {} bytes per day over the budget of {}; levels raised one step until the next day",
+            self.used,
+            now.format("%H:%M:%S"),
+            projected,
+            self.bytes_per_day
+        ))
+    }
+}
+
+/// `level` one step up; Fatal and Off stay.
+pub(crate) fn raise(level: LEVEL) -> LEVEL {
+    match level {
+        LEVEL::Trace => LEVEL::Debug,
+        LEVEL::Debug => LEVEL::Info,
+        LEVEL::Info => LEVEL::Warn,
+        LEVEL::Warn => LEVEL::Error,
+        LEVEL::Error => LEVEL::Fatal,
+        LEVEL::Fatal => LEVEL::Fatal,
+        LEVEL::Off => LEVEL::Off,
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loggers configured in one expression, with `sync::Logger::builder` and
+//! `Async::Logger::builder`.
+//!
+//! `build` makes a logger of its own; `apply_global` configures the logger
+//! behind `LOG` and the macros, as `tklog::init_once` does, and
+//! `apply_async_global` the one behind `ASYNC_LOG`. Each global is
+//! configured once: applying again fails with `init::AlreadyInitialized`
+//! and leaves the logger other threads already use as it is.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{Format, LEVEL};
+//!
+//! tklog::sync::Logger::builder()
+//!     .level(LEVEL::Debug)
+//!     .format(Format::LevelFlag | Format::Time | Format::ShortFileName)
+//!     .formatter("{level} {time} {message}\n")
+//!     .cutmode_by_size("app.log", 64 << 20, 10, true)
+//!     .console(false)
+//!     .apply_global()
+//!     .unwrap();
+//! ```
+
+use std::{future::Future, panic::Location};
+
+use crate::{
+    bridge::{self, LogBridge},
+    cut::{CutConfig, CutMixed, CutSize, CutTime},
+    global_async,
+    init::{self, AlreadyInitialized, AsyncHandle, Handle},
+    sync, Async, LEVEL, MODE, PRINTMODE,
+};
+
+/// What the builders set, left as the logger has it when not given.
+#[derive(Clone, Debug, Default)]
+struct Settings {
+    level: Option<LEVEL>,
+    printmode: Option<PRINTMODE>,
+    console: Option<bool>,
+    format: Option<u8>,
+    formatter: Option<String>,
+    cut: Option<CutConfig>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LoggerBuilder {
+    settings: Settings,
+}
+
+impl LoggerBuilder {
+    pub fn level(mut self, level: LEVEL) -> Self {
+        self.settings.level = Some(level);
+        self
+    }
+
+    pub fn printmode(mut self, mode: PRINTMODE) -> Self {
+        self.settings.printmode = Some(mode);
+        self
+    }
+
+    pub fn console(mut self, console: bool) -> Self {
+        self.settings.console = Some(console);
+        self
+    }
+
+    /// See `Logger::set_format`.
+    pub fn format(mut self, format: u8) -> Self {
+        self.settings.format = Some(format);
+        self
+    }
+
+    /// See `Logger::set_formatter`.
+    pub fn formatter(mut self, formatter: &str) -> Self {
+        self.settings.formatter = Some(formatter.to_string());
+        self
+    }
+
+    /// See `Logger::set_cut`; the last cut given wins.
+    pub fn cut(mut self, cut: impl Into<CutConfig>) -> Self {
+        self.settings.cut = Some(cut.into());
+        self
+    }
+
+    pub fn cutmode_by_size(self, filename: &str, maxsize: u64, maxbackups: u32, compress: bool) -> Self {
+        self.cut(CutSize::unchecked(filename, maxsize, maxbackups, compress))
+    }
+
+    pub fn cutmode_by_time(self, filename: &str, mode: MODE, maxbackups: u32, compress: bool) -> Self {
+        self.cut(CutTime::unchecked(filename, mode, maxbackups, compress))
+    }
+
+    pub fn cutmode_by_mixed(self, filename: &str, mode: MODE, maxsize: u64, maxbackups: u32, compress: bool) -> Self {
+        self.cut(CutMixed::unchecked(filename, mode, maxsize, maxbackups, compress))
+    }
+
+    pub fn build(self) -> sync::Logger {
+        let mut log = sync::Logger::new();
+        self.configure(&mut log);
+        log
+    }
+
+    /// Configures the global logger, unless that happened before, by this
+    /// or `tklog::init_once`: then the error tells where and what is set up.
+    #[track_caller]
+    pub fn apply_global(self) -> Result<Handle, AlreadyInitialized> {
+        init::init_once(Location::caller(), |log| self.configure(log))
+    }
+
+    fn configure(&self, log: &mut sync::Logger) {
+        let s = &self.settings;
+        if let Some(mode) = s.printmode {
+            log.set_printmode(mode);
+        }
+        if let Some(level) = s.level {
+            log.set_level(level);
+        }
+        if let Some(console) = s.console {
+            log.set_console(console);
+        }
+        if let Some(format) = s.format {
+            log.set_format(format);
+        }
+        if let Some(formatter) = &s.formatter {
+            log.set_formatter(formatter);
+        }
+        if let Some(cut) = s.cut.clone() {
+            log.set_cut(cut);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AsyncLoggerBuilder {
+    settings: Settings,
+}
+
+impl AsyncLoggerBuilder {
+    pub fn level(mut self, level: LEVEL) -> Self {
+        self.settings.level = Some(level);
+        self
+    }
+
+    pub fn printmode(mut self, mode: PRINTMODE) -> Self {
+        self.settings.printmode = Some(mode);
+        self
+    }
+
+    pub fn console(mut self, console: bool) -> Self {
+        self.settings.console = Some(console);
+        self
+    }
+
+    /// See `Logger::set_format`.
+    pub fn format(mut self, format: u8) -> Self {
+        self.settings.format = Some(format);
+        self
+    }
+
+    /// See `Logger::set_formatter`.
+    pub fn formatter(mut self, formatter: &str) -> Self {
+        self.settings.formatter = Some(formatter.to_string());
+        self
+    }
+
+    /// See `Logger::set_cut`; the last cut given wins.
+    pub fn cut(mut self, cut: impl Into<CutConfig>) -> Self {
+        self.settings.cut = Some(cut.into());
+        self
+    }
+
+    pub fn cutmode_by_size(self, filename: &str, maxsize: u64, maxbackups: u32, compress: bool) -> Self {
+        self.cut(CutSize::unchecked(filename, maxsize, maxbackups, compress))
+    }
+
+    pub fn cutmode_by_time(self, filename: &str, mode: MODE, maxbackups: u32, compress: bool) -> Self {
+        self.cut(CutTime::unchecked(filename, mode, maxbackups, compress))
+    }
+
+    pub fn cutmode_by_mixed(self, filename: &str, mode: MODE, maxsize: u64, maxbackups: u32, compress: bool) -> Self {
+        self.cut(CutMixed::unchecked(filename, mode, maxsize, maxbackups, compress))
+    }
+
+    pub async fn build(self) -> Async::Logger {
+        let mut log = Async::Logger::new();
+        self.configure(&mut log).await;
+        log
+    }
+
+    /// Configures the global async logger, unless that happened before:
+    /// then the error tells where and what is set up.
+    #[track_caller]
+    pub fn apply_async_global(self) -> impl Future<Output = Result<AsyncHandle, AlreadyInitialized>> {
+        let location = Location::caller();
+        async move {
+            let handle = init::claim_async(location).await?;
+            let mut log = global_async().await;
+            self.configure(&mut log).await;
+            bridge::refresh(LogBridge::Async, log.lowest_level());
+            Ok(handle)
+        }
+    }
+
+    async fn configure(&self, log: &mut Async::Logger) {
+        let s = &self.settings;
+        if let Some(mode) = s.printmode {
+            log.set_printmode(mode);
+        }
+        if let Some(level) = s.level {
+            log.set_level(level);
+        }
+        if let Some(console) = s.console {
+            log.set_console(console);
+        }
+        if let Some(format) = s.format {
+            log.set_format(format);
+        }
+        if let Some(formatter) = &s.formatter {
+            log.set_formatter(formatter);
+        }
+        if let Some(cut) = s.cut.clone() {
+            log.set_cut(cut).await;
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Caller tracing: backtraces of a sample of the lines containing a text,
+//! to find the code path behind a flood of them.
+
+use std::{
+    backtrace::Backtrace,
+    time::{Duration, Instant},
+};
+
+/// A `Logger::trace_callers` window.
+pub(crate) struct CallerTrace {
+    needle: String,
+    sample_rate: u64,
+    until: Instant,
+    matches: u64,
+}
+
+impl CallerTrace {
+    pub(crate) fn new(needle: &str, sample_rate: u64, now: Instant, duration: Duration) -> Self {
+        CallerTrace {
+            needle: needle.to_string(),
+            sample_rate: sample_rate.max(1),
+            until: now + duration,
+            matches: 0,
+        }
+    }
+
+    /// Counts `message` if it matches; the first match and every
+    /// `sample_rate`-th after it give the backtrace of this call.
+    fn sample(&mut self, message: &str) -> Option<String> {
+        if !message.contains(&self.needle) {
+            return None;
+        }
+        self.matches += 1;
+        if !(self.matches - 1).is_multiple_of(self.sample_rate) {
+            return None;
+        }
+        Some(format!("caller trace of {:?}, match {}:\n{}", self.needle, self.matches, Backtrace::force_capture()))
+    }
+}
+
+/// Runs the window in `trace` over `message` and the notice to log for it,
+/// a backtrace or, once the window is over, the match count; the window is
+/// then dropped.
+pub(crate) fn check(trace: &mut Option<CallerTrace>, now: Instant, message: &str) -> Option<String> {
+    let t = trace.as_mut()?;
+    if now >= t.until {
+        let notice = format!("caller trace of {:?} ended: {} matching lines", t.needle, t.matches);
+        *trace = None;
+        return Some(notice);
+    }
+    t.sample(message)
+}
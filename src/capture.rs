@@ -0,0 +1,240 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Captures the lines of a logger in memory, to assert on them in tests:
+//!
+//! ```
+//! use tklog::{capture::Capture, sync::Logger, LEVEL};
+//!
+//! let mut log = Logger::new();
+//! log.set_console(false);
+//! let capture = Capture::install(&log);
+//! let s = log.fmt("app", LEVEL::Warn, "main.rs", 7, "disk almost full".to_string());
+//! log.print(LEVEL::Warn, "app", s);
+//! let records = capture.records();
+//! assert_eq!(records[0].level, LEVEL::Warn);
+//! assert_eq!(records[0].message, "disk almost full");
+//! ```
+//!
+//! A capture takes each line as it was logged, before it is laid out, once
+//! it passed the level, the filters, the storm control and the custom
+//! handler, whether the console and the files take it or not. Lines are
+//! captured on the thread that logs them, also in `PRINTMODE::DELAY`. While
+//! a logger has a capture, the macros pass it the file and line of every
+//! line. Dropping the capture takes it off the logger.
+//!
+//! A capture on `LOG` or `ASYNC_LOG` takes the lines of every test logging
+//! through it; tests that run in parallel are better off with their own
+//! logger.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use tokio::sync::Notify;
+
+use crate::LEVEL;
+
+/// One line a `Capture` took.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CapturedRecord {
+    pub level: LEVEL,
+    pub module: String,
+    pub message: String,
+    /// The file and line of the macro, empty and 0 for a line logged
+    /// without them.
+    pub file: String,
+    pub line: u32,
+}
+
+#[derive(Default)]
+struct Shared {
+    records: Mutex<Vec<CapturedRecord>>,
+    added: Notify,
+}
+
+impl Shared {
+    fn records(&self) -> MutexGuard<'_, Vec<CapturedRecord>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The lines of a logger since `install`, until dropped.
+pub struct Capture {
+    shared: Arc<Shared>,
+    /// The captures of the logger, to take this one off when dropped.
+    logger: Weak<CaptureList>,
+}
+
+impl Capture {
+    /// Captures the lines of `logger`: a `sync::Logger`, an `Async::Logger`,
+    /// `LOG` or `ASYNC_LOG`, as is or in an `Arc` or a `Mutex`.
+    pub fn install<L: Capturable + ?Sized>(logger: &L) -> Capture {
+        let mut capture = Capture {
+            shared: Arc::new(Shared::default()),
+            logger: Weak::new(),
+        };
+        logger.attach(&mut capture);
+        capture
+    }
+
+    /// The lines taken so far, oldest first.
+    pub fn records(&self) -> Vec<CapturedRecord> {
+        self.shared.records().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.records().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets the lines taken so far.
+    pub fn clear(&self) {
+        self.shared.records().clear();
+    }
+
+    /// Waits until `n` lines were taken, for lines logged by other tasks;
+    /// false if they weren't within `timeout`.
+    pub async fn wait_for(&self, n: usize, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let added = self.shared.added.notified();
+            tokio::pin!(added);
+            added.as_mut().enable();
+            if self.len() >= n {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, added).await.is_err() {
+                return self.len() >= n;
+            }
+        }
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        if let Some(list) = self.logger.upgrade() {
+            list.remove(&self.shared);
+        }
+    }
+}
+
+/// The captures of a logger.
+#[derive(Default)]
+pub(crate) struct Captures(Arc<CaptureList>);
+
+#[derive(Default)]
+struct CaptureList {
+    on: AtomicBool,
+    list: Mutex<Vec<Weak<Shared>>>,
+}
+
+impl CaptureList {
+    fn list(&self) -> MutexGuard<'_, Vec<Weak<Shared>>> {
+        self.list.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn remove(&self, shared: &Arc<Shared>) {
+        let mut list = self.list();
+        list.retain(|c| !Weak::ptr_eq(c, &Arc::downgrade(shared)) && c.strong_count() > 0);
+        self.on.store(!list.is_empty(), Ordering::Release);
+    }
+}
+
+impl Captures {
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.0.on.load(Ordering::Acquire)
+    }
+
+    fn add(&self, capture: &mut Capture) {
+        self.0.list().push(Arc::downgrade(&capture.shared));
+        self.0.on.store(true, Ordering::Release);
+        capture.logger = Arc::downgrade(&self.0);
+    }
+
+    /// Hands one line to every capture.
+    pub(crate) fn record(&self, level: LEVEL, module: &str, file: &str, line: u32, message: &str) {
+        if self.is_empty() {
+            return;
+        }
+        for c in self.0.list().iter().filter_map(Weak::upgrade) {
+            c.records().push(CapturedRecord {
+                level,
+                module: module.to_string(),
+                message: message.to_string(),
+                file: file.to_string(),
+                line,
+            });
+            c.added.notify_waiters();
+        }
+    }
+}
+
+/// What a `Capture` can be installed on.
+pub trait Capturable {
+    #[doc(hidden)]
+    fn attach(&self, capture: &mut Capture);
+}
+
+impl Capturable for crate::sync::Logger {
+    fn attach(&self, capture: &mut Capture) {
+        self.captures().add(capture);
+    }
+}
+
+impl Capturable for crate::Async::Logger {
+    fn attach(&self, capture: &mut Capture) {
+        self.captures().add(capture);
+    }
+}
+
+impl Capturable for crate::sync::Log {
+    fn attach(&self, capture: &mut Capture) {
+        crate::global().captures().add(capture);
+    }
+}
+
+impl Capturable for crate::Async::Log {
+    fn attach(&self, capture: &mut Capture) {
+        crate::global_async_blocking().captures().add(capture);
+    }
+}
+
+impl<T: Capturable + ?Sized> Capturable for Arc<T> {
+    fn attach(&self, capture: &mut Capture) {
+        T::attach(self, capture);
+    }
+}
+
+impl<T: Capturable + ?Sized> Capturable for Mutex<T> {
+    fn attach(&self, capture: &mut Capture) {
+        self.lock().unwrap_or_else(|e| e.into_inner()).attach(capture);
+    }
+}
+
+impl<T: Capturable> Capturable for Lazy<T> {
+    fn attach(&self, capture: &mut Capture) {
+        T::attach(self, capture);
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The bound of the `PRINTMODE::DELAY` queue of an async logger, see
+//! `Logger::set_channel_capacity`.
+//!
+//! The queue is unbounded by default. Once it holds its capacity of lines,
+//! a new line waits for room, is dropped, or drops the oldest line queued,
+//! as `OverflowPolicy` says. The lines tklog writes itself, such as the
+//! warning of the lines dropped, are never dropped. A line that cannot
+//! wait, on a current-thread runtime or with the consumer stopped, is
+//! queued past the capacity.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Condvar, Mutex, MutexGuard, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    memory::{Held, Shed},
+    OverflowPolicy,
+};
+
+/// How often the lines dropped are told.
+pub(crate) const NOTICE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How long a blocked line sleeps before it checks the consumer again.
+const WAIT_STEP: Duration = Duration::from_millis(10);
+
+/// Shared between a logger and its queue consumer, which tells it when
+/// lines were taken.
+#[derive(Default)]
+pub(crate) struct ChannelBound {
+    /// 0 for no bound.
+    capacity: AtomicUsize,
+    dropped: AtomicU64,
+    state: Mutex<State>,
+    room: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    policy: OverflowPolicy,
+    /// The lines queued, oldest first; taken ones are skipped when found
+    /// at the front.
+    queued: VecDeque<Weak<dyn Shed>>,
+    /// Since when lines have been dropped without a notice, and how many.
+    window: Option<(Instant, u64)>,
+}
+
+impl State {
+    fn purge(&mut self) {
+        while self.queued.front().is_some_and(|w| w.upgrade().is_none_or(|s| s.is_taken())) {
+            self.queued.pop_front();
+        }
+    }
+
+    fn count_drop(&mut self, now: Instant) {
+        match &mut self.window {
+            Some((_, n)) => *n += 1,
+            None => self.window = Some((now, 1)),
+        }
+    }
+}
+
+/// What `ChannelBound::admit` makes of a line.
+pub(crate) enum Admission<T> {
+    Queue(Held<T>),
+    /// The queue is full and the line is to wait, see `wait_room`.
+    Wait(Held<T>),
+    Drop,
+}
+
+impl ChannelBound {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        self.room.notify_all();
+    }
+
+    pub(crate) fn set_policy(&self, policy: OverflowPolicy) {
+        self.lock().policy = policy;
+        self.room.notify_all();
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Queues `line` if there is room, making it under `DropOldest`.
+    pub(crate) fn admit<T: Send + 'static>(&self, line: Held<T>, now: Instant) -> Admission<T> {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return Admission::Queue(line);
+        }
+        let mut state = self.lock();
+        state.purge();
+        while state.queued.len() >= capacity {
+            match state.policy {
+                OverflowPolicy::Block => return Admission::Wait(line),
+                OverflowPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    state.count_drop(now);
+                    return Admission::Drop;
+                }
+                OverflowPolicy::DropOldest => {
+                    // The consumer counts it as shed when it gets to it.
+                    if state.queued.pop_front().and_then(|w| w.upgrade()).and_then(|s| s.shed()).is_some() {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        state.count_drop(now);
+                    }
+                    state.purge();
+                }
+            }
+        }
+        Admission::Queue(Self::push(&mut state, line))
+    }
+
+    /// Queues `line` whether or not there is room.
+    pub(crate) fn push_unchecked<T: Send + 'static>(&self, line: Held<T>) -> Held<T> {
+        if self.capacity.load(Ordering::Relaxed) == 0 {
+            return line;
+        }
+        Self::push(&mut self.lock(), line)
+    }
+
+    fn push<T: Send + 'static>(state: &mut State, line: Held<T>) -> Held<T> {
+        let (line, weak) = line.sheddable();
+        state.queued.push_back(weak);
+        line
+    }
+
+    /// Waits until the queue has room, the bound or policy changes, or
+    /// `running` turns false.
+    pub(crate) fn wait_room(&self, running: impl Fn() -> bool) {
+        let mut state = self.lock();
+        loop {
+            state.purge();
+            let capacity = self.capacity.load(Ordering::Relaxed);
+            if capacity == 0 || state.queued.len() < capacity || state.policy != OverflowPolicy::Block || !running() {
+                return;
+            }
+            state = self.room.wait_timeout(state, WAIT_STEP).unwrap_or_else(|e| e.into_inner()).0;
+        }
+    }
+
+    /// Called by the consumer once it took lines.
+    pub(crate) fn taken(&self) {
+        if self.capacity.load(Ordering::Relaxed) > 0 {
+            self.room.notify_all();
+        }
+    }
+
+    /// The warning of the lines dropped, once `NOTICE_PERIOD` has passed
+    /// since the first of them.
+    pub(crate) fn notice(&self, now: Instant) -> Option<String> {
+        if self.capacity.load(Ordering::Relaxed) == 0 && self.dropped() == 0 {
+            return None;
+        }
+        let mut state = self.lock();
+        let (since, n) = state.window?;
+        if now.duration_since(since) < NOTICE_PERIOD {
+            return None;
+        }
+        state.window = None;
+        Some(format!("dropped {} records in the last {}s", n, now.duration_since(since).as_secs()))
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Time sources for time-window logic such as storm control.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+/// A monotonic time source; `Logger::set_clock` swaps it, e.g. for tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// The time of day, for logic that follows the calendar such as the
+    /// adaptive budget.
+    fn wall(&self) -> DateTime<Local> {
+        crate::now()
+    }
+}
+
+/// The real clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// ### Example
+/// ```rust
+/// use std::time::Duration;
+/// use tklog::clock::{Clock, ManualClock};
+///
+/// let clock = ManualClock::new();
+/// let t0 = clock.now();
+/// clock.advance(Duration::from_secs(10));
+/// assert_eq!(clock.now() - t0, Duration::from_secs(10));
+/// ```
+pub struct ManualClock {
+    base: Instant,
+    wall: DateTime<Local>,
+    offset: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::at(crate::now())
+    }
+
+    /// A clock whose `wall` time starts at `wall`.
+    pub fn at(wall: DateTime<Local>) -> Self {
+        ManualClock {
+            base: Instant::now(),
+            wall,
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, d: Duration) {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner()) += d;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.base + *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wall(&self) -> DateTime<Local> {
+        self.wall + *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
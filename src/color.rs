@@ -0,0 +1,194 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Console lines colored by level, with `Logger::set_console_color`:
+//! Trace and Debug dim, Info green, Warn yellow, Error and Fatal red.
+//!
+//! Only the console gets the colors; the files, the custom sink and the
+//! rotated backups get the line as it was. Colors are left off when the
+//! stream a line goes to, stdout or stderr (see `ConsoleStream`), is not a
+//! terminal or `NO_COLOR` is set, unless forced by
+//! `ColorOptions::force` or a `CLICOLOR_FORCE` or `FORCE_COLOR` other than
+//! `0`, for CI logs that show ANSI colors without a terminal.
+//!
+//! On Windows the first colored line turns on the ANSI processing of its
+//! console. A console that refuses it, as older ones do, gets no colors,
+//! and the escapes of the messages are stripped from its lines; so are
+//! they when the stream is redirected to a file, even when forced.
+//! `force_ansi` overrides this detection, on every platform.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::color::ColorOptions;
+//! use tklog::sync::Logger;
+//!
+//! let mut log = Logger::new();
+//! log.set_console_color(true);
+//! // or the whole line, colored in CI too:
+//! log.set_console_color_options(ColorOptions { whole_line: true, force: true });
+//! ```
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::LEVEL;
+
+const RESET: &str = "\x1b[0m";
+
+/// Set by `force_ansi`: 0 to detect, 1 for ANSI, 2 for none.
+static FORCED_ANSI: AtomicU8 = AtomicU8::new(0);
+
+/// Whether the console takes ANSI escapes, for every logger of the
+/// process, in place of the detection of the module docs: `false` leaves
+/// the console lines without colors and strips the escapes of their
+/// messages, `true` writes them as they are, to a redirected stream too.
+pub fn force_ansi(on: bool) {
+    FORCED_ANSI.store(if on { 1 } else { 2 }, Ordering::Relaxed);
+}
+
+/// Whether lines to stderr, or stdout, may hold ANSI escapes.
+pub(crate) fn ansi(stderr: bool) -> bool {
+    match FORCED_ANSI.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => console::ansi(stderr),
+    }
+}
+
+#[cfg(not(windows))]
+mod console {
+    pub(super) fn ansi(_stderr: bool) -> bool {
+        true
+    }
+}
+
+#[cfg(windows)]
+mod console {
+    use std::{ffi::c_void, sync::OnceLock};
+
+    extern "system" {
+        fn GetStdHandle(which: u32) -> *mut c_void;
+        fn GetFileType(handle: *mut c_void) -> u32;
+        fn GetConsoleMode(handle: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(handle: *mut c_void, mode: u32) -> i32;
+    }
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+    const FILE_TYPE_DISK: u32 = 1;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 4;
+
+    /// The detection of stdout and stderr, made once each.
+    static DETECTED: [OnceLock<bool>; 2] = [OnceLock::new(), OnceLock::new()];
+
+    pub(super) fn ansi(stderr: bool) -> bool {
+        *DETECTED[stderr as usize].get_or_init(|| detect(stderr))
+    }
+
+    /// A console takes ANSI once its virtual terminal processing is on; a
+    /// file never does. A pipe passes the escapes to its reader.
+    fn detect(stderr: bool) -> bool {
+        unsafe {
+            let handle = GetStdHandle(if stderr { STD_ERROR_HANDLE } else { STD_OUTPUT_HANDLE });
+            if handle.is_null() || handle as isize == -1 || GetFileType(handle) == FILE_TYPE_DISK {
+                return false;
+            }
+            let mut mode = 0;
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                return true;
+            }
+            mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColorOptions {
+    /// Colors the whole line rather than the level flag. Default: false.
+    pub whole_line: bool,
+    /// Colors the lines even when their stream is not a terminal, but not
+    /// a console without ANSI, see `force_ansi`. Default: false.
+    pub force: bool,
+}
+
+impl ColorOptions {
+    /// Whether lines to a stream get colors, see the module docs.
+    fn active(&self, terminal: bool) -> bool {
+        let env_force = ["CLICOLOR_FORCE", "FORCE_COLOR"].iter().any(|var| std::env::var(var).is_ok_and(|v| !v.is_empty() && v != "0"));
+        self.force || env_force || (std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && terminal)
+    }
+}
+
+/// The colors of the console lines, with the streams that get them.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ConsoleColors {
+    pub(crate) opts: ColorOptions,
+    stdout: bool,
+    stderr: bool,
+}
+
+impl ConsoleColors {
+    /// `None` when neither stream gets colors.
+    pub(crate) fn new(opts: ColorOptions) -> Option<Self> {
+        let colors = ConsoleColors {
+            opts,
+            stdout: opts.active(std::io::stdout().is_terminal()) && ansi(false),
+            stderr: opts.active(std::io::stderr().is_terminal()) && ansi(true),
+        };
+        (colors.stdout || colors.stderr).then_some(colors)
+    }
+
+    /// Asks `ansi` again, for a `force_ansi` made after `new`.
+    pub(crate) fn on(&self, stderr: bool) -> bool {
+        let on = if stderr { self.stderr } else { self.stdout };
+        on && ansi(stderr)
+    }
+}
+
+fn code(level: LEVEL) -> &'static str {
+    match level {
+        LEVEL::Trace | LEVEL::Debug => "\x1b[2m",
+        LEVEL::Info => "\x1b[32m",
+        LEVEL::Warn => "\x1b[33m",
+        LEVEL::Error | LEVEL::Fatal => "\x1b[31m",
+        LEVEL::Off => "",
+    }
+}
+
+/// `s` colored for `level`: its level flag `flag`, or the whole line when
+/// asked or when the flag is not in it, as with a `set_level_fmt`. The end
+/// of line stays outside the colors.
+pub(crate) fn paint(opts: ColorOptions, level: LEVEL, flag: &str, s: &str) -> String {
+    let code = code(level);
+    let mut out = String::with_capacity(s.len() + code.len() + RESET.len());
+    match s.find(flag).filter(|_| !opts.whole_line && !flag.is_empty()) {
+        Some(i) => {
+            out.push_str(&s[..i]);
+            out.push_str(code);
+            out.push_str(flag);
+            out.push_str(RESET);
+            out.push_str(&s[i + flag.len()..]);
+        }
+        None => {
+            let body = s.strip_suffix('\n').unwrap_or(s);
+            out.push_str(code);
+            out.push_str(body);
+            out.push_str(RESET);
+            out.push_str(&s[body.len()..]);
+        }
+    }
+    out
+}
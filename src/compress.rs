@@ -0,0 +1,174 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading log files and their backups, compressed or not.
+//!
+//! `open_any` tells the encoding by the first bytes of the file, not by its
+//! name, and decompresses while reading. An archive cut short, as by a
+//! crash during compression, reads up to where it ends and then fails with
+//! a `Truncated` error. So does a live file written with
+//! `Logger::set_live_compression`, whose last gzip member is still open:
+//! it reads up to its last flush.
+//!
+//! ### Example
+//! ```no_run
+//! use std::io::BufRead;
+//!
+//! for line in tklog::compress::open_any("logs/app_20240501_1.log.gz").unwrap().lines() {
+//!     match line {
+//!         Ok(line) => println!("{}", line),
+//!         Err(e) if tklog::compress::Truncated::of(&e).is_some() => break,
+//!         Err(e) => panic!("{}", e),
+//!     }
+//! }
+//! ```
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use flate2::{
+    read::{MultiGzDecoder, ZlibDecoder},
+    write::GzEncoder,
+    Compression,
+};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// The error an archive cut short fails with, carried by an
+/// `io::ErrorKind::UnexpectedEof` error.
+#[derive(Clone, Debug)]
+pub struct Truncated {
+    /// The archive.
+    pub path: PathBuf,
+    /// How many bytes it decompressed to before it ended.
+    pub offset: u64,
+}
+
+impl Truncated {
+    /// The `Truncated` behind `e`, if it is one.
+    pub fn of(e: &io::Error) -> Option<&Truncated> {
+        e.get_ref().and_then(|e| e.downcast_ref())
+    }
+}
+
+impl fmt::Display for Truncated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is truncated after {} bytes", self.path.display(), self.offset)
+    }
+}
+
+impl std::error::Error for Truncated {}
+
+/// A streaming reader of `path`, decompressing gzip, every member of it,
+/// and zlib. Zstandard is
+/// recognized but has no decoder in this crate and fails with
+/// `io::ErrorKind::Unsupported`; anything else is read as it is.
+pub fn open_any(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    let path = path.as_ref();
+    let mut file = BufReader::new(File::open(path)?);
+    let head = file.fill_buf()?;
+    if head.starts_with(GZIP_MAGIC) {
+        return Ok(Box::new(BufReader::new(Decoded::new(path, MultiGzDecoder::new(file)))));
+    }
+    if is_zlib(head) {
+        return Ok(Box::new(BufReader::new(Decoded::new(path, ZlibDecoder::new(file)))));
+    }
+    if head.starts_with(ZSTD_MAGIC) {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is zstd compressed, which tklog can't read", path.display())));
+    }
+    Ok(Box::new(file))
+}
+
+/// A zlib header: deflate, a checksum over the first two bytes and no
+/// preset dictionary.
+fn is_zlib(head: &[u8]) -> bool {
+    match head {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && cmf >> 4 <= 7 && flg & 0x20 == 0 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+/// A decoder whose end of input before the end of the stream becomes a
+/// `Truncated` error.
+struct Decoded<R> {
+    path: PathBuf,
+    inner: R,
+    offset: u64,
+}
+
+impl<R: Read> Decoded<R> {
+    fn new(path: &Path, inner: R) -> Self {
+        Decoded { path: path.to_path_buf(), inner, offset: 0 }
+    }
+}
+
+impl<R: Read> Read for Decoded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(n) => {
+                self.offset += n as u64;
+                Ok(n)
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, Truncated { path: self.path.clone(), offset: self.offset }))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// The gzip stream of a live file, see `Logger::set_live_compression`.
+/// Each run of the file between two rotations is one gzip member.
+pub(crate) struct LiveEncoder {
+    encoder: GzEncoder<Vec<u8>>,
+    interval: Duration,
+    flushed: Instant,
+}
+
+impl LiveEncoder {
+    pub(crate) fn new(level: u32, interval: Duration) -> Self {
+        LiveEncoder { encoder: GzEncoder::new(Vec::new(), Compression::new(level)), interval, flushed: Instant::now() }
+    }
+
+    /// Compresses `data` and returns the bytes to append to the file, with
+    /// a sync flush once `interval` has passed since the last one.
+    pub(crate) fn write(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.encoder.write_all(data)?;
+        if self.flushed.elapsed() >= self.interval {
+            return self.flush();
+        }
+        Ok(mem::take(self.encoder.get_mut()))
+    }
+
+    /// The bytes to append for a reader to get everything written so far.
+    pub(crate) fn flush(&mut self) -> io::Result<Vec<u8>> {
+        self.encoder.flush()?;
+        self.flushed = Instant::now();
+        Ok(mem::take(self.encoder.get_mut()))
+    }
+
+    /// The last bytes of the member, its trailer included.
+    pub(crate) fn finish(self) -> io::Result<Vec<u8>> {
+        self.encoder.finish()
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fields of the work at hand, such as the ID of the request being
+//! handled, added to every line logged meanwhile: `insert` and `remove`
+//! change them for the thread, `scope` until its guard is dropped.
+//!
+//! A tokio task moves between threads and shares them with other tasks
+//! at every `.await`; run it in `in_task` to give it context of its own.
+//! Within it, the functions of this module change that of the task.
+//!
+//! A line gets the context after the fields of its macro and before the
+//! dynamic fields, leaving out the keys it has already, as ` key=value`
+//! or JSON fields. A logger's `set_context_fields(false)` leaves it out
+//! without looking it up.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{context, info};
+//!
+//! fn handle(request_id: u64) {
+//!     let _ctx = context::scope(&[("request_id", request_id)]);
+//!     info!("request done");
+//!     // [INFO] ... request done request_id=42
+//! }
+//!
+//! async fn serve(request_id: u64) {
+//!     context::in_task(&[("request_id", request_id)], async {
+//!         tklog::async_info!("request done");
+//!     })
+//!     .await;
+//! }
+//! ```
+
+use std::{cell::RefCell, fmt::Display, future::Future};
+
+use crate::fields::FieldMap;
+
+thread_local! {
+    static THREAD: RefCell<FieldMap> = RefCell::new(FieldMap::new());
+}
+
+tokio::task_local! {
+    static TASK: RefCell<FieldMap>;
+}
+
+/// Runs `f` on the context of the task if there is one, else of the thread.
+fn with<R>(f: impl FnOnce(&mut FieldMap) -> R) -> R {
+    match TASK.try_with(|_| ()) {
+        Ok(()) => TASK.with(|m| f(&mut m.borrow_mut())),
+        Err(_) => THREAD.with(|m| f(&mut m.borrow_mut())),
+    }
+}
+
+pub fn insert(key: impl Into<String>, value: impl Display) {
+    let value = value.to_string();
+    with(|m| {
+        m.insert(key, value);
+    });
+}
+
+pub fn remove(key: &str) {
+    with(|m| {
+        m.remove(key);
+    });
+}
+
+/// The context now.
+pub fn current() -> FieldMap {
+    with(|m| m.clone())
+}
+
+/// Adds `fields` to the context until the guard is dropped, which puts
+/// back the context from before.
+pub fn scope<K: AsRef<str>, V: Display>(fields: &[(K, V)]) -> ContextScope {
+    let fields = map_of(fields);
+    let previous = with(|m| {
+        let previous = m.clone();
+        m.merge(fields, true);
+        previous
+    });
+    ContextScope { previous: Some(previous) }
+}
+
+/// The guard of `scope`.
+#[must_use = "the fields are removed when the guard is dropped, right away unless bound to a name"]
+pub struct ContextScope {
+    previous: Option<FieldMap>,
+}
+
+impl Drop for ContextScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            with(|m| *m = previous);
+        }
+    }
+}
+
+/// Runs `f` with a context of its own, the current one with `fields`
+/// added, that no other task sees.
+pub async fn in_task<K: AsRef<str>, V: Display, F: Future>(fields: &[(K, V)], f: F) -> F::Output {
+    let mut context = current();
+    context.merge(map_of(fields), true);
+    TASK.scope(RefCell::new(context), f).await
+}
+
+fn map_of<K: AsRef<str>, V: Display>(fields: &[(K, V)]) -> FieldMap {
+    let mut map = FieldMap::new();
+    for (k, v) in fields {
+        map.insert(k.as_ref(), v);
+    }
+    map
+}
+
+/// Adds the context to `fields`, for the keys it doesn't have.
+pub(crate) fn add_to(fields: &mut FieldMap) {
+    with(|m| {
+        if !m.is_empty() {
+            fields.merge(m.clone(), false);
+        }
+    });
+}
+
+/// Whether a line would get no context.
+pub(crate) fn is_empty() -> bool {
+    with(|m| m.is_empty())
+}
@@ -0,0 +1,300 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validated cut-mode settings for `Logger::set_cut`, built by name
+//! instead of by position.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::cut::CutSize;
+//! use tklog::sync::Logger;
+//! use tklog::CompressType;
+//!
+//! let cut = CutSize::builder().file("app.log").max_size_mb(512).backups(7).compress(CompressType::Gzip).build().unwrap();
+//! Logger::new().set_cut(cut);
+//! ```
+
+use std::path::Path;
+
+use crate::{handle::FileOptionType, CompressType, Error, CUTMODE, MODE};
+
+/// Rotation by size.
+#[derive(Clone, Debug)]
+pub struct CutSize {
+    pub(crate) filename: String,
+    pub(crate) max_size: u64,
+    pub(crate) backups: u32,
+    pub(crate) compress: Option<CompressType>,
+}
+
+/// Rotation by time.
+#[derive(Clone, Debug)]
+pub struct CutTime {
+    pub(crate) filename: String,
+    pub(crate) mode: MODE,
+    pub(crate) backups: u32,
+    pub(crate) compress: Option<CompressType>,
+}
+
+/// Rotation by time and by size, whichever comes first.
+#[derive(Clone, Debug)]
+pub struct CutMixed {
+    pub(crate) filename: String,
+    pub(crate) mode: MODE,
+    pub(crate) max_size: u64,
+    pub(crate) backups: u32,
+    pub(crate) compress: Option<CompressType>,
+}
+
+/// What `Logger::set_cut` takes.
+#[derive(Clone, Debug)]
+pub enum CutConfig {
+    Size(CutSize),
+    Time(CutTime),
+    Mixed(CutMixed),
+}
+
+impl From<CutSize> for CutConfig {
+    fn from(cut: CutSize) -> Self {
+        CutConfig::Size(cut)
+    }
+}
+
+impl From<CutTime> for CutConfig {
+    fn from(cut: CutTime) -> Self {
+        CutConfig::Time(cut)
+    }
+}
+
+impl From<CutMixed> for CutConfig {
+    fn from(cut: CutMixed) -> Self {
+        CutConfig::Mixed(cut)
+    }
+}
+
+impl CutConfig {
+    pub fn filename(&self) -> &str {
+        match self {
+            CutConfig::Size(c) => &c.filename,
+            CutConfig::Time(c) => &c.filename,
+            CutConfig::Mixed(c) => &c.filename,
+        }
+    }
+
+    pub(crate) fn option(&self) -> FileOptionType {
+        match self {
+            CutConfig::Size(c) => FileOptionType::new(CUTMODE::SIZE, MODE::DAY, &c.filename, c.max_size, c.backups, c.compress.is_some()),
+            CutConfig::Time(c) => FileOptionType::new(CUTMODE::TIME, c.mode, &c.filename, 0, c.backups, c.compress.is_some()),
+            CutConfig::Mixed(c) => FileOptionType::new(CUTMODE::MIXED, c.mode, &c.filename, c.max_size, c.backups, c.compress.is_some()),
+        }
+    }
+}
+
+impl CutSize {
+    pub fn builder() -> CutSizeBuilder {
+        CutSizeBuilder::default()
+    }
+
+    /// The settings of `set_cutmode_by_size`, taken as they are.
+    pub(crate) fn unchecked(filename: &str, max_size: u64, backups: u32, compress: bool) -> Self {
+        CutSize { filename: filename.to_string(), max_size, backups, compress: compress.then_some(CompressType::Gzip) }
+    }
+}
+
+impl CutTime {
+    pub fn builder() -> CutTimeBuilder {
+        CutTimeBuilder::default()
+    }
+
+    /// The settings of `set_cutmode_by_time`, taken as they are.
+    pub(crate) fn unchecked(filename: &str, mode: MODE, backups: u32, compress: bool) -> Self {
+        CutTime { filename: filename.to_string(), mode, backups, compress: compress.then_some(CompressType::Gzip) }
+    }
+}
+
+impl CutMixed {
+    pub fn builder() -> CutMixedBuilder {
+        CutMixedBuilder::default()
+    }
+
+    /// The settings of `set_cutmode_by_mixed`, taken as they are.
+    pub(crate) fn unchecked(filename: &str, mode: MODE, max_size: u64, backups: u32, compress: bool) -> Self {
+        CutMixed { filename: filename.to_string(), mode, max_size, backups, compress: compress.then_some(CompressType::Gzip) }
+    }
+}
+
+/// Builds a `CutSize`; the file and the size are required.
+#[derive(Clone, Debug, Default)]
+pub struct CutSizeBuilder {
+    filename: Option<String>,
+    max_size: Option<u64>,
+    max_size_mb: Option<u64>,
+    backups: u32,
+    compress: Option<CompressType>,
+}
+
+impl CutSizeBuilder {
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.filename = Some(path.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// The size in bytes a file rotates at.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// The size in MiB a file rotates at.
+    pub fn max_size_mb(mut self, mb: u64) -> Self {
+        self.max_size_mb = Some(mb);
+        self
+    }
+
+    /// The backups to keep; 0, the default, keeps them all.
+    pub fn backups(mut self, n: u32) -> Self {
+        self.backups = n;
+        self
+    }
+
+    pub fn compress(mut self, compress: CompressType) -> Self {
+        self.compress = Some(compress);
+        self
+    }
+
+    /// `Error::InvalidCut` for a missing or empty file, a missing or zero
+    /// size, or a size given both in bytes and in MiB.
+    pub fn build(self) -> Result<CutSize, Error> {
+        let filename = required_file(self.filename)?;
+        let max_size = match (self.max_size, self.max_size_mb) {
+            (Some(_), Some(_)) => return Err(Error::InvalidCut("max size given both in bytes and in MiB")),
+            (None, None) => return Err(Error::InvalidCut("no max size")),
+            (Some(bytes), None) => bytes,
+            (None, Some(mb)) => mb.checked_mul(1 << 20).ok_or(Error::InvalidCut("max size overflows"))?,
+        };
+        if max_size == 0 {
+            return Err(Error::InvalidCut("max size is zero"));
+        }
+        Ok(CutSize { filename, max_size, backups: self.backups, compress: self.compress })
+    }
+}
+
+/// Builds a `CutTime`; the file and the mode are required.
+#[derive(Clone, Debug, Default)]
+pub struct CutTimeBuilder {
+    filename: Option<String>,
+    mode: Option<MODE>,
+    conflict: bool,
+    backups: u32,
+    compress: Option<CompressType>,
+}
+
+impl CutTimeBuilder {
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.filename = Some(path.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// The period a file covers. Giving two different ones is an error.
+    pub fn mode(mut self, mode: MODE) -> Self {
+        self.conflict |= self.mode.is_some_and(|m| m != mode);
+        self.mode = Some(mode);
+        self
+    }
+
+    /// The backups to keep; 0, the default, keeps them all.
+    pub fn backups(mut self, n: u32) -> Self {
+        self.backups = n;
+        self
+    }
+
+    pub fn compress(mut self, compress: CompressType) -> Self {
+        self.compress = Some(compress);
+        self
+    }
+
+    /// `Error::InvalidCut` for a missing or empty file, a missing or
+    /// conflicting mode, or an interval under a second.
+    pub fn build(self) -> Result<CutTime, Error> {
+        let filename = required_file(self.filename)?;
+        if self.conflict {
+            return Err(Error::InvalidCut("two different rotation modes"));
+        }
+        let mode = self.mode.ok_or(Error::InvalidCut("no rotation mode"))?;
+        if matches!(mode, MODE::INTERVAL(interval) if interval.as_secs() == 0) {
+            return Err(Error::InvalidCut("rotation interval under a second"));
+        }
+        Ok(CutTime { filename, mode, backups: self.backups, compress: self.compress })
+    }
+}
+
+/// Builds a `CutMixed`; the file, the mode and the size are required.
+#[derive(Clone, Debug, Default)]
+pub struct CutMixedBuilder {
+    time: CutTimeBuilder,
+    size: CutSizeBuilder,
+}
+
+impl CutMixedBuilder {
+    pub fn file(self, path: impl AsRef<Path>) -> Self {
+        CutMixedBuilder { time: self.time.file(&path), size: self.size.file(path) }
+    }
+
+    /// The period a file covers at most. Giving two different ones is an error.
+    pub fn mode(mut self, mode: MODE) -> Self {
+        self.time = self.time.mode(mode);
+        self
+    }
+
+    /// The size in bytes a file rotates at within its period.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.size = self.size.max_size(bytes);
+        self
+    }
+
+    /// The size in MiB a file rotates at within its period.
+    pub fn max_size_mb(mut self, mb: u64) -> Self {
+        self.size = self.size.max_size_mb(mb);
+        self
+    }
+
+    /// The backups to keep; 0, the default, keeps them all.
+    pub fn backups(mut self, n: u32) -> Self {
+        self.time = self.time.backups(n);
+        self
+    }
+
+    pub fn compress(mut self, compress: CompressType) -> Self {
+        self.time = self.time.compress(compress);
+        self
+    }
+
+    /// `Error::InvalidCut` for what `CutTimeBuilder::build` or
+    /// `CutSizeBuilder::build` refuse.
+    pub fn build(self) -> Result<CutMixed, Error> {
+        let time = self.time.build()?;
+        let size = self.size.build()?;
+        Ok(CutMixed { filename: time.filename, mode: time.mode, max_size: size.max_size, backups: time.backups, compress: time.compress })
+    }
+}
+
+fn required_file(filename: Option<String>) -> Result<String, Error> {
+    match filename {
+        None => Err(Error::InvalidCut("no file")),
+        Some(f) if f.trim().is_empty() => Err(Error::InvalidCut("empty file name")),
+        Some(f) => Ok(f),
+    }
+}
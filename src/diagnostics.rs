@@ -0,0 +1,183 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What tklog has to say about itself, such as a file it cannot reopen or
+//! delete. Warnings go to stderr as `tklog: ...` lines unless a handler is
+//! set with `tklog::set_diagnostics_handler`, which gets every diagnostic.
+//!
+//! A burst of one category, such as the deletions of a retention pass,
+//! rolls up: its first diagnostic is reported as it happens, the rest as
+//! one more with their count and their first and last file.
+
+use std::{
+    fmt,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// How far apart diagnostics of one category may be to roll up.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// What `tklog::set_diagnostics_handler` takes.
+pub type DiagnosticsHandler = Box<dyn Fn(&Diagnostic) + Send + Sync>;
+
+type Handler = Arc<dyn Fn(&Diagnostic) + Send + Sync>;
+
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+static RUN: Mutex<Option<Run>> = Mutex::new(None);
+
+/// What a diagnostic is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// A backup or dated file deleted by retention; not a warning.
+    Pruned,
+    /// A file or directory retention could not delete.
+    DeleteFailed,
+    /// A log file that could not be reopened.
+    Reopen,
+    /// Out of file descriptors.
+    FdExhausted,
+    /// A backup left uncompressed for lack of space.
+    CompressionSkipped,
+    /// The local time zone is unusable.
+    TimeZone,
+    /// A setting that has no effect.
+    Config,
+    /// A file handler refused for a path written with other cut settings.
+    PathConflict,
+    /// A user callback that panicked.
+    CallbackPanic,
+    /// A syslog socket that failed, see `syslog`.
+    Syslog,
+    /// A remote end that can't be reached, see `remote`.
+    Remote,
+    /// A journald socket that failed, see `journald`.
+    Journald,
+}
+
+impl Category {
+    /// True for the categories written to stderr when no handler is set.
+    pub fn is_warning(self) -> bool {
+        self != Category::Pruned
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Pruned => "files pruned",
+            Category::DeleteFailed => "files not deleted",
+            Category::Reopen => "files not reopened",
+            Category::FdExhausted => "file descriptor shortages",
+            Category::CompressionSkipped => "backups not compressed",
+            Category::TimeZone => "time zone problems",
+            Category::Config => "ineffective settings",
+            Category::PathConflict => "file handlers refused",
+            Category::CallbackPanic => "callback panics",
+            Category::Syslog => "syslog failures",
+            Category::Remote => "remote failures",
+            Category::Journald => "journald failures",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub category: Category,
+    pub message: String,
+    /// The file concerned; the first one for a roll-up.
+    pub path: Option<PathBuf>,
+    /// How many diagnostics this one stands for, more than 1 for a roll-up.
+    pub count: u64,
+    /// The last file of a roll-up.
+    pub last_path: Option<PathBuf>,
+}
+
+/// The diagnostics of a burst after its first.
+struct Run {
+    category: Category,
+    last: Instant,
+    count: u64,
+    first_path: Option<PathBuf>,
+    last_path: Option<PathBuf>,
+}
+
+impl Run {
+    fn summary(self) -> Option<Diagnostic> {
+        if self.count == 0 {
+            return None;
+        }
+        let name = |p: &Option<PathBuf>| p.as_ref().map_or_else(|| "-".to_string(), |p| p.display().to_string());
+        let mut message = format!("{} more {}: {}", self.count, self.category, name(&self.first_path));
+        if self.count > 1 {
+            message += &format!(" to {}", name(&self.last_path));
+        }
+        Some(Diagnostic {
+            category: self.category,
+            message,
+            path: self.first_path,
+            count: self.count,
+            last_path: self.last_path,
+        })
+    }
+}
+
+/// Reports `message` about `path`, or counts it in the burst of its
+/// category.
+pub(crate) fn report(category: Category, path: Option<&Path>, message: String) {
+    let now = Instant::now();
+    let mut run = RUN.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(r) = run.as_mut().filter(|r| r.category == category && now - r.last < WINDOW) {
+        r.last = now;
+        r.count += 1;
+        if r.first_path.is_none() {
+            r.first_path = path.map(Path::to_path_buf);
+        }
+        r.last_path = path.map(Path::to_path_buf);
+        return;
+    }
+    let ended = run.replace(Run { category, last: now, count: 0, first_path: None, last_path: None }).and_then(Run::summary);
+    drop(run);
+    if let Some(d) = ended {
+        emit(&d);
+    }
+    emit(&Diagnostic { category, message, path: path.map(Path::to_path_buf), count: 1, last_path: None });
+}
+
+/// Ends the current burst, reporting what it rolled up.
+pub(crate) fn flush() {
+    let ended = RUN.lock().unwrap_or_else(|e| e.into_inner()).take().and_then(Run::summary);
+    if let Some(d) = ended {
+        emit(&d);
+    }
+}
+
+fn emit(d: &Diagnostic) {
+    let handler = HANDLER.read().unwrap_or_else(|e| e.into_inner()).clone();
+    match handler {
+        Some(h) if catch_unwind(AssertUnwindSafe(|| h(d))).is_err() => eprintln!("tklog: the diagnostics handler panicked on: {}", d.message),
+        Some(_) => {}
+        None if d.category.is_warning() => eprintln!("tklog: {}", d.message),
+        None => {}
+    }
+}
+
+pub(crate) fn set_handler(handler: Option<Handler>) {
+    flush();
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = handler;
+}
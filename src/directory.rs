@@ -0,0 +1,213 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! One file per module and day under a root directory, set up by
+//! `Logger::set_directory_mode`.
+//!
+//! Module names become file or directory names with every character but
+//! ASCII letters, digits, `_` and `-` replaced by `_`, and `::` by `.`, so
+//! `../etc` can never leave the root. Files are opened on their first line
+//! and closed again after a minute without one.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{directory::DirLayout, sync::Logger};
+//!
+//! let mut log = Logger::new();
+//! // logs/app.db/2024-05-01.log, logs/app.http/2024-05-01.log, ...
+//! log.set_directory_mode("logs".into(), DirLayout::ModuleThenDate).set_directory_retention(7);
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Duration as Days, Local, NaiveDate};
+
+use crate::diagnostics::{self, Category};
+
+/// How `Logger::set_directory_mode` lays files out under its root.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DirLayout {
+    /// `<root>/<module>/<YYYY-MM-DD>.log`
+    ModuleThenDate,
+    /// `<root>/<YYYY-MM-DD>/<module>.log`
+    DateThenModule,
+}
+
+/// How long a file goes without a line before it is closed.
+pub(crate) const IDLE_CLOSE: Duration = Duration::from_secs(60);
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The longest file name a module gets, in bytes.
+const MAX_NAME: usize = 200;
+
+/// `module` as a single path component: `::` becomes `.` and anything else
+/// but ASCII letters, digits, `_` and `-` becomes `_`. Lines without a
+/// module go to `default`.
+pub(crate) fn sanitize(module: &str) -> String {
+    if module.is_empty() {
+        return "default".to_string();
+    }
+    let mut name: String = module
+        .split("::")
+        .map(|segment| {
+            let segment: String = segment.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect();
+            if segment.is_empty() {
+                "_".to_string()
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+    name.truncate(MAX_NAME);
+    name
+}
+
+/// The open handlers of a directory target, by path.
+pub(crate) struct Directory<H> {
+    root: PathBuf,
+    layout: DirLayout,
+    retention: u32,
+    day: String,
+    handlers: HashMap<PathBuf, (H, Instant)>,
+    swept: Instant,
+}
+
+impl<H> Directory<H> {
+    pub(crate) fn new(root: PathBuf, layout: DirLayout) -> Self {
+        Directory {
+            root,
+            layout,
+            retention: 0,
+            day: String::new(),
+            handlers: HashMap::new(),
+            swept: Instant::now(),
+        }
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub(crate) fn layout(&self) -> DirLayout {
+        self.layout
+    }
+
+    /// Keeps the files of the last `days` days, the current one included,
+    /// across the whole tree; 0 keeps everything. Applied on the next line
+    /// and at every change of day.
+    pub(crate) fn set_retention(&mut self, days: u32) {
+        self.retention = days;
+        self.day.clear();
+    }
+
+    /// Closes every file; each reopens on its next line.
+    pub(crate) fn close_all(&mut self) {
+        self.handlers.clear();
+    }
+
+    pub(crate) fn handlers(&self) -> impl Iterator<Item = &H> {
+        self.handlers.values().map(|(h, _)| h)
+    }
+
+    pub(crate) fn handlers_mut(&mut self) -> impl Iterator<Item = &mut H> {
+        self.handlers.values_mut().map(|(h, _)| h)
+    }
+
+    /// The file of a line of `module` on `day`.
+    pub(crate) fn path(&self, module: &str, day: &str) -> PathBuf {
+        let module = sanitize(module);
+        match self.layout {
+            DirLayout::ModuleThenDate => self.root.join(module).join(format!("{}.log", day)),
+            DirLayout::DateThenModule => self.root.join(day).join(format!("{}.log", module)),
+        }
+    }
+
+    /// The handler of a line of `module` logged at `time`, made by `open`
+    /// from its path when the file has none. A new day closes every file of
+    /// the old one and applies the retention.
+    pub(crate) fn handler(&mut self, module: &str, time: DateTime<Local>, open: impl FnOnce(&Path) -> H) -> &mut H {
+        let day = time.format(DATE_FORMAT).to_string();
+        if day != self.day {
+            self.handlers.clear();
+            self.prune(time.date_naive());
+            self.day = day;
+        }
+        if self.swept.elapsed() >= IDLE_CLOSE {
+            self.handlers.retain(|_, (_, used)| used.elapsed() < IDLE_CLOSE);
+            self.swept = Instant::now();
+        }
+        let path = self.path(module, &self.day);
+        let (handler, used) = self.handlers.entry(path).or_insert_with_key(|path| (open(path), Instant::now()));
+        *used = Instant::now();
+        handler
+    }
+
+    /// Deletes the files dated before the retention window ending on `today`.
+    fn prune(&self, today: NaiveDate) {
+        if self.retention == 0 {
+            return;
+        }
+        let oldest = today - Days::days(self.retention as i64 - 1);
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match self.layout {
+                DirLayout::DateThenModule => {
+                    if path.is_dir() && dated(&path, None).is_some_and(|d| d < oldest) {
+                        deleted(&path, fs::remove_dir_all(&path));
+                    }
+                }
+                DirLayout::ModuleThenDate => {
+                    let Ok(files) = fs::read_dir(&path) else {
+                        continue;
+                    };
+                    for file in files.flatten().map(|f| f.path()) {
+                        if file.is_file() && dated(&file, Some("log")).is_some_and(|d| d < oldest) {
+                            deleted(&file, fs::remove_file(&file));
+                        }
+                    }
+                }
+            }
+        }
+        diagnostics::flush();
+    }
+}
+
+fn deleted(path: &Path, result: io::Result<()>) {
+    match result {
+        Ok(()) => diagnostics::report(Category::Pruned, Some(path), format!("pruned {}", path.display())),
+        Err(e) => diagnostics::report(Category::DeleteFailed, Some(path), format!("cannot delete {}: {}", path.display(), e)),
+    }
+}
+
+/// The date `path` is named after, with `extension` if given; names that
+/// aren't a date are never touched by the retention.
+fn dated(path: &Path, extension: Option<&str>) -> Option<NaiveDate> {
+    if path.extension().and_then(|e| e.to_str()) != extension {
+        return None;
+    }
+    let stem = if extension.is_some() { path.file_stem() } else { path.file_name() };
+    NaiveDate::parse_from_str(stem?.to_str()?, DATE_FORMAT).ok()
+}
@@ -0,0 +1,70 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stable event IDs that alerting can key on whatever a message says.
+//!
+//! `warn!(event: "POOL_EXHAUSTED", "pool", name, "exhausted")` gives the
+//! line the ID: text lines end with `event=POOL_EXHAUSTED`, unless the
+//! formatter places `{event}` itself; JSON lines get an `event` key, see
+//! `json`; `ParsedRecord::event` reads it back to filter parsed lines.
+//! With `Logger::set_event_registry`, `Logger::validate` warns through
+//! `diagnostics` about the IDs logged so far that aren't registered; debug
+//! builds only keep track of them.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::warn;
+//!
+//! tklog::LOG.set_event_registry(&[("POOL_EXHAUSTED", "no free connection in the pool")]);
+//! warn!(event: "POOL_EXHAUSTED", "pool", "db", "exhausted");
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::diagnostics::{self, Category};
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Events {
+    registry: HashMap<String, String>,
+    seen: BTreeSet<&'static str>,
+}
+
+impl Events {
+    pub(crate) fn set_registry(&mut self, events: &[(&str, &str)]) {
+        self.registry = events.iter().map(|(id, d)| (id.to_string(), d.to_string())).collect();
+    }
+
+    pub(crate) fn description(&self, id: &str) -> Option<&str> {
+        self.registry.get(id).map(String::as_str)
+    }
+
+    pub(crate) fn seen(&mut self, id: &'static str) {
+        if cfg!(debug_assertions) {
+            self.seen.insert(id);
+        }
+    }
+
+    /// Warns about the IDs seen that the registry, if set, doesn't have.
+    pub(crate) fn check(&self) {
+        if self.registry.is_empty() {
+            return;
+        }
+        for id in self.seen.iter().filter(|id| !self.registry.contains_key(**id)) {
+            diagnostics::report(Category::Config, None, format!("event id `{}` is not in the event registry", id));
+        }
+        diagnostics::flush();
+    }
+}
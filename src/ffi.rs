@@ -0,0 +1,90 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A C ABI over the global logger, for C plugins of a Rust host; needs the
+//! `ffi` feature. The declarations are in `include/tklog.h`.
+//!
+//! Levels are the `LEVEL` discriminants, 1 for Trace to 6 for Fatal; any
+//! other level logs nothing. Strings are NUL-terminated and read as UTF-8,
+//! invalid bytes replaced; NULL reads as the empty string. A panic stops
+//! at the boundary: the call then does nothing, or returns false.
+
+use std::{
+    borrow::Cow,
+    ffi::{c_char, CStr},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use crate::{global_for_line, intern::intern, reentrant, LEVEL, PRINTMODE};
+
+/// The level of a line, `Off` being none.
+fn level(level: u8) -> Option<LEVEL> {
+    LEVEL::try_from(level).ok().filter(|&l| l != LEVEL::Off)
+}
+
+/// # Safety
+/// `s` is NULL or points to a NUL-terminated string that outlives `'a`.
+unsafe fn text<'a>(s: *const c_char) -> Cow<'a, str> {
+    if s.is_null() {
+        return Cow::Borrowed("");
+    }
+    CStr::from_ptr(s).to_string_lossy()
+}
+
+/// Logs `msg` from `module` at `level`, with `file` and `line` when the
+/// format asks for them.
+///
+/// # Safety
+/// Every pointer is NULL or points to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tklog_log(level: u8, module: *const c_char, file: *const c_char, line: u32, msg: *const c_char) {
+    let Some(level) = self::level(level).filter(|&l| crate::above_floor(l)) else {
+        return;
+    };
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let module = text(module);
+        if reentrant(level, &module, || text(msg).into_owned()) {
+            return;
+        }
+        let mut logger = global_for_line();
+        if logger.get_level(&module) > level {
+            return;
+        }
+        let (file, line) = if logger.is_file_line(level, &module) { (text(file), line) } else { (Cow::Borrowed(""), 0) };
+        let s = logger.fmt(&module, level, &file, line, text(msg).into_owned());
+        if s.is_empty() {
+            return;
+        }
+        if logger.mode == PRINTMODE::DELAY {
+            logger.log(level, intern(&module), s);
+        } else {
+            logger.safeprint(level, &module, s);
+        }
+    }));
+}
+
+/// True if a line of `module` at `level` would be logged, for callers that
+/// skip building the message otherwise.
+///
+/// # Safety
+/// `module` is NULL or points to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tklog_enabled(level: u8, module: *const c_char) -> bool {
+    let Some(level) = self::level(level) else {
+        return false;
+    };
+    crate::above_floor(level) && catch_unwind(AssertUnwindSafe(|| global_for_line().get_level(&text(module)) <= level)).unwrap_or(false)
+}
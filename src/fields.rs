@@ -0,0 +1,275 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fields added to every line by `Logger::set_dynamic_fields`, or to one
+//! line after a `;` in the logging macros.
+//!
+//! The callback runs once per emitted line, after the level checks, so it
+//! can read per-request state such as a thread-local tenant:
+//!
+//! ### Example
+//! ```no_run
+//! use std::cell::RefCell;
+//! use tklog::sync::Logger;
+//!
+//! thread_local! {
+//!     static TENANT: RefCell<Option<String>> = const { RefCell::new(None) };
+//! }
+//!
+//! let mut log = Logger::new();
+//! log.set_dynamic_fields(Box::new(|fields| {
+//!     TENANT.with(|t| {
+//!         if let Some(t) = &*t.borrow() {
+//!             fields.insert("tenant", t);
+//!         }
+//!     })
+//! }));
+//! ```
+//!
+//! In a macro, `key = value` and `key = %value` take the `Display` of the
+//! value and `key = ?value` its `Debug`; a key is a name or a string. They
+//! come before the `context` and the dynamic fields, and win over a field
+//! of theirs with the same key. Like the arguments, they are evaluated only when the line is logged.
+//!
+//! ```no_run
+//! use tklog::info;
+//!
+//! let request_id = "abc";
+//! let retry: Option<u32> = Some(3);
+//! info!("request done"; user_id = 42, req = %request_id, retry = ?retry, "http.status" = 200);
+//! // [INFO] ... request done user_id=42 req=abc retry=Some(3) http.status=200
+//! ```
+//!
+//! `Logger::set_max_fields` and `Logger::set_max_field_value_len` bound
+//! the fields of a line, for a loop that keeps adding to the `context`:
+//! the fields over the count are dropped and the longer values cut, before
+//! the line is laid out, so JSON and `key=value` lines stay whole. A line
+//! they changed ends with `_truncated_fields=N`, the fields dropped or cut.
+
+use std::{
+    collections::BTreeSet,
+    fmt::{Display, Write},
+    sync::Mutex,
+};
+
+use crate::{
+    diagnostics::{self, Category},
+    guard::Guarded,
+};
+
+/// The field that tells how many fields `FieldLimits` dropped or cut.
+pub const TRUNCATED_FIELDS: &str = "_truncated_fields";
+
+/// How many distinct rewritten keys are warned about, for a callback making
+/// up a new key per line.
+const WARN_LIMIT: usize = 64;
+
+static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// The callback of `Logger::set_dynamic_fields`.
+pub type DynamicFields = Box<dyn Fn(&mut FieldMap) + Send + Sync>;
+
+/// Key-value pairs in insertion order; inserting a key again replaces its value.
+///
+/// Keys are kept to ASCII letters, digits, `_`, `.` and `-`, so that the
+/// JSON and `key=value` lines read the same everywhere: any other character
+/// becomes `_`, an empty key `_`, with a warning the first time a key is
+/// rewritten.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldMap {
+    entries: Vec<(String, String)>,
+}
+
+impl FieldMap {
+    pub fn new() -> Self {
+        FieldMap::default()
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Display) -> &mut Self {
+        let key = machine_key(key.into());
+        let value = value.to_string();
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key, value)),
+        }
+        self
+    }
+
+    /// Removes `key`, returning its value.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let i = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(i).1)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds the fields of `other`: all of them with `replace`, else those
+    /// of the keys `self` doesn't have.
+    pub(crate) fn merge(&mut self, other: FieldMap, replace: bool) {
+        for (k, v) in other.entries {
+            match self.entries.iter_mut().find(|(key, _)| *key == k) {
+                Some(entry) if replace => entry.1 = v,
+                Some(_) => {}
+                None => self.entries.push((k, v)),
+            }
+        }
+    }
+
+    /// `message` with ` key=value` per field before its trailing newline;
+    /// values with spaces, quotes or `=` are quoted.
+    pub(crate) fn append_to(&self, mut message: String) -> String {
+        if self.entries.is_empty() {
+            return message;
+        }
+        let newline = message.ends_with('\n');
+        if newline {
+            message.pop();
+        }
+        for (k, v) in &self.entries {
+            if v.is_empty() || v.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+                let _ = write!(message, " {}={:?}", k, v);
+            } else {
+                let _ = write!(message, " {}={}", k, v);
+            }
+        }
+        if newline {
+            message.push('\n');
+        }
+        message
+    }
+}
+
+/// The bounds of the fields of a line, see the module docs; 0 is none.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct FieldLimits {
+    pub(crate) max_fields: usize,
+    pub(crate) max_value_len: usize,
+}
+
+impl FieldLimits {
+    /// The first `max_fields` of `fields`, with their values cut to
+    /// `max_value_len` bytes on a character boundary, then the
+    /// `TRUNCATED_FIELDS` marker if any was dropped or cut.
+    pub(crate) fn apply(self, mut fields: FieldMap) -> FieldMap {
+        let mut truncated = 0;
+        if self.max_fields > 0 && fields.entries.len() > self.max_fields {
+            truncated = fields.entries.len() - self.max_fields;
+            fields.entries.truncate(self.max_fields);
+        }
+        if self.max_value_len > 0 {
+            for (_, v) in fields.entries.iter_mut() {
+                if v.len() > self.max_value_len {
+                    let mut end = self.max_value_len;
+                    while !v.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    v.truncate(end);
+                    truncated += 1;
+                }
+            }
+        }
+        if truncated > 0 {
+            fields.entries.push((TRUNCATED_FIELDS.to_string(), truncated.to_string()));
+        }
+        fields
+    }
+}
+
+/// The fields of one line: `call`, the fields given to the macro, then,
+/// for the keys it doesn't have, the `context` if asked and the dynamic
+/// fields.
+pub(crate) fn of_line(mut call: FieldMap, context: bool, dynamic: Option<&Guarded<DynamicFields>>) -> FieldMap {
+    if context {
+        crate::context::add_to(&mut call);
+    }
+    if let Some(callback) = dynamic {
+        call.merge(collect(callback), false);
+    }
+    call
+}
+
+/// Runs `callback` on a fresh map. After a panic, reported on stderr, the
+/// line goes out without dynamic fields.
+pub(crate) fn collect(callback: &Guarded<DynamicFields>) -> FieldMap {
+    let mut fields = FieldMap::new();
+    match callback.call(|f| f(&mut fields)) {
+        Some(()) => fields,
+        None => FieldMap::new(),
+    }
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+/// `key` with the characters a field key may not have replaced, see `FieldMap`.
+pub(crate) fn machine_key(key: String) -> String {
+    if !key.is_empty() && key.chars().all(is_key_char) {
+        return key;
+    }
+    let safe: String = if key.is_empty() { "_".to_string() } else { key.chars().map(|c| if is_key_char(c) { c } else { '_' }).collect() };
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if warned.len() < WARN_LIMIT && warned.insert(key.clone()) {
+        diagnostics::report(Category::Config, None, format!("field key {:?} is written as `{}`", key, safe));
+    }
+    safe
+}
+
+/// The `FieldMap` of the `key = value` list after the `;` of a logging
+/// macro, see the module docs.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! fields_of {
+    (@key $key:ident) => {
+        stringify!($key)
+    };
+    (@key $key:literal) => {
+        $key
+    };
+    (@insert $map:ident,) => {};
+    (@insert $map:ident, $key:tt = ? $value:expr $(, $($rest:tt)*)?) => {
+        $map.insert($crate::fields_of!(@key $key), format_args!("{:?}", $value));
+        $crate::fields_of!(@insert $map, $($($rest)*)?);
+    };
+    (@insert $map:ident, $key:tt = % $value:expr $(, $($rest:tt)*)?) => {
+        $map.insert($crate::fields_of!(@key $key), &$value);
+        $crate::fields_of!(@insert $map, $($($rest)*)?);
+    };
+    (@insert $map:ident, $key:tt = $value:expr $(, $($rest:tt)*)?) => {
+        $map.insert($crate::fields_of!(@key $key), &$value);
+        $crate::fields_of!(@insert $map, $($($rest)*)?);
+    };
+    ($($fields:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::fields::FieldMap::new();
+        $crate::fields_of!(@insert map, $($fields)*);
+        map
+    }};
+}
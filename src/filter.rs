@@ -0,0 +1,77 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Filters that drop lines by more than their level, with `Logger::set_filter`:
+//!
+//! ```no_run
+//! use tklog::LEVEL;
+//!
+//! let mut log = tklog::sync::Logger::new();
+//! log.set_filter(Box::new(|r| !(r.level == LEVEL::Info && r.module == "app::http" && r.message.contains("healthcheck"))));
+//! ```
+//!
+//! A line is written only when every filter passes it. The filters see
+//! the line before it is laid out, on the thread that logs it, after the
+//! level checks; a line they drop costs no formatting. The file and line
+//! are those of the macro call. A filter that panics passes the line, and
+//! after three panics in a row is disabled until `try_recover`.
+
+use crate::{guard::Guarded, LEVEL};
+
+/// A line as the filters see it.
+#[derive(Clone, Copy, Debug)]
+pub struct LogRecord<'a> {
+    pub level: LEVEL,
+    pub module: &'a str,
+    pub file: &'a str,
+    pub line: u32,
+    /// The message, before the line is laid out around it.
+    pub message: &'a str,
+}
+
+pub type Filter = Box<dyn Fn(&LogRecord) -> bool + Send + Sync>;
+
+/// The filters of a logger.
+#[derive(Default)]
+pub(crate) struct Filters(Vec<Guarded<Filter>>);
+
+impl Filters {
+    pub(crate) fn push(&mut self, filter: Filter) {
+        self.0.push(Guarded::new("filter", filter));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether every filter passes `record`.
+    pub(crate) fn pass(&self, record: &LogRecord) -> bool {
+        self.0.iter().all(|g| g.call(|f| f(record)).unwrap_or(true))
+    }
+
+    /// `filter` if one of them is disabled, see `health`.
+    pub(crate) fn disabled(&self) -> Option<&'static str> {
+        self.0.iter().find_map(Guarded::disabled)
+    }
+
+    pub(crate) fn reset(&self) {
+        self.0.iter().for_each(Guarded::reset);
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{
+    diagnostics::{self, Category},
+    panic_reason,
+};
+
+/// Panics in a row after which a callback is no longer called.
+pub(crate) const MAX_PANICS: u32 = 3;
+
+/// How often a user callback has panicked in a row.
+#[derive(Debug, Default)]
+pub(crate) struct PanicCount(AtomicU32);
+
+impl PanicCount {
+    /// Runs `f`, the callback called `name`. A panic is reported on stderr
+    /// and gives `None`, as does every call once the callback has panicked
+    /// `MAX_PANICS` times in a row.
+    pub(crate) fn call<R>(&self, name: &str, f: impl FnOnce() -> R) -> Option<R> {
+        if self.0.load(Ordering::Relaxed) >= MAX_PANICS {
+            return None;
+        }
+        match catch_unwind(AssertUnwindSafe(f)) {
+            Ok(r) => {
+                self.0.store(0, Ordering::Relaxed);
+                Some(r)
+            }
+            Err(payload) => {
+                diagnostics::report(Category::CallbackPanic, None, format!("{} panicked: {}", name, panic_reason(&payload)));
+                if self.0.fetch_add(1, Ordering::Relaxed) + 1 == MAX_PANICS {
+                    diagnostics::report(Category::CallbackPanic, None, format!("{} panicked {} times in a row and is disabled until it is set again", name, MAX_PANICS));
+                }
+                None
+            }
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    /// Whether `call` no longer calls the callback.
+    pub(crate) fn is_disabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed) >= MAX_PANICS
+    }
+}
+
+/// A user callback with its panic count; setting the callback again makes
+/// a new one and so starts the count afresh.
+pub(crate) struct Guarded<F: ?Sized> {
+    name: &'static str,
+    panics: PanicCount,
+    f: F,
+}
+
+impl<F> Guarded<F> {
+    pub(crate) fn new(name: &'static str, f: F) -> Self {
+        Guarded { name, panics: PanicCount::default(), f }
+    }
+}
+
+impl<F: ?Sized> Guarded<F> {
+    /// `call` applied to the callback, see `PanicCount::call`.
+    pub(crate) fn call<R>(&self, call: impl FnOnce(&F) -> R) -> Option<R> {
+        self.panics.call(self.name, || call(&self.f))
+    }
+
+    /// The name of the callback if it is disabled, see `health`.
+    pub(crate) fn disabled(&self) -> Option<&'static str> {
+        self.panics.is_disabled().then_some(self.name)
+    }
+
+    /// Enables the callback again.
+    pub(crate) fn reset(&self) {
+        self.panics.reset();
+    }
+}
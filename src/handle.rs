@@ -14,11 +14,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::io;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use tokio::io::AsyncWriteExt;
-
-use crate::{asyncfile, syncfile, Format, LogContent, CUTMODE, LEVEL, MODE};
+use crate::{asyncfile, available_space, backupname::BackupTemplate, config::FileConfig, logerror::ErrorSink, metrics::Metrics, runtime::{Rt, Runtime}, syncfile, verify::TamperKey, CompressType, Format, LogContent, PrunePolicy, RotationEvent, CUTMODE, LEVEL, MODE};
 
 pub trait FileOption: Send + Sync {
     fn mode(&self) -> CUTMODE;
@@ -29,6 +31,11 @@ pub trait FileOption: Send + Sync {
     fn compress(&self) -> bool;
 }
 
+/// Whether the file of `option` rotates, as `FileHandler::is_rotating`.
+pub(crate) fn rotates(option: &dyn FileOption) -> bool {
+    option.mode() != CUTMODE::SIZE || option.size() > 0
+}
+
 pub struct FileOptionType {
     pub mode: CUTMODE,
     pub timemode: MODE,
@@ -39,6 +46,10 @@ pub struct FileOptionType {
 }
 
 impl FileOptionType {
+    pub fn from_config(fc: &FileConfig) -> Self {
+        FileOptionType::new(fc.cutmode, fc.timemode, &fc.filename, fc.maxsize, fc.maxbackups, fc.compress)
+    }
+
     pub fn new(
         mode: CUTMODE,
         timemode: MODE,
@@ -128,6 +139,53 @@ impl FileOption for FileTimeMode {
     }
 }
 
+/// Rotation by time and by size, whichever comes first.
+pub struct FileMixedMode {
+    filename: String,
+    mode: MODE,
+    max_size: u64,
+    max_backups: u32,
+    compress: bool,
+}
+
+impl FileMixedMode {
+    pub fn new(filename: &str, mode: MODE, maxsize: u64, maxbackups: u32, compress: bool) -> Self {
+        FileMixedMode {
+            filename: filename.to_string(),
+            mode,
+            max_size: maxsize,
+            max_backups: maxbackups,
+            compress,
+        }
+    }
+}
+
+impl FileOption for FileMixedMode {
+    fn mode(&self) -> CUTMODE {
+        CUTMODE::MIXED
+    }
+
+    fn timemode(&self) -> MODE {
+        self.mode
+    }
+
+    fn filename(&self) -> String {
+        self.filename.clone()
+    }
+
+    fn size(&self) -> u64 {
+        self.max_size
+    }
+
+    fn maxbackups(&self) -> u32 {
+        self.max_backups
+    }
+
+    fn compress(&self) -> bool {
+        self.compress
+    }
+}
+
 pub struct FileSizeMode {
     filename: String, //Log file path
     max_size: u64,    //Maximum size for each log file to be saved
@@ -172,6 +230,90 @@ impl FileSizeMode {
     }
 }
 
+/// Logger-wide settings shared by every file handler of a logger.
+#[derive(Clone, Debug)]
+pub struct FileSettings {
+    pub prune_policy: PrunePolicy,
+    /// Replaces the `maxbackups` of every handler, see `Logger::set_max_backups`.
+    pub max_backups: Option<u32>,
+    /// Deletes backups cut longer ago, see `Logger::set_backup_max_age`.
+    pub backup_max_age: Option<Duration>,
+    pub compress_type: CompressType,
+    /// 0 (fastest) to 9 (smallest).
+    pub compress_level: u32,
+    /// Keeps the raw backup next to its `.gz`, see
+    /// `Logger::set_compress_keep_original`.
+    pub compress_keep_original: bool,
+    /// The newest backups left raw, see `Logger::set_compress_after`.
+    pub compress_after: u32,
+    /// Keep the raw backup when a 64 KiB sample compresses to more than this
+    /// fraction of its size. `None` always compresses.
+    pub compress_skip_ratio: Option<f64>,
+    /// Compression needs `backup size × factor` bytes free on the backup's
+    /// filesystem, otherwise the raw backup is kept.
+    pub compress_space_factor: f64,
+    /// Free bytes on the filesystem of a path; `tklog::available_space` by default.
+    pub space_probe: fn(&Path) -> io::Result<u64>,
+    pub rotation_handler: Option<fn(&RotationEvent)>,
+    /// Chains every line with an HMAC, see `tklog::verify`.
+    pub tamper_key: Option<TamperKey>,
+    /// Writes the files compressed with a sync flush at this interval, see
+    /// `Logger::set_live_compression`.
+    pub live_compression: Option<(CompressType, Duration)>,
+    /// Names the backups, see `Logger::set_backup_name_template`.
+    pub backup_template: Option<BackupTemplate>,
+    /// Bytes a file gathers before it is written, 0 (the default) writes
+    /// every line; see `Logger::set_buffer_size`.
+    pub buffer_size: usize,
+    /// Unix permission bits of the files created, see `Logger::set_file_mode`.
+    pub file_mode: Option<u32>,
+    /// Appends and rotates under an advisory lock, for files several
+    /// processes write; see `Logger::set_shared_file`.
+    pub shared: bool,
+    /// Where the file failures go, see `Logger::set_error_handler`.
+    pub(crate) errors: ErrorSink,
+    /// What the files count into, see `Logger::metrics`.
+    pub(crate) metrics: Metrics,
+}
+
+impl Default for FileSettings {
+    fn default() -> Self {
+        FileSettings {
+            prune_policy: PrunePolicy::ByFile,
+            max_backups: None,
+            backup_max_age: None,
+            compress_type: CompressType::Gzip,
+            compress_level: 6,
+            compress_keep_original: false,
+            compress_after: 0,
+            compress_skip_ratio: None,
+            compress_space_factor: 1.0,
+            space_probe: available_space,
+            rotation_handler: None,
+            tamper_key: None,
+            live_compression: None,
+            backup_template: None,
+            buffer_size: 0,
+            file_mode: None,
+            shared: false,
+            errors: ErrorSink::default(),
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+impl FileSettings {
+    /// The file a handler of `filename` writes to: `filename.gz` with live
+    /// compression.
+    pub(crate) fn live_path(&self, filename: &Path) -> PathBuf {
+        let mut path = filename.as_os_str().to_owned();
+        if self.live_compression.is_some() {
+            path.push(".gz");
+        }
+        PathBuf::from(path)
+    }
+}
+
 pub struct FmtHandler {
     level: LEVEL,              // log level
     format: u8,                // log format
@@ -191,9 +333,7 @@ impl FmtHandler {
     }
 
     pub async fn async_console(&self, s: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let mut stdout = tokio::io::stdout();
-        tokio::io::stdout().write_all(s.as_bytes()).await?;
-        stdout.flush().await?;
+        Rt::stdout(s.as_bytes()).await?;
         Ok(())
     }
 
@@ -220,6 +360,10 @@ impl FmtHandler {
         self.formatter = Some(formatter);
     }
 
+    pub fn clear_formatter(&mut self) {
+        self.formatter = None;
+    }
+
     pub fn get_formatter(&self) -> Option<&String> {
         self.formatter.as_ref()
     }
@@ -246,6 +390,8 @@ pub struct FHandler {
     file_handler: Option<syncfile::FileHandler>,
     async_file_handler: Option<asyncfile::FileHandler>,
     async_console: Option<Console>,
+    /// Lines of this handler currently go to the default file, see `degrade`.
+    degraded: bool,
 }
 
 impl FHandler {
@@ -254,6 +400,7 @@ impl FHandler {
             file_handler: None,
             async_file_handler: None,
             async_console: None,
+            degraded: false,
         }
     }
 
@@ -262,6 +409,7 @@ impl FHandler {
             file_handler: Some(*fh),
             async_file_handler: None,
             async_console: None,
+            degraded: false,
         }
     }
 
@@ -270,44 +418,232 @@ impl FHandler {
             file_handler: None,
             async_file_handler: Some(*fh),
             async_console: Some(Console::new()),
+            degraded: false,
         }
     }
 
     pub fn print(&mut self, console: bool, s: LogContent) -> io::Result<()> {
+        self.write_line(console, &s)
+    }
+
+    pub fn write_line(&mut self, console: bool, s: &LogContent) -> io::Result<()> {
         if console {
-            print!("{}", s.console_body.unwrap_or(s.file_body.clone()));
+            let body = s.console_body.as_ref().unwrap_or(&s.file_body);
+            #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+            crate::wasm::print(body, s.stderr);
+            #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+            if s.stderr {
+                // What stdout holds goes first, for the order on a shared terminal.
+                let _ = std::io::Write::flush(&mut io::stdout());
+                eprint!("{}", body);
+            } else {
+                print!("{}", body);
+            }
         }
         if let Some(f) = self.file_handler.as_mut() {
             f.write(s.file_body.as_bytes())?;
+            self.degraded = false;
+        }
+        Ok(())
+    }
+
+    /// Writes `body` to the file only, for a tee file.
+    pub(crate) fn write_body(&mut self, body: &str) -> io::Result<()> {
+        if let Some(f) = self.file_handler.as_mut() {
+            f.write(body.as_bytes())?;
         }
         Ok(())
     }
 
     pub async fn async_print(&mut self, console: bool, s: LogContent) -> io::Result<()> {
+        self.async_write_line(console, &s).await
+    }
+
+    pub async fn async_write_line(&mut self, console: bool, s: &LogContent) -> io::Result<()> {
         if console {
+            let body = s.console_body.as_ref().unwrap_or(&s.file_body);
             if self.async_console.is_none() {
                 let cs = Console::new();
-                let _ = cs
-                    .async_print(s.console_body.unwrap_or(s.file_body.clone()).as_ref())
-                    .await;
+                let _ = cs.async_print(body, s.stderr).await;
                 self.async_console = Some(cs)
             } else if let Some(c) = self.async_console.as_mut() {
-                let _ = c.async_print(s.console_body.unwrap_or(s.file_body.clone()).as_ref()).await;
+                let _ = c.async_print(body, s.stderr).await;
             }
         }
         if let Some(f) = self.async_file_handler.as_mut() {
             f.write(s.file_body.as_bytes()).await?;
+            self.degraded = false;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn async_write_body(&mut self, body: &str) -> io::Result<()> {
+        if let Some(f) = self.async_file_handler.as_mut() {
+            f.write(body.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    /// The start of the current period of the file, see `rotation`.
+    pub(crate) fn startsec(&self) -> Option<u64> {
+        self.file_handler.as_ref().map(|f| f.startsec()).or_else(|| self.async_file_handler.as_ref().map(|f| f.startsec()))
+    }
+
+    pub(crate) fn set_grouped(&mut self, group: Option<u64>) {
+        if let Some(f) = self.file_handler.as_mut() {
+            f.set_grouped(group);
+        }
+        if let Some(f) = self.async_file_handler.as_mut() {
+            f.set_grouped(group);
+        }
+    }
+
+    pub(crate) fn rotate_as(&mut self, stamp: u64, startsec: u64, empty_backups: bool) -> io::Result<()> {
+        match self.file_handler.as_mut() {
+            Some(f) => f.rotate_as(stamp, startsec, empty_backups),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn async_rotate_as(&mut self, stamp: u64, startsec: u64, empty_backups: bool) -> io::Result<()> {
+        match self.async_file_handler.as_mut() {
+            Some(f) => f.rotate_as(stamp, startsec, empty_backups).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Closes the file, if open, to give its descriptor back; the next
+    /// write reopens it.
+    pub(crate) fn release_file(&mut self) -> bool {
+        self.file_handler.as_mut().is_some_and(|f| f.release())
+    }
+
+    pub(crate) async fn async_release_file(&mut self) -> bool {
+        match self.async_file_handler.as_mut() {
+            Some(f) => f.release().await,
+            None => false,
+        }
+    }
+
+    /// Writes out what the file holds and opens its path again, for a
+    /// file renamed by logrotate to be written anew.
+    pub(crate) fn reopen_file(&mut self) -> io::Result<()> {
+        match self.file_handler.as_mut() {
+            Some(f) => f.reopen(),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn async_reopen_file(&mut self) -> io::Result<()> {
+        match self.async_file_handler.as_mut() {
+            Some(f) => f.reopen().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Writes out the file and closes it, see `set_log_path`.
+    pub(crate) fn retire_file(&mut self, rotate: bool) {
+        if let Some(f) = self.file_handler.as_mut() {
+            f.retire(rotate);
+        }
+    }
+
+    pub(crate) async fn async_retire_file(&mut self, rotate: bool) {
+        if let Some(f) = self.async_file_handler.as_mut() {
+            f.retire(rotate).await;
+        }
+    }
+
+    /// Marks the lines of this handler as diverted to the default file until
+    /// a write succeeds again; true the first time, when it's worth a warning.
+    pub(crate) fn degrade(&mut self) -> bool {
+        !std::mem::replace(&mut self.degraded, true)
+    }
+
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+
+    /// Whether the rotation handler of the file is disabled after panicking.
+    pub(crate) fn rotation_handler_disabled(&self) -> bool {
+        self.file_handler.as_ref().is_some_and(|f| f.rotation_panics().is_disabled())
+            || self.async_file_handler.as_ref().is_some_and(|f| f.rotation_panics().is_disabled())
+    }
+
+    /// Enables the rotation handler again and, if the lines are diverted,
+    /// reopens the file, see `health`.
+    pub(crate) fn recover(&mut self) -> io::Result<()> {
+        if let Some(f) = self.file_handler.as_mut() {
+            f.rotation_panics().reset();
+            if self.degraded {
+                f.reopen()?;
+                self.degraded = false;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn async_recover(&mut self) -> io::Result<()> {
+        if let Some(f) = self.async_file_handler.as_mut() {
+            f.rotation_panics().reset();
+            if self.degraded {
+                f.reopen().await?;
+                self.degraded = false;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(f) = self.file_handler.as_mut() {
+            f.flush()?;
+        }
+        Ok(())
+    }
+
+    pub async fn async_flush(&mut self) -> io::Result<()> {
+        if let Some(f) = self.async_file_handler.as_mut() {
+            f.flush().await?;
         }
         Ok(())
     }
 
     pub async fn async_console(&self, s: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let mut stdout = tokio::io::stdout();
-        tokio::io::stdout().write_all(s.as_bytes()).await?;
-        stdout.flush().await?;
+        Rt::stdout(s.as_bytes()).await?;
         Ok(())
     }
 
+    pub fn file_config(&self) -> Option<FileConfig> {
+        if let Some(f) = &self.file_handler {
+            return Some(f.file_config());
+        }
+        self.async_file_handler.as_ref().map(|f| f.file_config())
+    }
+
+    /// Returns the file name of the first rotating handler, if any.
+    pub fn rotating_file(&self) -> Option<String> {
+        if let Some(f) = &self.file_handler {
+            if f.is_rotating() {
+                return Some(f.get_file_name());
+            }
+        }
+        if let Some(f) = &self.async_file_handler {
+            if f.is_rotating() {
+                return Some(f.get_file_name());
+            }
+        }
+        None
+    }
+
+    pub fn set_file_settings(&mut self, settings: &FileSettings) {
+        if let Some(f) = self.file_handler.as_mut() {
+            f.set_settings(settings.clone());
+        }
+        if let Some(f) = self.async_file_handler.as_mut() {
+            f.set_settings(settings.clone());
+        }
+    }
+
     pub fn set_file_handler(&mut self, filehandler: syncfile::FileHandler) {
         self.file_handler = Some(filehandler)
     }
@@ -322,10 +658,13 @@ impl Console {
     pub fn new() -> Self {
         Console {}
     }
-    pub async fn async_print(&self, s: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let mut stdout = tokio::io::stdout();
-        tokio::io::stdout().write_all(s.as_bytes()).await?;
-        stdout.flush().await?;
+    /// Writes `s` to stdout, or to stderr once stdout is flushed.
+    pub async fn async_print(&self, s: &str, stderr: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if stderr {
+            Rt::stderr(s.as_bytes()).await?;
+        } else {
+            Rt::stdout(s.as_bytes()).await?;
+        }
         Ok(())
     }
 }
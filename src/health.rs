@@ -0,0 +1,71 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What keeps a logger from working as configured, for an ops endpoint or
+//! a watchdog.
+//!
+//! `Logger::health` lists the reasons a logger is degraded: its DELAY queue
+//! consumer has stopped, a module or level file can't be reopened and its
+//! lines go to the default file, or a callback was disabled after
+//! panicking. `Logger::try_recover` restarts the consumer, reopens the
+//! files and enables the callbacks again, and errs with what is left.
+//!
+//! ### Example
+//! ```no_run
+//! let health = tklog::LOG.health();
+//! if !health.ok {
+//!     eprintln!("logging degraded: {:?}", health.degraded_reasons);
+//!     let _ = tklog::LOG.try_recover();
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::guard::MAX_PANICS;
+
+/// The state of a logger, see the module docs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Health {
+    /// No reason to report.
+    pub ok: bool,
+    pub degraded_reasons: Vec<String>,
+}
+
+impl Health {
+    pub(crate) fn new(reasons: Vec<Degradation>) -> Self {
+        Health { ok: reasons.is_empty(), degraded_reasons: reasons.iter().map(ToString::to_string).collect() }
+    }
+}
+
+/// A way a logger degrades; each has its reason here.
+pub(crate) enum Degradation {
+    /// The DELAY queue consumer has stopped, on a panic.
+    QueueStopped,
+    /// A file can't be reopened and its lines go to the default file.
+    Diverted(String),
+    /// A callback panicked `MAX_PANICS` times in a row.
+    CallbackDisabled(&'static str),
+}
+
+impl fmt::Display for Degradation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Degradation::QueueStopped => write!(f, "the queue consumer has stopped; queued lines are dropped"),
+            Degradation::Diverted(filename) => write!(f, "{} cannot be reopened; its lines go to the default file", filename),
+            Degradation::CallbackDisabled(name) => write!(f, "the {} panicked {} times in a row and is disabled", name, MAX_PANICS),
+        }
+    }
+}
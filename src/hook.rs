@@ -0,0 +1,82 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hooks called with every line, e.g. to count lines per level or page on
+//! the first Fatal, with `Logger::add_hook`:
+//!
+//! ```no_run
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//! use tklog::LEVEL;
+//!
+//! static ERRORS: AtomicU64 = AtomicU64::new(0);
+//!
+//! let mut log = tklog::sync::Logger::new();
+//! log.add_hook(Box::new(|level, _module, _line| {
+//!     if level >= LEVEL::Error {
+//!         ERRORS.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! }));
+//! ```
+//!
+//! A hook gets the level, the module and the file body of each line once
+//! it is laid out, on the thread that logs it, whether the console and the
+//! files take the line or not; in `PRINTMODE::DELAY` too, before the line
+//! is queued. An async logger that would lay lines out on the queue
+//! consumer, see `FormatStage::Worker`, lays them out on the call site
+//! while it has hooks.
+//!
+//! Hooks run with the logger taken. A line a hook logs is not written
+//! through the logger, which would deadlock: as from a formatter, Warn and
+//! above go to stderr and the rest are dropped. A hook that panics is
+//! disabled after three panics in a row, until `try_recover`.
+
+use crate::{guard::Guarded, LEVEL};
+
+pub type Hook = Box<dyn Fn(LEVEL, &str, &str) + Send + Sync>;
+
+/// The hooks of a logger.
+#[derive(Default)]
+pub(crate) struct Hooks(Vec<Guarded<Hook>>);
+
+impl Hooks {
+    pub(crate) fn push(&mut self, hook: Hook) {
+        self.0.push(Guarded::new("hook", hook));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Calls every hook with the line `body`.
+    pub(crate) fn call(&self, level: LEVEL, module: &str, body: &str) {
+        for g in &self.0 {
+            g.call(|f| f(level, module, body));
+        }
+    }
+
+    /// `hook` if one of them is disabled, see `health`.
+    pub(crate) fn disabled(&self) -> Option<&'static str> {
+        self.0.iter().find_map(Guarded::disabled)
+    }
+
+    pub(crate) fn reset(&self) {
+        self.0.iter().for_each(Guarded::reset);
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! One owner for the configuration of the global logger, see
+//! `tklog::init_once`.
+//!
+//! The first `init_once` of the process configures the logger behind `LOG`
+//! and the macros; later ones fail with where that happened and what it
+//! set up, instead of quietly overwriting it. Logging itself works whether
+//! or not anything was initialized. `AsyncLoggerBuilder::apply_async_global`
+//! does the same for the logger behind `ASYNC_LOG`.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::LEVEL;
+//!
+//! let log = tklog::init_once(|log| {
+//!     log.set_level(LEVEL::Info).set_cutmode_by_size("app.log", 1 << 20, 5, true);
+//! })
+//! .unwrap();
+//! println!("configured at {}", log.location());
+//! ```
+
+use std::{fmt, panic::Location, sync::Mutex};
+
+use crate::{
+    bridge::{self, LogBridge},
+    global, global_async, sync, Async,
+};
+
+static INITIALIZED: Mutex<Option<&'static Location<'static>>> = Mutex::new(None);
+
+static ASYNC_INITIALIZED: Mutex<Option<&'static Location<'static>>> = Mutex::new(None);
+
+/// Proof of configuring the global logger.
+#[derive(Clone, Copy, Debug)]
+pub struct Handle {
+    location: &'static Location<'static>,
+}
+
+impl Handle {
+    /// Where the configuration was made.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// The global logger.
+    pub fn log(&self) -> &'static sync::Log {
+        &crate::LOG
+    }
+}
+
+/// Proof of configuring the global async logger.
+#[derive(Clone, Copy, Debug)]
+pub struct AsyncHandle {
+    location: &'static Location<'static>,
+}
+
+impl AsyncHandle {
+    /// Where the configuration was made.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// The global async logger.
+    pub fn log(&self) -> &'static Async::Log {
+        &crate::ASYNC_LOG
+    }
+}
+
+/// The global logger is configured already.
+#[derive(Clone, Debug)]
+pub struct AlreadyInitialized {
+    /// Where the configuration in place was made.
+    pub location: &'static Location<'static>,
+    /// Its `describe()`.
+    pub config: String,
+}
+
+impl fmt::Display for AlreadyInitialized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tklog is already initialized at {}:\n{}", self.location, self.config)
+    }
+}
+
+impl std::error::Error for AlreadyInitialized {}
+
+pub(crate) fn init_once<F: FnOnce(&mut sync::Logger)>(location: &'static Location<'static>, configure: F) -> Result<Handle, AlreadyInitialized> {
+    let mut initialized = INITIALIZED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(first) = *initialized {
+        return Err(AlreadyInitialized { location: first, config: global().describe() });
+    }
+    configure_global(configure);
+    *initialized = Some(location);
+    Ok(Handle { location })
+}
+
+pub(crate) fn force_reconfigure<F: FnOnce(&mut sync::Logger)>(location: &'static Location<'static>, configure: F) -> Handle {
+    let mut initialized = INITIALIZED.lock().unwrap_or_else(|e| e.into_inner());
+    configure_global(configure);
+    *initialized = Some(location);
+    Handle { location }
+}
+
+/// Takes the configuration of the global async logger for `location`,
+/// before it is made: a second taker fails at once, even while the first
+/// still configures.
+pub(crate) async fn claim_async(location: &'static Location<'static>) -> Result<AsyncHandle, AlreadyInitialized> {
+    let first = {
+        let mut initialized = ASYNC_INITIALIZED.lock().unwrap_or_else(|e| e.into_inner());
+        let first = *initialized;
+        initialized.get_or_insert(location);
+        first
+    };
+    match first {
+        Some(first) => Err(AlreadyInitialized { location: first, config: global_async().await.describe() }),
+        None => Ok(AsyncHandle { location }),
+    }
+}
+
+fn configure_global<F: FnOnce(&mut sync::Logger)>(configure: F) {
+    let mut log = global();
+    configure(&mut log);
+    bridge::refresh(LogBridge::Sync, log.lowest_level());
+}
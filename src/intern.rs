@@ -0,0 +1,48 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Module names as `&'static str`, so a queued line carries its module
+//! without copying it. Names given at runtime, such as the targets of the
+//! `log` crate or of C callers, are leaked once, up to `MAX_NAMES`; past
+//! that a name is copied per line instead.
+
+use std::{borrow::Cow, collections::HashSet, sync::RwLock};
+
+use once_cell::sync::Lazy;
+
+/// The names leaked at most.
+const MAX_NAMES: usize = 1024;
+
+static NAMES: Lazy<RwLock<HashSet<&'static str>>> = Lazy::new(RwLock::default);
+
+pub(crate) fn intern(name: &str) -> Cow<'static, str> {
+    if name.is_empty() {
+        return Cow::Borrowed("");
+    }
+    if let Some(s) = NAMES.read().unwrap_or_else(|e| e.into_inner()).get(name) {
+        return Cow::Borrowed(s);
+    }
+    let mut names = NAMES.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(s) = names.get(name) {
+        return Cow::Borrowed(s);
+    }
+    if names.len() >= MAX_NAMES {
+        return Cow::Owned(name.to_string());
+    }
+    let s: &'static str = Box::leak(name.into());
+    names.insert(s);
+    Cow::Borrowed(s)
+}
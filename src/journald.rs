@@ -0,0 +1,222 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lines sent to journald as structured records over its native protocol;
+//! needs the `journald` feature.
+//!
+//! With `Logger::set_journald(true)` every line goes to journald instead of
+//! the console and the files, with the fields `PRIORITY`, from the level,
+//! `CODE_FILE` and `CODE_LINE`, from the macro call, `TARGET`, the module,
+//! and `MESSAGE`, the formatted line. A record too large for one datagram
+//! is passed in a sealed memfd, as the protocol asks. While the socket
+//! isn't there, as outside systemd, or can't take a record, the line goes
+//! to the console and the files as without journald, and a
+//! `Category::Journald` diagnostic tells why. The socket is opened again
+//! at most once a second meanwhile.
+//!
+//! ### Example
+//! ```no_run
+//! let mut log = tklog::sync::Logger::new();
+//! log.set_journald(true);
+//! ```
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+use crate::{
+    diagnostics::{self, Category},
+    preset::journald_priority,
+    LEVEL,
+};
+
+/// The socket of `Logger::set_journald`.
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// How long a failed socket is left closed before it is opened again.
+const RETRY: Duration = Duration::from_secs(1);
+
+/// The journald of a logger: its socket, if open.
+pub(crate) struct Journald {
+    path: PathBuf,
+    #[cfg(unix)]
+    socket: Option<UnixDatagram>,
+    /// When a failed socket may be opened again.
+    retry_at: Option<Instant>,
+}
+
+impl Journald {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Journald {
+            path,
+            #[cfg(unix)]
+            socket: None,
+            retry_at: None,
+        }
+    }
+
+    /// Sends the line `body` of `level` and `module`, written at `source`;
+    /// false when it is refused or the socket is failing, for the line to go
+    /// to the console and files.
+    pub(crate) fn send(&mut self, level: LEVEL, module: &str, source: Option<&(String, u32)>, body: &str) -> bool {
+        if self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return false;
+        }
+        let record = record(level, module, source, body);
+        match self.send_record(&record) {
+            Ok(()) => {
+                self.retry_at = None;
+                true
+            }
+            Err(e) => {
+                self.failed(e);
+                false
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn send_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => {
+                let socket = UnixDatagram::unbound()?;
+                socket.set_nonblocking(true)?;
+                self.socket.insert(socket)
+            }
+        };
+        match socket.send_to(record, &self.path) {
+            Ok(_) => Ok(()),
+            #[cfg(target_os = "linux")]
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EMSGSIZE) | Some(libc::ENOBUFS)) => memfd::send(socket, &self.path, record),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn send_record(&mut self, _record: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "journald needs a unix system"))
+    }
+
+    fn failed(&mut self, e: io::Error) {
+        #[cfg(unix)]
+        {
+            self.socket = None;
+        }
+        self.retry_at = Some(Instant::now() + RETRY);
+        diagnostics::report(Category::Journald, Some(Path::new(&self.path)), format!("journald: {}; lines go to the console and files", e));
+    }
+}
+
+/// The record of a line in the native protocol: a `KEY=value` line per
+/// field, or, for a value with newlines, the key, its length as 64-bit
+/// little endian and the value.
+pub(crate) fn record(level: LEVEL, module: &str, source: Option<&(String, u32)>, body: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + module.len() + 64);
+    // The number of the `<N>` prefix journald reads from stdout.
+    let priority = journald_priority(level);
+    field(&mut out, "PRIORITY", &priority[1..priority.len() - 1]);
+    if let Some((file, line)) = source.filter(|(file, _)| !file.is_empty()) {
+        field(&mut out, "CODE_FILE", file);
+        field(&mut out, "CODE_LINE", &line.to_string());
+    }
+    if !module.is_empty() {
+        field(&mut out, "TARGET", module);
+    }
+    field(&mut out, "MESSAGE", body.trim_end_matches('\n'));
+    out
+}
+
+fn field(out: &mut Vec<u8>, key: &str, value: &str) {
+    out.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        out.push(b'\n');
+        out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        out.push(b'=');
+    }
+    out.extend_from_slice(value.as_bytes());
+    out.push(b'\n');
+}
+
+/// A record passed as a sealed memfd, for one too large for a datagram.
+#[cfg(target_os = "linux")]
+mod memfd {
+    use std::{
+        fs::File,
+        io::{self, Write},
+        mem,
+        os::unix::{
+            ffi::OsStrExt,
+            io::{AsRawFd, FromRawFd},
+            net::UnixDatagram,
+        },
+        path::Path,
+        ptr,
+    };
+
+    pub(super) fn send(socket: &UnixDatagram, path: &Path, record: &[u8]) -> io::Result<()> {
+        let fd = unsafe { libc::memfd_create(c"tklog-journald".as_ptr(), libc::MFD_ALLOW_SEALING | libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Closed as it drops, once journald holds its own descriptor.
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(record)?;
+        let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+        if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        send_fd(socket, path, file.as_raw_fd())
+    }
+
+    /// Sends `fd` to `path` with no payload, as an `SCM_RIGHTS` message.
+    fn send_fd(socket: &UnixDatagram, path: &Path, fd: libc::c_int) -> io::Result<()> {
+        let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let name = path.as_os_str().as_bytes();
+        if name.len() >= addr.sun_path.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "socket path too long"));
+        }
+        for (d, s) in addr.sun_path.iter_mut().zip(name) {
+            *d = *s as libc::c_char;
+        }
+        // Room for one control message of one descriptor, aligned as cmsghdr.
+        let mut control = [0u64; 8];
+        let space = unsafe { libc::CMSG_SPACE(mem::size_of::<libc::c_int>() as u32) } as usize;
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = (&mut addr as *mut libc::sockaddr_un).cast();
+        msg.msg_namelen = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = space as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<libc::c_int>() as u32) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::c_int>(), fd);
+        }
+        if unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The versions of the JSON lines of `Logger::preset_k8s`.
+//!
+//! Each line starts with its schema version, `"v":2`, then the keys of
+//! that version in order; `caller`, `logger` and `event` are left out
+//! when empty, and the pod, namespace and dynamic fields follow. Adding a
+//! key bumps the version. `Logger::set_json_schema_version` pins an older one, so a
+//! crate upgrade doesn't break the parsers downstream; version 0 is the
+//! layout from before the `v` key.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::json::Schema;
+//! use tklog::sync::Logger;
+//!
+//! let mut log = Logger::new();
+//! log.preset_k8s();
+//! log.set_json_schema_version(0).unwrap();
+//! assert_eq!(Schema::version(0).unwrap().keys, ["ts", "level", "msg", "caller", "logger"]);
+//! ```
+
+/// The keys of one version of the JSON lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Schema {
+    pub version: u32,
+    pub keys: &'static [&'static str],
+}
+
+impl Schema {
+    /// The lines before they carried a version.
+    pub const V0: Schema = Schema { version: 0, keys: &["ts", "level", "msg", "caller", "logger"] };
+    pub const V1: Schema = Schema { version: 1, keys: &["v", "ts", "level", "msg", "caller", "logger"] };
+    /// Adds the event ID, see `events`.
+    pub const V2: Schema = Schema { version: 2, keys: &["v", "ts", "level", "msg", "caller", "logger", "event"] };
+    /// The version written unless pinned.
+    pub const CURRENT: Schema = Schema::V2;
+
+    const ALL: [Schema; 3] = [Schema::V0, Schema::V1, Schema::V2];
+
+    /// The schema of `version`, if there is one.
+    pub fn version(version: u32) -> Option<Schema> {
+        Schema::ALL.into_iter().find(|s| s.version == version)
+    }
+}
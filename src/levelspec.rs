@@ -0,0 +1,128 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Levels from an environment variable in the style of `RUST_LOG`, with
+//! `Logger::set_level_from_env` or `Logger::from_env`, which reads
+//! `TKLOG_LEVEL`.
+//!
+//! The spec is a comma-separated list of `level`, the level of every
+//! module, and `module=level`, the level of a module pattern as with
+//! `set_mod_option`. Levels are `trace`, `debug`, `info`, `warn`, `error`,
+//! `fatal` and `off`, in any case. Tokens that are neither are left out
+//! with a `Config` diagnostic.
+//!
+//! Reading the variable again, e.g. on a signal, replaces the module
+//! levels of the previous read; a module left out of the new spec gets
+//! the level it would have without the variable. An unset variable
+//! changes nothing.
+//!
+//! ### Example
+//! ```no_run
+//! // TKLOG_LEVEL=info,my_crate::db=debug,hyper=warn
+//! let mut log = tklog::sync::Logger::from_env();
+//! // later, to pick up a new value:
+//! log.set_level_from_env("TKLOG_LEVEL");
+//! ```
+
+use std::str::FromStr;
+
+use crate::{
+    diagnostics::{self, Category},
+    trie::Trie,
+    LogOptionConst, LEVEL,
+};
+
+/// The variable read by `Logger::from_env`.
+pub const TKLOG_LEVEL: &str = "TKLOG_LEVEL";
+
+/// A parsed spec: its level for every module, if any, and its modules.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct LevelSpec {
+    pub(crate) level: Option<LEVEL>,
+    pub(crate) modules: Vec<(String, LEVEL)>,
+}
+
+impl LevelSpec {
+    /// The spec in `var`; None when it is unset.
+    pub(crate) fn from_env(var: &str) -> Option<Self> {
+        std::env::var(var).ok().map(|spec| Self::parse(var, &spec))
+    }
+
+    pub(crate) fn parse(var: &str, spec: &str) -> Self {
+        let mut parsed = LevelSpec::default();
+        for token in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            match token.split_once('=') {
+                Some((module, level)) if !module.trim().is_empty() => match LEVEL::from_str(level.trim()) {
+                    Ok(level) => parsed.modules.push((module.trim().to_string(), level)),
+                    Err(_) => unknown(var, token),
+                },
+                Some(_) => unknown(var, token),
+                None => match LEVEL::from_str(token) {
+                    Ok(level) => parsed.level = Some(level),
+                    Err(_) => unknown(var, token),
+                },
+            }
+        }
+        parsed
+    }
+
+    /// Sets the module levels in `modmap`, after dropping those of the
+    /// previous read, `previous`, which becomes the modules of this one.
+    pub(crate) fn apply(self, modmap: &mut Trie<(LogOptionConst, String)>, previous: &mut Vec<String>) -> Option<LEVEL> {
+        for pattern in previous.drain(..) {
+            clear_level(modmap, &pattern);
+        }
+        for (pattern, level) in self.modules {
+            set_level(modmap, &pattern, level);
+            previous.push(pattern);
+        }
+        self.level
+    }
+}
+
+fn unknown(var: &str, token: &str) {
+    diagnostics::report(Category::Config, None, format!("{}: `{}` is not a level or module=level; left out", var, token));
+}
+
+/// Sets the level of `pattern`, keeping its other options.
+pub(crate) fn set_level(modmap: &mut Trie<(LogOptionConst, String)>, pattern: &str, level: LEVEL) {
+    match modmap.get_pattern_mut(pattern) {
+        Some((lo, _)) => lo.level = Some(level),
+        None => modmap.insert(pattern, (LogOptionConst { level: Some(level), format: None, formatter: None, console: None, separator: None }, String::new())),
+    }
+}
+
+/// Sets the separator of `pattern`, keeping its other options.
+pub(crate) fn set_separator(modmap: &mut Trie<(LogOptionConst, String)>, pattern: &str, separator: &str) {
+    match modmap.get_pattern_mut(pattern) {
+        Some((lo, _)) => lo.separator = Some(separator.to_string()),
+        None => modmap.insert(pattern, (LogOptionConst { level: None, format: None, formatter: None, console: None, separator: Some(separator.to_string()) }, String::new())),
+    }
+}
+
+/// Drops the level of `pattern`, and the pattern once it has no options.
+pub(crate) fn clear_level(modmap: &mut Trie<(LogOptionConst, String)>, pattern: &str) -> bool {
+    let Some((lo, filename)) = modmap.get_pattern_mut(pattern) else {
+        return false;
+    };
+    if lo.level.take().is_none() {
+        return false;
+    }
+    if lo.format.is_none() && lo.formatter.is_none() && lo.console.is_none() && lo.separator.is_none() && filename.is_empty() {
+        modmap.remove(pattern);
+    }
+    true
+}
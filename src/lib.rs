@@ -15,33 +15,115 @@
 // limitations under the License.
 
 use std::{
+    cell::{Cell, RefCell},
     env,
-    fmt::{self, Debug},
+    fmt::{self, Debug, Write as _},
+    ops::{Deref, DerefMut},
     fs::{self, File},
     io::{self, BufReader, BufWriter, Read, Write},
+    panic::Location,
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex, MutexGuard, RwLock,
+    },
+    time::Duration,
 };
 
-use chrono::{DateTime, Datelike, Local, NaiveDateTime, Timelike};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeDelta, Timelike, Utc};
 use flate2::{
     write::{GzEncoder, ZlibEncoder},
     Compression,
 };
+use backupname::Backups;
+use guard::Guarded;
 use handle::FileOptionType;
 use once_cell::sync::Lazy;
-use tokio::io::AsyncReadExt;
+use record::RecordSnapshot;
+use regex::Regex;
+use runtime::{Rt, Runtime};
+
 #[allow(non_snake_case)]
 pub mod Async;
 pub mod asyncfile;
 pub mod asyncmulti;
+pub mod backupname;
+pub mod badge;
+mod batch;
+pub mod block;
+pub mod boot;
+pub mod bridge;
+mod budget;
+pub mod builder;
+mod callers;
+pub mod capture;
+mod channel;
+pub mod clock;
+pub mod color;
+pub mod compress;
+pub mod config;
+pub mod context;
+pub mod cut;
+pub mod diagnostics;
+pub mod directory;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fields;
+pub mod filter;
+mod guard;
 pub mod handle;
+pub mod health;
+pub mod hook;
+pub mod init;
+mod intern;
+#[doc(hidden)]
+pub mod lines;
+#[cfg(feature = "journald")]
+pub mod journald;
+pub mod json;
+pub mod levelspec;
+pub mod logerror;
+pub mod logsink;
+mod memory;
+pub mod metrics;
 mod mwrite;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod output;
+pub mod parse;
+mod paths;
+pub mod persist;
+pub mod postmortem;
+mod preset;
+mod quota;
+pub mod record;
+pub mod remote;
+pub mod rotation;
+pub mod routing;
+mod runtime;
+mod scheduler;
+#[cfg(all(unix, feature = "signal"))]
+mod signal;
+pub mod stats;
+pub mod storm;
 pub mod sync;
 pub mod syncfile;
 pub mod syncmulti;
+#[cfg(feature = "syslog")]
+pub mod syslog;
+pub mod tee;
 #[allow(non_snake_case)]
 mod threadPool;
+pub mod timing;
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
 mod trie;
+pub mod verify;
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub mod wasm;
+mod writebuf;
 pub enum DateType {
     Date,
     Time,
@@ -149,6 +231,8 @@ pub struct LogOptionConst {
     pub format: Option<u8>,
     pub formatter: Option<String>,
     pub console: Option<bool>,
+    /// Set with `set_mod_separator`.
+    pub separator: Option<String>,
 }
 
 #[derive(Clone)]
@@ -160,9 +244,94 @@ pub struct LogContext {
     pub modname: String,
 }
 
+/// Compression format for rotated backups.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum CompressType {
+    Gzip,
+}
+
+/// Where the file and line of a macro's line come from, see
+/// `Logger::set_location_strategy`. They differ when the macro is in a
+/// `#[track_caller]` function, such as a logging helper of your own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LocationStrategy {
+    /// `file!()` and `line!()`: where the macro is, in the helper.
+    MacroExpansion,
+    /// `Location::caller()`: the caller of the outermost `#[track_caller]`
+    /// function around the macro, where the macro is when there is none.
+    #[default]
+    Caller,
+}
+
+/// The location of a macro, taken by the macros with `CallSite::here`.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug)]
+pub struct CallSite {
+    file: &'static str,
+    line: u32,
+    caller: &'static Location<'static>,
+    /// See `function_name!`.
+    pub function: Option<&'static str>,
+}
+
+impl CallSite {
+    #[track_caller]
+    pub fn here(file: &'static str, line: u32) -> Self {
+        CallSite { file, line, caller: Location::caller(), function: None }
+    }
+
+    /// The call site in `function`, a `function_name!`.
+    pub fn in_function(self, function: &'static str) -> Self {
+        CallSite { function: Some(function), ..self }
+    }
+
+    pub fn get(&self, strategy: LocationStrategy) -> (&'static str, u32) {
+        match strategy {
+            LocationStrategy::MacroExpansion => (self.file, self.line),
+            LocationStrategy::Caller => (self.caller.file(), self.caller.line()),
+        }
+    }
+}
+
+/// What happened to a rotated backup when compression was considered.
+#[derive(PartialEq, Clone, Debug)]
+pub enum CompressDecision {
+    /// The handler was configured without compression.
+    Disabled,
+    Compressed,
+    /// The sample compressed to `ratio` of its size, above the configured
+    /// skip ratio, so the raw backup was kept.
+    Skipped { ratio: f64 },
+    /// Less than the estimated `needed` bytes were free next to the backup,
+    /// so the raw backup was kept.
+    NoSpace { needed: u64, available: u64 },
+    Failed(String),
+    /// Left raw as one of the newest backups of `set_compress_after`; a
+    /// later rotation compresses it.
+    Deferred,
+}
+
+/// Passed to the rotation handler once a backup has been written, compressed
+/// (or not) and pruned.
+#[derive(Clone, Debug)]
+pub struct RotationEvent {
+    /// The live log file that was cut.
+    pub filename: String,
+    /// Where the rotated data ended up, `.gz` included when compressed.
+    pub backup: PathBuf,
+    pub compression: CompressDecision,
+}
+
+#[derive(Clone)]
 pub struct LogContent {
     pub file_body: String,
     pub console_body: Option<String>,
+    /// The bodies of the tee files, in the order they were added.
+    pub(crate) tees: Vec<String>,
+    /// The file and line of the macro call, kept for journald.
+    pub(crate) source: Option<(String, u32)>,
+    /// Whether the console line goes to stderr, see `ConsoleStream`.
+    pub(crate) stderr: bool,
 }
 
 impl LogContent {
@@ -170,12 +339,129 @@ impl LogContent {
         LogContent {
             file_body,
             console_body,
+            tees: Vec::new(),
+            source: None,
+            stderr: false,
         }
     }
 
     pub fn is_empty(&self) -> bool {
         self.file_body.is_empty() && self.console_body.is_none()
     }
+
+    /// Appends the bodies of `other`, for a block written as one line.
+    pub(crate) fn append(&mut self, other: LogContent) {
+        match (&mut self.console_body, other.console_body) {
+            (Some(console), Some(o)) => console.push_str(&o),
+            (Some(console), None) => console.push_str(&other.file_body),
+            (None, Some(o)) => self.console_body = Some(self.file_body.clone() + &o),
+            (None, None) => {}
+        }
+        self.file_body.push_str(&other.file_body);
+        for (tee, o) in self.tees.iter_mut().zip(other.tees) {
+            tee.push_str(&o);
+        }
+    }
+
+    /// The bytes of all the bodies.
+    pub(crate) fn size(&self) -> usize {
+        self.file_body.len() + self.console_body.as_ref().map_or(0, String::len) + self.tees.iter().map(String::len).sum::<usize>()
+    }
+}
+
+/// A message built by a closure, for the `debug!(|| summary(&state))`
+/// macro arms: the closure runs only once the line passes the level checks.
+/// A panic inside it is caught and logged in place of the message.
+#[doc(hidden)]
+pub struct LazyMessage<F>(pub F);
+
+impl<F, R> std::fmt::Display for LazyMessage<F>
+where
+    F: Fn() -> R,
+    R: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| (self.0)().to_string())) {
+            Ok(s) => f.write_str(&s),
+            Err(payload) => write!(f, "tklog: message closure panicked: {}", panic_reason(&payload)),
+        }
+    }
+}
+
+/// The literal of an `info!("cache miss")` style macro call: string
+/// literals go to `Logger::print_static` as they are, other literals take
+/// the general path, where `1.50` is written `1.5`.
+#[doc(hidden)]
+pub trait StaticMessage {
+    fn as_static(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+impl StaticMessage for &'static str {
+    fn as_static(&self) -> Option<&'static str> {
+        Some(self)
+    }
+}
+
+macro_rules! general_message {
+    ($($t:ty),*) => {
+        $(impl StaticMessage for $t {})*
+    };
+}
+
+general_message!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool, char);
+
+/// An error with its sources on one line, `request failed: caused by:
+/// connection refused`, for the macros: `errors!(log, "sync:",
+/// ErrorChain::new(&e))` or `; error = %ErrorChain::new(&e)`. In a message,
+/// the backtrace given to `with_backtrace`, when it was captured, goes
+/// indented on the lines after it with the logger's `set_error_backtraces`.
+pub struct ErrorChain<'a> {
+    error: &'a dyn std::error::Error,
+    backtrace: Option<&'a std::backtrace::Backtrace>,
+}
+
+impl<'a> ErrorChain<'a> {
+    pub fn new(error: &'a dyn std::error::Error) -> Self {
+        ErrorChain { error, backtrace: None }
+    }
+
+    /// The backtrace the error carries, such as `anyhow::Error::backtrace`.
+    pub fn with_backtrace(mut self, backtrace: &'a std::backtrace::Backtrace) -> Self {
+        self.backtrace = Some(backtrace);
+        self
+    }
+}
+
+impl fmt::Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        let mut source = self.error.source();
+        while let Some(e) = source {
+            write!(f, ": caused by: {}", e)?;
+            source = e.source();
+        }
+        match self.backtrace {
+            Some(backtrace) if ERROR_BACKTRACES.get() && backtrace.status() == std::backtrace::BacktraceStatus::Captured => {
+                for line in backtrace.to_string().lines() {
+                    write!(f, "\n    {}", line)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `error` and its sources on one line, see `ErrorChain`.
+pub fn error_chain(error: &dyn std::error::Error) -> String {
+    ErrorChain::new(error).to_string()
+}
+
+/// The message of a caught panic.
+pub(crate) fn panic_reason(payload: &Box<dyn std::any::Any + Send>) -> &str {
+    payload.downcast_ref::<&str>().copied().or_else(|| payload.downcast_ref::<String>().map(String::as_str)).unwrap_or("unknown panic")
 }
 
 pub struct LevelOption {
@@ -214,6 +500,113 @@ pub mod Format {
     pub const LongFileName: u8 = 8;
     pub const ShortFileName: u8 = 16;
     pub const LevelFlag: u8 = 32;
+    /// The name of the logging thread, or its ID when it has none, between
+    /// the time and the file.
+    pub const ThreadId: u8 = 64;
+    /// The function the macro is in, after the file, see
+    /// `Logger::set_function_with_module`.
+    pub const FuncName: u8 = 128;
+}
+
+/// Errors returned by tklog configuration methods.
+#[derive(Debug)]
+pub enum Error {
+    /// Test mode was requested while a file handler with rotation is active,
+    /// or a rotating file pattern in test mode. Frozen timestamps would make
+    /// every backup carry the same period stamp.
+    TestModeWithRotation(String),
+    /// A routing rule names a sink the logger doesn't have.
+    UnknownSink(String),
+    /// A routing matrix names more than 64 distinct sinks.
+    TooManySinks,
+    /// A setting needs a sink in `requested` output mode while it is in
+    /// `active` mode, see `output`.
+    ConflictingMode { active: output::OutputMode, requested: output::OutputMode },
+    /// A `cut` builder was given missing, zero or conflicting settings.
+    InvalidCut(&'static str),
+    /// `second` was refused a file handler on `path`, which `first` writes
+    /// with other cut settings, see `paths`.
+    PathConflict { path: PathBuf, first: String, second: String },
+    /// No JSON schema has this version, see `json`.
+    UnknownSchemaVersion(u32),
+    /// A tee file was asked for in a mode without a layout, see `tee`.
+    UnsupportedTeeMode(output::OutputMode),
+    /// A rotation group was given a handler it can't take, see `rotation`.
+    InvalidRotationGroup { handler: String, reason: &'static str },
+    /// A config file line tklog can't take, see `LogConfig::apply_text`.
+    InvalidConfig { line: usize, reason: String },
+    /// A config file that can't be read, see `LogConfig::apply_file`.
+    ConfigUnreadable(PathBuf, io::Error),
+    /// `try_recover` left these reasons of `health` standing.
+    NotRecovered(Vec<String>),
+    /// A fixed time zone offset of a day or more, in seconds.
+    InvalidTimeZone(i32),
+    /// A time format chrono can't read, see `Logger::set_time_format`.
+    InvalidTimeFormat(String),
+    /// A backup name template tklog can't take, see `backupname`.
+    InvalidBackupTemplate { template: String, reason: String },
+    /// `set_log_path` on a logger without a default file.
+    NoLogFile,
+    /// `set_log_path` couldn't open the file of this path.
+    FileUnopenable(PathBuf, io::Error),
+    /// A column order tklog can't lay lines out with, see
+    /// `Logger::set_column_order`.
+    InvalidColumnOrder(&'static str),
+    /// A compression level above 9, see `Logger::set_compress_level`.
+    InvalidCompressLevel(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TestModeWithRotation(filename) => write!(f, "test mode refused: file handler `{}` rotates", filename),
+            Error::UnknownSink(name) => write!(f, "routing refused: unknown sink `{}`", name),
+            Error::TooManySinks => write!(f, "routing refused: more than 64 sinks"),
+            Error::ConflictingMode { active, requested } => write!(f, "output mode refused: {} requested while {} is active", requested, active),
+            Error::InvalidCut(reason) => write!(f, "cut mode refused: {}", reason),
+            Error::PathConflict { path, first, second } => write!(f, "file handler refused: {} for {} is written by {} with other cut settings", path.display(), second, first),
+            Error::UnknownSchemaVersion(version) => write!(f, "json schema refused: no version {}", version),
+            Error::UnsupportedTeeMode(mode) => write!(f, "tee file refused: no {} layout", mode),
+            Error::InvalidRotationGroup { handler, reason } if handler.is_empty() => write!(f, "rotation group refused: {}", reason),
+            Error::InvalidRotationGroup { handler, reason } => write!(f, "rotation group refused: `{}` {}", handler, reason),
+            Error::InvalidConfig { line, reason } => write!(f, "config refused: line {}: {}", line, reason),
+            Error::ConfigUnreadable(path, e) => write!(f, "config refused: can't read {}: {}", path.display(), e),
+            Error::NotRecovered(reasons) => write!(f, "recovery incomplete: {}", reasons.join("; ")),
+            Error::InvalidTimeZone(secs) => write!(f, "time zone refused: offset of {}s is a day or more", secs),
+            Error::InvalidTimeFormat(format) => write!(f, "time format refused: `{}` is not a strftime format", format),
+            Error::InvalidBackupTemplate { template, reason } => write!(f, "backup name template refused: `{}`: {}", template, reason),
+            Error::NoLogFile => write!(f, "log path refused: no default file to move"),
+            Error::FileUnopenable(path, e) => write!(f, "log path refused: can't open {}: {}", path.display(), e),
+            Error::InvalidColumnOrder(reason) => write!(f, "column order refused: {}", reason),
+            Error::InvalidCompressLevel(level) => write!(f, "compression level refused: {} is not 0-9", level),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Deterministic output for golden-file tests. **Test only.**
+///
+/// Every record renders `fixed_time` as its timestamp, and the `{seq}`
+/// placeholder counts up from `fixed_seq_start`, so the produced bytes are
+/// identical from run to run.
+///
+/// ### Example
+/// ```no_run
+/// use chrono::{Local, TimeZone};
+/// use tklog::{sync::Logger, TestMode};
+///
+/// let mut log = Logger::new();
+/// log.set_test_mode(TestMode {
+///     fixed_time: Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+///     fixed_seq_start: 1,
+/// })
+/// .unwrap();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct TestMode {
+    pub fixed_time: DateTime<Local>,
+    pub fixed_seq_start: u64,
 }
 
 #[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
@@ -230,30 +623,466 @@ impl ErrCode {
 // const DEFAULT_FORMATTER: &str = "{level}{time} {file}:{message}\n";
 // const MWRITE: Lazy<mwrite::MWrite> = Lazy::new(|| mwrite::MWrite::new());
 
-pub const LOG: Lazy<sync::Log> = Lazy::new(|| sync::Log::new());
+pub static LOG: Lazy<sync::Log> = Lazy::new(sync::Log::new);
+
+pub static ASYNC_LOG: Lazy<Async::Log> = Lazy::new(Async::Log::new);
+
+static SYNC_LOGGER: Lazy<Mutex<sync::Logger>> = Lazy::new(|| Mutex::new(sync::Logger::new_global()));
+
+static ASYNC_LOGGER: Lazy<tokio::sync::Mutex<Async::Logger>> = Lazy::new(|| tokio::sync::Mutex::new(Async::Logger::new_global()));
+
+/// Locks the global logger behind `LOG` and the `trace!` … `fatal!` macros.
+///
+/// The lock is not reentrant: lines the macros log on this thread while the
+/// guard is held, e.g. from a `Drop`, take the path of `reentrant`.
+///
+/// ### Example
+/// ```no_run
+/// tklog::global().set_level(tklog::LEVEL::Warn);
+/// ```
+pub fn global() -> GlobalGuard<MutexGuard<'static, sync::Logger>> {
+    GlobalGuard {
+        guard: SYNC_LOGGER.lock().unwrap_or_else(|e| e.into_inner()),
+        _inside: Inside::enter(),
+        on_drop: Some(|log| {
+            SYNC_FLOOR.store(log.lowest_level() as u8, Ordering::Relaxed);
+            lines::changed();
+        }),
+    }
+}
+
+/// `global` for a line rather than a change of settings: the guard leaves
+/// the floor of `above_floor` and the generation of `lines` as they are.
+/// That holds as long as nothing under it changes a setting: a line only
+/// changes what goes with lines, such as the sequence number, the storm
+/// guard or the files, and the adaptive budget raises the levels of lines
+/// over the floor, never lowers them.
+#[doc(hidden)]
+pub fn global_for_line() -> GlobalGuard<MutexGuard<'static, sync::Logger>> {
+    GlobalGuard {
+        guard: SYNC_LOGGER.lock().unwrap_or_else(|e| e.into_inner()),
+        _inside: Inside::enter(),
+        on_drop: None,
+    }
+}
+
+/// The lowest level of the global logger, any module and sink, as of the
+/// last `global` guard dropped; 0 until then.
+static SYNC_FLOOR: AtomicU8 = AtomicU8::new(0);
+
+/// Whether a line at `level` may pass the levels of the global logger,
+/// checked without its lock: the macros leave a line below them there.
+#[doc(hidden)]
+pub fn above_floor(level: LEVEL) -> bool {
+    level as u8 >= SYNC_FLOOR.load(Ordering::Relaxed)
+}
+
+/// Configures the global logger, unless that happened before: then the
+/// error tells where and what is set up. See `init`.
+#[track_caller]
+pub fn init_once<F: FnOnce(&mut sync::Logger)>(configure: F) -> Result<init::Handle, init::AlreadyInitialized> {
+    init::init_once(Location::caller(), configure)
+}
+
+/// Configures the global logger even if `init_once` did so before, taking
+/// over as its owner; for the rare setup that really must change it.
+#[track_caller]
+pub fn force_reconfigure<F: FnOnce(&mut sync::Logger)>(configure: F) -> init::Handle {
+    init::force_reconfigure(Location::caller(), configure)
+}
+
+/// Makes `bridge` the logger of the `log` facade, see `bridge`. Errs when
+/// the facade has a logger already, this bridge or another.
+pub fn install_log_bridge(bridge: bridge::LogBridge) -> Result<(), log::SetLoggerError> {
+    bridge::install(bridge)
+}
+
+/// `LOG.set_level`: takes effect for the next line of every thread, and
+/// may come from any thread at any time.
+pub fn set_level(level: LEVEL) {
+    LOG.set_level(level);
+}
+
+/// `LOG.set_option`, from any thread at any time as `set_level`.
+pub fn set_option(option: LogOption) {
+    LOG.set_option(option);
+}
+
+/// A lock on a global logger that marks the thread as inside tklog until
+/// it is dropped.
+pub struct GlobalGuard<G> {
+    guard: G,
+    _inside: Inside,
+    /// Runs with the lock still held, before it is released.
+    on_drop: Option<fn(&G)>,
+}
+
+impl<G> Drop for GlobalGuard<G> {
+    fn drop(&mut self) {
+        if let Some(f) = self.on_drop {
+            f(&self.guard);
+        }
+    }
+}
+
+impl<G: Deref> Deref for GlobalGuard<G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &G::Target {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut> DerefMut for GlobalGuard<G> {
+    fn deref_mut(&mut self) -> &mut G::Target {
+        &mut self.guard
+    }
+}
+
+thread_local! {
+    /// Set while this thread holds a logger or runs one of its callbacks.
+    static INSIDE: Cell<bool> = const { Cell::new(false) };
+}
 
-static TKLOG2SYNCLOG: sync::Log = sync::Log;
+/// Marks the thread as inside tklog until dropped, see `reentrant`.
+#[doc(hidden)]
+pub struct Inside(bool);
 
-pub const ASYNC_LOG: Lazy<Async::Log> = Lazy::new(|| Async::Log::new());
+impl Inside {
+    pub fn enter() -> Self {
+        Inside(INSIDE.replace(true))
+    }
+}
 
-static TKLOG2ASYNC_LOG: Async::Log = Async::Log;
+impl Drop for Inside {
+    fn drop(&mut self) {
+        INSIDE.set(self.0);
+    }
+}
 
+pub(crate) fn inside_tklog() -> bool {
+    INSIDE.get()
+}
+
+/// Whether a line is logged from inside tklog on this thread: from a `Drop`
+/// run under a logger lock, a formatter, the custom handler or the dynamic
+/// fields callback. Such a line can't take the logger again, so Warn and
+/// above go to stderr and the rest are dropped.
+#[doc(hidden)]
+pub fn reentrant<F: FnOnce() -> String>(level: LEVEL, module: &str, message: F) -> bool {
+    if !inside_tklog() {
+        return false;
+    }
+    if level >= LEVEL::Warn && level < LEVEL::Off {
+        eprintln!("tklog: [{:?}] {} (logged from inside tklog): {}", level, module, message().trim_end());
+    }
+    true
+}
+
+thread_local! {
+    /// The function of the macro laying out a line on this thread, see
+    /// `in_function`.
+    static FUNCTION: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// The path of the function the macro is in, with a `::f` after it, such
+/// as `app::api::handle::f` or `app::api::handle::{{closure}}::f` in a
+/// closure or an async block. A constant of the call site: it costs
+/// nothing until a line shows it, see `function_of`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! function_name {
+    () => {{
+        fn f() {}
+        fn name_of<T>(_: T) -> &'static str {
+            ::std::any::type_name::<T>()
+        }
+        name_of(f)
+    }};
+}
+
+/// Runs `f`, the layout of a line of the macro in `function`, a
+/// `function_name!`, with the function at hand for `Format::FuncName` and
+/// `{function}`.
+#[doc(hidden)]
+pub fn in_function<R>(function: Option<&'static str>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<&'static str>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            FUNCTION.set(self.0);
+        }
+    }
+    let _restore = Restore(FUNCTION.replace(function));
+    f()
+}
+
+/// The function of the line laid out on this thread, without the `::f`
+/// and the `::{{closure}}` of `function_name!`, and without the path of
+/// `module` unless `with_module`.
+pub(crate) fn function_of(module: &str, with_module: bool) -> Option<&'static str> {
+    let name = FUNCTION.get()?;
+    let mut name = name.strip_suffix("::f").unwrap_or(name);
+    while let Some(n) = name.strip_suffix("::{{closure}}") {
+        name = n;
+    }
+    if !with_module {
+        if let Some(n) = name.strip_prefix(module).and_then(|n| n.strip_prefix("::")) {
+            name = n;
+        }
+    }
+    Some(name)
+}
+
+/// How many message buffers a thread keeps for `message_of`, and the
+/// largest one it keeps.
+const MESSAGE_POOL: usize = 4;
+const MESSAGE_MAX_CAPACITY: usize = 16 << 10;
+
+thread_local! {
+    /// Message buffers handed back by the sync `fmt`, see `recycle_message`.
+    static MESSAGES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+thread_local! {
+    /// Whether an `ErrorChain` shows its backtrace, see `with_error_backtraces`.
+    static ERROR_BACKTRACES: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f`, the message of a line of a logger with `set_error_backtraces`
+/// at `on`.
+#[doc(hidden)]
+pub fn with_error_backtraces<R>(on: bool, f: impl FnOnce() -> R) -> R {
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ERROR_BACKTRACES.set(self.0);
+        }
+    }
+    let _restore = Restore(ERROR_BACKTRACES.replace(on));
+    f()
+}
+
+/// The message of the `*!` macros: `args` written one after the other into
+/// one buffer, `sep` between them. The buffer is one the sync logger handed
+/// back after an earlier line of this thread, so a steady stream of lines
+/// allocates no message at all.
+#[doc(hidden)]
+pub fn message_of(sep: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut msg = MESSAGES.try_with(|p| p.try_borrow_mut().ok().and_then(|mut p| p.pop())).ok().flatten().unwrap_or_default();
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            msg.push_str(sep);
+        }
+        let _ = write!(msg, "{}", arg);
+    }
+    msg
+}
+
+/// Hands the buffer of a message that was rendered back to `message_of`.
+pub(crate) fn recycle_message(mut msg: String) {
+    if msg.capacity() == 0 || msg.capacity() > MESSAGE_MAX_CAPACITY {
+        return;
+    }
+    msg.clear();
+    let _ = MESSAGES.try_with(|p| {
+        if let Ok(mut p) = p.try_borrow_mut() {
+            if p.len() < MESSAGE_POOL {
+                p.push(msg);
+            }
+        }
+    });
+}
+
+/// Locks the global async logger behind `ASYNC_LOG` and the `async_*` macros.
+pub async fn global_async() -> tokio::sync::MutexGuard<'static, Async::Logger> {
+    ASYNC_LOGGER.lock().await
+}
+
+/// Locks the global async logger from synchronous code. On a multi-thread
+/// runtime a contended lock is waited for with `block_in_place`; a
+/// current-thread runtime can only yield, so prefer `global_async()` there.
+fn global_async_blocking() -> GlobalGuard<tokio::sync::MutexGuard<'static, Async::Logger>> {
+    GlobalGuard {
+        guard: lock_async_blocking(),
+        _inside: Inside::enter(),
+        on_drop: None,
+    }
+}
+
+fn lock_async_blocking() -> tokio::sync::MutexGuard<'static, Async::Logger> {
+    if let Ok(g) = ASYNC_LOGGER.try_lock() {
+        return g;
+    }
+    match tokio::runtime::Handle::try_current() {
+        Ok(h) if h.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => Rt::block_in_place(|| ASYNC_LOGGER.blocking_lock()),
+        Ok(_) => loop {
+            std::thread::yield_now();
+            if let Ok(g) = ASYNC_LOGGER.try_lock() {
+                return g;
+            }
+        },
+        Err(_) => ASYNC_LOGGER.blocking_lock(),
+    }
+}
+
+/// How long `FlushGuard` waits for the global async logger.
+const FLUSH_GUARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Flushes `LOG` and `ASYNC_LOG` when dropped, see `flush_guard`.
+#[must_use = "the logs are flushed when the guard is dropped"]
+pub struct FlushGuard(());
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        timing::log_report();
+        if Lazy::get(&SYNC_LOGGER).is_some() {
+            LOG.flush();
+        }
+        if Lazy::get(&ASYNC_LOGGER).is_some() {
+            global_async_blocking().flush_blocking(FLUSH_GUARD_TIMEOUT);
+        }
+        syncfile::drain_rotations(FLUSH_GUARD_TIMEOUT);
+    }
+}
+
+/// A guard that writes the lines still queued by the global loggers and
+/// syncs their files when it goes out of scope, as `main` returns or
+/// unwinds from a panic, then lets the sync loggers finish compressing
+/// their backups. Best effort: the async logger and the compressions are
+/// each waited for at most 5 seconds, and not at all on a current-thread runtime, whose worker
+/// can't run meanwhile; await `ASYNC_LOG.flush()` there instead. With
+/// `set_timing_report(true)`, the timing report is logged first.
+///
+/// ```no_run
+/// let _flush = tklog::flush_guard();
+/// tklog::info!("written before exit");
+/// ```
+pub fn flush_guard() -> FlushGuard {
+    FlushGuard(())
+}
+
+/// How long the panic hook waits for a logger another thread holds.
+const PANIC_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Logs every panic as a Fatal line on `LOG`, `thread 'main' panicked at
+/// src/main.rs:10:5: boom`, with the file and line of the panic, and
+/// writes out the files before running the hook in place, such as the
+/// default one printing to stderr. A panic on a thread that holds the
+/// logger, or with the logger held elsewhere for a second, goes to stderr.
+pub fn log_panics() {
+    set_panic_hook(false);
+}
+
+/// `log_panics` on `ASYNC_LOG`. On a multi-thread runtime the line is
+/// written before the hook in place runs; on a current-thread one it is
+/// only queued, the worker can't run while the hook waits.
+pub fn log_panics_async() {
+    set_panic_hook(true);
+}
+
+fn set_panic_hook(on_async: bool) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        log_panic(info, on_async);
+        previous(info);
+    }));
+}
+
+fn log_panic(info: &std::panic::PanicHookInfo, on_async: bool) {
+    let thread = std::thread::current();
+    let (file, line) = info.location().map_or(("", 0), |l| (l.file(), l.line()));
+    let message = || {
+        let at = info.location().map(|l| format!(" at {}", l)).unwrap_or_default();
+        format!("thread '{}' panicked{}: {}", thread.name().unwrap_or("<unnamed>"), at, info.payload_as_str().unwrap_or("Box<dyn Any>"))
+    };
+    if reentrant(LEVEL::Fatal, "panic", message) {
+        return;
+    }
+    let deadline = std::time::Instant::now() + PANIC_LOCK_TIMEOUT;
+    if on_async {
+        let logger = loop {
+            match ASYNC_LOGGER.try_lock() {
+                Ok(logger) => break logger,
+                Err(_) if std::time::Instant::now() < deadline => std::thread::sleep(Duration::from_millis(1)),
+                Err(_) => return eprintln!("tklog: [Fatal] panic (logger busy): {}", message()),
+            }
+        };
+        let _inside = Inside::enter();
+        let s = logger.fmt("panic", LEVEL::Fatal, file, line, message());
+        if !s.is_empty() {
+            logger.log(LEVEL::Fatal, "panic", s);
+        }
+        logger.flush_blocking(FLUSH_GUARD_TIMEOUT);
+        return;
+    }
+    {
+        let mut logger = loop {
+            match SYNC_LOGGER.try_lock() {
+                Ok(logger) => break logger,
+                Err(std::sync::TryLockError::Poisoned(e)) => break e.into_inner(),
+                Err(std::sync::TryLockError::WouldBlock) if std::time::Instant::now() < deadline => std::thread::sleep(Duration::from_millis(1)),
+                Err(std::sync::TryLockError::WouldBlock) => return eprintln!("tklog: [Fatal] panic (logger busy): {}", message()),
+            }
+        };
+        let _inside = Inside::enter();
+        let s = logger.fmt("panic", LEVEL::Fatal, file, line, message());
+        if !s.is_empty() {
+            if logger.mode == PRINTMODE::DELAY {
+                logger.log(LEVEL::Fatal, "panic", s);
+            } else {
+                logger.safeprint(LEVEL::Fatal, "panic", s);
+            }
+        }
+    }
+    LOG.flush();
+}
+
+/// The names of the global loggers from before `global()`, now `LOG` and
+/// `ASYNC_LOG` themselves: their settings reach the logger of the macros.
+/// The methods of `Logger` that `Log` doesn't have no longer compile on
+/// them.
 #[allow(non_upper_case_globals)]
 pub mod tklog {
     use crate::{sync, Async};
     use once_cell::sync::Lazy;
 
-    pub static mut synclog: Lazy<sync::Logger> = Lazy::new(|| sync::Logger::new());
-    pub static mut asynclog: Lazy<Async::Logger> = Lazy::new(|| Async::Logger::new());
+    #[deprecated(since = "0.2.8", note = "use `tklog::LOG` or `tklog::global()`")]
+    pub static synclog: &Lazy<sync::Log> = &crate::LOG;
+    #[deprecated(since = "0.2.8", note = "use `tklog::ASYNC_LOG` or `tklog::global_async()`")]
+    pub static asynclog: &Lazy<Async::Log> = &crate::ASYNC_LOG;
+}
+
+/// Where the console lines go, see `Logger::set_console_stream`.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum ConsoleStream {
+    #[default]
+    Stdout,
+    Stderr,
+    /// The lines below the level to stdout, the others to stderr: with
+    /// `SplitAt(LEVEL::Warn)`, Warn, Error and Fatal go to stderr.
+    SplitAt(LEVEL),
+}
+
+impl ConsoleStream {
+    pub(crate) fn is_stderr(self, level: LEVEL) -> bool {
+        match self {
+            ConsoleStream::Stdout => false,
+            ConsoleStream::Stderr => true,
+            ConsoleStream::SplitAt(at) => level >= at,
+        }
+    }
 }
 
-#[derive(PartialEq, PartialOrd)]
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
 pub enum PRINTMODE {
     DELAY,
     PUNCTUAL,
 }
 
+/// The levels of lines, `Trace` the lowest. With the `serde` feature a
+/// level is (de)serialized as its name, see `Display` and `FromStr`.
 #[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(into = "String", try_from = "String"))]
 #[repr(u8)]
 pub enum LEVEL {
     Trace = 1,
@@ -265,22 +1094,205 @@ pub enum LEVEL {
     Off = 7,
 }
 
+impl LEVEL {
+    /// The name of the level in the default level flag, `INFO` for `[INFO]`.
+    pub fn name(self) -> &'static str {
+        match self {
+            LEVEL::Trace => "TRACE",
+            LEVEL::Debug => "DEBUG",
+            LEVEL::Info => "INFO",
+            LEVEL::Warn => "WARN",
+            LEVEL::Error => "ERROR",
+            LEVEL::Fatal => "FATAL",
+            LEVEL::Off => "OFF",
+        }
+    }
+}
+
+/// The name in upper case, which `from_str` reads back.
+impl fmt::Display for LEVEL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// A name in any case, `warning` for `Warn`, or the number of the level,
+/// `1` for `Trace` to `7` for `Off`.
 impl FromStr for LEVEL {
-    type Err = ();
+    type Err = ParseLevelError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
+        match s.to_ascii_lowercase().as_str() {
             "fatal" => Ok(LEVEL::Fatal),
             "error" => Ok(LEVEL::Error),
-            "warn" => Ok(LEVEL::Warn),
+            "warn" | "warning" => Ok(LEVEL::Warn),
             "info" => Ok(LEVEL::Info),
             "debug" => Ok(LEVEL::Debug),
             "trace" => Ok(LEVEL::Trace),
             "off" => Ok(LEVEL::Off),
-            _ => Err(()),
+            n => n.parse::<u8>().ok().and_then(|n| LEVEL::try_from(n).ok()).ok_or_else(|| ParseLevelError { input: s.to_string() }),
+        }
+    }
+}
+
+impl From<LEVEL> for String {
+    fn from(level: LEVEL) -> String {
+        level.name().to_string()
+    }
+}
+
+/// See `FromStr`.
+impl TryFrom<String> for LEVEL {
+    type Error = ParseLevelError;
+    fn try_from(s: String) -> Result<Self, ParseLevelError> {
+        s.parse()
+    }
+}
+
+/// `1` for `Trace` to `7` for `Off`, the values `LEVEL as u8` gives.
+impl TryFrom<u8> for LEVEL {
+    type Error = ParseLevelError;
+    fn try_from(n: u8) -> Result<Self, ParseLevelError> {
+        match n {
+            1 => Ok(LEVEL::Trace),
+            2 => Ok(LEVEL::Debug),
+            3 => Ok(LEVEL::Info),
+            4 => Ok(LEVEL::Warn),
+            5 => Ok(LEVEL::Error),
+            6 => Ok(LEVEL::Fatal),
+            7 => Ok(LEVEL::Off),
+            _ => Err(ParseLevelError { input: n.to_string() }),
         }
     }
 }
 
+/// The level of the first `max_level_*` feature on, from `off` to `trace`.
+const MAX_LEVEL: Option<LEVEL> = if cfg!(feature = "max_level_off") {
+    Some(LEVEL::Off)
+} else if cfg!(feature = "max_level_fatal") {
+    Some(LEVEL::Fatal)
+} else if cfg!(feature = "max_level_error") {
+    Some(LEVEL::Error)
+} else if cfg!(feature = "max_level_warn") {
+    Some(LEVEL::Warn)
+} else if cfg!(feature = "max_level_info") {
+    Some(LEVEL::Info)
+} else if cfg!(feature = "max_level_debug") {
+    Some(LEVEL::Debug)
+} else if cfg!(feature = "max_level_trace") {
+    Some(LEVEL::Trace)
+} else {
+    None
+};
+
+/// `MAX_LEVEL` of the `release_max_level_*` features.
+const RELEASE_MAX_LEVEL: Option<LEVEL> = if cfg!(feature = "release_max_level_off") {
+    Some(LEVEL::Off)
+} else if cfg!(feature = "release_max_level_fatal") {
+    Some(LEVEL::Fatal)
+} else if cfg!(feature = "release_max_level_error") {
+    Some(LEVEL::Error)
+} else if cfg!(feature = "release_max_level_warn") {
+    Some(LEVEL::Warn)
+} else if cfg!(feature = "release_max_level_info") {
+    Some(LEVEL::Info)
+} else if cfg!(feature = "release_max_level_debug") {
+    Some(LEVEL::Debug)
+} else if cfg!(feature = "release_max_level_trace") {
+    Some(LEVEL::Trace)
+} else {
+    None
+};
+
+/// The lowest level the macros compile, as the `log` crate does: the
+/// `release_max_level_*` feature in a build without debug assertions, else
+/// the `max_level_*` feature, else `Trace`. A line below it is a branch
+/// never taken, whose arguments aren't evaluated, whatever the level set
+/// at run time.
+pub const MAX_COMPILED_LEVEL: LEVEL = match (cfg!(debug_assertions), RELEASE_MAX_LEVEL, MAX_LEVEL) {
+    (false, Some(level), _) | (_, _, Some(level)) => level,
+    _ => LEVEL::Trace,
+};
+
+/// Whether the macros compile the lines of `level`, see `MAX_COMPILED_LEVEL`.
+#[doc(hidden)]
+pub const fn compiled(level: LEVEL) -> bool {
+    level as u8 >= MAX_COMPILED_LEVEL as u8
+}
+
+/// A string or number that isn't a level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseLevelError {
+    /// What was given.
+    pub input: String,
+}
+
+impl fmt::Display for ParseLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown level `{}`, expected trace, debug, info, warn, error, fatal, off or 1 to 7", self.input)
+    }
+}
+
+impl std::error::Error for ParseLevelError {}
+
+/// A byte size such as `4096`, `512KB`, `1.5 GB` or `128MiB`: B, KB, MB
+/// and GB in any case, KiB, MiB and GiB alike, with a fraction if need be.
+/// KB, MB and GB are powers of 1000, KiB, MiB and GiB powers of 1024, as
+/// in the config file. A size of zero, or one that rounds to it, is
+/// refused: a file would rotate on every line.
+pub fn parse_size(s: &str) -> Result<u64, ParseSizeError> {
+    let err = |reason| ParseSizeError { input: s.to_string(), reason };
+    let t = s.trim();
+    if t.starts_with(['-', '+']) {
+        return Err(err("a signed number, expected a size of at least 1 byte"));
+    }
+    let (number, unit) = t.split_at(t.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(t.len()));
+    let unit: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1_000,
+        "M" | "MB" => 1_000_000,
+        "G" | "GB" => 1_000_000_000,
+        "KIB" => 1 << 10,
+        "MIB" => 1 << 20,
+        "GIB" => 1 << 30,
+        _ => return Err(err("an unknown unit, expected B, KB, MB, GB, KiB, MiB or GiB")),
+    };
+    if number.is_empty() {
+        return Err(err("no number"));
+    }
+    let bytes = match number.parse::<u64>() {
+        Ok(n) => n.checked_mul(unit).ok_or_else(|| err("too large"))?,
+        Err(_) => {
+            let n: f64 = number.parse().map_err(|_| err("not a number"))?;
+            let bytes = (n * unit as f64).round();
+            if bytes >= u64::MAX as f64 {
+                return Err(err("too large"));
+            }
+            bytes as u64
+        }
+    };
+    if bytes == 0 {
+        return Err(err("zero, a file would rotate on every line"));
+    }
+    Ok(bytes)
+}
+
+/// A string `parse_size` can't read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseSizeError {
+    /// What was given.
+    pub input: String,
+    /// What is wrong with it.
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ParseSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid size `{}`: {}", self.input, self.reason)
+    }
+}
+
+impl std::error::Error for ParseSizeError {}
+
 fn env_level() -> LEVEL {
     if let Ok(rust_log) = env::var("RUST_LOG") {
         match LEVEL::from_str(&rust_log) {
@@ -298,25 +1310,66 @@ fn env_level() -> LEVEL {
     }
 }
 
+/// The columns of the default layout, see `Logger::set_column_order`.
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum COLUMN {
     LOGFLAG,
     TIME,
+    THREAD,
     FILEFLAG,
     COLON,
     MESSAGE,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MODE {
     HOUR,
     DAY,
     MONTH,
+    /// Periods of a fixed length in whole seconds, at least one, counted
+    /// from the epoch in local wall-clock time: every process in the time
+    /// zone rotates at the same times. Across a DST change the period
+    /// holding it is shorter or longer by the shift; when the clock falls
+    /// back, the period before isn't started again.
+    INTERVAL(std::time::Duration),
 }
 
 #[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
 pub enum CUTMODE {
     TIME,
     SIZE,
+    /// By time and by size, whichever comes first. Backups carry the stamp
+    /// of their period and a counter, which size cuts within it count up.
+    MIXED,
+    /// By time, writing to the file the strftime file name names for each
+    /// period, such as `app-2024-05-01.log` for `app-%Y-%m-%d.log`: nothing
+    /// is renamed, the file of a period over is compressed in place. See
+    /// `Logger::set_cutmode_by_time_pattern`.
+    PATTERN,
+}
+
+/// How count-based retention (`maxbackups`) picks the backups to delete.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PrunePolicy {
+    /// Every backup file counts on its own; the oldest files go first.
+    ByFile,
+    /// Backups sharing a period stamp (`app_20240501_1.log` … `app_20240501_9.log.gz`)
+    /// count as one; whole periods are deleted oldest-first, so `maxbackups`
+    /// is the number of periods kept. Backups without a stamp fall back to `ByFile`.
+    ByPeriod,
+}
+
+/// What a line does when the `PRINTMODE::DELAY` queue of an async logger
+/// is full, see `Async::Logger::set_channel_capacity`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The line waits for the consumer to make room.
+    #[default]
+    Block,
+    /// The line is dropped.
+    DropNewest,
+    /// The oldest line queued is dropped to make room.
+    DropOldest,
 }
 
 // fn timenow() -> (String, String, String) {
@@ -330,20 +1383,160 @@ pub enum CUTMODE {
 //     full_format.split('|').map(|s| s.to_string()).collect()
 // }
 
+/// Set by `set_time_zone`; takes precedence over the local time zone.
+static TIME_ZONE: RwLock<Option<FixedOffset>> = RwLock::new(None);
+
+/// UTC when the local time zone is unusable, decided once per process.
+static LOCAL_FALLBACK: Lazy<Option<FixedOffset>> = Lazy::new(|| {
+    let tz = env::var("TZ").ok().filter(|tz| !tz.is_empty());
+    let broken = std::panic::catch_unwind(Local::now).is_err() || tz.as_deref().is_some_and(|tz| !valid_tz(tz));
+    if !broken {
+        return None;
+    }
+    diagnostics::report(diagnostics::Category::TimeZone, None, format!("local time zone unavailable (TZ={}); using UTC, see tklog::set_time_zone", tz.unwrap_or_default()));
+    FixedOffset::east_opt(0)
+});
+
+/// True if `tz` names a readable zone file or is a POSIX rule such as `CST-8`.
+fn valid_tz(tz: &str) -> bool {
+    let name = tz.strip_prefix(':').unwrap_or(tz);
+    let file = if name.starts_with('/') { PathBuf::from(name) } else { Path::new("/usr/share/zoneinfo").join(name) };
+    file.is_file() || Regex::new(r"^(<[^>]+>|[A-Za-z]{3,})[+-]?\d").unwrap().is_match(tz)
+}
+
+/// The time zone of `set_time_zone`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeZoneOption {
+    /// The local time zone, or UTC when it is unusable. The default.
+    Local,
+    Utc,
+    /// A fixed offset in seconds east of UTC, less than a day either way.
+    FixedOffset(i32),
+}
+
+impl From<FixedOffset> for TimeZoneOption {
+    fn from(offset: FixedOffset) -> Self {
+        TimeZoneOption::FixedOffset(offset.local_minus_utc())
+    }
+}
+
+/// Renders timestamps, computes rotation periods and stamps backups in
+/// `zone`, a `TimeZoneOption` or a chrono `FixedOffset`, all of them read
+/// from this one setting so that they never disagree. Applies to every
+/// logger of the process; set it before the first line, as a period
+/// already started is compared in the new zone.
+pub fn set_time_zone(zone: impl Into<TimeZoneOption>) -> Result<(), Error> {
+    let offset = match zone.into() {
+        TimeZoneOption::Local => None,
+        TimeZoneOption::Utc => FixedOffset::east_opt(0),
+        TimeZoneOption::FixedOffset(secs) => Some(FixedOffset::east_opt(secs).ok_or(Error::InvalidTimeZone(secs))?),
+    };
+    *TIME_ZONE.write().unwrap_or_else(|e| e.into_inner()) = offset;
+    Ok(())
+}
+
+/// The time format of `Logger::set_time_rfc3339`, such as
+/// `2024-05-01T12:00:00.123456+08:00`.
+pub const RFC3339_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.6f%:z";
+
+/// `format` if chrono can render times with it, else
+/// `Error::InvalidTimeFormat`: rendering a bad format would panic.
+pub(crate) fn check_time_format(format: &str) -> Result<String, Error> {
+    use chrono::format::{Item, StrftimeItems};
+    match StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        true => Err(Error::InvalidTimeFormat(format.to_string())),
+        false => Ok(format.to_string()),
+    }
+}
+
+/// `columns` if each column is in it once at most and `MESSAGE` once,
+/// else `Error::InvalidColumnOrder`.
+pub(crate) fn check_column_order(columns: &[COLUMN]) -> Result<Arc<[COLUMN]>, Error> {
+    if columns.iter().filter(|&&c| c == COLUMN::MESSAGE).count() != 1 {
+        return Err(Error::InvalidColumnOrder("MESSAGE must be in it once"));
+    }
+    if columns.iter().enumerate().any(|(i, c)| columns[..i].contains(c)) {
+        return Err(Error::InvalidColumnOrder("a column is in it twice"));
+    }
+    Ok(columns.into())
+}
+
+/// Caps the bytes of lines queued in `PRINTMODE::DELAY` across every logger
+/// of the process; 0, the default, leaves them unbounded. Over the budget
+/// lines are shed in the order documented in `memory`, counted in
+/// `LogStats::memory`.
+pub fn set_memory_budget(bytes: u64) {
+    memory::set_budget(bytes);
+}
+
+/// Turns the per-label timings of `timing::Timer` on or off. Off, the
+/// default, timers record nothing. On, `flush_guard` logs the report as it
+/// is dropped.
+pub fn set_timing_report(enabled: bool) {
+    timing::set_enabled(enabled);
+}
+
+/// The timings recorded so far, see `timing`.
+pub fn timing_report() -> timing::TimingReport {
+    timing::report()
+}
+
+/// Sends every diagnostic of tklog about itself to `handler` instead of
+/// writing the warnings among them to stderr; `None` goes back to stderr.
+pub fn set_diagnostics_handler(handler: Option<diagnostics::DiagnosticsHandler>) {
+    diagnostics::set_handler(handler.map(Arc::from));
+}
+
+/// Probes the local time zone, falling back to UTC with a warning on stderr.
+fn init_time_zone() {
+    Lazy::force(&LOCAL_FALLBACK);
+}
+
 fn now() -> DateTime<Local> {
-    Local::now()
+    to_local(Utc::now())
 }
 
-fn datefmt(now: DateTime<Local>) -> String {
-    format!("{:04}-{:02}-{:02}", now.year(), now.month(), now.day())
+/// The time of the last line given a `subseq`, in microseconds, over the
+/// low `SUBSEQ_BITS` holding its `subseq`.
+static SUBSEQ: AtomicU64 = AtomicU64::new(0);
+const SUBSEQ_BITS: u32 = 12;
+const SUBSEQ_MAX: u64 = (1 << SUBSEQ_BITS) - 1;
+
+/// How many lines of the process before this one were stamped `time`, as
+/// rendered: to the microsecond with `micros`, else to the second. Lines
+/// given an older time than the last get 0.
+fn subseq(time: &DateTime<Local>, micros: bool) -> u32 {
+    let t = time.timestamp_micros() as u64;
+    let t = if micros { t } else { t - t % 1_000_000 };
+    let mut n = 0;
+    let _ = SUBSEQ.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |packed| {
+        let last = packed >> SUBSEQ_BITS;
+        n = if last == t { ((packed & SUBSEQ_MAX) + 1).min(SUBSEQ_MAX) } else { 0 };
+        (t >= last).then_some(t << SUBSEQ_BITS | n)
+    });
+    n as u32
 }
 
-fn datetimefmt(now: DateTime<Local>) -> String {
-    format!("{:02}:{:02}:{:02}", now.hour(), now.minute(), now.second())
+fn to_local(t: DateTime<Utc>) -> DateTime<Local> {
+    let offset = (*TIME_ZONE.read().unwrap_or_else(|e| e.into_inner())).or(*LOCAL_FALLBACK);
+    match offset {
+        Some(offset) => DateTime::from_naive_utc_and_offset(t.naive_utc(), offset),
+        None => t.with_timezone(&Local),
+    }
 }
 
-fn microsecondfmt(now: DateTime<Local>) -> String {
-    format!("{:06}", now.nanosecond() / 1_000)
+/// `t` as seconds of local wall-clock time, the unit of `startsec`.
+fn localsec(t: std::time::SystemTime) -> u64 {
+    to_local(t.into()).naive_local().and_utc().timestamp() as u64
+}
+
+/// The `startsec` of a log file found at open, with metadata `md`: the
+/// earlier of when it was created, where the filesystem keeps that, and
+/// when it was last written, so that a file of a period already over is
+/// cut by its first line after a restart.
+fn opened_startsec(md: &fs::Metadata) -> io::Result<u64> {
+    let modified = md.modified()?;
+    Ok(localsec(md.created().map_or(modified, |created| created.min(modified))))
 }
 
 #[allow(dead_code)]
@@ -365,71 +1558,361 @@ fn zlib(filename: &str) -> io::Result<()> {
     Ok(())
 }
 
-fn gzip(filename: &str) -> io::Result<()> {
+/// The first 64 KiB of a rotated file decide whether it is worth compressing.
+const COMPRESS_SAMPLE: usize = 64 * 1024;
+
+fn gz_encoder(level: u32) -> GzEncoder<Vec<u8>> {
+    GzEncoder::new(Vec::new(), Compression::new(level.min(9)))
+}
+
+/// Returns the ratio when `sample` compresses worse than `skip_ratio`.
+fn compress_skip(sample: &[u8], level: u32, skip_ratio: Option<f64>) -> io::Result<Option<f64>> {
+    let Some(skip_ratio) = skip_ratio else {
+        return Ok(None);
+    };
+    if sample.is_empty() {
+        return Ok(None);
+    }
+    let mut encoder = gz_encoder(level);
+    encoder.write_all(sample)?;
+    let ratio = encoder.finish()?.len() as f64 / sample.len() as f64;
+    Ok(if ratio > skip_ratio { Some(ratio) } else { None })
+}
+
+/// Whether `err` means the process or the system is out of file descriptors.
+pub(crate) fn fd_exhausted(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    return matches!(err.raw_os_error(), Some(libc::EMFILE) | Some(libc::ENFILE));
+    #[cfg(windows)]
+    return err.raw_os_error() == Some(4); // ERROR_TOO_MANY_OPEN_FILES
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = err;
+        false
+    }
+}
+
+/// Free bytes for unprivileged users on the filesystem holding `path`.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let c = CString::new(dir.as_os_str().as_bytes()).map_err(io::Error::other)?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c.as_ptr(), &mut st) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(st.f_bavail as u64 * st.f_frsize as u64)
+}
+
+/// Free bytes for the calling user on the volume holding `path`.
+#[cfg(windows)]
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    extern "system" {
+        fn GetDiskFreeSpaceExW(dir: *const u16, available: *mut u64, total: *mut u64, free: *mut u64) -> i32;
+    }
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    if unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, std::ptr::null_mut(), std::ptr::null_mut()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+/// Gives `path` the permission bits `mode` of `set_file_mode`, whatever
+/// the umask; nothing without a mode or off unix.
+pub(crate) fn set_file_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        return fs::set_permissions(path, fs::Permissions::from_mode(mode));
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
+}
+
+/// Whether `file` is still the file at `path`, rather than one another
+/// process has rotated away, see `set_shared_file`. Off unix, where an
+/// open file is seldom renamed, their sizes are compared.
+pub(crate) fn is_same_file(file: &File, path: &Path) -> bool {
+    let (Ok(open), Ok(named)) = (file.metadata(), fs::metadata(path)) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        open.dev() == named.dev() && open.ino() == named.ino()
+    }
+    #[cfg(not(unix))]
+    {
+        open.len() == named.len()
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn available_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Best effort: `Some(NoSpace)` if the probe reports less free space than
+/// compressing `backup` is estimated to need. A failing probe lets
+/// compression go ahead.
+fn space_preflight(backup: &Path, settings: &handle::FileSettings) -> Option<CompressDecision> {
+    let size = fs::metadata(backup).ok()?.len();
+    let needed = (size as f64 * settings.compress_space_factor).ceil() as u64;
+    let available = (settings.space_probe)(backup).ok()?;
+    if available >= needed {
+        return None;
+    }
+    diagnostics::report(diagnostics::Category::CompressionSkipped, Some(backup), format!("not compressing {}: {} bytes needed, {} available", backup.display(), needed, available));
+    Some(CompressDecision::NoSpace { needed, available })
+}
+
+/// Writes `filename` gzipped to `filename.gz` and deletes it, unless
+/// `keep_original`: then a backup with its `.gz` already is left as it is.
+fn gzip(filename: &str, level: u32, skip_ratio: Option<f64>, mode: Option<u32>, keep_original: bool) -> io::Result<CompressDecision> {
+    let output_filename = format!("{}.gz", filename);
+    if keep_original && Path::new(&output_filename).exists() {
+        return Ok(CompressDecision::Compressed);
+    }
     let mut input_file = File::open(filename)?;
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let modified = input_file.metadata()?.modified()?;
+    let mut sample = Vec::new();
+    (&mut input_file).take(COMPRESS_SAMPLE as u64).read_to_end(&mut sample)?;
+    if let Some(ratio) = compress_skip(&sample, level, skip_ratio)? {
+        return Ok(CompressDecision::Skipped { ratio });
+    }
+    let mut encoder = gz_encoder(level);
+    encoder.write_all(&sample)?;
     io::copy(&mut input_file, &mut encoder)?;
     let compressed_data = encoder.finish()?;
-    let output_filename = format!("{}.gz", filename);
-    let mut output_file = File::create(&output_filename)?;
-    let ack = output_file.write_all(&compressed_data);
-    if ack.is_ok() {
+    // A `.gz` of the same name is left alone, and the backup uncompressed.
+    let mut output_file = File::options().write(true).create_new(true).open(&output_filename)?;
+    // The `.gz` keeps the time of the backup, for the age pruning and the
+    // order of `set_compress_after`.
+    if let Err(e) = set_file_mode(Path::new(&output_filename), mode).and_then(|()| output_file.write_all(&compressed_data)).and_then(|()| output_file.set_modified(modified)) {
+        let _ = fs::remove_file(&output_filename);
+        return Err(e);
+    }
+    if !keep_original {
         let _ = fs::remove_file(filename);
     }
-    Ok(())
+    Ok(CompressDecision::Compressed)
 }
 
-async fn async_gzip(filename: &str) -> io::Result<()> {
-    let mut input_file = tokio::fs::File::open(filename).await?;
-    let mut file_content = Vec::new();
-    input_file.read_to_end(&mut file_content).await?;
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+async fn async_gzip(filename: &str, level: u32, skip_ratio: Option<f64>, mode: Option<u32>, keep_original: bool) -> io::Result<CompressDecision> {
+    let output_filename = format!("{}.gz", filename);
+    if keep_original && Path::new(&output_filename).exists() {
+        return Ok(CompressDecision::Compressed);
+    }
+    let modified = Rt::metadata(Path::new(filename)).await?.modified()?;
+    let file_content = Rt::read(Path::new(filename)).await?;
+    if let Some(ratio) = compress_skip(&file_content[..file_content.len().min(COMPRESS_SAMPLE)], level, skip_ratio)? {
+        return Ok(CompressDecision::Skipped { ratio });
+    }
+    let mut encoder = gz_encoder(level);
     let _ = encoder.write_all(&file_content);
     let compressed_data = encoder.finish()?;
-    let output_filename = format!("{}.gz", filename);
-    let mut output_file = tokio::fs::File::create(output_filename).await?;
-    tokio::io::AsyncWriteExt::write_all(&mut output_file, &compressed_data).await?;
-    let _ = tokio::fs::remove_file(filename).await?;
-    Ok(())
+    let output_path = Path::new(&output_filename);
+    let mut output_file = Rt::create_new(output_path).await?;
+    let written = match set_file_mode(output_path, mode) {
+        Ok(()) => Rt::write_all(&mut output_file, &compressed_data).await,
+        Err(e) => Err(e),
+    };
+    let written = match written {
+        Ok(()) => Rt::std_clone(&output_file).await.and_then(|f| f.set_modified(modified)),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = written {
+        let _ = Rt::remove_file(output_path).await;
+        return Err(e);
+    }
+    if !keep_original {
+        Rt::remove_file(Path::new(filename)).await?;
+    }
+    Ok(CompressDecision::Compressed)
+}
+
+/// True if the template `formatter` has the placeholder `name`, with or
+/// without a case modifier.
+pub(crate) fn places(formatter: &str, name: &str) -> bool {
+    formatter.match_indices(name).any(|(i, _)| formatter[..i].ends_with('{') && matches!(formatter[i + name.len()..].chars().next(), Some('}' | ':')))
+}
+
+/// The std fmt style `[[fill]align][width][.precision]` modifier of a
+/// placeholder, such as `{level:<5}`, `{file:>30}` or `{file:.30}`.
+struct Spec {
+    fill: char,
+    align: Option<char>,
+    width: usize,
+    precision: Option<usize>,
 }
 
+impl Spec {
+    fn parse(spec: &str) -> Option<Spec> {
+        let mut chars = spec.chars();
+        let (fill, align, rest) = match (chars.next(), chars.next()) {
+            (Some(fill), Some(align @ ('<' | '^' | '>'))) => (fill, Some(align), &spec[fill.len_utf8() + 1..]),
+            (Some(align @ ('<' | '^' | '>')), _) => (' ', Some(align), &spec[1..]),
+            _ => (' ', None, spec),
+        };
+        let number = |s: &str| s.bytes().all(|b| b.is_ascii_digit()).then(|| s.parse().ok()).flatten();
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(number(precision)?)),
+            None => (rest, None),
+        };
+        let width = if width.is_empty() { 0 } else { number(width)? };
+        Some(Spec { fill, align, width, precision })
+    }
+
+    /// Truncates `s[mark..]` to the precision, keeping its tail if `tail`,
+    /// then pads it to the width, counting chars.
+    fn apply(&self, s: &mut String, mark: usize, tail: bool) {
+        let len = s[mark..].chars().count();
+        match self.precision {
+            Some(max) if len > max && tail => {
+                let cut = s[mark..].char_indices().nth(len - max).map_or(s.len(), |(i, _)| mark + i);
+                s.replace_range(mark..cut, "");
+            }
+            Some(max) if len > max => {
+                let cut = s[mark..].char_indices().nth(max).map_or(s.len(), |(i, _)| mark + i);
+                s.truncate(cut);
+            }
+            _ => {}
+        }
+        let pad = self.width.saturating_sub(len.min(self.precision.unwrap_or(len)));
+        let before = match self.align {
+            Some('>') => pad,
+            Some('^') => pad / 2,
+            _ => 0,
+        };
+        for _ in before..pad {
+            s.push(self.fill);
+        }
+        s.insert_str(mark, &self.fill.to_string().repeat(before));
+    }
+}
+
+/// `format_str` with its placeholders filled in; `{name:upper}` and
+/// `{name:lower}` change the case of ASCII letters only, so the line is the
+/// same under any locale, and `{name:<5}` and the like pad or truncate as
+/// `Spec` says; `{file:.30}` keeps the tail of the path. A modifier tklog
+/// can't read leaves the value as it is. `{{` and `}}` are braces; an
+/// unknown or unclosed placeholder is kept as it is, so that a typo shows
+/// in the line, and a brace inside one starts it again.
 fn parse_and_format_log(
     format_str: &str,
     level: &str,
     time: &str,
     file: &str,
     message: &str,
+    record: &RecordSnapshot,
 ) -> String {
     let mut result = String::with_capacity(
         format_str.len() + level.len() + time.len() + file.len() + message.len(),
     );
-    let mut in_placeholder = false;
-    let mut placeholder = String::new();
-
-    for c in format_str.chars() {
-        if in_placeholder {
-            if c == '}' {
-                in_placeholder = false;
-                match placeholder.as_str() {
+    let mut placeholder = None;
+    let mut chars = format_str.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if let Some(start) = placeholder {
+            if c == '{' {
+                result.push_str(&format_str[start - 1..i]);
+                placeholder = Some(i + 1);
+            } else if c == '}' {
+                placeholder = None;
+                let (name, modifier) = match format_str[start..i].split_once(':') {
+                    Some((name, modifier)) => (name, Some(modifier)),
+                    None => (&format_str[start..i], None),
+                };
+                let mark = result.len();
+                match name {
                     "level" => result.push_str(level),
                     "time" => result.push_str(time),
                     "file" => result.push_str(file),
                     "message" => result.push_str(message),
-                    _ => (),
+                    "seq" => {
+                        let _ = write!(result, "{}", record.seq);
+                    }
+                    "subseq" => {
+                        let _ = write!(result, "{}", record.subseq.unwrap_or(0));
+                    }
+                    "event" => result.push_str(record.event.unwrap_or("")),
+                    "bootid" => result.push_str(boot::boot_id()),
+                    "module" => result.push_str(&record.module),
+                    "line" => {
+                        if !file.is_empty() {
+                            let _ = write!(result, "{}", record.line);
+                        }
+                    }
+                    "thread" => result.push_str(record.thread.as_deref().unwrap_or("")),
+                    "function" => result.push_str(record.function.unwrap_or("")),
+                    "task" => {
+                        if let Some(id) = record.task {
+                            let _ = write!(result, "{}", id);
+                        }
+                    }
+                    "pid" => {
+                        let _ = write!(result, "{}", std::process::id());
+                    }
+                    _ => {
+                        result.push_str(&format_str[start - 1..=i]);
+                        continue;
+                    }
+                }
+                match modifier {
+                    Some("upper") => result[mark..].make_ascii_uppercase(),
+                    Some("lower") => result[mark..].make_ascii_lowercase(),
+                    Some(spec) => {
+                        if let Some(spec) = Spec::parse(spec) {
+                            spec.apply(&mut result, mark, name == "file");
+                        }
+                    }
+                    None => {}
                 }
-                placeholder.clear();
-            } else {
-                placeholder.push(c);
             }
-        } else if c == '{' {
-            in_placeholder = true;
+        } else if c == '{' && chars.next_if(|&(_, c)| c == '{').is_none() {
+            placeholder = Some(i + 1);
         } else {
+            // The second brace of `{{` or `}}` was taken above.
+            chars.next_if(|&(_, next)| c == '}' && next == '}');
             result.push(c);
         }
     }
+    if let Some(start) = placeholder {
+        result.push_str(&format_str[start - 1..]);
+    }
     result
 }
 
+/// The file of the period containing `startsec` for the strftime `pattern`
+/// of `CUTMODE::PATTERN`; an interval is named after its start.
+fn dated_name(pattern: &str, startsec: u64, timemode: MODE) -> String {
+    let secs = match timemode {
+        MODE::INTERVAL(interval) => startsec / interval_secs(interval) * interval_secs(interval),
+        _ => startsec,
+    };
+    DateTime::from_timestamp(secs as i64, 0).unwrap_or_default().naive_utc().format(pattern).to_string()
+}
+
+/// Errs unless `pattern` can name the files of `CUTMODE::PATTERN`: a
+/// strftime format with a date or time in the file name, none in its
+/// directories, which retention doesn't look through.
+pub(crate) fn check_file_pattern(pattern: &str) -> Result<(), Error> {
+    check_time_format(pattern)?;
+    let path = Path::new(pattern);
+    if path.parent().is_some_and(|dir| dir.to_string_lossy().contains('%')) {
+        return Err(Error::InvalidCut("the directory of a dated file can't change with the date"));
+    }
+    match path.file_name() {
+        Some(name) if name.to_string_lossy().contains('%') => Ok(()),
+        _ => Err(Error::InvalidCut("the file name has no date in it")),
+    }
+}
+
 fn getbackup_with_time(startsec: u64, timemode: MODE) -> String {
     let start_time = DateTime::from_timestamp(startsec as i64, 0).expect("");
     match timemode {
@@ -445,130 +1928,241 @@ fn getbackup_with_time(startsec: u64, timemode: MODE) -> String {
             let formatted_date = start_time.format("%Y%m");
             formatted_date.to_string()
         }
+        MODE::INTERVAL(interval) => {
+            let n = interval_secs(interval);
+            let start_time = DateTime::from_timestamp((startsec / n * n) as i64, 0).expect("");
+            start_time.format(if n.is_multiple_of(60) { "%Y%m%d%H%M" } else { "%Y%m%d%H%M%S" }).to_string()
+        }
     }
 }
 
+/// The line of `record` with `msg` as its message, built in one buffer;
+/// `strftime` is the format of `Logger::set_time_format`.
+#[allow(clippy::too_many_arguments)]
 fn log_fmt<LF, TF>(
     levelfmt: Option<LF>,
     timefmt: Option<TF>,
+    strftime: Option<&str>,
     fmat: u8,
     formatter: Option<&String>,
-    level: LEVEL,
-    filename: &str,
-    line: u32,
+    columns: Option<&[COLUMN]>,
+    record: &RecordSnapshot,
     msg: &str,
 ) -> String
 where
-    LF: Fn(LEVEL) -> String,
-    TF: Fn() -> (String, String, String),
+    LF: Fn(LEVEL) -> Option<String>,
+    TF: Fn() -> Option<(String, String, String)>,
 {
     if fmat == Format::Nano {
         return msg.to_string();
     }
-
-    let mut levelflag = String::new();
-    let mut time = String::new();
-    let mut file = String::new();
-
-    if fmat & Format::LevelFlag != 0 {
-        if let Some(f) = levelfmt {
-            levelflag = f(level);
+    let (level, filename, line) = (record.level, record.file.as_ref(), record.line);
+
+    let customlevel = if fmat & Format::LevelFlag != 0 { levelfmt.and_then(|f| f(level)) } else { None };
+    let levelflag = match &customlevel {
+        Some(s) => s.as_str(),
+        None if fmat & Format::LevelFlag != 0 => level_flag(level),
+        None => "",
+    };
+
+    let customtime = if fmat & (Format::Date | Format::Time | Format::Microseconds) != 0 { timefmt.and_then(|f| f()) } else { None };
+    let subseq = record.subseq.filter(|_| !formatter.is_some_and(|f| places(f, "subseq")));
+    let timecap = customtime.as_ref().map_or(strftime.map_or(26, |f| f.len() + 16), |t| t.0.len() + t.1.len() + t.2.len() + 2) + 11;
+    let file = if fmat & (Format::LongFileName | Format::ShortFileName) != 0 && !filename.is_empty() {
+        if fmat & Format::ShortFileName != 0 {
+            get_short_file_path(filename)
         } else {
-            levelflag = match level {
-                LEVEL::Trace => "[TRACE]",
-                LEVEL::Debug => "[DEBUG]",
-                LEVEL::Info => "[INFO]",
-                LEVEL::Warn => "[WARN]",
-                LEVEL::Error => "[ERROR]",
-                LEVEL::Fatal => "[FATAL]",
-                LEVEL::Off => "",
-            }
-            .to_string();
+            filename
         }
-    }
-
-    if fmat & (Format::Date | Format::Time | Format::Microseconds) != 0 {
-        let mut tss: (String, String, String);
-        if let Some(f) = timefmt {
-            tss = f();
-            if fmat & Format::Date != 0 {
-                tss.0.clear();
-            }
-            if fmat & Format::Time != 0 {
-                tss.1.clear();
-            }
-            if fmat & Format::Microseconds != 0 {
-                tss.2.clear();
-            }
-        } else {
-            let localtime = now();
-            tss = (String::new(), String::new(), String::new());
-            if fmat & Format::Date != 0 {
-                tss.0 = datefmt(localtime);
-            }
-            if fmat & (Format::Time | Format::Microseconds) != 0 {
-                tss.1 = datetimefmt(localtime);
-                if fmat & Format::Microseconds != 0 {
-                    tss.2 = microsecondfmt(localtime);
+    } else {
+        ""
+    };
+    let write_file = |out: &mut String| {
+        if !file.is_empty() {
+            let _ = write!(out, "{} {}", file, line);
+        }
+    };
+
+    let Some(fmts) = formatter else {
+        let thread = record.thread.as_deref().filter(|_| fmat & Format::ThreadId != 0).unwrap_or("");
+        let function = record.function.filter(|_| fmat & Format::FuncName != 0).unwrap_or("");
+        let mut r = String::with_capacity(levelflag.len() + timecap + thread.len() + file.len() + function.len() + msg.len() + 16);
+        if let Some(columns) = columns {
+            // Columns the format leaves empty are skipped along with their
+            // space; `COLON` follows the column before it only if that one
+            // was written, and the next column follows it with no space.
+            let (mut glued, mut written) = (true, false);
+            for column in columns {
+                let start = r.len();
+                if *column == COLUMN::COLON {
+                    if written {
+                        r.push(':');
+                        glued = true;
+                    }
+                    written = false;
+                    continue;
+                }
+                if !glued {
+                    r.push(' ');
+                }
+                let text = r.len();
+                match column {
+                    COLUMN::LOGFLAG => r.push_str(levelflag),
+                    COLUMN::TIME => write_time(&mut r, fmat, customtime.as_ref(), strftime, record.time, subseq),
+                    COLUMN::THREAD => r.push_str(thread),
+                    COLUMN::FILEFLAG => {
+                        write_file(&mut r);
+                        if !file.is_empty() && !function.is_empty() {
+                            r.push(' ');
+                        }
+                        r.push_str(function);
+                    }
+                    COLUMN::MESSAGE => r.push_str(msg),
+                    COLUMN::COLON => {}
+                }
+                written = r.len() > text || *column == COLUMN::MESSAGE;
+                if written {
+                    glued = false;
+                } else {
+                    r.truncate(start);
                 }
             }
+            r.push('\n');
+            return r;
         }
-        if !tss.0.is_empty() {
-            time.push_str(tss.0.as_str());
+        r.push_str(levelflag);
+        r.push(' ');
+        let start = r.len();
+        write_time(&mut r, fmat, customtime.as_ref(), strftime, record.time, subseq);
+        if r.len() > start {
+            r.push(' ');
         }
-
-        if !tss.1.is_empty() {
-            if !time.is_empty() {
-                time.push(' ');
+        if !thread.is_empty() {
+            r.push_str(thread);
+            r.push(' ');
+        }
+        if !file.is_empty() || !function.is_empty() {
+            write_file(&mut r);
+            if !file.is_empty() && !function.is_empty() {
+                r.push(' ');
             }
-            time.push_str(tss.1.as_str());
+            r.push_str(function);
+            r.push(':');
         }
+        r.push_str(msg);
+        r.push('\n');
+        return r;
+    };
+    let mut parts = String::with_capacity(timecap + file.len() + 10);
+    write_time(&mut parts, fmat, customtime.as_ref(), strftime, record.time, subseq);
+    let timelen = parts.len();
+    // `{line}` takes the line out of `{file}`.
+    if places(fmts, "line") {
+        parts.push_str(file);
+    } else {
+        write_file(&mut parts);
+    }
+    let (time, file) = parts.split_at(timelen);
+    parse_and_format_log(fmts.as_str(), levelflag, time, file, msg, record)
+}
+
+/// What `log_fmt` puts before a message when there is no formatter, file
+/// name, custom level or time, or subseq, kept while the level, format and
+/// second stay the same; see `Logger::print_static`.
+#[derive(Default)]
+pub(crate) struct StaticPrefix {
+    key: Option<(LEVEL, u8, i64)>,
+    prefix: String,
+}
 
-        if !tss.2.is_empty() {
-            if !time.is_empty() {
-                time.push('.');
+impl StaticPrefix {
+    pub(crate) fn get(&mut self, level: LEVEL, fmat: u8, time: DateTime<Local>) -> &str {
+        let key = Some((level, fmat, time.timestamp()));
+        // Microseconds change with every line.
+        if self.key != key || fmat & Format::Microseconds != 0 {
+            self.prefix.clear();
+            if fmat & Format::LevelFlag != 0 {
+                self.prefix.push_str(level_flag(level));
+            }
+            self.prefix.push(' ');
+            let start = self.prefix.len();
+            write_time(&mut self.prefix, fmat, None, None, time, None);
+            if self.prefix.len() > start {
+                self.prefix.push(' ');
             }
-            time.push_str(tss.2.as_str());
+            self.key = key;
         }
+        &self.prefix
     }
-    if fmat & (Format::LongFileName | Format::ShortFileName) != 0 {
-        let mut f = filename;
-        if fmat & Format::ShortFileName != 0 {
-            f = get_short_file_path(f)
+}
+
+thread_local! {
+    /// The label of this thread, made by its first line: a thread's name
+    /// can't change once it runs.
+    static THREAD_LABEL: Arc<str> = {
+        let thread = std::thread::current();
+        match thread.name() {
+            Some(name) => name.into(),
+            None => format!("{:?}", thread.id()).trim_start_matches("ThreadId(").trim_end_matches(')').into(),
         }
-        file.push_str(f);
-        file.push(' ');
-        file.push_str(line.to_string().as_str());
+    };
+}
+
+/// The name of the current thread, or its ID when it has none, for
+/// `{thread}` and `Format::ThreadId`.
+pub(crate) fn thread_label() -> Arc<str> {
+    THREAD_LABEL.with(Arc::clone)
+}
+
+pub(crate) fn level_flag(level: LEVEL) -> &'static str {
+    match level {
+        LEVEL::Trace => "[TRACE]",
+        LEVEL::Debug => "[DEBUG]",
+        LEVEL::Info => "[INFO]",
+        LEVEL::Warn => "[WARN]",
+        LEVEL::Error => "[ERROR]",
+        LEVEL::Fatal => "[FATAL]",
+        LEVEL::Off => "",
     }
+}
 
-    if formatter.is_none() {
-        let mut r =
-            String::with_capacity(levelflag.len() + time.len() + file.len() + msg.len() + 4);
-        if !levelflag.is_empty() {
-            r.push_str(&levelflag);
-        }
-        if !time.is_empty() {
-            r.push(' ');
-            r.push_str(&time);
+/// Appends the date, time and microseconds `fmat` asks for, from `custom`
+/// if the time format gave them, else all of `localtime` in `strftime` if
+/// set, then `#subseq`.
+fn write_time(out: &mut String, fmat: u8, custom: Option<&(String, String, String)>, strftime: Option<&str>, localtime: DateTime<Local>, subseq: Option<u32>) {
+    let start = out.len();
+    let sep = |out: &mut String, c: char| {
+        if out.len() > start {
+            out.push(c);
         }
-        r.push(' ');
-        if !file.is_empty() {
-            r.push_str(&file);
-            r.push(':');
+    };
+    let strftime = strftime.filter(|_| custom.is_none() && fmat & (Format::Date | Format::Time | Format::Microseconds) != 0);
+    if let Some(format) = strftime {
+        let _ = write!(out, "{}", localtime.format(format));
+    } else if let Some((date, time, micros)) = custom {
+        // A part the format asks for is left out of a custom time.
+        for (part, flag, c) in [(date, Format::Date, ' '), (time, Format::Time, ' '), (micros, Format::Microseconds, '.')] {
+            if fmat & flag == 0 && !part.is_empty() {
+                sep(out, c);
+                out.push_str(part);
+            }
         }
-
-        r.push_str(&msg);
-        r.push('\n');
-        return r;
     } else {
-        let fmts = formatter.unwrap();
-        return parse_and_format_log(
-            fmts.as_str(),
-            levelflag.as_str(),
-            time.as_str(),
-            file.as_str(),
-            msg,
-        );
+        if fmat & Format::Date != 0 {
+            let _ = write!(out, "{:04}-{:02}-{:02}", localtime.year(), localtime.month(), localtime.day());
+        }
+        if fmat & (Format::Time | Format::Microseconds) != 0 {
+            sep(out, ' ');
+            let _ = write!(out, "{:02}:{:02}:{:02}", localtime.hour(), localtime.minute(), localtime.second());
+            if fmat & Format::Microseconds != 0 {
+                let _ = write!(out, ".{:06}", localtime.nanosecond() / 1_000);
+            }
+        }
+    }
+    if let Some(n) = subseq {
+        if out.len() > start {
+            let _ = write!(out, "#{}", n);
+        }
     }
 }
 
@@ -587,40 +2181,190 @@ fn get_short_file_path(filename: &str) -> &str {
 }
 
 fn timesec() -> u64 {
-    let now: NaiveDateTime = Local::now().naive_local();
+    let now: NaiveDateTime = now().naive_local();
     return now.and_utc().timestamp() as u64;
 }
 
+/// Whether the period containing `startsec` is over, by the same
+/// boundaries as `next_rotation`.
 fn passtimemode(startsec: u64, timemode: MODE) -> bool {
-    let start_time = DateTime::from_timestamp(startsec as i64, 0).expect("");
-    let now: NaiveDateTime = Local::now().naive_local();
+    timesec() >= next_rotation(startsec, timemode)
+}
 
-    let (now_year, now_month, now_day, now_hour) = (now.year(), now.month(), now.day(), now.hour());
+/// The length of a `MODE::INTERVAL` period in seconds.
+fn interval_secs(interval: std::time::Duration) -> u64 {
+    interval.as_secs().max(1)
+}
 
-    let (start_year, start_month, start_day, start_hour) = (
-        start_time.year(),
-        start_time.month(),
-        start_time.day(),
-        start_time.hour(),
-    );
+/// The first second of the period after the one containing `startsec`, in
+/// the wall-clock seconds of `timesec`: the instant `passtimemode` turns true.
+fn next_rotation(startsec: u64, timemode: MODE) -> u64 {
+    let start = DateTime::from_timestamp(startsec as i64, 0).expect("").naive_utc();
+    let date = start.date();
+    let next = match timemode {
+        MODE::HOUR => date.and_hms_opt(start.hour(), 0, 0).expect("") + TimeDelta::hours(1),
+        MODE::DAY => date.and_hms_opt(0, 0, 0).expect("") + TimeDelta::days(1),
+        MODE::MONTH => {
+            let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+            NaiveDate::from_ymd_opt(year, month, 1).expect("").and_hms_opt(0, 0, 0).expect("")
+        }
+        MODE::INTERVAL(interval) => {
+            let n = interval_secs(interval);
+            return (startsec / n + 1) * n;
+        }
+    };
+    next.and_utc().timestamp() as u64
+}
 
-    match timemode {
-        MODE::HOUR => {
-            now_year > start_year
-                || (now_year == start_year && now_month > start_month)
-                || (now_year == start_year && now_month == start_month && now_day > start_day)
-                || (now_year == start_year
-                    && now_month == start_month
-                    && now_day == start_day
-                    && now_hour > start_hour)
+/// Matches the backups of a log file named `stem.extension`:
+/// `stem_1.ext`, `stem_20240501_1.ext`, optionally followed by `.gz`.
+fn backup_pattern(stem: &str, extension: &str) -> Regex {
+    let mut suffix = String::new();
+    if !extension.is_empty() {
+        suffix.push_str("\\.");
+        suffix.push_str(&regex::escape(extension));
+    }
+    Regex::new(&format!(r"^{}((?:_\d+)*)_(\d+){}(\.gz)?$", regex::escape(stem), suffix)).unwrap()
+}
+
+/// The counter of the next backup of `log_path` in period `stamp`, or of a
+/// size backup without one: one past the highest there is, compressed or
+/// not, so counters keep growing once the lowest backups are pruned.
+fn next_backup_counter(log_path: &Path, backups: &Backups, stamp: Option<&str>) -> u64 {
+    let dir = match log_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => env::current_dir().unwrap_or_default(),
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 1;
+    };
+    let mut highest = 0;
+    for entry in entries.flatten() {
+        if let Some(counter) = entry.file_name().to_str().and_then(|n| backups.counter_in(n, stamp)) {
+            highest = highest.max(counter);
         }
-        MODE::DAY => {
-            now_year > start_year
-                || (now_year == start_year && now_month > start_month)
-                || (now_year == start_year && now_month == start_month && now_day > start_day)
+    }
+    highest + 1
+}
+
+/// Moves `from` to the backup `to` unless `to` exists, atomically where the
+/// filesystem has hard links; false, with `from` left, when `to` is taken.
+fn rename_unless_exists(from: &Path, to: &Path) -> io::Result<bool> {
+    match fs::hard_link(from, to) {
+        Ok(()) => fs::remove_file(from).map(|()| true),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(false),
+        Err(_) if !to.exists() => fs::rename(from, to).map(|()| true),
+        Err(e) => Err(e),
+    }
+}
+
+async fn async_rename_unless_exists(from: &Path, to: &Path) -> io::Result<bool> {
+    match Rt::hard_link(from, to).await {
+        Ok(()) => Rt::remove_file(from).await.map(|()| true),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(false),
+        Err(_) if !to.exists() => Rt::rename(from, to).await.map(|()| true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Picks the backups to delete so that at most `maxbackup` files, or periods
+/// under `PrunePolicy::ByPeriod`, remain. `candidates` are (modified secs, path);
+/// files go oldest first by the period stamp and counter in their names,
+/// the modification time breaking ties. A backup kept along with its `.gz`,
+/// see `set_compress_keep_original`, counts as one file.
+fn backups_to_prune(mut candidates: Vec<(u64, PathBuf)>, backups: &Backups, maxbackup: u32, policy: PrunePolicy) -> Vec<PathBuf> {
+    let maxbackup = maxbackup as usize;
+    if policy == PrunePolicy::ByPeriod {
+        let stamps: Option<Vec<u64>> = candidates.iter().map(|(_, p)| backups.period(p)).collect();
+        if let Some(stamps) = stamps {
+            let mut periods = stamps.clone();
+            periods.sort();
+            periods.dedup();
+            if periods.len() <= maxbackup {
+                return Vec::new();
+            }
+            let expired = &periods[..periods.len() - maxbackup];
+            return unqueued(candidates.into_iter().zip(stamps).filter(|(_, s)| expired.contains(s)).map(|((_, p), _)| p).collect());
+        }
+    }
+    let names = names_by_age(&mut candidates, backups);
+    if names.len() <= maxbackup {
+        return Vec::new();
+    }
+    let expired = &names[..names.len() - maxbackup];
+    unqueued(candidates.into_iter().map(|c| c.1).filter(|p| expired.contains(&backup_name(p))).collect())
+}
+
+/// The raw backups among `candidates` older than the newest `keep`, with
+/// no `.gz` yet: those `set_compress_after` compresses.
+fn due_for_compression(mut candidates: Vec<(u64, PathBuf)>, backups: &Backups, keep: u32) -> Vec<PathBuf> {
+    let names = names_by_age(&mut candidates, backups);
+    let old = &names[..names.len().saturating_sub(keep as usize)];
+    let compressed: Vec<String> = candidates.iter().filter(|(_, p)| is_gz(p)).map(|(_, p)| backup_name(p)).collect();
+    candidates.into_iter().map(|c| c.1).filter(|p| !is_gz(p) && old.contains(&backup_name(p)) && !compressed.contains(&backup_name(p))).collect()
+}
+
+/// Sorts `candidates` oldest first, as `backups_to_prune` says, and gives
+/// the names of their backups in that order.
+fn names_by_age(candidates: &mut [(u64, PathBuf)], backups: &Backups) -> Vec<String> {
+    candidates.sort_by_key(|(secs, p)| (backups.order(p), *secs));
+    let mut names: Vec<String> = Vec::new();
+    for (_, p) in candidates.iter() {
+        let name = backup_name(p);
+        if !names.contains(&name) {
+            names.push(name);
         }
-        MODE::MONTH => now_year > start_year || (now_year == start_year && now_month > start_month),
     }
+    names
+}
+
+/// The name of the backup at `path`, the same for it raw and compressed.
+fn backup_name(path: &Path) -> String {
+    let s = path.to_string_lossy();
+    s.strip_suffix(".gz").unwrap_or(&s).to_string()
+}
+
+fn is_gz(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gz")
+}
+
+/// The backups among `candidates`, (modified secs, path), cut more than
+/// `max_age` before `now`, in secs since the epoch; a backup is last
+/// written when it is cut.
+fn backups_older_than(candidates: Vec<(u64, PathBuf)>, max_age: std::time::Duration, now: u64) -> Vec<PathBuf> {
+    unqueued(candidates.into_iter().filter(|(secs, _)| secs.saturating_add(max_age.as_secs()) < now).map(|(_, p)| p).collect())
+}
+
+/// The backups rotated with their compression still to run. A burst of
+/// rotations queues several at once, and the pruning of one leaves the
+/// others to their own, see `archive`.
+static QUEUED_BACKUPS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Keeps pruning away from `backup` until `dequeue_backup`.
+fn queue_backup(backup: &Path) {
+    QUEUED_BACKUPS.lock().unwrap_or_else(|e| e.into_inner()).push(backup.to_path_buf());
+}
+
+fn dequeue_backup(backup: &Path) {
+    let mut queued = QUEUED_BACKUPS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(i) = queued.iter().position(|p| p == backup) {
+        queued.swap_remove(i);
+    }
+}
+
+/// `files` but the backups queued for compression.
+fn unqueued(mut files: Vec<PathBuf>) -> Vec<PathBuf> {
+    let queued = QUEUED_BACKUPS.lock().unwrap_or_else(|e| e.into_inner());
+    files.retain(|f| !queued.contains(f));
+    files
+}
+
+/// Seconds since the epoch, to compare with modification times.
+fn epoch_secs() -> u64 {
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    return wasm::epoch_secs();
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
 }
 
 fn l2tk(level: log::Level) -> LEVEL {
@@ -637,12 +2381,19 @@ fn arguments_to_string(args: &std::fmt::Arguments) -> String {
     fmt::format(*args)
 }
 
+type LevelFmt = Arc<Guarded<dyn Fn(LEVEL) -> String + Send + Sync>>;
+type TimeFmt = Arc<Guarded<dyn Fn() -> (String, String, String) + Send + Sync>>;
+type BodyFmt = Arc<Guarded<dyn Fn(LEVEL, String) -> String + Send + Sync>>;
+
+/// The formatting callbacks. One that panics is skipped for that line, see
+/// `guard`, and the built-in format is used instead.
+#[derive(Clone, Default)]
 pub struct AttrFormat {
-    levelfmt: Option<Box<dyn Fn(LEVEL) -> String + Send + Sync>>,
-    timefmt: Option<Box<dyn Fn() -> (String, String, String) + Send + Sync>>,
+    levelfmt: Option<LevelFmt>,
+    timefmt: Option<TimeFmt>,
     // bodyfmt: Option<Box<dyn Fn(LEVEL, String) -> String + Send + Sync>>,
-    filebodyfmt: Option<Box<dyn Fn(LEVEL, String) -> String + Send + Sync>>,
-    consolebodyfmt: Option<Box<dyn Fn(LEVEL, String) -> String + Send + Sync>>,
+    filebodyfmt: Option<BodyFmt>,
+    consolebodyfmt: Option<BodyFmt>,
 }
 
 impl AttrFormat {
@@ -675,7 +2426,7 @@ impl AttrFormat {
     where
         F: Fn(LEVEL) -> String + Send + Sync + 'static,
     {
-        self.levelfmt = Some(Box::new(levelfmt));
+        self.levelfmt = Some(Arc::new(Guarded::new("level format", levelfmt)));
     }
 
     /// - This function splits a date into three parts and returns a tuple (String, String, String).
@@ -695,7 +2446,7 @@ impl AttrFormat {
     where
         F: Fn() -> (String, String, String) + Send + Sync + 'static,
     {
-        self.timefmt = Some(Box::new(timefmt));
+        self.timefmt = Some(Arc::new(Guarded::new("time format", timefmt)));
     }
 
     /// Shortcut for setting the function to reprocess **both the console format and the file format** of logs.  
@@ -718,7 +2469,7 @@ impl AttrFormat {
     where
         F: Fn(LEVEL, String) -> String + Send + Sync + 'static,
     {
-        self.filebodyfmt = Some(Box::new(bodyfmt));
+        self.filebodyfmt = Some(Arc::new(Guarded::new("file body format", bodyfmt)));
         self.consolebodyfmt = None; // Setting it to none will force the console to use filebodyfmt.
     }
 
@@ -734,7 +2485,7 @@ impl AttrFormat {
     where
         F: Fn(LEVEL, String) -> String + Send + Sync + 'static,
     {
-        self.filebodyfmt = Some(Box::new(filebodyfmt));
+        self.filebodyfmt = Some(Arc::new(Guarded::new("file body format", filebodyfmt)));
     }
 
     /// Set the function to reprocess logs **writing to console**  
@@ -749,6 +2500,6 @@ impl AttrFormat {
     where
         F: Fn(LEVEL, String) -> String + Send + Sync + 'static,
     {
-        self.consolebodyfmt = Some(Box::new(consolebodyfmt));
+        self.consolebodyfmt = Some(Arc::new(Guarded::new("console body format", consolebodyfmt)));
     }
 }
@@ -0,0 +1,187 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The lines of the macros of `LOG`, laid out without the lock of the
+//! global logger.
+//!
+//! Every `global` guard dropped starts a new generation of the settings. A
+//! thread takes what the lines of a module need of a generation, the level
+//! and separator of the module and its layout at each level, under the lock
+//! once, and keeps it: until the next generation the lines of the module
+//! are checked, built and laid out on the thread alone, and take the lock
+//! only to be written. Threads logging at once then wait on each other's
+//! writes, not on their layouts.
+//!
+//! A logger with something that sees or changes with every line, such as
+//! filters, a storm guard, hooks, the adaptive budget or a `{seq}`, still
+//! lays its lines out under the lock, see `sync::log_line`.
+//!
+//! A line is laid out with the settings as of its logging. The lines of a
+//! thread are written in their order, whole; those of threads logging at
+//! once may be written in another order than their times.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    fields::{FieldLimits, FieldMap},
+    record::Render,
+    sync::{self, Layout},
+    CallSite, LocationStrategy, TestMode, LEVEL,
+};
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The generation of the settings of the global logger.
+pub(crate) fn generation() -> u64 {
+    GENERATION.load(Ordering::Acquire)
+}
+
+/// Starts a new generation, as the settings may have changed. Called with
+/// the lock held, so that a generation read under it stays current.
+pub(crate) fn changed() {
+    GENERATION.fetch_add(1, Ordering::Release);
+}
+
+/// What the lines of a generation need of the global logger beyond their
+/// module, see `sync::Logger::line_config`.
+pub(crate) struct LineConfig {
+    /// Whether the lines are laid out under the lock.
+    pub(crate) locked: bool,
+    pub(crate) error_backtraces: bool,
+    pub(crate) location_strategy: LocationStrategy,
+    pub(crate) render: Render,
+    pub(crate) field_limits: FieldLimits,
+    pub(crate) context_fields: bool,
+    pub(crate) testmode: Option<TestMode>,
+    pub(crate) boot_id: bool,
+    pub(crate) function_module: bool,
+}
+
+impl LineConfig {
+    fn layout(&self) -> Layout<'_> {
+        Layout {
+            render: &self.render,
+            field_limits: self.field_limits,
+            context_fields: self.context_fields,
+            dynamic_fields: None,
+            testmode: self.testmode,
+            // Shown by none of these lines: a logger showing it lays its
+            // lines out under the lock.
+            seq: 0,
+            subseq: false,
+            boot_id: self.boot_id,
+            function_module: self.function_module,
+        }
+    }
+}
+
+/// The layout of the lines of a module at a level.
+pub(crate) struct LevelLayout {
+    /// Whether a line gets its file and line, see `is_file_line`.
+    pub(crate) file_line: bool,
+    pub(crate) format: u8,
+    pub(crate) formatter: Option<String>,
+    /// Whether `fmt_static` lays a line out alone, but for its message and
+    /// the context fields.
+    pub(crate) plain: bool,
+}
+
+/// What the lines of a module need of a generation, see
+/// `sync::Logger::module_lines`.
+pub(crate) struct ModuleLines {
+    pub(crate) generation: u64,
+    pub(crate) module: &'static str,
+    /// The level of the module, after the output levels.
+    pub(crate) level: LEVEL,
+    pub(crate) separator: String,
+    /// By level, `LEVEL::Trace` first.
+    pub(crate) layouts: [LevelLayout; 7],
+    pub(crate) config: Arc<LineConfig>,
+}
+
+/// How many modules a thread keeps the lines of.
+const MAX_MODULES: usize = 64;
+
+thread_local! {
+    static MODULES: RefCell<Vec<Rc<ModuleLines>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The lines of `module` in the current generation.
+fn module_lines(module: &'static str) -> Rc<ModuleLines> {
+    let generation = generation();
+    let kept = MODULES.try_with(|m| m.try_borrow().ok()?.iter().find(|l| l.generation == generation && l.module == module).cloned());
+    if let Some(lines) = kept.ok().flatten() {
+        return lines;
+    }
+    let lines = Rc::new(crate::global_for_line().module_lines(module));
+    let _ = MODULES.try_with(|m| {
+        if let Ok(mut m) = m.try_borrow_mut() {
+            m.retain(|l| l.generation == lines.generation);
+            if m.len() == MAX_MODULES {
+                m.clear();
+            }
+            m.push(lines.clone());
+        }
+    });
+    lines
+}
+
+/// A line of the macros of `LOG`, see the module docs.
+#[doc(hidden)]
+pub fn log_line(level: LEVEL, module: &'static str, site: CallSite, event: Option<&'static str>, message: impl FnOnce(&str) -> String, fields: impl FnOnce() -> FieldMap) {
+    let lines = module_lines(module);
+    if level < lines.level {
+        return;
+    }
+    let config = &lines.config;
+    if config.locked {
+        sync::log_line(&crate::SYNC_LOGGER, level, module, site, event, message, fields, sync::Logger::queue_or_print);
+        return;
+    }
+    let layout = &lines.layouts[level as usize - 1];
+    let (file, line) = if layout.file_line { site.get(config.location_strategy) } else { ("", 0) };
+    let message = crate::with_error_backtraces(config.error_backtraces, || message(&lines.separator));
+    let fields = fields();
+    let s = crate::in_function(site.function, || config.layout().content(module, level, file, line, event, message, fields, layout.format, layout.formatter.as_ref()));
+    crate::global_for_line().write_laid_out(level, module, event, s);
+}
+
+/// `log_line` for a message known at compile time, see `fmt_static`: a
+/// plain line is laid out under the lock, where its prefix is kept, and
+/// is below no budget, a plain logger having none.
+#[doc(hidden)]
+pub fn log_static(level: LEVEL, module: &'static str, site: CallSite, msg: &'static str) {
+    let lines = module_lines(module);
+    if level < lines.level {
+        return;
+    }
+    if !lines.layouts[level as usize - 1].plain {
+        log_line(level, module, site, None, |_| crate::message_of("", &[&msg]), FieldMap::new);
+        return;
+    }
+    let mut logger = crate::global_for_line();
+    let s = crate::in_function(site.function, || logger.fmt_static(level, module, msg));
+    if !s.is_empty() {
+        logger.queue_or_print(level, module, s);
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Failures of the files of a logger, such as a full disk or a deleted
+//! log directory, with `Logger::set_error_handler`.
+//!
+//! The handler gets every failed open, write, rotation, compression of a
+//! backup and deletion of old backups, on the thread or task that met it:
+//! the one configuring or logging for opens, writes and rotations, the one
+//! compressing for the rest. A missing directory of a file is created
+//! first; the failure to create it carries the directory. Without a handler each kind of failure is written to stderr at
+//! most once every 10 seconds, with the count of those left out since.
+//! A handler that panics is disabled after three panics in a row, until
+//! `try_recover`, and failures go to stderr meanwhile.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::logerror::LogError;
+//!
+//! let mut log = tklog::sync::Logger::new();
+//! log.set_error_handler(Box::new(|e| {
+//!     if let LogError::WriteFailed(path, error) = e {
+//!         eprintln!("lines to {} are lost: {}", path.display(), error);
+//!     }
+//! }));
+//! ```
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::guard::Guarded;
+
+/// How long stderr is left quiet after a failure of one kind.
+const QUIET: Duration = Duration::from_secs(10);
+
+/// A failure of a log file, with the path it concerns.
+#[derive(Debug)]
+pub enum LogError {
+    /// A file that could not be opened, or its directory created, when
+    /// set; the logger goes on without it.
+    OpenFailed(PathBuf, io::Error),
+    /// A line that could not be written to its file.
+    WriteFailed(PathBuf, io::Error),
+    /// A file that could not be cut or reopened after its cut.
+    RotateFailed(PathBuf, io::Error),
+    /// A backup left uncompressed.
+    CompressFailed(PathBuf, io::Error),
+    /// A directory whose old backups could not all be deleted.
+    BackupCleanupFailed(PathBuf, io::Error),
+}
+
+impl LogError {
+    pub fn path(&self) -> &Path {
+        match self {
+            LogError::OpenFailed(path, _) | LogError::WriteFailed(path, _) | LogError::RotateFailed(path, _) | LogError::CompressFailed(path, _) | LogError::BackupCleanupFailed(path, _) => path,
+        }
+    }
+
+    pub fn error(&self) -> &io::Error {
+        match self {
+            LogError::OpenFailed(_, e) | LogError::WriteFailed(_, e) | LogError::RotateFailed(_, e) | LogError::CompressFailed(_, e) | LogError::BackupCleanupFailed(_, e) => e,
+        }
+    }
+
+    fn kind(&self) -> usize {
+        match self {
+            LogError::WriteFailed(..) => 0,
+            LogError::RotateFailed(..) => 1,
+            LogError::CompressFailed(..) => 2,
+            LogError::BackupCleanupFailed(..) => 3,
+            LogError::OpenFailed(..) => 4,
+        }
+    }
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self {
+            LogError::OpenFailed(..) => "cannot open",
+            LogError::WriteFailed(..) => "cannot write",
+            LogError::RotateFailed(..) => "cannot rotate",
+            LogError::CompressFailed(..) => "cannot compress",
+            LogError::BackupCleanupFailed(..) => "cannot delete the old backups in",
+        };
+        write!(f, "{} {}: {}", what, self.path().display(), self.error())
+    }
+}
+
+impl std::error::Error for LogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error())
+    }
+}
+
+/// What `Logger::set_error_handler` takes.
+pub type ErrorHandler = Box<dyn Fn(LogError) + Send + Sync>;
+
+/// When stderr last got a failure of a kind, and how many were left out
+/// since.
+#[derive(Clone, Copy, Default)]
+struct Quiet {
+    since: Option<Instant>,
+    left_out: u64,
+}
+
+#[derive(Default)]
+struct Shared {
+    handler: RwLock<Option<Guarded<ErrorHandler>>>,
+    quiet: Mutex<[Quiet; 5]>,
+}
+
+/// Where the file handlers of a logger report their failures; clones of
+/// the file settings share it, so a handler set on the logger reaches
+/// every file at once.
+#[derive(Clone, Default)]
+pub(crate) struct ErrorSink(Arc<Shared>);
+
+impl fmt::Debug for ErrorSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ErrorSink")
+    }
+}
+
+impl ErrorSink {
+    pub(crate) fn set(&self, handler: Option<ErrorHandler>) {
+        *self.0.handler.write().unwrap_or_else(|e| e.into_inner()) = handler.map(|h| Guarded::new("error handler", h));
+    }
+
+    pub(crate) fn report(&self, error: LogError) {
+        let handler = self.0.handler.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(h) = handler.as_ref().filter(|h| h.disabled().is_none()) {
+            h.call(|f| f(error));
+            return;
+        }
+        drop(handler);
+        self.to_stderr(&error);
+    }
+
+    fn to_stderr(&self, error: &LogError) {
+        let now = Instant::now();
+        let mut quiet = self.0.quiet.lock().unwrap_or_else(|e| e.into_inner());
+        let q = &mut quiet[error.kind()];
+        if q.since.is_some_and(|since| now - since < QUIET) {
+            q.left_out += 1;
+            return;
+        }
+        match std::mem::replace(q, Quiet { since: Some(now), left_out: 0 }).left_out {
+            0 => eprintln!("tklog: {}", error),
+            n => eprintln!("tklog: {} ({} more left out)", error, n),
+        }
+    }
+
+    /// `error handler` if the handler is disabled, see `health`.
+    pub(crate) fn disabled(&self) -> Option<&'static str> {
+        self.0.handler.read().unwrap_or_else(|e| e.into_inner()).as_ref().and_then(Guarded::disabled)
+    }
+
+    pub(crate) fn reset(&self) {
+        if let Some(h) = self.0.handler.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            h.reset();
+        }
+    }
+}
+
+/// A copy of `e` for the handler, the original going back to the caller.
+pub(crate) fn copy(e: &io::Error) -> io::Error {
+    match e.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(e.kind(), e.to_string()),
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Destinations of your own beside the console and the files.
+//!
+//! With `Logger::set_custom_sink` every line, once laid out, also goes to
+//! a `LogSink`; `set_custom_sink_only(true)` sends it there alone. A line
+//! written in place is flushed right away; the lines of the DELAY queue,
+//! on the sync consumer thread or the async consumer task, are flushed
+//! once the queue is drained, as is the async logger on `flush`.
+//!
+//! ### Example
+//! ```no_run
+//! use std::sync::{Arc, Mutex};
+//! use tklog::{logsink::LogSink, sync::Logger, LEVEL};
+//!
+//! struct Ring(Arc<Mutex<Vec<String>>>);
+//!
+//! impl LogSink for Ring {
+//!     fn write(&mut self, _level: LEVEL, formatted: &str) {
+//!         self.0.lock().unwrap().push(formatted.to_string());
+//!     }
+//!     fn flush(&mut self) {}
+//! }
+//!
+//! let lines = Arc::new(Mutex::new(Vec::new()));
+//! let mut log = Logger::new();
+//! log.set_custom_sink(Box::new(Ring(lines.clone())));
+//! ```
+
+use crate::LEVEL;
+
+/// A destination of laid out lines, see the module docs.
+pub trait LogSink: Send {
+    /// Takes one line as the files get it, end of line included.
+    fn write(&mut self, level: LEVEL, formatted: &str);
+
+    /// Called after a line written in place and after each batch of
+    /// queued lines.
+    fn flush(&mut self);
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The process-wide memory budget of queued lines, see `set_memory_budget`.
+//!
+//! Every line queued by a logger in `PRINTMODE::DELAY` is charged until its
+//! consumer takes it. A line that doesn't fit makes room by shedding queued
+//! lines, oldest first and lowest class first: Trace and Debug, then Info,
+//! then Warn and above. A line never sheds a class above its own, so Warn
+//! and above only go once nothing else is left; a line that still doesn't
+//! fit is shed itself.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{stats::MemoryStats, LEVEL};
+
+/// What a queued line costs beyond its text: the queue slot, the target
+/// and the bookkeeping here.
+pub(crate) const LINE_OVERHEAD: usize = 128;
+
+static ACCOUNTANT: Lazy<Accountant> = Lazy::new(Accountant::default);
+
+#[derive(Default)]
+struct Accountant {
+    budget: AtomicU64,
+    used: AtomicU64,
+    shed: [AtomicU64; 3],
+    /// The charged lines of each class in queue order; taken and dropped
+    /// ones are skipped when found at the front.
+    queued: Mutex<[VecDeque<Weak<dyn Shed>>; 3]>,
+}
+
+pub(crate) trait Shed: Send + Sync {
+    /// Drops the value; the bytes it freed, `None` if it was taken already.
+    fn shed(&self) -> Option<u64>;
+    fn is_taken(&self) -> bool;
+}
+
+/// A charged value the accountant can drop before its consumer gets to it.
+pub(crate) struct Slot<T> {
+    value: Mutex<Option<T>>,
+    bytes: u64,
+}
+
+impl<T> Slot<T> {
+    fn take(&self) -> Option<T> {
+        let value = self.value.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if value.is_some() {
+            ACCOUNTANT.used.fetch_sub(self.bytes, Ordering::Relaxed);
+        }
+        value
+    }
+}
+
+impl<T: Send> Shed for Slot<T> {
+    fn shed(&self) -> Option<u64> {
+        self.take().map(|_| self.bytes)
+    }
+
+    fn is_taken(&self) -> bool {
+        self.value.lock().unwrap_or_else(|e| e.into_inner()).is_none()
+    }
+}
+
+impl<T> Drop for Slot<T> {
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+/// A queued value: as it is without a budget, else charged in a slot.
+pub(crate) enum Held<T> {
+    Plain(T),
+    Charged(Arc<Slot<T>>),
+}
+
+impl<T> Held<T> {
+    /// The value, `None` when it was shed.
+    pub(crate) fn take(self) -> Option<T> {
+        match self {
+            Held::Plain(value) => Some(value),
+            Held::Charged(slot) => slot.take(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Held<T> {
+    /// The value in a slot that can be shed through the handle returned,
+    /// for the bound of a queue, see `channel`.
+    pub(crate) fn sheddable(self) -> (Held<T>, Weak<dyn Shed>) {
+        let slot = match self {
+            Held::Plain(value) => Arc::new(Slot { value: Mutex::new(Some(value)), bytes: 0 }),
+            Held::Charged(slot) => slot,
+        };
+        let weak: Weak<dyn Shed> = Arc::downgrade(&slot) as Weak<Slot<T>>;
+        (Held::Charged(slot), weak)
+    }
+}
+
+fn class(level: LEVEL) -> usize {
+    match level {
+        LEVEL::Trace | LEVEL::Debug => 0,
+        LEVEL::Info => 1,
+        _ => 2,
+    }
+}
+
+/// Charges `bytes` for `value`, a line at `level`, shedding queued lines to
+/// make room; `None` when the line itself is shed.
+pub(crate) fn hold<T: Send + 'static>(level: LEVEL, bytes: usize, value: T) -> Option<Held<T>> {
+    let a = &*ACCOUNTANT;
+    let budget = a.budget.load(Ordering::Relaxed);
+    if budget == 0 {
+        return Some(Held::Plain(value));
+    }
+    let bytes = (bytes + LINE_OVERHEAD) as u64;
+    let class = class(level);
+    let mut queued = a.queued.lock().unwrap_or_else(|e| e.into_inner());
+    for q in queued.iter_mut() {
+        while q.front().is_some_and(|w| w.upgrade().is_none_or(|s| s.is_taken())) {
+            q.pop_front();
+        }
+    }
+    while a.used.load(Ordering::Relaxed) + bytes > budget {
+        let Some(c) = (0..=class).find(|&c| !queued[c].is_empty()) else {
+            a.shed[class].fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        if queued[c].pop_front().and_then(|w| w.upgrade()).and_then(|s| s.shed()).is_some() {
+            a.shed[c].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    a.used.fetch_add(bytes, Ordering::Relaxed);
+    let slot = Arc::new(Slot { value: Mutex::new(Some(value)), bytes });
+    let weak: Weak<dyn Shed> = Arc::downgrade(&slot) as Weak<Slot<T>>;
+    queued[class].push_back(weak);
+    Some(Held::Charged(slot))
+}
+
+pub(crate) fn set_budget(bytes: u64) {
+    ACCOUNTANT.budget.store(bytes, Ordering::Relaxed);
+}
+
+pub(crate) fn stats() -> MemoryStats {
+    let a = &*ACCOUNTANT;
+    MemoryStats {
+        budget: a.budget.load(Ordering::Relaxed),
+        used: a.used.load(Ordering::Relaxed),
+        shed: [0, 1, 2].map(|c| a.shed[c].load(Ordering::Relaxed)),
+    }
+}
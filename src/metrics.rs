@@ -0,0 +1,116 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Counters of what a logger has written, see `Logger::metrics()`.
+//!
+//! Relaxed atomics, shared by the logger, its file handlers and the
+//! threads or tasks that rotate and compress, so counting takes no lock.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::LEVEL;
+
+/// A snapshot of the counters of a logger.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogMetrics {
+    /// The lines printed at each level, past the level and the filters.
+    pub trace: u64,
+    pub debug: u64,
+    pub info: u64,
+    pub warn: u64,
+    pub error: u64,
+    pub fatal: u64,
+    /// Bytes written to the log files, before compression.
+    pub bytes_written: u64,
+    /// Backups made by rotation.
+    pub rotations: u64,
+    /// Backups compressed.
+    pub compressions: u64,
+}
+
+impl LogMetrics {
+    /// The lines printed at `level`.
+    pub fn records(&self, level: LEVEL) -> u64 {
+        match level {
+            LEVEL::Trace => self.trace,
+            LEVEL::Debug => self.debug,
+            LEVEL::Info => self.info,
+            LEVEL::Warn => self.warn,
+            LEVEL::Error => self.error,
+            LEVEL::Fatal => self.fatal,
+            LEVEL::Off => 0,
+        }
+    }
+}
+
+/// The counters behind `LogMetrics`; the file settings share them, so the
+/// handlers of a logger count into it.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Metrics(Arc<Counters>);
+
+#[derive(Debug, Default)]
+struct Counters {
+    levels: [AtomicU64; 6],
+    bytes_written: AtomicU64,
+    rotations: AtomicU64,
+    compressions: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn printed(&self, level: LEVEL) {
+        if level != LEVEL::Off {
+            self.0.levels[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn written(&self, bytes: usize) {
+        self.0.bytes_written.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn rotated(&self) {
+        self.0.rotations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn compressed(&self) {
+        self.0.compressions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> LogMetrics {
+        let c = &self.0;
+        let level = |i: usize| c.levels[i].load(Ordering::Relaxed);
+        LogMetrics {
+            trace: level(0),
+            debug: level(1),
+            info: level(2),
+            warn: level(3),
+            error: level(4),
+            fatal: level(5),
+            bytes_written: c.bytes_written.load(Ordering::Relaxed),
+            rotations: c.rotations.load(Ordering::Relaxed),
+            compressions: c.compressions.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        let c = &self.0;
+        for counter in c.levels.iter().chain([&c.bytes_written, &c.rotations, &c.compressions]) {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
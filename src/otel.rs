@@ -0,0 +1,73 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Correlation with OpenTelemetry traces; needs the `otel` feature.
+//!
+//! `Logger::set_auto_trace_ids(true)` appends ` trace_id=… span_id=…` to
+//! every line logged while a span is active.
+
+use std::cell::RefCell;
+
+use opentelemetry::{
+    trace::{SpanId, TraceContextExt, TraceId},
+    Context,
+};
+
+thread_local! {
+    /// The IDs last rendered on this thread; a run of lines in one span
+    /// formats them once.
+    static LAST: RefCell<Option<(TraceId, SpanId, String, String)>> = const { RefCell::new(None) };
+}
+
+/// The hex trace and span IDs of the active OpenTelemetry context, or
+/// `None` outside a valid span.
+pub fn current_ids() -> Option<(String, String)> {
+    let (trace_id, span_id) = Context::map_current(|cx| {
+        let span = cx.span();
+        let sc = span.span_context();
+        sc.is_valid().then(|| (sc.trace_id(), sc.span_id()))
+    })?;
+    LAST.with(|last| {
+        let mut last = last.borrow_mut();
+        match &*last {
+            Some((t, s, ts, ss)) if *t == trace_id && *s == span_id => Some((ts.clone(), ss.clone())),
+            _ => {
+                let ids = (trace_id.to_string(), span_id.to_string());
+                *last = Some((trace_id, span_id, ids.0.clone(), ids.1.clone()));
+                Some(ids)
+            }
+        }
+    })
+}
+
+/// `message` with the IDs of the active span appended, before its trailing
+/// newline if it has one.
+pub(crate) fn with_trace_ids(mut message: String) -> String {
+    if let Some((trace_id, span_id)) = current_ids() {
+        let newline = message.ends_with('\n');
+        if newline {
+            message.pop();
+        }
+        message.push_str(" trace_id=");
+        message.push_str(&trace_id);
+        message.push_str(" span_id=");
+        message.push_str(&span_id);
+        if newline {
+            message.push('\n');
+        }
+    }
+    message
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The layout family of the console and of the files.
+//!
+//! Text, JSON, logfmt and delimited lines don't mix: once a sink is in one
+//! mode, `Logger::set_output_mode` refuses the others with
+//! `Error::ConflictingMode` until `clear_output_mode`. Presets replace the
+//! modes they set up. `Logger::validate` also catches text-only settings,
+//! the `set_formatter` template and the body formats, on a sink in another
+//! mode, where they would otherwise be ignored or garble the lines.
+//!
+//! Each sink may also have a level of its own with
+//! `Logger::set_output_level`, in place of the level of the line's module:
+//! the console at `Warn` and the files at `Trace`, say. The macros then let
+//! a line through once one sink takes it, the console counting while it is
+//! on, and each sink writes only the lines at its level or above.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::output::{OutputMode, OutputSink};
+//! use tklog::sync::Logger;
+//!
+//! let mut log = Logger::new();
+//! log.preset_k8s();
+//! assert!(log.set_output_mode(OutputSink::Console, OutputMode::Logfmt).is_err());
+//! ```
+
+use std::fmt;
+
+use crate::{record::Render, Error, LEVEL};
+
+/// The layout family of a sink's lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputMode {
+    Text,
+    Json,
+    Logfmt,
+    Delimited,
+}
+
+impl fmt::Display for OutputMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputMode::Text => "text",
+            OutputMode::Json => "json",
+            OutputMode::Logfmt => "logfmt",
+            OutputMode::Delimited => "delimited",
+        })
+    }
+}
+
+/// Where lines are laid out separately: the console body and the file body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputSink {
+    Console,
+    File,
+}
+
+impl fmt::Display for OutputSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputSink::Console => "console",
+            OutputSink::File => "file",
+        })
+    }
+}
+
+/// The level of each sink set with `Logger::set_output_level`; `None`
+/// follows the level of the line's module.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct OutputLevels {
+    console: Option<LEVEL>,
+    file: Option<LEVEL>,
+}
+
+/// The sinks a line goes to, see `OutputLevels::gate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Gate {
+    pub(crate) console: bool,
+    pub(crate) file: bool,
+}
+
+impl Gate {
+    pub(crate) const ALL: Gate = Gate { console: true, file: true };
+}
+
+impl OutputLevels {
+    pub(crate) fn set(&mut self, sink: OutputSink, level: Option<LEVEL>) {
+        match sink {
+            OutputSink::Console => self.console = level,
+            OutputSink::File => self.file = level,
+        }
+    }
+
+    pub(crate) fn get(&self, sink: OutputSink) -> Option<LEVEL> {
+        match sink {
+            OutputSink::Console => self.console,
+            OutputSink::File => self.file,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.console.is_none() && self.file.is_none()
+    }
+
+    /// The lowest level a sink takes for a module at `level`, the console
+    /// counting only when it is on.
+    pub(crate) fn lowest(&self, level: LEVEL, console: bool) -> LEVEL {
+        let file = self.file.unwrap_or(level);
+        match self.console.unwrap_or(level) {
+            c if console && c < file => c,
+            _ => file,
+        }
+    }
+
+    /// The sinks a line at `level` of a module at `module_level` goes to.
+    pub(crate) fn gate(&self, level: LEVEL, module_level: LEVEL) -> Gate {
+        if self.is_empty() {
+            return Gate::ALL;
+        }
+        Gate { console: level >= self.console.unwrap_or(module_level), file: level >= self.file.unwrap_or(module_level) }
+    }
+}
+
+/// The mode of each sink; `None` until something sets one, which lays
+/// lines out as text but accepts any mode.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct OutputModes {
+    console: Option<OutputMode>,
+    file: Option<OutputMode>,
+}
+
+impl OutputModes {
+    fn slot(&mut self, sink: OutputSink) -> &mut Option<OutputMode> {
+        match sink {
+            OutputSink::Console => &mut self.console,
+            OutputSink::File => &mut self.file,
+        }
+    }
+
+    pub(crate) fn get(&self, sink: OutputSink) -> OutputMode {
+        match sink {
+            OutputSink::Console => self.console,
+            OutputSink::File => self.file,
+        }
+        .unwrap_or(OutputMode::Text)
+    }
+
+    /// Puts `sink` in `mode` unless it is already in another one.
+    pub(crate) fn claim(&mut self, sink: OutputSink, mode: OutputMode) -> Result<(), Error> {
+        let slot = self.slot(sink);
+        match *slot {
+            Some(active) if active != mode => Err(Error::ConflictingMode { active, requested: mode }),
+            _ => {
+                *slot = Some(mode);
+                Ok(())
+            }
+        }
+    }
+
+    /// Puts `sink` in `mode` whatever it was in, for presets.
+    pub(crate) fn reset(&mut self, sink: OutputSink, mode: OutputMode) {
+        *self.slot(sink) = Some(mode);
+    }
+
+    pub(crate) fn clear(&mut self, sink: OutputSink) {
+        *self.slot(sink) = None;
+    }
+
+    /// `output: console <mode>, file <mode>`.
+    pub(crate) fn describe(&self) -> String {
+        format!("output: console {}, file {}\n", self.get(OutputSink::Console), self.get(OutputSink::File))
+    }
+
+    /// The mode of each sink, or the first sink whose text-only settings
+    /// don't fit its mode: the `set_formatter` template applies to both,
+    /// each body format to its own, the console formatter to the console.
+    pub(crate) fn validate(&self, render: &Render, template: bool) -> Result<Vec<(OutputSink, OutputMode)>, Error> {
+        let mut modes = Vec::new();
+        for (sink, body_fmt) in [(OutputSink::Console, render.attrfmt.consolebodyfmt.is_some() || render.console_formatter.is_some()), (OutputSink::File, render.attrfmt.filebodyfmt.is_some())] {
+            let mode = self.get(sink);
+            if mode != OutputMode::Text && (template || body_fmt) {
+                return Err(Error::ConflictingMode { active: mode, requested: OutputMode::Text });
+            }
+            modes.push((sink, mode));
+        }
+        Ok(modes)
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of lines written in tklog's own text layouts.
+//!
+//! ### Example
+//! ```rust
+//! use tklog::{parse::parse_line, Format, LEVEL};
+//!
+//! let r = parse_line("[INFO] 2024-05-01 12:00:00 main.rs 42:hello", Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName).unwrap();
+//! assert_eq!(r.level, Some(LEVEL::Info));
+//! assert_eq!(r.line, Some(42));
+//! assert_eq!(r.message, "hello");
+//! ```
+
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::{Format, LEVEL};
+
+/// The layout a line was written with: the `Format` flags alone (the default
+/// layout), or the flags together with a `set_formatter` template.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub format: u8,
+    pub formatter: Option<String>,
+}
+
+impl From<u8> for Layout {
+    fn from(format: u8) -> Self {
+        Layout { format, formatter: None }
+    }
+}
+
+impl From<(u8, &str)> for Layout {
+    fn from((format, formatter): (u8, &str)) -> Self {
+        Layout {
+            format,
+            formatter: Some(formatter.to_string()),
+        }
+    }
+}
+
+/// The fields recovered from one formatted line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedRecord {
+    pub level: Option<LEVEL>,
+    pub time: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub seq: Option<u64>,
+    /// With `Format::ThreadId`, or a template placing `{thread}`.
+    pub thread: Option<String>,
+    /// With `Format::FuncName`, or a template placing `{function}`.
+    pub function: Option<String>,
+    pub message: String,
+}
+
+impl ParsedRecord {
+    /// The event ID at the end of the message, see `events`.
+    pub fn event(&self) -> Option<&str> {
+        let (_, rest) = self.message.rsplit_once(" event=")?;
+        let id = rest.split_whitespace().next()?;
+        Some(id)
+    }
+}
+
+/// A compiled parser for one layout; reuse it when parsing many lines.
+pub struct Parser {
+    re: Regex,
+}
+
+impl Parser {
+    pub fn new(layout: impl Into<Layout>) -> Self {
+        let layout = layout.into();
+        // Format::Nano writes the bare message even when a template is set.
+        let body = match &layout.formatter {
+            Some(f) if layout.format != Format::Nano => template_pattern(layout.format, f.strip_suffix('\n').unwrap_or(f)),
+            _ => default_pattern(layout.format),
+        };
+        let re = Regex::new(&format!("(?s)^{}\n?$", body)).expect("layout pattern");
+        Parser { re }
+    }
+
+    pub fn parse(&self, line: &str) -> Option<ParsedRecord> {
+        let caps = self.re.captures(line)?;
+        let text = |name: &str| caps.name(name).map(|m| m.as_str().to_string());
+        Some(ParsedRecord {
+            level: match caps.name("level") {
+                Some(m) => Some(LEVEL::from_str(m.as_str()).ok()?),
+                None => None,
+            },
+            time: text("time"),
+            file: text("file"),
+            line: match caps.name("line") {
+                Some(m) => Some(m.as_str().parse().ok()?),
+                None => None,
+            },
+            seq: match caps.name("seq") {
+                Some(m) => Some(m.as_str().parse().ok()?),
+                None => None,
+            },
+            thread: text("thread"),
+            function: text("function"),
+            message: text("message").unwrap_or_default(),
+        })
+    }
+}
+
+/// Parses a single line written with `layout`; `None` if it doesn't match.
+pub fn parse_line(line: &str, layout: impl Into<Layout>) -> Option<ParsedRecord> {
+    Parser::new(layout).parse(line)
+}
+
+fn level_pattern(name: bool) -> String {
+    format!(r"\[({}[A-Z]+)\]", if name { "?P<level>" } else { "?:" })
+}
+
+fn time_pattern(format: u8, name: bool) -> String {
+    let mut p = String::new();
+    if format & Format::Date != 0 {
+        p.push_str(r"\d{4}-\d{2}-\d{2}");
+    }
+    if format & (Format::Time | Format::Microseconds) != 0 {
+        if !p.is_empty() {
+            p.push(' ');
+        }
+        p.push_str(r"\d{2}:\d{2}:\d{2}");
+        if format & Format::Microseconds != 0 {
+            p.push_str(r"\.\d{6}");
+        }
+    }
+    format!("({}{})", if name { "?P<time>" } else { "?:" }, p)
+}
+
+/// A thread label, which the parser takes to have no spaces.
+fn thread_pattern(name: bool) -> &'static str {
+    if name {
+        r"(?P<thread>\S+)"
+    } else {
+        r"\S+"
+    }
+}
+
+fn file_pattern(name: bool) -> String {
+    if name {
+        r"(?P<file>.+?) (?P<line>\d+)".to_string()
+    } else {
+        r"(?:.+?) (?:\d+)".to_string()
+    }
+}
+
+/// A function path, `::` apart, without the spaces of `<T as Trait>`.
+fn function_pattern(name: bool) -> &'static str {
+    if name {
+        r"(?P<function>[^\s:]+(?:::[^\s:]+)*)"
+    } else {
+        r"(?:[^\s:]+(?:::[^\s:]+)*)"
+    }
+}
+
+fn has_time(format: u8) -> bool {
+    format & (Format::Date | Format::Time | Format::Microseconds) != 0
+}
+
+fn has_file(format: u8) -> bool {
+    format & (Format::LongFileName | Format::ShortFileName) != 0
+}
+
+fn default_pattern(format: u8) -> String {
+    if format == Format::Nano {
+        return "(?P<message>.*?)".to_string();
+    }
+    let mut p = String::new();
+    if format & Format::LevelFlag != 0 {
+        p.push_str(&level_pattern(true));
+    }
+    if has_time(format) {
+        p.push(' ');
+        p.push_str(&time_pattern(format, true));
+    }
+    p.push(' ');
+    if format & Format::ThreadId != 0 {
+        p.push_str(thread_pattern(true));
+        p.push(' ');
+    }
+    if format & Format::FuncName != 0 {
+        let file = if has_file(format) { format!("(?:{})?", file_pattern(true)) } else { String::new() };
+        p.push_str(&format!("(?:{}(?: ?{})?:)?", file, function_pattern(true)));
+    } else if has_file(format) {
+        p.push_str(&format!("(?:{}:)?", file_pattern(true)));
+    }
+    p.push_str("(?P<message>.*?)");
+    p
+}
+
+fn template_pattern(format: u8, template: &str) -> String {
+    let mut p = String::new();
+    let mut seen: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        p.push_str(&regex::escape(&rest[..start]));
+        let name = &rest[start + 1..start + len];
+        let first = !seen.iter().any(|s| s == name);
+        seen.push(name.to_string());
+        match name {
+            "level" if format & Format::LevelFlag != 0 => p.push_str(&level_pattern(first)),
+            "time" if has_time(format) => p.push_str(&time_pattern(format, first)),
+            "file" if has_file(format) => p.push_str(&format!("(?:{})?", file_pattern(first))),
+            "seq" => p.push_str(if first { r"(?P<seq>\d+)" } else { r"\d+" }),
+            "thread" => p.push_str(thread_pattern(first)),
+            "function" => p.push_str(&format!("(?:{})?", function_pattern(first))),
+            "message" => p.push_str(if first { "(?P<message>.*?)" } else { ".*?" }),
+            _ => (),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    p.push_str(&regex::escape(rest));
+    p
+}
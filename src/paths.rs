@@ -0,0 +1,186 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Which handler of a logger writes which file. Two handlers on one path,
+//! however it is spelled, share the first one's handler when their cut
+//! settings match; otherwise the second is refused, since two rotations
+//! of one file corrupt each other. The global loggers also check each
+//! other's files, where no handler can be shared. A refused module or
+//! level writes to the default file.
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    diagnostics::{self, Category},
+    handle::FileOption,
+    Error, CUTMODE, MODE,
+};
+
+/// The owner of a logger's default file.
+pub(crate) const DEFAULT_FILE: &str = "default file";
+
+/// The files of the global loggers, by canonical path: the logger, the
+/// owner and its cut settings.
+type GlobalFiles = HashMap<PathBuf, (&'static str, String, Cut)>;
+
+static GLOBAL: Lazy<Mutex<GlobalFiles>> = Lazy::new(Mutex::default);
+
+/// The settings that must match for two handlers to share a file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cut {
+    mode: CUTMODE,
+    timemode: Option<MODE>,
+    size: u64,
+    maxbackups: u32,
+    compress: bool,
+}
+
+impl Cut {
+    fn of(option: &dyn FileOption) -> Self {
+        let mode = option.mode();
+        Cut {
+            mode,
+            timemode: (mode != CUTMODE::SIZE).then(|| option.timemode()),
+            size: if mode != CUTMODE::TIME { option.size() } else { 0 },
+            maxbackups: option.maxbackups(),
+            compress: option.compress(),
+        }
+    }
+}
+
+struct Owner {
+    owner: String,
+    /// The file name the handler is registered under.
+    filename: String,
+    cut: Cut,
+}
+
+/// What `Paths::claim` decided.
+pub(crate) enum Claim {
+    /// Open a handler of its own.
+    New,
+    /// Write through the handler registered under this file name.
+    Shared(String),
+    /// Refused, see `Logger::check_paths`.
+    Conflict,
+}
+
+#[derive(Default)]
+pub(crate) struct Paths {
+    /// The name of a global logger.
+    global: Option<&'static str>,
+    owners: HashMap<PathBuf, Owner>,
+    conflicts: Vec<(PathBuf, String, String)>,
+}
+
+impl Paths {
+    pub(crate) fn global(name: &'static str) -> Self {
+        Paths { global: Some(name), ..Default::default() }
+    }
+
+    /// Registers the file of `option` for `owner`, which gives up the file
+    /// it had.
+    pub(crate) fn claim(&mut self, owner: &str, option: &dyn FileOption) -> Claim {
+        self.release(owner);
+        let filename = option.filename();
+        let path = canonical(Path::new(&filename));
+        let cut = Cut::of(option);
+        if let Some(o) = self.owners.get(&path) {
+            if o.cut == cut {
+                return Claim::Shared(o.filename.clone());
+            }
+            let first = o.owner.clone();
+            return self.conflict(path, first, owner);
+        }
+        if let Some(name) = self.global {
+            let mut global = GLOBAL.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((logger, first, _)) = global.get(&path).filter(|(logger, _, _)| *logger != name) {
+                let first = format!("{}, {}", logger, first);
+                drop(global);
+                return self.conflict(path, first, owner);
+            }
+            global.insert(path.clone(), (name, owner.to_string(), cut));
+        }
+        self.owners.insert(path, Owner { owner: owner.to_string(), filename, cut });
+        Claim::New
+    }
+
+    /// Registers the file of `option` for `owner` alone, for a tee file,
+    /// which lays its lines out differently from any handler it could share.
+    pub(crate) fn claim_alone(&mut self, owner: &str, option: &dyn FileOption) -> Result<(), Error> {
+        let path = canonical(Path::new(&option.filename()));
+        let claim = match self.owners.get(&path).filter(|o| o.owner != owner) {
+            Some(o) => {
+                let first = o.owner.clone();
+                self.conflict(path, first, owner)
+            }
+            None => self.claim(owner, option),
+        };
+        match (claim, self.conflicts.last()) {
+            (Claim::New, _) => Ok(()),
+            (_, Some((path, first, second))) => Err(Error::PathConflict { path: path.clone(), first: first.clone(), second: second.clone() }),
+            (_, None) => unreachable!("a refused claim is recorded"),
+        }
+    }
+
+    pub(crate) fn release(&mut self, owner: &str) {
+        let Some(path) = self.owners.iter().find(|(_, o)| o.owner == owner).map(|(p, _)| p.clone()) else {
+            return;
+        };
+        self.owners.remove(&path);
+        if let Some(name) = self.global {
+            let mut global = GLOBAL.lock().unwrap_or_else(|e| e.into_inner());
+            if global.get(&path).is_some_and(|(logger, o, _)| *logger == name && o == owner) {
+                global.remove(&path);
+            }
+        }
+    }
+
+    fn conflict(&mut self, path: PathBuf, first: String, second: &str) -> Claim {
+        let e = Error::PathConflict { path: path.clone(), first: first.clone(), second: second.to_string() };
+        diagnostics::report(Category::PathConflict, Some(&path), e.to_string());
+        self.conflicts.push((path, first, second.to_string()));
+        Claim::Conflict
+    }
+
+    /// The first handler refused so far.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        match self.conflicts.first() {
+            Some((path, first, second)) => Err(Error::PathConflict { path: path.clone(), first: first.clone(), second: second.clone() }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `path` made absolute with its symlinks resolved; a file that doesn't
+/// exist yet is resolved through its directory.
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(p) = fs::canonicalize(path) {
+        return p;
+    }
+    let absolute = if path.is_absolute() { path.to_path_buf() } else { env::current_dir().unwrap_or_default().join(path) };
+    match (absolute.parent().and_then(|d| fs::canonicalize(d).ok()), absolute.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => absolute,
+    }
+}
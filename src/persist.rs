@@ -0,0 +1,166 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Warnings logged only if their condition persists, with
+//! `warn_if_persists!` and `clear!`.
+//!
+//! The first call for a key arms it with its line; `clear!` with that key
+//! before the delay is over cancels it, and once the delay is over the line
+//! is logged, its time being when it was logged and its `since` field when
+//! it was armed. Calls for a key already armed are left out, so a condition
+//! that never clears logs once per delay.
+//!
+//! An expired line is logged by the next line of its logger, or by the
+//! thread the macros start for the global loggers. At most `MAX_PENDING`
+//! keys are armed at once; the line of another key is logged right away.
+//!
+//! ### Example
+//! ```no_run
+//! use std::time::Duration;
+//! use tklog::{clear, warn_if_persists};
+//!
+//! # let ms = 900;
+//! warn_if_persists!(Duration::from_secs(5), key: "db_slow", "db latency {}ms", ms);
+//! // fast again within 5 seconds: nothing is logged
+//! clear!("db_slow");
+//! ```
+
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use once_cell::sync::Lazy;
+
+use crate::{fields::FieldMap, LEVEL};
+
+/// The most keys armed at once on a logger.
+pub const MAX_PENDING: usize = 1024;
+
+/// An armed line.
+pub(crate) struct Pending {
+    pub(crate) level: LEVEL,
+    pub(crate) module: String,
+    pub(crate) file: String,
+    pub(crate) line: u32,
+    pub(crate) message: String,
+    /// When it was armed.
+    pub(crate) since: DateTime<Local>,
+    deadline: Instant,
+}
+
+impl Pending {
+    pub(crate) fn new(level: LEVEL, module: &str, (file, line): (&str, u32), message: String, since: DateTime<Local>, deadline: Instant) -> Self {
+        Pending { level, module: module.to_string(), file: file.to_string(), line, message, since, deadline }
+    }
+
+    /// The fields of the line: when it was armed.
+    pub(crate) fn fields(&self) -> FieldMap {
+        let mut fields = FieldMap::new();
+        fields.insert("since", self.since.format("%Y-%m-%dT%H:%M:%S%.3f%:z"));
+        fields
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Persisting {
+    entries: HashMap<String, Pending>,
+    /// The earliest deadline.
+    next: Option<Instant>,
+}
+
+impl Persisting {
+    /// Arms `key` with the line of `pending`, unless it is armed already.
+    /// Returns the line when no more keys can be armed.
+    pub(crate) fn arm(&mut self, key: &str, pending: impl FnOnce() -> Pending) -> Option<Pending> {
+        if self.entries.contains_key(key) {
+            return None;
+        }
+        let pending = pending();
+        let deadline = pending.deadline;
+        if self.entries.len() >= MAX_PENDING {
+            return Some(pending);
+        }
+        self.entries.insert(key.to_string(), pending);
+        self.next = Some(self.next.map_or(deadline, |next| next.min(deadline)));
+        None
+    }
+
+    /// Cancels `key`; false when it wasn't armed.
+    pub(crate) fn clear(&mut self, key: &str) -> bool {
+        let cleared = self.entries.remove(key).is_some();
+        if cleared {
+            self.next = self.entries.values().map(|p| p.deadline).min();
+        }
+        cleared
+    }
+
+    /// Whether a line is due at `now`, which is only read when one is armed.
+    pub(crate) fn is_due(&self, now: impl FnOnce() -> Instant) -> bool {
+        self.next.is_some_and(|next| next <= now())
+    }
+
+    /// Takes the lines due at `now`, the earliest first.
+    pub(crate) fn expired(&mut self, now: Instant) -> Vec<Pending> {
+        if !self.is_due(|| now) {
+            return Vec::new();
+        }
+        let keys: Vec<String> = self.entries.iter().filter(|(_, p)| p.deadline <= now).map(|(k, _)| k.clone()).collect();
+        let mut due: Vec<Pending> = keys.iter().filter_map(|k| self.entries.remove(k)).collect();
+        due.sort_by_key(|p| p.deadline);
+        self.next = self.entries.values().map(|p| p.deadline).min();
+        due
+    }
+
+    /// How long from `now` until the next line is due.
+    pub(crate) fn wait(&self, now: Instant) -> Option<Duration> {
+        self.next.map(|next| next.saturating_duration_since(now))
+    }
+}
+
+/// A thread logging the due lines of a global logger with `expire`, parked
+/// until the next is due. Without it, they are logged by the next line.
+fn ticker(name: &str, expire: fn() -> Option<Duration>) -> Option<thread::Thread> {
+    let spawned = thread::Builder::new().name(name.to_string()).spawn(move || loop {
+        match expire() {
+            Some(wait) => thread::park_timeout(wait),
+            None => thread::park(),
+        }
+    });
+    spawned.ok().map(|handle| handle.thread().clone())
+}
+
+static SYNC_TICKER: Lazy<Option<thread::Thread>> = Lazy::new(|| ticker("tklog-persist", || crate::global().expire_persisting()));
+
+static ASYNC_TICKER: Lazy<Option<thread::Thread>> = Lazy::new(|| ticker("tklog-persist-async", || crate::global_async_blocking().expire_persisting()));
+
+/// Wakes the thread of `LOG` after a key was armed, starting it first.
+#[doc(hidden)]
+pub fn wake() {
+    if let Some(ticker) = &*SYNC_TICKER {
+        ticker.unpark();
+    }
+}
+
+/// `wake` for `ASYNC_LOG`.
+#[doc(hidden)]
+pub fn wake_async() {
+    if let Some(ticker) = &*ASYNC_TICKER {
+        ticker.unpark();
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Reading back what a previous run wrote, e.g. for a crash report.
+//!
+//! ### Example
+//! ```no_run
+//! let lines = tklog::postmortem::previous_tail("logs/app.log", 200).unwrap();
+//! for line in lines {
+//!     eprintln!("{}", line);
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    env,
+    fs::{self, File},
+    io::{self, BufRead, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+
+use crate::{backup_pattern, boot, compress};
+
+const BLOCK: u64 = 8 * 1024;
+
+static BOOT_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?:\bboot_id=|"boot_id":")([a-z2-7]{8})\b"#).unwrap());
+
+/// The last `max_lines` lines of the log file `path`, oldest first and
+/// without line endings. When the live file is empty or missing, as right
+/// after a rotation, the newest backup is read instead, decompressing it if
+/// needed. A file written with live compression is read from its `.gz`, up
+/// to its last flush. No lines at all is not an error.
+pub fn previous_tail(path: impl AsRef<Path>, max_lines: usize) -> io::Result<Vec<String>> {
+    let path = path.as_ref();
+    if max_lines == 0 {
+        return Ok(Vec::new());
+    }
+    if fs::metadata(path).is_ok_and(|m| m.len() > 0) {
+        return tail_file(path, max_lines);
+    }
+    let live = live_gz(path);
+    if fs::metadata(&live).is_ok_and(|m| m.len() > 0) {
+        return tail_lines(compress::open_any(&live)?, max_lines);
+    }
+    match newest_backup(path)? {
+        Some(b) if b.extension().is_some_and(|e| e == "gz") => tail_lines(compress::open_any(&b)?, max_lines),
+        Some(b) => tail_file(&b, max_lines),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The boot ID of the run that last wrote the log file `path` before this
+/// one, see `boot`: the last ID that isn't this run's, in the live file or
+/// else in its newest backup. None when no line carries one.
+pub fn previous_boot_id(path: impl AsRef<Path>) -> io::Result<Option<String>> {
+    let path = path.as_ref();
+    if let Some(id) = last_boot_id(path)? {
+        return Ok(Some(id));
+    }
+    if let Some(id) = last_boot_id(&live_gz(path))? {
+        return Ok(Some(id));
+    }
+    match newest_backup(path)? {
+        Some(b) => last_boot_id(&b),
+        None => Ok(None),
+    }
+}
+
+/// The live file of `path` written with `Logger::set_live_compression`.
+fn live_gz(path: &Path) -> PathBuf {
+    let mut live = path.as_os_str().to_owned();
+    live.push(".gz");
+    PathBuf::from(live)
+}
+
+fn last_boot_id(path: &Path) -> io::Result<Option<String>> {
+    let reader = match compress::open_any(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        reader => reader?,
+    };
+    let mut last = None;
+    for line in reader.split(b'\n') {
+        let line = match line {
+            Err(e) if compress::Truncated::of(&e).is_some() => break,
+            line => line?,
+        };
+        for caps in BOOT_ID.captures_iter(&line) {
+            if &caps[1] != boot::boot_id().as_bytes() {
+                last = Some(String::from_utf8_lossy(&caps[1]).into_owned());
+            }
+        }
+    }
+    Ok(last)
+}
+
+/// Scans blocks backwards from the end until enough line breaks are found.
+fn tail_file(path: &Path, max_lines: usize) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut start = len;
+    let mut buf: Vec<u8> = Vec::new();
+    let mut breaks = 0;
+    // A trailing line break ends the last line, it doesn't start a new one.
+    while start > 0 && breaks <= max_lines {
+        let n = BLOCK.min(start);
+        start -= n;
+        let mut block = vec![0; n as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut block)?;
+        breaks += block.iter().filter(|b| **b == b'\n').count();
+        block.extend_from_slice(&buf);
+        buf = block;
+    }
+    let text = String::from_utf8_lossy(&buf);
+    let lines: Vec<&str> = text.lines().collect();
+    let skip = lines.len().saturating_sub(max_lines);
+    Ok(lines[skip..].iter().map(|l| l.to_string()).collect())
+}
+
+fn tail_lines(reader: impl BufRead, max_lines: usize) -> io::Result<Vec<String>> {
+    let mut lines = VecDeque::with_capacity(max_lines);
+    for line in reader.split(b'\n') {
+        // A backup cut short still has its last lines up to the cut.
+        let line = match line {
+            Err(e) if compress::Truncated::of(&e).is_some() => break,
+            line => line?,
+        };
+        if lines.len() == max_lines {
+            lines.pop_front();
+        }
+        let line = String::from_utf8_lossy(&line);
+        lines.push_back(line.strip_suffix('\r').unwrap_or(&line).to_string());
+    }
+    Ok(lines.into())
+}
+
+fn newest_backup(path: &Path) -> io::Result<Option<PathBuf>> {
+    let stem = path.file_stem().map_or("tklog".to_string(), |s| s.to_string_lossy().to_string());
+    let extension = path.extension().map_or(String::new(), |e| e.to_string_lossy().to_string());
+    let mut dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    if dir.as_os_str().is_empty() {
+        dir = env::current_dir()?;
+    }
+    let re = backup_pattern(&stem, &extension);
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_name().to_str().is_some_and(|n| re.is_match(n)) {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if newest.as_ref().is_none_or(|(t, _)| modified > *t) {
+            newest = Some((modified, entry.path()));
+        }
+    }
+    Ok(newest.map(|(_, p)| p))
+}
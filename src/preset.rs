@@ -0,0 +1,192 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+
+use chrono::{SecondsFormat, Utc};
+
+use crate::{json::Schema, record::RecordSnapshot, LEVEL};
+
+/// A one-call setup applied by `Logger::preset_*`, reported by `describe()`.
+#[derive(Clone)]
+pub(crate) enum Preset {
+    K8s(K8sPreset),
+    Systemd,
+    /// `Logger::set_format_json`.
+    Json,
+}
+
+impl Preset {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Preset::K8s(_) => "k8s",
+            Preset::Systemd => "systemd",
+            Preset::Json => "json",
+        }
+    }
+}
+
+/// The `<N>` syslog priority journald reads from the start of a stdout line.
+pub(crate) fn journald_priority(level: LEVEL) -> &'static str {
+    match level {
+        LEVEL::Trace | LEVEL::Debug => "<7>",
+        LEVEL::Info => "<6>",
+        LEVEL::Warn => "<4>",
+        LEVEL::Error => "<3>",
+        LEVEL::Fatal | LEVEL::Off => "<2>",
+    }
+}
+
+/// The line layout of `Logger::preset_k8s`: one JSON object per line with
+/// the keys fluent-bit and Elasticsearch mappings usually expect.
+#[derive(Clone)]
+pub(crate) struct K8sPreset {
+    fields: Vec<(&'static str, String)>,
+}
+
+impl K8sPreset {
+    /// Picks up `pod` and `namespace` from `POD_NAME` and `POD_NAMESPACE`,
+    /// the usual names for the downward-API variables.
+    pub(crate) fn from_env() -> Self {
+        let fields = [("pod", "POD_NAME"), ("namespace", "POD_NAMESPACE")]
+            .into_iter()
+            .filter_map(|(key, var)| std::env::var(var).ok().filter(|v| !v.is_empty()).map(|v| (key, v)))
+            .collect();
+        K8sPreset { fields }
+    }
+
+    /// `{"v":2,"ts":…,"level":…,"msg":…,"caller":…,"logger":…,"event":…}`
+    /// plus the boot ID, the static and the dynamic fields, in `schema`;
+    /// `ts` is RFC 3339 in UTC.
+    pub(crate) fn render(&self, record: &RecordSnapshot, schema: Schema) -> String {
+        let ts = record.time.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Micros, true);
+        let message = record.message.as_str();
+        let mut out = String::with_capacity(96 + message.len());
+        out.push('{');
+        if schema.version >= 1 {
+            let _ = write!(out, "\"v\":{},", schema.version);
+        }
+        out.push_str("\"ts\":");
+        json_string(&mut out, &ts);
+        out.push_str(",\"level\":");
+        json_string(&mut out, level_name(record.level));
+        out.push_str(",\"msg\":");
+        json_string(&mut out, message.strip_suffix('\n').unwrap_or(message));
+        if !record.file.is_empty() {
+            out.push_str(",\"caller\":");
+            json_string(&mut out, &format!("{}:{}", record.file, record.line));
+        }
+        if !record.module.is_empty() {
+            out.push_str(",\"logger\":");
+            json_string(&mut out, &record.module);
+        }
+        if let Some(event) = record.event.filter(|_| schema.version >= 2) {
+            out.push_str(",\"event\":");
+            json_string(&mut out, event);
+        }
+        if let Some(id) = record.boot_id {
+            out.push_str(",\"boot_id\":");
+            json_string(&mut out, id);
+        }
+        for (key, value) in self.fields.iter().map(|(k, v)| (*k, v.as_str())).chain(record.fields.iter()) {
+            out.push(',');
+            json_string(&mut out, key);
+            out.push(':');
+            json_string(&mut out, value);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// The line of `Logger::set_format_json`: `{"level":…,"time":…,"file":…,
+/// "line":…,"module":…,"message":…}`, then the event and boot IDs, the
+/// thread and task with `Format::ThreadId`, the function with
+/// `Format::FuncName`, and the fields;
+/// `time` is RFC 3339 in local time.
+pub(crate) fn json_record(record: &RecordSnapshot) -> String {
+    let message = record.message.as_str();
+    let mut out = String::with_capacity(96 + message.len());
+    out.push_str("{\"level\":");
+    json_string(&mut out, level_name(record.level));
+    out.push_str(",\"time\":");
+    json_string(&mut out, &record.time.to_rfc3339_opts(SecondsFormat::Micros, false));
+    out.push_str(",\"file\":");
+    json_string(&mut out, &record.file);
+    let _ = write!(out, ",\"line\":{},\"module\":", record.line);
+    json_string(&mut out, &record.module);
+    out.push_str(",\"message\":");
+    json_string(&mut out, message.strip_suffix('\n').unwrap_or(message));
+    if let Some(event) = record.event {
+        out.push_str(",\"event\":");
+        json_string(&mut out, event);
+    }
+    if let Some(id) = record.boot_id {
+        out.push_str(",\"boot_id\":");
+        json_string(&mut out, id);
+    }
+    if let Some(thread) = &record.thread {
+        out.push_str(",\"thread\":");
+        json_string(&mut out, thread);
+    }
+    if let Some(id) = record.task {
+        let _ = write!(out, ",\"task\":{}", id);
+    }
+    if let Some(function) = record.function {
+        out.push_str(",\"function\":");
+        json_string(&mut out, function);
+    }
+    for (key, value) in record.fields.iter() {
+        out.push(',');
+        json_string(&mut out, key);
+        out.push(':');
+        json_string(&mut out, value);
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// The `level` of the machine formats, the same whatever `set_level_fmt` does.
+pub(crate) fn level_name(level: LEVEL) -> &'static str {
+    match level {
+        LEVEL::Trace => "trace",
+        LEVEL::Debug => "debug",
+        LEVEL::Info => "info",
+        LEVEL::Warn => "warn",
+        LEVEL::Error => "error",
+        LEVEL::Fatal => "fatal",
+        LEVEL::Off => "off",
+    }
+}
+
+/// Appends `s` to `out` as a quoted JSON string.
+pub(crate) fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Per-handler daily byte quotas, see `Logger::set_handler_quota()`.
+
+use crate::{passtimemode, stats::QuotaStats, timesec, MODE};
+
+pub(crate) enum Admission {
+    Admit,
+    Drop,
+    /// The first line over the quota of the current day; also dropped.
+    Exceeded,
+}
+
+pub(crate) struct Quota {
+    bytes_per_day: u64,
+    used: u64,
+    dropped: u64,
+    exceeded: bool,
+    startsec: u64,
+}
+
+impl Quota {
+    pub(crate) fn new(bytes_per_day: u64) -> Self {
+        Quota {
+            bytes_per_day,
+            used: 0,
+            dropped: 0,
+            exceeded: false,
+            startsec: timesec(),
+        }
+    }
+
+    pub(crate) fn admit(&mut self, bytes: u64) -> Admission {
+        if passtimemode(self.startsec, MODE::DAY) {
+            self.used = 0;
+            self.exceeded = false;
+            self.startsec = timesec();
+        }
+        if !self.exceeded && self.used + bytes <= self.bytes_per_day {
+            self.used += bytes;
+            return Admission::Admit;
+        }
+        self.dropped += 1;
+        if self.exceeded {
+            Admission::Drop
+        } else {
+            self.exceeded = true;
+            Admission::Exceeded
+        }
+    }
+
+    pub(crate) fn stats(&self, handler: &str) -> QuotaStats {
+        QuotaStats {
+            handler: handler.to_string(),
+            bytes_per_day: self.bytes_per_day,
+            used: self.used,
+            dropped: self.dropped,
+        }
+    }
+
+    pub(crate) fn exceeded_notice(&self, handler: &str) -> String {
+        format!("quota exceeded for handler `{}`: {} bytes per day; dropping its lines until the next day", handler, self.bytes_per_day)
+    }
+}
@@ -0,0 +1,378 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A line as captured at the call site, before it is laid out.
+//!
+//! `Logger::set_record_formatter` lays lines out from a [`RecordSnapshot`]
+//! instead of the `Format` flags, and `Async::Logger::set_format_stage`
+//! chooses whether that happens on the logging task or on the queue
+//! consumer. `Logger::set_console_formatter_impl` lays out the console
+//! lines alone with a [`LogFormatter`], such as the built-in
+//! `badge::ConsoleBadgeFormatter`.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::sync::Logger;
+//!
+//! let mut log = Logger::new();
+//! log.set_record_formatter(Box::new(|r| format!("{} {:?} {}:{} {}", r.time.format("%H:%M:%S"), r.level, r.module, r.line, r.message)));
+//! ```
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use chrono::{DateTime, Local};
+
+use crate::{color::{self, ConsoleColors}, fields::FieldMap, guard::Guarded, json::Schema, level_flag, log_fmt, places, preset::{journald_priority, json_record, Preset}, tee::TeeLayout, AttrFormat, ConsoleStream, Format, Inside, LogContent, COLUMN, LEVEL};
+
+/// One line with the state it was logged in: the time, the call site, the
+/// message and the dynamic fields. `file` is empty and `line` is 0 when the
+/// format doesn't show them.
+#[derive(Clone, Debug)]
+pub struct RecordSnapshot<'a> {
+    pub level: LEVEL,
+    pub time: DateTime<Local>,
+    pub module: Cow<'a, str>,
+    pub file: Cow<'a, str>,
+    pub line: u32,
+    pub message: String,
+    pub fields: FieldMap,
+    pub seq: u64,
+    /// See `Logger::set_time_subseq`.
+    pub subseq: Option<u32>,
+    /// The event ID given to the macro, see `events`.
+    pub event: Option<&'static str>,
+    /// The boot ID when the logger includes it, see `boot`.
+    pub boot_id: Option<&'static str>,
+    /// The name or ID of the logging thread, with `Format::ThreadId` or when
+    /// the formatter places `{thread}`.
+    pub thread: Option<Arc<str>>,
+    /// The tokio task logging to the async logger, with `Format::ThreadId`
+    /// or when the formatter places `{task}`.
+    pub task: Option<tokio::task::Id>,
+    /// The function the macro is in, with `Format::FuncName` or when the
+    /// formatter places `{function}`.
+    pub function: Option<&'static str>,
+}
+
+impl RecordSnapshot<'_> {
+    /// The snapshot with its borrowed names copied, to send to another thread.
+    pub fn into_owned(self) -> RecordSnapshot<'static> {
+        RecordSnapshot {
+            level: self.level,
+            time: self.time,
+            module: Cow::Owned(self.module.into_owned()),
+            file: Cow::Owned(self.file.into_owned()),
+            line: self.line,
+            message: self.message,
+            fields: self.fields,
+            seq: self.seq,
+            subseq: self.subseq,
+            event: self.event,
+            boot_id: self.boot_id,
+            thread: self.thread,
+            task: self.task,
+            function: self.function,
+        }
+    }
+}
+
+/// The callback of `Logger::set_record_formatter`: one laid-out line,
+/// newline included. The body formats of `set_attr_format` still apply.
+pub type RecordFormatter = Box<dyn Fn(&RecordSnapshot) -> String + Send + Sync>;
+
+type SharedFormatter = Arc<Guarded<RecordFormatter>>;
+
+/// Lays a line out from its snapshot, newline included. Closures taking a
+/// `&RecordSnapshot` are formatters too.
+pub trait LogFormatter: Send + Sync {
+    fn format(&self, record: &RecordSnapshot) -> String;
+}
+
+impl<F: Fn(&RecordSnapshot) -> String + Send + Sync> LogFormatter for F {
+    fn format(&self, record: &RecordSnapshot) -> String {
+        self(record)
+    }
+}
+
+type SharedLogFormatter = Arc<Guarded<Box<dyn LogFormatter>>>;
+
+/// Where `Async::Logger` lays lines out in `PRINTMODE::DELAY`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FormatStage {
+    /// In the logging task, before the line is queued.
+    #[default]
+    CallSite,
+    /// In the queue consumer; the call site only takes the snapshot. Custom
+    /// `set_time_fmt` functions then run on the consumer too.
+    Worker,
+}
+
+/// How lines are laid out beyond their `Format` flags, cloned cheaply to
+/// the queue consumer when it formats.
+#[derive(Clone, Default)]
+pub(crate) struct Render {
+    pub(crate) attrfmt: AttrFormat,
+    pub(crate) preset: Option<Preset>,
+    pub(crate) formatter: Option<SharedFormatter>,
+    /// Keeps ANSI escapes in the file body, see `Logger::allow_ansi_in_files`.
+    pub(crate) allow_ansi: bool,
+    /// The JSON schema pinned by `Logger::set_json_schema_version`.
+    pub(crate) json_schema: Option<Schema>,
+    /// Lays out the console lines, see `Logger::set_console_formatter_impl`.
+    pub(crate) console_formatter: Option<SharedLogFormatter>,
+    /// The layouts of the tee files, see `Logger::add_tee_file`.
+    pub(crate) tees: Vec<TeeLayout>,
+    /// Set when the console lines get colors, see `color`.
+    pub(crate) console_color: Option<ConsoleColors>,
+    /// Where the console lines go, see `Logger::set_console_stream`.
+    pub(crate) console_stream: ConsoleStream,
+    /// The level names of `Logger::set_level_label`.
+    pub(crate) labels: LevelLabels,
+    /// Keeps the file and line of each line in its content, for journald.
+    pub(crate) source: bool,
+    /// The strftime format of `{time}`, see `Logger::set_time_format`.
+    pub(crate) time_format: Option<String>,
+    /// The columns of the default layout, see `Logger::set_column_order`.
+    pub(crate) columns: Option<Arc<[COLUMN]>>,
+}
+
+/// The level names set with `Logger::set_level_label`, kept as the level
+/// flags they make.
+#[derive(Clone, Default)]
+pub(crate) struct LevelLabels([Option<String>; 7]);
+
+impl LevelLabels {
+    pub(crate) fn set(&mut self, level: LEVEL, label: &str) {
+        self.0[level as usize - 1] = Some(format!("[{}]", label));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = LevelLabels::default();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.iter().all(Option::is_none)
+    }
+
+    /// The level flag of `level`, the default one if it has no label.
+    pub(crate) fn flag(&self, level: LEVEL) -> &str {
+        self.0[level as usize - 1].as_deref().unwrap_or(level_flag(level))
+    }
+}
+
+impl Render {
+    pub(crate) fn set_formatter(&mut self, f: RecordFormatter) {
+        self.formatter = Some(Arc::new(Guarded::new("record formatter", f)));
+    }
+
+    /// The names of the callbacks disabled after panicking, see `health`.
+    pub(crate) fn disabled_callbacks(&self) -> Vec<&'static str> {
+        let a = &self.attrfmt;
+        [
+            self.formatter.as_ref().and_then(|g| g.disabled()),
+            self.console_formatter.as_ref().and_then(|g| g.disabled()),
+            a.levelfmt.as_ref().and_then(|g| g.disabled()),
+            a.timefmt.as_ref().and_then(|g| g.disabled()),
+            a.filebodyfmt.as_ref().and_then(|g| g.disabled()),
+            a.consolebodyfmt.as_ref().and_then(|g| g.disabled()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Enables every callback again.
+    pub(crate) fn reset_callbacks(&self) {
+        let a = &self.attrfmt;
+        self.formatter.iter().for_each(|g| g.reset());
+        self.console_formatter.iter().for_each(|g| g.reset());
+        a.levelfmt.iter().for_each(|g| g.reset());
+        a.timefmt.iter().for_each(|g| g.reset());
+        a.filebodyfmt.iter().for_each(|g| g.reset());
+        a.consolebodyfmt.iter().for_each(|g| g.reset());
+    }
+
+    /// Whether lines are their `Format` flags around the message and
+    /// nothing more, see `Logger::print_static`.
+    pub(crate) fn is_plain(&self) -> bool {
+        let a = &self.attrfmt;
+        self.formatter.is_none()
+            && self.preset.is_none()
+            && self.console_formatter.is_none()
+            && self.tees.is_empty()
+            && a.levelfmt.is_none()
+            && a.timefmt.is_none()
+            && a.filebodyfmt.is_none()
+            && a.consolebodyfmt.is_none()
+            && self.console_color.is_none()
+            && self.console_stream == ConsoleStream::Stdout
+            && self.labels.is_empty()
+            && !self.source
+            && self.time_format.is_none()
+            && self.columns.is_none()
+    }
+
+    pub(crate) fn set_console_formatter(&mut self, f: Box<dyn LogFormatter>) {
+        self.console_formatter = Some(Arc::new(Guarded::new("console formatter", f)));
+    }
+
+    /// `layout`, with the console body from the console formatter if there
+    /// is one and it doesn't panic, else colored if asked for a text line,
+    /// without ANSI escapes for a console that doesn't take them, and the
+    /// bodies of the tee files.
+    pub(crate) fn content(&self, record: &RecordSnapshot, fmat: u8, formatter: Option<&String>) -> LogContent {
+        let mut content = self.layout(record, fmat, formatter);
+        content.stderr = self.console_stream.is_stderr(record.level);
+        if let Some(s) = self.console_formatter.as_ref().and_then(|f| f.call(|f| f.format(record))) {
+            content.console_body = Some(s);
+        } else if let Some(colors) = self.console_color.filter(|c| self.preset.is_none() && c.on(content.stderr)) {
+            let flag = self.labels.flag(record.level);
+            content.console_body = Some(color::paint(colors.opts, record.level, flag, content.console_body.as_deref().unwrap_or(&content.file_body)));
+        }
+        if !color::ansi(content.stderr) {
+            let s = content.console_body.as_deref().unwrap_or(&content.file_body);
+            if s.contains('\x1b') {
+                content.console_body = Some(strip_ansi(s));
+            }
+        }
+        if !self.tees.is_empty() {
+            content.tees = self.tees.iter().map(|tee| self.tee_body(tee, &content, record, fmat, formatter)).collect();
+        }
+        if self.source {
+            content.source = Some((record.file.to_string(), record.line));
+        }
+        content
+    }
+
+    /// The line of a tee file: the file body when it is in the tee's mode
+    /// already, else `record` laid out again.
+    fn tee_body(&self, tee: &TeeLayout, content: &LogContent, record: &RecordSnapshot, fmat: u8, formatter: Option<&String>) -> String {
+        let json = matches!(self.preset, Some(Preset::K8s(_) | Preset::Json)) && self.formatter.is_none();
+        match tee {
+            TeeLayout::Text if json => Render { preset: None, ..self.clone() }.layout(record, fmat, formatter).file_body,
+            TeeLayout::Json(k8s) if !json => k8s.render(record, self.json_schema.unwrap_or(Schema::CURRENT)),
+            _ => content.file_body.clone(),
+        }
+    }
+
+    /// The file and console bodies of `record` in format `fmat`.
+    fn layout(&self, record: &RecordSnapshot, fmat: u8, formatter: Option<&String>) -> LogContent {
+        let _inside = Inside::enter();
+        if let Some(s) = self.formatter.as_ref().and_then(|f| f.call(|f| f(record))) {
+            return self.bodies(record.level, s, None);
+        }
+        match &self.preset {
+            Some(Preset::K8s(k8s)) => return LogContent::new(k8s.render(record, self.json_schema.unwrap_or(Schema::CURRENT)), None),
+            Some(Preset::Json) => return LogContent::new(json_record(record), None),
+            _ => {}
+        }
+        let placed = |name| formatter.is_some_and(|f| places(f, name));
+        let event = record.event.filter(|_| !placed("event"));
+        let boot_id = record.boot_id.filter(|_| !placed("bootid"));
+        let message = match (event, boot_id) {
+            (None, None) if record.fields.is_empty() => Cow::Borrowed(record.message.as_str()),
+            (None, None) => Cow::Owned(record.fields.append_to(record.message.clone())),
+            (event, boot_id) => {
+                let mut fields = FieldMap::new();
+                if let Some(id) = event {
+                    fields.insert("event", id);
+                }
+                if let Some(id) = boot_id {
+                    fields.insert("boot_id", id);
+                }
+                Cow::Owned(record.fields.append_to(fields.append_to(record.message.clone())))
+            }
+        };
+        let levelfmt = self.attrfmt.levelfmt.as_ref();
+        let line = |fmat| {
+            log_fmt(
+                (levelfmt.is_some() || !self.labels.is_empty())
+                    .then_some(|level| levelfmt.and_then(|g| g.call(|f| f(level))).or_else(|| Some(self.labels.flag(level).to_string()))),
+                self.attrfmt.timefmt.as_ref().map(|g| || g.call(|f| f())),
+                self.time_format.as_deref(),
+                fmat,
+                formatter,
+                self.columns.as_deref(),
+                record,
+                &message,
+            )
+        };
+        let s = line(fmat);
+        let console_s = if let Some(Preset::Systemd) = self.preset {
+            let s = line(fmat & !(Format::Date | Format::Time | Format::Microseconds));
+            Some(journald_priority(record.level).to_string() + &self.console_body(record.level, s))
+        } else {
+            None
+        };
+        self.bodies(record.level, s, console_s)
+    }
+
+    /// The file body of `s` and, unless given, its console body. A body
+    /// format that panics leaves `s` as it is. ANSI escapes are stripped
+    /// from the file body, the console keeps them.
+    fn bodies(&self, level: LEVEL, s: String, console_s: Option<String>) -> LogContent {
+        let mut console_s = console_s.or_else(|| self.attrfmt.consolebodyfmt.as_ref().map(|_| self.console_body(level, s.clone())));
+        let mut file_s = match &self.attrfmt.filebodyfmt {
+            Some(g) => g.call(|f| f(level, s.clone())).unwrap_or(s),
+            None => s,
+        };
+        if !self.allow_ansi && file_s.as_bytes().contains(&0x1b) {
+            let stripped = strip_ansi(&file_s);
+            console_s.get_or_insert(file_s);
+            file_s = stripped;
+        }
+        LogContent::new(file_s, console_s)
+    }
+
+    fn console_body(&self, level: LEVEL, s: String) -> String {
+        match &self.attrfmt.consolebodyfmt {
+            Some(g) => g.call(|f| f(level, s.clone())).unwrap_or(s),
+            None => s,
+        }
+    }
+}
+
+/// `s` without its ANSI escape sequences: CSI ones such as colors, OSC
+/// ones such as hyperlinks, and two-byte ones.
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next().is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
@@ -0,0 +1,425 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lines shipped over TCP or UDP, e.g. to a Logstash or Vector input, with
+//! `Logger::set_remote`.
+//!
+//! Every line written goes to the remote end too, as the files get it: a
+//! text line, or a JSON object with `set_format_json`, one per line over
+//! TCP and one per datagram over UDP. Lines wait in a queue of
+//! `capacity` lines for a sender of their own, a thread or, for the async
+//! logger, a task; when the queue is full the oldest line is dropped and
+//! counted by `remote_dropped`. The sender connects again after a failure,
+//! waiting twice as long each time up to 10 seconds, and reports the
+//! failures as `Category::Remote` diagnostics. Lines sent when a TCP
+//! connection broke are sent again, so the remote end may get one twice.
+//!
+//! `flush`, `clear_remote` and dropping the logger wait at most
+//! `flush_timeout` for the queue to be sent.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::remote::{RemoteConfig, RemoteTransport};
+//!
+//! let mut log = tklog::sync::Logger::new();
+//! log.set_format_json(true).set_remote(RemoteConfig::new(RemoteTransport::Tcp("vector:9000".to_string())));
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Write},
+    net::{TcpStream, UdpSocket},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+
+use crate::diagnostics::{self, Category};
+
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteTransport {
+    /// A `host:port` resolved at each connect.
+    Tcp(String),
+    Udp(String),
+}
+
+impl RemoteTransport {
+    /// The transport of an endpoint such as `tcp://logs:5140` or
+    /// `udp://logs:514`, as it displays.
+    pub fn parse(endpoint: &str) -> Option<Self> {
+        match endpoint.split_once("://")? {
+            ("tcp", addr) if !addr.is_empty() => Some(RemoteTransport::Tcp(addr.to_string())),
+            ("udp", addr) if !addr.is_empty() => Some(RemoteTransport::Udp(addr.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RemoteTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteTransport::Tcp(addr) => write!(f, "tcp://{}", addr),
+            RemoteTransport::Udp(addr) => write!(f, "udp://{}", addr),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteConfig {
+    pub transport: RemoteTransport,
+    /// The lines the queue holds before dropping the oldest. Default: 10000.
+    pub capacity: usize,
+    /// How long a flush waits for the queue to be sent. Default: 5 seconds.
+    pub flush_timeout: Duration,
+}
+
+impl RemoteConfig {
+    pub fn new(transport: RemoteTransport) -> Self {
+        RemoteConfig { transport, capacity: 10_000, flush_timeout: Duration::from_secs(5) }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    lines: VecDeque<String>,
+    /// The lines the sender took and hasn't sent yet.
+    sending: usize,
+    dropped: u64,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    /// Signaled on every change of `state`, for the sender thread and the
+    /// flushes.
+    changed: Condvar,
+    /// Wakes the sender task.
+    wake: Notify,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn notify(&self) {
+        self.changed.notify_all();
+        self.wake.notify_one();
+    }
+
+    /// The lines queued, once there are any; None once closed and sent.
+    fn take(&self, blocking: bool) -> Option<Vec<String>> {
+        let mut state = self.lock();
+        while blocking && state.lines.is_empty() && !state.closed {
+            state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        if state.lines.is_empty() && state.closed {
+            return None;
+        }
+        let batch: Vec<String> = state.lines.drain(..).collect();
+        state.sending = batch.len();
+        Some(batch)
+    }
+
+    fn sent(&self) {
+        self.lock().sending = 0;
+        self.notify();
+    }
+
+    /// Drops the lines left once closed, false until then.
+    fn given_up(&self, unsent: usize) -> bool {
+        let mut state = self.lock();
+        if !state.closed {
+            return false;
+        }
+        state.dropped += unsent as u64;
+        state.sending = 0;
+        drop(state);
+        self.notify();
+        true
+    }
+}
+
+/// The queue of a logger's remote end and its sender.
+pub(crate) struct Remote {
+    config: RemoteConfig,
+    shared: Arc<Shared>,
+}
+
+impl Remote {
+    /// Starts the sender as a task of the current runtime, or as a thread
+    /// outside of any.
+    pub(crate) fn new(config: RemoteConfig, task: bool) -> Self {
+        let shared = Arc::new(Shared::default());
+        let transport = config.transport.clone();
+        let sender = shared.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) if task => {
+                runtime.spawn(send_task(transport, sender));
+            }
+            _ => {
+                thread::spawn(move || send_thread(transport, sender));
+            }
+        }
+        Remote { config, shared }
+    }
+
+    /// Queues a line, dropping the oldest when the queue is full.
+    pub(crate) fn push(&self, line: &str) {
+        let mut state = self.shared.lock();
+        if state.lines.len() >= self.config.capacity.max(1) {
+            state.lines.pop_front();
+            state.dropped += 1;
+        }
+        state.lines.push_back(line.to_string());
+        drop(state);
+        self.shared.notify();
+    }
+
+    pub(crate) fn config(&self) -> &RemoteConfig {
+        &self.config
+    }
+
+    /// The settings of the remote end at `endpoint` for `LogConfig::remote`:
+    /// those of `current` with the new transport. None, which clears the
+    /// remote end, for no endpoint or one `RemoteTransport::parse` refuses.
+    pub(crate) fn reconfigured(current: Option<&Remote>, endpoint: Option<&str>) -> Option<RemoteConfig> {
+        let transport = RemoteTransport::parse(endpoint?)?;
+        Some(match current {
+            Some(r) => RemoteConfig { transport, ..r.config.clone() },
+            None => RemoteConfig::new(transport),
+        })
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.shared.lock().dropped
+    }
+
+    /// Waits at most `flush_timeout` for the lines queued to be sent; true
+    /// when they were. False at once on a current-thread runtime, whose
+    /// sender task can't run meanwhile.
+    pub(crate) fn flush_blocking(&self) -> bool {
+        if tokio::runtime::Handle::try_current().is_ok_and(|h| h.runtime_flavor() == tokio::runtime::RuntimeFlavor::CurrentThread) {
+            return self.is_sent();
+        }
+        let deadline = Instant::now() + self.config.flush_timeout;
+        let mut state = self.shared.lock();
+        while !state.lines.is_empty() || state.sending > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = self.shared.changed.wait_timeout(state, deadline - now).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        true
+    }
+
+    /// `flush_blocking` for async code.
+    pub(crate) async fn flush(&self) -> bool {
+        let deadline = Instant::now() + self.config.flush_timeout;
+        while !self.is_sent() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        true
+    }
+
+    fn is_sent(&self) -> bool {
+        let state = self.shared.lock();
+        state.lines.is_empty() && state.sending == 0
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        self.flush_blocking();
+        self.shared.lock().closed = true;
+        self.shared.notify();
+    }
+}
+
+fn failed(transport: &RemoteTransport, e: &io::Error) {
+    diagnostics::report(Category::Remote, None, format!("remote {:?}: {}; its lines are queued", transport, e));
+}
+
+enum Conn {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+}
+
+impl Conn {
+    fn open(transport: &RemoteTransport) -> io::Result<Self> {
+        match transport {
+            RemoteTransport::Tcp(addr) => Ok(Conn::Tcp(TcpStream::connect(addr)?)),
+            RemoteTransport::Udp(addr) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(addr)?;
+                Ok(Conn::Udp(socket))
+            }
+        }
+    }
+
+    fn send(&mut self, batch: &[String]) -> io::Result<()> {
+        match self {
+            Conn::Tcp(stream) => stream.write_all(framed(batch).as_bytes()),
+            Conn::Udp(socket) => batch.iter().try_for_each(|line| socket.send(line.as_bytes()).map(|_| ())),
+        }
+    }
+}
+
+/// The lines of `batch` as a stream, each ending in a newline.
+fn framed(batch: &[String]) -> String {
+    let mut out = String::with_capacity(batch.iter().map(|l| l.len() + 1).sum());
+    for line in batch {
+        out.push_str(line);
+        if !line.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn send_thread(transport: RemoteTransport, shared: Arc<Shared>) {
+    let mut conn = None;
+    let mut backoff = MIN_BACKOFF;
+    while let Some(batch) = shared.take(true) {
+        loop {
+            let result = match conn.as_mut() {
+                Some(c) => Conn::send(c, &batch),
+                None => Conn::open(&transport).and_then(|c| Conn::send(conn.insert(c), &batch)),
+            };
+            match result {
+                Ok(()) => {
+                    backoff = MIN_BACKOFF;
+                    shared.sent();
+                    break;
+                }
+                Err(e) => {
+                    conn = None;
+                    failed(&transport, &e);
+                    if shared.given_up(batch.len()) {
+                        break;
+                    }
+                    // Woken early by a close, to give up at once.
+                    let state = shared.lock();
+                    drop(shared.changed.wait_timeout_while(state, backoff, |s| !s.closed));
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    }
+}
+
+/// A connection of the send task; there is none on wasm32, where tokio
+/// has no sockets.
+enum AsyncConn {
+    #[cfg(not(target_arch = "wasm32"))]
+    Tcp(tokio::net::TcpStream),
+    #[cfg(not(target_arch = "wasm32"))]
+    Udp(tokio::net::UdpSocket),
+}
+
+impl AsyncConn {
+    #[cfg(target_arch = "wasm32")]
+    async fn open(_: &RemoteTransport) -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn open(transport: &RemoteTransport) -> io::Result<Self> {
+        match transport {
+            RemoteTransport::Tcp(addr) => Ok(AsyncConn::Tcp(tokio::net::TcpStream::connect(addr).await?)),
+            RemoteTransport::Udp(addr) => {
+                let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await?;
+                socket.connect(addr).await?;
+                Ok(AsyncConn::Udp(socket))
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn send(&mut self, _: &[String]) -> io::Result<()> {
+        match *self {}
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn send(&mut self, batch: &[String]) -> io::Result<()> {
+        match self {
+            AsyncConn::Tcp(stream) => stream.write_all(framed(batch).as_bytes()).await,
+            AsyncConn::Udp(socket) => {
+                for line in batch {
+                    socket.send(line.as_bytes()).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn send_task(transport: RemoteTransport, shared: Arc<Shared>) {
+    let mut conn = None;
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let notified = shared.wake.notified();
+        let Some(batch) = shared.take(false) else {
+            return;
+        };
+        if batch.is_empty() {
+            notified.await;
+            continue;
+        }
+        loop {
+            let result = match conn.as_mut() {
+                Some(c) => AsyncConn::send(c, &batch).await,
+                None => match AsyncConn::open(&transport).await {
+                    Ok(c) => AsyncConn::send(conn.insert(c), &batch).await,
+                    Err(e) => Err(e),
+                },
+            };
+            match result {
+                Ok(()) => {
+                    backoff = MIN_BACKOFF;
+                    shared.sent();
+                    break;
+                }
+                Err(e) => {
+                    conn = None;
+                    failed(&transport, &e);
+                    if shared.given_up(batch.len()) {
+                        break;
+                    }
+                    // Woken early by a close, to give up at once.
+                    let until = Instant::now() + backoff;
+                    while !shared.lock().closed && Instant::now() < until {
+                        let _ = tokio::time::timeout(until - Instant::now(), shared.wake.notified()).await;
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rotation groups: time-rotated handlers that roll over together, so the
+//! backups of one period line up across files.
+//!
+//! A group takes over the time checks of its members. On every line the
+//! logger checks the group against its clock, see `Logger::set_clock`;
+//! once the period of the member that started first is over, every member
+//! rotates at once, also those with no line that period, and all the
+//! backups carry the stamp of that period.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::handle::FileTimeMode;
+//! use tklog::sync::Logger;
+//! use tklog::MODE;
+//!
+//! let mut log = Logger::new();
+//! log.set_cutmode_by_time("app.log", MODE::DAY, 30, false)
+//!     .add_file_sink("errors", FileTimeMode::new("error.log", MODE::DAY, 30, false))
+//!     .add_file_sink("audit", FileTimeMode::new("audit.log", MODE::DAY, 30, false));
+//! log.set_rotation_group(&["app.log", "error.log", "audit.log"]).unwrap();
+//! ```
+
+use chrono::{DateTime, Local};
+
+use crate::{config::FileConfig, next_rotation, Error, CUTMODE, MODE};
+
+/// The handlers of one group and the period they are in.
+pub(crate) struct RotationGroup {
+    pub(crate) members: Vec<String>,
+    mode: MODE,
+    pub(crate) startsec: u64,
+    /// Whether a member with an empty file gets a backup too.
+    pub(crate) empty_backups: bool,
+}
+
+impl RotationGroup {
+    /// A group of `members`, whose files are described by `file` and current
+    /// periods start at `startsec`, from `now` at the latest. Errs with
+    /// `Error::InvalidRotationGroup` for a handler the logger doesn't have,
+    /// one that doesn't rotate by time, one in another period or group, or
+    /// one given twice.
+    pub(crate) fn new(
+        members: &[&str],
+        file: impl Fn(&str) -> Option<(FileConfig, u64)>,
+        grouped: impl Fn(&str) -> bool,
+        now: DateTime<Local>,
+        empty_backups: bool,
+    ) -> Result<Self, Error> {
+        let refused = |handler: &str, reason| Err(Error::InvalidRotationGroup { handler: handler.to_string(), reason });
+        let mut mode = None;
+        let mut startsec = wallsec(now);
+        for (i, id) in members.iter().enumerate() {
+            if members[..i].contains(id) {
+                return refused(id, "is given twice");
+            }
+            let Some((config, start)) = file(id) else {
+                return refused(id, "is not a file handler");
+            };
+            if config.cutmode == CUTMODE::SIZE {
+                return refused(id, "does not rotate by time");
+            }
+            if config.cutmode == CUTMODE::PATTERN {
+                return refused(id, "names its files by date");
+            }
+            if mode.is_some_and(|m| m != config.timemode) {
+                return refused(id, "rotates by another period");
+            }
+            if grouped(id) {
+                return refused(id, "is in another rotation group");
+            }
+            mode = Some(config.timemode);
+            startsec = startsec.min(start);
+        }
+        let Some(mode) = mode else {
+            return refused("", "no members");
+        };
+        Ok(RotationGroup { members: members.iter().map(|id| id.to_string()).collect(), mode, startsec, empty_backups })
+    }
+
+    /// The start of the period that ended and the start of the next one,
+    /// once `now` is past the current one.
+    pub(crate) fn check(&mut self, now: DateTime<Local>) -> Option<(u64, u64)> {
+        let nowsec = wallsec(now);
+        if nowsec < next_rotation(self.startsec, self.mode) {
+            return None;
+        }
+        Some((std::mem::replace(&mut self.startsec, nowsec), nowsec))
+    }
+}
+
+/// `now` in the wall-clock seconds of `timesec`.
+fn wallsec(now: DateTime<Local>) -> u64 {
+    now.naive_local().and_utc().timestamp() as u64
+}
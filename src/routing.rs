@@ -0,0 +1,189 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-level routing of lines to named sinks, see `Logger::set_routing`.
+//!
+//! Sinks are named `console`, `file` for the logger's own file, and
+//! `file:<name>` for a sink added with `add_file_sink` (or any file the
+//! logger already writes, by its file name).
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{handle::FileSizeMode, sync::Logger, LEVEL};
+//!
+//! let mut log = Logger::new();
+//! log.set_cutmode_by_size("app.log", 1 << 20, 5, true)
+//!     .add_file_sink("error", FileSizeMode::new("error.log", 1 << 20, 5, true));
+//! log.set_routing(|route| {
+//!     route.level(LEVEL::Error..).to(["file:error", "console"]);
+//!     route.level(..=LEVEL::Warn).to(["file"]);
+//! })
+//! .unwrap();
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::{Bound, RangeBounds};
+
+use crate::{
+    diagnostics::{self, Category},
+    Error, LEVEL,
+};
+
+/// The levels a routing rule applies to: `Off` is never routed.
+const LEVELS: [LEVEL; 6] = [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal];
+
+/// One level, or a range such as `LEVEL::Error..` or `..=LEVEL::Warn`.
+pub trait LevelSet {
+    fn contains_level(&self, level: LEVEL) -> bool;
+}
+
+impl LevelSet for LEVEL {
+    fn contains_level(&self, level: LEVEL) -> bool {
+        *self == level
+    }
+}
+
+impl<R: RangeBounds<LEVEL>> LevelSet for R {
+    fn contains_level(&self, level: LEVEL) -> bool {
+        let above = match self.start_bound() {
+            Bound::Included(l) => level >= *l,
+            Bound::Excluded(l) => level > *l,
+            Bound::Unbounded => true,
+        };
+        let below = match self.end_bound() {
+            Bound::Included(l) => level <= *l,
+            Bound::Excluded(l) => level < *l,
+            Bound::Unbounded => true,
+        };
+        above && below
+    }
+}
+
+/// The rules collected by the `set_routing` closure.
+#[derive(Default)]
+pub struct Routes {
+    rules: Vec<(Vec<LEVEL>, Vec<String>)>,
+}
+
+impl Routes {
+    pub fn level(&mut self, levels: impl LevelSet) -> Rule<'_> {
+        Rule { routes: self, levels: level_list(&levels) }
+    }
+}
+
+/// The routable levels in `levels`.
+pub(crate) fn level_list(levels: &impl LevelSet) -> Vec<LEVEL> {
+    LEVELS.into_iter().filter(|l| levels.contains_level(*l)).collect()
+}
+
+pub struct Rule<'a> {
+    routes: &'a mut Routes,
+    levels: Vec<LEVEL>,
+}
+
+impl Rule<'_> {
+    /// Sends the levels of this rule to `sinks`, in addition to what earlier
+    /// rules send them to.
+    pub fn to<I, S>(self, sinks: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.routes.rules.push((self.levels, sinks.into_iter().map(Into::into).collect()));
+    }
+}
+
+/// A resolved destination.
+#[derive(Clone, PartialEq)]
+pub(crate) enum Sink {
+    Console,
+    /// A file handler of the logger, by file name.
+    File(String),
+}
+
+/// The compiled matrix: the distinct sinks, and per level a bitmask over them.
+pub(crate) struct RoutingTable {
+    sinks: Vec<(String, Sink)>,
+    masks: [u64; 6],
+}
+
+impl RoutingTable {
+    /// Compiles `routes`, naming sinks with `resolve`. Warns on stderr about
+    /// levels with no destination.
+    pub(crate) fn compile(routes: Routes, resolve: impl Fn(&str) -> Option<Sink>) -> Result<Self, Error> {
+        let mut table = RoutingTable { sinks: Vec::new(), masks: [0; 6] };
+        for (levels, names) in routes.rules {
+            for name in names {
+                let i = match table.sinks.iter().position(|(n, _)| *n == name) {
+                    Some(i) => i,
+                    None => {
+                        let sink = resolve(&name).ok_or_else(|| Error::UnknownSink(name.clone()))?;
+                        if table.sinks.len() == 64 {
+                            return Err(Error::TooManySinks);
+                        }
+                        table.sinks.push((name, sink));
+                        table.sinks.len() - 1
+                    }
+                };
+                for l in &levels {
+                    table.masks[*l as usize - 1] |= 1 << i;
+                }
+            }
+        }
+        for l in LEVELS {
+            if table.masks[l as usize - 1] == 0 {
+                diagnostics::report(Category::Config, None, format!("routing sends {:?} lines nowhere", l));
+            }
+        }
+        Ok(table)
+    }
+
+    /// The destinations of `level`.
+    pub(crate) fn route(&self, level: LEVEL) -> impl Iterator<Item = &Sink> + '_ {
+        let mask = if level == LEVEL::Off { 0 } else { self.masks[level as usize - 1] };
+        self.sinks.iter().enumerate().filter(move |(i, _)| mask & (1 << i) != 0).map(|(_, (_, s))| s)
+    }
+
+    /// One `route: LEVEL -> sink, sink` line per level.
+    pub(crate) fn describe(&self) -> String {
+        let mut out = String::new();
+        for l in LEVELS {
+            let mask = self.masks[l as usize - 1];
+            let names: Vec<&str> = self.sinks.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, (n, _))| n.as_str()).collect();
+            let _ = writeln!(out, "route: {:?} -> {}", l, if names.is_empty() { "(none)".to_string() } else { names.join(", ") });
+        }
+        out
+    }
+}
+
+/// Resolves a sink name for a logger whose own file is `default_file`
+/// (empty without one); `named` maps the `add_file_sink` names to file
+/// names, and `has_file` tells the file names the logger writes.
+pub(crate) fn resolve(name: &str, default_file: &str, named: &HashMap<String, String>, has_file: impl Fn(&str) -> bool) -> Option<Sink> {
+    match name {
+        "console" => Some(Sink::Console),
+        "file" if !default_file.is_empty() => Some(Sink::File(default_file.to_string())),
+        _ => {
+            let n = name.strip_prefix("file:")?;
+            match named.get(n) {
+                Some(filename) => Some(Sink::File(filename.clone())),
+                None if n == default_file || has_file(n) => Some(Sink::File(n.to_string())),
+                None => None,
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What the async logger asks of its runtime: tasks, timers and files.
+//!
+//! `Async.rs`, `asyncfile.rs` and the compression of their backups go
+//! through `Rt`, the runtime they were built for, rather than through
+//! tokio itself. Tokio is the only runtime there is; on wasm32, where tokio
+//! has neither files nor standard streams, `Wasm` gives it those of `std`,
+//! which a browser refuses, or the console of `crate::wasm`.
+//!
+//! What the public API names of tokio, the runtime handles of
+//! `Logger::with_runtime` and `attach_runtime`, the task ID of records and
+//! the mutex of the async macros, stays with tokio, as do the channels of
+//! the queue, which run on any runtime.
+
+#[cfg(target_arch = "wasm32")]
+use std::io::Write;
+use std::{
+    fs::Metadata,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::AsyncWriteExt;
+
+/// The runtime of the async logger.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type Rt = Tokio;
+#[cfg(target_arch = "wasm32")]
+pub(crate) type Rt = Wasm;
+
+pub(crate) trait Runtime {
+    /// A file open to write.
+    type File: Send + Sync + 'static;
+
+    /// Runs `future` in a task of the current runtime.
+    fn spawn(future: impl Future<Output = ()> + Send + 'static);
+
+    async fn sleep(duration: Duration);
+
+    /// Runs `f`, which blocks, off the worker of a multi-thread runtime.
+    fn block_in_place<T>(f: impl FnOnce() -> T) -> T;
+
+    async fn stdout(data: &[u8]) -> io::Result<()>;
+
+    async fn stderr(data: &[u8]) -> io::Result<()>;
+
+    /// The output of `future`, or None if `deadline` comes first.
+    async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output>;
+
+    /// Opens `path` to append, creating it if need be.
+    async fn append(path: &Path) -> io::Result<Self::File>;
+
+    /// Creates `path` to write, failing if it exists.
+    async fn create_new(path: &Path) -> io::Result<Self::File>;
+
+    async fn write_all(file: &mut Self::File, data: &[u8]) -> io::Result<()>;
+
+    async fn flush(file: &mut Self::File) -> io::Result<()>;
+
+    async fn sync_data(file: &mut Self::File) -> io::Result<()>;
+
+    async fn file_metadata(file: &Self::File) -> io::Result<Metadata>;
+
+    /// A blocking handle of the file of `file`, written through by the
+    /// write buffer and locked by shared files.
+    async fn std_clone(file: &Self::File) -> io::Result<std::fs::File>;
+
+    /// `file` as a blocking one, if no operation of it is in flight; for a
+    /// drop, out of the runtime.
+    fn try_into_std(file: Self::File) -> Option<std::fs::File>;
+
+    async fn read(path: &Path) -> io::Result<Vec<u8>>;
+
+    async fn metadata(path: &Path) -> io::Result<Metadata>;
+
+    /// The entries of the directory `path` with their metadata, not
+    /// following links.
+    async fn read_dir(path: &Path) -> io::Result<Vec<(PathBuf, Metadata)>>;
+
+    async fn create_dir_all(path: &Path) -> io::Result<()>;
+
+    async fn remove_file(path: &Path) -> io::Result<()>;
+
+    async fn rename(from: &Path, to: &Path) -> io::Result<()>;
+
+    async fn hard_link(from: &Path, to: &Path) -> io::Result<()>;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct Tokio;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Runtime for Tokio {
+    type File = tokio::fs::File;
+
+    fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+        tokio::spawn(future);
+    }
+
+    async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    fn block_in_place<T>(f: impl FnOnce() -> T) -> T {
+        tokio::task::block_in_place(f)
+    }
+
+    async fn stdout(data: &[u8]) -> io::Result<()> {
+        let mut stdout = tokio::io::stdout();
+        stdout.write_all(data).await?;
+        stdout.flush().await
+    }
+
+    async fn stderr(data: &[u8]) -> io::Result<()> {
+        let mut stderr = tokio::io::stderr();
+        stderr.write_all(data).await?;
+        stderr.flush().await
+    }
+
+    async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output> {
+        tokio::time::timeout_at(deadline.into(), future).await.ok()
+    }
+
+    async fn append(path: &Path) -> io::Result<Self::File> {
+        tokio::fs::OpenOptions::new().append(true).create(true).open(path).await
+    }
+
+    async fn create_new(path: &Path) -> io::Result<Self::File> {
+        tokio::fs::OpenOptions::new().write(true).create_new(true).open(path).await
+    }
+
+    async fn write_all(file: &mut Self::File, data: &[u8]) -> io::Result<()> {
+        file.write_all(data).await
+    }
+
+    async fn flush(file: &mut Self::File) -> io::Result<()> {
+        file.flush().await
+    }
+
+    async fn sync_data(file: &mut Self::File) -> io::Result<()> {
+        file.sync_data().await
+    }
+
+    async fn file_metadata(file: &Self::File) -> io::Result<Metadata> {
+        file.metadata().await
+    }
+
+    async fn std_clone(file: &Self::File) -> io::Result<std::fs::File> {
+        Ok(file.try_clone().await?.into_std().await)
+    }
+
+    fn try_into_std(file: Self::File) -> Option<std::fs::File> {
+        file.try_into_std().ok()
+    }
+
+    async fn read(path: &Path) -> io::Result<Vec<u8>> {
+        tokio::fs::read(path).await
+    }
+
+    async fn metadata(path: &Path) -> io::Result<Metadata> {
+        tokio::fs::metadata(path).await
+    }
+
+    async fn read_dir(path: &Path) -> io::Result<Vec<(PathBuf, Metadata)>> {
+        let mut entries = Vec::new();
+        let mut dir = tokio::fs::read_dir(path).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            entries.push((entry.path(), entry.metadata().await?));
+        }
+        Ok(entries)
+    }
+
+    async fn create_dir_all(path: &Path) -> io::Result<()> {
+        tokio::fs::create_dir_all(path).await
+    }
+
+    async fn remove_file(path: &Path) -> io::Result<()> {
+        tokio::fs::remove_file(path).await
+    }
+
+    async fn rename(from: &Path, to: &Path) -> io::Result<()> {
+        tokio::fs::rename(from, to).await
+    }
+
+    async fn hard_link(from: &Path, to: &Path) -> io::Result<()> {
+        tokio::fs::hard_link(from, to).await
+    }
+}
+
+/// Tokio's tasks and timers, with the blocking files of `std` and its
+/// streams, or the browser console with the `wasm` feature.
+#[cfg(target_arch = "wasm32")]
+pub(crate) struct Wasm;
+
+#[cfg(target_arch = "wasm32")]
+impl Runtime for Wasm {
+    type File = std::fs::File;
+
+    fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+        tokio::spawn(future);
+    }
+
+    async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output> {
+        tokio::time::timeout_at(deadline.into(), future).await.ok()
+    }
+
+    fn block_in_place<T>(f: impl FnOnce() -> T) -> T {
+        f()
+    }
+
+    async fn stdout(data: &[u8]) -> io::Result<()> {
+        #[cfg(feature = "wasm")]
+        crate::wasm::print(&String::from_utf8_lossy(data), false);
+        #[cfg(not(feature = "wasm"))]
+        io::stdout().write_all(data)?;
+        Ok(())
+    }
+
+    async fn stderr(data: &[u8]) -> io::Result<()> {
+        #[cfg(feature = "wasm")]
+        crate::wasm::print(&String::from_utf8_lossy(data), true);
+        #[cfg(not(feature = "wasm"))]
+        io::stderr().write_all(data)?;
+        Ok(())
+    }
+
+    async fn append(path: &Path) -> io::Result<Self::File> {
+        std::fs::OpenOptions::new().append(true).create(true).open(path)
+    }
+
+    async fn create_new(path: &Path) -> io::Result<Self::File> {
+        std::fs::OpenOptions::new().write(true).create_new(true).open(path)
+    }
+
+    async fn write_all(file: &mut Self::File, data: &[u8]) -> io::Result<()> {
+        file.write_all(data)
+    }
+
+    async fn flush(file: &mut Self::File) -> io::Result<()> {
+        file.flush()
+    }
+
+    async fn sync_data(file: &mut Self::File) -> io::Result<()> {
+        file.sync_data()
+    }
+
+    async fn file_metadata(file: &Self::File) -> io::Result<Metadata> {
+        file.metadata()
+    }
+
+    async fn std_clone(file: &Self::File) -> io::Result<std::fs::File> {
+        file.try_clone()
+    }
+
+    fn try_into_std(file: Self::File) -> Option<std::fs::File> {
+        Some(file)
+    }
+
+    async fn read(path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    async fn metadata(path: &Path) -> io::Result<Metadata> {
+        std::fs::metadata(path)
+    }
+
+    async fn read_dir(path: &Path) -> io::Result<Vec<(PathBuf, Metadata)>> {
+        std::fs::read_dir(path)?.map(|entry| entry.and_then(|e| Ok((e.path(), e.metadata()?)))).collect()
+    }
+
+    async fn create_dir_all(path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    async fn remove_file(path: &Path) -> io::Result<()> {
+        std::fs::remove_file(path)
+    }
+
+    async fn rename(from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    async fn hard_link(from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::hard_link(from, to)
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Rotation scheduling for time-based file handlers.
+//!
+//! Instead of every write comparing the clock with the rotation period, a
+//! scheduler (a thread for the sync logger, a tokio task for the async one)
+//! sleeps until the next period boundary and raises the "rotation due" flag
+//! of the handlers that reached it. Between boundaries a write only loads
+//! that flag. The scheduler wakes at least every `RECHECK` so that wall-clock
+//! adjustments are picked up.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+
+use crate::{
+    next_rotation, now, passtimemode,
+    runtime::{Rt, Runtime},
+    MODE,
+};
+
+const RECHECK: Duration = Duration::from_secs(30);
+
+/// The rotation state of one handler, shared with its scheduler.
+pub(crate) struct RotationTimer(Arc<TimerState>);
+
+struct TimerState {
+    due: AtomicBool,
+    startsec: AtomicU64,
+    timemode: MODE,
+    shared: Arc<Shared>,
+}
+
+impl RotationTimer {
+    /// Whether the handler should check for a rotation on this write. Also
+    /// true once the scheduler is gone, e.g. with the runtime it ran on.
+    pub(crate) fn is_due(&self) -> bool {
+        self.0.due.load(Ordering::Acquire) || !self.0.shared.running.load(Ordering::Acquire)
+    }
+
+    /// Starts the next period after a rotation, waking the scheduler: its
+    /// sleep left this timer out, and a period may end before `RECHECK`.
+    pub(crate) fn restart(&self, startsec: u64) {
+        self.0.startsec.store(startsec, Ordering::Release);
+        self.0.due.store(false, Ordering::Release);
+        // Under the lock, so the thread can't miss it between a tick and its wait.
+        let _timers = self.0.shared.timers.lock().unwrap_or_else(|e| e.into_inner());
+        self.0.shared.changed.notify_all();
+        self.0.shared.notify.notify_one();
+    }
+}
+
+enum Runner {
+    Thread,
+    Task,
+}
+
+#[derive(Default)]
+struct Shared {
+    timers: Mutex<Vec<Weak<TimerState>>>,
+    changed: Condvar,
+    notify: Notify,
+    running: AtomicBool,
+    stopped: AtomicBool,
+}
+
+/// Owned by a logger; stops its thread or task when dropped.
+pub(crate) struct Scheduler {
+    runner: Runner,
+    shared: Arc<Shared>,
+}
+
+impl Scheduler {
+    pub(crate) fn new_thread() -> Self {
+        Scheduler {
+            runner: Runner::Thread,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Needs a tokio runtime by the time the first handler registers.
+    pub(crate) fn new_task() -> Self {
+        Scheduler {
+            runner: Runner::Task,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Schedules a handler whose current period started at `startsec`.
+    pub(crate) fn register(&self, startsec: u64, timemode: MODE) -> RotationTimer {
+        let timer = Arc::new(TimerState {
+            due: AtomicBool::new(passtimemode(startsec, timemode)),
+            startsec: AtomicU64::new(startsec),
+            timemode,
+            shared: self.shared.clone(),
+        });
+        self.shared.timers.lock().unwrap_or_else(|e| e.into_inner()).push(Arc::downgrade(&timer));
+        if !self.shared.running.swap(true, Ordering::AcqRel) {
+            self.spawn();
+        }
+        self.shared.changed.notify_all();
+        self.shared.notify.notify_one();
+        RotationTimer(timer)
+    }
+
+    fn spawn(&self) {
+        let shared = self.shared.clone();
+        match self.runner {
+            Runner::Thread => {
+                thread::spawn(move || {
+                    let mut timers = shared.timers.lock().unwrap_or_else(|e| e.into_inner());
+                    while !shared.stopped.load(Ordering::Acquire) {
+                        let wait = tick(&mut timers);
+                        timers = shared.changed.wait_timeout(timers, wait).unwrap_or_else(|e| e.into_inner()).0;
+                    }
+                    shared.running.store(false, Ordering::Release);
+                });
+            }
+            Runner::Task => {
+                Rt::spawn(async move {
+                    // Dropped with the task, also when its runtime shuts down.
+                    let _running = Running(shared.clone());
+                    while !shared.stopped.load(Ordering::Acquire) {
+                        let wait = tick(&mut shared.timers.lock().unwrap_or_else(|e| e.into_inner()));
+                        tokio::select! {
+                            _ = Rt::sleep(wait) => {}
+                            _ = shared.notify.notified() => {}
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Release);
+        self.shared.changed.notify_all();
+        self.shared.notify.notify_one();
+    }
+}
+
+struct Running(Arc<Shared>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Release);
+    }
+}
+
+/// Raises the flag of every timer past its boundary and returns how long to
+/// sleep until the next one.
+fn tick(timers: &mut Vec<Weak<TimerState>>) -> Duration {
+    let now = now();
+    let nowsec = now.naive_local().and_utc().timestamp() as u64;
+    let mut wait = RECHECK;
+    timers.retain(|t| {
+        let Some(t) = t.upgrade() else {
+            return false;
+        };
+        let next = next_rotation(t.startsec.load(Ordering::Acquire), t.timemode);
+        if nowsec >= next {
+            t.due.store(true, Ordering::Release);
+        } else {
+            let left = Duration::from_secs(next - nowsec).saturating_sub(Duration::from_nanos(now.timestamp_subsec_nanos() as u64));
+            wait = wait.min(left);
+        }
+        true
+    });
+    wait
+}
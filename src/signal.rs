@@ -0,0 +1,69 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SIGHUP, counted for the loggers that reopen their files on it, see
+//! `Logger::enable_reopen_on_sighup`.
+//!
+//! A thread of its own waits for the signal with `tokio::signal`, so that
+//! a sync logger needs no runtime. Each logger compares the count with the
+//! one it last saw before it writes, and reopens its files when it moved.
+
+use std::{
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+    sync::mpsc,
+    thread,
+};
+
+use once_cell::sync::Lazy;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::logerror;
+
+static HANGUPS: AtomicU64 = AtomicU64::new(0);
+
+/// Whether the listener could install its handler, decided once per process.
+static LISTENER: Lazy<io::Result<()>> = Lazy::new(|| {
+    let (installed, result) = mpsc::channel();
+    thread::Builder::new().name("tklog-sighup".to_string()).spawn(move || {
+        let rt = match tokio::runtime::Builder::new_current_thread().enable_io().build() {
+            Ok(rt) => rt,
+            Err(e) => return installed.send(Err(e)).unwrap_or(()),
+        };
+        rt.block_on(async {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(s) => s,
+                Err(e) => return installed.send(Err(e)).unwrap_or(()),
+            };
+            let _ = installed.send(Ok(()));
+            while hangup.recv().await.is_some() {
+                HANGUPS.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    })?;
+    result.recv().unwrap_or_else(|_| Err(io::Error::other("the SIGHUP listener stopped")))
+});
+
+/// Starts listening for SIGHUP, once; from then on the signal no longer
+/// ends the process.
+pub(crate) fn listen() -> io::Result<()> {
+    LISTENER.as_ref().map(|_| ()).map_err(logerror::copy)
+}
+
+/// The SIGHUPs received so far.
+pub(crate) fn hangups() -> u64 {
+    HANGUPS.load(Ordering::Relaxed)
+}
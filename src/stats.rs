@@ -0,0 +1,300 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime statistics of a logger, see `Logger::stats()`.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{memory, output::OutputMode};
+
+/// Upper bounds of the latency buckets; the last bucket is everything above.
+pub const LATENCY_BOUNDS: [Duration; 3] = [Duration::from_millis(1), Duration::from_millis(10), Duration::from_millis(100)];
+
+/// Enqueue-to-write latency of the sampled lines: `<1ms`, `<10ms`, `<100ms`, `>=100ms`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LatencyHistogram {
+    pub buckets: [u64; 4],
+    pub sum: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let i = LATENCY_BOUNDS.iter().position(|b| latency < *b).unwrap_or(LATENCY_BOUNDS.len());
+        self.buckets[i] += 1;
+        self.sum += latency;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// Queue statistics of one sink: a log file, or `console` for a logger without one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SinkStats {
+    pub sink: String,
+    /// Lines delivered through the DELAY queue.
+    pub lines: u64,
+    pub queue_depth: u64,
+    pub max_queue_depth: u64,
+    pub latency: LatencyHistogram,
+}
+
+/// Consumption of one handler's daily byte quota.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuotaStats {
+    pub handler: String,
+    pub bytes_per_day: u64,
+    /// Bytes written since the last day boundary.
+    pub used: u64,
+    /// Lines dropped over the quota since it was set.
+    pub dropped: u64,
+}
+
+/// What one tee file, see `Logger::add_tee_file`, has written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeeStats {
+    pub file: String,
+    pub mode: OutputMode,
+    pub lines: u64,
+    pub bytes: u64,
+}
+
+/// The process-wide memory budget of queued lines, see `set_memory_budget`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryStats {
+    /// 0 without a budget.
+    pub budget: u64,
+    /// Bytes charged for the lines queued now.
+    pub used: u64,
+    /// Lines shed over the budget: Trace and Debug, Info, Warn and above.
+    pub shed: [u64; 3],
+}
+
+/// A snapshot of a logger's statistics, one entry per sink ordered by name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogStats {
+    pub sinks: Vec<SinkStats>,
+    /// Lines dropped by storm control.
+    pub storm_suppressed: u64,
+    /// Lines dropped by the filters, see `filter`.
+    pub filtered: u64,
+    /// Lines dropped past the pre-start buffer of an async logger, see
+    /// `set_prestart_buffer`.
+    pub prestart_dropped: u64,
+    /// One entry per handler with a quota, ordered by name.
+    pub quotas: Vec<QuotaStats>,
+    /// One entry per tee file, ordered by name.
+    pub tees: Vec<TeeStats>,
+    /// Shared by all loggers.
+    pub memory: MemoryStats,
+}
+
+impl LogStats {
+    pub fn sink(&self, name: &str) -> Option<&SinkStats> {
+        self.sinks.iter().find(|s| s.sink == name)
+    }
+
+    pub fn quota(&self, handler: &str) -> Option<&QuotaStats> {
+        self.quotas.iter().find(|q| q.handler == handler)
+    }
+
+    pub fn tee(&self, file: &str) -> Option<&TeeStats> {
+        self.tees.iter().find(|t| t.file == file)
+    }
+
+    /// Renders the snapshot in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP tklog_queue_lines_total Lines delivered through the queue.\n# TYPE tklog_queue_lines_total counter\n");
+        for s in &self.sinks {
+            let _ = writeln!(out, "tklog_queue_lines_total{{sink=\"{}\"}} {}", label(&s.sink), s.lines);
+        }
+        out.push_str("# HELP tklog_queue_depth Lines currently queued.\n# TYPE tklog_queue_depth gauge\n");
+        for s in &self.sinks {
+            let _ = writeln!(out, "tklog_queue_depth{{sink=\"{}\"}} {}", label(&s.sink), s.queue_depth);
+        }
+        out.push_str("# HELP tklog_queue_depth_max Maximum observed queue depth.\n# TYPE tklog_queue_depth_max gauge\n");
+        for s in &self.sinks {
+            let _ = writeln!(out, "tklog_queue_depth_max{{sink=\"{}\"}} {}", label(&s.sink), s.max_queue_depth);
+        }
+        out.push_str("# HELP tklog_storm_suppressed_total Lines dropped by storm control.\n# TYPE tklog_storm_suppressed_total counter\n");
+        let _ = writeln!(out, "tklog_storm_suppressed_total {}", self.storm_suppressed);
+        if self.filtered > 0 {
+            out.push_str("# HELP tklog_filtered_total Lines dropped by the filters.\n# TYPE tklog_filtered_total counter\n");
+            let _ = writeln!(out, "tklog_filtered_total {}", self.filtered);
+        }
+        if self.prestart_dropped > 0 {
+            out.push_str("# HELP tklog_prestart_dropped_total Lines dropped before the queue consumer ran.\n# TYPE tklog_prestart_dropped_total counter\n");
+            let _ = writeln!(out, "tklog_prestart_dropped_total {}", self.prestart_dropped);
+        }
+        out.push_str("# HELP tklog_quota_bytes Daily byte quota of a handler.\n# TYPE tklog_quota_bytes gauge\n");
+        for q in &self.quotas {
+            let _ = writeln!(out, "tklog_quota_bytes{{handler=\"{}\"}} {}", label(&q.handler), q.bytes_per_day);
+        }
+        out.push_str("# HELP tklog_quota_used_bytes Bytes written against the quota today.\n# TYPE tklog_quota_used_bytes gauge\n");
+        for q in &self.quotas {
+            let _ = writeln!(out, "tklog_quota_used_bytes{{handler=\"{}\"}} {}", label(&q.handler), q.used);
+        }
+        out.push_str("# HELP tklog_quota_dropped_total Lines dropped over the quota.\n# TYPE tklog_quota_dropped_total counter\n");
+        for q in &self.quotas {
+            let _ = writeln!(out, "tklog_quota_dropped_total{{handler=\"{}\"}} {}", label(&q.handler), q.dropped);
+        }
+        if !self.tees.is_empty() {
+            out.push_str("# HELP tklog_tee_bytes_total Bytes written to a tee file.\n# TYPE tklog_tee_bytes_total counter\n");
+            for t in &self.tees {
+                let _ = writeln!(out, "tklog_tee_bytes_total{{file=\"{}\",mode=\"{}\"}} {}", label(&t.file), t.mode, t.bytes);
+            }
+        }
+        if self.memory.budget > 0 {
+            out.push_str("# HELP tklog_memory_budget_bytes Memory budget of queued lines.\n# TYPE tklog_memory_budget_bytes gauge\n");
+            let _ = writeln!(out, "tklog_memory_budget_bytes {}", self.memory.budget);
+            out.push_str("# HELP tklog_memory_used_bytes Bytes charged for queued lines.\n# TYPE tklog_memory_used_bytes gauge\n");
+            let _ = writeln!(out, "tklog_memory_used_bytes {}", self.memory.used);
+            out.push_str("# HELP tklog_memory_shed_total Queued lines shed over the memory budget.\n# TYPE tklog_memory_shed_total counter\n");
+            for (class, n) in ["debug", "info", "warn"].iter().zip(self.memory.shed) {
+                let _ = writeln!(out, "tklog_memory_shed_total{{class=\"{}\"}} {}", class, n);
+            }
+        }
+        out.push_str("# HELP tklog_write_latency_seconds Enqueue-to-write latency of sampled lines.\n# TYPE tklog_write_latency_seconds histogram\n");
+        for s in &self.sinks {
+            let name = label(&s.sink);
+            let mut cumulative = 0;
+            for (i, b) in LATENCY_BOUNDS.iter().enumerate() {
+                cumulative += s.latency.buckets[i];
+                let _ = writeln!(out, "tklog_write_latency_seconds_bucket{{sink=\"{}\",le=\"{}\"}} {}", name, b.as_secs_f64(), cumulative);
+            }
+            let _ = writeln!(out, "tklog_write_latency_seconds_bucket{{sink=\"{}\",le=\"+Inf\"}} {}", name, s.latency.count());
+            let _ = writeln!(out, "tklog_write_latency_seconds_sum{{sink=\"{}\"}} {}", name, s.latency.sum.as_secs_f64());
+            let _ = writeln!(out, "tklog_write_latency_seconds_count{{sink=\"{}\"}} {}", name, s.latency.count());
+        }
+        out
+    }
+}
+
+fn label(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Shared between a logger and its queue consumer.
+pub(crate) struct StatsCollector {
+    sinks: Mutex<BTreeMap<String, SinkStats>>,
+    storm_suppressed: AtomicU64,
+    filtered: AtomicU64,
+    prestart_dropped: AtomicU64,
+    tees: Mutex<BTreeMap<String, TeeStats>>,
+}
+
+impl StatsCollector {
+    pub(crate) fn new() -> Self {
+        StatsCollector {
+            sinks: Mutex::new(BTreeMap::new()),
+            storm_suppressed: AtomicU64::new(0),
+            filtered: AtomicU64::new(0),
+            prestart_dropped: AtomicU64::new(0),
+            tees: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub(crate) fn enqueued(&self, sink: &str) {
+        let mut sinks = self.sinks.lock().unwrap_or_else(|e| e.into_inner());
+        let s = sinks.entry(sink.to_string()).or_insert_with(|| SinkStats { sink: sink.to_string(), ..Default::default() });
+        s.queue_depth += 1;
+        s.max_queue_depth = s.max_queue_depth.max(s.queue_depth);
+    }
+
+    pub(crate) fn written(&self, sink: &str, enqueued_at: Option<Instant>) {
+        let mut sinks = self.sinks.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(s) = sinks.get_mut(sink) {
+            s.queue_depth = s.queue_depth.saturating_sub(1);
+            s.lines += 1;
+            if let Some(t) = enqueued_at {
+                s.latency.record(t.elapsed());
+            }
+        }
+    }
+
+    /// A queued line shed over the memory budget before it was written.
+    pub(crate) fn shed(&self, sink: &str) {
+        let mut sinks = self.sinks.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(s) = sinks.get_mut(sink) {
+            s.queue_depth = s.queue_depth.saturating_sub(1);
+        }
+    }
+
+    /// The lines queued across every sink.
+    pub(crate) fn pending(&self) -> u64 {
+        self.sinks.lock().unwrap_or_else(|e| e.into_inner()).values().map(|s| s.queue_depth).sum()
+    }
+
+    pub(crate) fn snapshot(&self) -> LogStats {
+        let sinks = self.sinks.lock().unwrap_or_else(|e| e.into_inner());
+        let tees = self.tees.lock().unwrap_or_else(|e| e.into_inner());
+        LogStats {
+            sinks: sinks.values().cloned().collect(),
+            storm_suppressed: self.storm_suppressed.load(Ordering::Relaxed),
+            filtered: self.filtered.load(Ordering::Relaxed),
+            prestart_dropped: self.prestart_dropped.load(Ordering::Relaxed),
+            quotas: Vec::new(),
+            tees: tees.values().cloned().collect(),
+            memory: memory::stats(),
+        }
+    }
+
+    /// Counts the writes of a tee file from now on, from zero.
+    pub(crate) fn add_tee(&self, file: &str, mode: OutputMode) {
+        let mut tees = self.tees.lock().unwrap_or_else(|e| e.into_inner());
+        tees.insert(file.to_string(), TeeStats { file: file.to_string(), mode, lines: 0, bytes: 0 });
+    }
+
+    pub(crate) fn remove_tee(&self, file: &str) {
+        self.tees.lock().unwrap_or_else(|e| e.into_inner()).remove(file);
+    }
+
+    pub(crate) fn tee_written(&self, file: &str, bytes: usize) {
+        let mut tees = self.tees.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(t) = tees.get_mut(file) {
+            t.lines += 1;
+            t.bytes += bytes as u64;
+        }
+    }
+
+    pub(crate) fn storm_suppressed(&self) {
+        self.storm_suppressed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn filtered(&self) {
+        self.filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn prestart_dropped(&self) {
+        self.prestart_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A line waiting in the DELAY queue, with what the stats need to know about it.
+pub(crate) struct Queued {
+    pub(crate) sink: String,
+    pub(crate) enqueued_at: Option<Instant>,
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storm control: a temporary level floor after a burst of Error lines.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::LEVEL;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum StormScope {
+    /// One counter for the whole logger; a storm suppresses every module.
+    Global,
+    /// One counter per module; only the offending module is suppressed.
+    PerModule,
+}
+
+/// When `threshold` Error lines arrive within `window`, lines below Warn are
+/// dropped for `cooldown`. Warn and above keep flowing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StormConfig {
+    pub threshold: u32,
+    pub window: Duration,
+    pub cooldown: Duration,
+    pub scope: StormScope,
+}
+
+#[derive(Default)]
+struct StormState {
+    errors: VecDeque<Instant>,
+    suppressed_until: Option<Instant>,
+    suppressed: u64,
+}
+
+pub(crate) struct StormControl {
+    config: StormConfig,
+    states: HashMap<String, StormState>,
+}
+
+impl StormControl {
+    pub(crate) fn new(config: StormConfig) -> Self {
+        StormControl { config, states: HashMap::new() }
+    }
+
+    /// Counts the line and says whether it may be written. Announcements of
+    /// suppressions starting or lifting are pushed onto `notices`.
+    pub(crate) fn check(&mut self, now: Instant, module: &str, level: LEVEL, notices: &mut Vec<String>) -> bool {
+        for (key, st) in self.states.iter_mut() {
+            if st.suppressed_until.is_some_and(|until| now >= until) {
+                notices.push(format!("storm control lifted for {}: {} lines suppressed", describe(key), st.suppressed));
+                st.suppressed_until = None;
+                st.suppressed = 0;
+                st.errors.clear();
+            }
+        }
+        if module == "tklog" {
+            return true;
+        }
+        let key = match self.config.scope {
+            StormScope::Global => "",
+            StormScope::PerModule => module,
+        };
+        let st = self.states.entry(key.to_string()).or_default();
+        if level == LEVEL::Error && st.suppressed_until.is_none() {
+            st.errors.push_back(now);
+            while st.errors.front().is_some_and(|t| now.duration_since(*t) > self.config.window) || st.errors.len() > self.config.threshold as usize {
+                st.errors.pop_front();
+            }
+            if st.errors.len() >= self.config.threshold as usize {
+                st.suppressed_until = Some(now + self.config.cooldown);
+                notices.push(format!(
+                    "storm control: {} Error lines within {:?} from {}; suppressing lines below Warn for {:?}",
+                    st.errors.len(),
+                    self.config.window,
+                    describe(key),
+                    self.config.cooldown
+                ));
+            }
+        }
+        if st.suppressed_until.is_some() && level < LEVEL::Warn {
+            st.suppressed += 1;
+            return false;
+        }
+        true
+    }
+}
+
+fn describe(key: &str) -> String {
+    if key.is_empty() {
+        "all modules".to_string()
+    } else {
+        format!("module `{}`", key)
+    }
+}
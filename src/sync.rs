@@ -15,19 +15,75 @@
 // limitations under the License.
 
 use crate::{
-    arguments_to_string,
-    handle::{FHandler, FileOptionType, FmtHandler},
-    l2tk, log_fmt,
+    backupname::BackupTemplate,
+    batch::{Batch, BatchPolicy, Wait},
+    block::{self, BlockWriter},
+    boot,
+    bridge::{self, LogBridge},
+    budget::{self, AdaptiveBudget},
+    builder::LoggerBuilder,
+    callers::{self, CallerTrace},
+    capture::Captures,
+    clock::{Clock, SystemClock},
+    color::{self, ColorOptions, ConsoleColors},
+    config::{self, describe_changes, ConfigWatch, LogConfig},
+    cut::{CutConfig, CutMixed, CutSize, CutTime},
+    diagnostics::{self, Category},
+    directory::{DirLayout, Directory},
+    events::Events,
+    fd_exhausted, global,
+    fields::{self, DynamicFields, FieldLimits, FieldMap},
+    filter::{Filter, Filters, LogRecord},
+    hook::{Hook, Hooks},
+    guard::{Guarded, PanicCount},
+    handle::{self, FHandler, FileOption, FileOptionType, FileSettings, FmtHandler},
+    health::{Degradation, Health},
+    init_time_zone, intern::intern, memory::{self, Held}, now, places, subseq, thread_label,
+    output::{Gate, OutputLevels, OutputMode, OutputModes, OutputSink},
+    paths::{Claim, Paths, DEFAULT_FILE},
+    persist::{Pending, Persisting},
+    Inside,
     syncfile::FileHandler,
-    tklog::synclog,
+    json::Schema,
+    levelspec::{self, LevelSpec},
+    lines::{self, LevelLayout, LineConfig, ModuleLines},
+    logerror::{self, ErrorHandler, LogError},
+    logsink::LogSink,
+    preset::{K8sPreset, Preset},
+    record::{LogFormatter, RecordFormatter, RecordSnapshot, Render},
+    remote::{Remote, RemoteConfig},
+    quota::{Admission, Quota},
+    rotation::RotationGroup,
+    routing::{self, LevelSet, Routes, RoutingTable, Sink},
+    scheduler::Scheduler,
+    writebuf::Flusher,
+    metrics::LogMetrics,
+    stats::{LogStats, Queued, StatsCollector},
+    storm::{StormConfig, StormControl},
+    tee::{self, TeeLayout},
     trie::Trie,
-    AttrFormat, Format, LogContent, LogContext, LogOption, LogOptionConst, OptionTrait, LEVEL,
-    MODE, PRINTMODE, TKLOG2SYNCLOG,
+    verify::TamperKey,
+    check_column_order, check_file_pattern, check_time_format, AttrFormat, CallSite, CompressType, ConsoleStream, Error, Format, LocationStrategy, LogContent, LogContext, LogOption, LogOptionConst, OptionTrait, PrunePolicy,
+    ParseSizeError, RotationEvent, StaticPrefix, TestMode, COLUMN, CUTMODE, LEVEL, MODE, PRINTMODE, RFC3339_TIME_FORMAT,
 };
+#[cfg(feature = "journald")]
+use crate::journald::{Journald, JOURNALD_SOCKET};
+#[cfg(feature = "syslog")]
+use crate::syslog::{Syslog, SyslogConfig};
+use chrono::{DateTime, Local};
 use std::thread;
 use std::{
-    collections::HashMap,
-    sync::mpsc::{channel, Sender},
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    io,
+    panic::Location,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, SendError, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 /// this is the tklog encapsulated Logger whose File operations
@@ -53,66 +109,375 @@ use std::{
 ///     .set_cutmode_by_size("tklog.log", 1<<20, 0, true);
 /// ```
 pub struct Logger {
-    sender: Sender<(LEVEL, String, LogContent)>,
+    sender: Sender<QueueItem>,
+    /// The thread writing the DELAY queue, see `health`; None if none
+    /// could be started, as on wasm32.
+    consumer: Option<thread::JoinHandle<()>>,
+    /// The batches the consumer writes, see `set_delay_batch`.
+    batch: Arc<BatchPolicy>,
     fmthandle: FmtHandler,
     filehandle: (String, FHandler),
     mutex: std::sync::Mutex<u32>,
     pub mode: PRINTMODE,
     modmap: Trie<(LogOptionConst, String)>,
+    /// The module patterns of the last `set_level_from_env`.
+    env_modules: Vec<String>,
     fmap: HashMap<String, FHandler>,
+    paths: Paths,
     custom_handler: Option<fn(&LogContext) -> bool>,
+    custom_panics: PanicCount,
+    filters: Filters,
+    hooks: Hooks,
+    captures: Captures,
     separator: String,
     levels: Option<[Option<(LogOption, String)>; 7]>,
     // levelfmt: Option<Box<dyn Fn(LEVEL) -> String + Send + Sync>>,
     // timefmt: Option<Box<dyn Fn() -> (String, String, String) + Send + Sync>>,
-    attrfmt: AttrFormat,
+    render: Render,
+    output: OutputModes,
+    output_levels: OutputLevels,
+    testmode: Option<TestMode>,
+    seq: u64,
+    subseq: bool,
+    boot_id: bool,
+    /// See `set_function_with_module`.
+    function_module: bool,
+    /// See `set_error_backtraces`.
+    error_backtraces: bool,
+    location_strategy: LocationStrategy,
+    filesettings: FileSettings,
+    /// The SIGHUPs seen, see `enable_reopen_on_sighup`.
+    #[cfg(all(unix, feature = "signal"))]
+    hangups: Option<u64>,
+    stats: Arc<StatsCollector>,
+    latency_sampling: u64,
+    sampled: u64,
+    storm: Option<StormControl>,
+    callers: Option<CallerTrace>,
+    persisting: Persisting,
+    clock: Arc<dyn Clock>,
+    budget: Option<AdaptiveBudget>,
+    events: Events,
+    quotas: BTreeMap<String, Quota>,
+    scheduler: Scheduler,
+    flusher: Flusher,
+    routing: Option<RoutingTable>,
+    sinks: HashMap<String, String>,
+    /// The files of `add_level_file`, with their levels.
+    level_files: Vec<(Vec<LEVEL>, String)>,
+    directory: Option<Directory<FHandler>>,
+    /// The tee files by name, in the order of `Render::tees`.
+    tees: Vec<(String, FHandler)>,
+    groups: Vec<RotationGroup>,
+    dynamic_fields: Option<Guarded<DynamicFields>>,
+    context_fields: bool,
+    field_limits: FieldLimits,
+    custom_sink: Option<Box<dyn LogSink>>,
+    custom_sink_only: bool,
+    static_prefix: StaticPrefix,
+    /// The settings of the lines of `LOG` laid out without the lock, with
+    /// their generation, see `lines`.
+    line_config: Option<(u64, Arc<LineConfig>)>,
+    #[cfg(feature = "otel")]
+    auto_trace_ids: bool,
+    remote: Option<Remote>,
+    #[cfg(feature = "journald")]
+    journald: Option<Journald>,
+    #[cfg(feature = "syslog")]
+    syslog: Option<Syslog>,
+    console: FHandler,
+}
+
+/// A line of the DELAY queue.
+type QueuedLine = (LEVEL, Cow<'static, str>, Held<LogContent>, Queued);
+
+enum QueueItem {
+    Line(QueuedLine),
+    /// Answered once the lines queued before it are written, see `flush`.
+    Flush(Sender<()>),
+}
+
+/// Starts the thread writing the DELAY queue, through the global logger,
+/// in the batches of `policy`.
+fn spawn_consumer(stats: Arc<StatsCollector>, policy: Arc<BatchPolicy>) -> (Sender<QueueItem>, Option<thread::JoinHandle<()>>) {
+    let (sender, receiver) = channel::<QueueItem>();
+    let consumer = thread::Builder::new().spawn(move || {
+        let mut batch = Batch::default();
+        loop {
+            let next = match batch.wait(&policy) {
+                Wait::Idle => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                Wait::Queued => receiver.try_recv().map_err(|e| match e {
+                    TryRecvError::Empty => RecvTimeoutError::Timeout,
+                    TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                }),
+                Wait::Until(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                Wait::Due => Err(RecvTimeoutError::Timeout),
+            };
+            match next {
+                Ok(QueueItem::Line(line)) => batch.push(line),
+                Ok(QueueItem::Flush(done)) => {
+                    write_batch(&stats, batch.take());
+                    let _ = done.send(());
+                }
+                Err(RecvTimeoutError::Timeout) => write_batch(&stats, batch.take()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    write_batch(&stats, batch.take());
+                    break;
+                }
+            }
+        }
+    });
+    (sender, consumer.ok())
+}
+
+/// Writes the lines of a batch, then flushes the custom sink once for them.
+fn write_batch(stats: &StatsCollector, lines: Vec<QueuedLine>) {
+    for (level, module, msg, queued) in lines {
+        let Some(m2) = msg.take() else {
+            stats.shed(&queued.sink);
+            continue;
+        };
+        if !crate::reentrant(level, &module, || m2.file_body.clone()) {
+            crate::global_for_line().print_queued(level, &module, m2);
+        }
+        stats.written(&queued.sink, queued.enqueued_at);
+    }
+    crate::global_for_line().flush_custom_sink();
+}
+
+/// A line of the macros on `logger`. Its level, its file and line and the
+/// separator of its module are read under the lock; its message and fields
+/// are built without it and outside tklog, so that a `Display` of theirs may
+/// log in turn; then it is laid out and handed to `write` under the lock
+/// again. A line below the level of its module takes the lock once.
+#[doc(hidden)]
+#[allow(clippy::too_many_arguments)]
+pub fn log_line(
+    logger: &Mutex<Logger>,
+    level: LEVEL,
+    module: &'static str,
+    site: CallSite,
+    event: Option<&'static str>,
+    message: impl FnOnce(&str) -> String,
+    fields: impl FnOnce() -> FieldMap,
+    write: fn(&mut Logger, LEVEL, &'static str, LogContent),
+) {
+    let lock = || (logger.lock().unwrap_or_else(|e| e.into_inner()), Inside::enter());
+    let (file, line, separator, error_backtraces) = {
+        let (mut logger, _inside) = lock();
+        if logger.get_level(module) > level {
+            return;
+        }
+        let (file, line) = if logger.is_file_line(level, module) { site.get(logger.location_strategy) } else { ("", 0) };
+        let separator = match logger.separator(module) {
+            "" => String::new(),
+            s => {
+                let mut separator = crate::message_of("", &[]);
+                separator.push_str(s);
+                separator
+            }
+        };
+        (file, line, separator, logger.error_backtraces)
+    };
+    let message = crate::with_error_backtraces(error_backtraces, || message(&separator));
+    crate::recycle_message(separator);
+    let fields = fields();
+    let (mut logger, _inside) = lock();
+    let s = crate::in_function(site.function, || logger.fmt_with_fields(module, level, file, line, event, message, fields));
+    if !s.is_empty() {
+        write(&mut logger, level, module, s);
+    }
+}
+
+/// What lays a line out once it passed the filters, the storm guard and the
+/// custom handler, borrowed from a logger by `fmt_with_fields`, or from the
+/// settings of the lines laid out without the lock, see `lines`.
+pub(crate) struct Layout<'a> {
+    pub(crate) render: &'a Render,
+    pub(crate) field_limits: FieldLimits,
+    pub(crate) context_fields: bool,
+    pub(crate) dynamic_fields: Option<&'a Guarded<DynamicFields>>,
+    pub(crate) testmode: Option<TestMode>,
+    pub(crate) seq: u64,
+    pub(crate) subseq: bool,
+    pub(crate) boot_id: bool,
+    pub(crate) function_module: bool,
+}
+
+impl Layout<'_> {
+    /// The content of a line with its fields, then `message` is handed back
+    /// to the buffers of `message_of`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn content(&self, module: &str, level: LEVEL, filename: &str, line: u32, event: Option<&'static str>, message: String, fields: FieldMap, fmat: u8, formatter: Option<&String>) -> LogContent {
+        let fields = self.field_limits.apply(fields::of_line(fields, self.context_fields, self.dynamic_fields));
+        let time = self.testmode.map_or_else(now, |t| t.fixed_time);
+        let record = RecordSnapshot {
+            level,
+            time,
+            module: Cow::Borrowed(module),
+            file: Cow::Borrowed(filename),
+            line,
+            message,
+            fields,
+            seq: self.seq,
+            subseq: self.subseq.then(|| subseq(&time, fmat & Format::Microseconds != 0)),
+            event,
+            boot_id: self.boot_id.then(boot::boot_id),
+            thread: (fmat & Format::ThreadId != 0 || formatter.is_some_and(|f| places(f, "thread"))).then(thread_label),
+            task: None,
+            function: (fmat & Format::FuncName != 0 || formatter.is_some_and(|f| places(f, "function"))).then(|| crate::function_of(module, self.function_module)).flatten(),
+        };
+        let content = self.render.content(&record, fmat, formatter);
+        crate::recycle_message(record.message);
+        content
+    }
 }
 
 impl Logger {
+    /// The logger behind `LOG`, whose files are checked against those of
+    /// the global async logger.
+    pub(crate) fn new_global() -> Self {
+        Logger { paths: Paths::global("global sync logger"), ..Logger::new() }
+    }
+
+    /// A logger with the levels of `TKLOG_LEVEL`, see `levelspec`.
+    pub fn from_env() -> Self {
+        let mut log = Self::new();
+        log.set_level_from_env(levelspec::TKLOG_LEVEL);
+        log
+    }
+
+    /// A logger configured in one expression, see `builder`.
+    pub fn builder() -> LoggerBuilder {
+        LoggerBuilder::default()
+    }
+
     pub fn new() -> Self {
-        let (sender, receiver) = channel();
-        thread::spawn(move || {
-            while let Ok(s) = receiver.recv() {
-                let (level, module, msg) = s;
-                let m1: String = module;
-                let m2: LogContent = msg;
-                crate::log!(level, m1.as_str(), m2);
-            }
-        });
+        init_time_zone();
+        let stats = Arc::new(StatsCollector::new());
+        let batch = Arc::new(BatchPolicy::default());
+        let (sender, consumer) = spawn_consumer(stats.clone(), batch.clone());
         Logger {
             sender,
+            consumer,
+            batch,
             fmthandle: FmtHandler::new(),
             filehandle: ("".to_string(), FHandler::new()),
             mutex: std::sync::Mutex::new(0),
             mode: PRINTMODE::DELAY,
             modmap: Trie::new(),
+            env_modules: Vec::new(),
             fmap: HashMap::new(),
+            paths: Paths::default(),
             custom_handler: None,
+            custom_panics: PanicCount::default(),
+            filters: Filters::default(),
+            hooks: Hooks::default(),
+            captures: Captures::default(),
             separator: "".to_string(),
             levels: None,
             // levelfmt: None,
             // timefmt: None,
-            attrfmt: AttrFormat::new(),
+            render: Render::default(),
+            output: OutputModes::default(),
+            output_levels: OutputLevels::default(),
+            testmode: None,
+            seq: 1,
+            subseq: false,
+            boot_id: false,
+            function_module: false,
+            error_backtraces: false,
+            location_strategy: LocationStrategy::default(),
+            filesettings: FileSettings::default(),
+            #[cfg(all(unix, feature = "signal"))]
+            hangups: None,
+            stats,
+            latency_sampling: 64,
+            sampled: 0,
+            storm: None,
+            callers: None,
+            persisting: Persisting::default(),
+            clock: Arc::new(SystemClock),
+            budget: None,
+            events: Events::default(),
+            quotas: BTreeMap::new(),
+            scheduler: Scheduler::new_thread(),
+            flusher: Flusher::new_thread(),
+            routing: None,
+            sinks: HashMap::new(),
+            level_files: Vec::new(),
+            directory: None,
+            tees: Vec::new(),
+            groups: Vec::new(),
+            dynamic_fields: None,
+            context_fields: true,
+            field_limits: FieldLimits::default(),
+            custom_sink: None,
+            custom_sink_only: false,
+            static_prefix: StaticPrefix::default(),
+            line_config: None,
+            #[cfg(feature = "otel")]
+            auto_trace_ids: false,
+            remote: None,
+            #[cfg(feature = "journald")]
+            journald: None,
+            #[cfg(feature = "syslog")]
+            syslog: None,
+            console: FHandler::new(),
         }
     }
 
     pub fn print(&mut self, level: LEVEL, module: &str, message: LogContent) {
-        let mut console = self.fmthandle.get_console();
+        self.count(level, &message);
+        self.print_queued(level, module, message);
+        self.flush_custom_sink();
+    }
+
+    /// `print` without flushing the custom sink, for the lines of the DELAY
+    /// queue, flushed once per batch.
+    pub(crate) fn print_queued(&mut self, level: LEVEL, module: &str, message: LogContent) {
+        self.reopen_on_hangup();
+        self.rotate_groups();
+        if self.write_custom_sink(level, &message) {
+            return;
+        }
+        if let Some(remote) = &self.remote {
+            remote.push(&message.file_body);
+        }
+        #[cfg(feature = "syslog")]
+        if self.syslog.as_mut().is_some_and(|s| s.send(level, &message.file_body)) {
+            return;
+        }
+        #[cfg(feature = "journald")]
+        if self.journald.as_mut().is_some_and(|j| j.send(level, module, message.source.as_ref(), &message.file_body)) {
+            return;
+        }
+        let gate = self.gate(level, module);
+        if gate.file {
+            self.write_tees(&message);
+            self.write_level_files(level, &message);
+        }
+        if self.routed(module) {
+            self.print_routed(level, gate, message);
+            return;
+        }
+        if self.over_quota(level, module, &message) {
+            return;
+        }
+        if !gate.file {
+            self.print_console_only(level, module, gate, &message);
+            return;
+        }
+        let mut console = self.fmthandle.get_console() && gate.console;
 
         if self.modmap.len() > 0 {
             if let Some(mm) = self.modmap.get(module) {
                 let (lo, filename) = mm;
                 if let Some(cs) = lo.console {
-                    console = cs
+                    console = cs && gate.console
                 }
                 if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.print(console, message);
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.print(console, message);
-                        }
-                    }
+                    Self::write_file(&mut self.filehandle, &mut self.fmap, filename, console, &message);
                     return;
                 }
             }
@@ -122,41 +487,58 @@ impl Logger {
             if let Some(lp) = &levels[level as usize - 1] {
                 let (lo, filename) = lp;
                 if let Some(cs) = lo.console {
-                    console = cs
+                    console = cs && gate.console
                 }
                 if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.print(console, message);
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.print(console, message);
-                        }
-                    }
+                    Self::write_file(&mut self.filehandle, &mut self.fmap, filename, console, &message);
                     return;
                 }
             }
         }
 
+        if let Some(directory) = self.directory.as_mut() {
+            let time = self.testmode.map_or_else(now, |t| t.fixed_time);
+            Self::write_directory(directory, &self.filesettings, module, time, console, &message);
+            return;
+        }
         let _ = self.filehandle.1.print(console, message);
     }
 
     pub fn safeprint(&mut self, level: LEVEL, module: &str, message: LogContent) {
-        let _guard = self.mutex.lock().expect("Failed to acquire lock");
-        let mut console = self.fmthandle.get_console();
+        self.count(level, &message);
+        self.reopen_on_hangup();
+        self.rotate_groups();
+        let only = self.write_custom_sink(level, &message);
+        self.flush_custom_sink();
+        if only {
+            return;
+        }
+        let gate = self.gate(level, module);
+        if gate.file {
+            self.write_tees(&message);
+            self.write_level_files(level, &message);
+        }
+        if self.routed(module) {
+            self.print_routed(level, gate, message);
+            return;
+        }
+        if self.over_quota(level, module, &message) {
+            return;
+        }
+        if !gate.file {
+            self.print_console_only(level, module, gate, &message);
+            return;
+        }
+        let _guard = self.mutex.lock().unwrap_or_else(|e| e.into_inner());
+        let mut console = self.fmthandle.get_console() && gate.console;
         if self.modmap.len() > 0 {
             if let Some(mm) = self.modmap.get(module) {
                 let (lo, filename) = mm;
                 if let Some(cs) = lo.console {
-                    console = cs
+                    console = cs && gate.console
                 }
                 if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.print(console, message);
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.print(console, message);
-                        }
-                    }
+                    Self::write_file(&mut self.filehandle, &mut self.fmap, filename, console, &message);
                     return;
                 }
             }
@@ -166,526 +548,3039 @@ impl Logger {
             if let Some(lp) = &levels[level as usize - 1] {
                 let (lo, filename) = lp;
                 if let Some(cs) = lo.console {
-                    console = cs
+                    console = cs && gate.console
                 }
                 if filename != "" {
-                    if *filename == self.filehandle.0 {
-                        let _ = self.filehandle.1.print(console, message);
-                    } else {
-                        if let Some(fm) = self.fmap.get_mut(filename) {
-                            let _ = fm.print(console, message);
-                        }
-                    }
+                    Self::write_file(&mut self.filehandle, &mut self.fmap, filename, console, &message);
                     return;
                 }
             }
         }
 
+        if let Some(directory) = self.directory.as_mut() {
+            let time = self.testmode.map_or_else(now, |t| t.fixed_time);
+            Self::write_directory(directory, &self.filesettings, module, time, console, &message);
+            return;
+        }
         let _ = self.filehandle.1.print(console, message);
     }
 
-    pub fn log(&self, level: LEVEL, module: String, message: LogContent) {
-        self.sender
-            .send((level, module, message))
-            .expect("send error");
-    }
-
-    pub fn get_level(&mut self, module: &str) -> LEVEL {
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, _) = mm;
-                if let Some(level) = lo.level {
-                    return level;
+    /// Rotates the members of every rotation group whose period is over.
+    fn rotate_groups(&mut self) {
+        if self.groups.is_empty() {
+            return;
+        }
+        let now = self.clock.wall();
+        for group in &mut self.groups {
+            let Some((stamp, startsec)) = group.check(now) else {
+                continue;
+            };
+            for id in &group.members {
+                let fh = if *id == self.filehandle.0 { Some(&mut self.filehandle.1) } else { self.fmap.get_mut(id) };
+                if let Some(Err(e)) = fh.map(|fh| fh.rotate_as(stamp, startsec, group.empty_backups)) {
+                    diagnostics::report(Category::Reopen, Some(Path::new(id)), format!("cannot rotate {} with its group: {}", id, e));
                 }
             }
         }
-        self.fmthandle.get_level()
     }
 
-    pub fn is_file_line(&mut self, level: LEVEL, module: &str) -> bool {
-        if let Some(levels) = &self.levels {
-            if let Some(lp) = &levels[level as usize - 1] {
-                let (lo, _) = lp;
-                if let Some(v) = lo.format {
-                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
-                }
+    /// Hands the line to the custom sink; true when it goes there alone.
+    fn write_custom_sink(&mut self, level: LEVEL, message: &LogContent) -> bool {
+        match &mut self.custom_sink {
+            Some(sink) => {
+                sink.write(level, &message.file_body);
+                self.custom_sink_only
             }
+            None => false,
         }
+    }
 
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, _) = mm;
-                if let Some(v) = lo.format {
-                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
-                }
+    pub(crate) fn flush_custom_sink(&mut self) {
+        if let Some(sink) = &mut self.custom_sink {
+            sink.flush();
+        }
+    }
+
+    fn write_tees(&mut self, message: &LogContent) {
+        for ((filename, fh), body) in self.tees.iter_mut().zip(&message.tees) {
+            if fh.write_body(body).is_ok() {
+                self.stats.tee_written(filename, body.len());
             }
         }
-        self.fmthandle.is_file_line()
     }
 
-    pub fn fmt(
-        &mut self,
-        module: &str,
-        level: LEVEL,
-        filename: &str,
-        line: u32,
-        message: String,
-    ) -> LogContent {
-        if let Some(ch) = &self.custom_handler {
-            if !ch(&LogContext {
-                level,
-                filename: filename.to_string(),
-                line,
-                log_body: message.clone(),
-                modname: module.to_string(),
-            }) {
-                return LogContent::new(String::new(), None);
+    fn write_level_files(&mut self, level: LEVEL, message: &LogContent) {
+        for (levels, filename) in &self.level_files {
+            if levels.contains(&level) {
+                Self::write_file(&mut self.filehandle, &mut self.fmap, filename, false, message);
             }
         }
+    }
 
-        let mut fmat = self.fmthandle.get_format();
-        let mut formatter = self.fmthandle.get_formatter();
-        if module != "" && self.modmap.len() > 0 {
-            if let Some(mm) = self.modmap.get(module) {
-                let (lo, _) = mm;
-                if let Some(v) = lo.format {
-                    fmat = v;
-                }
-                if lo.formatter.is_some() {
-                    formatter = lo.formatter.as_ref();
+    /// Writes to the file of `module` for the day of `time` under the
+    /// directory root.
+    fn write_directory(directory: &mut Directory<FHandler>, settings: &FileSettings, module: &str, time: DateTime<Local>, console: bool, message: &LogContent) {
+        let handler = directory.handler(module, time, |path| {
+            let mut f = FileHandler::unopened(path.to_string_lossy().into_owned());
+            f.set_settings(settings.clone());
+            let mut fhandler = FHandler::new();
+            fhandler.set_file_handler(f);
+            fhandler
+        });
+        let _ = handler.write_line(console, message);
+    }
+
+    /// Whether the routing matrix decides where lines of `module` go: it is
+    /// set, and no module option sends them to a file of its own.
+    fn routed(&mut self, module: &str) -> bool {
+        if self.routing.is_none() {
+            return false;
+        }
+        !(self.modmap.len() > 0 && self.modmap.get(module).is_some_and(|(_, filename)| !filename.is_empty()))
+    }
+
+    fn print_routed(&mut self, level: LEVEL, gate: Gate, message: LogContent) {
+        let Some(routing) = &self.routing else {
+            return;
+        };
+        let sinks: Vec<Sink> = routing.route(level).filter(|sink| if matches!(sink, Sink::Console) { gate.console } else { gate.file }).cloned().collect();
+        for sink in sinks {
+            let name = match &sink {
+                Sink::Console => "console",
+                Sink::File(filename) => filename.as_str(),
+            };
+            if self.charge_quota(name, &message) {
+                continue;
+            }
+            match &sink {
+                Sink::Console => {
+                    let _ = self.console.write_line(true, &message);
                 }
+                Sink::File(filename) => Self::write_file(&mut self.filehandle, &mut self.fmap, filename, false, &message),
             }
         }
+    }
 
-        if let Some(levels) = &self.levels {
-            if let Some(lp) = &levels[level as usize - 1] {
-                let (lo, _) = lp;
-                if let Some(v) = lo.format {
-                    fmat = v;
-                }
-                if lo.formatter.is_some() {
-                    formatter = lo.formatter.as_ref();
-                }
+    /// The sinks a line of `module` at `level` goes to, see
+    /// `set_output_level`.
+    fn gate(&mut self, level: LEVEL, module: &str) -> Gate {
+        if self.output_levels.is_empty() {
+            return Gate::ALL;
+        }
+        let module_level = self.set_level_of(module);
+        self.output_levels.gate(level, module_level)
+    }
+
+    /// Writes a line the files don't take to the console, if the module
+    /// and level options leave it on.
+    fn print_console_only(&mut self, level: LEVEL, module: &str, gate: Gate, message: &LogContent) {
+        let mut console = self.fmthandle.get_console();
+        if !module.is_empty() && self.modmap.len() > 0 {
+            if let Some(cs) = self.modmap.get(module).and_then(|(lo, _)| lo.console) {
+                console = cs;
             }
         }
+        if let Some(cs) = self.levels.as_ref().and_then(|levels| levels[level as usize - 1].as_ref()).and_then(|(lo, _)| lo.console) {
+            console = cs;
+        }
+        if console && gate.console {
+            let _ = self.console.write_line(true, message);
+        }
+    }
 
-        let s = log_fmt(
-            self.attrfmt.levelfmt.as_ref(),
-            self.attrfmt.timefmt.as_ref(),
-            fmat,
-            formatter,
-            level,
-            filename,
-            line,
-            message.as_str(),
-        );
-        let console_s = if let Some(f) = &self.attrfmt.consolebodyfmt {
-            Some(f(level, s.clone()))
-        } else {
-            None
+    /// Writes to the handler of `filename`. Out of file descriptors, the
+    /// other module files are closed to make room and the write is retried
+    /// once; if it still fails the line goes to the default file instead.
+    fn write_file(default: &mut (String, FHandler), fmap: &mut HashMap<String, FHandler>, filename: &str, console: bool, message: &LogContent) {
+        if *filename == default.0 {
+            let _ = default.1.write_line(console, message);
+            return;
+        }
+        let Some(fm) = fmap.get_mut(filename) else {
+            return;
+        };
+        match fm.write_line(console, message) {
+            Err(e) if fd_exhausted(&e) => {}
+            _ => return,
+        }
+        Self::reclaim_descriptors(fmap, filename);
+        let Some(fm) = fmap.get_mut(filename) else {
+            return;
         };
+        if let Err(e) = fm.write_line(false, message) {
+            if fm.degrade() {
+                diagnostics::report(Category::Reopen, Some(Path::new(&filename)), format!("cannot reopen {}: {}; its lines go to the default file", filename, e));
+            }
+            let _ = default.1.write_line(false, message);
+        }
+    }
+
+    /// Closes every module file but `keep`; each reopens on its next write.
+    fn reclaim_descriptors(fmap: &mut HashMap<String, FHandler>, keep: &str) {
+        for (filename, fm) in fmap.iter_mut() {
+            if filename != keep {
+                fm.release_file();
+            }
+        }
+    }
+
+    /// Replaces per-level destinations with a routing matrix over named
+    /// sinks, see `routing`. Module options that name a file still win.
+    /// Errs on a sink name the logger doesn't have; levels left without a
+    /// destination are warned about on stderr.
+    pub fn set_routing<F: FnOnce(&mut Routes)>(&mut self, f: F) -> Result<&mut Self, Error> {
+        let mut routes = Routes::default();
+        f(&mut routes);
+        let table = RoutingTable::compile(routes, |name| routing::resolve(name, &self.filehandle.0, &self.sinks, |f| self.fmap.contains_key(f)))?;
+        self.routing = Some(table);
+        Ok(self)
+    }
+
+    pub fn clear_routing(&mut self) -> &mut Self {
+        self.routing = None;
+        self
+    }
+
+    /// Adds a file handler that routing rules can name `file:<name>`.
+    pub fn add_file_sink(&mut self, name: &str, option: impl FileOption + 'static) -> &mut Self {
+        if let Ok(filename) = self.module_file(&format!("file sink `{}`", name), Box::new(option)) {
+            if !filename.is_empty() {
+                self.sinks.insert(name.to_string(), filename);
+            }
+        }
+        self
+    }
+
+    /// Writes the lines at `levels`, such as `LEVEL::Warn..`, to a file of
+    /// their own as well, rotated by `cut`; wherever else they go, they
+    /// still do. Adding its file again replaces its levels.
+    pub fn add_level_file(&mut self, levels: impl LevelSet, cut: impl Into<CutConfig>) -> &mut Self {
+        let cut = cut.into();
+        if let Ok(filename) = self.module_file(&format!("level file {}", cut.filename()), Box::new(cut.option())) {
+            self.insert_level_file(routing::level_list(&levels), filename);
+        }
+        self
+    }
+
+    /// Stops writing the files of `add_level_file`.
+    pub fn clear_level_files(&mut self) -> &mut Self {
+        self.level_files.clear();
+        self
+    }
+
+    fn insert_level_file(&mut self, levels: Vec<LEVEL>, filename: String) {
+        if filename.is_empty() || filename == self.filehandle.0 {
+            return;
+        }
+        match self.level_files.iter_mut().find(|(_, f)| *f == filename) {
+            Some(entry) => entry.0 = levels,
+            None => self.level_files.push((levels, filename)),
+        }
+    }
+
+    /// Makes the time-rotated handlers `handler_ids`, log file names, rotate
+    /// together, see `rotation`; a member with an empty file then gets an
+    /// empty backup. Errs with `Error::InvalidRotationGroup` for a handler
+    /// a group can't take.
+    pub fn set_rotation_group(&mut self, handler_ids: &[&str]) -> Result<&mut Self, Error> {
+        self.set_rotation_group_with(handler_ids, true)
+    }
 
-        let content = LogContent::new(
-            if let Some(f) = &self.attrfmt.filebodyfmt {
-                f(level, s)
-            } else {
-                s
+    /// `set_rotation_group`, leaving the members with an empty file as they
+    /// are unless `empty_backups`.
+    pub fn set_rotation_group_with(&mut self, handler_ids: &[&str], empty_backups: bool) -> Result<&mut Self, Error> {
+        let group = RotationGroup::new(
+            handler_ids,
+            |id| {
+                let fh = self.group_member(id)?;
+                Some((fh.file_config()?, fh.startsec()?))
             },
-            console_s,
-        );
+            |id| self.groups.iter().any(|g| g.members.iter().any(|m| m == id)),
+            self.clock.wall(),
+            empty_backups,
+        )?;
+        self.set_grouped(&group.members, Some(group.startsec));
+        self.groups.push(group);
+        Ok(self)
+    }
 
-        content
+    /// Gives the members of the rotation groups their own time checks back.
+    pub fn clear_rotation_groups(&mut self) -> &mut Self {
+        for group in std::mem::take(&mut self.groups) {
+            self.set_grouped(&group.members, None);
+        }
+        self
     }
 
-    pub fn set_printmode(&mut self, mode: PRINTMODE) -> &mut Self {
-        self.mode = mode;
+    fn group_member(&self, id: &str) -> Option<&FHandler> {
+        if !id.is_empty() && id == self.filehandle.0 {
+            return Some(&self.filehandle.1);
+        }
+        self.fmap.get(id)
+    }
+
+    fn set_grouped(&mut self, members: &[String], group: Option<u64>) {
+        for id in members {
+            let fh = if *id == self.filehandle.0 { Some(&mut self.filehandle.1) } else { self.fmap.get_mut(id) };
+            if let Some(fh) = fh {
+                fh.set_grouped(group);
+            }
+        }
+    }
+
+    /// Adds a tee file, written with every line in `mode` and rotated by
+    /// `cut`, see `tee`; adding its file again replaces it. Errs with
+    /// `Error::UnsupportedTeeMode` for logfmt and delimited lines, and with
+    /// `Error::PathConflict` for a file another handler writes.
+    pub fn add_tee_file(&mut self, mode: OutputMode, cut: impl Into<CutConfig>) -> Result<&mut Self, Error> {
+        let layout = TeeLayout::new(mode)?;
+        let option = cut.into().option();
+        let owner = tee::owner(&option.filename());
+        self.paths.claim_alone(&owner, &option)?;
+        let f = match self.new_filehandler(Box::new(option)) {
+            Ok(f) => f,
+            Err(e) => {
+                self.paths.release(&owner);
+                diagnostics::report(Category::Reopen, None, format!("cannot open the {}: {}", owner, e));
+                return Ok(self);
+            }
+        };
+        let filename = f.get_file_name();
+        let mut fh = FHandler::new();
+        fh.set_file_handler(f);
+        self.stats.add_tee(&filename, mode);
+        match self.tees.iter().position(|(name, _)| *name == filename) {
+            Some(i) => {
+                self.tees[i].1 = fh;
+                self.render.tees[i] = layout;
+            }
+            None => {
+                self.tees.push((filename, fh));
+                self.render.tees.push(layout);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Stops writing the tee files.
+    pub fn clear_tee_files(&mut self) -> &mut Self {
+        for (filename, _) in self.tees.drain(..) {
+            self.paths.release(&tee::owner(&filename));
+            self.stats.remove_tee(&filename);
+        }
+        self.render.tees.clear();
         self
     }
 
-    pub fn set_level(&mut self, level: LEVEL) -> &mut Self {
-        self.fmthandle.set_level(level);
+    /// Sends every line, once laid out, to `sink` as well, see `logsink`;
+    /// a later call replaces it.
+    pub fn set_custom_sink(&mut self, sink: Box<dyn LogSink>) -> &mut Self {
+        self.custom_sink = Some(sink);
+        self
+    }
+
+    /// Sends the lines to the custom sink alone, not to the console, the
+    /// files and the tee files. Default: false.
+    pub fn set_custom_sink_only(&mut self, on: bool) -> &mut Self {
+        self.custom_sink_only = on;
+        self
+    }
+
+    /// Flushes and drops the custom sink.
+    pub fn clear_custom_sink(&mut self) -> &mut Self {
+        self.flush_custom_sink();
+        self.custom_sink = None;
+        self
+    }
+
+    /// Formats and writes one line unless `module` filters it out. Without
+    /// an explicit `location` the caller's file and line are used.
+    #[track_caller]
+    pub fn log_record(&mut self, level: LEVEL, module: &str, location: Option<(&str, u32)>, message: String) {
+        if self.get_level(module) > level {
+            return;
+        }
+        let (file, line) = match location {
+            Some(l) if self.is_file_line(level, module) => l,
+            None if self.is_file_line(level, module) => {
+                let caller = Location::caller();
+                (caller.file(), caller.line())
+            }
+            _ => ("", 0),
+        };
+        let s = self.fmt(module, level, file, line, message);
+        if s.is_empty() {
+            return;
+        }
+        if self.mode == PRINTMODE::DELAY {
+            self.log(level, intern(module), s);
+        } else {
+            self.safeprint(level, module, s);
+        }
+    }
+
+    /// Writes the lines `block` adds as one, see `block`. `block` only runs
+    /// when `module` doesn't filter `level` out.
+    pub fn log_block(&mut self, level: LEVEL, module: &str, block: impl FnOnce(&mut BlockWriter)) {
+        if self.get_level(module) > level {
+            return;
+        }
+        let mut writer = BlockWriter::default();
+        block(&mut writer);
+        let lines: Vec<LogContent> = writer.into_lines().into_iter().map(|message| self.fmt(module, level, "", 0, message)).collect();
+        let Some(s) = block::join(lines) else {
+            return;
+        };
+        if self.mode == PRINTMODE::DELAY {
+            self.log(level, intern(module), s);
+        } else {
+            self.safeprint(level, module, s);
+        }
+    }
+
+    /// Logs a line after `after` unless `clear_persisting(key)` comes first,
+    /// see `persist`. `message` is only called when `key` isn't armed yet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_if_persists(&mut self, key: &str, after: Duration, level: LEVEL, module: &str, file: &str, line: u32, message: impl FnOnce() -> String) {
+        let (now, since) = (self.clock.now(), self.clock.wall());
+        if let Some(pending) = self.persisting.arm(key, || Pending::new(level, module, (file, line), message(), since, now + after)) {
+            self.log_persisted(pending);
+        }
+    }
+
+    /// Cancels the line armed for `key`; false when none was.
+    pub fn clear_persisting(&mut self, key: &str) -> bool {
+        self.persisting.clear(key)
+    }
+
+    /// Logs the lines of `log_if_persists` that are due, and returns how long
+    /// until the next one is.
+    pub fn expire_persisting(&mut self) -> Option<Duration> {
+        for pending in self.persisting.expired(self.clock.now()) {
+            self.log_persisted(pending);
+        }
+        self.persisting.wait(self.clock.now())
+    }
+
+    fn log_persisted(&mut self, pending: Pending) {
+        let fields = pending.fields();
+        let Pending { level, module, file, line, message, .. } = pending;
+        let s = self.fmt_with_fields(&module, level, &file, line, None, message, fields);
+        if s.is_empty() {
+            return;
+        }
+        if self.mode == PRINTMODE::DELAY {
+            self.log(level, module, s);
+        } else {
+            self.safeprint(level, &module, s);
+        }
+    }
+
+    /// Writes a line of the macros of `LOG`: queued under
+    /// `PRINTMODE::DELAY`, at once otherwise.
+    #[doc(hidden)]
+    pub fn queue_or_print(&mut self, level: LEVEL, module: &'static str, message: LogContent) {
+        if self.mode == PRINTMODE::DELAY {
+            self.log(level, module, message);
+        } else {
+            self.safeprint(level, module, message);
+        }
+    }
+
+    /// Queues a formatted line. A `&'static str` module, such as
+    /// `module_path!()`, is queued without a copy.
+    pub fn log(&mut self, level: LEVEL, module: impl Into<Cow<'static, str>>, message: LogContent) {
+        self.count(level, &message);
+        let module = module.into();
+        let bytes = module.len() + message.size();
+        let Some(message) = memory::hold(level, bytes, message) else {
+            return;
+        };
+        let sink = self.sink_name(&module, level);
+        let mut enqueued_at = None;
+        if self.latency_sampling > 0 {
+            if self.sampled.is_multiple_of(self.latency_sampling) {
+                enqueued_at = Some(Instant::now());
+            }
+            self.sampled += 1;
+        }
+        self.stats.enqueued(&sink);
+        // With the consumer stopped the line is dropped, see `health`.
+        if let Err(SendError(QueueItem::Line((.., queued)))) = self.sender.send(QueueItem::Line((level, module, message, Queued { sink, enqueued_at }))) {
+            self.stats.shed(&queued.sink);
+        }
+    }
+
+    /// Waits until the lines queued so far are written, then syncs the
+    /// files of this logger to disk, e.g. before the process exits. Under
+    /// `global()` the queue can't be waited for, since its lines are written
+    /// through the global logger: `LOG.flush` waits first, then syncs.
+    pub fn flush(&mut self) {
+        if !crate::inside_tklog() {
+            if let Some(wait) = self.mark_queue() {
+                let _ = wait.recv();
+            }
+        }
+        for fh in self.fmap.values_mut().chain(std::iter::once(&mut self.filehandle.1)).chain(self.tees.iter_mut().map(|(_, fh)| fh)) {
+            let _ = fh.flush();
+        }
+        if let Some(d) = &mut self.directory {
+            for fh in d.handlers_mut() {
+                let _ = fh.flush();
+            }
+        }
+        self.flush_custom_sink();
+        if let Some(remote) = &self.remote {
+            remote.flush_blocking();
+        }
+    }
+
+    /// Answered once the consumer has written the lines queued so far;
+    /// None when it stopped.
+    pub(crate) fn mark_queue(&self) -> Option<Receiver<()>> {
+        let (done, wait) = channel();
+        self.sender.send(QueueItem::Flush(done)).ok().map(|_| wait)
+    }
+
+    /// What keeps this logger from working as configured, see `health`.
+    pub fn health(&self) -> Health {
+        let mut reasons = Vec::new();
+        if self.consumer.as_ref().is_none_or(|c| c.is_finished()) {
+            reasons.push(Degradation::QueueStopped);
+        }
+        let mut diverted: Vec<&String> = self.fmap.iter().filter(|(_, fh)| fh.is_degraded()).map(|(filename, _)| filename).collect();
+        diverted.sort();
+        reasons.extend(diverted.into_iter().map(|filename| Degradation::Diverted(filename.clone())));
+        if self.custom_panics.is_disabled() {
+            reasons.push(Degradation::CallbackDisabled("custom handler"));
+        }
+        if let Some(name) = self.filters.disabled() {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        if let Some(name) = self.hooks.disabled() {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        if let Some(name) = self.filesettings.errors.disabled() {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        reasons.extend(self.render.disabled_callbacks().into_iter().map(Degradation::CallbackDisabled));
+        if let Some(name) = self.dynamic_fields.as_ref().and_then(|g| g.disabled()) {
+            reasons.push(Degradation::CallbackDisabled(name));
+        }
+        if self.file_handlers().any(|fh| fh.rotation_handler_disabled()) {
+            reasons.push(Degradation::CallbackDisabled("rotation handler"));
+        }
+        Health::new(reasons)
+    }
+
+    /// Restarts a stopped queue consumer, reopens the files whose lines
+    /// go to the default file and enables the disabled callbacks again.
+    /// Errs with the reasons of `health` left, such as a file that still
+    /// can't be opened. The lines queued when the consumer stopped are lost.
+    pub fn try_recover(&mut self) -> Result<(), Error> {
+        if self.consumer.as_ref().is_none_or(|c| c.is_finished()) {
+            (self.sender, self.consumer) = spawn_consumer(self.stats.clone(), self.batch.clone());
+        }
+        for fh in self.fmap.values_mut().chain(std::iter::once(&mut self.filehandle.1)).chain(self.tees.iter_mut().map(|(_, fh)| fh)) {
+            let _ = fh.recover();
+        }
+        self.custom_panics.reset();
+        self.filters.reset();
+        self.hooks.reset();
+        self.filesettings.errors.reset();
+        self.render.reset_callbacks();
+        if let Some(g) = &self.dynamic_fields {
+            g.reset();
+        }
+        let health = self.health();
+        if health.ok {
+            Ok(())
+        } else {
+            Err(Error::NotRecovered(health.degraded_reasons))
+        }
+    }
+
+    /// Moves the default file to `path` for the lines after this call.
+    /// What the old file holds is written out and, with `rotate_old`,
+    /// renamed to its next backup as a cut would; the file of `path` is
+    /// opened with the same cut settings, its directories created, and the
+    /// cuts count from it. The path the default file has is a no-op. Errs
+    /// with `Error::NoLogFile` without a default file, and with
+    /// `Error::PathConflict` or `Error::FileUnopenable` leaving the old file
+    /// as it was.
+    pub fn set_log_path(&mut self, path: &str, rotate_old: bool) -> Result<&mut Self, Error> {
+        if path == self.filehandle.0 {
+            return Ok(self);
+        }
+        let Some(old) = self.filehandle.1.file_config() else {
+            return Err(Error::NoLogFile);
+        };
+        let option = FileOptionType::from_config(&config::FileConfig { filename: path.to_string(), ..old.clone() });
+        self.paths.claim_alone(DEFAULT_FILE, &option)?;
+        let f = match self.new_filehandler(Box::new(option)) {
+            Ok(f) => f,
+            Err(e) => {
+                self.paths.claim(DEFAULT_FILE, &FileOptionType::from_config(&old));
+                return Err(Error::FileUnopenable(path.into(), e));
+            }
+        };
+        self.filehandle.1.retire_file(rotate_old);
+        self.filehandle.0 = f.get_file_name();
+        self.filehandle.1.set_file_handler(f);
+        Ok(self)
+    }
+
+    /// Writes out what the files hold and opens their paths again, for the
+    /// files renamed by logrotate to be written anew; the size cuts count
+    /// from the files opened. The files of `set_directory_mode` are closed
+    /// and open again on their next line. Errs with the first file that
+    /// can't be opened, after trying every one.
+    pub fn reopen(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        let default = std::iter::once((&self.filehandle.0, &mut self.filehandle.1));
+        for (filename, fh) in default.chain(self.fmap.iter_mut()).chain(self.tees.iter_mut().map(|(f, fh)| (&*f, fh))) {
+            if let Err(e) = fh.reopen_file() {
+                diagnostics::report(Category::Reopen, Some(Path::new(filename)), format!("cannot reopen {}: {}", filename, e));
+                result = result.and(Err(e));
+            }
+        }
+        if let Some(d) = &mut self.directory {
+            d.close_all();
+        }
+        result
+    }
+
+    /// Reopens the files, see `reopen`, on every SIGHUP, as logrotate's
+    /// `postrotate` sends; the signal no longer ends the process. Errs if
+    /// the signal can't be listened for.
+    #[cfg(all(unix, feature = "signal"))]
+    pub fn enable_reopen_on_sighup(&mut self) -> io::Result<&mut Self> {
+        crate::signal::listen()?;
+        self.hangups = Some(crate::signal::hangups());
+        Ok(self)
+    }
+
+    /// `reopen` once a SIGHUP came since the last line.
+    fn reopen_on_hangup(&mut self) {
+        #[cfg(all(unix, feature = "signal"))]
+        if let Some(seen) = self.hangups {
+            let hangups = crate::signal::hangups();
+            if hangups != seen {
+                self.hangups = Some(hangups);
+                let _ = self.reopen();
+            }
+        }
+    }
+
+    /// The default file, the module and level files and the tee files.
+    fn file_handlers(&self) -> impl Iterator<Item = &FHandler> {
+        std::iter::once(&self.filehandle.1).chain(self.fmap.values()).chain(self.tees.iter().map(|(_, fh)| fh))
+    }
+
+    /// The file a line of `module` at `level` is written to, following the
+    /// same routing as `print`; `console` when there is no file.
+    fn sink_name(&mut self, module: &str, level: LEVEL) -> String {
+        if !module.is_empty() && self.modmap.len() > 0 {
+            if let Some((_, filename)) = self.modmap.get(module) {
+                if !filename.is_empty() {
+                    return filename.clone();
+                }
+            }
+        }
+        if let Some(levels) = &self.levels {
+            if let Some((_, filename)) = &levels[level as usize - 1] {
+                if !filename.is_empty() {
+                    return filename.clone();
+                }
+            }
+        }
+        if self.filehandle.0.is_empty() {
+            "console".to_string()
+        } else {
+            self.filehandle.0.clone()
+        }
+    }
+
+    /// Caps the bytes written to `handler_id`, a log file name, per day. Lines
+    /// over the quota are dropped and counted, with a single warning to the
+    /// default handler; the quota resets at the next day boundary.
+    pub fn set_handler_quota(&mut self, handler_id: &str, bytes_per_day: u64) -> &mut Self {
+        self.quotas.insert(handler_id.to_string(), Quota::new(bytes_per_day));
+        self
+    }
+
+    /// Charges `message` to the quota of its handler; true if it must be dropped.
+    fn over_quota(&mut self, level: LEVEL, module: &str, message: &LogContent) -> bool {
+        if self.quotas.is_empty() {
+            return false;
+        }
+        let handler = self.sink_name(module, level);
+        self.charge_quota(&handler, message)
+    }
+
+    fn charge_quota(&mut self, handler: &str, message: &LogContent) -> bool {
+        let Some(quota) = self.quotas.get_mut(handler) else {
+            return false;
+        };
+        match quota.admit(message.file_body.len() as u64) {
+            Admission::Admit => false,
+            Admission::Drop => true,
+            Admission::Exceeded => {
+                let notice = quota.exceeded_notice(handler);
+                if self.get_level("tklog") <= LEVEL::Warn {
+                    let s = self.fmt("tklog", LEVEL::Warn, "", 0, notice);
+                    if !s.is_empty() {
+                        let console = self.fmthandle.get_console();
+                        let _ = self.filehandle.1.print(console, s);
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    /// Temporarily drops lines below Warn after an Error storm. Off by default.
+    pub fn set_storm_control(&mut self, config: StormConfig) -> &mut Self {
+        self.storm = Some(StormControl::new(config));
+        self
+    }
+
+    pub fn clear_storm_control(&mut self) -> &mut Self {
+        self.storm = None;
+        self
+    }
+
+    /// For `duration`, logs a backtrace with the match count for the first
+    /// and then every `sample_rate`-th line whose message contains
+    /// `message`, at the level of that line, to find where a flood of lines
+    /// comes from. The window then closes with a count of the matches.
+    pub fn trace_callers(&mut self, message: &str, sample_rate: u64, duration: Duration) -> &mut Self {
+        self.callers = Some(CallerTrace::new(message, sample_rate, self.clock.now(), duration));
+        self
+    }
+
+    pub fn stop_trace_callers(&mut self) -> &mut Self {
+        self.callers = None;
+        self
+    }
+
+    /// Calls `f` for every line that passes the level checks, before it is
+    /// formatted, to add fields such as the tenant of the current request;
+    /// they follow the message as ` key=value`. See `tklog::fields`.
+    pub fn set_dynamic_fields(&mut self, f: DynamicFields) -> &mut Self {
+        self.dynamic_fields = Some(Guarded::new("dynamic fields callback", f));
+        self
+    }
+
+    pub fn clear_dynamic_fields(&mut self) -> &mut Self {
+        self.dynamic_fields = None;
+        self
+    }
+
+    /// Whether lines get the fields of `tklog::context`, looked up per
+    /// line. Default: true.
+    pub fn set_context_fields(&mut self, on: bool) -> &mut Self {
+        self.context_fields = on;
+        self
+    }
+
+    /// Keeps the first `n` fields of a line, those of the macro first, and
+    /// drops the others, see `tklog::fields`. 0, the default, keeps them all.
+    pub fn set_max_fields(&mut self, n: usize) -> &mut Self {
+        self.field_limits.max_fields = n;
+        self
+    }
+
+    /// Cuts the field values of a line to `len` bytes, see `tklog::fields`.
+    /// 0, the default, leaves them whole.
+    pub fn set_max_field_value_len(&mut self, len: usize) -> &mut Self {
+        self.field_limits.max_value_len = len;
+        self
+    }
+
+    /// Lays every line out with `f` instead of the format flags and the
+    /// formatter; the body formats of `set_attr_format` still apply. See
+    /// `tklog::record`.
+    pub fn set_record_formatter(&mut self, f: RecordFormatter) -> &mut Self {
+        self.leave_format_json();
+        self.render.set_formatter(f);
+        self
+    }
+
+    /// Lays every line out as one JSON object, `{"level":…,"time":…,"file":…,
+    /// "line":…,"module":…,"message":…}`, on the console and in the files.
+    /// It replaces the `set_formatter` template and the record formatter,
+    /// and the next one set replaces it; `false` goes back to text lines.
+    pub fn set_format_json(&mut self, on: bool) -> &mut Self {
+        if !on {
+            self.leave_format_json();
+            return self;
+        }
+        self.fmthandle.clear_formatter();
+        self.render.formatter = None;
+        self.render.preset = Some(Preset::Json);
+        self.output.reset(OutputSink::Console, OutputMode::Json);
+        self.output.reset(OutputSink::File, OutputMode::Json);
+        self
+    }
+
+    fn leave_format_json(&mut self) {
+        if matches!(self.render.preset, Some(Preset::Json)) {
+            self.render.preset = None;
+            self.output.clear(OutputSink::Console);
+            self.output.clear(OutputSink::File);
+        }
+    }
+
+    pub fn clear_record_formatter(&mut self) -> &mut Self {
+        self.render.formatter = None;
+        self
+    }
+
+    /// Lays out the console lines with `f`, such as a
+    /// `badge::ConsoleBadgeFormatter`, instead of the format flags, the
+    /// formatter and the console body format. Files are unaffected.
+    pub fn set_console_formatter_impl(&mut self, f: impl LogFormatter + 'static) -> &mut Self {
+        self.render.set_console_formatter(Box::new(f));
+        self
+    }
+
+    pub fn clear_console_formatter(&mut self) -> &mut Self {
+        self.render.console_formatter = None;
+        self
+    }
+
+    /// Keeps ANSI escape sequences, such as the colors of a body format
+    /// shared with the console, in the lines written to files and custom
+    /// sinks. Default: false, they are stripped there.
+    /// Appends `#n` to the time of a line, n counting the lines before it
+    /// with the same rendered time, or gives it as `{subseq}` to a body
+    /// format that has it; ties of a coarse clock then keep their order.
+    /// Default: false.
+    pub fn set_time_subseq(&mut self, on: bool) -> &mut Self {
+        self.subseq = on;
+        self
+    }
+
+    /// Gives every line the boot ID of the process, see `boot`. A formatter
+    /// template shows it with `{bootid}` either way. Default: false.
+    pub fn set_include_boot_id(&mut self, on: bool) -> &mut Self {
+        self.boot_id = on;
+        self
+    }
+
+    /// Keeps the module path in the function of `Format::FuncName` and
+    /// `{function}`, `app::api::Server::handle` instead of `Server::handle`.
+    /// Default: false.
+    pub fn set_function_with_module(&mut self, on: bool) -> &mut Self {
+        self.function_module = on;
+        self
+    }
+
+    /// Writes the backtrace of an `ErrorChain` given one, on indented lines
+    /// after its message. Default: false, one line per record.
+    pub fn set_error_backtraces(&mut self, on: bool) -> &mut Self {
+        self.error_backtraces = on;
+        self
+    }
+
+    /// Where the macros take the file and line of a line from. The default,
+    /// `LocationStrategy::Caller`, reports the caller of a `#[track_caller]`
+    /// helper the macro is in; `MacroExpansion` the macro itself.
+    pub fn set_location_strategy(&mut self, strategy: LocationStrategy) -> &mut Self {
+        self.location_strategy = strategy;
+        self
+    }
+
+    pub fn location_strategy(&self) -> LocationStrategy {
+        self.location_strategy
+    }
+
+    pub fn allow_ansi_in_files(&mut self, allow: bool) -> &mut Self {
+        self.render.allow_ansi = allow;
+        self
+    }
+
+    /// Names `level` `label` in the level flag of text lines, `[label]`,
+    /// and in `{level}`; the other levels keep their names. A
+    /// `set_level_fmt` comes first, JSON lines keep the usual names.
+    pub fn set_level_label(&mut self, level: LEVEL, label: &str) -> &mut Self {
+        self.render.labels.set(level, label);
+        self
+    }
+
+    pub fn clear_level_labels(&mut self) -> &mut Self {
+        self.render.labels.clear();
+        self
+    }
+
+    /// Colors the level flag of the console lines, see `color`; left off
+    /// on a stream that is not a terminal. Default: false.
+    pub fn set_console_color(&mut self, on: bool) -> &mut Self {
+        match on {
+            true => self.set_console_color_options(ColorOptions::default()),
+            false => {
+                self.render.console_color = None;
+                self
+            }
+        }
+    }
+
+    /// Colors the console lines as `opts` says, see `color`.
+    pub fn set_console_color_options(&mut self, opts: ColorOptions) -> &mut Self {
+        self.render.console_color = ConsoleColors::new(opts);
+        self
+    }
+
+    /// Sends the console lines to stdout, the default, to stderr, or split
+    /// by level with `ConsoleStream::SplitAt`. Stdout is flushed before a
+    /// line goes to stderr, so the two keep their order on a shared terminal.
+    pub fn set_console_stream(&mut self, stream: ConsoleStream) -> &mut Self {
+        self.render.console_stream = stream;
+        self
+    }
+
+    /// Sets up logging for a container: JSON lines on stdout with `v`, the
+    /// schema version of `json`, `ts` (RFC 3339, UTC), `level`, `msg`,
+    /// `caller` and `logger` keys, plus `pod` and `namespace` from
+    /// `POD_NAME` and `POD_NAMESPACE` when set. Level Info, no file, no body formatters; `describe()` shows the preset.
+    pub fn preset_k8s(&mut self) -> &mut Self {
+        self.fmthandle.set_console(true);
+        self.fmthandle.set_level(LEVEL::Info);
+        self.fmthandle.set_format(Format::LevelFlag | Format::Date | Format::Time | Format::Microseconds | Format::LongFileName);
+        self.fmthandle.clear_formatter();
+        self.render.attrfmt = AttrFormat::new();
+        self.render.formatter = None;
+        self.render.console_formatter = None;
+        self.filehandle = ("".to_string(), FHandler::new());
+        self.routing = None;
+        self.render.preset = Some(Preset::K8s(K8sPreset::from_env()));
+        self.output.reset(OutputSink::Console, OutputMode::Json);
+        self.output.reset(OutputSink::File, OutputMode::Json);
+        self
+    }
+
+    /// Pins the JSON lines to an older schema version, see `json`.
+    /// Errs with `Error::UnknownSchemaVersion` for a version there isn't.
+    pub fn set_json_schema_version(&mut self, version: u32) -> Result<&mut Self, Error> {
+        let schema = Schema::version(version).ok_or(Error::UnknownSchemaVersion(version))?;
+        self.render.json_schema = Some(schema);
+        Ok(self)
+    }
+
+    /// Sets up a service whose stdout goes to journald: each console line
+    /// starts with the `<N>` priority of its level (`<7>` for Trace and
+    /// Debug up to `<2>` for Fatal), ahead of any console body formatting,
+    /// and leaves out the time journald stamps itself. Files are unaffected.
+    pub fn preset_systemd(&mut self) -> &mut Self {
+        self.fmthandle.set_console(true);
+        self.render.preset = Some(Preset::Systemd);
+        self.output.reset(OutputSink::Console, OutputMode::Text);
+        self
+    }
+
+    /// Puts `sink` in `mode`, see `output`. Errs with
+    /// `Error::ConflictingMode` while it is in another mode.
+    pub fn set_output_mode(&mut self, sink: OutputSink, mode: OutputMode) -> Result<&mut Self, Error> {
+        self.output.claim(sink, mode)?;
+        Ok(self)
+    }
+
+    pub fn clear_output_mode(&mut self, sink: OutputSink) -> &mut Self {
+        self.output.clear(sink);
+        self
+    }
+
+    /// Writes to `sink` only the lines at `level` or above, whatever the
+    /// level of their module, see `output`. The macros let a line through
+    /// once one sink takes it.
+    pub fn set_output_level(&mut self, sink: OutputSink, level: LEVEL) -> &mut Self {
+        self.output_levels.set(sink, Some(level));
+        self
+    }
+
+    /// Lets `sink` follow the level of each line's module again.
+    pub fn clear_output_level(&mut self, sink: OutputSink) -> &mut Self {
+        self.output_levels.set(sink, None);
+        self
+    }
+
+    pub fn output_level(&self, sink: OutputSink) -> Option<LEVEL> {
+        self.output_levels.get(sink)
+    }
+
+    /// The output mode of each sink; errs with `Error::ConflictingMode` on
+    /// a sink not in text mode that has a formatter template or a body format.
+    pub fn validate(&self) -> Result<Vec<(OutputSink, OutputMode)>, Error> {
+        self.events.check();
+        self.output.validate(&self.render, self.fmthandle.get_formatter().is_some())
+    }
+
+    /// The event IDs lines are expected to carry, with what each means;
+    /// `validate` warns about the others, see `events`.
+    pub fn set_event_registry(&mut self, events: &[(&str, &str)]) -> &mut Self {
+        self.events.set_registry(events);
+        self
+    }
+
+    pub fn event_description(&self, id: &str) -> Option<String> {
+        self.events.description(id).map(str::to_string)
+    }
+
+    /// Appends the trace and span IDs of the active OpenTelemetry span to
+    /// every line, see `otel`. Does nothing without the `otel` feature.
+    pub fn set_auto_trace_ids(&mut self, on: bool) -> &mut Self {
+        #[cfg(feature = "otel")]
+        {
+            self.auto_trace_ids = on;
+        }
+        #[cfg(not(feature = "otel"))]
+        let _ = on;
+        self
+    }
+
+    /// Sends the lines to journald as structured records instead of the
+    /// console and the files, falling back to those while its socket fails,
+    /// see `journald`.
+    #[cfg(feature = "journald")]
+    pub fn set_journald(&mut self, on: bool) -> &mut Self {
+        self.set_journald_socket(on.then(|| PathBuf::from(JOURNALD_SOCKET)))
+    }
+
+    /// `set_journald` with a socket other than `JOURNALD_SOCKET`, as in a
+    /// container with the journal mounted elsewhere; None turns it off.
+    #[cfg(feature = "journald")]
+    pub fn set_journald_socket(&mut self, socket: Option<PathBuf>) -> &mut Self {
+        self.render.source = socket.is_some();
+        self.journald = socket.map(Journald::new);
+        self
+    }
+
+    /// Sends the lines to syslog instead of the console and the files,
+    /// falling back to those while the socket fails, see `syslog`.
+    #[cfg(feature = "syslog")]
+    pub fn set_syslog(&mut self, config: SyslogConfig) -> &mut Self {
+        self.syslog = Some(Syslog::new(config));
+        self
+    }
+
+    #[cfg(feature = "syslog")]
+    pub fn clear_syslog(&mut self) -> &mut Self {
+        self.syslog = None;
+        self
+    }
+
+    /// Ships the lines over TCP or UDP too, see `remote`. A remote end set
+    /// before is flushed first.
+    pub fn set_remote(&mut self, config: RemoteConfig) -> &mut Self {
+        self.remote = Some(Remote::new(config, false));
+        self
+    }
+
+    /// Stops shipping the lines, once those queued are sent or the flush
+    /// timeout is over.
+    pub fn clear_remote(&mut self) -> &mut Self {
+        self.remote = None;
+        self
+    }
+
+    /// The lines dropped from the full queue of the remote end.
+    pub fn remote_dropped(&self) -> u64 {
+        self.remote.as_ref().map_or(0, |r| r.dropped())
+    }
+
+    /// Replaces the time source of time-window logic such as storm control.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keeps the bytes laid out per day near `bytes_per_day` on long
+    /// unattended runs: when the day's volume projects over it, every
+    /// level is raised one step for the rest of the day, with a notice
+    /// from module `tklog`, and back as set the next day. 0 turns it off,
+    /// the default.
+    pub fn set_adaptive_budget(&mut self, bytes_per_day: u64) -> &mut Self {
+        self.budget = (bytes_per_day > 0).then(|| AdaptiveBudget::new(bytes_per_day, self.clock.wall()));
+        self
+    }
+
+    /// Measures the enqueue-to-write latency of one in `n` queued lines;
+    /// 0 turns latency sampling off. Default: 64.
+    pub fn set_latency_sampling(&mut self, n: u64) -> &mut Self {
+        self.latency_sampling = n;
+        self
+    }
+
+    /// Per-sink queue depth and latency statistics.
+    pub fn stats(&self) -> LogStats {
+        let mut stats = self.stats.snapshot();
+        stats.quotas = self.quotas.iter().map(|(handler, q)| q.stats(handler)).collect();
+        stats
+    }
+
+    /// The lines printed at each level, and the bytes, rotations and
+    /// compressions of the files.
+    pub fn metrics(&self) -> LogMetrics {
+        self.filesettings.metrics.snapshot()
+    }
+
+    /// Sets every counter of `metrics` back to 0.
+    pub fn reset_metrics(&self) {
+        self.filesettings.metrics.reset();
+    }
+
+    /// Counts a line that passed the level and the filters.
+    fn count(&self, level: LEVEL, message: &LogContent) {
+        if !message.is_empty() {
+            self.filesettings.metrics.printed(level);
+        }
+    }
+
+    /// A readable summary of the configuration, the extra handlers and the
+    /// consumption of each handler quota.
+    pub fn describe(&self) -> String {
+        let mut out = self.config().to_string();
+        let mut handlers: Vec<&String> = self.fmap.keys().collect();
+        handlers.sort();
+        for h in handlers {
+            let _ = writeln!(out, "handler: {}", h);
+        }
+        for (handler, q) in &self.quotas {
+            let q = q.stats(handler);
+            let _ = writeln!(out, "quota: {} {}/{} bytes today, {} lines dropped", q.handler, q.used, q.bytes_per_day, q.dropped);
+        }
+        if let Some(routing) = &self.routing {
+            out.push_str(&routing.describe());
+        }
+        if let Some(preset) = &self.render.preset {
+            out.push_str(&format!("preset: {}\n", preset.name()));
+        }
+        out.push_str(&self.output.describe());
+        if let Some(d) = &self.directory {
+            let _ = writeln!(out, "directory: {} ({:?})", d.root().display(), d.layout());
+        }
+        out
+    }
+
+    pub fn get_level(&mut self, module: &str) -> LEVEL {
+        let level = self.set_level_of(module);
+        let level = self.output_levels.lowest(level, self.fmthandle.get_console());
+        let Some(budget) = self.budget.as_mut() else {
+            return level;
+        };
+        let (raised, notice) = budget.check(self.clock.wall());
+        if let Some(notice) = notice {
+            self.log_internal(LEVEL::Info, notice);
+        }
+        if raised {
+            budget::raise(level)
+        } else {
+            level
+        }
+    }
+
+    /// The level of `module` as set, before the adaptive budget.
+    fn set_level_of(&mut self, module: &str) -> LEVEL {
+        if module != "" && self.modmap.len() > 0 {
+            if let Some(mm) = self.modmap.get(module) {
+                let (lo, _) = mm;
+                if let Some(level) = lo.level {
+                    return level;
+                }
+            }
+        }
+        self.fmthandle.get_level()
+    }
+
+    /// Whether the macros pass the file and line of a line: when its format
+    /// shows them, or for the filters and the captures.
+    pub fn is_file_line(&mut self, level: LEVEL, module: &str) -> bool {
+        !self.filters.is_empty() || !self.captures.is_empty() || self.shows_file_line(level, module)
+    }
+
+    fn shows_file_line(&mut self, level: LEVEL, module: &str) -> bool {
+        if let Some(levels) = &self.levels {
+            if let Some(lp) = &levels[level as usize - 1] {
+                let (lo, _) = lp;
+                if let Some(v) = lo.format {
+                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
+                }
+            }
+        }
+
+        if module != "" && self.modmap.len() > 0 {
+            if let Some(mm) = self.modmap.get(module) {
+                let (lo, _) = mm;
+                if let Some(v) = lo.format {
+                    return v & (Format::LongFileName | Format::ShortFileName) != 0;
+                }
+            }
+        }
+        self.fmthandle.is_file_line()
+    }
+
+    pub fn fmt(
+        &mut self,
+        module: &str,
+        level: LEVEL,
+        filename: &str,
+        line: u32,
+        message: String,
+    ) -> LogContent {
+        self.fmt_with_event(module, level, filename, line, None, message)
+    }
+
+    /// `fmt` for a line with an event ID, see `events`.
+    pub fn fmt_with_event(&mut self, module: &str, level: LEVEL, filename: &str, line: u32, event: Option<&'static str>, message: String) -> LogContent {
+        self.fmt_with_fields(module, level, filename, line, event, message, FieldMap::new())
+    }
+
+    /// `fmt_with_event` with the fields of the line, as given to a macro
+    /// after its `;`, see `fields`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn fmt_with_fields(&mut self, module: &str, level: LEVEL, filename: &str, line: u32, event: Option<&'static str>, message: String, fields: FieldMap) -> LogContent {
+        if self.persisting.is_due(|| self.clock.now()) {
+            self.expire_persisting();
+        }
+        let _inside = Inside::enter();
+        if !self.filters.is_empty() && !self.filters.pass(&LogRecord { level, module, file: filename, line, message: &message }) {
+            self.stats.filtered();
+            return LogContent::new(String::new(), None);
+        }
+        let at = (filename, line);
+        let (filename, line) = match (self.filters.is_empty() && self.captures.is_empty()) || self.shows_file_line(level, module) {
+            true => (filename, line),
+            false => ("", 0),
+        };
+        if let Some(storm) = self.storm.as_mut() {
+            let mut notices = Vec::new();
+            let pass = storm.check(self.clock.now(), module, level, &mut notices);
+            for n in notices {
+                self.log_internal(LEVEL::Warn, n);
+            }
+            if !pass {
+                self.stats.storm_suppressed();
+                return LogContent::new(String::new(), None);
+            }
+        }
+        // Not the clock without a trace: wasm32 has no `Instant`.
+        if module != "tklog" && self.callers.is_some() {
+            if let Some(notice) = callers::check(&mut self.callers, self.clock.now(), &message) {
+                self.log_internal(level, notice);
+            }
+        }
+        if let Some(ch) = &self.custom_handler {
+            let ctx = LogContext {
+                level,
+                filename: filename.to_string(),
+                line,
+                log_body: message.clone(),
+                modname: module.to_string(),
+            };
+            if !self.custom_panics.call("custom handler", || ch(&ctx)).unwrap_or(true) {
+                return LogContent::new(String::new(), None);
+            }
+        }
+        self.captures.record(level, module, at.0, at.1, &message);
+
+        let (fmat, formatter) = Self::layout_of(&self.fmthandle, &mut self.modmap, &self.levels, module, level);
+
+        #[cfg(feature = "otel")]
+        let message = if self.auto_trace_ids { crate::otel::with_trace_ids(message) } else { message };
+        let layout = Layout {
+            render: &self.render,
+            field_limits: self.field_limits,
+            context_fields: self.context_fields,
+            dynamic_fields: self.dynamic_fields.as_ref(),
+            testmode: self.testmode,
+            seq: self.seq,
+            subseq: self.subseq,
+            boot_id: self.boot_id,
+            function_module: self.function_module,
+        };
+        let content = layout.content(module, level, filename, line, event, message, fields, fmat, formatter);
+        if let Some(id) = event {
+            self.events.seen(id);
+        }
+        self.seq += 1;
+        if let Some(notice) = self.budget.as_mut().and_then(|b| b.charge(self.clock.wall(), content.size())) {
+            self.log_internal(LEVEL::Warn, notice);
+        }
+        if !self.hooks.is_empty() && !content.is_empty() {
+            self.hooks.call(level, module, &content.file_body);
+        }
+        content
+    }
+
+    /// The `Format` flags and formatter of the lines of `module` at `level`,
+    /// after the module and level options.
+    fn layout_of<'a>(
+        fmthandle: &'a FmtHandler,
+        modmap: &'a mut Trie<(LogOptionConst, String)>,
+        levels: &'a Option<[Option<(LogOption, String)>; 7]>,
+        module: &str,
+        level: LEVEL,
+    ) -> (u8, Option<&'a String>) {
+        let mut fmat = fmthandle.get_format();
+        let mut formatter = fmthandle.get_formatter();
+        if !module.is_empty() && modmap.len() > 0 {
+            if let Some(mm) = modmap.get(module) {
+                let (lo, _) = mm;
+                if let Some(v) = lo.format {
+                    fmat = v;
+                }
+                if lo.formatter.is_some() {
+                    formatter = lo.formatter.as_ref();
+                }
+            }
+        }
+
+        if let Some(levels) = levels {
+            if let Some(lp) = &levels[level as usize - 1] {
+                let (lo, _) = lp;
+                if let Some(v) = lo.format {
+                    fmat = v;
+                }
+                if lo.formatter.is_some() {
+                    formatter = lo.formatter.as_ref();
+                }
+            }
+        }
+        (fmat, formatter)
+    }
+
+    /// Writes a message known at compile time, see `fmt_static`.
+    pub fn print_static(&mut self, level: LEVEL, module: &str, msg: &'static str) {
+        let content = self.fmt_static(level, module, msg);
+        if !content.is_empty() {
+            self.safeprint(level, module, content);
+        }
+    }
+
+    /// `fmt` for a message known at compile time, as in `info!("cache miss")`:
+    /// unless something needs the whole record, such as a formatter, a custom
+    /// handler, fields or a storm guard, only the level and time are laid out
+    /// around it, and their prefix is reused within a second.
+    pub fn fmt_static(&mut self, level: LEVEL, module: &str, msg: &'static str) -> LogContent {
+        match self.plain_format(module, level, msg) {
+            Some(fmat) => {
+                let time = self.testmode.map_or_else(now, |t| t.fixed_time);
+                let prefix = self.static_prefix.get(level, fmat, time);
+                let mut body = String::with_capacity(prefix.len() + msg.len() + 1);
+                body.push_str(prefix);
+                body.push_str(msg);
+                body.push('\n');
+                self.seq += 1;
+                LogContent::new(body, None)
+            }
+            None => self.fmt(module, level, "", 0, msg.to_string()),
+        }
+    }
+
+    /// The format of `msg` when `print_static` can lay it out alone.
+    fn plain_format(&mut self, module: &str, level: LEVEL, msg: &str) -> Option<u8> {
+        let plain = self.plain_lines()
+            && (!self.context_fields || crate::context::is_empty())
+            && (!msg.contains('\x1b') || self.render.allow_ansi && color::ansi(false));
+        let (fmat, formatter) = Self::layout_of(&self.fmthandle, &mut self.modmap, &self.levels, module, level);
+        (plain && Self::plain_layout(fmat, formatter)).then_some(fmat)
+    }
+
+    /// Whether `plain_format` may take the lines of some module and level.
+    fn plain_lines(&self) -> bool {
+        self.stateless_lines() && !self.subseq && !self.boot_id && self.render.is_plain()
+    }
+
+    fn plain_layout(fmat: u8, formatter: Option<&String>) -> bool {
+        formatter.is_none() && fmat != Format::Nano && fmat & (Format::ThreadId | Format::FuncName) == 0
+    }
+
+    /// Whether nothing of the logger but its sequence number sees or changes
+    /// with a line, such as a filter, the storm guard or a hook.
+    fn stateless_lines(&self) -> bool {
+        let stateless = self.storm.is_none()
+            && self.callers.is_none()
+            && self.custom_handler.is_none()
+            && self.filters.is_empty()
+            && self.hooks.is_empty()
+            && self.captures.is_empty()
+            && self.dynamic_fields.is_none()
+            && self.budget.is_none();
+        #[cfg(feature = "otel")]
+        let stateless = stateless && !self.auto_trace_ids;
+        stateless
+    }
+
+    /// Whether a line may show its sequence number: with `{seq}` in a
+    /// formatter, or to a formatter of the whole record.
+    fn shows_seq(&self) -> bool {
+        let places_seq = |f: Option<&String>| f.is_some_and(|f| places(f, "seq"));
+        self.render.formatter.is_some()
+            || self.render.console_formatter.is_some()
+            || places_seq(self.fmthandle.get_formatter())
+            || self.modmap.entries().iter().any(|(_, (lo, _))| places_seq(lo.formatter.as_ref()))
+            || self.levels.iter().flatten().flatten().any(|(lo, _)| places_seq(lo.formatter.as_ref()))
+    }
+
+    /// What the lines of `LOG` need of this logger beyond their module, as
+    /// of `generation`, see `lines`. They are laid out under the lock when a
+    /// line may show its sequence number or subsequence, or changes more of
+    /// the logger than the sequence number.
+    fn line_config(&mut self, generation: u64) -> Arc<LineConfig> {
+        if let Some((g, config)) = &self.line_config {
+            if *g == generation {
+                return config.clone();
+            }
+        }
+        let config = Arc::new(LineConfig {
+            locked: self.subseq || !self.stateless_lines() || self.shows_seq(),
+            error_backtraces: self.error_backtraces,
+            location_strategy: self.location_strategy,
+            render: self.render.clone(),
+            field_limits: self.field_limits,
+            context_fields: self.context_fields,
+            testmode: self.testmode,
+            boot_id: self.boot_id,
+            function_module: self.function_module,
+        });
+        self.line_config = Some((generation, config.clone()));
+        config
+    }
+
+    /// What the lines of `LOG` in `module` need of this logger, as of the
+    /// current generation, see `lines`.
+    pub(crate) fn module_lines(&mut self, module: &'static str) -> ModuleLines {
+        const LEVELS: [LEVEL; 7] = [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal, LEVEL::Off];
+        let generation = lines::generation();
+        let config = self.line_config(generation);
+        let level = self.set_level_of(module);
+        let plain = self.plain_lines();
+        let layouts = LEVELS.map(|level| {
+            let file_line = self.is_file_line(level, module);
+            let (format, formatter) = Self::layout_of(&self.fmthandle, &mut self.modmap, &self.levels, module, level);
+            LevelLayout { file_line, format, formatter: formatter.cloned(), plain: plain && !file_line && Self::plain_layout(format, formatter) }
+        });
+        ModuleLines {
+            generation,
+            module,
+            level: self.output_levels.lowest(level, self.fmthandle.get_console()),
+            separator: self.separator(module).to_string(),
+            layouts,
+            config,
+        }
+    }
+
+    /// Writes a line of `LOG` laid out without the lock, see `lines`, after
+    /// what `fmt_with_fields` does of the logger for each line.
+    pub(crate) fn write_laid_out(&mut self, level: LEVEL, module: &'static str, event: Option<&'static str>, content: LogContent) {
+        if self.persisting.is_due(|| self.clock.now()) {
+            self.expire_persisting();
+        }
+        if let Some(id) = event {
+            self.events.seen(id);
+        }
+        self.seq += 1;
+        if !content.is_empty() {
+            self.queue_or_print(level, module, content);
+        }
+    }
+
+    /// Leaving `PRINTMODE::DELAY` writes the lines queued so far first,
+    /// except under `global()`, where `LOG.set_printmode` does.
+    pub fn set_printmode(&mut self, mode: PRINTMODE) -> &mut Self {
+        if self.mode == PRINTMODE::DELAY && mode != PRINTMODE::DELAY && !crate::inside_tklog() {
+            if let Some(wait) = self.mark_queue() {
+                let _ = wait.recv();
+            }
+        }
+        self.mode = mode;
+        self
+    }
+
+    /// Writes the lines of `PRINTMODE::DELAY` `lines` at a time, trading
+    /// latency for throughput; a partial batch is written once its oldest
+    /// line has waited `set_delay_max_latency`. 0, the default, writes
+    /// whatever is queued when the queue thread gets to it.
+    pub fn set_delay_batch(&mut self, lines: usize) -> &mut Self {
+        self.batch.set_size(lines);
+        self
+    }
+
+    /// How long a line of `PRINTMODE::DELAY` waits at most for its batch to
+    /// fill, see `set_delay_batch`. Default: 100ms.
+    pub fn set_delay_max_latency(&mut self, latency: Duration) -> &mut Self {
+        self.batch.set_max_latency(latency);
+        self
+    }
+
+    /// The lines queued by `PRINTMODE::DELAY` and not written yet, those
+    /// held in a batch included.
+    pub fn pending_records(&self) -> u64 {
+        self.stats.pending()
+    }
+
+    pub fn set_level(&mut self, level: LEVEL) -> &mut Self {
+        self.fmthandle.set_level(level);
+        self
+    }
+
+    pub fn set_console(&mut self, console: bool) -> &mut Self {
+        self.fmthandle.set_console(console);
+        self
+    }
+
+    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
+    pub fn set_format(&mut self, format: u8) -> &mut Self {
+        self.fmthandle.set_format(format);
+        self
+    }
+
+    /// The template of the lines, default `"{level}{time} {file}:{message}\n"`.
+    /// It places `{level}`, `{time}`, `{file}`, `{line}`, `{module}`,
+    /// `{message}`, `{thread}` (the thread name, or its ID), `{pid}`, `{seq}`,
+    /// `{subseq}`, `{event}` and `{bootid}`. `{file}` includes the line unless
+    /// `{line}` is placed too; both need a file name `Format`. An unknown
+    /// placeholder is written as it is.
+    pub fn set_formatter(&mut self, formatter: &str) -> &mut Self {
+        self.leave_format_json();
+        self.fmthandle.set_formatter(formatter.to_string());
+        self
+    }
+
+    /// Lays out the lines of `level` with `formatter` instead of the one
+    /// of `set_formatter`, keeping the other options of `level`, as
+    /// `set_level_option` would set them. The format flags still decide
+    /// what the lines carry; JSON lines are unaffected.
+    pub fn set_formatter_for(&mut self, level: LEVEL, formatter: &str) -> &mut Self {
+        let levels = self.levels.get_or_insert_with(|| std::array::from_fn(|_| None));
+        let (lo, _) = levels[level as usize - 1].get_or_insert_with(|| (LogOption::new(), String::new()));
+        lo.formatter = Some(formatter.to_string());
+        self
+    }
+
+    /// Rotates the default file as `cut` says, see `cut::CutSize::builder`,
+    /// `cut::CutTime::builder` and `cut::CutMixed::builder`.
+    pub fn set_cut(&mut self, cut: impl Into<CutConfig>) -> &mut Self {
+        // A file that cannot be opened went to the error handler.
+        let _ = self.set_default_file(Box::new(cut.into().option()));
+        self
+    }
+
+    pub fn set_cutmode_by_size(
+        &mut self,
+        filename: &str,
+        maxsize: u64,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &mut Self {
+        self.set_cut(CutSize::unchecked(filename, maxsize, maxbackups, compress))
+    }
+
+    /// `set_cutmode_by_size` with a size such as `"128MB"`, see `parse_size`.
+    pub fn set_cutmode_by_size_str(&mut self, filename: &str, maxsize: &str, maxbackups: u32, compress: bool) -> Result<&mut Self, ParseSizeError> {
+        let maxsize = crate::parse_size(maxsize)?;
+        Ok(self.set_cutmode_by_size(filename, maxsize, maxbackups, compress))
+    }
+
+    pub fn set_cutmode_by_time(
+        &mut self,
+        filename: &str,
+        mode: MODE,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &mut Self {
+        self.set_cut(CutTime::unchecked(filename, mode, maxbackups, compress))
+    }
+
+    /// Rotates the default file every `interval`, e.g. every 6 hours, see
+    /// `MODE::INTERVAL`; backups carry the start of their period.
+    pub fn set_cutmode_by_interval(&mut self, filename: &str, interval: Duration, maxbackups: u32, compress: bool) -> &mut Self {
+        self.set_cut(CutTime::unchecked(filename, MODE::INTERVAL(interval), maxbackups, compress))
+    }
+
+    /// Rotates the default file at the end of each `mode` period and
+    /// whenever it would pass `maxsize` bytes, see `CUTMODE::MIXED`.
+    pub fn set_cutmode_by_mixed(&mut self, filename: &str, mode: MODE, maxsize: u64, maxbackups: u32, compress: bool) -> &mut Self {
+        self.set_cut(CutMixed::unchecked(filename, mode, maxsize, maxbackups, compress))
+    }
+
+    /// Rotates the default file at the end of each `mode` period by writing
+    /// to the file the strftime `pattern` names for the period, such as
+    /// `logs/app-%Y-%m-%d.log`, instead of renaming one file, see
+    /// `CUTMODE::PATTERN`. A restart appends to the file of its period; the
+    /// file of a period over is compressed in place, and `maxbackups`
+    /// counts the files the pattern names. Errs with
+    /// `Error::InvalidTimeFormat` or `Error::InvalidCut` for a pattern
+    /// without a date in its file name or with one in its directories, and
+    /// with `Error::TestModeWithRotation` in test mode.
+    pub fn set_cutmode_by_time_pattern(&mut self, pattern: &str, mode: MODE, maxbackups: u32, compress: bool) -> Result<&mut Self, Error> {
+        check_file_pattern(pattern)?;
+        if self.testmode.is_some() {
+            return Err(Error::TestModeWithRotation(pattern.to_string()));
+        }
+        // A file that cannot be opened went to the error handler.
+        let _ = self.set_default_file(Box::new(FileOptionType::new(CUTMODE::PATTERN, mode, pattern, 0, maxbackups, compress)));
+        Ok(self)
+    }
+
+    /// Sets how `maxbackups` retention counts backups, for every file handler
+    /// configured so far and any configured later. Default: `PrunePolicy::ByFile`.
+    pub fn set_prune_policy(&mut self, policy: PrunePolicy) -> &mut Self {
+        self.filesettings.prune_policy = policy;
+        self.update_file_settings();
+        self
+    }
+
+    /// Keeps at most `n` backups of every file handler configured so far and
+    /// any configured later, time or size cut, compressed or not, in place
+    /// of their own `maxbackups`; the oldest go at rotation. 0 keeps them all.
+    pub fn set_max_backups(&mut self, n: u32) -> &mut Self {
+        self.filesettings.max_backups = Some(n);
+        self.update_file_settings();
+        self
+    }
+
+    /// Names the backups of every file handler configured so far and any
+    /// configured later after `template`, see `backupname`, in place of
+    /// `app_1.log` and `app_20240501_1.log`. Errs with
+    /// `Error::InvalidBackupTemplate` for a template `backupname` can't take
+    /// or whose backups could be named like a configured log file.
+    pub fn set_backup_name_template(&mut self, template: &str) -> Result<&mut Self, Error> {
+        let template = BackupTemplate::parse(template)?;
+        for filename in std::iter::once(&self.filehandle.0).chain(self.fmap.keys()) {
+            template.check_file(filename)?;
+        }
+        self.filesettings.backup_template = Some(template);
+        self.update_file_settings();
+        Ok(self)
+    }
+
+    /// At each rotation, also deletes the backups of the rotated file cut
+    /// more than `max_age` ago, by their modification time, for every file
+    /// handler configured so far and any configured later. A backup that
+    /// can't be deleted is reported as a `DeleteFailed` diagnostic.
+    pub fn set_backup_max_age(&mut self, max_age: Duration) -> &mut Self {
+        self.filesettings.backup_max_age = Some(max_age);
+        self.update_file_settings();
+        self
+    }
+
+    /// Sets the format and level (0-9, default 6) used for rotated backups
+    /// of handlers with compression enabled.
+    pub fn set_compression(&mut self, compress_type: CompressType, level: u32) -> &mut Self {
+        self.filesettings.compress_type = compress_type;
+        self.filesettings.compress_level = level.min(9);
+        self.update_file_settings();
+        self
+    }
+
+    /// Sets the level of `set_compression` alone: 0 (fastest) to 9
+    /// (smallest). Errs with `Error::InvalidCompressLevel` above 9.
+    pub fn set_compress_level(&mut self, level: u32) -> Result<&mut Self, Error> {
+        if level > 9 {
+            return Err(Error::InvalidCompressLevel(level));
+        }
+        self.filesettings.compress_level = level;
+        self.update_file_settings();
+        Ok(self)
+    }
+
+    /// Keeps each rotated backup as it is next to its `.gz`, for an
+    /// archiver to remove once it has it. The pair counts as one backup
+    /// for `maxbackups`, and a backup with its `.gz` is not compressed
+    /// again. Default: false.
+    pub fn set_compress_keep_original(&mut self, keep: bool) -> &mut Self {
+        self.filesettings.compress_keep_original = keep;
+        self.update_file_settings();
+        self
+    }
+
+    /// Leaves the newest `n` backups of a handler with compression raw,
+    /// for `grep` and `tail`; each rotation compresses those older, and
+    /// its event says `CompressDecision::Deferred`. 0, the default,
+    /// compresses every backup as it is cut.
+    pub fn set_compress_after(&mut self, n: u32) -> &mut Self {
+        self.filesettings.compress_after = n;
+        self.update_file_settings();
+        self
+    }
+
+    /// Gives the files created from now on, backups and their `.gz`
+    /// included, the unix permission bits `mode`, such as `0o640`, whatever
+    /// the umask. Files already there keep theirs. Does nothing off unix.
+    pub fn set_file_mode(&mut self, mode: u32) -> &mut Self {
+        self.filesettings.file_mode = Some(mode);
+        self.update_file_settings();
+        self
+    }
+
+    /// Lets several processes write the same files: each line is appended
+    /// under an exclusive advisory lock of the file (flock, LockFileEx),
+    /// whole, and a rotation first checks whether another process has
+    /// rotated the file already. The size cuts count the size of the file.
+    /// Every line then costs a lock, a stat and an unbuffered write, several
+    /// times a line of one process. `set_buffer_size` does not apply, and
+    /// a file with live compression is not locked.
+    pub fn set_shared_file(&mut self, shared: bool) -> &mut Self {
+        self.filesettings.shared = shared;
+        self.update_file_settings();
+        self
+    }
+
+    /// Writes the files compressed as they are logged, rather than raw and
+    /// then compressed at rotation, for captures too large to write twice.
+    /// A file `app.log` is written as `app.log.gz`, and each rotation ends
+    /// its gzip stream and moves it to a backup such as `app_1.log.gz`.
+    /// Lines reach the file at a sync flush, at the first write once
+    /// `flush_interval` has passed since the last one and when the logger is dropped; a reader such
+    /// as `compress::open_any` gets them up to there. Rotation by size
+    /// counts the bytes before compression. Uses the level of
+    /// `set_compression`.
+    pub fn set_live_compression(&mut self, compress_type: CompressType, flush_interval: Duration) -> &mut Self {
+        self.filesettings.live_compression = Some((compress_type, flush_interval));
+        self.update_file_settings();
+        self
+    }
+
+    /// Writes raw files again, see `set_live_compression`.
+    pub fn clear_live_compression(&mut self) -> &mut Self {
+        self.filesettings.live_compression = None;
+        self.update_file_settings();
+        self
+    }
+
+    /// Gathers up to `size` bytes of lines per file before writing them, for
+    /// fewer writes under load; 0, the default, writes every line. What a
+    /// file holds is written out before a rotation, a release or a close, at
+    /// `flush` and, with `set_flush_interval`, once it is that old. Files
+    /// with live compression, which buffers on its own, write every line.
+    pub fn set_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.filesettings.buffer_size = size;
+        self.update_file_settings();
+        self
+    }
+
+    /// Writes out the buffer of a file once its oldest line has waited
+    /// `interval`, see `set_buffer_size`; zero turns it off. Takes effect at
+    /// once for the open files too.
+    pub fn set_flush_interval(&mut self, interval: Duration) -> &mut Self {
+        self.flusher.set_interval((!interval.is_zero()).then_some(interval));
+        self
+    }
+
+    /// Skips compressing a rotated backup whose first 64 KiB compress to more
+    /// than `ratio` of their size, e.g. 0.9; the raw backup is kept instead.
+    pub fn set_compression_skip_ratio(&mut self, ratio: f64) -> &mut Self {
+        self.filesettings.compress_skip_ratio = Some(ratio);
+        self.update_file_settings();
+        self
+    }
+
+    /// Compresses a rotated backup only with at least `factor` times its size
+    /// free on its filesystem; otherwise the raw backup is kept. Default: 1.0.
+    pub fn set_compression_space_factor(&mut self, factor: f64) -> &mut Self {
+        self.filesettings.compress_space_factor = factor;
+        self.update_file_settings();
+        self
+    }
+
+    /// Replaces the free-space probe of the compression preflight, e.g. in tests.
+    pub fn set_space_probe(&mut self, probe: fn(&Path) -> io::Result<u64>) -> &mut Self {
+        self.filesettings.space_probe = probe;
+        self.update_file_settings();
+        self
+    }
+
+    /// Called after each rotation, once compression and pruning are done.
+    pub fn set_rotation_handler(&mut self, handler: fn(&RotationEvent)) -> &mut Self {
+        self.filesettings.rotation_handler = Some(handler);
+        self.update_file_settings();
+        self
+    }
+
+    /// Called with every failed write, rotation, compression and backup
+    /// deletion of the files, instead of the notices on stderr, see
+    /// `logerror`.
+    pub fn set_error_handler(&mut self, handler: ErrorHandler) -> &mut Self {
+        self.filesettings.errors.set(Some(handler));
+        self
+    }
+
+    /// Goes back to the notices on stderr.
+    pub fn clear_error_handler(&mut self) -> &mut Self {
+        self.filesettings.errors.set(None);
+        self
+    }
+
+    /// Ends every line written to a file with an HMAC chained from the line
+    /// before, so edits can be detected with `tklog::verify::chain`. Lines
+    /// a file gets are one record each, terminated with a newline.
+    pub fn set_tamper_evidence(&mut self, key: &[u8]) -> &mut Self {
+        self.filesettings.tamper_key = Some(TamperKey::new(key));
+        self.update_file_settings();
+        self
+    }
+
+    fn update_file_settings(&mut self) {
+        self.filehandle.1.set_file_settings(&self.filesettings);
+        for fh in self.fmap.values_mut() {
+            fh.set_file_settings(&self.filesettings);
+        }
+        if let Some(d) = self.directory.as_mut() {
+            d.close_all();
+        }
+    }
+
+    /// Sends the lines that would go to the default file to one file per
+    /// module and day under `root`, see `directory`. Module and level
+    /// options that name a file still win.
+    pub fn set_directory_mode(&mut self, root: PathBuf, layout: DirLayout) -> &mut Self {
+        self.directory = Some(Directory::new(root, layout));
+        self
+    }
+
+    /// Keeps the directory files of the last `days` days, deleting older
+    /// ones across the tree; 0, the default, keeps everything. Applies to
+    /// the directory of `set_directory_mode`, which must be set first.
+    pub fn set_directory_retention(&mut self, days: u32) -> &mut Self {
+        match self.directory.as_mut() {
+            Some(d) => d.set_retention(days),
+            None => diagnostics::report(Category::Config, None, "set_directory_retention without set_directory_mode is ignored".to_string()),
+        }
+        self
+    }
+
+    pub fn clear_directory_mode(&mut self) -> &mut Self {
+        self.directory = None;
+        self
+    }
+
+    pub fn set_option(&mut self, option: LogOption) -> &mut Self {
+        if let Some(v) = option.console {
+            self.fmthandle.set_console(v);
+        }
+        if let Some(v) = option.format {
+            self.fmthandle.set_format(v);
+        }
+        if let Some(v) = option.formatter {
+            self.fmthandle.set_formatter(v);
+        }
+        if let Some(v) = option.level {
+            self.fmthandle.set_level(v);
+        }
+        if let Some(v) = option.fileoption {
+            let _ = self.set_default_file(v);
+        }
+
+        self
+    }
+
+    /// The options of the lines of `module` and of the modules under it:
+    /// the longest pattern matching a module path wins, `app::db` matching
+    /// `app::db::pool` too and `app::db::*` only the modules under
+    /// `app::db`. The separator of `set_mod_separator` is kept.
+    pub fn set_mod_option(&mut self, module: &str, option: LogOption) -> &mut Self {
+        let mut filename = "".to_string();
+        if let Some(v) = option.fileoption {
+            match self.module_file(&format!("module `{}`", module), v) {
+                Ok(f) => filename = f,
+                Err(e) if fd_exhausted(&e) => diagnostics::report(Category::FdExhausted, None, format!("out of file descriptors ({}); module {} logs to the default file", e, module)),
+                Err(_) => {}
+            }
+        }
+        let separator = self.modmap.get_pattern_mut(module).and_then(|(lo, _)| lo.separator.take());
+        self.modmap.insert(
+            module,
+            (
+                LogOptionConst {
+                    level: option.level,
+                    format: option.format,
+                    formatter: option.formatter,
+                    console: option.console,
+                    separator,
+                },
+                filename.clone(),
+            ),
+        );
+        self
+    }
+
+    /// The level overrides set with `set_mod_option`, sorted by pattern.
+    pub fn module_levels(&self) -> Vec<(String, LEVEL)> {
+        self.modmap.entries().into_iter().filter_map(|(pattern, (lo, _))| lo.level.map(|l| (pattern, l))).collect()
+    }
+
+    /// The lowest level of any module, the one the `log` facade filters by.
+    pub(crate) fn lowest_level(&self) -> LEVEL {
+        let lowest = self.module_levels().into_iter().fold(self.fmthandle.get_level(), |lowest, (_, l)| if l < lowest { l } else { lowest });
+        self.output_levels.lowest(lowest, self.fmthandle.get_console())
+    }
+
+    /// Sets the level of the modules `pattern` matches, as `set_mod_option`,
+    /// keeping the other options of `pattern`.
+    pub fn set_mod_level(&mut self, pattern: &str, level: LEVEL) -> &mut Self {
+        levelspec::set_level(&mut self.modmap, pattern, level);
+        self
+    }
+
+    /// Sets the separator the macros put between their arguments on the
+    /// lines of the modules `pattern` matches, as `set_mod_option`, keeping
+    /// the other options of `pattern`.
+    pub fn set_mod_separator(&mut self, pattern: &str, separator: &str) -> &mut Self {
+        levelspec::set_separator(&mut self.modmap, pattern, separator);
+        self
+    }
+
+    /// Drops the level override of `pattern`, keeping its other options;
+    /// false if it had none.
+    pub fn clear_module_level(&mut self, pattern: &str) -> bool {
+        levelspec::clear_level(&mut self.modmap, pattern)
+    }
+
+    /// Sets the levels of the spec in the variable `var`, such as
+    /// `info,my_crate::db=debug`, see `levelspec`. Call again to read it
+    /// anew.
+    pub fn set_level_from_env(&mut self, var: &str) -> &mut Self {
+        if let Some(level) = LevelSpec::from_env(var).and_then(|spec| spec.apply(&mut self.modmap, &mut self.env_modules)) {
+            self.set_level(level);
+        }
+        self
+    }
+
+    /// Adds a filter: a line is written only when every filter passes it,
+    /// see `filter`.
+    pub fn set_filter(&mut self, filter: Filter) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn clear_filters(&mut self) -> &mut Self {
+        self.filters.clear();
+        self
+    }
+
+    /// Adds a hook called with the level, module and body of every line,
+    /// see `hook`.
+    pub fn add_hook(&mut self, hook: Hook) -> &mut Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// The captures of this logger, see `capture`.
+    pub(crate) fn captures(&self) -> &Captures {
+        &self.captures
+    }
+
+    pub fn remove_hooks(&mut self) -> &mut Self {
+        self.hooks.clear();
+        self
+    }
+
+    pub fn set_custom_handler(&mut self, handler: fn(&LogContext) -> bool) -> &mut Self {
+        self.custom_handler = Some(handler);
+        self.custom_panics.reset();
+        self
+    }
+
+    pub fn set_level_option(&mut self, level: LEVEL, option: &dyn OptionTrait) -> &mut Self {
+        let mut filename = "".to_string();
+        if let Some(v) = option.get_fileoption() {
+            filename = self.module_file(&format!("level {:?}", level), v).unwrap_or_default();
+        }
+        let lo = LogOption {
+            level: None,
+            format: option.get_format(),
+            formatter: option.get_formatter(),
+            console: option.get_console(),
+            fileoption: option.get_fileoption(),
+        };
+
+        if self.levels.is_none() {
+            self.levels = Some(std::array::from_fn(|_| None));
+        }
+
+        if let Some(levels) = &mut self.levels {
+            levels[level as usize - 1] = Some((lo, filename));
+        }
+
+        self
+    }
+
+    pub fn set_separator(&mut self, separator: &str) -> &mut Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    pub fn get_separator(&self) -> String {
+        self.separator.clone()
+    }
+
+    /// The message of the macros, `args` joined with the separator of
+    /// `module`, see `crate::message_of`.
+    #[doc(hidden)]
+    pub fn message_of(&self, module: &str, args: &[&dyn std::fmt::Display]) -> String {
+        crate::with_error_backtraces(self.error_backtraces, || crate::message_of(self.separator(module), args))
+    }
+
+    /// The separator of the lines of `module`, for the macros.
+    #[doc(hidden)]
+    pub fn separator(&self, module: &str) -> &str {
+        if self.modmap.len() > 0 {
+            if let Some(separator) = self.modmap.lookup(module).and_then(|(lo, _)| lo.separator.as_deref()) {
+                return separator;
+            }
+        }
+        &self.separator
+    }
+
+    // pub fn set_levelfmt<F>(&mut self, levelfmt: F)
+    // where
+    //     F: Fn(LEVEL) -> String + Send + Sync + 'static,
+    // {
+    //     self.levelfmt = Some(Box::new(levelfmt));
+    // }
+
+    // pub fn set_timefmt<F>(&mut self, timefmt: F)
+    // where
+    //     F: Fn() -> (String, String, String) + Send + Sync + 'static,
+    // {
+    //     self.timefmt = Some(Box::new(timefmt));
+    // }
+
+    pub fn set_attr_format<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut AttrFormat) + Send + Sync + 'static,
+    {
+        f(&mut self.render.attrfmt);
+    }
+
+    /// Renders the time of text lines, in `{time}` and the default layout,
+    /// as the chrono strftime `format` whenever `Format` asks for any of
+    /// the date, time or microseconds. A `set_time_fmt` comes first; backup
+    /// names keep their own stamps. Errs with `Error::InvalidTimeFormat`
+    /// for a format chrono can't read.
+    pub fn set_time_format(&mut self, format: &str) -> Result<&mut Self, Error> {
+        self.render.time_format = Some(check_time_format(format)?);
+        Ok(self)
+    }
+
+    /// `set_time_format` with RFC 3339 to the microsecond, such as
+    /// `2024-05-01T12:00:00.123456+08:00`.
+    pub fn set_time_rfc3339(&mut self) -> &mut Self {
+        self.render.time_format = Some(RFC3339_TIME_FORMAT.to_string());
+        self
+    }
+
+    /// Lays the default layout out as `columns` in their order, leaving
+    /// out the columns not in it, such as `[TIME, LOGFLAG, MESSAGE]`. The
+    /// `Format` flags still say what each column holds, and a column they
+    /// leave empty is skipped along with its space; `COLON` is written
+    /// only after a column that was. A formatter or preset lays lines out
+    /// instead. Errs with `Error::InvalidColumnOrder` unless `MESSAGE` is
+    /// in `columns` once and no column twice.
+    pub fn set_column_order(&mut self, columns: &[COLUMN]) -> Result<&mut Self, Error> {
+        self.render.columns = Some(check_column_order(columns)?);
+        Ok(self)
+    }
+
+    /// Lays lines out in the default column order again.
+    pub fn clear_column_order(&mut self) -> &mut Self {
+        self.render.columns = None;
+        self
+    }
+
+    /// Returns a comparable snapshot of the effective configuration.
+    pub fn config(&self) -> LogConfig {
+        LogConfig {
+            level: self.fmthandle.get_level(),
+            console: self.fmthandle.get_console(),
+            format: self.fmthandle.get_format(),
+            formatter: self.fmthandle.get_formatter().cloned(),
+            separator: self.separator.clone(),
+            printmode: self.mode,
+            modules: self.module_levels(),
+            file: self.filehandle.1.file_config(),
+            remote: self.remote.as_ref().map(|r| r.config().transport.to_string()),
+        }
+    }
+
+    /// Applies `config` and logs one Info line describing what changed,
+    /// e.g. `level: Info→Debug, backups: 7→30`. A no-op apply logs nothing.
+    pub fn apply_config(&mut self, config: LogConfig) -> &mut Self {
+        let current = self.config();
+        let changes = current.diff(&config);
+        if changes.is_empty() {
+            return self;
+        }
+        self.fmthandle.set_level(config.level);
+        self.fmthandle.set_console(config.console);
+        self.fmthandle.set_format(config.format);
+        match config.formatter {
+            Some(f) => self.fmthandle.set_formatter(f),
+            None => self.fmthandle.clear_formatter(),
+        }
+        self.separator = config.separator;
+        self.mode = config.printmode;
+        if current.file != config.file {
+            match &config.file {
+                Some(fc) => {
+                    let _ = self.set_default_file(Box::new(FileOptionType::from_config(fc)));
+                }
+                None => self.filehandle = ("".to_string(), FHandler::new()),
+            }
+        }
+        if current.modules != config.modules {
+            for (pattern, _) in &current.modules {
+                levelspec::clear_level(&mut self.modmap, pattern);
+            }
+            for (pattern, level) in &config.modules {
+                levelspec::set_level(&mut self.modmap, pattern, *level);
+            }
+        }
+        if current.remote != config.remote {
+            match Remote::reconfigured(self.remote.as_ref(), config.remote.as_deref()) {
+                Some(remote) => self.set_remote(remote),
+                None => self.clear_remote(),
+            };
+        }
+        self.log_internal(LEVEL::Info, describe_changes(&changes));
+        self
+    }
+
+    /// A logger set up by the config file at `path`, see
+    /// `LogConfig::apply_text`.
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut log = Self::new();
+        log.load_config_file(path)?;
+        Ok(log)
+    }
+
+    /// Applies the config file at `path`, see `LogConfig::apply_text`. A
+    /// file it can't take is refused whole, leaving the configuration as
+    /// it was.
+    pub fn load_config_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, Error> {
+        let config = self.config().apply_file(path)?;
+        Ok(self.apply_config(config))
+    }
+
+    /// Emits a line produced by tklog itself, attributed to module `tklog`.
+    fn log_internal(&mut self, level: LEVEL, message: String) {
+        if self.get_level("tklog") <= level {
+            let s = self.fmt("tklog", level, "", 0, message);
+            if !s.is_empty() {
+                self.print(level, "tklog", s);
+            }
+        }
+    }
+
+    /// Makes `option` the default file; a module file already open on its
+    /// path with the same settings is taken over instead. A conflicting
+    /// one leaves the default file as it is, see `check_paths`.
+    fn set_default_file(&mut self, option: Box<dyn FileOption>) -> io::Result<()> {
+        match self.paths.claim(DEFAULT_FILE, &*option) {
+            Claim::Conflict => return Ok(()),
+            Claim::Shared(filename) => {
+                if let Some(fh) = self.fmap.remove(&filename) {
+                    self.filehandle = (filename, fh);
+                    return Ok(());
+                }
+            }
+            Claim::New => {}
+        }
+        let f = self.new_filehandler(option).inspect_err(|_| self.paths.release(DEFAULT_FILE))?;
+        self.filehandle.0 = f.get_file_name();
+        self.filehandle.1.set_file_handler(f);
+        Ok(())
+    }
+
+    /// The file name `owner` writes under: the handler open on the path of
+    /// `option` when the settings match, else a new one. Empty, which is
+    /// the default file, when it conflicts with another handler.
+    fn module_file(&mut self, owner: &str, option: Box<dyn FileOption>) -> io::Result<String> {
+        match self.paths.claim(owner, &*option) {
+            Claim::Conflict => return Ok(String::new()),
+            Claim::Shared(filename) => return Ok(filename),
+            Claim::New => {}
+        }
+        let f = self.new_filehandler(option).inspect_err(|_| self.paths.release(owner))?;
+        let filename = f.get_file_name();
+        if filename != self.filehandle.0 && !self.fmap.contains_key(&filename) {
+            let mut fhandler = FHandler::new();
+            fhandler.set_file_handler(f);
+            self.fmap.insert(filename.clone(), fhandler);
+        }
+        Ok(filename)
+    }
+
+    /// `Error::PathConflict` for the first file handler refused because
+    /// its path is already written with other cut settings, see `paths`.
+    pub fn check_paths(&self) -> Result<(), Error> {
+        self.paths.check()
+    }
+
+    /// Opens the file of `option`. Out of file descriptors, the module files
+    /// are closed to make room and the open is retried once. A failure goes
+    /// to the error handler.
+    fn new_filehandler(&mut self, option: Box<dyn FileOption>) -> io::Result<FileHandler> {
+        if self.testmode.is_some() && handle::rotates(&*option) {
+            let e = io::Error::other("refused in test mode: the file rotates");
+            self.filesettings.errors.report(LogError::OpenFailed(option.filename().into(), logerror::copy(&e)));
+            return Err(e);
+        }
+        let opened = match FileHandler::open(&*option, self.filesettings.file_mode) {
+            Err(e) if fd_exhausted(&e) => {
+                Self::reclaim_descriptors(&mut self.fmap, "");
+                FileHandler::open(&*option, self.filesettings.file_mode)
+            }
+            r => r,
+        };
+        let mut f = opened.inspect_err(|e| self.filesettings.errors.report(LogError::OpenFailed(option.filename().into(), logerror::copy(e))))?;
+        f.set_settings(self.filesettings.clone());
+        f.schedule(&self.scheduler);
+        f.flush_with(&self.flusher);
+        Ok(f)
+    }
+
+    /// Freezes the rendered time and restarts `{seq}` at `fixed_seq_start`.
+    /// **Test only**: refused while any file handler with rotation is active,
+    /// and while it is on, a rotating file is refused in turn: it goes to the
+    /// error handler as `LogError::OpenFailed` and the logger keeps its files.
+    pub fn set_test_mode(&mut self, mode: TestMode) -> Result<&mut Self, Error> {
+        if let Some(filename) = self.filehandle.1.rotating_file() {
+            return Err(Error::TestModeWithRotation(filename));
+        }
+        for fh in self.fmap.values().chain(self.tees.iter().map(|(_, fh)| fh)) {
+            if let Some(filename) = fh.rotating_file() {
+                return Err(Error::TestModeWithRotation(filename));
+            }
+        }
+        self.seq = mode.fixed_seq_start;
+        self.testmode = Some(mode);
+        Ok(self)
+    }
+
+    pub fn clear_test_mode(&mut self) -> &mut Self {
+        self.testmode = None;
+        self
+    }
+}
+
+pub struct Log;
+
+impl Log {
+    pub fn new() -> Self {
+        Log {}
+    }
+    pub fn set_printmode(&self, mode: PRINTMODE) -> &Self {
+        // The queue is written through the global logger: waited for unlocked.
+        let wait = {
+            let log = global();
+            (log.mode == PRINTMODE::DELAY && mode != PRINTMODE::DELAY).then(|| log.mark_queue()).flatten()
+        };
+        if let Some(wait) = wait {
+            let _ = wait.recv();
+        }
+        global().set_printmode(mode);
+        self
+    }
+
+    pub fn set_delay_batch(&self, lines: usize) -> &Self {
+        global().set_delay_batch(lines);
+        self
+    }
+
+    pub fn set_delay_max_latency(&self, latency: Duration) -> &Self {
+        global().set_delay_max_latency(latency);
+        self
+    }
+
+    pub fn pending_records(&self) -> u64 {
+        global().pending_records()
+    }
+
+    pub fn set_level(&self, level: LEVEL) -> &Self {
+        let mut log = global();
+        log.set_level(level);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
+        self
+    }
+
+    pub fn set_console(&self, console: bool) -> &Self {
+        let mut log = global();
+        log.set_console(console);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
+        self
+    }
+
+    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
+    pub fn set_format(&self, format: u8) -> &Self {
+        global().set_format(format);
+        self
+    }
+
+    /** default: "{level}{time} {file}:{message}\n" */
+    pub fn set_formatter(&self, formatter: &str) -> &Self {
+        global().set_formatter(formatter);
+        self
+    }
+
+    pub fn set_formatter_for(&self, level: LEVEL, formatter: &str) -> &Self {
+        global().set_formatter_for(level, formatter);
+        self
+    }
+
+    pub fn set_cut(&self, cut: impl Into<CutConfig>) -> &Self {
+        global().set_cut(cut);
+        self
+    }
+
+    pub fn set_cutmode_by_size(
+        &self,
+        filename: &str,
+        maxsize: u64,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &Self {
+        global().set_cutmode_by_size(filename, maxsize, maxbackups, compress);
+        self
+    }
+
+    pub fn set_cutmode_by_size_str(&self, filename: &str, maxsize: &str, maxbackups: u32, compress: bool) -> Result<&Self, ParseSizeError> {
+        global().set_cutmode_by_size_str(filename, maxsize, maxbackups, compress)?;
+        Ok(self)
+    }
+
+    pub fn set_cutmode_by_time(
+        &self,
+        filename: &str,
+        mode: MODE,
+        maxbackups: u32,
+        compress: bool,
+    ) -> &Self {
+        global().set_cutmode_by_time(filename, mode, maxbackups, compress);
+        self
+    }
+
+    pub fn set_cutmode_by_interval(&self, filename: &str, interval: Duration, maxbackups: u32, compress: bool) -> &Self {
+        global().set_cutmode_by_interval(filename, interval, maxbackups, compress);
+        self
+    }
+
+    pub fn set_cutmode_by_mixed(&self, filename: &str, mode: MODE, maxsize: u64, maxbackups: u32, compress: bool) -> &Self {
+        global().set_cutmode_by_mixed(filename, mode, maxsize, maxbackups, compress);
+        self
+    }
+
+    pub fn set_cutmode_by_time_pattern(&self, pattern: &str, mode: MODE, maxbackups: u32, compress: bool) -> Result<&Self, Error> {
+        global().set_cutmode_by_time_pattern(pattern, mode, maxbackups, compress)?;
+        Ok(self)
+    }
+
+    pub fn set_prune_policy(&self, policy: PrunePolicy) -> &Self {
+        global().set_prune_policy(policy);
+        self
+    }
+
+    pub fn set_max_backups(&self, n: u32) -> &Self {
+        global().set_max_backups(n);
+        self
+    }
+
+    pub fn set_backup_name_template(&self, template: &str) -> Result<&Self, Error> {
+        global().set_backup_name_template(template)?;
+        Ok(self)
+    }
+
+    pub fn set_backup_max_age(&self, max_age: Duration) -> &Self {
+        global().set_backup_max_age(max_age);
+        self
+    }
+
+    pub fn health(&self) -> Health {
+        global().health()
+    }
+
+    pub fn try_recover(&self) -> Result<(), Error> {
+        global().try_recover()
+    }
+
+    pub fn reopen(&self) -> io::Result<()> {
+        global().reopen()
+    }
+
+    /// `Logger::set_log_path`, once the lines queued so far are written to
+    /// the old file.
+    pub fn set_log_path(&self, path: &str, rotate_old: bool) -> Result<&Self, Error> {
+        let wait = global().mark_queue();
+        if let Some(wait) = wait {
+            let _ = wait.recv();
+        }
+        global().set_log_path(path, rotate_old)?;
+        Ok(self)
+    }
+
+    #[cfg(all(unix, feature = "signal"))]
+    pub fn enable_reopen_on_sighup(&self) -> io::Result<&Self> {
+        global().enable_reopen_on_sighup()?;
+        Ok(self)
+    }
+
+    /// Waits until the lines queued so far are written, then syncs the
+    /// files to disk, see `Logger::flush`.
+    pub fn flush(&self) {
+        let wait = global().mark_queue();
+        if let Some(wait) = wait {
+            let _ = wait.recv();
+        }
+        global().flush();
+    }
+
+    pub fn set_compression(&self, compress_type: CompressType, level: u32) -> &Self {
+        global().set_compression(compress_type, level);
+        self
+    }
+
+    pub fn set_compress_level(&self, level: u32) -> Result<&Self, Error> {
+        global().set_compress_level(level)?;
+        Ok(self)
+    }
+
+    pub fn set_compress_keep_original(&self, keep: bool) -> &Self {
+        global().set_compress_keep_original(keep);
+        self
+    }
+
+    pub fn set_compress_after(&self, n: u32) -> &Self {
+        global().set_compress_after(n);
+        self
+    }
+
+    pub fn set_file_mode(&self, mode: u32) -> &Self {
+        global().set_file_mode(mode);
+        self
+    }
+
+    pub fn set_shared_file(&self, shared: bool) -> &Self {
+        global().set_shared_file(shared);
+        self
+    }
+
+    pub fn set_live_compression(&self, compress_type: CompressType, flush_interval: Duration) -> &Self {
+        global().set_live_compression(compress_type, flush_interval);
+        self
+    }
+
+    pub fn clear_live_compression(&self) -> &Self {
+        global().clear_live_compression();
+        self
+    }
+
+    pub fn set_buffer_size(&self, size: usize) -> &Self {
+        global().set_buffer_size(size);
+        self
+    }
+
+    pub fn set_flush_interval(&self, interval: Duration) -> &Self {
+        global().set_flush_interval(interval);
+        self
+    }
+
+    pub fn set_compression_skip_ratio(&self, ratio: f64) -> &Self {
+        global().set_compression_skip_ratio(ratio);
+        self
+    }
+
+    pub fn set_compression_space_factor(&self, factor: f64) -> &Self {
+        global().set_compression_space_factor(factor);
+        self
+    }
+
+    pub fn set_space_probe(&self, probe: fn(&Path) -> io::Result<u64>) -> &Self {
+        global().set_space_probe(probe);
+        self
+    }
+
+    pub fn set_tamper_evidence(&self, key: &[u8]) -> &Self {
+        global().set_tamper_evidence(key);
+        self
+    }
+
+    pub fn set_rotation_handler(&self, handler: fn(&RotationEvent)) -> &Self {
+        global().set_rotation_handler(handler);
+        self
+    }
+
+    pub fn set_error_handler(&self, handler: ErrorHandler) -> &Self {
+        global().set_error_handler(handler);
+        self
+    }
+
+    pub fn clear_error_handler(&self) -> &Self {
+        global().clear_error_handler();
+        self
+    }
+
+    pub fn set_latency_sampling(&self, n: u64) -> &Self {
+        global().set_latency_sampling(n);
+        self
+    }
+
+    pub fn stats(&self) -> LogStats {
+        global().stats()
+    }
+
+    pub fn metrics(&self) -> LogMetrics {
+        global().metrics()
+    }
+
+    pub fn reset_metrics(&self) {
+        global().reset_metrics();
+    }
+
+    pub fn describe(&self) -> String {
+        global().describe()
+    }
+
+    pub fn set_routing<F: FnOnce(&mut Routes)>(&self, f: F) -> Result<&Self, Error> {
+        global().set_routing(f)?;
+        Ok(self)
+    }
+
+    pub fn clear_routing(&self) -> &Self {
+        global().clear_routing();
+        self
+    }
+
+    pub fn add_level_file(&self, levels: impl LevelSet, cut: impl Into<CutConfig>) -> &Self {
+        global().add_level_file(levels, cut);
+        self
+    }
+
+    pub fn clear_level_files(&self) -> &Self {
+        global().clear_level_files();
+        self
+    }
+
+    pub fn set_directory_mode(&self, root: PathBuf, layout: DirLayout) -> &Self {
+        global().set_directory_mode(root, layout);
+        self
+    }
+
+    pub fn set_directory_retention(&self, days: u32) -> &Self {
+        global().set_directory_retention(days);
+        self
+    }
+
+    pub fn clear_directory_mode(&self) -> &Self {
+        global().clear_directory_mode();
+        self
+    }
+
+    pub fn set_output_mode(&self, sink: OutputSink, mode: OutputMode) -> Result<&Self, Error> {
+        global().set_output_mode(sink, mode)?;
+        Ok(self)
+    }
+
+    pub fn clear_output_mode(&self, sink: OutputSink) -> &Self {
+        global().clear_output_mode(sink);
+        self
+    }
+
+    pub fn set_output_level(&self, sink: OutputSink, level: LEVEL) -> &Self {
+        let mut log = global();
+        log.set_output_level(sink, level);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
+        self
+    }
+
+    pub fn clear_output_level(&self, sink: OutputSink) -> &Self {
+        let mut log = global();
+        log.clear_output_level(sink);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
+        self
+    }
+
+    pub fn output_level(&self, sink: OutputSink) -> Option<LEVEL> {
+        global().output_level(sink)
+    }
+
+    pub fn validate(&self) -> Result<Vec<(OutputSink, OutputMode)>, Error> {
+        global().validate()
+    }
+
+    pub fn set_event_registry(&self, events: &[(&str, &str)]) -> &Self {
+        global().set_event_registry(events);
+        self
+    }
+
+    pub fn event_description(&self, id: &str) -> Option<String> {
+        global().event_description(id)
+    }
+
+    pub fn add_file_sink(&self, name: &str, option: impl FileOption + 'static) -> &Self {
+        global().add_file_sink(name, option);
+        self
+    }
+
+    pub fn set_rotation_group(&self, handler_ids: &[&str]) -> Result<&Self, Error> {
+        global().set_rotation_group(handler_ids)?;
+        Ok(self)
+    }
+
+    pub fn set_rotation_group_with(&self, handler_ids: &[&str], empty_backups: bool) -> Result<&Self, Error> {
+        global().set_rotation_group_with(handler_ids, empty_backups)?;
+        Ok(self)
+    }
+
+    pub fn clear_rotation_groups(&self) -> &Self {
+        global().clear_rotation_groups();
+        self
+    }
+
+    pub fn add_tee_file(&self, mode: OutputMode, cut: impl Into<CutConfig>) -> Result<&Self, Error> {
+        global().add_tee_file(mode, cut)?;
+        Ok(self)
+    }
+
+    pub fn clear_tee_files(&self) -> &Self {
+        global().clear_tee_files();
+        self
+    }
+
+    pub fn set_custom_sink(&self, sink: Box<dyn LogSink>) -> &Self {
+        global().set_custom_sink(sink);
+        self
+    }
+
+    pub fn set_custom_sink_only(&self, on: bool) -> &Self {
+        global().set_custom_sink_only(on);
+        self
+    }
+
+    pub fn clear_custom_sink(&self) -> &Self {
+        global().clear_custom_sink();
+        self
+    }
+
+    pub fn set_handler_quota(&self, handler_id: &str, bytes_per_day: u64) -> &Self {
+        global().set_handler_quota(handler_id, bytes_per_day);
+        self
+    }
+
+    pub fn set_storm_control(&self, config: StormConfig) -> &Self {
+        global().set_storm_control(config);
+        self
+    }
+
+    pub fn trace_callers(&self, message: &str, sample_rate: u64, duration: Duration) -> &Self {
+        global().trace_callers(message, sample_rate, duration);
+        self
+    }
+
+    pub fn stop_trace_callers(&self) -> &Self {
+        global().stop_trace_callers();
+        self
+    }
+
+    pub fn set_dynamic_fields(&self, f: DynamicFields) -> &Self {
+        global().set_dynamic_fields(f);
+        self
+    }
+
+    pub fn clear_dynamic_fields(&self) -> &Self {
+        global().clear_dynamic_fields();
+        self
+    }
+
+    pub fn set_context_fields(&self, on: bool) -> &Self {
+        global().set_context_fields(on);
+        self
+    }
+
+    pub fn set_max_fields(&self, n: usize) -> &Self {
+        global().set_max_fields(n);
+        self
+    }
+
+    pub fn set_max_field_value_len(&self, len: usize) -> &Self {
+        global().set_max_field_value_len(len);
+        self
+    }
+
+    pub fn set_record_formatter(&self, f: RecordFormatter) -> &Self {
+        global().set_record_formatter(f);
+        self
+    }
+
+    pub fn set_format_json(&self, on: bool) -> &Self {
+        global().set_format_json(on);
+        self
+    }
+
+    pub fn clear_record_formatter(&self) -> &Self {
+        global().clear_record_formatter();
+        self
+    }
+
+    pub fn set_console_formatter_impl(&self, f: impl LogFormatter + 'static) -> &Self {
+        global().set_console_formatter_impl(f);
+        self
+    }
+
+    pub fn clear_console_formatter(&self) -> &Self {
+        global().clear_console_formatter();
+        self
+    }
+
+    pub fn set_time_subseq(&self, on: bool) -> &Self {
+        global().set_time_subseq(on);
+        self
+    }
+
+    pub fn set_include_boot_id(&self, on: bool) -> &Self {
+        global().set_include_boot_id(on);
+        self
+    }
+
+    pub fn set_function_with_module(&self, on: bool) -> &Self {
+        global().set_function_with_module(on);
+        self
+    }
+
+    pub fn set_error_backtraces(&self, on: bool) -> &Self {
+        global().set_error_backtraces(on);
+        self
+    }
+
+    pub fn set_location_strategy(&self, strategy: LocationStrategy) -> &Self {
+        global().set_location_strategy(strategy);
+        self
+    }
+
+    pub fn allow_ansi_in_files(&self, allow: bool) -> &Self {
+        global().allow_ansi_in_files(allow);
+        self
+    }
+
+    pub fn set_level_label(&self, level: LEVEL, label: &str) -> &Self {
+        global().set_level_label(level, label);
+        self
+    }
+
+    pub fn clear_level_labels(&self) -> &Self {
+        global().clear_level_labels();
+        self
+    }
+
+    pub fn set_console_color(&self, on: bool) -> &Self {
+        global().set_console_color(on);
+        self
+    }
+
+    pub fn set_console_color_options(&self, opts: ColorOptions) -> &Self {
+        global().set_console_color_options(opts);
+        self
+    }
+
+    pub fn set_console_stream(&self, stream: ConsoleStream) -> &Self {
+        global().set_console_stream(stream);
+        self
+    }
+
+    pub fn check_paths(&self) -> Result<(), Error> {
+        global().check_paths()
+    }
+
+    pub fn set_json_schema_version(&self, version: u32) -> Result<&Self, Error> {
+        global().set_json_schema_version(version)?;
+        Ok(self)
+    }
+
+    pub fn preset_k8s(&self) -> &Self {
+        global().preset_k8s();
+        self
+    }
+
+    pub fn preset_systemd(&self) -> &Self {
+        global().preset_systemd();
+        self
+    }
+
+    pub fn set_auto_trace_ids(&self, on: bool) -> &Self {
+        global().set_auto_trace_ids(on);
         self
     }
 
-    pub fn set_console(&mut self, console: bool) -> &mut Self {
-        self.fmthandle.set_console(console);
+    pub fn set_remote(&self, config: RemoteConfig) -> &Self {
+        global().set_remote(config);
         self
     }
 
-    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
-    pub fn set_format(&mut self, format: u8) -> &mut Self {
-        self.fmthandle.set_format(format);
+    pub fn clear_remote(&self) -> &Self {
+        global().clear_remote();
         self
     }
 
-    /** default: "{level}{time} {file}:{message}\n" */
-    pub fn set_formatter(&mut self, formatter: &str) -> &mut Self {
-        self.fmthandle.set_formatter(formatter.to_string());
-        self
+    pub fn remote_dropped(&self) -> u64 {
+        global().remote_dropped()
     }
 
-    pub fn set_cutmode_by_size(
-        &mut self,
-        filename: &str,
-        maxsize: u64,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &mut Self {
-        let fsm = FileOptionType::new(
-            crate::CUTMODE::SIZE,
-            MODE::DAY,
-            filename,
-            maxsize,
-            maxbackups,
-            compress,
-        );
-        let fh = FileHandler::new(Box::new(fsm));
-        self.filehandle.0 = filename.to_string();
-        self.filehandle.1.set_file_handler(fh.unwrap());
+    #[cfg(feature = "journald")]
+    pub fn set_journald(&self, on: bool) -> &Self {
+        global().set_journald(on);
         self
     }
 
-    pub fn set_cutmode_by_time(
-        &mut self,
-        filename: &str,
-        mode: MODE,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &mut Self {
-        let ftm = FileOptionType::new(
-            crate::CUTMODE::TIME,
-            mode,
-            filename,
-            0,
-            maxbackups,
-            compress,
-        );
-        let fh = FileHandler::new(Box::new(ftm));
-        self.filehandle.0 = filename.to_string();
-        self.filehandle.1.set_file_handler(fh.unwrap());
+    #[cfg(feature = "journald")]
+    pub fn set_journald_socket(&self, socket: Option<PathBuf>) -> &Self {
+        global().set_journald_socket(socket);
         self
     }
 
-    pub fn set_option(&mut self, option: LogOption) -> &mut Self {
-        if let Some(v) = option.console {
-            self.fmthandle.set_console(v);
-        }
-        if let Some(v) = option.format {
-            self.fmthandle.set_format(v);
-        }
-        if let Some(v) = option.formatter {
-            self.fmthandle.set_formatter(v);
-        }
-        if let Some(v) = option.level {
-            self.fmthandle.set_level(v);
-        }
-        if let Some(v) = option.fileoption {
-            match FileHandler::new(v) {
-                Ok(f) => {
-                    self.filehandle.0 = f.get_file_name();
-                    self.filehandle.1.set_file_handler(f);
-                }
-                Err(_) => {}
-            }
-        }
-
+    #[cfg(feature = "syslog")]
+    pub fn set_syslog(&self, config: SyslogConfig) -> &Self {
+        global().set_syslog(config);
         self
     }
 
-    pub fn set_mod_option(&mut self, module: &str, option: LogOption) -> &mut Self {
-        let mut filename = "".to_string();
-        if let Some(v) = option.fileoption {
-            match FileHandler::new(v) {
-                Ok(f) => {
-                    filename = f.get_file_name();
-                    if filename != self.filehandle.0 && !self.fmap.contains_key(&filename) {
-                        let mut fhandler = FHandler::new();
-                        fhandler.set_file_handler(f);
-                        self.fmap.insert(filename.clone(), fhandler);
-                    }
-                }
-                Err(_) => {}
-            }
-        }
-        self.modmap.insert(
-            module,
-            (
-                LogOptionConst {
-                    level: option.level,
-                    format: option.format,
-                    formatter: option.formatter,
-                    console: option.console,
-                },
-                filename.clone(),
-            ),
-        );
+    #[cfg(feature = "syslog")]
+    pub fn clear_syslog(&self) -> &Self {
+        global().clear_syslog();
         self
     }
 
-    pub fn set_custom_handler(&mut self, handler: fn(&LogContext) -> bool) -> &mut Self {
-        self.custom_handler = Some(handler);
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) -> &Self {
+        global().set_clock(clock);
         self
     }
 
-    pub fn set_level_option(&mut self, level: LEVEL, option: &dyn OptionTrait) -> &mut Self {
-        let mut filename = "".to_string();
-        if let Some(v) = option.get_fileoption() {
-            match FileHandler::new(v) {
-                Ok(f) => {
-                    filename = f.get_file_name();
-                    if filename != self.filehandle.0 && !self.fmap.contains_key(&filename) {
-                        let mut fhandler = FHandler::new();
-                        fhandler.set_file_handler(f);
-                        self.fmap.insert(filename.clone(), fhandler);
-                    }
-                }
-                Err(_) => {}
-            }
-        }
-        let lo = LogOption {
-            level: None,
-            format: option.get_format(),
-            formatter: option.get_formatter(),
-            console: option.get_console(),
-            fileoption: option.get_fileoption(),
-        };
+    pub fn set_adaptive_budget(&self, bytes_per_day: u64) -> &Self {
+        global().set_adaptive_budget(bytes_per_day);
+        self
+    }
 
-        if self.levels.is_none() {
-            self.levels = Some(std::array::from_fn(|_| None));
+    pub fn log_block(&self, level: LEVEL, module: &str, block: impl FnOnce(&mut BlockWriter)) {
+        if crate::reentrant(level, module, || "a block of lines".to_string()) {
+            return;
         }
+        global().log_block(level, module, block);
+    }
 
-        if let Some(levels) = &mut self.levels {
-            levels[level as usize - 1] = Some((lo, filename));
+    #[track_caller]
+    pub fn log_record(&self, level: LEVEL, module: &str, location: Option<(&str, u32)>, message: String) {
+        let location = location.or_else(|| {
+            let caller = Location::caller();
+            Some((caller.file(), caller.line()))
+        });
+        if crate::reentrant(level, module, || message.clone()) {
+            return;
         }
+        global().log_record(level, module, location, message);
+    }
 
+    pub fn set_option(&self, option: LogOption) -> &Self {
+        let mut log = global();
+        log.set_option(option);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
         self
     }
 
-    pub fn set_separator(&mut self, separator: &str) -> &mut Self {
-        self.separator = separator.to_string();
+    pub fn set_mod_option(&self, module: &str, option: LogOption) -> &Self {
+        let mut log = global();
+        log.set_mod_option(module, option);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
         self
     }
 
-    pub fn get_separator(&self) -> String {
-        self.separator.clone()
+    pub fn set_level_option(&self, level: LEVEL, option: impl OptionTrait) -> &Self {
+        global().set_level_option(level, &option);
+        self
     }
 
-    // pub fn set_levelfmt<F>(&mut self, levelfmt: F)
-    // where
-    //     F: Fn(LEVEL) -> String + Send + Sync + 'static,
-    // {
-    //     self.levelfmt = Some(Box::new(levelfmt));
-    // }
-
-    // pub fn set_timefmt<F>(&mut self, timefmt: F)
-    // where
-    //     F: Fn() -> (String, String, String) + Send + Sync + 'static,
-    // {
-    //     self.timefmt = Some(Box::new(timefmt));
-    // }
-
-    pub fn set_attr_format<F>(&mut self, mut f: F)
-    where
-        F: FnMut(&mut AttrFormat) + Send + Sync + 'static,
-    {
-        f(&mut self.attrfmt);
+    pub fn module_levels(&self) -> Vec<(String, LEVEL)> {
+        global().module_levels()
     }
-}
-
-pub struct Log;
 
-impl Log {
-    pub fn new() -> Self {
-        Log {}
-    }
-    pub fn set_printmode(&self, mode: PRINTMODE) -> &Self {
-        unsafe {
-            synclog.set_printmode(mode);
-        }
+    pub fn set_mod_separator(&self, pattern: &str, separator: &str) -> &Self {
+        global().set_mod_separator(pattern, separator);
         self
     }
 
-    pub fn set_level(&self, level: LEVEL) -> &Self {
-        unsafe {
-            synclog.set_level(level);
-        }
+    pub fn set_mod_level(&self, pattern: &str, level: LEVEL) -> &Self {
+        let mut log = global();
+        log.set_mod_level(pattern, level);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
         self
     }
 
-    pub fn set_console(&self, console: bool) -> &Self {
-        unsafe {
-            synclog.set_console(console);
-        }
-        self
+    pub fn clear_module_level(&self, pattern: &str) -> bool {
+        let mut log = global();
+        let cleared = log.clear_module_level(pattern);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
+        cleared
     }
 
-    /**Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName; */
-    pub fn set_format(&self, format: u8) -> &Self {
-        unsafe {
-            synclog.set_format(format);
-        }
+    pub fn set_level_from_env(&self, var: &str) -> &Self {
+        let mut log = global();
+        log.set_level_from_env(var);
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
         self
     }
 
-    /** default: "{level}{time} {file}:{message}\n" */
-    pub fn set_formatter(&self, formatter: &str) -> &Self {
-        unsafe {
-            synclog.set_formatter(formatter);
-        }
+    pub fn set_filter(&self, filter: Filter) -> &Self {
+        global().set_filter(filter);
         self
     }
 
-    pub fn set_cutmode_by_size(
-        &self,
-        filename: &str,
-        maxsize: u64,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &Self {
-        unsafe {
-            synclog.set_cutmode_by_size(filename, maxsize, maxbackups, compress);
-        }
+    pub fn clear_filters(&self) -> &Self {
+        global().clear_filters();
         self
     }
 
-    pub fn set_cutmode_by_time(
-        &self,
-        filename: &str,
-        mode: MODE,
-        maxbackups: u32,
-        compress: bool,
-    ) -> &Self {
-        unsafe {
-            synclog.set_cutmode_by_time(filename, mode, maxbackups, compress);
-        }
+    pub fn add_hook(&self, hook: Hook) -> &Self {
+        global().add_hook(hook);
         self
     }
 
-    pub fn set_option(&self, option: LogOption) -> &Self {
-        unsafe {
-            synclog.set_option(option);
-        }
+    pub fn remove_hooks(&self) -> &Self {
+        global().remove_hooks();
         self
     }
 
-    pub fn set_mod_option(&self, module: &str, option: LogOption) -> &Self {
-        unsafe {
-            synclog.set_mod_option(module, option);
-        }
+    pub fn set_custom_handler(&self, handler: fn(&LogContext) -> bool) -> &Self {
+        global().set_custom_handler(handler);
         self
     }
 
-    pub fn set_level_option(&self, level: LEVEL, option: impl OptionTrait) -> &Self {
-        unsafe {
-            synclog.set_level_option(level, &option);
-        }
+    pub fn set_separator(&self, separator: &str) -> &Self {
+        global().set_separator(separator);
         self
     }
 
-    pub fn set_custom_handler(&self, handler: fn(&LogContext) -> bool) -> &Self {
-        unsafe {
-            synclog.set_custom_handler(handler);
-        }
+    /// Installs `LogBridge::Sync` as the logger of the `log` facade,
+    /// unless it has one already, see `bridge`.
+    pub fn uselog(&self) -> &Self {
+        let _ = bridge::install(LogBridge::Sync);
         self
     }
 
-    pub fn set_separator(&self, separator: &str) -> &Self {
-        unsafe {
-            synclog.set_separator(separator);
-        }
+    pub fn set_attr_format<F>(&self, f: F)
+    where
+        F: FnMut(&mut AttrFormat) + Send + Sync + 'static,
+    {
+        global().set_attr_format(f);
+    }
+
+    pub fn set_time_format(&self, format: &str) -> Result<&Self, Error> {
+        global().set_time_format(format)?;
+        Ok(self)
+    }
+
+    pub fn set_time_rfc3339(&self) -> &Self {
+        global().set_time_rfc3339();
         self
     }
 
-    fn is_file_line(&self, level: LEVEL, module: &str) -> bool {
-        unsafe {
-            return synclog.is_file_line(level, module);
-        }
+    pub fn set_column_order(&self, columns: &[COLUMN]) -> Result<&Self, Error> {
+        global().set_column_order(columns)?;
+        Ok(self)
     }
 
-    pub fn uselog(&self) -> &Self {
-        let _ = log::set_logger(&TKLOG2SYNCLOG);
-        log::set_max_level(log::LevelFilter::Trace);
+    pub fn clear_column_order(&self) -> &Self {
+        global().clear_column_order();
         self
     }
 
-    // pub fn set_levelfmt<F>(&self, levelfmt: F)
-    // where
-    //     F: Fn(LEVEL) -> String + Send + Sync + 'static,
-    // {
-    //     unsafe { synclog.set_levelfmt(levelfmt) };
-    // }
+    pub fn load_config_file(&self, path: impl AsRef<Path>) -> Result<&Self, Error> {
+        let mut log = global();
+        log.load_config_file(path)?;
+        bridge::refresh(LogBridge::Sync, log.lowest_level());
+        Ok(self)
+    }
 
-    // pub fn set_timefmt<F>(&self, timefmt: F)
-    // where
-    //     F: Fn() -> (String, String, String) + Send + Sync + 'static,
-    // {
-    //     unsafe { synclog.set_timefmt(timefmt) };
-    // }
+    /// Applies the config file at `path`, then again whenever it changes,
+    /// checked every `interval` on a thread of its own, until the watch is
+    /// dropped. A change the file can't take is reported as a `Config`
+    /// diagnostic and leaves the configuration as it was. Keys taken out
+    /// of the file keep their last values.
+    pub fn watch_config_file(&self, path: impl AsRef<Path>, interval: Duration) -> Result<ConfigWatch, Error> {
+        let path = path.as_ref().to_path_buf();
+        let last = config::stamp(&path);
+        self.load_config_file(&path)?;
+        Ok(config::watch_thread(path, last, interval, |path| {
+            if let Err(e) = crate::LOG.load_config_file(path) {
+                config::refused(path, e);
+            }
+        }))
+    }
 
-    pub fn set_attr_format<F>(&self, f: F)
-    where
-        F: FnMut(&mut AttrFormat) + Send + Sync + 'static,
-    {
-        unsafe {
-            synclog.set_attr_format(f);
-        }
+    pub fn set_test_mode(&self, mode: TestMode) -> Result<&Self, Error> {
+        global().set_test_mode(mode)?;
+        Ok(self)
     }
 }
 
 impl log::Log for Log {
-    fn enabled(&self, _: &log::Metadata) -> bool {
-        return true;
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        LogBridge::Sync.enabled(metadata)
     }
     fn log(&self, record: &log::Record) {
-        let level = l2tk(record.level());
-        let mut module = "";
-        if let Some(m) = record.module_path() {
-            module = m;
-            unsafe {
-                if synclog.get_level(module) > level {
-                    return;
-                }
-            }
-        }
-        let args = record.args();
-        let mut file = "";
-        let mut line: u32 = 0;
-        if self.is_file_line(level, module) {
-            line = record.line().unwrap_or(0);
-            file = record.file().unwrap_or("");
-        }
-        unsafe {
-            if synclog.mode == PRINTMODE::DELAY {
-                let s = synclog.fmt(module, level, file, line, arguments_to_string(args));
-                if !s.is_empty() {
-                    synclog.log(level, module.to_string(), s);
-                }
-            } else {
-                let s = synclog.fmt(module, level, file, line, arguments_to_string(args));
-                if !s.is_empty() {
-                    synclog.safeprint(level, module, s);
-                }
-            }
-        }
+        LogBridge::Sync.log(record)
     }
     fn flush(&self) {}
 }
 
+/// A line on `LOG` at the level of an expression, evaluated once:
+/// `log!(level, "status", code)` is `warn!("status", code)` when `level`
+/// is `LEVEL::Warn`.
 #[macro_export]
 macro_rules! log {
-    ($level:expr, $module:expr,$msg:expr) => {
-        let level: LEVEL = $level;
-        let msg: LogContent = $msg;
-        let module: &str = $module;
-        unsafe {
-            synclog.print(level, module, msg);
-        }
-    };
+    ($level:expr, || $body:expr) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::log_common!(level, $crate::LazyMessage(|| $body));
+    }};
+    ($level:expr, $($arg:expr),+ ; $($fields:tt)*) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::log_common!(@fields None, level, ($($arg),*), $($fields)*);
+    }};
+    ($level:expr, $msg:literal) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::log_common!(@static level, $msg);
+    }};
+    ($level:expr, $($arg:expr),+) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::log_common!(level, $($arg),*);
+    }};
+}
+
+/// `log!` with a format string: `logf!(level, "took {}ms", ms)`.
+#[macro_export]
+macro_rules! logf {
+    ($level:expr, $($arg:tt)+) => {{
+        let level: $crate::LEVEL = $level;
+        $crate::log_common!(level, format_args!($($arg)+));
+    }};
 }
 
 #[macro_export]
 macro_rules! trace {
     () => {};
+    (|| $body:expr) => {
+        $crate::log_common!($crate::LEVEL::Trace, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::log_common!(@fields Some($event), $crate::LEVEL::Trace, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::log_common!(@event Some($event), $crate::LEVEL::Trace, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::log_common!(@fields None, $crate::LEVEL::Trace, ($($arg),*), $($fields)*);
+    };
+    ($msg:literal) => {
+        $crate::log_common!(@static $crate::LEVEL::Trace, $msg);
+    };
     ($($arg:expr),*) => {
         $crate::log_common!($crate::LEVEL::Trace, $($arg),*);
     };
@@ -694,6 +3589,21 @@ macro_rules! trace {
 #[macro_export]
 macro_rules! debug {
     () => {};
+    (|| $body:expr) => {
+        $crate::log_common!($crate::LEVEL::Debug, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::log_common!(@fields Some($event), $crate::LEVEL::Debug, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::log_common!(@event Some($event), $crate::LEVEL::Debug, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::log_common!(@fields None, $crate::LEVEL::Debug, ($($arg),*), $($fields)*);
+    };
+    ($msg:literal) => {
+        $crate::log_common!(@static $crate::LEVEL::Debug, $msg);
+    };
     ($($arg:expr),*) => {
         $crate::log_common!($crate::LEVEL::Debug, $($arg),*);
     };
@@ -702,6 +3612,21 @@ macro_rules! debug {
 #[macro_export]
 macro_rules! info {
     () => {};
+    (|| $body:expr) => {
+        $crate::log_common!($crate::LEVEL::Info, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::log_common!(@fields Some($event), $crate::LEVEL::Info, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::log_common!(@event Some($event), $crate::LEVEL::Info, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::log_common!(@fields None, $crate::LEVEL::Info, ($($arg),*), $($fields)*);
+    };
+    ($msg:literal) => {
+        $crate::log_common!(@static $crate::LEVEL::Info, $msg);
+    };
     ($($arg:expr),*) => {
         $crate::log_common!($crate::LEVEL::Info, $($arg),*);
     };
@@ -710,6 +3635,21 @@ macro_rules! info {
 #[macro_export]
 macro_rules! warn {
     () => {};
+    (|| $body:expr) => {
+        $crate::log_common!($crate::LEVEL::Warn, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::log_common!(@fields Some($event), $crate::LEVEL::Warn, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::log_common!(@event Some($event), $crate::LEVEL::Warn, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::log_common!(@fields None, $crate::LEVEL::Warn, ($($arg),*), $($fields)*);
+    };
+    ($msg:literal) => {
+        $crate::log_common!(@static $crate::LEVEL::Warn, $msg);
+    };
     ($($arg:expr),*) => {
         $crate::log_common!($crate::LEVEL::Warn, $($arg),*);
     };
@@ -718,6 +3658,21 @@ macro_rules! warn {
 #[macro_export]
 macro_rules! error {
     () => {};
+    (|| $body:expr) => {
+        $crate::log_common!($crate::LEVEL::Error, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::log_common!(@fields Some($event), $crate::LEVEL::Error, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::log_common!(@event Some($event), $crate::LEVEL::Error, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::log_common!(@fields None, $crate::LEVEL::Error, ($($arg),*), $($fields)*);
+    };
+    ($msg:literal) => {
+        $crate::log_common!(@static $crate::LEVEL::Error, $msg);
+    };
     ($($arg:expr),*) => {
         $crate::log_common!($crate::LEVEL::Error, $($arg),*);
     };
@@ -726,6 +3681,21 @@ macro_rules! error {
 #[macro_export]
 macro_rules! fatal {
     () => {};
+    (|| $body:expr) => {
+        $crate::log_common!($crate::LEVEL::Fatal, $crate::LazyMessage(|| $body));
+    };
+    (event: $event:expr, $($arg:expr),* ; $($fields:tt)*) => {
+        $crate::log_common!(@fields Some($event), $crate::LEVEL::Fatal, ($($arg),*), $($fields)*);
+    };
+    (event: $event:expr, $($arg:expr),*) => {
+        $crate::log_common!(@event Some($event), $crate::LEVEL::Fatal, $($arg),*);
+    };
+    ($($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::log_common!(@fields None, $crate::LEVEL::Fatal, ($($arg),*), $($fields)*);
+    };
+    ($msg:literal) => {
+        $crate::log_common!(@static $crate::LEVEL::Fatal, $msg);
+    };
     ($($arg:expr),*) => {
         $crate::log_common!($crate::LEVEL::Fatal, $($arg),*);
     };
@@ -733,31 +3703,61 @@ macro_rules! fatal {
 
 #[macro_export]
 macro_rules! log_common {
-    ($level:expr, $($arg:expr),*) => {
-        unsafe {
+    (@event $event:expr, $level:expr, $($arg:expr),*) => {
+        $crate::log_common!(@fields $event, $level, ($($arg),*),)
+    };
+    (@fields $event:expr, $level:expr, ($($arg:expr),*), $($fields:tt)*) => {
+        if $crate::compiled($level) && $crate::above_floor($level) {
             let module = module_path!();
-            if $crate::tklog::synclog.get_level(module) <= $level {
-                let formatted_args: Vec<String> = vec![$(format!("{}", $arg)),*];
-                let mut file = "";
-                let mut line = 0;
-                if $crate::tklog::synclog.is_file_line($level,module) {
-                    file = file!();
-                    line = line!();
-                }
-                let msg: String = formatted_args.join($crate::tklog::synclog.get_separator().as_str());
-                if  $crate::tklog::synclog.mode==$crate::PRINTMODE::DELAY {
-                    let s = $crate::tklog::synclog.fmt(module,$level, file, line, msg);
-                    if !s.is_empty(){
-                        $crate::tklog::synclog.log($level,module.to_string(),s);
-                    }
-                }else {
-                    let s = $crate::tklog::synclog.fmt(module,$level, file, line, msg);
-                    if !s.is_empty(){
-                        $crate::tklog::synclog.safeprint($level,module,s);
+            if !$crate::reentrant($level, module, || $crate::message_of("", &[$(&$arg),*])) {
+                let site = $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!());
+                $crate::lines::log_line($level, module, site, $event, |sep| $crate::message_of(sep, &[$(&$arg),*]), || $crate::fields_of!($($fields)*));
+            }
+        }
+    };
+    (@static $level:expr, $msg:literal) => {
+        if $crate::compiled($level) && $crate::above_floor($level) {
+            match $crate::StaticMessage::as_static(&$msg) {
+                Some(msg) => {
+                    let module = module_path!();
+                    if !$crate::reentrant($level, module, || msg.to_string()) {
+                        $crate::lines::log_static($level, module, $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!()), msg);
                     }
                 }
+                None => $crate::log_common!(@event None, $level, $msg),
             }
         }
     };
+    ($level:expr, $($arg:expr),*) => {
+        $crate::log_common!(@event None, $level, $($arg),*)
+    };
     () => {};
 }
+
+/// Logs a Warn line on `LOG` only if `clear!` with its key doesn't come
+/// within the delay, see `persist`.
+#[macro_export]
+macro_rules! warn_if_persists {
+    ($after:expr, key: $key:expr, $($arg:tt)+) => {
+        {
+            let module = module_path!();
+            if $crate::compiled($crate::LEVEL::Warn) && $crate::above_floor($crate::LEVEL::Warn) && !$crate::reentrant($crate::LEVEL::Warn, module, || format!($($arg)+)) {
+                let mut logger = $crate::global_for_line();
+                if logger.get_level(module) <= $crate::LEVEL::Warn {
+                    let (file, line) = if logger.is_file_line($crate::LEVEL::Warn, module) { $crate::CallSite::here(file!(), line!()).get(logger.location_strategy()) } else { ("", 0) };
+                    logger.log_if_persists($key, $after, $crate::LEVEL::Warn, module, file, line, || format!($($arg)+));
+                    drop(logger);
+                    $crate::persist::wake();
+                }
+            }
+        }
+    };
+}
+
+/// Cancels the line `warn_if_persists!` armed for a key; false when none was.
+#[macro_export]
+macro_rules! clear {
+    ($key:expr) => {
+        !$crate::reentrant($crate::LEVEL::Off, module_path!(), String::new) && $crate::global_for_line().clear_persisting($key)
+    };
+}
@@ -20,13 +20,30 @@ use std::{
     fs::{self, File, OpenOptions},
     io::{self, Error, ErrorKind, Write},
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
-use chrono::{DateTime, Local};
 use once_cell::sync::Lazy;
-use regex::Regex;
 
-use crate::{getbackup_with_time, gzip, handle::FileOption, passtimemode, threadPool::ThreadPool, timesec, ErrCode, CUTMODE, MODE};
+use crate::{
+    backupname::Backups, backups_older_than, backups_to_prune, due_for_compression, epoch_secs, next_backup_counter,
+    compress::LiveEncoder,
+    config::FileConfig,
+    diagnostics::{self, Category},
+    dated_name, getbackup_with_time, guard::PanicCount, gzip, is_same_file,
+    handle::{FileOption, FileSettings},
+    localsec, opened_startsec,
+    logerror::{self, LogError},
+    passtimemode, rename_unless_exists,
+    scheduler::{RotationTimer, Scheduler},
+    set_file_mode, space_preflight,
+    threadPool::ThreadPool,
+    verify::Chain,
+    timesec,
+    writebuf::{Flusher, WriteBuffer},
+    CompressDecision, ErrCode, PrunePolicy, RotationEvent, CUTMODE, MODE,
+};
 
 pub struct FileHandler {
     filename: String, //Log file path
@@ -36,29 +53,50 @@ pub struct FileHandler {
     cutmode: CUTMODE,
     timemode: MODE,
     filesize: u64,
-    filehandle: File,
+    /// `None` once released to free a descriptor, or after a failed
+    /// reopen; the next write opens the file again.
+    filehandle: Option<File>,
     startsec: u64,
+    settings: FileSettings,
+    timer: Option<RotationTimer>,
+    chain: Option<Chain>,
+    rotation_panics: Arc<PanicCount>,
+    /// Leaves the time checks to a rotation group, see `rotate_as`.
+    grouped: bool,
+    /// The open gzip member with live compression, started by the first
+    /// write after an open or a rotation.
+    live: Option<LiveEncoder>,
+    /// Holds the lines with a buffer size, see `writebuf`.
+    buffer: WriteBuffer,
 }
 
 impl FileHandler {
     pub fn new(option: Box<dyn FileOption>) -> Result<Self, Error> {
-        let fo = option;
-        let filename = fo.filename();
+        Self::open(&*option, None)
+    }
+
+    /// Opens the file of `fo`, created with the permission bits `mode`; a
+    /// `CUTMODE::PATTERN` opens the file of the current period.
+    pub(crate) fn open(fo: &dyn FileOption, mode: Option<u32>) -> Result<Self, Error> {
+        let dated = (fo.mode() == CUTMODE::PATTERN).then(timesec);
+        let filename = match dated {
+            Some(secs) => dated_name(&fo.filename(), secs, fo.timemode()),
+            None => fo.filename(),
+        };
         let log_path = Path::new(&filename);
-        let _ = mkdirs(log_path);
+        mkdirs(log_path)?;
 
-        let file = Self::newfile(filename.clone());
+        let file = Self::newfile(filename.clone(), mode);
 
         if file.is_err() {
             return Err(file.err().unwrap());
         }
 
         let f = file.unwrap();
-        let modified_time = f.metadata()?.modified()?;
-
-        let datetime_utc: DateTime<chrono::Utc> = modified_time.into();
-        let datetime_local = datetime_utc.with_timezone(&Local);
-        let startsec = datetime_local.naive_local().and_utc().timestamp() as u64;
+        let startsec = match dated {
+            Some(secs) => secs,
+            None => opened_startsec(&f.metadata()?)?,
+        };
 
         let fh = FileHandler {
             filename: fo.filename(),
@@ -68,76 +106,466 @@ impl FileHandler {
             cutmode: fo.mode(),
             timemode: fo.timemode(),
             filesize: fs::metadata(&log_path)?.len(),
-            filehandle: f,
+            filehandle: Some(f),
             startsec,
+            settings: FileSettings::default(),
+            timer: None,
+            chain: None,
+            rotation_panics: Arc::default(),
+            grouped: false,
+            live: None,
+            buffer: WriteBuffer::default(),
         };
         Ok(fh)
     }
 
+    /// A handler of `filename` that never rotates; the file and its
+    /// directories are created by the first write.
+    pub(crate) fn unopened(filename: String) -> Self {
+        FileHandler {
+            filename,
+            max_size: 0,
+            max_backups: 0,
+            compress: false,
+            cutmode: CUTMODE::SIZE,
+            timemode: MODE::DAY,
+            filesize: 0,
+            filehandle: None,
+            startsec: timesec(),
+            settings: FileSettings::default(),
+            timer: None,
+            chain: None,
+            rotation_panics: Arc::default(),
+            grouped: false,
+            live: None,
+            buffer: WriteBuffer::default(),
+        }
+    }
+
     pub fn get_file_name(&self) -> String {
         self.filename.clone()
     }
 
+    pub fn file_config(&self) -> FileConfig {
+        FileConfig {
+            filename: self.filename.clone(),
+            cutmode: self.cutmode,
+            timemode: self.timemode,
+            maxsize: self.max_size,
+            maxbackups: self.settings.max_backups.unwrap_or(self.max_backups),
+            compress: self.compress,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: FileSettings) {
+        let switched = settings.live_compression.is_some() != self.settings.live_compression.is_some();
+        if switched {
+            self.close_stream();
+        }
+        match &settings.tamper_key {
+            Some(key) if self.chain.as_ref().is_some_and(|c| c.key() == key) => {}
+            Some(key) => self.chain = Some(Chain::new(key.clone(), &self.filename)),
+            None => self.chain = None,
+        }
+        // Live compression buffers on its own; a shared file takes each line
+        // as it comes.
+        let capacity = if settings.live_compression.is_some() || settings.shared { 0 } else { settings.buffer_size };
+        if let Err(e) = self.buffer.set_capacity(capacity) {
+            self.report(LogError::WriteFailed, &e);
+        }
+        self.settings = settings;
+        self.rotation_panics.reset();
+        if switched {
+            self.filesize = fs::metadata(self.path()).map_or(0, |m| m.len());
+        }
+    }
+
+    /// The file written to: the file name, or its `.gz` with live compression.
+    fn path(&self) -> PathBuf {
+        self.settings.live_path(&self.active())
+    }
+
+    /// The file name, or the file of the current period of a `CUTMODE::PATTERN`.
+    fn active(&self) -> PathBuf {
+        match self.cutmode {
+            CUTMODE::PATTERN => PathBuf::from(dated_name(&self.filename, self.startsec, self.timemode)),
+            _ => PathBuf::from(&self.filename),
+        }
+    }
+
+    /// Ends the gzip member of the live file, making what it holds a
+    /// complete archive; the next write starts another member. Writes out
+    /// the buffer first, which lets go of the file until the next write.
+    fn finish_live(&mut self) -> io::Result<()> {
+        self.buffer.detach()?;
+        if let (Some(live), Some(f)) = (self.live.take(), &mut self.filehandle) {
+            f.write_all(&live.finish()?)?;
+        }
+        Ok(())
+    }
+
+    /// Closes the file before live compression is turned on or off, and
+    /// removes it if it was left empty, as by the open of the handler.
+    fn close_stream(&mut self) {
+        let _ = self.finish_live();
+        if self.filehandle.take().is_some() {
+            let path = self.path();
+            if fs::metadata(&path).is_ok_and(|m| m.len() == 0) {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+
+    /// Leaves the checks of a time-based rotation to `scheduler`.
+    pub(crate) fn schedule(&mut self, scheduler: &Scheduler) {
+        if self.cutmode != CUTMODE::SIZE {
+            self.timer = Some(scheduler.register(self.startsec, self.timemode));
+        }
+    }
+
+    /// Leaves the periodic flush of the buffer to `flusher`.
+    pub(crate) fn flush_with(&self, flusher: &Flusher) {
+        flusher.register(&self.buffer);
+    }
+
+    /// The wall-clock second the current period started at.
+    pub(crate) fn startsec(&self) -> u64 {
+        self.startsec
+    }
+
+    /// Leaves the time checks to a rotation group whose period started at
+    /// `group`, or takes them back with `None`.
+    pub(crate) fn set_grouped(&mut self, group: Option<u64>) {
+        self.grouped = group.is_some();
+        if let Some(startsec) = group {
+            self.startsec = startsec;
+        }
+    }
+
+    /// Rotates now for a rotation group: the backup carries the period
+    /// starting at `stamp` and the next period starts at `startsec`. An
+    /// empty file is left as it is unless `empty_backups`.
+    fn cut_as(&mut self, stamp: u64) -> io::Result<()> {
+        self.finish_live()?;
+        rename(Path::new(&self.filename), self.compress, self.max_backups, self.settings.clone(), Some((stamp, self.timemode)), self.rotation_panics.clone())?;
+        self.new_from_clone()
+    }
+
+    pub(crate) fn rotate_as(&mut self, stamp: u64, startsec: u64, empty_backups: bool) -> io::Result<()> {
+        if empty_backups || self.filesize > 0 {
+            if let Err(e) = self.cut_as(stamp) {
+                self.report(LogError::RotateFailed, &e);
+                return Err(e);
+            }
+        }
+        self.startsec = startsec;
+        Ok(())
+    }
+
+    /// Whether this handler will ever cut the file: time and mixed modes
+    /// always do, size mode only with a non-zero max size.
+    pub fn is_rotating(&self) -> bool {
+        match self.cutmode {
+            CUTMODE::TIME | CUTMODE::MIXED | CUTMODE::PATTERN => true,
+            CUTMODE::SIZE => self.max_size > 0,
+        }
+    }
+
     pub fn new_from_clone(&mut self) -> io::Result<()> {
-        let filename = self.filename.clone();
-        let log_path = Path::new(&filename);
-        mkdirs(log_path)?;
-        let file = Self::newfile(filename)?;
-        self.filesize = 0;
-        self.filehandle = file;
+        let path = self.path();
+        mkdirs(&path)?;
+        self.filehandle = None;
+        self.live = None;
+        if let Some(c) = &mut self.chain {
+            c.restart();
+        }
+        let file = Self::newfile(&path, self.settings.file_mode)?;
+        // The file of a new period may be there already.
+        self.filesize = file.metadata()?.len();
+        self.filehandle = Some(file);
+        Ok(())
+    }
+
+    /// Closes the file to give its descriptor back; the next write reopens it.
+    pub(crate) fn release(&mut self) -> bool {
+        let _ = self.finish_live();
+        self.filehandle.take().is_some()
+    }
+
+    /// Opens the file again now rather than on the next write. The size
+    /// cut counts from what the file opened holds, 0 for a new one.
+    pub(crate) fn reopen(&mut self) -> io::Result<()> {
+        let _ = self.finish_live();
+        let path = self.path();
+        mkdirs(&path)?;
+        let f = Self::newfile(&path, self.settings.file_mode)?;
+        self.filesize = f.metadata()?.len();
+        self.filehandle = Some(f);
+        match &mut self.chain {
+            Some(c) if self.filesize == 0 => c.restart(),
+            _ => {}
+        }
         Ok(())
     }
 
-    fn newfile(filename: String) -> io::Result<File> {
-        OpenOptions::new().append(true).create(true).open(filename)
+    /// Writes out the file and closes it, for a logger moving to another
+    /// path; with `rotate` a file holding lines is renamed to its next
+    /// backup first, as a cut would.
+    pub(crate) fn retire(&mut self, rotate: bool) {
+        let _ = self.flush();
+        if rotate && self.filesize > 0 {
+            if let Err(e) = self.rename() {
+                self.report(LogError::RotateFailed, &e);
+            }
+        }
+        self.release();
+    }
+
+    pub(crate) fn rotation_panics(&self) -> &PanicCount {
+        &self.rotation_panics
+    }
+
+    /// Whether the period of a time or mixed cut is over.
+    fn due_by_time(&self) -> bool {
+        self.cutmode != CUTMODE::SIZE && !self.grouped && self.timer.as_ref().is_none_or(|t| t.is_due()) && passtimemode(self.startsec, self.timemode)
     }
 
-    fn rename(&self) -> io::Result<()> {
+    /// Whether `len` more bytes would take a size or mixed cut over its size.
+    fn due_by_size(&self, len: usize) -> bool {
+        self.cutmode != CUTMODE::TIME && self.max_size > 0 && self.filesize + len as u64 > self.max_size
+    }
+
+    /// Starts the period again after a cut, by time or by size, of a time
+    /// or mixed mode; a rotation group keeps the periods of its members.
+    fn report(&self, kind: fn(PathBuf, io::Error) -> LogError, e: &io::Error) {
+        self.settings.errors.report(kind(self.path(), logerror::copy(e)));
+    }
+
+    fn restart_period(&mut self) {
+        if self.cutmode == CUTMODE::SIZE || self.grouped {
+            return;
+        }
+        self.startsec = timesec();
+        if let Some(t) = &self.timer {
+            t.restart(self.startsec);
+        }
+    }
+
+    /// Opens `filename` to append, giving it `mode` if it is created.
+    fn newfile(filename: impl AsRef<Path>, mode: Option<u32>) -> io::Result<File> {
+        let created = mode.is_some() && !filename.as_ref().exists();
+        let f = OpenOptions::new().append(true).create(true).open(&filename)?;
+        if created {
+            set_file_mode(filename.as_ref(), mode)?;
+        }
+        Ok(f)
+    }
+
+    fn rename(&mut self) -> io::Result<()> {
+        self.finish_live()?;
         let log_path = Path::new(&self.filename);
         match self.cutmode {
-            CUTMODE::TIME => rename(&log_path, self.compress, self.max_backups, Some(getbackup_with_time(self.startsec, self.timemode))),
-            CUTMODE::SIZE => rename(&log_path, self.compress, self.max_backups, None),
+            CUTMODE::TIME | CUTMODE::MIXED => rename(log_path, self.compress, self.max_backups, self.settings.clone(), Some((self.startsec, self.timemode)), self.rotation_panics.clone()),
+            CUTMODE::SIZE => rename(log_path, self.compress, self.max_backups, self.settings.clone(), None, self.rotation_panics.clone()),
+            CUTMODE::PATTERN => self.close_period(),
         }
     }
 
+    /// The cut of a `CUTMODE::PATTERN`: nothing is renamed, the file of
+    /// the period over is closed and archived, and the next write opens
+    /// the file of the period started.
+    fn close_period(&mut self) -> io::Result<()> {
+        let old = self.path();
+        self.release();
+        self.restart_period();
+        self.settings.metrics.rotated();
+        let name = |path: &Path| path.file_name().map_or(String::new(), |n| n.to_string_lossy().to_string());
+        let backups = Backups::dated(&name(Path::new(&self.filename)), &name(&self.active()));
+        let dir = backup_location(Path::new(&self.filename))?.0;
+        let maxbackup = self.settings.max_backups.unwrap_or(self.max_backups);
+        archive(old, dir, backups, self.filename.clone(), self.compress, maxbackup, self.settings.clone(), self.rotation_panics.clone());
+        Ok(())
+    }
+
     pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
-        match self.cutmode {
-            CUTMODE::TIME => {
-                if passtimemode(self.startsec, self.timemode) {
-                    if let Ok(_) = self.rename() {
-                        let _ = self.new_from_clone();
-                        self.startsec = timesec();
-                    }
+        if self.settings.shared && self.settings.live_compression.is_none() {
+            return self.write_shared(data);
+        }
+        if self.due_by_time() || self.due_by_size(data.len()) {
+            if let Err(e) = self.rename().and_then(|()| self.new_from_clone()) {
+                self.report(LogError::RotateFailed, &e);
+            }
+            self.restart_period();
+        }
+        let written = self.append(data);
+        if let Err(e) = &written {
+            self.report(LogError::WriteFailed, e);
+        }
+        written
+    }
+
+    /// `write` to a file other processes write too: the line is appended
+    /// under an exclusive advisory lock, and the cut is decided on the size
+    /// of the file rather than on what this process wrote.
+    fn write_shared(&mut self, data: &[u8]) -> io::Result<()> {
+        if let Err(e) = self.lock_shared() {
+            self.report(LogError::WriteFailed, &e);
+            return Err(e);
+        }
+        let mut due = self.due_by_time() || self.due_by_size(data.len());
+        while due {
+            // The lock of the file renamed goes with it.
+            let rotated = self.rename().and_then(|()| self.new_from_clone()).and_then(|()| self.lock_shared());
+            self.restart_period();
+            // Another process may have filled the new file before it was locked.
+            due = match rotated {
+                Ok(()) => self.filesize > 0 && self.due_by_size(data.len()),
+                Err(e) => {
+                    self.report(LogError::RotateFailed, &e);
+                    false
                 }
+            };
+        }
+        let written = self.append(data);
+        if let Err(e) = &written {
+            self.report(LogError::WriteFailed, e);
+        }
+        if let Some(f) = &self.filehandle {
+            let _ = f.unlock();
+        }
+        written
+    }
+
+    /// Locks the file at the path, opening it again when another process
+    /// has rotated the one open: that rotation ends this period too.
+    fn lock_shared(&mut self) -> io::Result<()> {
+        let path = self.path();
+        loop {
+            let file = match &mut self.filehandle {
+                Some(f) => f,
+                None => {
+                    mkdirs(&path)?;
+                    self.filehandle.insert(Self::newfile(&path, self.settings.file_mode)?)
+                }
+            };
+            file.lock()?;
+            if is_same_file(file, &path) {
+                self.filesize = file.metadata()?.len();
+                return Ok(());
             }
-            CUTMODE::SIZE => {
-                if self.max_size > 0 && self.filesize + data.len() as u64 > self.max_size {
-                    if let Ok(_) = self.rename() {
-                        let _ = self.new_from_clone();
-                    }
+            self.filehandle = None;
+            self.restart_period();
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        let framed;
+        let data = match &mut self.chain {
+            Some(c) => {
+                framed = c.frame(data);
+                &framed[..]
+            }
+            None => data,
+        };
+        let path = self.path();
+        let file = match &mut self.filehandle {
+            Some(f) => f,
+            None => {
+                mkdirs(&path)?;
+                let f = Self::newfile(&path, self.settings.file_mode)?;
+                self.filesize = f.metadata()?.len();
+                self.filehandle.insert(f)
+            }
+        };
+        match self.settings.live_compression {
+            Some((_, interval)) => {
+                let live = self.live.get_or_insert_with(|| LiveEncoder::new(self.settings.compress_level, interval));
+                file.write_all(&live.write(data)?)?;
+            }
+            None if self.buffer.is_on() => {
+                if !self.buffer.is_attached() {
+                    self.buffer.attach(file.try_clone()?);
                 }
+                self.buffer.write(data)?;
+            }
+            None => {
+                file.write_all(data)?;
             }
         }
-        self.filehandle.write(data)?;
         self.filesize += data.len() as u64;
+        self.settings.metrics.written(data.len());
         Ok(())
     }
+
+    /// Syncs the file to disk, with a sync flush of a live compressed file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let flushed = self.sync();
+        if let Err(e) = &flushed {
+            self.report(LogError::WriteFailed, e);
+        }
+        flushed
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.buffer.drain()?;
+        match &mut self.filehandle {
+            Some(f) => {
+                if let Some(live) = &mut self.live {
+                    f.write_all(&live.flush()?)?;
+                }
+                f.sync_data()
+            }
+            None => Ok(()),
+        }
+    }
 }
 
-fn mkdirs(dir_path: &Path) -> io::Result<()> {
-    let parent_dir = dir_path.parent().ok_or_else(|| Error::new(ErrorKind::Other, ErrCode::NotFound.to_string()))?;
-    if !parent_dir.exists() {
-        fs::create_dir_all(parent_dir)?;
+impl Drop for FileHandler {
+    fn drop(&mut self) {
+        let _ = self.finish_live();
+    }
+}
+
+/// Creates the missing directories of the file `path`; a path without a
+/// directory, such as `app.log`, has none to create.
+fn mkdirs(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.exists() => fs::create_dir_all(dir).map_err(|e| mkdirs_error(dir, e)),
+        _ => Ok(()),
     }
-    Ok(())
 }
 
-static POOL: Lazy<ThreadPool> = Lazy::new(|| ThreadPool::new(4));
+/// `e` with the directory that could not be created, for the error handler.
+pub(crate) fn mkdirs_error(dir: &Path, e: io::Error) -> io::Error {
+    Error::new(e.kind(), format!("cannot create the directory {}: {}", dir.display(), e))
+}
+
+/// Compresses and prunes the backups after the rotations of every sync
+/// handler, on one low-priority thread, so the rotating line only pays for
+/// the rename. Rotations wait once 8 backups are pending.
+static POOL: Lazy<ThreadPool> = Lazy::new(|| ThreadPool::background("tklog-rotation", 8));
 
-fn rename(log_path: &Path, compress: bool, maxbackup: u32, backupsuffix: Option<String>) -> io::Result<()> {
-    let mut counter = 1;
+/// Waits up to `timeout` for the backups pending in `POOL`; false if some
+/// are left.
+pub(crate) fn drain_rotations(timeout: Duration) -> bool {
+    Lazy::get(&POOL).is_none_or(|pool| pool.wait_idle(timeout))
+}
+
+/// Renames `log_path` to its next backup, of the time cut `period` (its
+/// start and mode) or of a size cut, then compresses and prunes in `POOL`.
+fn rename(log_path: &Path, compress: bool, maxbackup: u32, settings: FileSettings, period: Option<(u64, MODE)>, panics: Arc<PanicCount>) -> io::Result<()> {
+    let maxbackup = settings.max_backups.unwrap_or(maxbackup);
+    let live = settings.live_compression.is_some();
     let file_stem = log_path.file_stem().unwrap_or_else(|| OsStr::new("tklog"));
     let extension = log_path.extension().map_or("", |e| e.to_str().unwrap()).to_owned();
+    let template = settings.backup_template.clone();
+    let backups = Backups::new(&file_stem.to_string_lossy(), &extension, template.as_ref());
+    let cutsec = period.map_or_else(|| localsec(std::time::SystemTime::now()), |(secs, _)| secs);
+    let backupsuffix = match &template {
+        Some(t) => t.time_text(cutsec),
+        None => period.map(|(secs, mode)| getbackup_with_time(secs, mode)),
+    };
+    let mut counter = next_backup_counter(log_path, &backups, backupsuffix.as_deref());
     let mut maxloop = 1 << 20;
     while maxloop > 0 {
         let mut parent = log_path.parent().ok_or_else(|| Error::new(ErrorKind::Other, ErrCode::NotFound.to_string()))?.to_path_buf();
@@ -155,33 +583,25 @@ fn rename(log_path: &Path, compress: bool, maxbackup: u32, backupsuffix: Option<
             suffix.push_str(extension.as_str());
         }
 
-        let new_name: String;
-
-        if backupsuffix.is_some() {
-            new_name = format!("{}_{}_{}{}", file_stem.to_string_lossy(), backupsuffix.as_ref().unwrap(), counter, suffix);
-        } else {
-            new_name = format!("{}_{}{}", file_stem.to_string_lossy(), counter, suffix);
-        }
+        let new_name = match (&template, &backupsuffix) {
+            (Some(t), _) => t.name(&file_stem.to_string_lossy(), &extension, cutsec, counter),
+            (None, Some(stamp)) => format!("{}_{}_{}{}", file_stem.to_string_lossy(), stamp, counter, suffix),
+            (None, None) => format!("{}_{}{}", file_stem.to_string_lossy(), counter, suffix),
+        };
 
         let new_path = parent.join(&new_name);
 
         let new_path_gz = parent.join(format!("{}.gz", new_path.display().to_string()));
         if !new_path.exists() && !new_path_gz.exists() {
-            let r = fs::rename(log_path, &new_path);
-            if r.is_err() && maxloop <= 0 {
-                return Err(r.err().unwrap());
-            } else {
-                let p = parent.clone();
-                let e = extension.clone();
-                let fname = file_stem.to_string_lossy().to_string().clone();
-                POOL.execute(move || {
-                    if compress {
-                        let _ = gzip(new_path.to_str().unwrap());
-                    }
-                    if maxbackup > 0 {
-                        let _ = maxbackup_with_size(&p, e, fname, maxbackup);
-                    }
-                });
+            // Another process may have taken the name since: the next one then.
+            let r = if live { rename_unless_exists(&settings.live_path(log_path), &new_path_gz) } else { rename_unless_exists(log_path, &new_path) };
+            if r? {
+                settings.metrics.rotated();
+                let renamed = if live { &new_path_gz } else { &new_path };
+                if let Err(e) = set_file_mode(renamed, settings.file_mode) {
+                    settings.errors.report(LogError::RotateFailed(renamed.clone(), e));
+                }
+                archive(renamed.clone(), parent, backups, log_path.to_string_lossy().to_string(), compress, maxbackup, settings, panics);
                 return Ok(());
             }
         }
@@ -190,9 +610,85 @@ fn rename(log_path: &Path, compress: bool, maxbackup: u32, backupsuffix: Option<
     Ok(())
 }
 
-fn filter_files(dir_path: &Path, extension: String, filename: String, maxbackup: u32) -> io::Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-    let mut sortvec = Vec::new();
+/// Compresses the backup `backup` of `filename` unless live compression
+/// did, or those of the `backups` of `dir` past the newest of
+/// `set_compress_after`, then prunes them and calls the rotation handler,
+/// in `POOL`.
+#[allow(clippy::too_many_arguments)]
+fn archive(backup: PathBuf, dir: PathBuf, backups: Backups, filename: String, compress: bool, maxbackup: u32, settings: FileSettings, panics: Arc<PanicCount>) {
+    // Compressed below: no pruning gets to it first.
+    if compress && settings.live_compression.is_none() && settings.compress_after == 0 {
+        crate::queue_backup(&backup);
+    }
+    POOL.execute(move || {
+        let mut backup = backup;
+        let mut compression = CompressDecision::Disabled;
+        if settings.live_compression.is_some() {
+            compression = CompressDecision::Compressed;
+        } else if compress && settings.compress_after > 0 {
+            compression = CompressDecision::Deferred;
+            match backup_files(&dir, &backups) {
+                Ok(files) => {
+                    for old in due_for_compression(files, &backups, settings.compress_after) {
+                        compress_backup(&old, &settings);
+                    }
+                }
+                Err(error) => settings.errors.report(LogError::BackupCleanupFailed(dir.clone(), error)),
+            }
+        } else if compress {
+            compression = compress_backup(&backup, &settings);
+            crate::dequeue_backup(&backup);
+            if compression == CompressDecision::Compressed {
+                backup = PathBuf::from(format!("{}.gz", backup.display()));
+            }
+        }
+        if maxbackup > 0 {
+            if let Err(error) = maxbackup_with_size(&dir, &backups, maxbackup, settings.prune_policy) {
+                settings.errors.report(LogError::BackupCleanupFailed(dir.clone(), error));
+            }
+        }
+        if let Some(max_age) = settings.backup_max_age {
+            if let Err(error) = expired_files(&dir, &backups, max_age).and_then(delete_files) {
+                settings.errors.report(LogError::BackupCleanupFailed(dir.clone(), error));
+            }
+        }
+        if let Some(handler) = settings.rotation_handler {
+            panics.call("rotation handler", || handler(&RotationEvent { filename, backup, compression }));
+        }
+    });
+}
+
+/// Gzips `backup` if there is room, reporting a failure.
+fn compress_backup(backup: &Path, settings: &FileSettings) -> CompressDecision {
+    let compression = match space_preflight(backup, settings) {
+        Some(d) => d,
+        None => match gzip(backup.to_str().unwrap(), settings.compress_level, settings.compress_skip_ratio, settings.file_mode, settings.compress_keep_original) {
+            Ok(d) => d,
+            Err(e) => {
+                let failed = CompressDecision::Failed(e.to_string());
+                settings.errors.report(LogError::CompressFailed(backup.to_path_buf(), e));
+                failed
+            }
+        },
+    };
+    if compression == CompressDecision::Compressed {
+        settings.metrics.compressed();
+    }
+    compression
+}
+
+fn filter_files(dir_path: &Path, backups: &Backups, maxbackup: u32, policy: PrunePolicy) -> io::Result<Vec<PathBuf>> {
+    Ok(backups_to_prune(backup_files(dir_path, backups)?, backups, maxbackup, policy))
+}
+
+/// The `backups` cut more than `max_age` ago.
+fn expired_files(dir_path: &Path, backups: &Backups, max_age: Duration) -> io::Result<Vec<PathBuf>> {
+    Ok(backups_older_than(backup_files(dir_path, backups)?, max_age, epoch_secs()))
+}
+
+/// The files of `dir_path` among `backups`, as (modified secs, path).
+fn backup_files(dir_path: &Path, backups: &Backups) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut candidates = Vec::new();
     for entry in fs::read_dir(dir_path)? {
         let entry = entry?;
         let path = entry.path();
@@ -203,34 +699,62 @@ fn filter_files(dir_path: &Path, extension: String, filename: String, maxbackup:
         let sec = md.modified()?.duration_since(std::time::UNIX_EPOCH).expect("").as_secs();
 
         if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-            let mut suffix = String::new();
-            if !extension.is_empty() {
-                suffix.push_str("\\.");
-                suffix.push_str(extension.as_str());
-            }
-            let parrent = format!("{}{}{}{}{}{}", "^", filename, "(_\\d+){0,}", "_\\d+", suffix, "(\\.gz){0,}$");
-            let re = Regex::new(parrent.as_str()).unwrap();
-            if re.is_match(file_name) {
-                sortvec.push((sec, path.clone()))
+            if backups.is_match(file_name) {
+                candidates.push((sec, path.clone()))
             }
         }
     }
-    sortvec.sort_by(|a, b| a.0.cmp(&b.0));
-    if sortvec.len() > maxbackup as usize {
-        for tuple in sortvec.iter().take(sortvec.len() - maxbackup as usize) {
-            files.push(tuple.1.clone());
-        }
-    }
+    Ok(candidates)
+}
+
+
+/// Applies count-based retention to the backups of `log_path` right away,
+/// the same way a rotation does, and returns the deleted files.
+pub fn prune_backups(log_path: &Path, maxbackups: u32, policy: PrunePolicy) -> io::Result<Vec<PathBuf>> {
+    let (parent, extension, file_stem) = backup_location(log_path)?;
+    let files = filter_files(&parent, &Backups::new(&file_stem, &extension, None), maxbackups, policy)?;
+    delete_files(files.clone())?;
     Ok(files)
 }
 
+/// Applies age-based retention to the backups of `log_path` right away,
+/// the same way a rotation does, and returns the deleted files.
+pub fn expire_backups(log_path: &Path, max_age: Duration) -> io::Result<Vec<PathBuf>> {
+    let (parent, extension, file_stem) = backup_location(log_path)?;
+    let files = expired_files(&parent, &Backups::new(&file_stem, &extension, None), max_age)?;
+    delete_files(files.clone())?;
+    Ok(files)
+}
+
+/// The directory, extension and stem the backups of `log_path` go by.
+pub(crate) fn backup_location(log_path: &Path) -> io::Result<(PathBuf, String, String)> {
+    let file_stem = log_path.file_stem().unwrap_or_else(|| OsStr::new("tklog")).to_string_lossy().to_string();
+    let extension = log_path.extension().map_or("", |e| e.to_str().unwrap()).to_owned();
+    let mut parent = log_path.parent().ok_or_else(|| Error::other(ErrCode::NotFound.to_string()))?.to_path_buf();
+    if parent.as_os_str().is_empty() {
+        parent = env::current_dir()?;
+    }
+    Ok((parent, extension, file_stem))
+}
+
+/// Deletes `files`, reporting each one; the first failure is returned once
+/// the rest have been tried.
 fn delete_files(files: Vec<PathBuf>) -> io::Result<()> {
-    Ok(for file in files {
-        fs::remove_file(file)?;
-    })
+    let mut result = Ok(());
+    for file in files {
+        match fs::remove_file(&file) {
+            Ok(()) => diagnostics::report(Category::Pruned, Some(&file), format!("pruned {}", file.display())),
+            Err(e) => {
+                diagnostics::report(Category::DeleteFailed, Some(&file), format!("cannot delete {}: {}", file.display(), e));
+                result = result.and(Err(e));
+            }
+        }
+    }
+    diagnostics::flush();
+    result
 }
 
-fn maxbackup_with_size(parant: &PathBuf, extension: String, filename: String, maxbackup: u32) -> io::Result<()> {
-    let matched_files = filter_files(parant, extension, filename, maxbackup)?;
+fn maxbackup_with_size(parant: &Path, backups: &Backups, maxbackup: u32, policy: PrunePolicy) -> io::Result<()> {
+    let matched_files = filter_files(parant, backups, maxbackup, policy)?;
     delete_files(matched_files)
 }
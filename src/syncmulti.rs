@@ -18,6 +18,12 @@
 
 #[macro_export]
 macro_rules! traces {
+    ($logger:expr, || $body:expr) => {
+        $crate::logs_common!($logger, $crate::LEVEL::Trace, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::logs_common!(@fields $logger, $crate::LEVEL::Trace, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::logs_common!($logger, $crate::LEVEL::Trace, $($arg),*);
     };
@@ -27,6 +33,12 @@ macro_rules! traces {
 //Debug log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! debugs {
+    ($logger:expr, || $body:expr) => {
+        $crate::logs_common!($logger, $crate::LEVEL::Debug, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::logs_common!(@fields $logger, $crate::LEVEL::Debug, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::logs_common!($logger, $crate::LEVEL::Debug, $($arg),*);
     };
@@ -36,6 +48,12 @@ macro_rules! debugs {
 //Info log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! infos {
+    ($logger:expr, || $body:expr) => {
+        $crate::logs_common!($logger, $crate::LEVEL::Info, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::logs_common!(@fields $logger, $crate::LEVEL::Info, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::logs_common!($logger, $crate::LEVEL::Info, $($arg),*);
     };
@@ -45,6 +63,12 @@ macro_rules! infos {
 // Error log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! warns {
+    ($logger:expr, || $body:expr) => {
+        $crate::logs_common!($logger, $crate::LEVEL::Warn, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::logs_common!(@fields $logger, $crate::LEVEL::Warn, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::logs_common!($logger, $crate::LEVEL::Warn, $($arg),*);
     };
@@ -54,6 +78,12 @@ macro_rules! warns {
 // Error log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! errors {
+    ($logger:expr, || $body:expr) => {
+        $crate::logs_common!($logger, $crate::LEVEL::Error, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::logs_common!(@fields $logger, $crate::LEVEL::Error, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::logs_common!($logger, $crate::LEVEL::Error, $($arg),*);
     };
@@ -63,6 +93,12 @@ macro_rules! errors {
 // Fatal log macro, call secondary macro processing logic
 #[macro_export]
 macro_rules! fatals {
+    ($logger:expr, || $body:expr) => {
+        $crate::logs_common!($logger, $crate::LEVEL::Fatal, $crate::LazyMessage(|| $body));
+    };
+    ($logger:expr, $($arg:expr),+ ; $($fields:tt)*) => {
+        $crate::logs_common!(@fields $logger, $crate::LEVEL::Fatal, ($($arg),*), $($fields)*);
+    };
     ($logger:expr, $($arg:expr),+) => {
         $crate::logs_common!($logger, $crate::LEVEL::Fatal, $($arg),*);
     };
@@ -73,22 +109,10 @@ macro_rules! fatals {
 macro_rules! formats {
     ($logger:expr, $level:expr, $($arg:expr),*) => {
         let level:$crate::LEVEL = $level;
-        unsafe {
-            let log:&mut Arc<Mutex<tklog::sync::Logger>> = $logger;
-            let mut logger  = log.lock().unwrap();
-            let module = module_path!();
-            if logger.get_level(module) <= level {
-                let mut file = "";
-                let mut line = 0;
-                if logger.is_file_line($level,module) {
-                    file = file!();
-                    line = line!();
-                }
-                let ss = logger.fmt(module,$level, file, line, format!($($arg),*));
-                if !ss.is_empty(){
-                    logger.print($level,module,ss);
-                }
-            }
+        if $crate::compiled(level) && !$crate::reentrant(level, module_path!(), || format!($($arg),*)) {
+            let log: &mut ::std::sync::Arc<::std::sync::Mutex<$crate::sync::Logger>> = $logger;
+            let site = $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!());
+            $crate::sync::log_line(log, level, module_path!(), site, None, |_| format!($($arg),*), $crate::fields::FieldMap::new, $crate::sync::Logger::print);
         }
     };
     () => {};
@@ -97,25 +121,15 @@ macro_rules! formats {
 #[macro_export]
 macro_rules! logs_common {
     ($logger:expr, $level:expr, $($arg:expr),*) => {
-        unsafe {
-            let  log:&mut Arc<Mutex<tklog::sync::Logger>> = $logger;
-            let mut logger  = log.lock().unwrap();
-            let module = module_path!();
-            if logger.get_level(module) <= $level {
-                let formatted_args: Vec<String> = vec![$(format!("{}", $arg)),*];
-                let mut file = "";
-                let mut line = 0;
-                if logger.is_file_line($level,module) {
-                    file = file!();
-                    line = line!();
-                }
-                let msg: String = formatted_args.join(logger.get_separator().as_str());
-                let ss = logger.fmt(module,$level, file, line, msg);
-                if !ss.is_empty(){
-                    logger.print($level,module, ss);
-                }
-            }
-        }
+        $crate::logs_common!(@fields $logger, $level, ($($arg),*),)
     };
+    (@fields $logger:expr, $level:expr, ($($arg:expr),*), $($fields:tt)*) => {{
+        let level: $crate::LEVEL = $level;
+        if $crate::compiled(level) && !$crate::reentrant(level, module_path!(), || $crate::message_of("", &[$(&$arg),*])) {
+            let log: &mut ::std::sync::Arc<::std::sync::Mutex<$crate::sync::Logger>> = $logger;
+            let site = $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!());
+            $crate::sync::log_line(log, level, module_path!(), site, None, |sep| $crate::message_of(sep, &[$(&$arg),*]), || $crate::fields_of!($($fields)*), $crate::sync::Logger::print);
+        }
+    }};
     () => {};
 }
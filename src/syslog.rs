@@ -0,0 +1,214 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lines sent to a syslog daemon as RFC 5424 messages, over a unix socket
+//! or UDP; needs the `syslog` feature.
+//!
+//! With `Logger::set_syslog` every line goes to syslog instead of the
+//! console and the files, with the severity of its level, the time, the
+//! host name, the process ID and the formatted line as the message. The
+//! socket is never waited for: while the daemon can't take a line, the
+//! line goes to the console and the files as without syslog, and a
+//! `Category::Syslog` diagnostic tells why. The socket is opened again at
+//! most once a second meanwhile.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::syslog::{Facility, SyslogConfig, Transport};
+//!
+//! let mut log = tklog::sync::Logger::new();
+//! log.set_syslog(SyslogConfig { transport: Transport::UnixSocket("/dev/log".into()), facility: Facility::Local0, app_name: "shop".to_string() });
+//! ```
+
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+use crate::{
+    diagnostics::{self, Category},
+    LEVEL,
+};
+
+/// How long a failed socket is left closed before it is opened again.
+const RETRY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transport {
+    /// A datagram socket such as `/dev/log`; unix only.
+    UnixSocket(PathBuf),
+    Udp(SocketAddr),
+}
+
+/// The facilities of RFC 5424, most often `User` or one of the `Local`s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Facility {
+    Kern = 0,
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyslogConfig {
+    pub transport: Transport,
+    pub facility: Facility,
+    /// The APP-NAME of the messages; `-` when empty.
+    pub app_name: String,
+}
+
+/// The severity of RFC 5424 for `level`.
+pub fn severity(level: LEVEL) -> u8 {
+    match level {
+        LEVEL::Trace | LEVEL::Debug => 7,
+        LEVEL::Info => 6,
+        LEVEL::Warn => 4,
+        LEVEL::Error => 3,
+        LEVEL::Fatal | LEVEL::Off => 2,
+    }
+}
+
+enum Socket {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+}
+
+impl Socket {
+    fn open(transport: &Transport) -> io::Result<Self> {
+        match transport {
+            #[cfg(unix)]
+            Transport::UnixSocket(path) => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(path)?;
+                socket.set_nonblocking(true)?;
+                Ok(Socket::Unix(socket))
+            }
+            #[cfg(not(unix))]
+            Transport::UnixSocket(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets need a unix system")),
+            Transport::Udp(addr) => {
+                let socket = UdpSocket::bind(if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+                socket.connect(addr)?;
+                socket.set_nonblocking(true)?;
+                Ok(Socket::Udp(socket))
+            }
+        }
+    }
+
+    fn send(&self, message: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Socket::Unix(socket) => socket.send(message),
+            Socket::Udp(socket) => socket.send(message),
+        }
+    }
+}
+
+/// The syslog of a logger: its config and socket, if open.
+pub(crate) struct Syslog {
+    config: SyslogConfig,
+    hostname: String,
+    socket: Option<Socket>,
+    /// When a failed socket may be opened again.
+    retry_at: Option<Instant>,
+}
+
+impl Syslog {
+    pub(crate) fn new(config: SyslogConfig) -> Self {
+        Syslog { config, hostname: hostname(), socket: None, retry_at: None }
+    }
+
+    /// Sends the line `body` of `level`; false when it is refused or the
+    /// socket is failing, for the line to go to the console and files.
+    pub(crate) fn send(&mut self, level: LEVEL, body: &str) -> bool {
+        if self.socket.is_none() {
+            if self.retry_at.is_some_and(|at| Instant::now() < at) {
+                return false;
+            }
+            match Socket::open(&self.config.transport) {
+                Ok(socket) => self.socket = Some(socket),
+                Err(e) => return self.failed(e),
+            }
+        }
+        let message = self.message(level, body);
+        match self.socket.as_ref().map(|s| s.send(message.as_bytes())) {
+            Some(Ok(_)) => {
+                self.retry_at = None;
+                true
+            }
+            Some(Err(e)) => self.failed(e),
+            None => false,
+        }
+    }
+
+    fn failed(&mut self, e: io::Error) -> bool {
+        self.socket = None;
+        self.retry_at = Some(Instant::now() + RETRY);
+        diagnostics::report(Category::Syslog, None, format!("syslog {:?}: {}; lines go to the console and files", self.config.transport, e));
+        false
+    }
+
+    fn message(&self, level: LEVEL, body: &str) -> String {
+        let app_name = if self.config.app_name.is_empty() { "-" } else { &self.config.app_name };
+        message(self.config.facility, level, &crate::now().format("%Y-%m-%dT%H:%M:%S%.6f%:z").to_string(), &self.hostname, app_name, body)
+    }
+}
+
+/// The RFC 5424 message of a line, without structured data or message ID.
+pub(crate) fn message(facility: Facility, level: LEVEL, time: &str, hostname: &str, app_name: &str, body: &str) -> String {
+    let pri = facility as u8 * 8 + severity(level);
+    format!("<{}>1 {} {} {} {} - - {}", pri, time, hostname, app_name, std::process::id(), body.trim_end_matches('\n'))
+}
+
+/// The host name, `-` when it can't be told.
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return "-".to_string();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    match std::str::from_utf8(&buf[..len]) {
+        Ok(name) if !name.is_empty() => name.to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").ok().filter(|name| !name.is_empty()).unwrap_or_else(|| "-".to_string())
+}
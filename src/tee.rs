@@ -0,0 +1,61 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tee files: more files written with every line of the logger, each in
+//! its own output mode and with its own rotation, e.g. the text lines for
+//! people and the same lines as JSON for a pipeline.
+//!
+//! A tee gets each record that passes the level filters, laid out from
+//! the record rather than from the primary line: a JSON tee of a text
+//! logger has the `preset_k8s` keys, a text tee of a `preset_k8s` logger
+//! the plain text line. Only text and JSON have a layout of their own.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::cut::CutSize;
+//! use tklog::output::OutputMode;
+//! use tklog::sync::Logger;
+//!
+//! let mut log = Logger::new();
+//! log.set_cutmode_by_size("app.log", 1 << 20, 7, false);
+//! let json = CutSize::builder().file("app.json.log").max_size_mb(64).backups(7).build().unwrap();
+//! log.add_tee_file(OutputMode::Json, json).unwrap();
+//! ```
+
+use crate::{output::OutputMode, preset::K8sPreset, Error};
+
+/// How one tee file lays out its lines.
+#[derive(Clone)]
+pub(crate) enum TeeLayout {
+    Text,
+    Json(K8sPreset),
+}
+
+impl TeeLayout {
+    /// `Error::UnsupportedTeeMode` for the modes without a layout of their own.
+    pub(crate) fn new(mode: OutputMode) -> Result<Self, Error> {
+        match mode {
+            OutputMode::Text => Ok(TeeLayout::Text),
+            OutputMode::Json => Ok(TeeLayout::Json(K8sPreset::from_env())),
+            mode => Err(Error::UnsupportedTeeMode(mode)),
+        }
+    }
+}
+
+/// The owner name of a tee file in `paths`.
+pub(crate) fn owner(filename: &str) -> String {
+    format!("tee file `{}`", filename)
+}
@@ -14,34 +14,48 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crossbeam_channel::{unbounded, Receiver, Sender};
-use std::sync::Arc;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 type Task = Box<dyn FnOnce() + Send + 'static>;
 
 pub struct ThreadPool {
     _workers: Vec<Worker>,
     sender: Sender<Task>,
+    pending: Arc<Pending>,
 }
 
-impl ThreadPool {
-    pub fn new(mut size: usize) -> ThreadPool {
-        if size == 0 {
-            size = 1
-        }
-
-        let (sender, receiver) = unbounded();
-        let receiver = Arc::new(receiver);
-        let mut workers = Vec::with_capacity(size);
+/// The tasks sent and not yet done, for `ThreadPool::wait_idle`.
+#[derive(Default)]
+struct Pending {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
 
-        for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+impl Pending {
+    fn add(&self, n: isize) {
+        let mut count = self.count.lock().unwrap_or_else(|e| e.into_inner());
+        *count = count.saturating_add_signed(n);
+        if *count == 0 {
+            self.idle.notify_all();
         }
+    }
+}
 
+impl ThreadPool {
+    /// One worker thread named `name`, at a lower priority where the
+    /// platform lets a thread have its own, taking up to `capacity` tasks
+    /// ahead; `execute` waits for room beyond that.
+    pub fn background(name: &str, capacity: usize) -> ThreadPool {
+        let (sender, receiver) = bounded(capacity);
+        let pending = Arc::new(Pending::default());
+        let worker = Worker::new(0, Arc::new(receiver), Arc::clone(&pending), name);
         ThreadPool {
-            _workers: workers,
+            _workers: vec![worker],
             sender,
+            pending,
         }
     }
 
@@ -50,8 +64,23 @@ impl ThreadPool {
         F: FnOnce() + Send + 'static,
     {
         let task = Box::new(f);
+        self.pending.add(1);
         self.sender.send(task).expect("send error");
     }
+
+    /// Waits up to `timeout` for every task sent so far to be done; false
+    /// if some are left.
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.pending.count.lock().unwrap_or_else(|e| e.into_inner());
+        while *count > 0 {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                return false;
+            };
+            count = self.pending.idle.wait_timeout(count, left).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        true
+    }
 }
 
 struct Worker {
@@ -60,15 +89,30 @@ struct Worker {
 }
 
 impl Worker {
-    fn new(id: usize, receiver: Arc<Receiver<Task>>) -> Worker {
-        let thread = thread::spawn(move || {
-            while let Ok(task) = receiver.recv() {
-                task();
-            }
-        });
+    fn new(id: usize, receiver: Arc<Receiver<Task>>, pending: Arc<Pending>, name: &str) -> Worker {
+        let thread = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                lower_priority();
+                while let Ok(task) = receiver.recv() {
+                    // A task that panics is done too.
+                    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(task));
+                    pending.add(-1);
+                }
+            })
+            .expect("spawn error");
         Worker {
             _id: id,
             _thread: thread,
         }
     }
 }
+
+/// Sets the nice value of the calling thread to 10. Linux alone gives each
+/// thread its own; elsewhere it would slow the whole process.
+fn lower_priority() {
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, 10);
+    }
+}
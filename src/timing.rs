@@ -0,0 +1,292 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-label timings of a run, a poor man's profiler for batch jobs.
+//!
+//! With `tklog::set_timing_report(true)`, every `Timer`, `timer!` and
+//! `timers!` adds its duration to its label's count, total and max when
+//! dropped; `tklog::timing_report`
+//! gives them sorted by total, as a table or as JSON. Past `MAX_LABELS`
+//! labels, new ones are counted under `other`.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{info, timing::Timer};
+//!
+//! tklog::set_timing_report(true);
+//! for _ in 0..3 {
+//!     let _t = Timer::start("load");
+//! }
+//! info!(tklog::timing_report());
+//! ```
+//!
+//! Under `tklog::flush_guard()`, the report is also logged at Info on `LOG`
+//! as the guard is dropped, before the lines are flushed.
+//!
+//! `timer!` and `timers!` log the duration of their scope instead, as a
+//! line `load users took 12.4ms` at their level, from where they are:
+//!
+//! ```no_run
+//! use tklog::{timer, LEVEL};
+//!
+//! fn load() {
+//!     let _t = timer!(LEVEL::Debug, "load users");
+//!     // ...
+//! }
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{preset::json_string, sync::Logger, CallSite, LEVEL, PRINTMODE};
+
+/// The labels kept apart; later ones go to `other`.
+pub const MAX_LABELS: usize = 256;
+
+const OTHER: &str = "other";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMINGS: Lazy<Mutex<HashMap<String, LabelTiming>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The timings of one label.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LabelTiming {
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+/// Times the scope it lives in under `label`, for the timing report.
+pub struct Timer {
+    label: Cow<'static, str>,
+    start: Instant,
+}
+
+impl Timer {
+    pub fn start(label: impl Into<Cow<'static, str>>) -> Self {
+        Timer { label: label.into(), start: Instant::now() }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        record(&self.label, self.start.elapsed());
+    }
+}
+
+/// The guard of `timer!` and `timers!`: logs `<label> took <elapsed>` at
+/// its level when dropped, or at once with `finish`. The line is left out
+/// as any line of its level would be, checked then; the duration goes to
+/// the timing report either way.
+#[must_use = "a timer logs when dropped, right away unless bound to a name"]
+pub struct LogTimer {
+    level: LEVEL,
+    label: Cow<'static, str>,
+    module: &'static str,
+    site: CallSite,
+    start: Instant,
+    /// `None` for `LOG`.
+    logger: Option<Arc<Mutex<Logger>>>,
+    armed: bool,
+}
+
+impl LogTimer {
+    #[doc(hidden)]
+    pub fn new(logger: Option<Arc<Mutex<Logger>>>, level: LEVEL, label: impl Into<Cow<'static, str>>, module: &'static str, site: CallSite) -> Self {
+        LogTimer { level, label: label.into(), module, site, start: Instant::now(), logger, armed: true }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Logs the line now rather than when dropped.
+    pub fn finish(mut self) {
+        self.emit();
+    }
+
+    fn emit(&mut self) {
+        if !std::mem::take(&mut self.armed) {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        record(&self.label, elapsed);
+        if !crate::compiled(self.level) {
+            return;
+        }
+        let (level, module, site) = (self.level, self.module, self.site);
+        let message = || format!("{} took {:.1?}", self.label, elapsed);
+        if crate::reentrant(level, module, message) {
+            return;
+        }
+        let line = |logger: &mut Logger| {
+            if logger.get_level(module) > level {
+                return None;
+            }
+            let (file, line) = if logger.is_file_line(level, module) { site.get(logger.location_strategy()) } else { ("", 0) };
+            let s = crate::in_function(site.function, || logger.fmt(module, level, file, line, message()));
+            (!s.is_empty()).then_some(s)
+        };
+        match &self.logger {
+            None if !crate::above_floor(level) => {}
+            None => {
+                let mut logger = crate::global_for_line();
+                if let Some(s) = line(&mut logger) {
+                    if logger.mode == PRINTMODE::DELAY {
+                        logger.log(level, module, s);
+                    } else {
+                        logger.safeprint(level, module, s);
+                    }
+                }
+            }
+            Some(log) => {
+                let mut logger = log.lock().unwrap_or_else(|e| e.into_inner());
+                let _inside = crate::Inside::enter();
+                if let Some(s) = line(&mut logger) {
+                    logger.print(level, module, s);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for LogTimer {
+    fn drop(&mut self) {
+        self.emit();
+    }
+}
+
+/// A `LogTimer` logging on `LOG`: `let _t = timer!(LEVEL::Debug, "load
+/// users");` logs `load users took 12.4ms` at Debug when `_t` goes out of
+/// scope.
+#[macro_export]
+macro_rules! timer {
+    ($level:expr, $label:expr) => {
+        $crate::timing::LogTimer::new(None, $level, $label, module_path!(), $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!()))
+    };
+}
+
+/// `timer!` on the logger of `infos!` and the others:
+/// `timers!(&mut log, LEVEL::Debug, "load users")`.
+#[macro_export]
+macro_rules! timers {
+    ($logger:expr, $level:expr, $label:expr) => {
+        $crate::timing::LogTimer::new(
+            Some(::std::sync::Arc::clone($logger)),
+            $level,
+            $label,
+            module_path!(),
+            $crate::CallSite::here(file!(), line!()).in_function($crate::function_name!()),
+        )
+    };
+}
+
+/// Adds `elapsed` to `label`, if the report is on.
+pub fn record(label: &str, elapsed: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let label = if timings.contains_key(label) || timings.len() < MAX_LABELS { label } else { OTHER };
+    let t = timings.entry(label.to_string()).or_default();
+    t.count += 1;
+    t.total += elapsed;
+    t.max = t.max.max(elapsed);
+}
+
+/// The timings so far, by total duration, longest first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimingReport {
+    pub labels: Vec<(String, LabelTiming)>,
+}
+
+impl TimingReport {
+    /// `{"label":{"count":1,"total_us":2,"max_us":2},...}`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        for (i, (l, t)) in self.labels.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            json_string(&mut out, l);
+            let _ = write!(out, ":{{\"count\":{},\"total_us\":{},\"max_us\":{}}}", t.count, t.total.as_micros(), t.max.as_micros());
+        }
+        out.push('}');
+        out
+    }
+}
+
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.labels.iter().map(|(l, _)| l.len()).max().unwrap_or(0).max("label".len());
+        writeln!(f, "{:<width$} {:>10} {:>14} {:>14}", "label", "count", "total", "max")?;
+        for (l, t) in &self.labels {
+            writeln!(f, "{:<width$} {:>10} {:>14} {:>14}", l, t.count, format!("{:?}", t.total), format!("{:?}", t.max))?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Logs the report on `LOG` at Info, for `FlushGuard`: nothing while the
+/// report is off or empty.
+pub(crate) fn log_report() {
+    const MODULE: &str = "tklog::timing";
+    if !ENABLED.load(Ordering::Relaxed) || !crate::compiled(LEVEL::Info) || !crate::above_floor(LEVEL::Info) {
+        return;
+    }
+    let report = report();
+    if report.labels.is_empty() {
+        return;
+    }
+    let message = || format!("timing report\n{}", report);
+    if crate::reentrant(LEVEL::Info, MODULE, message) {
+        return;
+    }
+    let mut logger = crate::global_for_line();
+    if logger.get_level(MODULE) > LEVEL::Info {
+        return;
+    }
+    let s = logger.fmt(MODULE, LEVEL::Info, "", 0, message());
+    if s.is_empty() {
+        return;
+    }
+    if logger.mode == PRINTMODE::DELAY {
+        logger.log(LEVEL::Info, MODULE, s);
+    } else {
+        logger.safeprint(LEVEL::Info, MODULE, s);
+    }
+}
+
+pub(crate) fn report() -> TimingReport {
+    let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut labels: Vec<(String, LabelTiming)> = timings.iter().map(|(l, t)| (l.clone(), *t)).collect();
+    labels.sort_by(|a, b| b.1.total.cmp(&a.1.total).then_with(|| a.0.cmp(&b.0)));
+    TimingReport { labels }
+}
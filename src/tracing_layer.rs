@@ -0,0 +1,168 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! tklog as a sink of `tracing` events; needs the `tracing` feature.
+//!
+//! `TklogLayer` turns every event into a line of the global `LOG` or of a
+//! logger of its own. The target of the event is its module, so the
+//! levels of `set_mod_option` apply; the fields other than `message`
+//! follow the message as `key=value`, and with `with_span_names` the names
+//! of the entered spans, outermost first, come before it. Files, rotation
+//! and compression are the logger's own.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::tracing_layer::TklogLayer;
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! let subscriber = tracing_subscriber::registry().with(TklogLayer::global().with_span_names(true));
+//! tracing::subscriber::set_global_default(subscriber).unwrap();
+//! tracing::info!(user = "ann", "signed in");
+//! ```
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Write as _},
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{fields::FieldMap, global_for_line, reentrant, sync, Inside, LEVEL, PRINTMODE};
+
+/// A `tracing_subscriber::Layer` writing events into tklog.
+pub struct TklogLayer {
+    logger: Option<Arc<Mutex<sync::Logger>>>,
+    span_names: bool,
+}
+
+impl TklogLayer {
+    /// A layer writing into the global `LOG`.
+    pub fn global() -> Self {
+        TklogLayer { logger: None, span_names: false }
+    }
+
+    /// A layer writing into `logger`, the way the `infos!` … macros do.
+    pub fn new(logger: Arc<Mutex<sync::Logger>>) -> Self {
+        TklogLayer { logger: Some(logger), span_names: false }
+    }
+
+    /// Puts the names of the entered spans before the message, as in
+    /// `request:db: query failed`. Default: false.
+    pub fn with_span_names(mut self, on: bool) -> Self {
+        self.span_names = on;
+        self
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TklogLayer {
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = tracing_level(*metadata.level());
+        let module = metadata.target();
+        let message = || {
+            let mut fields = EventFields::default();
+            event.record(&mut fields);
+            let mut message = String::new();
+            if self.span_names {
+                for span in ctx.event_scope(event).into_iter().flat_map(|scope| scope.from_root()) {
+                    let _ = write!(message, "{}:", span.name());
+                }
+                if !message.is_empty() {
+                    message.push(' ');
+                }
+            }
+            message.push_str(&fields.message);
+            fields.fields.append_to(message)
+        };
+        if reentrant(level, module, message) {
+            return;
+        }
+        let (file, line) = (metadata.file().unwrap_or(""), metadata.line().unwrap_or(0));
+        match &self.logger {
+            None => {
+                if !crate::above_floor(level) {
+                    return;
+                }
+                let mut logger = global_for_line();
+                if logger.get_level(module) > level {
+                    return;
+                }
+                let (file, line) = if logger.is_file_line(level, module) { (file, line) } else { ("", 0) };
+                let s = logger.fmt(module, level, file, line, message());
+                if s.is_empty() {
+                    return;
+                }
+                if logger.mode == PRINTMODE::DELAY {
+                    logger.log(level, Cow::Borrowed(module), s);
+                } else {
+                    logger.safeprint(level, module, s);
+                }
+            }
+            Some(logger) => {
+                let mut logger = logger.lock().unwrap_or_else(|e| e.into_inner());
+                let _inside = Inside::enter();
+                if logger.get_level(module) > level {
+                    return;
+                }
+                let (file, line) = if logger.is_file_line(level, module) { (file, line) } else { ("", 0) };
+                let s = logger.fmt(module, level, file, line, message());
+                if !s.is_empty() {
+                    logger.print(level, module, s);
+                }
+            }
+        }
+    }
+}
+
+fn tracing_level(level: Level) -> LEVEL {
+    match level {
+        Level::TRACE => LEVEL::Trace,
+        Level::DEBUG => LEVEL::Debug,
+        Level::INFO => LEVEL::Info,
+        Level::WARN => LEVEL::Warn,
+        Level::ERROR => LEVEL::Error,
+    }
+}
+
+/// The message of an event and its other fields.
+#[derive(Default)]
+struct EventFields {
+    message: String,
+    fields: FieldMap,
+}
+
+impl Visit for EventFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields.insert(field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            self.fields.insert(field.name(), format!("{:?}", value));
+        }
+    }
+}
@@ -34,6 +34,8 @@ pub struct Trie<V> {
     root: TrieNode<V>,
     count: i32,
     cache: HashMap<String, Option<V>>, 
+    /// Bumped on every change, for the caches of lookups kept elsewhere.
+    generation: u64,
 }
 
 impl<V: Clone> Trie<V> {
@@ -42,6 +44,7 @@ impl<V: Clone> Trie<V> {
             root: TrieNode::new(),
             count: 0,
             cache: HashMap::new(),  
+            generation: 0,
         }
     }
 
@@ -49,20 +52,78 @@ impl<V: Clone> Trie<V> {
         self.count
     }
 
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn changed(&mut self) {
+        self.cache.clear();
+        self.generation += 1;
+    }
+
     pub fn insert(&mut self, pattern: &str, module: V) {
         let segments: Vec<&str> = pattern.split("::").collect();
         let mut node = &mut self.root;
         for segment in segments {
             node = node.children.entry(segment.to_string()).or_insert_with(TrieNode::new);
         }
-        node.module = Some(module);
-        self.count += 1;
+        if node.module.replace(module).is_none() {
+            self.count += 1;
+        }
+        self.changed();
+    }
+
+    /// The value stored for exactly `pattern`, without wildcard matching.
+    pub fn get_pattern_mut(&mut self, pattern: &str) -> Option<&mut V> {
+        self.changed();
+        let mut node = &mut self.root;
+        for segment in pattern.split("::") {
+            node = node.children.get_mut(segment)?;
+        }
+        node.module.as_mut()
+    }
+
+    pub fn remove(&mut self, pattern: &str) -> Option<V> {
+        self.changed();
+        let mut node = &mut self.root;
+        for segment in pattern.split("::") {
+            node = node.children.get_mut(segment)?;
+        }
+        let v = node.module.take()?;
+        self.count -= 1;
+        Some(v)
+    }
+
+    /// Every pattern with its value, sorted by pattern.
+    pub fn entries(&self) -> Vec<(String, V)> {
+        let mut out = Vec::new();
+        let mut stack: Vec<(String, &TrieNode<V>)> = self.root.children.iter().map(|(k, n)| (k.clone(), n)).collect();
+        while let Some((pattern, node)) = stack.pop() {
+            if let Some(v) = &node.module {
+                out.push((pattern.clone(), v.clone()));
+            }
+            for (k, n) in &node.children {
+                stack.push((format!("{}::{}", pattern, k), n));
+            }
+        }
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
     }
 
     pub fn get(&mut self, input: &str) -> Option<&V> {
         if self.cache.contains_key(input) {
             return  self.cache.get(input).and_then(|opt|opt.as_ref());
         }
+        let v = self.lookup(input).cloned();
+        self.cache.insert(input.to_string(), v);
+        self.cache.get(input).and_then(|opt| opt.as_ref())
+    }
+
+    /// Like `get`, without the cache, so it can be called through `&self`.
+    /// The longest pattern matching `input` segment by segment wins: `a::b`
+    /// matches `a::b` and every module under it, `a::b::*` only those under
+    /// it.
+    pub fn lookup(&self, input: &str) -> Option<&V> {
         let segments: Vec<&str> = input.split("::").collect();
         let mut node = &self.root;
         let mut last_matched_module: Option<&V> = None;
@@ -81,9 +142,6 @@ impl<V: Clone> Trie<V> {
                 break;
             }
         }
-        if let Some(v) = last_matched_module {
-            self.cache.insert(input.to_string(), Some(v.clone()));
-        }
-        self.cache.get(input).and_then(|opt| opt.as_ref())
+        last_matched_module
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,354 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tamper evidence for log files, see `Logger::set_tamper_evidence`.
+//!
+//! Each line written to a file ends with ` mac=<hex>`, the first 16 bytes of
+//! an HMAC-SHA256 over the previous line's MAC and the line itself. Every
+//! file, and every run appending to one, opens with a header line
+//! `tklog-chain prev=<hex>` carrying the last MAC before it, so removing or
+//! editing a whole rotated file breaks the chain as well.
+//!
+//! ### Example
+//! ```no_run
+//! let report = tklog::verify::chain("logs/app.log", b"secret").unwrap();
+//! if let Some(broken) = &report.broken {
+//!     eprintln!("{}:{}: {}", broken.file.display(), broken.line, broken.reason);
+//! }
+//! ```
+
+use std::{
+    env, fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use crate::{backup_pattern, compress};
+
+const MAC_LEN: usize = 16;
+const MAC_FIELD: &[u8] = b" mac=";
+const HEADER: &[u8] = b"tklog-chain prev=";
+
+type Mac = [u8; MAC_LEN];
+
+/// The HMAC key of `Logger::set_tamper_evidence`; `Debug` doesn't show it.
+#[derive(Clone, PartialEq)]
+pub struct TamperKey(Arc<[u8]>);
+
+impl TamperKey {
+    pub fn new(key: &[u8]) -> Self {
+        TamperKey(key.into())
+    }
+
+    fn mac(&self, prev: Option<&Mac>, data: &[u8]) -> Mac {
+        let mut h = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any length");
+        if let Some(prev) = prev {
+            h.update(prev);
+        }
+        h.update(data);
+        let mut mac = [0; MAC_LEN];
+        mac.copy_from_slice(&h.finalize().into_bytes()[..MAC_LEN]);
+        mac
+    }
+}
+
+impl fmt::Debug for TamperKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TamperKey(..)")
+    }
+}
+
+/// The chain state of one file handler.
+pub(crate) struct Chain {
+    key: TamperKey,
+    last: Option<Mac>,
+    /// Whether the current file has its header yet.
+    started: bool,
+}
+
+impl Chain {
+    /// A chain for the file at `filename`, going on from the last MAC
+    /// already in it, if any.
+    pub(crate) fn new(key: TamperKey, filename: &str) -> Self {
+        Chain { key, last: file_tail_mac(filename), started: false }
+    }
+
+    pub(crate) fn key(&self) -> &TamperKey {
+        &self.key
+    }
+
+    /// After a rotation: the next line opens the new file with a header.
+    pub(crate) fn restart(&mut self) {
+        self.started = false;
+    }
+
+    /// `data` as one chained line, after a header when the file needs one.
+    pub(crate) fn frame(&mut self, data: &[u8]) -> Vec<u8> {
+        let record = data.strip_suffix(b"\n").unwrap_or(data);
+        let mut out = Vec::with_capacity(record.len() + 96);
+        if !self.started {
+            let mut header = HEADER.to_vec();
+            match self.last {
+                Some(prev) => header.extend_from_slice(hex(&prev).as_bytes()),
+                None => header.extend_from_slice(b"none"),
+            }
+            let mac = self.key.mac(None, &header);
+            push_line(&mut out, &header, &mac);
+            self.last = Some(mac);
+            self.started = true;
+        }
+        let mac = self.key.mac(self.last.as_ref(), record);
+        push_line(&mut out, record, &mac);
+        self.last = Some(mac);
+        out
+    }
+}
+
+fn push_line(out: &mut Vec<u8>, line: &[u8], mac: &Mac) {
+    out.extend_from_slice(line);
+    out.extend_from_slice(MAC_FIELD);
+    out.extend_from_slice(hex(mac).as_bytes());
+    out.push(b'\n');
+}
+
+fn hex(mac: &Mac) -> String {
+    mac.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &[u8]) -> Option<Mac> {
+    if s.len() != MAC_LEN * 2 {
+        return None;
+    }
+    let mut mac = [0; MAC_LEN];
+    for (i, pair) in s.chunks(2).enumerate() {
+        mac[i] = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(mac)
+}
+
+/// Splits a chained line into its text and MAC; `None` for a line without
+/// one, such as the inner lines of a multi-line message.
+fn split_mac(line: &[u8]) -> Option<(&[u8], Mac)> {
+    let at = line.len().checked_sub(MAC_FIELD.len() + MAC_LEN * 2)?;
+    if &line[at..at + MAC_FIELD.len()] != MAC_FIELD {
+        return None;
+    }
+    Some((&line[..at], unhex(&line[at + MAC_FIELD.len()..])?))
+}
+
+/// The last MAC in `content`, where a restarted run picks the chain up.
+fn tail_mac(content: &[u8]) -> Option<Mac> {
+    let content = content.strip_suffix(b"\n")?;
+    let line = content.rsplit(|b| *b == b'\n').next()?;
+    split_mac(line).map(|(_, mac)| mac)
+}
+
+/// The last MAC of the file at `filename`, reading at most its last 4 KiB.
+fn file_tail_mac(filename: &str) -> Option<Mac> {
+    use std::io::{Seek, SeekFrom};
+    let mut f = File::open(filename).ok()?;
+    let len = f.metadata().ok()?.len();
+    f.seek(SeekFrom::Start(len.saturating_sub(4096))).ok()?;
+    let mut buf = Vec::new();
+    f.read_to_end(&mut buf).ok()?;
+    tail_mac(&buf)
+}
+
+/// What `chain` found.
+#[derive(Clone, Debug)]
+pub struct VerifyReport {
+    /// The files checked in chain order: linked backups, oldest first, then the live file.
+    pub files: Vec<PathBuf>,
+    /// Lines whose MAC matched.
+    pub lines: u64,
+    /// The first place the chain doesn't hold, if any.
+    pub broken: Option<BrokenLink>,
+}
+
+impl VerifyReport {
+    pub fn is_intact(&self) -> bool {
+        self.broken.is_none()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrokenLink {
+    pub file: PathBuf,
+    /// 1-based; 0 when the file as a whole doesn't fit the chain.
+    pub line: u64,
+    pub reason: String,
+}
+
+/// One file, checked on its own.
+struct Checked {
+    path: PathBuf,
+    /// The `prev` of its first header: `Some(None)` for a chain that starts
+    /// here, `None` for a file without a header.
+    prev: Option<Option<Mac>>,
+    last: Option<Mac>,
+    lines: u64,
+    /// The first line ahead of the first header.
+    unchained: Option<u64>,
+    broken: Option<BrokenLink>,
+}
+
+/// Checks the chain of the log file `path` and of its backups, compressed
+/// or not, and reports the first broken link. Lines ahead of the first
+/// header of the oldest file predate the chain and are skipped; backups the
+/// chain doesn't lead to count as broken.
+pub fn chain(path: impl AsRef<Path>, key: &[u8]) -> io::Result<VerifyReport> {
+    let path = path.as_ref();
+    let key = TamperKey::new(key);
+    let live = check(path, &fs::read(path)?, &key);
+    let mut backups = Vec::new();
+    for b in backups_of(path)? {
+        let content = if b.extension().is_some_and(|e| e == "gz") {
+            let mut buf = Vec::new();
+            compress::open_any(&b)?.read_to_end(&mut buf)?;
+            buf
+        } else {
+            fs::read(&b)?
+        };
+        backups.push(check(&b, &content, &key));
+    }
+
+    // Walk back from the newest file, each header naming the file before
+    // it. Right after a rotation the live file is still empty, and the
+    // newest file is the backup no other file follows.
+    let mut ordered = Vec::new();
+    let mut head = Some(live);
+    if head.as_ref().is_some_and(|l| l.prev.is_none() && l.unchained.is_none()) {
+        ordered.extend(head.take());
+        let followed: Vec<Option<Mac>> = backups.iter().filter_map(|b| b.prev.flatten()).map(Some).collect();
+        head = backups.iter().position(|b| b.prev.is_some() && !followed.contains(&b.last)).map(|i| backups.swap_remove(i));
+    }
+    while let Some(c) = head.take() {
+        let prev = c.prev.flatten();
+        ordered.push(c);
+        if let Some(prev) = prev {
+            head = backups.iter().position(|b| b.last == Some(prev)).map(|i| backups.swap_remove(i));
+        }
+    }
+    ordered.reverse();
+
+    let mut report = VerifyReport { files: Vec::new(), lines: 0, broken: None };
+    for (i, c) in ordered.into_iter().enumerate() {
+        report.lines += c.lines;
+        if report.broken.is_none() {
+            report.broken = match (c.broken, c.unchained) {
+                (Some(b), _) => Some(b),
+                (None, Some(line)) if i > 0 => Some(BrokenLink { file: c.path.clone(), line, reason: "line before the chain header".to_string() }),
+                _ => None,
+            };
+        }
+        report.files.push(c.path);
+    }
+    backups.sort_by(|a, b| a.path.cmp(&b.path));
+    if report.broken.is_none() {
+        if let Some(b) = backups.into_iter().find(|b| b.prev.is_some()) {
+            report.broken = Some(BrokenLink { file: b.path, line: 0, reason: "not linked into the chain".to_string() });
+        }
+    }
+    Ok(report)
+}
+
+fn check(path: &Path, content: &[u8], key: &TamperKey) -> Checked {
+    let mut c = Checked { path: path.to_path_buf(), prev: None, last: None, lines: 0, unchained: None, broken: None };
+    // Past a break the MACs as written still link the files together.
+    let fail = |c: &mut Checked, line: u64, reason: &str| {
+        if c.broken.is_none() {
+            c.broken = Some(BrokenLink { file: path.to_path_buf(), line, reason: reason.to_string() });
+        }
+    };
+    let content = content.strip_suffix(b"\n").unwrap_or(content);
+    if content.is_empty() {
+        return c;
+    }
+    // The lines of the record being read, for messages spanning several lines.
+    let mut record: Vec<u8> = Vec::new();
+    let mut first = 0;
+    for (n, line) in content.split(|b| *b == b'\n').enumerate() {
+        let n = n as u64 + 1;
+        if record.is_empty() {
+            first = n;
+        } else {
+            record.push(b'\n');
+        }
+        let Some((text, mac)) = split_mac(line) else {
+            record.extend_from_slice(line);
+            continue;
+        };
+        if record.is_empty() {
+            if let Some(prev) = text.strip_prefix(HEADER) {
+                let prev = if prev == b"none" { None } else { unhex(prev) };
+                if key.mac(None, text) != mac {
+                    fail(&mut c, n, "header MAC mismatch");
+                }
+                match c.prev {
+                    None => c.prev = Some(prev),
+                    Some(_) if prev != c.last => fail(&mut c, n, "header doesn't follow the line before"),
+                    Some(_) => {}
+                }
+                c.last = Some(mac);
+                continue;
+            }
+        }
+        record.extend_from_slice(text);
+        if c.prev.is_none() {
+            c.unchained.get_or_insert(first);
+            record.clear();
+            continue;
+        }
+        if key.mac(c.last.as_ref(), &record) == mac {
+            c.lines += 1;
+        } else {
+            fail(&mut c, first, "MAC mismatch");
+        }
+        c.last = Some(mac);
+        record.clear();
+    }
+    if !record.is_empty() {
+        if c.prev.is_none() {
+            c.unchained.get_or_insert(first);
+        } else {
+            fail(&mut c, first, "line without a MAC");
+        }
+    }
+    c
+}
+
+fn backups_of(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let stem = path.file_stem().map_or("tklog".to_string(), |s| s.to_string_lossy().to_string());
+    let extension = path.extension().map_or(String::new(), |e| e.to_string_lossy().to_string());
+    let mut dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    if dir.as_os_str().is_empty() {
+        dir = env::current_dir()?;
+    }
+    let re = backup_pattern(&stem, &extension);
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_str().is_some_and(|n| re.is_match(n)) {
+            backups.push(entry.path());
+        }
+    }
+    Ok(backups)
+}
@@ -0,0 +1,72 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The browser console, on wasm32 with the `wasm` feature.
+//!
+//! The console lines of a logger go to `console.log`, and those of stderr,
+//! see `set_console_stream`, to `console.error`: with
+//! `ConsoleStream::SplitAt(LEVEL::Warn)`, Warn, Error and Fatal are errors
+//! of the console. `Console`, a `LogSink`, maps every level to a method of
+//! its own instead. The time of the lines comes from `js_sys::Date`, there
+//! being no system clock; there are no files either, and writing one fails.
+//!
+//! ### Example
+//! ```no_run
+//! use tklog::{info, wasm::Console, LEVEL, LOG};
+//!
+//! LOG.set_level(LEVEL::Debug).set_custom_sink(Box::new(Console)).set_custom_sink_only(true);
+//! info!("started");
+//! ```
+
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+use crate::{logsink::LogSink, LEVEL};
+
+/// Writes every line with the console method of its level: `debug` for
+/// Trace and Debug, `info`, `warn`, and `error` for Error and Fatal.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Console;
+
+impl LogSink for Console {
+    fn write(&mut self, level: LEVEL, formatted: &str) {
+        let line = JsValue::from_str(formatted.trim_end_matches('\n'));
+        match level {
+            LEVEL::Trace | LEVEL::Debug => console::debug_1(&line),
+            LEVEL::Info => console::info_1(&line),
+            LEVEL::Warn => console::warn_1(&line),
+            LEVEL::Error | LEVEL::Fatal => console::error_1(&line),
+            LEVEL::Off => {}
+        }
+    }
+
+    fn flush(&mut self) {}
+}
+
+/// A console line, to `console.error` if it goes to stderr.
+pub(crate) fn print(body: &str, stderr: bool) {
+    let line = JsValue::from_str(body.trim_end_matches('\n'));
+    if stderr {
+        console::error_1(&line);
+    } else {
+        console::log_1(&line);
+    }
+}
+
+/// Seconds since the epoch, by `js_sys::Date`.
+pub(crate) fn epoch_secs() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
@@ -0,0 +1,271 @@
+// Copyright (c) 2024, donnie4w <donnie4w@gmail.com>
+// All rights reserved.
+// https://github.com/donnie4w/tklog
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Write buffering of the file handlers, see `Logger::set_buffer_size`.
+//!
+//! A buffered handler gathers its lines in memory and writes them out
+//! through a clone of its file once the buffer is full. A flusher (a thread
+//! for the sync logger, a tokio task for the async one) writes out the
+//! buffers holding bytes older than the flush interval. Both go through the
+//! lock of the buffer, as does the handler before it rotates, closes or
+//! reopens its file, dropping the clone: a periodic flush never lands in a
+//! file that was moved to a backup.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+
+use crate::runtime::{Rt, Runtime};
+
+/// How long an idle flusher sleeps before looking at its interval again.
+const RECHECK: Duration = Duration::from_secs(30);
+
+/// The buffer of one handler, shared with the flusher of its logger.
+#[derive(Default)]
+pub(crate) struct WriteBuffer(Arc<Mutex<Held>>);
+
+#[derive(Default)]
+struct Held {
+    bytes: Vec<u8>,
+    /// When the oldest byte held was written.
+    since: Option<Instant>,
+    /// The clone of the handler's file the bytes go to.
+    file: Option<File>,
+    capacity: usize,
+}
+
+impl Held {
+    fn drain(&mut self) -> io::Result<()> {
+        if let (false, Some(f)) = (self.bytes.is_empty(), &mut self.file) {
+            f.write_all(&self.bytes)?;
+        }
+        self.bytes.clear();
+        self.since = None;
+        Ok(())
+    }
+}
+
+impl WriteBuffer {
+    fn held(&self) -> MutexGuard<'_, Held> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether writes go through the buffer: its size isn't 0.
+    pub(crate) fn is_on(&self) -> bool {
+        self.held().capacity > 0
+    }
+
+    /// Sets the size the buffer is written out at; 0 writes out what it
+    /// holds and lets the handler write through its own file again.
+    pub(crate) fn set_capacity(&self, capacity: usize) -> io::Result<()> {
+        let mut held = self.held();
+        held.capacity = capacity;
+        match capacity {
+            0 => {
+                let drained = held.drain();
+                held.file = None;
+                drained
+            }
+            _ if held.bytes.len() >= capacity => held.drain(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the buffer has a file to write to, see `attach`.
+    pub(crate) fn is_attached(&self) -> bool {
+        self.held().file.is_some()
+    }
+
+    /// Writes to `file`, a clone of the handler's file, from now on.
+    pub(crate) fn attach(&self, file: File) {
+        self.held().file = Some(file);
+    }
+
+    pub(crate) fn write(&self, data: &[u8]) -> io::Result<()> {
+        let mut held = self.held();
+        held.bytes.extend_from_slice(data);
+        held.since.get_or_insert_with(Instant::now);
+        match held.bytes.len() >= held.capacity {
+            true => held.drain(),
+            false => Ok(()),
+        }
+    }
+
+    /// Writes out what the buffer holds.
+    pub(crate) fn drain(&self) -> io::Result<()> {
+        self.held().drain()
+    }
+
+    /// Writes out what the buffer holds and drops its file, before the
+    /// handler closes or moves its own.
+    pub(crate) fn detach(&self) -> io::Result<()> {
+        let mut held = self.held();
+        let drained = held.drain();
+        held.file = None;
+        drained
+    }
+}
+
+enum Runner {
+    Thread,
+    Task,
+}
+
+#[derive(Default)]
+struct Shared {
+    buffers: Mutex<Vec<Weak<Mutex<Held>>>>,
+    /// The flush interval in nanoseconds, 0 when off.
+    interval: AtomicU64,
+    changed: Condvar,
+    notify: Notify,
+    running: AtomicBool,
+    stopped: AtomicBool,
+}
+
+/// Owned by a logger; stops its thread or task when dropped.
+pub(crate) struct Flusher {
+    runner: Runner,
+    shared: Arc<Shared>,
+}
+
+impl Flusher {
+    pub(crate) fn new_thread() -> Self {
+        Flusher {
+            runner: Runner::Thread,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Spawns its task once a tokio runtime is there.
+    pub(crate) fn new_task() -> Self {
+        Flusher {
+            runner: Runner::Task,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Flushes `buffer` at the interval of the logger.
+    pub(crate) fn register(&self, buffer: &WriteBuffer) {
+        self.shared.buffers.lock().unwrap_or_else(|e| e.into_inner()).push(Arc::downgrade(&buffer.0));
+        self.wake();
+    }
+
+    /// Takes effect at once, for the buffers registered so far too; `None`
+    /// stops the periodic flush.
+    pub(crate) fn set_interval(&self, interval: Option<Duration>) {
+        let nanos = interval.map_or(0, |d| d.as_nanos().min(u64::MAX as u128) as u64);
+        self.shared.interval.store(nanos, Ordering::Release);
+        self.wake();
+    }
+
+    fn wake(&self) {
+        if self.shared.interval.load(Ordering::Acquire) > 0 && !self.shared.running.load(Ordering::Acquire) {
+            self.spawn();
+        }
+        self.shared.changed.notify_all();
+        self.shared.notify.notify_one();
+    }
+
+    fn spawn(&self) {
+        let shared = self.shared.clone();
+        match self.runner {
+            Runner::Thread => {
+                if shared.running.swap(true, Ordering::AcqRel) {
+                    return;
+                }
+                thread::spawn(move || {
+                    let mut buffers = shared.buffers.lock().unwrap_or_else(|e| e.into_inner());
+                    while !shared.stopped.load(Ordering::Acquire) {
+                        let wait = tick(&mut buffers, shared.interval.load(Ordering::Acquire));
+                        buffers = shared.changed.wait_timeout(buffers, wait).unwrap_or_else(|e| e.into_inner()).0;
+                    }
+                    shared.running.store(false, Ordering::Release);
+                });
+            }
+            Runner::Task => {
+                let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+                    return;
+                };
+                if shared.running.swap(true, Ordering::AcqRel) {
+                    return;
+                }
+                runtime.spawn(async move {
+                    // Dropped with the task, also when its runtime shuts down.
+                    let _running = Running(shared.clone());
+                    while !shared.stopped.load(Ordering::Acquire) {
+                        let wait = tick(&mut shared.buffers.lock().unwrap_or_else(|e| e.into_inner()), shared.interval.load(Ordering::Acquire));
+                        tokio::select! {
+                            _ = Rt::sleep(wait) => {}
+                            _ = shared.notify.notified() => {}
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl Drop for Flusher {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Release);
+        self.shared.changed.notify_all();
+        self.shared.notify.notify_one();
+    }
+}
+
+struct Running(Arc<Shared>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Release);
+    }
+}
+
+/// Writes out every buffer holding bytes older than `interval` nanoseconds
+/// and returns how long to sleep until the next one is due. A buffer that
+/// fails keeps its bytes for the write of its handler, which reports it.
+fn tick(buffers: &mut Vec<Weak<Mutex<Held>>>, interval: u64) -> Duration {
+    if interval == 0 {
+        return RECHECK;
+    }
+    let interval = Duration::from_nanos(interval);
+    let mut wait = interval;
+    buffers.retain(|b| {
+        let Some(b) = b.upgrade() else {
+            return false;
+        };
+        let mut held = b.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(since) = held.since {
+            let age = since.elapsed();
+            if age < interval {
+                wait = wait.min(interval - age);
+            } else {
+                let _ = held.drain();
+            }
+        }
+        true
+    });
+    wait
+}
@@ -0,0 +1,90 @@
+//! The fixtures the integration tests share, with `mod common;`.
+//!
+//! Their paths are named after the test file and the process, so that
+//! test files and runs don't write over each other's files.
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, TimeZone};
+use flate2::read::GzDecoder;
+use tklog::{RotationEvent, TestMode};
+
+/// `tklog_<test>_<name>_<pid><ext>` in the temp directory, `<test>` being
+/// the name of the test file without its `test_`.
+fn temp_path(name: &str, ext: &str) -> PathBuf {
+    let test = env!("CARGO_CRATE_NAME");
+    let test = test.strip_prefix("test_").unwrap_or(test);
+    std::env::temp_dir().join(format!("tklog_{}_{}_{}{}", test, name, std::process::id(), ext))
+}
+
+/// An empty directory of the test's own.
+pub fn dir(name: &str) -> PathBuf {
+    let dir = missing_dir(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// `dir`, not created, for the tests of the directories tklog creates.
+pub fn missing_dir(name: &str) -> PathBuf {
+    let dir = temp_path(name, "");
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// A log file of the test's own, not created.
+pub fn logfile(name: &str) -> String {
+    let path = temp_path(name, ".log");
+    let _ = fs::remove_file(&path);
+    path.to_string_lossy().into_owned()
+}
+
+/// The names of the files in `dir`, sorted.
+pub fn files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    names.sort();
+    names
+}
+
+/// What a gzip backup holds.
+pub fn gunzip(path: &Path) -> String {
+    let mut s = String::new();
+    GzDecoder::new(fs::File::open(path).unwrap()).read_to_string(&mut s).unwrap();
+    s
+}
+
+/// The time 2024-05-01 12:00:00, its sequence from 1.
+pub fn testmode() -> TestMode {
+    TestMode {
+        fixed_time: Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+        fixed_seq_start: 1,
+    }
+}
+
+/// The events of `on_rotate`.
+pub static EVENTS: Mutex<Vec<RotationEvent>> = Mutex::new(Vec::new());
+
+/// A rotation handler keeping the events for `wait_backups`.
+pub fn on_rotate(e: &RotationEvent) {
+    EVENTS.lock().unwrap().push(e.clone());
+}
+
+/// The backups of `filename` once `n` of them were rotated, in order.
+pub fn wait_backups(filename: &str, n: usize) -> Vec<PathBuf> {
+    let start = Instant::now();
+    loop {
+        let backups: Vec<PathBuf> = EVENTS.lock().unwrap().iter().filter(|e| e.filename == filename).map(|e| e.backup.clone()).collect();
+        if backups.len() >= n {
+            return backups;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "no rotation of {}: {:?}", filename, backups);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{sync::Logger, Format, LEVEL};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocs(f: impl FnOnce()) -> usize {
+    let before = ALLOCS.with(Cell::get);
+    f();
+    ALLOCS.with(Cell::get) - before
+}
+
+#[test]
+fn test_plain_line_allocates_once() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName);
+    log.fmt(module_path!(), LEVEL::Info, file!(), line!(), "warm up".to_string());
+    for format in [Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName, Format::LevelFlag | Format::Microseconds | Format::LongFileName] {
+        log.set_format(format);
+        let message = "ready to serve".to_string();
+        let n = allocs(|| {
+            let s = log.fmt(module_path!(), LEVEL::Info, file!(), line!(), message);
+            assert!(s.file_body.ends_with("ready to serve\n"));
+        });
+        assert!(n <= 1, "{} allocations for format {}", n, format);
+    }
+    log.set_formatter("{level} {time} {file} #{seq}: {message}\n");
+    let message = "ready".to_string();
+    let n = allocs(|| {
+        log.fmt(module_path!(), LEVEL::Info, file!(), line!(), message);
+    });
+    assert!(n <= 2, "{} allocations with a body format", n);
+}
+
+// The arguments of a macro are written into one reused buffer, so more of
+// them cost no more allocations than one.
+#[test]
+fn test_macro_args_share_one_buffer() {
+    let mut log = Arc::new(Mutex::new(Logger::new()));
+    log.lock().unwrap().set_console(false).set_printmode(tklog::PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag);
+    tklog::infos!(&mut log, "warm", "up", 1);
+    let one = allocs(|| tklog::infos!(&mut log, "ready to serve on port 8080"));
+    let many = allocs(|| tklog::infos!(&mut log, "ready", "to", "serve", "on", "port", 8080));
+    assert!(many <= one, "{} allocations for 6 arguments, {} for 1", many, one);
+}
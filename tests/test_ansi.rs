@@ -0,0 +1,39 @@
+use std::fs;
+
+use tklog::{sync::Logger, Format, LEVEL};
+
+fn colored(log: &mut Logger) {
+    log.set_console(false).set_format(Format::LevelFlag).set_attr_format(|fmt| {
+        fmt.set_body_fmt(|_, body| format!("\x1b[32m{}\x1b[0m \x1b]8;;https://x\x07link\x1b]8;;\x1b\\", body.trim_end()));
+    });
+}
+
+#[test]
+fn test_files_get_lines_without_ansi() {
+    let path = std::env::temp_dir().join(format!("tklog_ansi_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    colored(&mut log);
+    log.set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+
+    let s = log.fmt("app", LEVEL::Info, "", 0, "ready".to_string());
+    assert_eq!(s.file_body, "[INFO] ready link");
+    assert_eq!(s.console_body.as_deref(), Some("\x1b[32m[INFO] ready\x1b[0m \x1b]8;;https://x\x07link\x1b]8;;\x1b\\"));
+    log.print(LEVEL::Info, "app", s);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] ready link");
+
+    log.set_attr_format(|fmt| fmt.set_body_fmt(|_, body| body));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "plain".to_string());
+    assert!(s.console_body.is_none(), "nothing to strip, one body");
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_allow_ansi_in_files() {
+    let mut log = Logger::new();
+    colored(&mut log);
+    log.allow_ansi_in_files(true);
+    let s = log.fmt("app", LEVEL::Info, "", 0, "ready".to_string());
+    assert!(s.file_body.starts_with("\x1b[32m[INFO] ready\x1b[0m"));
+    assert!(s.console_body.is_none());
+}
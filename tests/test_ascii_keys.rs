@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex};
+
+use tklog::{fields::FieldMap, sync::Logger, Format, LEVEL};
+
+/// Where case mapping and JSON escaping go wrong: Turkish and German
+/// letters, the Kelvin sign, a ligature, emoji, quotes, `=`, blanks and
+/// control characters.
+const HOSTILE: [char; 18] = ['İ', 'ı', 'ß', 'K', 'ﬁ', 'é', '😀', '"', '\\', '=', ' ', '\t', '\n', '\0', '\u{200b}', 'a', 'Z', '9'];
+
+/// A few hundred keys or level names made of `HOSTILE`, the same every run.
+fn hostile_strings() -> Vec<String> {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    (0..300)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (0..state % 8).map(|i| HOSTILE[((state >> (i * 5)) % HOSTILE.len() as u64) as usize]).collect()
+        })
+        .collect()
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+#[test]
+fn test_field_keys_kept_ascii() {
+    for key in hostile_strings() {
+        let mut fields = FieldMap::new();
+        fields.insert(key.clone(), "v");
+        let (safe, _) = fields.iter().next().unwrap();
+        assert!(is_key(safe), "{:?} became {:?}", key, safe);
+        if is_key(&key) {
+            assert_eq!(safe, key);
+        } else {
+            assert_eq!(safe.len(), key.chars().count().max(1), "{:?} became {:?}", key, safe);
+        }
+    }
+}
+
+#[test]
+fn test_machine_lines_ascii() {
+    let keys = Arc::new(Mutex::new(hostile_strings().into_iter()));
+    for k8s in [false, true] {
+        let mut log = Logger::new();
+        if k8s {
+            log.preset_k8s();
+        } else {
+            log.set_console(false).set_format_json(true);
+        }
+        let source = keys.clone();
+        log.set_dynamic_fields(Box::new(move |fields| {
+            if let Some(key) = source.lock().unwrap().next() {
+                fields.insert(key, "v");
+            }
+        }));
+        log.set_attr_format(|fmt| fmt.set_level_fmt(|level| format!("İ{:?}ß", level).to_uppercase()));
+        for level in [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal] {
+            for _ in 0..25 {
+                let s = log.fmt("app", level, "", 0, "m".to_string()).file_body;
+                assert!(s.is_ascii(), "{}", s);
+                assert!(s.contains(&format!("\"level\":\"{}\"", format!("{:?}", level).to_ascii_lowercase())), "{}", s);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_template_case_ascii_only() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_formatter("{level:lower}|{message:upper}|{level:title}|{message}\n");
+    log.set_attr_format(|fmt| fmt.set_level_fmt(|_| "İNFO".to_string()));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "straße ıi".to_string()).file_body;
+    assert_eq!(s, "İnfo|STRAßE ıI|İNFO|straße ıi\n");
+}
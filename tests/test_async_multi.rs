@@ -0,0 +1,94 @@
+mod common;
+
+use std::{
+    fmt, fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use tklog::{async_debugs, async_formats, async_infos, async_warns, Async::Logger, Format, LEVEL};
+
+use common::dir;
+
+const TASKS: usize = 8;
+const LINES: usize = 100;
+
+async fn logger(path: &Path, format: u8) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(format).set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false).await;
+    log
+}
+
+fn sorted(path: &Path) -> Vec<String> {
+    let mut lines: Vec<String> = fs::read_to_string(path).unwrap().lines().map(str::to_string).collect();
+    lines.sort();
+    lines
+}
+
+/// Counts how often it is formatted.
+struct Counted<'a>(&'a AtomicUsize);
+
+impl fmt::Display for Counted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        f.write_str("counted")
+    }
+}
+
+// Two loggers with their own format and file, one shared as is and one
+// behind a tokio mutex, written by spawned tasks at the same time.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_async_multi_two_loggers() {
+    let dir = dir("two");
+    let (a_path, b_path) = (dir.join("a.log"), dir.join("b.log"));
+    let a = Arc::new(logger(&a_path, Format::Nano).await);
+    let b = Arc::new(tokio::sync::Mutex::new(logger(&b_path, Format::LevelFlag).await));
+
+    let mut handles = Vec::new();
+    for t in 0..TASKS {
+        let (a, b) = (a.clone(), b.clone());
+        handles.push(tokio::spawn(async move {
+            for i in 0..LINES {
+                async_infos!(&a, t, "-", i, "\n");
+                async_formats!(b, LEVEL::Warn, "{}:{}", t, i);
+                async_debugs!(&a, "below the level");
+                tokio::task::yield_now().await;
+            }
+        }));
+    }
+    for h in handles {
+        h.await.unwrap();
+    }
+    a.flush().await;
+    b.lock().await.flush().await;
+
+    let mut want_a: Vec<String> = (0..TASKS).flat_map(|t| (0..LINES).map(move |i| format!("{}-{}", t, i))).collect();
+    let mut want_b: Vec<String> = (0..TASKS).flat_map(|t| (0..LINES).map(move |i| format!("[WARN] {}:{}", t, i))).collect();
+    want_a.sort();
+    want_b.sort();
+    assert_eq!(sorted(&a_path), want_a);
+    assert_eq!(sorted(&b_path), want_b);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// A line below the level of its logger doesn't format its arguments.
+#[tokio::test]
+async fn test_async_multi_level_short_circuit() {
+    let dir = dir("level");
+    let path = dir.join("app.log");
+    let a = logger(&path, Format::Nano).await;
+    let b = Arc::new(tokio::sync::Mutex::new(logger(&dir.join("b.log"), Format::Nano).await));
+    let formatted = AtomicUsize::new(0);
+    async_debugs!(&a, Counted(&formatted));
+    async_debugs!(b, Counted(&formatted));
+    async_formats!(b, LEVEL::Trace, "{}", Counted(&formatted));
+    assert_eq!(formatted.load(Ordering::Relaxed), 0);
+    async_warns!(&a, Counted(&formatted));
+    a.flush().await;
+    assert_eq!(formatted.load(Ordering::Relaxed), 1);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "counted");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::{fs, sync::Arc, thread};
+
+use tklog::{Async::Logger, Format, LEVEL};
+
+use common::logfile;
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build().unwrap()
+}
+
+fn line(log: &Logger, message: String) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message);
+    log.log(LEVEL::Info, "app", s);
+}
+
+fn lines(path: &str) -> Vec<String> {
+    fs::read_to_string(path).unwrap().lines().map(str::to_string).collect()
+}
+
+#[test]
+fn test_log_before_runtime() {
+    let path = logfile("before");
+    let rt = runtime();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_prestart_buffer(100);
+    rt.block_on(log.set_cutmode_by_size(&path, 0, 0, false));
+    assert!(!log.is_started());
+
+    for i in 0..120 {
+        line(&log, format!("early {}", i));
+    }
+    assert!(!log.is_started());
+    assert_eq!(log.stats().prestart_dropped, 20);
+
+    rt.block_on(async {
+        line(&log, "in the runtime".to_string());
+        assert!(log.is_started());
+        log.flush().await;
+    });
+    let mut expected: Vec<String> = (0..100).map(|i| format!("[INFO] early {}", i)).collect();
+    expected.push("[INFO] in the runtime".to_string());
+    assert_eq!(lines(&path), expected);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_log_during_startup_burst() {
+    let path = logfile("burst");
+    let mut log = Logger::new();
+    assert!(log.is_started());
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).await;
+    let log = Arc::new(log);
+
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let log = log.clone();
+            thread::spawn(move || {
+                for i in 0..500 {
+                    line(&log, format!("{} {}", t, i));
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    log.flush().await;
+
+    let lines = lines(&path);
+    assert_eq!(lines.len(), 2000);
+    for t in 0..4 {
+        let own: Vec<String> = lines.iter().filter(|l| l.starts_with(&format!("[INFO] {} ", t))).cloned().collect();
+        assert_eq!(own, (0..500).map(|i| format!("[INFO] {} {}", t, i)).collect::<Vec<_>>());
+    }
+    assert_eq!(log.stats().prestart_dropped, 0);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_attach_runtime() {
+    let path = logfile("attach");
+    let rt = runtime();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    rt.block_on(log.set_cutmode_by_size(&path, 0, 0, false));
+    line(&log, "before".to_string());
+
+    log.attach_runtime(rt.handle());
+    assert!(log.is_started());
+    let log = Arc::new(log);
+    let writer = log.clone();
+    thread::spawn(move || line(&writer, "from a plain thread".to_string())).join().unwrap();
+
+    rt.block_on(log.flush());
+    assert_eq!(lines(&path), ["[INFO] before", "[INFO] from a plain thread"]);
+    let _ = fs::remove_file(&path);
+}
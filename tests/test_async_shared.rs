@@ -0,0 +1,65 @@
+use std::{collections::HashSet, fs, sync::Arc};
+
+use tklog::{async_infos, Async::Logger, Format, LEVEL};
+
+const TASKS: usize = 32;
+const LINES: usize = 500;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_shared_logger_many_tasks() {
+    let dir = std::env::temp_dir().join(format!("tklog_async_shared_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("shared.log");
+    let filename = path.to_str().unwrap().to_string();
+
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Debug).set_format(Format::Nano).set_cutmode_by_size(&filename, 0, 0, false).await;
+    let log = Arc::new(log);
+
+    let mut handles = Vec::new();
+    for t in 0..TASKS {
+        let log = log.clone();
+        handles.push(tokio::spawn(async move {
+            for i in 0..LINES {
+                async_infos!(&log, format!("{} {}\n", t, i));
+                if i % 50 == 0 {
+                    tokio::task::yield_now().await;
+                }
+            }
+        }));
+    }
+    for h in handles {
+        h.await.unwrap();
+    }
+    log.flush().await;
+
+    let content = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), TASKS * LINES);
+    let unique: HashSet<&str> = lines.iter().copied().collect();
+    assert_eq!(unique.len(), TASKS * LINES, "duplicated lines");
+    for t in 0..TASKS {
+        for i in 0..LINES {
+            assert!(unique.contains(format!("{} {}", t, i).as_str()), "lost line {} {}", t, i);
+        }
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_enqueue_through_shared_reference() {
+    let dir = std::env::temp_dir().join(format!("tklog_async_enqueue_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("enqueue.log");
+    let filename = path.to_str().unwrap().to_string();
+
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::Nano).set_cutmode_by_size(&filename, 0, 0, false).await;
+    let shared: &Logger = &log;
+    shared.enqueue(LEVEL::Debug, module_path!(), file!(), line!(), "filtered\n".to_string());
+    shared.enqueue(LEVEL::Info, module_path!(), file!(), line!(), "kept\n".to_string());
+    shared.flush().await;
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "kept\n");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tklog::{sync::Logger, Format, LEVEL, MODE};
+
+use common::{gunzip, missing_dir, on_rotate, wait_backups};
+
+/// A logger started on `filename`, as a process would be, that writes
+/// `lines` and cuts before each line after the first.
+fn run(filename: &str, maxbackups: u32, lines: &[&str]) {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_rotation_handler(on_rotate);
+    log.set_cutmode_by_mixed(filename, MODE::HOUR, 5, maxbackups, true);
+    for line in lines {
+        let s = log.fmt("app", LEVEL::Info, "", 0, line.to_string());
+        log.print(LEVEL::Info, "app", s);
+    }
+}
+
+// Restarts within one hour cut in the same period: each backup takes the
+// next free counter, and no `.gz` is written over.
+#[test]
+fn test_backup_collision_restarts() {
+    let dir = missing_dir("restarts");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    run(filename, 0, &["first", "second"]);
+    let first = wait_backups(filename, 1);
+    run(filename, 0, &["third"]);
+    let backups = wait_backups(filename, 2);
+    assert_eq!(backups[0], first[0]);
+    assert_ne!(backups[0], backups[1]);
+    assert_eq!(gunzip(&backups[0]), "first");
+    assert_eq!(gunzip(&backups[1]), "second");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// Pruning orders the backups of one period by their counter, past 9 too.
+#[test]
+fn test_backup_collision_prune_order() {
+    let dir = missing_dir("prune");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let lines: Vec<String> = (0..12).map(|i| format!("l{:03}", i)).collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    run(filename, 3, &lines);
+    let backups = wait_backups(filename, 11);
+    let start = Instant::now();
+    let mut left: Vec<PathBuf> = loop {
+        let left: Vec<PathBuf> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).filter(|p| p != &path).collect();
+        if left.len() == 3 || start.elapsed() > Duration::from_secs(10) {
+            break left;
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    left.sort();
+    let mut newest = backups[8..].to_vec();
+    newest.sort();
+    assert_eq!(left, newest);
+    let _ = fs::remove_dir_all(&dir);
+}
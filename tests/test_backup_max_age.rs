@@ -0,0 +1,81 @@
+mod common;
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use tklog::{syncfile::expire_backups, Format, LEVEL};
+
+use common::{dir, files};
+
+const DAY: Duration = Duration::from_secs(86400);
+
+/// Creates `name` in `dir`, last written `age` ago.
+fn seed(dir: &Path, name: &str, age: Duration) {
+    File::create(dir.join(name)).unwrap().set_modified(SystemTime::now() - age).unwrap();
+}
+
+/// Waits for the background cleanup of a rotation to delete `name`.
+fn gone(dir: &Path, name: &str) -> bool {
+    (0..200).any(|_| {
+        let gone = !dir.join(name).exists();
+        if !gone {
+            thread::sleep(Duration::from_millis(10));
+        }
+        gone
+    })
+}
+
+#[test]
+fn test_expire_backups() {
+    let dir = dir("expire");
+    for name in ["app_20240101_1.log.gz", "app_20240102_1.log", "app_3.log.gz", "app_4.log", "app.log", "other_1.log", "app_notes.log"] {
+        seed(&dir, name, 10 * DAY);
+    }
+    seed(&dir, "app_20240110_1.log.gz", DAY);
+    seed(&dir, "app_5.log", DAY);
+
+    let mut deleted = expire_backups(&dir.join("app.log"), 7 * DAY).unwrap();
+    deleted.sort();
+    let expect: Vec<PathBuf> = ["app_20240101_1.log.gz", "app_20240102_1.log", "app_3.log.gz", "app_4.log"].iter().map(|n| dir.join(n)).collect();
+    assert_eq!(deleted, expect);
+    assert_eq!(files(&dir), ["app.log", "app_20240110_1.log.gz", "app_5.log", "app_notes.log", "other_1.log"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_backup_max_age_sync() {
+    let dir = dir("sync");
+    seed(&dir, "app_1.log", 30 * DAY);
+    seed(&dir, "app_2.log.gz", 30 * DAY);
+    let mut log = tklog::sync::Logger::new();
+    log.set_backup_max_age(DAY).set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&dir.join("app.log").to_string_lossy(), 40, 0, false);
+    for i in 0..4 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {} of the size cut", i));
+        log.print(LEVEL::Info, "app", s);
+    }
+    assert!(gone(&dir, "app_1.log") && gone(&dir, "app_2.log.gz"), "{:?}", files(&dir));
+    let names = files(&dir);
+    assert!(names.contains(&"app.log".to_string()) && names.contains(&"app_3.log".to_string()), "{:?}", names);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_backup_max_age_async() {
+    let dir = dir("async");
+    seed(&dir, "app_1.log.gz", 30 * DAY);
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_backup_max_age(DAY);
+    log.set_cutmode_by_size(&dir.join("app.log").to_string_lossy(), 40, 0, false).await;
+    for i in 0..4 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {} of the size cut", i));
+        log.log(LEVEL::Info, "app", s);
+    }
+    log.flush().await;
+    assert!(gone(&dir, "app_1.log.gz"), "{:?}", files(&dir));
+    assert!(files(&dir).contains(&"app_2.log".to_string()), "{:?}", files(&dir));
+    let _ = fs::remove_dir_all(&dir);
+}
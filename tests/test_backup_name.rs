@@ -0,0 +1,52 @@
+mod common;
+
+use std::{
+    fs::{self, File},
+    thread,
+    time::Duration,
+};
+
+use chrono::Local;
+use tklog::{sync::Logger, Error, Format, LEVEL, PRINTMODE};
+
+use common::{dir, files};
+
+#[test]
+fn test_backup_name_template() {
+    let dir = dir("size");
+    // Backups of older days, which sort before today's whatever their index.
+    for name in ["app-2020-01-02_007.log", "app-2020-01-01_009.log.gz", "other-2020-01-01_001.log"] {
+        File::create(dir.join(name)).unwrap();
+    }
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 10, 3, false);
+    log.set_backup_name_template("{stem}-{time:%Y-%m-%d}_{index:3}.{ext}").unwrap();
+    for i in 0..3 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {:04}", i));
+        log.print(LEVEL::Info, "app", s);
+    }
+    // Pruning and compression run off the logging thread.
+    thread::sleep(Duration::from_millis(300));
+    let today = Local::now().format("%Y-%m-%d");
+    let expected = vec!["app-2020-01-02_007.log".to_string(), format!("app-{}_001.log", today), format!("app-{}_002.log", today), "app.log".to_string(), "other-2020-01-01_001.log".to_string()];
+    assert_eq!(files(&dir), expected);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_backup_name_template_refused() {
+    let mut log = Logger::new();
+    log.set_console(false).set_cutmode_by_size("app1.log", 10, 3, false);
+    let reason = |r: Result<&mut Logger, Error>| match r {
+        Err(Error::InvalidBackupTemplate { reason, .. }) => reason,
+        other => panic!("{:?}", other.map(|_| ())),
+    };
+    assert_eq!(reason(log.set_backup_name_template("{stem}-{time:%Y}.{ext}")), "`{index}` is missing");
+    assert_eq!(reason(log.set_backup_name_template("{stem}_{index}.{extension}")), "unknown token `{extension}`");
+    assert_eq!(reason(log.set_backup_name_template("old/{stem}_{index}")), "a backup name has no directory");
+    assert_eq!(reason(log.set_backup_name_template("{stem}-{time:%Y-%Q}_{index}")), "`%Y-%Q` is not a strftime format");
+    // `app{index}.log` would take app1.log for one of its backups.
+    assert_eq!(reason(log.set_backup_name_template("app{index}.{ext}")), "backups could be named like the log file `app1.log`");
+    assert!(log.set_backup_name_template("{stem}.{index}.{ext}").is_ok());
+}
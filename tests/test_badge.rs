@@ -0,0 +1,46 @@
+use chrono::{Local, TimeZone};
+use tklog::{
+    badge::{BadgeOptions, ConsoleBadgeFormatter},
+    sync::Logger,
+    Format, TestMode, LEVEL,
+};
+
+fn logger(opts: BadgeOptions) -> Logger {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Trace).set_format(Format::LevelFlag | Format::Time);
+    log.set_test_mode(TestMode { fixed_time: Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(), fixed_seq_start: 1 }).unwrap();
+    log.set_console_formatter_impl(ConsoleBadgeFormatter::new(opts));
+    log
+}
+
+#[test]
+fn test_badge_levels() {
+    let mut log = logger(BadgeOptions::default());
+    for (level, console) in [
+        (LEVEL::Trace, "\x1b[97;44m TRACE \x1b[0m \x1b[2m12:00:00\x1b[0m up\n"),
+        (LEVEL::Debug, "\x1b[30;46m DEBUG \x1b[0m \x1b[2m12:00:00\x1b[0m up\n"),
+        (LEVEL::Info, "\x1b[30;42m INFO  \x1b[0m \x1b[2m12:00:00\x1b[0m up\n"),
+        (LEVEL::Warn, "\x1b[30;43m WARN  \x1b[0m \x1b[2m12:00:00\x1b[0m up\n"),
+        (LEVEL::Error, "\x1b[97;41m ERROR \x1b[0m \x1b[2m12:00:00\x1b[0m up\n"),
+        (LEVEL::Fatal, "\x1b[1;97;41m FATAL \x1b[0m \x1b[2m12:00:00\x1b[0m up\n"),
+    ] {
+        let s = log.fmt("cli", level, "", 0, "up".to_string());
+        assert_eq!(s.console_body.as_deref(), Some(console));
+        // The file keeps its own layout.
+        assert!(s.file_body.ends_with(" 12:00:00 up\n"), "{}", s.file_body);
+    }
+}
+
+#[test]
+fn test_badge_icons_without_time() {
+    let mut log = logger(BadgeOptions { icons: true, time: false });
+    for (level, console) in [
+        (LEVEL::Info, "\x1b[30;42m ✔ INFO  \x1b[0m done\n"),
+        (LEVEL::Warn, "\x1b[30;43m ⚠ WARN  \x1b[0m done\n"),
+        (LEVEL::Error, "\x1b[97;41m ✖ ERROR \x1b[0m done\n"),
+    ] {
+        assert_eq!(log.fmt("cli", level, "", 0, "done\n".to_string()).console_body.as_deref(), Some(console));
+    }
+    log.clear_console_formatter();
+    assert_eq!(log.fmt("cli", LEVEL::Info, "", 0, "done".to_string()).console_body, None);
+}
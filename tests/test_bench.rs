@@ -83,3 +83,62 @@ async fn bench_log_parallel() {
     println!("Average log time per task: {:.2?}", total_duration / num_tasks as u32);
     println!("Average log time per operation: {:.2?}", avg_duration);
 }
+
+async fn format_stage_cost(stage: tklog::record::FormatStage) -> Duration {
+    let path = std::env::temp_dir().join(format!("tklog_bench_stage_{:?}_{}.log", stage, std::process::id()));
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Trace)
+        .set_format(tklog::Format::LevelFlag | tklog::Format::Date | tklog::Format::Time | tklog::Format::Microseconds | tklog::Format::LongFileName)
+        .set_cutmode_by_size(path.to_str().unwrap(), 1 << 30, 0, false)
+        .await;
+    log.set_format_stage(stage);
+    let iterations = 10_000;
+    let start = Instant::now();
+    for i in 0..iterations {
+        log.enqueue_static(LEVEL::Info, module_path!(), file!(), line!(), format!("this is async log {}", i));
+    }
+    let elapsed = start.elapsed();
+    log.flush().await;
+    let _ = std::fs::remove_file(&path);
+    elapsed / iterations
+}
+
+#[tokio::test]
+async fn bench_format_stage() {
+    use tklog::record::FormatStage;
+    let call_site = format_stage_cost(FormatStage::CallSite).await;
+    let worker = format_stage_cost(FormatStage::Worker).await;
+    println!("Average call-site cost, formatting at the call site: {:.2?}", call_site);
+    println!("Average call-site cost, formatting on the worker: {:.2?}", worker);
+}
+
+fn static_message_cost(fast: bool) -> Duration {
+    let path = std::env::temp_dir().join(format!("tklog_bench_static_{}_{}.log", fast, std::process::id()));
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false)
+        .set_printmode(tklog::PRINTMODE::PUNCTUAL)
+        .set_format(tklog::Format::LevelFlag | tklog::Format::Date | tklog::Format::Time)
+        .set_cutmode_by_size(path.to_str().unwrap(), 1 << 30, 0, false);
+    let iterations = 100_000;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        if fast {
+            log.print_static(LEVEL::Info, module_path!(), "cache miss");
+        } else {
+            let s = log.fmt(module_path!(), LEVEL::Info, "", 0, vec![format!("{}", "cache miss")].join(""));
+            log.safeprint(LEVEL::Info, module_path!(), s);
+        }
+    }
+    let elapsed = start.elapsed();
+    let _ = std::fs::remove_file(&path);
+    elapsed / iterations
+}
+
+#[test]
+fn bench_static_message() {
+    let general = static_message_cost(false);
+    let fast = static_message_cost(true);
+    println!("Average cost of a literal message, general path: {:.2?}", general);
+    println!("Average cost of a literal message, print_static: {:.2?}", fast);
+}
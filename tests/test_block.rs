@@ -0,0 +1,128 @@
+mod common;
+
+use std::{fs, thread, time::Duration};
+
+use tklog::{block::MAX_BLOCK_BYTES, info, sync::Logger, Format, LEVEL, LOG, PRINTMODE};
+
+use common::dir;
+
+#[test]
+fn test_block_lines_formatted() {
+    let dir = dir("formatted");
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_formatter("{level} {module}: {message}\n");
+    log.set_cutmode_by_size(&path.to_string_lossy(), 0, 0, false);
+    log.log_block(LEVEL::Info, "app", |block| {
+        block.line("config:").line("  port = 8080");
+    });
+    let mut ran = false;
+    log.set_level(LEVEL::Warn).log_block(LEVEL::Info, "app", |_| ran = true);
+    assert!(!ran);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] app: config:\n[INFO] app:   port = 8080\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_block_truncated() {
+    let dir = dir("truncated");
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 0, 0, false);
+    let line = "x".repeat(MAX_BLOCK_BYTES / 4);
+    log.log_block(LEVEL::Info, "app", |block| {
+        for _ in 0..6 {
+            block.line(line.as_str());
+        }
+        block.line("short");
+    });
+    let s = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = s.lines().collect();
+    assert_eq!(lines.len(), 5, "{:?}", lines.iter().map(|l| l.len()).collect::<Vec<_>>());
+    assert_eq!(lines[4], format!("[INFO] tklog: 3 more lines of the block left out past {} bytes", MAX_BLOCK_BYTES));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_block_not_split_by_cut() {
+    let dir = dir("cut");
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 30, 0, false);
+    for b in 0..3 {
+        log.log_block(LEVEL::Info, "app", |block| {
+            for i in 0..4 {
+                block.line(format!("block {} line {}", b, i));
+            }
+        });
+    }
+    for entry in fs::read_dir(&dir).unwrap() {
+        let s = fs::read_to_string(entry.unwrap().path()).unwrap();
+        let blocks: Vec<&str> = s.lines().map(|l| &l[..13]).collect();
+        assert!(blocks.len() % 4 == 0 && blocks.chunks(4).all(|c| c.iter().all(|b| *b == c[0])), "{}", s);
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_block_contiguous_across_threads() {
+    let dir = dir("threads");
+    let path = dir.join("app.log");
+    LOG.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 0, 0, false);
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            thread::spawn(move || {
+                for n in 0..20 {
+                    LOG.log_block(LEVEL::Info, "app", |block| {
+                        for i in 0..10 {
+                            block.line(format!("block {}.{} row {}", t, n, i));
+                        }
+                    });
+                    info!("between", t, n);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let mut s = String::new();
+    for _ in 0..200 {
+        s = fs::read_to_string(&path).unwrap_or_default();
+        if s.lines().count() == 4 * 20 * 11 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let lines: Vec<&str> = s.lines().collect();
+    assert_eq!(lines.len(), 4 * 20 * 11);
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].starts_with("[INFO] between") {
+            i += 1;
+            continue;
+        }
+        let block = lines[i].rsplit_once(" row ").unwrap().0;
+        for row in 0..10 {
+            assert_eq!(lines[i + row], format!("{} row {}", block, row));
+        }
+        i += 10;
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_block_async() {
+    let dir = dir("async");
+    let path = dir.join("app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 0, 0, false).await;
+    log.log_block(LEVEL::Warn, "app", |block| {
+        block.line("a").line("b");
+    })
+    .await;
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[WARN] a\n[WARN] b\n");
+    let _ = fs::remove_dir_all(&dir);
+}
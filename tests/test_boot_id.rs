@@ -0,0 +1,61 @@
+use std::fs;
+
+use tklog::{boot::boot_id, postmortem::previous_boot_id, sync::Logger, Format, LEVEL};
+
+#[test]
+fn test_boot_id_shape_and_stability() {
+    let id = boot_id();
+    assert_eq!(id.len(), 8);
+    assert!(id.bytes().all(|b| b.is_ascii_lowercase() || (b'2'..=b'7').contains(&b)), "{}", id);
+    assert_eq!(boot_id(), id);
+
+    tklog::force_reconfigure(|log| {
+        log.set_console(false);
+    });
+    assert_eq!(boot_id(), id);
+}
+
+#[test]
+fn test_boot_id_in_lines() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    let line = |log: &mut Logger| log.fmt("app", LEVEL::Info, "", 0, "up".to_string()).file_body;
+    assert_eq!(line(&mut log), "[INFO] up\n");
+
+    log.set_include_boot_id(true);
+    assert_eq!(line(&mut log), format!("[INFO] up boot_id={}\n", boot_id()));
+    log.set_formatter("{bootid} {level}{message}\n");
+    assert_eq!(line(&mut log), format!("{} [INFO]up\n", boot_id()));
+
+    log.set_format_json(true);
+    assert!(line(&mut log).ends_with(&format!(",\"message\":\"up\",\"boot_id\":\"{}\"}}\n", boot_id())));
+    log.preset_k8s();
+    assert!(line(&mut log).contains(&format!(",\"boot_id\":\"{}\"", boot_id())));
+    log.set_include_boot_id(false);
+    assert!(!line(&mut log).contains("boot_id"));
+}
+
+#[test]
+fn test_previous_boot_id() {
+    let dir = std::env::temp_dir().join(format!("tklog_boot_id_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.log");
+    fs::write(&path, "[INFO] up boot_id=aaaaaaaa\n[INFO] up boot_id=bbbbbbbb\n{\"v\":2,\"boot_id\":\"cccccccc\"}\n").unwrap();
+    assert_eq!(previous_boot_id(&path).unwrap().as_deref(), Some("cccccccc"));
+
+    // The lines of this run come after the previous one's.
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_include_boot_id(true).set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false);
+    let s = log.fmt("app", LEVEL::Info, "", 0, "restarted".to_string());
+    log.print(LEVEL::Info, "app", s);
+    assert!(fs::read_to_string(&path).unwrap().ends_with(&format!("boot_id={}\n", boot_id())));
+    assert_eq!(previous_boot_id(&path).unwrap().as_deref(), Some("cccccccc"));
+
+    // Right after a rotation the live file has none yet.
+    fs::rename(&path, dir.join("app_1.log")).unwrap();
+    fs::write(&path, "").unwrap();
+    assert_eq!(previous_boot_id(&path).unwrap().as_deref(), Some("cccccccc"));
+    assert_eq!(previous_boot_id(dir.join("other.log")).unwrap(), None);
+    let _ = fs::remove_dir_all(&dir);
+}
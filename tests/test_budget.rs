@@ -0,0 +1,63 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{Local, TimeZone};
+use tklog::{clock::ManualClock, sync::Logger, LogContext, LEVEL};
+
+static NOTICES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn keep_notices(ctx: &LogContext) -> bool {
+    if ctx.modname == "tklog" {
+        NOTICES.lock().unwrap().push(ctx.log_body.clone());
+    }
+    true
+}
+
+#[test]
+fn test_adaptive_budget_raises_and_resets() {
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 5, 1, 6, 0, 0).unwrap()));
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_clock(clock.clone()).set_custom_handler(keep_notices);
+    log.set_adaptive_budget(1000);
+    let line = "x".repeat(99);
+
+    // Six lines by 6:00 are under the budget; the projection waits for noon.
+    for _ in 0..6 {
+        log.fmt("app", LEVEL::Info, "", 0, line.clone());
+    }
+    assert_eq!(log.get_level("app"), LEVEL::Info);
+    assert!(NOTICES.lock().unwrap().is_empty());
+
+    // At noon seven lines project to about twice the budget.
+    clock.advance(Duration::from_secs(6 * 3600));
+    log.fmt("app", LEVEL::Info, "", 0, line.clone());
+    assert_eq!(log.get_level("app"), LEVEL::Warn);
+    assert_eq!(log.get_level("tklog"), LEVEL::Warn);
+    assert!(NOTICES.lock().unwrap()[0].contains("levels raised one step until the next day"), "{:?}", NOTICES.lock().unwrap());
+
+    clock.advance(Duration::from_secs(11 * 3600));
+    assert_eq!(log.get_level("app"), LEVEL::Warn, "still the same day");
+
+    clock.advance(Duration::from_secs(3600));
+    assert_eq!(log.get_level("app"), LEVEL::Info);
+    assert!(NOTICES.lock().unwrap()[1].contains("new day, levels back as set"), "{:?}", NOTICES.lock().unwrap());
+
+    log.set_adaptive_budget(0);
+    for _ in 0..20 {
+        log.fmt("app", LEVEL::Info, "", 0, line.clone());
+    }
+    assert_eq!(log.get_level("app"), LEVEL::Info, "off");
+}
+
+#[test]
+fn test_adaptive_budget_used_up_before_noon() {
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap()));
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Debug).set_clock(clock).set_adaptive_budget(500);
+    for _ in 0..10 {
+        log.fmt("app", LEVEL::Info, "", 0, "y".repeat(99));
+    }
+    assert_eq!(log.get_level("app"), LEVEL::Info, "Debug raised one step");
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::fs;
+
+use tklog::{info, sync::Logger, Format, LEVEL, PRINTMODE};
+
+use common::logfile;
+
+#[test]
+fn test_builder_build() {
+    let file = logfile("build");
+    let mut log = Logger::builder()
+        .level(LEVEL::Warn)
+        .format(Format::LevelFlag)
+        .formatter("{level} {message}\n")
+        .cutmode_by_size(&file, 64 << 20, 10, false)
+        .console(false)
+        .printmode(PRINTMODE::PUNCTUAL)
+        .build();
+    assert_eq!(log.get_level("app"), LEVEL::Warn);
+    let s = log.fmt("app", LEVEL::Error, "", 0, "built".to_string());
+    log.print(LEVEL::Error, "app", s);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[ERROR] built\n");
+    let _ = fs::remove_file(&file);
+}
+
+// The only test of this file on `LOG`: the global is configured once.
+#[test]
+fn test_builder_apply_global() {
+    let file = logfile("global");
+    let builder = Logger::builder().format(Format::LevelFlag).cutmode_by_size(&file, 0, 0, false).console(false).printmode(PRINTMODE::PUNCTUAL);
+    let handle = builder.clone().apply_global().unwrap();
+    assert_eq!(handle.location().line(), line!() - 1);
+    info!("applied");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] applied\n");
+
+    let err = builder.level(LEVEL::Off).apply_global().unwrap_err();
+    assert_eq!(err.location.line(), handle.location().line());
+    assert!(tklog::init_once(|_| {}).is_err());
+    info!("still applied");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] applied\n[INFO] still applied\n");
+    let _ = fs::remove_file(&file);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_builder_async() {
+    let file = logfile("async");
+    let builder = tklog::Async::Logger::builder().format(Format::LevelFlag).cutmode_by_size(&file, 0, 0, false).console(false);
+    let log = builder.clone().level(LEVEL::Info).build().await;
+    let s = log.fmt("app", LEVEL::Info, "", 0, "built".to_string());
+    log.log(LEVEL::Info, "app", s);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] built\n");
+    drop(log);
+
+    let handle = builder.clone().level(LEVEL::Error).apply_async_global().await.unwrap();
+    assert_eq!(handle.location().line(), line!() - 1);
+    let err = builder.apply_async_global().await.unwrap_err();
+    assert_eq!(err.location.line(), handle.location().line());
+    assert_eq!(tklog::global_async().await.get_level("app"), LEVEL::Error);
+    let _ = fs::remove_file(&file);
+}
@@ -0,0 +1,48 @@
+use std::{fs, sync::Arc, time::Duration};
+
+use tklog::{clock::ManualClock, sync::Logger, Format, LEVEL};
+
+fn emit(log: &mut Logger, msg: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+    if !s.is_empty() {
+        log.print(LEVEL::Info, "app", s);
+    }
+}
+
+#[test]
+fn test_trace_callers_samples_and_expires() {
+    let path = std::env::temp_dir().join(format!("tklog_callers_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let clock = Arc::new(ManualClock::new());
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_format(Format::LevelFlag)
+        .set_formatter("{level} {message}\n")
+        .set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false)
+        .set_clock(clock.clone())
+        .trace_callers("cache miss", 3, Duration::from_secs(60));
+
+    for i in 1..=5 {
+        emit(&mut log, &format!("cache miss {}", i));
+        emit(&mut log, "cache hit");
+    }
+    clock.advance(Duration::from_secs(60));
+    emit(&mut log, "cache miss 6");
+    emit(&mut log, "cache miss 7");
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(content.matches("caller trace of \"cache miss\", match ").count(), 2, "{}", content);
+    assert!(content.contains("match 1:\n"), "{}", content);
+    assert!(content.contains("match 4:\n"), "{}", content);
+    assert!(content.contains("test_trace_callers_samples_and_expires"), "the backtrace shows the caller: {}", content);
+    assert!(content.contains("[INFO] caller trace of \"cache miss\" ended: 5 matching lines\n[INFO] cache miss 6\n[INFO] cache miss 7\n"), "{}", content);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_stop_trace_callers() {
+    let mut log = Logger::new();
+    log.set_console(false).trace_callers("x", 1, Duration::from_secs(60)).stop_trace_callers();
+    let s = log.fmt("app", LEVEL::Info, "", 0, "x".to_string());
+    assert!(!s.file_body.contains("caller trace"));
+}
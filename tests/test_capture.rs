@@ -0,0 +1,103 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tklog::{
+    capture::{Capture, CapturedRecord},
+    sync::Logger,
+    Format, LEVEL, LOG,
+};
+
+fn logger() -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::Nano);
+    log
+}
+
+#[test]
+fn test_capture_multi_logger() {
+    let mut log = Arc::new(Mutex::new(logger()));
+    let capture = Capture::install(&log);
+    tklog::debugs!(&mut log, "below the level");
+    tklog::infos!(&mut log, "cache", "miss");
+    let line = line!() - 1;
+    tklog::formats!(&mut log, LEVEL::Error, "{} failed", "upload");
+    let records = capture.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        records[0],
+        CapturedRecord {
+            level: LEVEL::Info,
+            module: module_path!().to_string(),
+            message: "cachemiss".to_string(),
+            file: file!().to_string(),
+            line,
+        }
+    );
+    assert_eq!((records[1].level, records[1].message.as_str()), (LEVEL::Error, "upload failed"));
+
+    capture.clear();
+    assert!(capture.is_empty());
+    tklog::warns!(&mut log, "again");
+    assert_eq!(capture.records()[0].message, "again");
+}
+
+// The message is the one logged, whatever the format lays out around it.
+#[test]
+fn test_capture_before_layout() {
+    let mut log = logger();
+    log.set_format(Format::LevelFlag).set_formatter("{level}|{message}|{time}\n");
+    let capture = Capture::install(&log);
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "raw".to_string());
+    assert!(s.file_body.contains("|raw|"));
+    assert_eq!(capture.records()[0].message, "raw");
+}
+
+#[test]
+fn test_capture_uninstall_on_drop() {
+    let mut log = logger();
+    let first = Capture::install(&log);
+    let second = Capture::install(&log);
+    log.fmt("app", LEVEL::Info, "", 0, "both".to_string());
+    drop(first);
+    log.fmt("app", LEVEL::Info, "", 0, "second".to_string());
+    let messages: Vec<String> = second.records().into_iter().map(|r| r.message).collect();
+    assert_eq!(messages, ["both", "second"]);
+    drop(second);
+    // No capture left: the format alone decides on the file and line again.
+    assert!(!log.is_file_line(LEVEL::Info, "app"));
+}
+
+#[test]
+fn test_capture_global() {
+    LOG.set_console(false).set_level(LEVEL::Trace);
+    let capture = Capture::install(&LOG);
+    tklog::info!("global", 7);
+    let records = capture.records();
+    let mine: Vec<&CapturedRecord> = records.iter().filter(|r| r.message == "global7").collect();
+    assert_eq!(mine.len(), 1);
+    assert_eq!(mine[0].file, file!());
+}
+
+#[tokio::test]
+async fn test_capture_async_wait_for() {
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano);
+    let log = Arc::new(log);
+    let capture = Capture::install(&log);
+    let task = {
+        let log = log.clone();
+        tokio::spawn(async move {
+            for i in 0..3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                tklog::async_infos!(&log, "line ", i);
+            }
+        })
+    };
+    assert!(capture.wait_for(3, Duration::from_secs(5)).await);
+    task.await.unwrap();
+    let messages: Vec<String> = capture.records().into_iter().map(|r| r.message).collect();
+    assert_eq!(messages, ["line 0", "line 1", "line 2"]);
+    assert!(!capture.wait_for(4, Duration::from_millis(20)).await);
+}
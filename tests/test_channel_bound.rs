@@ -0,0 +1,98 @@
+mod common;
+
+use std::{fs, sync::Arc, time::Duration};
+
+use tklog::{clock::ManualClock, Async::Logger, Format, OverflowPolicy, LEVEL};
+
+use common::logfile;
+
+async fn logger(path: &str, capacity: usize, policy: OverflowPolicy) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    log.set_channel_capacity(capacity).set_overflow_policy(policy);
+    log.set_cutmode_by_size(path, 0, 0, false).await;
+    log
+}
+
+fn write(log: &Logger, lines: std::ops::Range<usize>) {
+    for i in lines {
+        log.enqueue(LEVEL::Info, "app", "", 0, format!("l{}", i));
+    }
+}
+
+fn expected(lines: std::ops::Range<usize>) -> String {
+    lines.map(|i| format!("[INFO] l{}\n", i)).collect()
+}
+
+// The consumer of a current-thread runtime only runs once the test awaits,
+// so the queue fills up.
+#[tokio::test]
+async fn test_channel_bound_drop_newest() {
+    let path = logfile("newest");
+    let log = logger(&path, 3, OverflowPolicy::DropNewest).await;
+    write(&log, 0..5);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected(0..3));
+    assert_eq!(log.dropped_records(), 2);
+    assert_eq!(log.pending_records(), 0);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_channel_bound_drop_oldest() {
+    let path = logfile("oldest");
+    let log = logger(&path, 3, OverflowPolicy::DropOldest).await;
+    write(&log, 0..5);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected(2..5));
+    assert_eq!(log.dropped_records(), 2);
+    assert_eq!(log.pending_records(), 0);
+    let _ = fs::remove_file(&path);
+}
+
+// Lines wait for room on a multi-thread runtime, and none is lost.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_channel_bound_block() {
+    let path = logfile("block");
+    let log = logger(&path, 2, OverflowPolicy::Block).await;
+    write(&log, 0..200);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected(0..200));
+    assert_eq!(log.dropped_records(), 0);
+    let _ = fs::remove_file(&path);
+}
+
+// A line that cannot wait on a current-thread runtime is queued past the
+// capacity.
+#[tokio::test]
+async fn test_channel_bound_block_current_thread() {
+    let path = logfile("current");
+    let log = logger(&path, 2, OverflowPolicy::Block).await;
+    write(&log, 0..5);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected(0..5));
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_channel_bound_notice() {
+    let path = logfile("notice");
+    let clock = Arc::new(ManualClock::new());
+    let mut log = logger(&path, 3, OverflowPolicy::DropNewest).await;
+    log.set_clock(clock.clone());
+    write(&log, 0..5);
+    log.flush().await;
+
+    // No warning before 10s have passed.
+    clock.advance(Duration::from_secs(5));
+    write(&log, 5..6);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected(0..3) + &expected(5..6));
+
+    clock.advance(Duration::from_secs(5));
+    write(&log, 6..7);
+    log.flush().await;
+    let lines = expected(0..3) + &expected(5..6) + "[WARN] dropped 2 records in the last 10s\n" + &expected(6..7);
+    assert_eq!(fs::read_to_string(&path).unwrap(), lines);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "check")]
+
+mod common;
+
+use std::{fs, process::Command};
+
+use common::dir;
+
+fn check(args: &[&str]) -> (bool, String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_tklog-check")).args(args).output().unwrap();
+    (out.status.success(), String::from_utf8(out.stdout).unwrap(), String::from_utf8(out.stderr).unwrap())
+}
+
+#[test]
+fn test_check_config() {
+    let dir = dir("config");
+    let config = dir.join("tklog.conf");
+    let logfile = dir.join("app.log");
+    fs::write(&config, format!("level = \"info\"\nconsole = false\nformat = \"LevelFlag\"\nfile = \"{}\"\nmaxsize = \"1MB\"\nbackups = 3\n", logfile.display())).unwrap();
+
+    let (ok, out, _) = check(&[config.to_str().unwrap()]);
+    assert!(ok);
+    assert!(out.starts_with("valid: console text, file text\nlevel: Info\n"), "{}", out);
+    assert!(out.contains(&format!("file: {} (cutmode: SIZE, mode: DAY, maxsize: 1000000, backups: 3, compress: false)\n", logfile.display())), "{}", out);
+    assert!(out.ends_with("preview:\n  Debug is below the level\n  file:    [INFO] Info preview\n  file:    [WARN] Warn preview\n  file:    [ERROR] Error preview\n  file:    [FATAL] Fatal preview\n"), "{}", out);
+    // Loading the config logs nothing of its own.
+    assert_eq!(fs::read_to_string(&logfile).unwrap(), "");
+
+    fs::write(&config, "level = \"loud\"\n").unwrap();
+    let (ok, _, err) = check(&[config.to_str().unwrap()]);
+    assert!(!ok);
+    assert!(err.ends_with("config refused: line 1: `level` takes a level, not `loud`\n"), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_check_simulate() {
+    let dir = dir("simulate");
+    let (ok, out, _) = check(&["simulate", "--lines", "5000", "--size-cut", "64KB", "--dir", dir.to_str().unwrap(), "--backups", "2"]);
+    assert!(ok);
+    let files: Vec<&str> = out.lines().skip(1).map(|l| l.split_whitespace().next().unwrap()).collect();
+    assert_eq!(files.first(), Some(&"app.log"));
+    assert_eq!(files.len(), 3, "{}", out);
+    assert!(out.starts_with(&format!("5000 lines into {}, cut at 64000 bytes:\n", dir.display())));
+
+    let (ok, _, err) = check(&["simulate", "--lines", "10"]);
+    assert!(!ok);
+    assert!(err.starts_with("simulate needs --size-cut\nusage:"), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,63 @@
+use chrono::{Local, TimeZone};
+use tklog::{sync::Logger, Error, Format, TestMode, COLUMN, LEVEL};
+
+fn line(log: &mut Logger) -> String {
+    log.fmt("app", LEVEL::Info, "src/main.rs", 3, "m".to_string()).file_body
+}
+
+#[test]
+fn test_column_order() {
+    let time = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::Time | Format::ShortFileName);
+    log.set_test_mode(TestMode {
+        fixed_time: time,
+        fixed_seq_start: 1,
+    })
+    .unwrap();
+    assert_eq!(line(&mut log), "[INFO] 12:00:00 main.rs 3:m\n");
+
+    log.set_column_order(&[COLUMN::TIME, COLUMN::LOGFLAG, COLUMN::MESSAGE]).unwrap();
+    assert_eq!(line(&mut log), "12:00:00 [INFO] m\n");
+
+    log.set_column_order(&[COLUMN::LOGFLAG, COLUMN::MESSAGE, COLUMN::TIME, COLUMN::FILEFLAG]).unwrap();
+    assert_eq!(line(&mut log), "[INFO] m 12:00:00 main.rs 3\n");
+
+    // A column the format leaves empty takes no space, nor its colon.
+    log.set_column_order(&[COLUMN::LOGFLAG, COLUMN::TIME, COLUMN::THREAD, COLUMN::FILEFLAG, COLUMN::COLON, COLUMN::MESSAGE])
+        .unwrap();
+    log.set_format(Format::Time);
+    assert_eq!(line(&mut log), "12:00:00 m\n");
+    log.set_format(Format::ShortFileName);
+    assert_eq!(line(&mut log), "main.rs 3:m\n");
+
+    log.clear_column_order().set_format(Format::LevelFlag | Format::Time | Format::ShortFileName);
+    assert_eq!(line(&mut log), "[INFO] 12:00:00 main.rs 3:m\n");
+
+    // A formatter comes first.
+    log.set_column_order(&[COLUMN::MESSAGE, COLUMN::LOGFLAG]).unwrap().set_formatter("{file}|{message}\n");
+    assert_eq!(line(&mut log), "main.rs 3|m\n");
+}
+
+#[test]
+fn test_column_order_invalid() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    assert!(matches!(log.set_column_order(&[COLUMN::LOGFLAG]), Err(Error::InvalidColumnOrder(_))));
+    assert!(matches!(log.set_column_order(&[COLUMN::MESSAGE, COLUMN::MESSAGE]), Err(Error::InvalidColumnOrder(_))));
+    assert!(matches!(
+        log.set_column_order(&[COLUMN::TIME, COLUMN::MESSAGE, COLUMN::TIME]),
+        Err(Error::InvalidColumnOrder(_))
+    ));
+    // The logger keeps its layout.
+    assert_eq!(line(&mut log), "[INFO] m\n");
+}
+
+#[tokio::test]
+async fn test_column_order_async() {
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::ShortFileName);
+    log.set_column_order(&[COLUMN::MESSAGE, COLUMN::LOGFLAG, COLUMN::FILEFLAG]).unwrap();
+    let s = log.fmt("app", LEVEL::Warn, "src/main.rs", 3, "m".to_string());
+    assert_eq!(s.file_body, "m [WARN] main.rs 3\n");
+}
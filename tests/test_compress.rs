@@ -0,0 +1,113 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tklog::{sync::Logger, CompressDecision, CompressType, Format, RotationEvent};
+
+static EVENTS: Mutex<Vec<RotationEvent>> = Mutex::new(Vec::new());
+
+fn on_rotate(e: &RotationEvent) {
+    EVENTS.lock().unwrap().push(e.clone());
+}
+
+fn wait_event(filename: &str) -> RotationEvent {
+    let start = Instant::now();
+    loop {
+        if let Some(e) = EVENTS.lock().unwrap().iter().find(|e| e.filename == filename) {
+            return e.clone();
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "no rotation event for {}", filename);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn rotate(name: &str, line: impl Fn(usize) -> String, configure: impl FnOnce(&mut Logger)) -> (PathBuf, RotationEvent) {
+    let dir = std::env::temp_dir().join(format!("tklog_compress_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap().to_string();
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_format(Format::Nano)
+        .set_cutmode_by_size(&filename, 100 * 1024, 0, true)
+        .set_compression(CompressType::Gzip, 9)
+        .set_compression_skip_ratio(0.7)
+        .set_rotation_handler(on_rotate);
+    configure(&mut log);
+    let (mut i, mut written) = (0, 0);
+    while written <= 110 * 1024 {
+        let s = log.fmt("compress", tklog::LEVEL::Info, "", 0, line(i));
+        written += s.file_body.len();
+        log.print(tklog::LEVEL::Info, "compress", s);
+        i += 1;
+    }
+    (dir, wait_event(&filename))
+}
+
+#[test]
+fn test_compression_skipped_for_random_payload() {
+    let mut rng = XorShift(0x2545F4914F6CDD1D);
+    let lines: Vec<String> = (0..1200).map(|_| (0..100).map(|_| BASE64[(rng.next() % 64) as usize] as char).collect::<String>() + "\n").collect();
+    let (dir, event) = rotate("skip", |i| lines[i].clone(), |_| ());
+    match event.compression {
+        CompressDecision::Skipped { ratio } => assert!(ratio > 0.7, "{}", ratio),
+        other => panic!("expected a skipped compression, got {:?}", other),
+    }
+    assert_eq!(event.backup, dir.join("app_1.log"));
+    assert!(event.backup.exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_compression_applied_for_text() {
+    let (dir, event) = rotate("text", |i| format!("request {} served in 12ms by worker-3\n", i % 10), |_| ());
+    assert_eq!(event.compression, CompressDecision::Compressed);
+    assert_eq!(event.backup, dir.join("app_1.log.gz"));
+    assert!(event.backup.exists());
+    assert!(!dir.join("app_1.log").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn nearly_full(_: &Path) -> io::Result<u64> {
+    Ok(4096)
+}
+
+#[test]
+fn test_compression_skipped_without_space() {
+    let (dir, event) = rotate("nospace", |i| format!("request {} served in 12ms by worker-3\n", i % 10), |log| {
+        log.set_compression_space_factor(0.5).set_space_probe(nearly_full);
+    });
+    match event.compression {
+        CompressDecision::NoSpace { needed, available } => {
+            assert_eq!(available, 4096);
+            assert_eq!(needed, (fs::metadata(&event.backup).unwrap().len() as f64 * 0.5).ceil() as u64);
+        }
+        other => panic!("expected a NoSpace decision, got {:?}", other),
+    }
+    assert_eq!(event.backup, dir.join("app_1.log"));
+    assert!(event.backup.exists());
+    assert!(!dir.join("app_1.log.gz").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_available_space() {
+    assert!(tklog::available_space(&std::env::temp_dir().join("app.log")).unwrap() > 0);
+}
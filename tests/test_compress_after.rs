@@ -0,0 +1,86 @@
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tklog::{sync::Logger, CompressDecision, Format, RotationEvent, LEVEL};
+
+use common::missing_dir;
+
+static EVENTS: Mutex<Vec<RotationEvent>> = Mutex::new(Vec::new());
+
+fn on_rotate(e: &RotationEvent) {
+    EVENTS.lock().unwrap().push(e.clone());
+}
+
+/// The names of the backups next to `live` once they are `expected`.
+fn wait_backups(dir: &Path, live: &Path, expected: &[&str]) -> Vec<String> {
+    let start = Instant::now();
+    loop {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p != live)
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        if names == expected || start.elapsed() > Duration::from_secs(10) {
+            return names;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+// Size backups count up: the two of the highest counters stay raw.
+#[test]
+fn test_compress_after() {
+    let dir = missing_dir("sync");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_rotation_handler(on_rotate).set_compress_after(2);
+    log.set_cutmode_by_size(filename, 5, 3, true);
+    for msg in ["first", "secnd", "third", "forth", "fifth"] {
+        let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "app", s);
+    }
+    let expected = ["app_2.log.gz", "app_3.log", "app_4.log"];
+    assert_eq!(wait_backups(&dir, &path, &expected), expected);
+    assert_eq!(fs::read_to_string(dir.join("app_3.log")).unwrap(), "third");
+    assert!(EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| e.filename == filename)
+        .all(|e| e.compression == CompressDecision::Deferred));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_compress_after_async() {
+    let dir = missing_dir("async");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_compress_after(1);
+    log.set_cutmode_by_size(filename, 5, 0, true).await;
+    for msg in ["first", "secnd", "third", "forth"] {
+        let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "app", s).await;
+    }
+    log.flush().await;
+    let expected = ["app_1.log.gz", "app_2.log.gz", "app_3.log"];
+    let names = tokio::task::spawn_blocking({
+        let (dir, path) = (dir.clone(), path.clone());
+        move || wait_backups(&dir, &path, &expected)
+    })
+    .await
+    .unwrap();
+    assert_eq!(names, expected);
+    let _ = fs::remove_dir_all(&dir);
+}
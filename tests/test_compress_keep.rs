@@ -0,0 +1,62 @@
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tklog::{sync::Logger, CompressDecision, Error, Format, LEVEL};
+
+use common::{gunzip, missing_dir, on_rotate, wait_backups, EVENTS};
+
+fn backups(dir: &Path, live: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p != live)
+        .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_compress_keep_original() {
+    let dir = missing_dir("sync");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_format(Format::Nano)
+        .set_rotation_handler(on_rotate)
+        .set_compress_keep_original(true);
+    assert!(matches!(log.set_compress_level(10), Err(Error::InvalidCompressLevel(10))));
+    log.set_compress_level(1).unwrap();
+    log.set_cutmode_by_size(filename, 5, 2, true);
+    for msg in ["first", "second", "third", "fourth", "fifth"] {
+        let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "app", s);
+    }
+    let rotated = wait_backups(filename, 4);
+    assert!(EVENTS.lock().unwrap().iter().all(|e| e.compression == CompressDecision::Compressed));
+
+    // Two backups are kept, each raw and compressed.
+    let start = Instant::now();
+    while backups(&dir, &path).len() > 4 && start.elapsed() < Duration::from_secs(10) {
+        thread::sleep(Duration::from_millis(20));
+    }
+    let newest: Vec<&PathBuf> = rotated[2..].iter().collect();
+    let mut expected: Vec<String> = Vec::new();
+    for gz in &newest {
+        let name = gz.file_name().unwrap().to_string_lossy().into_owned();
+        expected.push(name.strip_suffix(".gz").unwrap().to_string());
+        expected.push(name);
+    }
+    expected.sort();
+    assert_eq!(backups(&dir, &path), expected);
+    assert_eq!(gunzip(newest[0]), "third");
+    assert_eq!(fs::read_to_string(newest[0].with_extension("")).unwrap(), "third");
+    let _ = fs::remove_dir_all(&dir);
+}
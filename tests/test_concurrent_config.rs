@@ -0,0 +1,73 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use tklog::{debug, info, Format, LogOption, LEVEL, LOG, PRINTMODE};
+
+const THREADS: usize = 8;
+const LINES: usize = 2_000;
+
+// Reconfiguring `LOG` while threads log through it: every line is whole,
+// and each thread's Info lines all come, in order.
+#[test]
+fn test_concurrent_config() {
+    let path = std::env::temp_dir().join(format!("tklog_concurrent_config_{}.log", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    LOG.set_console(false)
+        .set_printmode(PRINTMODE::PUNCTUAL)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(path, 0, 0, false);
+
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            let mut debug = false;
+            while !done.load(Ordering::Relaxed) {
+                debug = !debug;
+                tklog::set_level(if debug { LEVEL::Debug } else { LEVEL::Info });
+                tklog::set_option(LogOption {
+                    format: Some(Format::LevelFlag),
+                    ..LogOption::new()
+                });
+                LOG.set_mod_level(module_path!(), if debug { LEVEL::Trace } else { LEVEL::Info });
+                LOG.clear_module_level(module_path!());
+            }
+        });
+        let loggers: Vec<_> = (0..THREADS)
+            .map(|t| {
+                s.spawn(move || {
+                    for i in 0..LINES {
+                        info!("t", t, " ", i);
+                        debug!("t", t, " ", i);
+                    }
+                })
+            })
+            .collect();
+        for l in loggers {
+            l.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+
+    let content = fs::read_to_string(path).unwrap();
+    let mut next = [0; THREADS];
+    for line in content.lines() {
+        let (level, rest) = line.split_once(" t").unwrap_or_else(|| panic!("torn line {:?}", line));
+        let (t, i) = rest.split_once(' ').unwrap_or_else(|| panic!("torn line {:?}", line));
+        let (t, i): (usize, usize) = (t.parse().unwrap(), i.parse().unwrap());
+        match level {
+            "[INFO]" => {
+                assert_eq!(i, next[t], "thread {} out of order", t);
+                next[t] += 1;
+            }
+            "[DEBUG]" => assert!(i < next[t], "debug line {} of thread {} before its info line", i, t),
+            _ => panic!("torn line {:?}", line),
+        }
+    }
+    assert_eq!(next, [LINES; THREADS]);
+    let _ = fs::remove_file(path);
+}
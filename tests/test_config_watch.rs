@@ -0,0 +1,67 @@
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tklog::{ASYNC_LOG, LEVEL, LOG};
+
+fn config_file(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tklog_config_watch_{}_{}.toml", name, std::process::id()));
+    fs::write(&path, text).unwrap();
+    path
+}
+
+fn wait_for(mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        if Instant::now() > deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_watch_config_file() {
+    let path = config_file("sync", "console = false\nlevel = \"info\"\n");
+    let watch = LOG.watch_config_file(&path, Duration::from_millis(10)).unwrap();
+    let level = |module: &str| tklog::global().get_level(module);
+    assert_eq!(level("app::db"), LEVEL::Info);
+
+    fs::write(&path, "console = false\nlevel = \"info\"\n\n[modules]\n\"app::db\" = \"debug\"\n").unwrap();
+    assert!(wait_for(|| level("app::db") == LEVEL::Debug));
+
+    // A bad change is refused whole, a good one after it applies.
+    fs::write(&path, "level = \"trace\"\nconsole = \"maybe\"\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!((level("app"), level("app::db")), (LEVEL::Info, LEVEL::Debug));
+    fs::write(&path, "level = \"error\"\n[modules]\n").unwrap();
+    assert!(wait_for(|| level("app") == LEVEL::Error && level("app::db") == LEVEL::Error));
+
+    // Dropped, the watch stops.
+    drop(watch);
+    thread::sleep(Duration::from_millis(50));
+    fs::write(&path, "level = \"trace\"\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(level("app"), LEVEL::Error);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_config_file_async() {
+    let path = config_file("async", "console = false\nlevel = \"warn\"\n");
+    let _watch = ASYNC_LOG.watch_config_file(&path, Duration::from_millis(10)).await.unwrap();
+    assert_eq!(tklog::global_async().await.get_level("app"), LEVEL::Warn);
+    fs::write(&path, "console = false\nlevel = \"debug\"\n[modules]\nhyper = \"off\"\n").unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while tklog::global_async().await.get_level("hyper") != LEVEL::Off {
+        assert!(Instant::now() < deadline);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(tklog::global_async().await.get_level("app"), LEVEL::Debug);
+    let _ = fs::remove_file(&path);
+}
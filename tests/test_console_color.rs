@@ -0,0 +1,66 @@
+mod common;
+
+use std::{fs, io::IsTerminal};
+
+use tklog::{color::ColorOptions, sync::Logger, Format, LEVEL};
+
+use common::logfile;
+
+const FORCED: ColorOptions = ColorOptions { whole_line: false, force: true };
+
+#[test]
+fn test_console_color_level_flag() {
+    let path = logfile("flag");
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).set_console_color_options(FORCED);
+    for (level, code) in [(LEVEL::Debug, "2"), (LEVEL::Info, "32"), (LEVEL::Warn, "33"), (LEVEL::Fatal, "31")] {
+        let s = log.fmt("app", level, "", 0, "ready".to_string());
+        assert_eq!(s.console_body.unwrap(), format!("\x1b[{}m[{}]\x1b[0m ready\n", code, format!("{:?}", level).to_uppercase()));
+        assert_eq!(s.file_body, format!("[{}] ready\n", format!("{:?}", level).to_uppercase()));
+    }
+
+    let s = log.fmt("app", LEVEL::Error, "", 0, "disk full".to_string());
+    log.print(LEVEL::Error, "app", s);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[ERROR] disk full\n");
+
+    log.set_console_color_options(ColorOptions { whole_line: true, force: true });
+    let s = log.fmt("app", LEVEL::Error, "", 0, "disk full".to_string());
+    assert_eq!(s.console_body.unwrap(), "\x1b[31m[ERROR] disk full\x1b[0m\n");
+
+    log.set_console_color(false);
+    assert!(log.fmt("app", LEVEL::Error, "", 0, "plain".to_string()).console_body.is_none());
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_console_color_off_without_terminal() {
+    let forced = ["CLICOLOR_FORCE", "FORCE_COLOR"].iter().any(|v| std::env::var(v).is_ok_and(|v| !v.is_empty() && v != "0"));
+    if std::io::stdout().is_terminal() || forced {
+        return;
+    }
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_console_color(true);
+    assert!(log.fmt("app", LEVEL::Error, "", 0, "piped".to_string()).console_body.is_none());
+}
+
+#[test]
+fn test_console_color_skips_json() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format_json(true).set_console_color_options(FORCED);
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "m".to_string());
+    assert!(s.console_body.is_none());
+    assert!(!s.file_body.contains('\x1b'));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_console_color_async() {
+    let path = logfile("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_console_color_options(FORCED).set_cutmode_by_size(&path, 0, 0, false).await;
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "slow".to_string());
+    assert_eq!(s.console_body.as_deref(), Some("\x1b[33m[WARN]\x1b[0m slow\n"));
+    log.log(LEVEL::Warn, "app", s);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[WARN] slow\n");
+    let _ = fs::remove_file(&path);
+}
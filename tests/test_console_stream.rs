@@ -0,0 +1,49 @@
+use std::process::Command;
+
+use tklog::{sync::Logger, ConsoleStream, Format, LEVEL, PRINTMODE};
+
+const CHILD: &str = "TKLOG_CONSOLE_STREAM_CHILD";
+const LEVELS: [LEVEL; 4] = [LEVEL::Info, LEVEL::Warn, LEVEL::Info, LEVEL::Error];
+
+/// Runs `test` of this binary again with the logger writing to the console,
+/// and returns the lines of its stdout and stderr.
+fn child(test: &str) -> (Vec<String>, Vec<String>) {
+    let out = Command::new(std::env::current_exe().unwrap()).args([test, "--exact", "--nocapture"]).env(CHILD, "1").output().unwrap();
+    // The harness prints the name of the test before the first line.
+    let lines = |b: Vec<u8>| String::from_utf8(b).unwrap().lines().filter_map(|l| l.find('[').map(|i| l[i..].to_string())).collect();
+    (lines(out.stdout), lines(out.stderr))
+}
+
+#[test]
+fn test_console_stream_split() {
+    if std::env::var_os(CHILD).is_some() {
+        let mut log = Logger::new();
+        log.set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_console_stream(ConsoleStream::SplitAt(LEVEL::Warn));
+        for (i, level) in LEVELS.into_iter().enumerate() {
+            let s = log.fmt("app", level, "", 0, i.to_string());
+            log.print(level, "app", s);
+        }
+        return;
+    }
+    let (out, err) = child("test_console_stream_split");
+    assert_eq!(out, ["[INFO] 0", "[INFO] 2"]);
+    assert_eq!(err, ["[WARN] 1", "[ERROR] 3"]);
+}
+
+#[test]
+fn test_console_stream_stderr_async() {
+    if std::env::var_os(CHILD).is_some() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut log = tklog::Async::Logger::new();
+            log.set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_console_stream(ConsoleStream::Stderr);
+            for (i, level) in LEVELS.into_iter().enumerate() {
+                let s = log.fmt("app", level, "", 0, i.to_string());
+                log.print(level, "app", s).await;
+            }
+        });
+        return;
+    }
+    let (out, err) = child("test_console_stream_stderr_async");
+    assert!(out.is_empty(), "{:?}", out);
+    assert_eq!(err, ["[INFO] 0", "[WARN] 1", "[INFO] 2", "[ERROR] 3"]);
+}
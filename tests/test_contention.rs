@@ -0,0 +1,60 @@
+mod common;
+
+use std::{fs, thread};
+
+use tklog::{debug, info, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+const THREADS: usize = 16;
+const LINES: usize = 2_000;
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_contention_keeps_lines_whole_and_in_order() {
+    let path = logfile("order");
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            s.spawn(move || {
+                for i in 0..LINES {
+                    debug!("skipped ", t, i);
+                    info!("t", t, " ", i);
+                }
+            });
+        }
+    });
+
+    let content = fs::read_to_string(&path).unwrap();
+    let mut next = [0; THREADS];
+    for line in content.lines() {
+        let (t, i) = line.strip_prefix("[INFO] t").unwrap().split_once(' ').unwrap();
+        let t: usize = t.parse().unwrap();
+        assert_eq!(i.parse::<usize>().unwrap(), next[t], "thread {} out of order", t);
+        next[t] += 1;
+    }
+    assert_eq!(next, [LINES; THREADS]);
+    let _ = fs::remove_file(&path);
+
+    // Laid out under the lock, the sequence numbers follow the file.
+    let path = logfile("seq");
+    LOG.set_formatter("{seq} {message}\n").set_cutmode_by_size(&path, 0, 0, false);
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            s.spawn(move || {
+                for i in 0..LINES / 10 {
+                    info!("t", t, " ", i);
+                }
+            });
+        }
+    });
+    let seqs: Vec<u64> = fs::read_to_string(&path).unwrap().lines().map(|l| l.split_once(' ').unwrap().0.parse().unwrap()).collect();
+    assert_eq!(seqs.len(), THREADS * LINES / 10);
+    assert!(seqs.windows(2).all(|w| w[1] == w[0] + 1), "sequence numbers out of order");
+
+    // A change of settings reaches the lines of a thread that logged before.
+    LOG.set_level(LEVEL::Warn);
+    info!("dropped");
+    assert!(!fs::read_to_string(&path).unwrap().contains("dropped"));
+    let _ = fs::remove_file(&path);
+}
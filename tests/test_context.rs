@@ -0,0 +1,64 @@
+use tklog::{context, sync::Logger, Format, LEVEL};
+
+fn line(log: &mut Logger, msg: &str) -> String {
+    log.fmt("app", LEVEL::Info, "", 0, msg.to_string()).file_body
+}
+
+#[test]
+fn test_context_fields() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    assert_eq!(line(&mut log, "idle"), "[INFO] idle\n");
+
+    context::insert("user", "joe");
+    {
+        let _ctx = context::scope(&[("request_id", 42)]);
+        assert_eq!(line(&mut log, "start"), "[INFO] start user=joe request_id=42\n");
+        {
+            let _inner = context::scope(&[("request_id", 43)]);
+            assert_eq!(line(&mut log, "nested"), "[INFO] nested user=joe request_id=43\n");
+        }
+        let s = log.fmt_with_fields("app", LEVEL::Info, "", 0, None, "call".to_string(), tklog::fields_of!(request_id = 1));
+        assert_eq!(s.file_body, "[INFO] call request_id=1 user=joe\n");
+
+        log.set_format_json(true);
+        assert!(
+            line(&mut log, "json").ends_with(",\"message\":\"json\",\"user\":\"joe\",\"request_id\":\"42\"}\n"),
+            "{}",
+            line(&mut log, "json")
+        );
+        log.set_format_json(false);
+
+        log.set_context_fields(false);
+        assert_eq!(line(&mut log, "off"), "[INFO] off\n");
+        log.set_context_fields(true);
+    }
+    assert_eq!(line(&mut log, "done"), "[INFO] done user=joe\n");
+    context::remove("user");
+    assert!(context::current().is_empty());
+    assert_eq!(line(&mut log, "idle"), "[INFO] idle\n");
+}
+
+// Two tasks on one thread, each with its own context across `.await`.
+#[tokio::test]
+async fn test_context_tasks() {
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    let log = &log;
+    let task = |id: u32| async move {
+        context::in_task(&[("request_id", id)], async {
+            let mut lines = Vec::new();
+            for step in 0..3 {
+                context::insert("step", step);
+                tokio::task::yield_now().await;
+                lines.push(log.fmt("app", LEVEL::Info, "", 0, "work".to_string()).file_body);
+            }
+            lines
+        })
+        .await
+    };
+    let (a, b) = tokio::join!(task(1), task(2));
+    assert_eq!(a, (0..3).map(|s| format!("[INFO] work request_id=1 step={}\n", s)).collect::<Vec<_>>());
+    assert_eq!(b, (0..3).map(|s| format!("[INFO] work request_id=2 step={}\n", s)).collect::<Vec<_>>());
+    assert!(context::current().is_empty());
+}
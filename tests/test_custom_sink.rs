@@ -0,0 +1,123 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use tklog::{info, logsink::LogSink, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+#[derive(Debug, PartialEq)]
+enum Call {
+    Write(LEVEL, String),
+    Flush,
+}
+
+/// Records what a logger hands it.
+#[derive(Clone, Default)]
+struct Ring(Arc<Mutex<Vec<Call>>>);
+
+impl LogSink for Ring {
+    fn write(&mut self, level: LEVEL, formatted: &str) {
+        self.0.lock().unwrap().push(Call::Write(level, formatted.to_string()));
+    }
+
+    fn flush(&mut self) {
+        self.0.lock().unwrap().push(Call::Flush);
+    }
+}
+
+impl Ring {
+    fn take(&self) -> Vec<Call> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+fn write(level: LEVEL, s: &str) -> Call {
+    Call::Write(level, s.to_string())
+}
+
+#[test]
+fn test_custom_sink_sync() {
+    let path = logfile("sync");
+    let ring = Ring::default();
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).set_custom_sink(Box::new(ring.clone()));
+
+    let s = log.fmt("app", LEVEL::Info, "", 0, "both".to_string());
+    log.print(LEVEL::Info, "app", s);
+    assert_eq!(ring.take(), [write(LEVEL::Info, "[INFO] both\n"), Call::Flush]);
+
+    log.set_custom_sink_only(true);
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "sink only".to_string());
+    log.safeprint(LEVEL::Warn, "app", s);
+    assert_eq!(ring.take(), [write(LEVEL::Warn, "[WARN] sink only\n"), Call::Flush]);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] both\n");
+
+    log.clear_custom_sink();
+    assert_eq!(ring.take(), [Call::Flush]);
+    let s = log.fmt("app", LEVEL::Info, "", 0, "file again".to_string());
+    log.print(LEVEL::Info, "app", s);
+    assert!(ring.take().is_empty());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] both\n[INFO] file again\n");
+    let _ = fs::remove_file(&path);
+}
+
+// The only test of this file on `LOG`: its DELAY queue flushes the sink
+// once per batch.
+#[test]
+fn test_custom_sink_delay_batches() {
+    let ring = Ring::default();
+    LOG.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::LevelFlag).set_custom_sink(Box::new(ring.clone())).set_custom_sink_only(true);
+    for i in 0..200 {
+        info!("line", i);
+    }
+    let mut calls = Vec::new();
+    for _ in 0..500 {
+        calls.extend(ring.take());
+        if calls.iter().filter(|c| matches!(c, Call::Write(..))).count() == 200 && calls.last() == Some(&Call::Flush) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    LOG.clear_custom_sink();
+
+    let writes: Vec<&Call> = calls.iter().filter(|c| matches!(c, Call::Write(..))).collect();
+    let expected: Vec<Call> = (0..200).map(|i| write(LEVEL::Info, &format!("[INFO] line{}\n", i))).collect();
+    assert_eq!(writes, expected.iter().collect::<Vec<_>>());
+    assert_eq!(calls.last(), Some(&Call::Flush));
+    let flushes = calls.iter().filter(|c| **c == Call::Flush).count();
+    assert!((1..=200).contains(&flushes));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_custom_sink_async() {
+    let path = logfile("async");
+    let ring = Ring::default();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).await;
+    log.set_custom_sink(Box::new(ring.clone()));
+
+    for i in 0..100 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("queued {}", i));
+        log.log(LEVEL::Info, "app", s);
+    }
+    log.flush().await;
+    let calls = ring.take();
+    let writes: Vec<&Call> = calls.iter().filter(|c| matches!(c, Call::Write(..))).collect();
+    assert_eq!(writes.len(), 100);
+    assert_eq!(writes[99], &write(LEVEL::Info, "[INFO] queued 99\n"));
+    assert_eq!(calls.last(), Some(&Call::Flush));
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 100);
+
+    log.set_printmode(PRINTMODE::PUNCTUAL).set_custom_sink_only(true);
+    let s = log.fmt("app", LEVEL::Error, "", 0, "in place".to_string());
+    log.safeprint(LEVEL::Error, "app", s).await;
+    assert_eq!(ring.take(), [write(LEVEL::Error, "[ERROR] in place\n"), Call::Flush]);
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 100);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,43 @@
+use std::fs;
+
+use tklog::{
+    cut::{CutSize, CutTime},
+    sync::Logger,
+    CompressType, Error, Format, LEVEL, MODE,
+};
+
+fn refused(r: Result<impl std::fmt::Debug, Error>) -> &'static str {
+    match r {
+        Err(Error::InvalidCut(reason)) => reason,
+        r => panic!("expected InvalidCut, got {:?}", r),
+    }
+}
+
+#[test]
+fn test_cut_builders_validate() {
+    assert_eq!(refused(CutSize::builder().max_size(1).build()), "no file");
+    assert_eq!(refused(CutSize::builder().file(" ").max_size(1).build()), "empty file name");
+    assert_eq!(refused(CutSize::builder().file("a.log").build()), "no max size");
+    assert_eq!(refused(CutSize::builder().file("a.log").max_size_mb(0).build()), "max size is zero");
+    assert_eq!(refused(CutSize::builder().file("a.log").max_size(1).max_size_mb(1).build()), "max size given both in bytes and in MiB");
+    assert_eq!(refused(CutTime::builder().file("a.log").build()), "no rotation mode");
+    assert_eq!(refused(CutTime::builder().file("a.log").mode(MODE::DAY).mode(MODE::HOUR).build()), "two different rotation modes");
+    assert!(CutTime::builder().file("a.log").mode(MODE::DAY).mode(MODE::DAY).backups(3).compress(CompressType::Gzip).build().is_ok());
+}
+
+#[test]
+fn test_set_cut_rotates_by_size() {
+    let dir = std::env::temp_dir().join(format!("tklog_cut_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let cut = CutSize::builder().file(dir.join("app.log")).max_size(64).backups(2).build().unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cut(cut);
+    for i in 0..20 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {} of the size cut\n", i));
+        log.print(LEVEL::Info, "app", s);
+    }
+    let backups = fs::read_dir(&dir).unwrap().filter(|e| e.as_ref().unwrap().file_name() != "app.log").count();
+    assert!(backups > 0, "the file rotated");
+    let _ = fs::remove_dir_all(&dir);
+}
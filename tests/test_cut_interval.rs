@@ -0,0 +1,106 @@
+mod common;
+
+use std::{fs, path::PathBuf, sync::Arc, sync::Once, time::Duration};
+
+use chrono::{Local, TimeZone, Utc};
+use tklog::{clock::ManualClock, cut::CutTime, sync::Logger, Error, Format, LEVEL, MODE};
+
+use common::{dir, files};
+
+const HOUR: u64 = 3600;
+const MINUTE: u64 = 60;
+
+/// The DST cases need a zone that has it; every test of this file sets
+/// the same, as they share the process.
+fn berlin() {
+    static TZ: Once = Once::new();
+    TZ.call_once(|| std::env::set_var("TZ", "Europe/Berlin"));
+}
+
+/// A logger rotating `app.log` in `dir` every `interval`, driven by `clock`
+/// through a rotation group of its own.
+fn interval_logger(dir: &PathBuf, interval: Duration, clock: Arc<ManualClock>) -> Logger {
+    let path = dir.join("app.log").to_string_lossy().into_owned();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_clock(clock).set_cutmode_by_interval(&path, interval, 0, false);
+    log.set_rotation_group(&[&path]).unwrap();
+    log
+}
+
+fn line(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_interval_six_hours() {
+    berlin();
+    let dir = dir("6h");
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 5, 1, 11, 0, 0).unwrap()));
+    let mut log = interval_logger(&dir, Duration::from_secs(6 * HOUR), clock.clone());
+
+    line(&mut log, "a");
+    clock.advance(Duration::from_secs(2 * HOUR));
+    line(&mut log, "b");
+    clock.advance(Duration::from_secs(4 * HOUR));
+    line(&mut log, "c");
+    clock.advance(Duration::from_secs(HOUR));
+    line(&mut log, "d");
+
+    assert_eq!(files(&dir), ["app.log", "app_202405010600_1.log", "app_202405011200_1.log"]);
+    assert_eq!(fs::read_to_string(dir.join("app_202405010600_1.log")).unwrap(), "[INFO] a\n");
+    assert_eq!(fs::read_to_string(dir.join("app_202405011200_1.log")).unwrap(), "[INFO] b\n[INFO] c\n");
+    assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "[INFO] d\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_interval_ninety_minutes_across_dst() {
+    berlin();
+    // Spring forward: 02:00 CET is 03:00 CEST, the 01:30 period lasts 30 minutes.
+    let dir = dir("90m_spring");
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap()));
+    let mut log = interval_logger(&dir, Duration::from_secs(90 * MINUTE), clock.clone());
+    line(&mut log, "01:00");
+    clock.advance(Duration::from_secs(45 * MINUTE));
+    line(&mut log, "01:45");
+    clock.advance(Duration::from_secs(30 * MINUTE));
+    line(&mut log, "03:15");
+    clock.advance(Duration::from_secs(HOUR));
+    line(&mut log, "04:15");
+    assert_eq!(files(&dir), ["app.log", "app_202403310000_1.log", "app_202403310130_1.log"]);
+    assert_eq!(fs::read_to_string(dir.join("app_202403310130_1.log")).unwrap(), "[INFO] 01:45\n");
+    assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "[INFO] 03:15\n[INFO] 04:15\n");
+    let _ = fs::remove_dir_all(&dir);
+
+    // Fall back: 03:00 CEST is 02:00 CET, the 01:30 period lasts two and a
+    // half hours and isn't started again.
+    let dir = self::dir("90m_fall");
+    // 02:50 CEST, the first of the two.
+    let clock = Arc::new(ManualClock::at(Utc.with_ymd_and_hms(2024, 10, 27, 0, 50, 0).unwrap().with_timezone(&Local)));
+    let mut log = interval_logger(&dir, Duration::from_secs(90 * MINUTE), clock.clone());
+    line(&mut log, "02:50 CEST");
+    clock.advance(Duration::from_secs(15 * MINUTE));
+    line(&mut log, "02:05 CET");
+    clock.advance(Duration::from_secs(HOUR));
+    line(&mut log, "03:05 CET");
+    assert_eq!(files(&dir), ["app.log", "app_202410270130_1.log"]);
+    assert_eq!(fs::read_to_string(dir.join("app_202410270130_1.log")).unwrap(), "[INFO] 02:50 CEST\n[INFO] 02:05 CET\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_interval_config() {
+    berlin();
+    let dir = dir("config");
+    let path = dir.join("app.log").to_string_lossy().into_owned();
+    let mut log = Logger::new();
+    log.set_console(false).set_cutmode_by_interval(&path, Duration::from_secs(15 * MINUTE), 4, true);
+    let file = log.config().file.unwrap();
+    assert_eq!((file.timemode, file.maxbackups, file.compress), (MODE::INTERVAL(Duration::from_secs(15 * MINUTE)), 4, true));
+
+    let cut = |interval| CutTime::builder().file(&path).mode(MODE::INTERVAL(interval)).build();
+    assert!(matches!(cut(Duration::from_millis(500)), Err(Error::InvalidCut("rotation interval under a second"))));
+    assert!(cut(Duration::from_secs(1)).is_ok());
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::{fs, path::PathBuf, sync::Arc, thread, time::Duration};
+
+use chrono::{Local, TimeZone};
+use tklog::{clock::ManualClock, cut::CutMixed, sync::Logger, Error, Format, CUTMODE, LEVEL, MODE};
+
+use common::{dir, files};
+
+const DAY: u64 = 24 * 3600;
+
+fn line(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+fn read(dir: &PathBuf, name: &str) -> String {
+    fs::read_to_string(dir.join(name)).unwrap()
+}
+
+#[test]
+fn test_mixed_size_then_day() {
+    let dir = dir("day");
+    let path = dir.join("app.log").to_string_lossy().into_owned();
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap()));
+    let mut log = Logger::new();
+    // "[INFO] a\n" is 9 bytes: two lines fit, a third cuts.
+    log.set_console(false).set_format(Format::LevelFlag).set_clock(clock.clone()).set_cutmode_by_mixed(&path, MODE::DAY, 20, 0, false);
+    log.set_rotation_group(&[&path]).unwrap();
+
+    for m in ["a", "b", "c", "d", "e"] {
+        line(&mut log, m);
+    }
+    clock.advance(Duration::from_secs(DAY));
+    line(&mut log, "f");
+    for m in ["g", "h"] {
+        line(&mut log, m);
+    }
+
+    assert_eq!(files(&dir), ["app.log", "app_20240501_1.log", "app_20240501_2.log", "app_20240501_3.log", "app_20240502_1.log"]);
+    assert_eq!(read(&dir, "app_20240501_1.log"), "[INFO] a\n[INFO] b\n");
+    assert_eq!(read(&dir, "app_20240501_2.log"), "[INFO] c\n[INFO] d\n");
+    // The day ended before the file was full.
+    assert_eq!(read(&dir, "app_20240501_3.log"), "[INFO] e\n");
+    assert_eq!(read(&dir, "app_20240502_1.log"), "[INFO] f\n[INFO] g\n");
+    assert_eq!(read(&dir, "app.log"), "[INFO] h\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_mixed_period_restarts_after_size_cut() {
+    let dir = dir("interval");
+    let path = dir.join("app.log").to_string_lossy().into_owned();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_mixed(&path, MODE::INTERVAL(Duration::from_secs(1)), 9, 0, false);
+    line(&mut log, "a");
+    line(&mut log, "b");
+    assert_eq!(files(&dir).len(), 2, "{:?}", files(&dir));
+    thread::sleep(Duration::from_millis(1100));
+    line(&mut log, "c");
+    let names = files(&dir);
+    assert_eq!(names.len(), 3, "{:?}", names);
+    assert!(names[1..].iter().all(|n| n.starts_with("app_") && n.ends_with(".log")), "{:?}", names);
+    assert_eq!(read(&dir, "app.log"), "[INFO] c\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_mixed_builder_and_config() {
+    let cut = CutMixed::builder().file("app.log").mode(MODE::DAY).max_size_mb(512).backups(7).build().unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_cut(cut);
+    let file = log.config().file.unwrap();
+    assert_eq!((file.cutmode, file.timemode, file.maxsize, file.maxbackups), (CUTMODE::MIXED, MODE::DAY, 512 << 20, 7));
+    assert!(matches!(CutMixed::builder().file("app.log").max_size(10).build(), Err(Error::InvalidCut("no rotation mode"))));
+    assert!(matches!(CutMixed::builder().file("app.log").mode(MODE::HOUR).build(), Err(Error::InvalidCut("no max size"))));
+
+    let cfg = log.config().apply_text("file = \"logs/app.log\"\ncutmode = \"mixed\"\nmode = \"day\"\nmaxsize = \"512MB\"\n").unwrap();
+    let file = cfg.file.unwrap();
+    assert_eq!((file.cutmode, file.timemode, file.maxsize), (CUTMODE::MIXED, MODE::DAY, 512_000_000));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_mixed_async() {
+    let dir = dir("async");
+    let path = dir.join("app.log").to_string_lossy().into_owned();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_mixed(&path, MODE::DAY, 20, 0, false).await;
+    for m in ["a", "b", "c"] {
+        let s = log.fmt("app", LEVEL::Info, "", 0, m.to_string());
+        log.print(LEVEL::Info, "app", s).await;
+    }
+    let stamp = Local::now().format("%Y%m%d").to_string();
+    assert_eq!(files(&dir), ["app.log".to_string(), format!("app_{}_1.log", stamp)]);
+    assert_eq!(read(&dir, &format!("app_{}_1.log", stamp)), "[INFO] a\n[INFO] b\n");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use std::{fs, path::PathBuf, thread, time::Duration};
+
+use chrono::{Local, Timelike};
+use tklog::{sync::Logger, Error, Format, LEVEL, MODE};
+
+use common::{files, gunzip, missing_dir, on_rotate, wait_backups};
+
+/// Sleeps into the next second, for periods of one second.
+fn next_second() {
+    thread::sleep(Duration::from_nanos(1_000_000_000 - Local::now().nanosecond() as u64 % 1_000_000_000) + Duration::from_millis(20));
+}
+
+fn write(log: &mut Logger, msg: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+// Each period writes its own file, compressed in place once it is over;
+// the oldest go past `maxbackups`.
+#[test]
+fn test_cut_pattern() {
+    let dir = missing_dir("sync");
+    let pattern = dir.join("app-%H%M%S.log");
+    let pattern = pattern.to_str().unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_rotation_handler(on_rotate);
+    log.set_cutmode_by_time_pattern(pattern, MODE::INTERVAL(Duration::from_secs(1)), 2, true).unwrap();
+    fs::write(dir.join("app.log"), "not a period").unwrap();
+    for (i, msg) in ["a", "b", "c", "d"].iter().enumerate() {
+        if i > 0 {
+            next_second();
+        }
+        write(&mut log, msg);
+    }
+    let backups = wait_backups(pattern, 3);
+    assert!(backups.iter().all(|b| b.to_str().unwrap().ends_with(".log.gz")), "{:?}", backups);
+    assert_eq!(gunzip(&backups[1]), "b");
+    assert_eq!(gunzip(&backups[2]), "c");
+    let mut left = files(&dir);
+    let name = |p: &PathBuf| p.file_name().unwrap().to_string_lossy().into_owned();
+    let known = ["app.log".to_string(), name(&backups[1]), name(&backups[2])];
+    assert!(known.iter().all(|f| left.contains(f)), "{:?}", left);
+    left.retain(|f| !known.contains(f));
+    assert_eq!(left.len(), 1, "{:?}", left);
+    assert_eq!(fs::read_to_string(dir.join(&left[0])).unwrap(), "d");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// A restart appends to the file of its period.
+#[test]
+fn test_cut_pattern_restart() {
+    let dir = missing_dir("restart");
+    let pattern = dir.join("app-%Y-%m-%d.log");
+    for msg in ["first ", "second"] {
+        let mut log = Logger::new();
+        log.set_console(false).set_format(Format::Nano);
+        log.set_cutmode_by_time_pattern(pattern.to_str().unwrap(), MODE::DAY, 0, false).unwrap();
+        write(&mut log, msg);
+    }
+    let today = Local::now().format("app-%Y-%m-%d.log").to_string();
+    assert_eq!(files(&dir), [today.clone()]);
+    assert_eq!(fs::read_to_string(dir.join(&today)).unwrap(), "first second");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cut_pattern_invalid() {
+    let mut log = Logger::new();
+    log.set_console(false);
+    assert!(matches!(log.set_cutmode_by_time_pattern("app.log", MODE::DAY, 0, false), Err(Error::InvalidCut(_))));
+    assert!(matches!(
+        log.set_cutmode_by_time_pattern("logs/%Y/app-%d.log", MODE::DAY, 0, false),
+        Err(Error::InvalidCut(_))
+    ));
+    assert!(matches!(
+        log.set_cutmode_by_time_pattern("app-%Q.log", MODE::DAY, 0, false),
+        Err(Error::InvalidTimeFormat(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_cut_pattern_async() {
+    let dir = missing_dir("async");
+    let pattern = dir.join("app-%H%M%S.log");
+    let pattern = pattern.to_str().unwrap();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_rotation_handler(on_rotate);
+    log.set_cutmode_by_time_pattern(pattern, MODE::INTERVAL(Duration::from_secs(1)), 0, true).await.unwrap();
+    for (i, msg) in ["a", "b"].iter().enumerate() {
+        if i > 0 {
+            tokio::task::spawn_blocking(next_second).await.unwrap();
+        }
+        let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "app", s).await;
+        log.flush().await;
+    }
+    let backup = tokio::task::spawn_blocking({
+        let pattern = pattern.to_string();
+        move || wait_backups(&pattern, 1)
+    })
+    .await
+    .unwrap()
+    .remove(0);
+    assert!(backup.to_str().unwrap().ends_with(".log.gz"), "{}", backup.display());
+    assert_eq!(gunzip(&backup), "a");
+    assert_eq!(files(&dir).len(), 2);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use tklog::{Format, LEVEL, LOG, PRINTMODE};
+
+use common::dir;
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+fn settle() {
+    thread::sleep(Duration::from_millis(50));
+}
+
+// The DELAY queue of the sync logger writes through the global one.
+#[test]
+fn test_delay_batch_sync() {
+    let dir = dir("sync");
+    let path = dir.join("app.log");
+    LOG.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false);
+    LOG.set_delay_batch(3).set_delay_max_latency(Duration::from_millis(300));
+    tklog::info!("a");
+    tklog::info!("b");
+    settle();
+    assert_eq!(read(&path), "");
+    assert_eq!(LOG.pending_records(), 2);
+    tklog::info!("c");
+    settle();
+    assert_eq!(read(&path), "abc");
+    assert_eq!(LOG.pending_records(), 0);
+
+    // A partial batch is written once its oldest line waited the max latency.
+    tklog::info!("d");
+    settle();
+    assert_eq!(read(&path), "abc");
+    thread::sleep(Duration::from_millis(400));
+    assert_eq!(read(&path), "abcd");
+
+    // Leaving DELAY writes the batch first.
+    tklog::info!("e");
+    LOG.set_printmode(PRINTMODE::PUNCTUAL);
+    assert_eq!(read(&path), "abcde");
+    tklog::info!("f");
+    assert_eq!(read(&path), "abcdef");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_delay_batch_async() {
+    let dir = dir("async");
+    let path = dir.join("app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::Nano);
+    log.set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false).await;
+    log.set_delay_batch(3).set_delay_max_latency(Duration::from_secs(10));
+    let line = |log: &tklog::Async::Logger, msg: &str| log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+    log.log(LEVEL::Info, "app", line(&log, "a"));
+    log.log(LEVEL::Info, "app", line(&log, "b"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(read(&path), "");
+    assert_eq!(log.pending_records(), 2);
+    log.log(LEVEL::Info, "app", line(&log, "c"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(read(&path), "abc");
+    assert_eq!(log.pending_records(), 0);
+
+    // `flush` writes a partial batch.
+    log.log(LEVEL::Info, "app", line(&log, "d"));
+    log.flush().await;
+    assert_eq!(read(&path), "abcd");
+
+    // The first direct write after leaving DELAY waits for the batch.
+    log.log(LEVEL::Info, "app", line(&log, "e"));
+    log.set_printmode(PRINTMODE::PUNCTUAL);
+    log.safeprint(LEVEL::Info, "app", line(&log, "f")).await;
+    log.flush().await;
+    assert_eq!(read(&path), "abcdef");
+    assert_eq!(log.pending_records(), 0);
+    let _ = fs::remove_dir_all(&dir);
+}
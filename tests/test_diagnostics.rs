@@ -0,0 +1,40 @@
+use std::{
+    fs::{self, File},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use tklog::{
+    diagnostics::{Category, Diagnostic},
+    syncfile::prune_backups,
+    PrunePolicy,
+};
+
+#[test]
+fn test_pruning_rolls_up() {
+    let dir = std::env::temp_dir().join(format!("tklog_diagnostics_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let start = SystemTime::now() - Duration::from_secs(3600);
+    for i in 1..=500 {
+        let f = File::create(dir.join(format!("app_20240501_{}.log", i))).unwrap();
+        f.set_modified(start + Duration::from_secs(i)).unwrap();
+    }
+    let seen: Arc<Mutex<Vec<Diagnostic>>> = Arc::default();
+    let sink = seen.clone();
+    tklog::set_diagnostics_handler(Some(Box::new(move |d| sink.lock().unwrap().push(d.clone()))));
+
+    assert_eq!(prune_backups(&dir.join("app.log"), 1, PrunePolicy::ByFile).unwrap().len(), 499);
+    tklog::set_diagnostics_handler(None);
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2, "{:?}", *seen);
+    assert_eq!(seen[0].category, Category::Pruned);
+    assert_eq!(seen[0].count, 1);
+    assert!(seen[0].message.ends_with("app_20240501_1.log"), "{}", seen[0].message);
+    assert_eq!(seen[1].count, 498);
+    assert_eq!(seen[1].path, Some(dir.join("app_20240501_2.log")));
+    assert_eq!(seen[1].last_path, Some(dir.join("app_20240501_499.log")));
+    assert!(seen[1].message.starts_with("498 more files pruned: "), "{}", seen[1].message);
+    let _ = fs::remove_dir_all(&dir);
+}
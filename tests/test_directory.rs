@@ -0,0 +1,74 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use tklog::{directory::DirLayout, handle::FileSizeMode, sync, Async, Format, LogOption, LEVEL};
+
+use common::testmode;
+
+fn root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("tklog_directory_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    root
+}
+
+#[test]
+fn test_directory_module_then_date() {
+    let root = root("module_then_date");
+    let own = root.join("own.log");
+    let mut log = sync::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_test_mode(testmode()).unwrap();
+    log.set_directory_mode(root.clone(), DirLayout::ModuleThenDate);
+    log.set_mod_option("audit", LogOption { level: None, format: None, formatter: None, console: None, fileoption: Some(Box::new(FileSizeMode::new(own.to_str().unwrap(), 0, 0, false))) });
+    for (module, message) in [("app::db", "query"), ("app::http", "request"), ("app::db", "commit"), ("../../etc", "escape"), ("", "bare"), ("audit", "own file")] {
+        let s = log.fmt(module, LEVEL::Info, "", 0, format!("{}\n", message));
+        log.print(LEVEL::Info, module, s);
+    }
+
+    assert_eq!(fs::read_to_string(root.join("app.db/2024-05-01.log")).unwrap(), "query\ncommit\n");
+    assert_eq!(fs::read_to_string(root.join("app.http/2024-05-01.log")).unwrap(), "request\n");
+    assert_eq!(fs::read_to_string(root.join("______etc/2024-05-01.log")).unwrap(), "escape\n");
+    assert_eq!(fs::read_to_string(root.join("default/2024-05-01.log")).unwrap(), "bare\n");
+    assert_eq!(fs::read_to_string(&own).unwrap(), "own file\n");
+    assert!(!root.join("audit").exists());
+    assert!(log.describe().contains("directory: "));
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_directory_retention() {
+    let root = root("retention");
+    for day in ["2024-04-28", "2024-04-30"] {
+        fs::create_dir_all(root.join(day)).unwrap();
+        fs::write(root.join(day).join("app.log"), "old\n").unwrap();
+    }
+    fs::create_dir_all(root.join("archive")).unwrap();
+    let mut log = sync::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_test_mode(testmode()).unwrap();
+    log.set_directory_mode(root.clone(), DirLayout::DateThenModule).set_directory_retention(2);
+    let s = log.fmt("app", LEVEL::Info, "", 0, "new\n".to_string());
+    log.print(LEVEL::Info, "app", s);
+
+    assert!(!root.join("2024-04-28").exists());
+    assert_eq!(fs::read_to_string(root.join("2024-04-30/app.log")).unwrap(), "old\n");
+    assert_eq!(fs::read_to_string(root.join("2024-05-01/app.log")).unwrap(), "new\n");
+    assert!(root.join("archive").exists(), "only dated entries are pruned");
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[tokio::test]
+async fn test_directory_async() {
+    let root = root("async");
+    let mut log = Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano);
+    log.set_test_mode(testmode()).unwrap();
+    log.set_directory_mode(root.clone(), DirLayout::DateThenModule);
+    log.enqueue(LEVEL::Info, "billing", "", 0, "invoice\n".to_string());
+    log.enqueue(LEVEL::Warn, "shipping", "", 0, "late\n".to_string());
+    log.enqueue(LEVEL::Info, "billing", "", 0, "paid\n".to_string());
+    log.flush().await;
+
+    assert_eq!(fs::read_to_string(root.join("2024-05-01/billing.log")).unwrap(), "invoice\npaid\n");
+    assert_eq!(fs::read_to_string(root.join("2024-05-01/shipping.log")).unwrap(), "late\n");
+    let _ = fs::remove_dir_all(&root);
+}
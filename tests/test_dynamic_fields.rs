@@ -0,0 +1,60 @@
+use std::{
+    cell::RefCell,
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tklog::{debugs, infos, sync::Logger, warns, Format, LEVEL};
+
+thread_local! {
+    static TENANT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn test_dynamic_fields_per_line() {
+    let path = std::env::temp_dir().join(format!("tklog_dynamic_fields_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    log.set_dynamic_fields(Box::new(|fields| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        TENANT.with(|t| match &*t.borrow() {
+            Some(t) if t == "broken" => panic!("no tenant table"),
+            Some(t) => {
+                fields.insert("tenant", t);
+            }
+            None => {}
+        });
+    }));
+    let mut logger = Arc::new(Mutex::new(log));
+    let log = &mut logger;
+
+    TENANT.with(|t| *t.borrow_mut() = Some("acme".to_string()));
+    infos!(log, "created\n");
+    debugs!(log, "filtered\n");
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1, "filtered lines must not run the callback");
+    TENANT.with(|t| *t.borrow_mut() = Some("blue sky".to_string()));
+    warns!(log, "quota\n");
+    TENANT.with(|t| *t.borrow_mut() = Some("broken".to_string()));
+    infos!(log, "kept\n");
+    TENANT.with(|t| *t.borrow_mut() = None);
+    infos!(log, "anonymous\n");
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "created tenant=acme\nquota tenant=\"blue sky\"\nkept\nanonymous\n");
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_dynamic_fields_in_k8s_json() {
+    let mut log = Logger::new();
+    log.preset_k8s().set_dynamic_fields(Box::new(|fields| {
+        fields.insert("tenant", "acme").insert("region", "eu");
+    }));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "hi\n".to_string()).file_body;
+    assert!(s.ends_with(",\"tenant\":\"acme\",\"region\":\"eu\"}\n"), "{}", s);
+}
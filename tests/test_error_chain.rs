@@ -0,0 +1,98 @@
+mod common;
+
+use std::{
+    backtrace::Backtrace,
+    error::Error,
+    fmt, fs, io,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{async_errors, error_chain, errors, sync::Logger, ErrorChain, Format, LEVEL};
+
+use common::logfile;
+
+#[derive(Debug)]
+struct RequestError(io::Error);
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request failed")
+    }
+}
+
+impl Error for RequestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+fn request_error() -> RequestError {
+    RequestError(io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused"))
+}
+
+#[test]
+fn test_error_chain() {
+    assert_eq!(error_chain(&request_error()), "request failed: caused by: connection refused");
+    assert_eq!(error_chain(&io::Error::other("disk full")), "disk full");
+
+    let path = logfile("sync");
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(&path, 0, 0, false);
+    let mut log = Arc::new(Mutex::new(log));
+    let e = request_error();
+    errors!(&mut log, "sync:", ErrorChain::new(&e));
+    errors!(&mut log, "sync"; error = %ErrorChain::new(&e));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "[ERROR] sync:request failed: caused by: connection refused\n[ERROR] sync error=\"request failed: caused by: connection refused\"\n"
+    );
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_error_chain_backtrace() {
+    let path = logfile("backtrace");
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(&path, 0, 0, false);
+    let mut log = Arc::new(Mutex::new(log));
+    let e = request_error();
+    let backtrace = Backtrace::force_capture();
+    errors!(&mut log, ErrorChain::new(&e).with_backtrace(&backtrace));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[ERROR] request failed: caused by: connection refused\n");
+
+    // A backtrace that wasn't captured stays out.
+    log.lock().unwrap().set_error_backtraces(true);
+    errors!(&mut log, ErrorChain::new(&e).with_backtrace(&Backtrace::disabled()));
+    errors!(&mut log, ErrorChain::new(&e).with_backtrace(&backtrace));
+    let lines = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(lines[1], "[ERROR] request failed: caused by: connection refused");
+    assert_eq!(lines[2], "[ERROR] request failed: caused by: connection refused");
+    assert!(lines.len() > 3);
+    assert!(lines[3..].iter().all(|l| l.starts_with("    ")), "{:?}", lines);
+    assert!(lines[3..].iter().any(|l| l.contains("test_error_chain_backtrace")), "{:?}", lines);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_error_chain_async() {
+    let path = logfile("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_error_backtraces(true);
+    log.set_cutmode_by_size(&path, 0, 0, false).await;
+    let e = request_error();
+    let backtrace = Backtrace::force_capture();
+    async_errors!(&log, ErrorChain::new(&e).with_backtrace(&backtrace));
+    log.flush().await;
+    let lines = fs::read_to_string(&path).unwrap();
+    let mut lines = lines.lines();
+    assert_eq!(lines.next(), Some("[ERROR] request failed: caused by: connection refused"));
+    assert!(lines.next().is_some_and(|l| l.starts_with("    ")));
+    let _ = fs::remove_file(&path);
+}
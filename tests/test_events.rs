@@ -0,0 +1,50 @@
+use std::sync::{Arc, Mutex};
+
+use tklog::{diagnostics::Diagnostic, parse::Parser, sync::Logger, warn, Format, LEVEL, LOG, PRINTMODE};
+
+#[test]
+fn test_event_ids() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    let s = log.fmt_with_event("app", LEVEL::Warn, "", 0, Some("POOL_EXHAUSTED"), "pool db exhausted".to_string());
+    assert_eq!(s.file_body, "[WARN] pool db exhausted event=POOL_EXHAUSTED\n");
+    let r = Parser::new(Format::LevelFlag).parse(&s.file_body).unwrap();
+    assert_eq!(r.event(), Some("POOL_EXHAUSTED"));
+    assert_eq!(Parser::new(Format::LevelFlag).parse("[WARN] no event\n").unwrap().event(), None);
+
+    log.set_formatter("{level} [{event}] {message}\n");
+    assert_eq!(log.fmt_with_event("app", LEVEL::Warn, "", 0, Some("TK1042"), "pool exhausted".to_string()).file_body, "[WARN] [TK1042] pool exhausted\n");
+}
+
+#[test]
+fn test_event_registry_and_macro() {
+    let diagnostics: Arc<Mutex<Vec<String>>> = Arc::default();
+    let seen = diagnostics.clone();
+    tklog::set_diagnostics_handler(Some(Box::new(move |d: &Diagnostic| seen.lock().unwrap().push(d.message.clone()))));
+
+    let file = std::env::temp_dir().join(format!("tklog_events_{}.log", std::process::id()));
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(file.to_str().unwrap(), 0, 0, false);
+    LOG.set_event_registry(&[("POOL_EXHAUSTED", "no free connection in the pool")]);
+    warn!(event: "POOL_EXHAUSTED", "pool", "db", "exhausted");
+    warn!(event: "DISK_FULL", "disk full");
+    warn!("plain");
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "[WARN] pooldbexhausted event=POOL_EXHAUSTED\n[WARN] disk full event=DISK_FULL\n[WARN] plain\n");
+    assert_eq!(LOG.event_description("POOL_EXHAUSTED").as_deref(), Some("no free connection in the pool"));
+
+    LOG.validate().unwrap();
+    let diagnostics = diagnostics.lock().unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert!(diagnostics[0].contains("`DISK_FULL` is not in the event registry"));
+    let _ = std::fs::remove_file(&file);
+}
+
+#[tokio::test]
+async fn test_async_event_macro() {
+    let file = std::env::temp_dir().join(format!("tklog_events_async_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&file);
+    tklog::ASYNC_LOG.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(file.to_str().unwrap(), 0, 0, false).await;
+    tklog::async_error!(event: "TK1042", "connection pool exhausted");
+    tklog::global_async().await.flush().await;
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "[ERROR] connection pool exhausted event=TK1042\n");
+    let _ = std::fs::remove_file(&file);
+}
@@ -0,0 +1,91 @@
+#![cfg(unix)]
+
+use std::{fs, fs::File, path::Path};
+
+use tklog::{handle::FileSizeMode, sync::Logger, Format, LogOption, LEVEL};
+
+/// Lowers the soft descriptor limit to what is open now plus `room`.
+fn limit_to_open_plus(room: u64) -> libc::rlimit {
+    let mut old = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut old) }, 0);
+    let highest = (0..old.rlim_cur.min(4096) as i32).filter(|fd| unsafe { libc::fcntl(*fd, libc::F_GETFD) } != -1).max().unwrap();
+    let new = libc::rlimit { rlim_cur: highest as u64 + 1 + room, rlim_max: old.rlim_max };
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &new) }, 0);
+    old
+}
+
+fn module_file(dir: &Path, module: &str, max_size: u64) -> LogOption {
+    LogOption {
+        level: None,
+        format: None,
+        formatter: None,
+        console: None,
+        fileoption: Some(Box::new(FileSizeMode::new(dir.join(format!("{}.log", module)).to_str().unwrap(), max_size, 0, false))),
+    }
+}
+
+fn hog_descriptors(hogs: &mut Vec<File>) {
+    while let Ok(f) = File::open("/dev/null") {
+        hogs.push(f);
+    }
+}
+
+#[test]
+fn test_no_lines_lost_when_descriptors_run_out() {
+    let dir = std::env::temp_dir().join(format!("tklog_fdlimit_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Trace).set_format(Format::Nano).set_cutmode_by_size(dir.join("default.log").to_str().unwrap(), 0, 0, false);
+    let old = limit_to_open_plus(2);
+    let mut expected = Vec::new();
+    let mut write = |log: &mut Logger, module: &str, line: String| {
+        let s = log.fmt(module, LEVEL::Info, "", 0, line.clone());
+        log.print(LEVEL::Info, module, s);
+        expected.push(line);
+    };
+
+    // Nothing to reclaim: the module keeps logging, to the default file.
+    let mut hogs = Vec::new();
+    hog_descriptors(&mut hogs);
+    log.set_mod_option("late", module_file(&dir, "late", 0));
+    write(&mut log, "late", "late 0\n".to_string());
+    hogs.clear();
+
+    // Room for two more files: the module files take turns with their descriptors.
+    let modules: Vec<String> = (0..6).map(|i| format!("m{}", i)).collect();
+    for m in &modules {
+        log.set_mod_option(m, module_file(&dir, m, 0));
+    }
+    for round in 0..3 {
+        for m in &modules {
+            write(&mut log, m, format!("{} {}\n", m, round));
+        }
+    }
+
+    // Each line of `rot` rotates its file, reopening it with every other
+    // descriptor taken: the module files' are reclaimed, or its own reused.
+    log.set_mod_option("rot", module_file(&dir, "rot", 10));
+    hog_descriptors(&mut hogs);
+    write(&mut log, "rot", "rot 0\n".to_string());
+    write(&mut log, "rot", "rot 1\n".to_string());
+    hog_descriptors(&mut hogs);
+    write(&mut log, "rot", "rot 2\n".to_string());
+    hogs.clear();
+    write(&mut log, "rot", "rot 3\n".to_string());
+    write(&mut log, "m0", "m0 3\n".to_string());
+    unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &old) };
+
+    assert!(!dir.join("late.log").exists());
+    assert_eq!(fs::read_to_string(dir.join("default.log")).unwrap(), "late 0\n");
+    assert_eq!(fs::read_to_string(dir.join("m1.log")).unwrap(), "m1 0\nm1 1\nm1 2\n");
+    assert_eq!(fs::read_to_string(dir.join("rot.log")).unwrap(), "rot 3\n");
+    let mut written: Vec<String> = Vec::new();
+    for entry in fs::read_dir(&dir).unwrap() {
+        written.extend(fs::read_to_string(entry.unwrap().path()).unwrap().lines().map(|l| format!("{}\n", l)));
+    }
+    written.sort();
+    expected.sort();
+    assert_eq!(written, expected);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "ffi")]
+
+use std::{ffi::c_char, fs, ptr};
+
+use tklog::{Format, LEVEL, LOG, PRINTMODE};
+
+extern "C" {
+    fn tklog_log(level: u8, module: *const c_char, file: *const c_char, line: u32, msg: *const c_char);
+    fn tklog_enabled(level: u8, module: *const c_char) -> bool;
+}
+
+#[test]
+fn test_log_through_the_c_abi() {
+    let path = std::env::temp_dir().join(format!("tklog_ffi_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    LOG.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag | Format::ShortFileName).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false).set_printmode(PRINTMODE::PUNCTUAL);
+    unsafe {
+        assert!(tklog_enabled(3, c"plugin".as_ptr()));
+        assert!(!tklog_enabled(2, c"plugin".as_ptr()));
+        assert!(!tklog_enabled(0, ptr::null()));
+        assert!(!tklog_enabled(7, ptr::null()));
+        tklog_log(3, c"plugin".as_ptr(), c"plugin.c".as_ptr(), 12, c"loaded".as_ptr());
+        tklog_log(2, c"plugin".as_ptr(), ptr::null(), 0, c"not logged".as_ptr());
+        tklog_log(4, ptr::null(), ptr::null(), 0, c"bad \xff byte".as_ptr());
+        tklog_log(5, c"plugin".as_ptr(), ptr::null(), 0, ptr::null());
+        tklog_log(9, c"plugin".as_ptr(), ptr::null(), 0, c"no such level".as_ptr());
+    }
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("[INFO] plugin.c 12:loaded\n"), "{}", content);
+    assert!(content.contains("[WARN] bad \u{fffd} byte\n"), "{}", content);
+    assert!(content.contains("[ERROR] \n"), "{}", content);
+    assert!(!content.contains("not logged") && !content.contains("no such level"), "{}", content);
+    let _ = fs::remove_file(&path);
+}
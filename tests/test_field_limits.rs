@@ -0,0 +1,101 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{context, fields::TRUNCATED_FIELDS, infos, sync::Logger, Format, LEVEL};
+
+use common::logfile;
+
+fn logger(path: &str) -> Arc<Mutex<Logger>> {
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(path, 0, 0, false);
+    log.set_max_fields(3).set_max_field_value_len(8);
+    Arc::new(Mutex::new(log))
+}
+
+/// The ` key=value` fields of a text line, values quoted as Rust strings
+/// unquoted again.
+fn logfmt_fields(line: &str) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut rest = line.split_once(' ').unwrap().1.split_once(' ').unwrap().1;
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=').unwrap();
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut end = 0;
+                let mut escaped = false;
+                for (i, c) in quoted.char_indices() {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => {
+                            end = i;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                let value: String = serde_json::from_str(&format!("\"{}\"", &quoted[..end])).unwrap();
+                (value, &quoted[end + 1..])
+            }
+            None => after.split_once(' ').map_or((after.to_string(), ""), |(v, a)| (v.to_string(), a)),
+        };
+        fields.push((key.to_string(), value));
+        rest = after.trim_start();
+    }
+    fields
+}
+
+// Fields over the count are dropped, long values cut on a character
+// boundary, and the line says how many fields it lost.
+#[test]
+fn test_field_limits_text() {
+    let path = logfile("text");
+    let log = &mut logger(&path);
+    let _scope = context::scope(&[("tenant", "acme"), ("region", "eu-west")]);
+    infos!(log, "short"; a = 1);
+    infos!(log, "many"; a = 1, quote = "say \"hello\" twice", accent = "éééééé");
+    let content = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "[INFO] short a=1 tenant=acme region=eu-west");
+    assert_eq!(
+        logfmt_fields(lines[1]),
+        [("a", "1"), ("quote", "say \"hel"), ("accent", "éééé"), (TRUNCATED_FIELDS, "4")].map(|(k, v)| (k.to_string(), v.to_string()))
+    );
+    let _ = fs::remove_file(&path);
+}
+
+// A JSON line stays an object with the values cut, whatever they hold.
+#[test]
+fn test_field_limits_json() {
+    let path = logfile("json");
+    let log = &mut logger(&path);
+    log.lock().unwrap().set_format_json(true);
+    infos!(log, "many"; a = 1, quote = "\"\"\"\"\"\"\"\"\"", newline = "line\nline\nline", dropped = true);
+    let line: serde_json::Value = serde_json::from_str(fs::read_to_string(&path).unwrap().trim_end()).unwrap();
+    assert_eq!(line["message"], "many");
+    assert_eq!(line["a"], "1");
+    assert_eq!(line["quote"], "\"\"\"\"\"\"\"\"");
+    assert_eq!(line["newline"], "line\nlin");
+    assert_eq!(line.get("dropped"), None);
+    assert_eq!(line[TRUNCATED_FIELDS], "3");
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_field_limits_async() {
+    let path = logfile("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).await;
+    log.set_max_fields(1).set_max_field_value_len(3);
+    tklog::async_infos!(&log, "many"; a = 12345, b = 2);
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] many a=123 _truncated_fields=2\n");
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,68 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+use tklog::{sync::Logger, Format, LEVEL};
+
+use common::{missing_dir, on_rotate, wait_backups};
+
+fn mode(path: &Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+fn write(log: &mut Logger, msg: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_file_mode() {
+    let dir = missing_dir("sync");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_file_mode(0o640).set_rotation_handler(on_rotate);
+    log.set_cutmode_by_size(filename, 10, 0, true);
+    write(&mut log, "first line");
+    assert_eq!(mode(&path), 0o640);
+    write(&mut log, "second line");
+    let backup = &wait_backups(filename, 1)[0];
+    assert!(backup.to_str().unwrap().ends_with(".gz"), "{}", backup.display());
+    assert_eq!(mode(backup), 0o640);
+    assert_eq!(mode(&path), 0o640);
+
+    // The files created afterward take the new mode.
+    log.set_file_mode(0o600);
+    write(&mut log, "third line");
+    let backups = wait_backups(filename, 2);
+    assert_eq!(mode(&backups[1]), 0o600);
+    assert_eq!(mode(&path), 0o600);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_file_mode_async() {
+    let dir = missing_dir("async");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_file_mode(0o640).set_rotation_handler(on_rotate);
+    log.set_cutmode_by_size(filename, 10, 0, true).await;
+    for msg in ["first line", "second line"] {
+        let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "app", s).await;
+    }
+    log.flush().await;
+    let backup = &tokio::task::spawn_blocking({
+        let filename = filename.to_string();
+        move || wait_backups(&filename, 1)
+    })
+    .await
+    .unwrap()[0];
+    assert!(backup.to_str().unwrap().ends_with(".gz"), "{}", backup.display());
+    assert_eq!(mode(backup), 0o640);
+    assert_eq!(mode(&path), 0o640);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use tklog::{filter::LogRecord, info, sync::Logger, warn, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+fn write(log: &mut Logger, level: LEVEL, module: &str, message: &str) {
+    let s = log.fmt(module, level, "src/http.rs", 12, message.to_string());
+    if !s.is_empty() {
+        log.print(level, module, s);
+    }
+}
+
+#[test]
+fn test_filters_compose() {
+    let file = logfile("compose");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false);
+    log.set_filter(Box::new(|r| !(r.level == LEVEL::Info && r.module == "app::http" && r.message.contains("healthcheck"))));
+    log.set_filter(Box::new(|r| r.file == "src/http.rs" && r.line == 12 && !r.message.starts_with("secret")));
+    write(&mut log, LEVEL::Info, "app::http", "GET /healthcheck");
+    write(&mut log, LEVEL::Warn, "app::http", "slow healthcheck");
+    write(&mut log, LEVEL::Info, "app::db", "healthcheck of the pool");
+    write(&mut log, LEVEL::Info, "app::db", "secret token");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[WARN] slow healthcheck\n[INFO] healthcheck of the pool\n");
+    assert_eq!(log.stats().filtered, 2);
+
+    // The file and line reach the filters, not the lines.
+    assert!(log.is_file_line(LEVEL::Info, "app"));
+    log.clear_filters();
+    assert!(!log.is_file_line(LEVEL::Info, "app"));
+    write(&mut log, LEVEL::Info, "app::db", "secret token");
+    assert!(fs::read_to_string(&file).unwrap().ends_with("[INFO] secret token\n"));
+    let _ = fs::remove_file(&file);
+}
+
+#[test]
+fn test_filter_panics() {
+    let file = logfile("panics");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false);
+    log.set_filter(Box::new(|r: &LogRecord| if r.message == "boom" { panic!("filter") } else { false }));
+    write(&mut log, LEVEL::Info, "app", "dropped");
+    write(&mut log, LEVEL::Info, "app", "boom");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] boom\n");
+    let _ = fs::remove_file(&file);
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_filter_macros() {
+    let file = logfile("macros");
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let s = seen.clone();
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false);
+    LOG.set_filter(Box::new(move |r| {
+        s.lock().unwrap().push((r.file.to_string(), r.line));
+        r.level >= LEVEL::Warn
+    }));
+    info!("dropped");
+    warn!("kept");
+    LOG.clear_filters();
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert!(seen.iter().all(|(f, line)| f.ends_with("test_filter.rs") && *line > 0), "{:?}", seen);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[WARN] kept\n");
+    let _ = fs::remove_file(&file);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_filter_async() {
+    let file = logfile("async");
+    let threads = Arc::new(Mutex::new(Vec::new()));
+    let t = threads.clone();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false).await;
+    log.set_filter(Box::new(move |r| {
+        t.lock().unwrap().push(thread::current().id());
+        !r.message.ends_with('3')
+    }));
+    for i in 0..6 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {}", i));
+        if !s.is_empty() {
+            log.log(LEVEL::Info, "app", s);
+        }
+    }
+    log.flush().await;
+    assert!(threads.lock().unwrap().iter().all(|id| *id == thread::current().id()));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] line 0\n[INFO] line 1\n[INFO] line 2\n[INFO] line 4\n[INFO] line 5\n");
+    let _ = fs::remove_file(&file);
+}
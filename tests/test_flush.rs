@@ -0,0 +1,72 @@
+mod common;
+
+use std::{fs, sync::Arc};
+
+use tklog::{info, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+fn lines(path: &str) -> Vec<String> {
+    fs::read_to_string(path).unwrap_or_default().lines().map(str::to_string).collect()
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_flush_global() {
+    let path = logfile("global");
+    LOG.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+    for i in 0..200 {
+        info!("queued ", i);
+    }
+    LOG.flush();
+    assert_eq!(lines(&path).len(), 200);
+    assert_eq!(lines(&path)[199], "[INFO] queued 199");
+
+    // The guard flushes as it goes out of scope.
+    {
+        let _flush = tklog::flush_guard();
+        info!("before the end");
+    }
+    assert_eq!(lines(&path).last().unwrap(), "[INFO] before the end");
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_flush_concurrent_async() {
+    let path = logfile("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).await;
+    let log = Arc::new(log);
+    let tasks: Vec<_> = (0..8)
+        .map(|task| {
+            let log = log.clone();
+            let path = path.clone();
+            tokio::spawn(async move {
+                for i in 0..20 {
+                    let s = log.fmt("app", LEVEL::Info, "", 0, format!("task {} line {}", task, i));
+                    log.log(LEVEL::Info, "app", s);
+                }
+                // Each flush covers the lines queued before it.
+                log.flush().await;
+                assert!(lines(&path).contains(&format!("[INFO] task {} line 19", task)));
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(lines(&path).len(), 160);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_flush_sync_logger() {
+    let path = logfile("sync");
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "synced".to_string());
+    log.print(LEVEL::Warn, "app", s);
+    log.flush();
+    assert_eq!(lines(&path), ["[WARN] synced"]);
+    let _ = fs::remove_file(&path);
+}
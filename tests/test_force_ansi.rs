@@ -0,0 +1,25 @@
+use tklog::{
+    color::{force_ansi, ColorOptions},
+    sync::Logger,
+    Format, LEVEL,
+};
+
+// One test: `force_ansi` holds for the whole process.
+#[test]
+fn test_force_ansi() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).allow_ansi_in_files(true);
+    log.set_console_color_options(ColorOptions { whole_line: false, force: true });
+
+    force_ansi(false);
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "\x1b[1mbold\x1b[0m".to_string());
+    assert_eq!(s.file_body, "[WARN] \x1b[1mbold\x1b[0m\n");
+    assert_eq!(s.console_body.as_deref(), Some("[WARN] bold\n"));
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "plain".to_string());
+    assert!(s.console_body.is_none(), "no colors, nothing to strip");
+
+    // Colors set while ANSI was off come back with it.
+    force_ansi(true);
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "\x1b[1mbold\x1b[0m".to_string());
+    assert_eq!(s.console_body.as_deref(), Some("\x1b[33m[WARN]\x1b[0m \x1b[1mbold\x1b[0m\n"));
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use chrono::SecondsFormat;
+use tklog::{infos, output::OutputSink, sync::Logger, Format, LEVEL};
+
+use common::testmode;
+
+fn time() -> String {
+    testmode().fixed_time.to_rfc3339_opts(SecondsFormat::Micros, false)
+}
+
+#[test]
+fn test_format_json_line() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::ShortFileName).set_format_json(true);
+    log.set_test_mode(testmode()).unwrap();
+    let s = log.fmt("app::db", LEVEL::Warn, "db.rs", 7, "say \"hi\"\nthen: ünïcode\t✓".to_string());
+    assert_eq!(
+        s.file_body,
+        format!("{{\"level\":\"warn\",\"time\":\"{}\",\"file\":\"db.rs\",\"line\":7,\"module\":\"app::db\",\"message\":\"say \\\"hi\\\"\\nthen: ünïcode\\t✓\"}}\n", time())
+    );
+    assert!(s.console_body.is_none());
+    assert_eq!(log.validate().unwrap(), [(OutputSink::Console, tklog::output::OutputMode::Json), (OutputSink::File, tklog::output::OutputMode::Json)]);
+}
+
+#[test]
+fn test_format_json_last_set_wins() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    let line = |log: &mut Logger| log.fmt("app", LEVEL::Info, "", 0, "m".to_string()).file_body;
+
+    log.set_formatter("{level}{message}\n").set_format_json(true);
+    assert!(line(&mut log).starts_with("{\"level\":\"info\""));
+    log.set_formatter("{level}{message}\n");
+    assert_eq!(line(&mut log), "[INFO]m\n");
+    assert!(log.validate().is_ok());
+
+    log.set_format_json(true).set_record_formatter(Box::new(|r| format!("custom {}\n", r.message)));
+    assert_eq!(line(&mut log), "custom m\n");
+    log.clear_record_formatter().set_format_json(true).set_format_json(false);
+    assert_eq!(line(&mut log), "[INFO] m\n");
+}
+
+#[test]
+fn test_format_json_multi_logger() {
+    let path = std::env::temp_dir().join(format!("tklog_format_json_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_format_json(true).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    log.set_test_mode(testmode()).unwrap();
+    let mut logger = Arc::new(Mutex::new(log));
+    let log = &mut logger;
+    infos!(log, "first", 1);
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!("{{\"level\":\"info\",\"time\":\"{}\",\"file\":\"\",\"line\":0,\"module\":\"{}\",\"message\":\"first1\"}}\n", time(), module_path!())
+    );
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_async_format_json() {
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_format_json(true);
+    let s = log.fmt("app", LEVEL::Error, "", 0, "boom".to_string());
+    assert!(s.file_body.starts_with("{\"level\":\"error\",\"time\":\""), "{}", s.file_body);
+    assert!(s.file_body.ends_with(",\"file\":\"\",\"line\":0,\"module\":\"app\",\"message\":\"boom\"}\n"), "{}", s.file_body);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{async_infos, infos, parse::Parser, sync::Logger, Format, LEVEL};
+
+use common::logfile;
+
+fn logger(path: &str, format: u8) -> Arc<Mutex<Logger>> {
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(format).set_cutmode_by_size(path, 0, 0, false);
+    Arc::new(Mutex::new(log))
+}
+
+fn handle_request(log: &mut Arc<Mutex<Logger>>) {
+    infos!(log, "plain");
+}
+
+struct Server;
+
+impl Server {
+    fn handle(&self, log: &mut Arc<Mutex<Logger>>) {
+        infos!(log, "method");
+    }
+}
+
+#[test]
+fn test_function_name() {
+    let path = logfile("text");
+    let mut log = logger(&path, Format::LevelFlag | Format::FuncName);
+    handle_request(&mut log);
+    Server.handle(&mut log);
+    let mut run = || infos!(&mut log, "closure");
+    run();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "[INFO] handle_request:plain\n[INFO] Server::handle:method\n[INFO] test_function_name:closure\n"
+    );
+
+    log.lock().unwrap().set_function_with_module(true);
+    handle_request(&mut log);
+    let lines = fs::read_to_string(&path).unwrap();
+    assert_eq!(lines.lines().last(), Some("[INFO] test_function_name::handle_request:plain"));
+
+    // Without the flag the line has no function.
+    log.lock().unwrap().set_format(Format::LevelFlag);
+    handle_request(&mut log);
+    let lines = fs::read_to_string(&path).unwrap();
+    assert_eq!(lines.lines().last(), Some("[INFO] plain"));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_function_name_formatter() {
+    let path = logfile("formatter");
+    let mut log = logger(&path, Format::LevelFlag | Format::ShortFileName);
+    log.lock().unwrap().set_formatter("{level} {function} {message}\n");
+    handle_request(&mut log);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] handle_request plain\n");
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_function_name_parse() {
+    let path = logfile("parse");
+    let mut log = logger(&path, Format::LevelFlag | Format::ShortFileName | Format::FuncName);
+    Server.handle(&mut log);
+    let line = fs::read_to_string(&path).unwrap();
+    let record = Parser::new(Format::LevelFlag | Format::ShortFileName | Format::FuncName).parse(&line).unwrap();
+    assert_eq!(record.file.as_deref(), Some("test_function_name.rs"));
+    assert_eq!(record.function.as_deref(), Some("Server::handle"));
+    assert_eq!(record.message, "method");
+    let _ = fs::remove_file(&path);
+}
+
+async fn serve(log: &Arc<tokio::sync::Mutex<tklog::Async::Logger>>) {
+    async_infos!(log, "async");
+}
+
+#[tokio::test]
+async fn test_function_name_async() {
+    let path = logfile("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag | Format::FuncName);
+    log.set_cutmode_by_size(&path, 0, 0, false).await;
+    let log = Arc::new(tokio::sync::Mutex::new(log));
+    serve(&log).await;
+    tokio::spawn({
+        let log = log.clone();
+        async move { async_infos!(&log, "task") }
+    })
+    .await
+    .unwrap();
+    log.lock().await.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] serve:async\n[INFO] test_function_name_async:task\n");
+    let _ = fs::remove_file(&path);
+}
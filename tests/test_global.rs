@@ -0,0 +1,25 @@
+use std::{fs, thread, time::Duration};
+
+use tklog::{info, warn, Format, LEVEL, LOG, PRINTMODE};
+
+#[test]
+fn test_global_accessor() {
+    let path = std::env::temp_dir().join(format!("tklog_global_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    LOG.set_console(false).set_level(LEVEL::Warn).set_format(Format::LevelFlag).set_formatter("{level} {message}\n");
+    LOG.set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false).set_printmode(PRINTMODE::PUNCTUAL);
+    assert_eq!(tklog::global().get_level(""), LEVEL::Warn);
+
+    info!("dropped");
+    warn!("kept");
+    tklog::global().set_level(LEVEL::Info);
+    info!("after", 2);
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[WARN] kept\n[INFO] after2\n");
+
+    // The deprecated name configures the same logger.
+    #[allow(deprecated)]
+    tklog::tklog::synclog.set_level(LEVEL::Error);
+    assert_eq!(tklog::global().get_level(""), LEVEL::Error);
+    let _ = fs::remove_file(&path);
+}
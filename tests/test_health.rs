@@ -0,0 +1,122 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use tklog::{health::Health, info, logsink::LogSink, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+/// Panics on every line until `armed` is cleared.
+struct Tripwire(Arc<AtomicBool>);
+
+impl LogSink for Tripwire {
+    fn write(&mut self, _level: LEVEL, _formatted: &str) {
+        if self.0.load(Ordering::Relaxed) {
+            panic!("sink tripped");
+        }
+    }
+
+    fn flush(&mut self) {}
+}
+
+const STOPPED: &str = "the queue consumer has stopped; queued lines are dropped";
+
+#[test]
+fn test_health_disabled_callback() {
+    let path = logfile("callback");
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_cutmode_by_size(&path, 0, 0, false).set_record_formatter(Box::new(|r| {
+        if r.message == "boom" {
+            panic!("formatter tripped");
+        }
+        format!("{}\n", r.message)
+    }));
+    assert_eq!(log.health(), Health { ok: true, degraded_reasons: vec![] });
+    for _ in 0..3 {
+        let _ = log.fmt("app", LEVEL::Info, "", 0, "boom".to_string());
+    }
+    let health = log.health();
+    assert!(!health.ok);
+    assert_eq!(health.degraded_reasons, ["the record formatter panicked 3 times in a row and is disabled"]);
+
+    assert!(log.try_recover().is_ok());
+    assert!(log.health().ok);
+    let s = log.fmt("app", LEVEL::Info, "", 0, "fine".to_string());
+    log.print(LEVEL::Info, "app", s);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fine\n");
+    let _ = fs::remove_file(&path);
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_health_queue_stopped() {
+    let path = logfile("queue");
+    let armed = Arc::new(AtomicBool::new(true));
+    LOG.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+    LOG.set_custom_sink(Box::new(Tripwire(armed.clone())));
+    info!("lost");
+    let stopped = (0..200).any(|_| {
+        thread::sleep(Duration::from_millis(10));
+        LOG.health().degraded_reasons == [STOPPED]
+    });
+    assert!(stopped, "{:?}", LOG.health());
+    info!("dropped while stopped");
+
+    armed.store(false, Ordering::Relaxed);
+    LOG.try_recover().unwrap();
+    assert!(LOG.health().ok);
+    info!("after recovery");
+    let written = (0..200).any(|_| {
+        thread::sleep(Duration::from_millis(10));
+        fs::read_to_string(&path).is_ok_and(|s| s == "[INFO] after recovery\n")
+    });
+    LOG.clear_custom_sink();
+    assert!(written, "{:?}", fs::read_to_string(&path));
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_health_async_queue_stopped() {
+    let path = logfile("async");
+    let armed = Arc::new(AtomicBool::new(true));
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).await;
+    log.set_custom_sink(Box::new(Tripwire(armed.clone())));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "lost".to_string());
+    log.log(LEVEL::Info, "app", s);
+    let mut stopped = false;
+    for _ in 0..200 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        if log.health().await.degraded_reasons == [STOPPED] {
+            stopped = true;
+            break;
+        }
+    }
+    assert!(stopped, "{:?}", log.health().await);
+
+    armed.store(false, Ordering::Relaxed);
+    log.try_recover().await.unwrap();
+    let s = log.fmt("app", LEVEL::Info, "", 0, "after recovery".to_string());
+    log.log(LEVEL::Info, "app", s);
+    log.flush().await;
+    assert!(log.health().await.ok);
+    assert!(fs::read_to_string(&path).unwrap().ends_with("[INFO] after recovery\n"));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_not_recovered_display() {
+    let e = tklog::Error::NotRecovered(vec!["a.log cannot be reopened; its lines go to the default file".to_string(), STOPPED.to_string()]);
+    assert_eq!(
+        e.to_string(),
+        "recovery incomplete: a.log cannot be reopened; its lines go to the default file; the queue consumer has stopped; queued lines are dropped"
+    );
+}
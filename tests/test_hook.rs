@@ -0,0 +1,98 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tklog::{debug, error, info, sync::Logger, warn, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+fn write(log: &mut Logger, level: LEVEL, module: &str, message: &str) {
+    let s = log.fmt(module, level, "", 0, message.to_string());
+    if !s.is_empty() {
+        log.print(level, module, s);
+    }
+}
+
+#[test]
+fn test_hook_counts() {
+    let counts = Arc::new(Mutex::new(Vec::new()));
+    let c = counts.clone();
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag);
+    log.add_hook(Box::new(move |level, module, line| c.lock().unwrap().push((level, module.to_string(), line.to_string()))));
+    write(&mut log, LEVEL::Info, "app::db", "connected");
+    write(&mut log, LEVEL::Error, "app", "failed");
+    assert_eq!(
+        *counts.lock().unwrap(),
+        vec![(LEVEL::Info, "app::db".to_string(), "[INFO] connected\n".to_string()), (LEVEL::Error, "app".to_string(), "[ERROR] failed\n".to_string())]
+    );
+
+    log.remove_hooks();
+    write(&mut log, LEVEL::Error, "app", "unseen");
+    assert_eq!(counts.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_hook_delay() {
+    let file = logfile("delay");
+    let calls = Arc::new(AtomicU64::new(0));
+    let c = calls.clone();
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false);
+    log.add_hook(Box::new(move |_, _, _| {
+        c.fetch_add(1, Ordering::SeqCst);
+    }));
+    write(&mut log, LEVEL::Warn, "app", "queued");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    log.flush();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[WARN] queued\n");
+    let _ = fs::remove_file(&file);
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_hook_logs() {
+    let file = logfile("logs");
+    let levels = Arc::new(Mutex::new(Vec::new()));
+    let l = levels.clone();
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false);
+    LOG.add_hook(Box::new(move |level, _, _| {
+        l.lock().unwrap().push(level);
+        // Doesn't deadlock: goes to stderr instead.
+        warn!("from a hook");
+    }));
+    LOG.set_level(LEVEL::Info);
+    debug!("below the level");
+    info!("first");
+    error!("second");
+    LOG.remove_hooks();
+    assert_eq!(*levels.lock().unwrap(), vec![LEVEL::Info, LEVEL::Error]);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] first\n[ERROR] second\n");
+    let _ = fs::remove_file(&file);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_hook_async() {
+    let file = logfile("async");
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let l = lines.clone();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false).await;
+    log.add_hook(Box::new(move |level, _, line| l.lock().unwrap().push((level, line.to_string()))));
+    for i in 0..3 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {}", i));
+        log.log(LEVEL::Info, "app", s);
+    }
+    // Called on the call site, before the queue is written.
+    assert_eq!(lines.lock().unwrap().len(), 3);
+    log.flush().await;
+    assert_eq!(lines.lock().unwrap()[2], (LEVEL::Info, "[INFO] line 2\n".to_string()));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] line 0\n[INFO] line 1\n[INFO] line 2\n");
+    let _ = fs::remove_file(&file);
+}
@@ -0,0 +1,32 @@
+use tklog::{info, LEVEL};
+
+// One test: initialization is once per process.
+#[test]
+fn test_init_once() {
+    info!("logging works before any initialization");
+    let handle = tklog::init_once(|log| {
+        log.set_level(LEVEL::Warn).set_console(false);
+    })
+    .unwrap();
+    let first_line = line!() - 4;
+    assert_eq!(handle.location().file(), file!());
+    assert_eq!(handle.location().line(), first_line);
+
+    let err = tklog::init_once(|log| {
+        log.set_level(LEVEL::Trace);
+    })
+    .unwrap_err();
+    assert_eq!(err.location.line(), first_line);
+    assert!(err.config.contains("level: Warn\n"), "{}", err.config);
+    assert!(err.to_string().starts_with(&format!("tklog is already initialized at {}:{}:", file!(), first_line)), "{}", err);
+    assert_eq!(tklog::global().get_level("app"), LEVEL::Warn, "a refused init changes nothing");
+
+    let handle = tklog::force_reconfigure(|log| {
+        log.set_level(LEVEL::Error);
+    });
+    assert_eq!(tklog::global().get_level("app"), LEVEL::Error);
+    let err = tklog::init_once(|_| {}).unwrap_err();
+    assert_eq!(err.location.line(), handle.location().line());
+    handle.log().set_level(LEVEL::Info);
+    info!("logging works after");
+}
@@ -0,0 +1,114 @@
+#![cfg(all(feature = "journald", unix))]
+
+mod common;
+
+use std::{
+    fs,
+    os::unix::{fs::FileExt, io::FromRawFd, net::UnixDatagram},
+    path::PathBuf,
+    time::Duration,
+};
+
+use tklog::{sync::Logger, Format, LEVEL, PRINTMODE};
+
+use common::logfile;
+
+fn socket(name: &str) -> (PathBuf, UnixDatagram) {
+    let path = std::env::temp_dir().join(format!("tklog_journald_{}_{}.sock", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    let journal = UnixDatagram::bind(&path).unwrap();
+    journal.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    (path, journal)
+}
+
+fn logger(socket: PathBuf, file: &str) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(file, 0, 0, false);
+    log.set_journald_socket(Some(socket));
+    log
+}
+
+#[test]
+fn test_journald_fields() {
+    let (path, journal) = socket("fields");
+    let file = logfile("fields");
+    let mut log = logger(path.clone(), &file);
+    let s = log.fmt("app::db", LEVEL::Warn, "src/db.rs", 42, "disk low".to_string());
+    log.print(LEVEL::Warn, "app::db", s);
+    let s = log.fmt("app::db", LEVEL::Error, "src/db.rs", 43, "two\nlines".to_string());
+    log.print(LEVEL::Error, "app::db", s);
+
+    let mut buf = vec![0u8; 4096];
+    let n = journal.recv(&mut buf).unwrap();
+    assert_eq!(std::str::from_utf8(&buf[..n]).unwrap(), "PRIORITY=4\nCODE_FILE=src/db.rs\nCODE_LINE=42\nTARGET=app::db\nMESSAGE=[WARN] disk low\n");
+
+    // A value with a newline goes as its length and bytes.
+    let n = journal.recv(&mut buf).unwrap();
+    let mut expected = b"PRIORITY=3\nCODE_FILE=src/db.rs\nCODE_LINE=43\nTARGET=app::db\nMESSAGE\n".to_vec();
+    expected.extend_from_slice(&17u64.to_le_bytes());
+    expected.extend_from_slice(b"[ERROR] two\nlines\n");
+    assert_eq!(buf[..n], expected[..]);
+    assert_eq!(fs::read_to_string(&file).unwrap_or_default(), "");
+    let _ = fs::remove_file(&file);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_journald_fallback() {
+    let file = logfile("fallback");
+    let missing = std::env::temp_dir().join(format!("tklog_journald_missing_{}.sock", std::process::id()));
+    let mut log = logger(missing, &file);
+    for m in ["first", "second"] {
+        let s = log.fmt("app", LEVEL::Info, "src/main.rs", 1, m.to_string());
+        log.print(LEVEL::Info, "app", s);
+    }
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[INFO] first\n[INFO] second\n");
+    let _ = fs::remove_file(&file);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_journald_memfd() {
+    let (path, journal) = socket("memfd");
+    let file = logfile("memfd");
+    let mut log = logger(path.clone(), &file);
+    let big = "x".repeat(4 << 20);
+    let s = log.fmt("app", LEVEL::Info, "src/main.rs", 7, big.clone());
+    log.print(LEVEL::Info, "app", s);
+
+    // The datagram is empty and carries one descriptor.
+    let mut control = [0u64; 8];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = std::mem::size_of_val(&control) as _;
+    let n = unsafe { libc::recvmsg(std::os::unix::io::AsRawFd::as_raw_fd(&journal), &mut msg, 0) };
+    assert_eq!(n, 0);
+    let fd = unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        assert_eq!((*cmsg).cmsg_type, libc::SCM_RIGHTS);
+        std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::c_int>())
+    };
+    let memfd = unsafe { fs::File::from_raw_fd(fd) };
+    let mut record = vec![0u8; memfd.metadata().unwrap().len() as usize];
+    memfd.read_exact_at(&mut record, 0).unwrap();
+    let record = String::from_utf8(record).unwrap();
+    assert!(record.starts_with("PRIORITY=6\nCODE_FILE=src/main.rs\nCODE_LINE=7\nTARGET=app\nMESSAGE=[INFO] x"));
+    assert_eq!(record.len(), "PRIORITY=6\nCODE_FILE=src/main.rs\nCODE_LINE=7\nTARGET=app\nMESSAGE=[INFO] \n".len() + big.len());
+    assert_eq!(fs::read_to_string(&file).unwrap_or_default(), "");
+    let _ = fs::remove_file(&file);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_journald_async() {
+    let (path, journal) = socket("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_journald_socket(Some(path.clone()));
+    let s = log.fmt("app", LEVEL::Debug, "src/task.rs", 9, "queued".to_string());
+    log.log(LEVEL::Debug, "app", s);
+
+    let mut buf = vec![0u8; 4096];
+    let n = journal.recv(&mut buf).unwrap();
+    assert_eq!(std::str::from_utf8(&buf[..n]).unwrap(), "PRIORITY=7\nCODE_FILE=src/task.rs\nCODE_LINE=9\nTARGET=app\nMESSAGE=[DEBUG] queued\n");
+    let _ = fs::remove_file(&path);
+}
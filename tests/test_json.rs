@@ -0,0 +1,53 @@
+use tklog::{json::Schema, sync::Logger, Error, LEVEL};
+
+/// The top-level keys of a flat JSON object, in order.
+fn keys(line: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let (mut in_string, mut escaped, mut current) = (false, false, String::new());
+    let mut expect_key = true;
+    for c in line.trim_end().trim_start_matches('{').trim_end_matches('}').chars() {
+        match (in_string, c) {
+            (true, '\\') if !escaped => escaped = true,
+            (true, '"') if !escaped => {
+                in_string = false;
+                let s = std::mem::take(&mut current);
+                if expect_key {
+                    keys.push(s);
+                    expect_key = false;
+                }
+            }
+            (true, c) => {
+                escaped = false;
+                current.push(c);
+            }
+            (false, '"') => in_string = true,
+            (false, ',') => expect_key = true,
+            _ => (),
+        }
+    }
+    keys
+}
+
+#[test]
+fn test_json_schema_keys() {
+    std::env::remove_var("POD_NAME");
+    std::env::remove_var("POD_NAMESPACE");
+    let mut log = Logger::new();
+    log.preset_k8s();
+    let line = |log: &mut Logger| log.fmt_with_event("app::db", LEVEL::Warn, "src/db.rs", 42, Some("POOL_EXHAUSTED"), "a \"quoted\", message\n".to_string()).file_body;
+
+    let current = line(&mut log);
+    assert!(current.starts_with("{\"v\":2,"), "{}", current);
+    assert_eq!(keys(&current), Schema::CURRENT.keys);
+    assert_eq!(Schema::CURRENT, Schema::V2);
+
+    for version in [0, 1, 2] {
+        let schema = Schema::version(version).unwrap();
+        log.set_json_schema_version(version).unwrap();
+        assert_eq!(keys(&line(&mut log)), schema.keys, "version {}", version);
+    }
+    assert_eq!(Schema::V2.keys, ["v", "ts", "level", "msg", "caller", "logger", "event"]);
+    assert_eq!(Schema::V1.keys, ["v", "ts", "level", "msg", "caller", "logger"]);
+    assert_eq!(Schema::V0.keys, ["ts", "level", "msg", "caller", "logger"]);
+    assert!(matches!(log.set_json_schema_version(3), Err(Error::UnknownSchemaVersion(3))));
+}
@@ -0,0 +1,50 @@
+use std::{
+    cell::Cell,
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{debugs, errors, infos, sync::Logger, Format, LEVEL};
+
+fn summary(calls: &Cell<u32>) -> String {
+    calls.set(calls.get() + 1);
+    format!("{} entries", 3)
+}
+
+fn broken(calls: &Cell<u32>) -> String {
+    if calls.get() > 0 {
+        panic!("bad state");
+    }
+    String::new()
+}
+
+#[test]
+fn test_lazy_message_closure() {
+    let path = std::env::temp_dir().join(format!("tklog_lazy_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_formatter("{level} {message}\n").set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    let mut logger = Arc::new(Mutex::new(log));
+    let log = &mut logger;
+
+    let calls = Cell::new(0);
+    debugs!(log, || summary(&calls));
+    assert_eq!(calls.get(), 0, "a filtered line must not run its closure");
+    infos!(log, || summary(&calls));
+    assert_eq!(calls.get(), 1);
+    errors!(log, || broken(&calls));
+    infos!(log, "after");
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] 3 entries\n[ERROR] tklog: message closure panicked: bad state\n[INFO] after\n");
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_lazy_global_macros() {
+    tklog::LOG.set_console(false).set_level(LEVEL::Warn);
+    let calls = Cell::new(0);
+    tklog::info!(|| summary(&calls));
+    assert_eq!(calls.get(), 0);
+    tklog::warn!(|| summary(&calls));
+    assert_eq!(calls.get(), 1);
+}
@@ -0,0 +1,50 @@
+use std::env;
+
+use tklog::{sync::Logger, LogOption, LEVEL};
+
+#[test]
+fn test_level_from_env() {
+    env::set_var("TKLOG_TEST_LEVEL", " INFO, app::db=Debug ,hyper=warn,app::cache=off");
+    let mut log = Logger::new();
+    log.set_level_from_env("TKLOG_TEST_LEVEL");
+    assert_eq!(log.get_level("app"), LEVEL::Info);
+    assert_eq!(log.get_level("app::db"), LEVEL::Debug);
+    assert_eq!(log.get_level("hyper"), LEVEL::Warn);
+    assert_eq!(log.get_level("app::cache"), LEVEL::Off);
+
+    // Unknown tokens are left out, the rest applies.
+    env::set_var("TKLOG_TEST_LEVEL", "verbose,=debug,app::db=loud,error");
+    log.set_level_from_env("TKLOG_TEST_LEVEL");
+    assert_eq!(log.get_level("app"), LEVEL::Error);
+    assert_eq!(log.module_levels(), vec![]);
+
+    // Unset, nothing changes.
+    env::remove_var("TKLOG_TEST_LEVEL");
+    log.set_level_from_env("TKLOG_TEST_LEVEL");
+    assert_eq!(log.get_level("app"), LEVEL::Error);
+}
+
+#[test]
+fn test_level_from_env_reread() {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Warn).set_mod_option("app::http", LogOption { level: None, format: None, formatter: None, console: Some(false), fileoption: None });
+    env::set_var("TKLOG_TEST_REREAD", "app::db=trace,app::http=debug");
+    log.set_level_from_env("TKLOG_TEST_REREAD");
+    assert_eq!(log.module_levels(), vec![("app::db".to_string(), LEVEL::Trace), ("app::http".to_string(), LEVEL::Debug)]);
+
+    // The modules of the previous read are dropped; other options stay.
+    env::set_var("TKLOG_TEST_REREAD", "app::db=info");
+    log.set_level_from_env("TKLOG_TEST_REREAD");
+    assert_eq!(log.module_levels(), vec![("app::db".to_string(), LEVEL::Info)]);
+    assert_eq!(log.get_level("app::http"), LEVEL::Warn);
+    assert!(!log.clear_module_level("app::http"));
+}
+
+#[tokio::test]
+async fn test_level_from_env_async() {
+    env::set_var(tklog::levelspec::TKLOG_LEVEL, "debug,app::db=error");
+    let log = tklog::Async::Logger::from_env();
+    env::remove_var(tklog::levelspec::TKLOG_LEVEL);
+    assert_eq!(log.get_level("app"), LEVEL::Debug);
+    assert_eq!(log.get_level("app::db"), LEVEL::Error);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use tklog::{cut::CutSize, Format, LEVEL};
+
+use common::{dir, files};
+
+fn cut(path: &PathBuf, max_size: u64) -> CutSize {
+    CutSize::builder().file(path).max_size(max_size).build().unwrap()
+}
+
+const LINES: [(LEVEL, &str); 4] = [(LEVEL::Info, "started"), (LEVEL::Warn, "slow disk"), (LEVEL::Error, "write failed"), (LEVEL::Debug, "retry")];
+
+#[test]
+fn test_level_file_sync() {
+    let dir = dir("sync");
+    let (app, errors) = (dir.join("app.log"), dir.join("app_error.log"));
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_level(LEVEL::Debug).set_format(Format::LevelFlag).set_cut(cut(&app, 1 << 20)).add_level_file(LEVEL::Warn.., cut(&errors, 1 << 20));
+
+    for (level, message) in LINES {
+        let s = log.fmt("app", level, "", 0, message.to_string());
+        log.print(level, "app", s);
+    }
+    assert_eq!(fs::read_to_string(&app).unwrap(), "[INFO] started\n[WARN] slow disk\n[ERROR] write failed\n[DEBUG] retry\n");
+    assert_eq!(fs::read_to_string(&errors).unwrap(), "[WARN] slow disk\n[ERROR] write failed\n");
+
+    // Adding the file again replaces its levels.
+    log.add_level_file(LEVEL::Error, cut(&errors, 1 << 20));
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "warn only in app.log".to_string());
+    log.print(LEVEL::Warn, "app", s);
+    assert_eq!(fs::read_to_string(&errors).unwrap(), "[WARN] slow disk\n[ERROR] write failed\n");
+
+    log.clear_level_files();
+    let s = log.fmt("app", LEVEL::Error, "", 0, "error only in app.log".to_string());
+    log.print(LEVEL::Error, "app", s);
+    assert_eq!(fs::read_to_string(&errors).unwrap(), "[WARN] slow disk\n[ERROR] write failed\n");
+    assert_eq!(fs::read_to_string(&app).unwrap().lines().count(), 6);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_level_file_rotates_alone() {
+    let dir = dir("rotate");
+    let (app, errors) = (dir.join("app.log"), dir.join("app_error.log"));
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cut(cut(&app, 1 << 20)).add_level_file(LEVEL::Error.., cut(&errors, 30));
+
+    for i in 0..6 {
+        let s = log.fmt("app", LEVEL::Error, "", 0, format!("failure {}", i));
+        log.print(LEVEL::Error, "app", s);
+    }
+    let names = files(&dir);
+    assert!(names.iter().filter(|n| n.starts_with("app_error_")).count() >= 2, "{:?}", names);
+    assert!(!names.iter().any(|n| n.starts_with("app_") && !n.starts_with("app_error")), "{:?}", names);
+    assert_eq!(fs::read_to_string(&app).unwrap().lines().count(), 6);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_level_file_async() {
+    let dir = dir("async");
+    let (app, errors) = (dir.join("app.log"), dir.join("app_error.log"));
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_level(LEVEL::Debug).set_format(Format::LevelFlag).set_cut(cut(&app, 1 << 20)).await;
+    log.add_level_file(LEVEL::Warn.., cut(&errors, 1 << 20)).await;
+
+    for (level, message) in LINES {
+        let s = log.fmt("app", level, "", 0, message.to_string());
+        log.log(level, "app", s);
+    }
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&app).unwrap(), "[INFO] started\n[WARN] slow disk\n[ERROR] write failed\n[DEBUG] retry\n");
+    assert_eq!(fs::read_to_string(&errors).unwrap(), "[WARN] slow disk\n[ERROR] write failed\n");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,31 @@
+use std::fs;
+
+use tklog::{debug, info, trace, Format, LEVEL, LOG, PRINTMODE};
+
+// The macros skip the lock below the lowest level set; each way of
+// setting a level lowers that floor in time for the next line.
+#[test]
+fn test_level_floor() {
+    let path = std::env::temp_dir().join(format!("tklog_level_floor_{}.log", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    LOG.set_console(false)
+        .set_printmode(PRINTMODE::PUNCTUAL)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(path, 0, 0, false);
+    debug!("hidden");
+    info!("shown");
+
+    tklog::global().set_level(LEVEL::Debug);
+    debug!("after global");
+    trace!("hidden");
+
+    LOG.set_level(LEVEL::Warn).set_mod_level(module_path!(), LEVEL::Trace);
+    trace!("module");
+    LOG.clear_module_level(module_path!());
+    info!("hidden");
+
+    assert_eq!(fs::read_to_string(path).unwrap(), "[INFO] shown\n[DEBUG] after global\n[TRACE] module\n");
+    let _ = fs::remove_file(path);
+}
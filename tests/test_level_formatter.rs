@@ -0,0 +1,53 @@
+mod common;
+
+use tklog::{sync::Logger, Format, LogOption, LEVEL};
+
+use common::testmode;
+
+fn logger() -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName);
+    log.set_formatter("{level} {time} {file}: {message}\n").set_test_mode(testmode()).unwrap();
+    log
+}
+
+fn line(log: &mut Logger, level: LEVEL) -> String {
+    log.fmt("app", level, "main.rs", 7, "m".to_string()).file_body
+}
+
+#[test]
+fn test_level_formatter() {
+    let mut log = logger();
+    log.set_formatter_for(LEVEL::Info, "{level} {message}\n");
+    assert_eq!(line(&mut log, LEVEL::Info), "[INFO] m\n");
+    // The other levels keep the formatter of the logger.
+    assert_eq!(line(&mut log, LEVEL::Error), "[ERROR] 2024-05-01 12:00:00 main.rs 7: m\n");
+
+    // The format flags still decide what the line carries.
+    log.set_formatter_for(LEVEL::Warn, "{file}|{message}|{time}\n").set_format(Format::LevelFlag | Format::Time);
+    assert_eq!(line(&mut log, LEVEL::Warn), "|m|12:00:00\n");
+}
+
+// A formatter set for a level keeps its other options, and a level option
+// set afterwards replaces it.
+#[test]
+fn test_level_formatter_with_level_option() {
+    let mut log = logger();
+    let mut option = LogOption::new();
+    option.set_format(Format::LevelFlag);
+    log.set_level_option(LEVEL::Debug, &option);
+    log.set_formatter_for(LEVEL::Debug, "<{level}> {time}{message}\n");
+    assert_eq!(line(&mut log, LEVEL::Debug), "<[DEBUG]> m\n");
+
+    log.set_level_option(LEVEL::Debug, &LogOption::new());
+    assert_eq!(line(&mut log, LEVEL::Debug), "[DEBUG] 2024-05-01 12:00:00 main.rs 7: m\n");
+}
+
+#[tokio::test]
+async fn test_level_formatter_async() {
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::ShortFileName).set_formatter("{level} {file}: {message}\n");
+    log.set_formatter_for(LEVEL::Info, "{message}\n");
+    assert_eq!(log.fmt("app", LEVEL::Info, "main.rs", 7, "m".to_string()).file_body, "m\n");
+    assert_eq!(log.fmt("app", LEVEL::Fatal, "main.rs", 7, "m".to_string()).file_body, "[FATAL] main.rs 7: m\n");
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{infos, record::FormatStage, sync::Logger, warns, Format, LEVEL};
+
+use common::logfile;
+
+#[test]
+fn test_level_labels() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_level_label(LEVEL::Warn, "WARNING").set_level_label(LEVEL::Fatal, "CRITICAL");
+    let line = |log: &mut Logger, level| log.fmt("app", level, "", 0, "m".to_string()).file_body;
+    assert_eq!(line(&mut log, LEVEL::Warn), "[WARNING] m\n");
+    assert_eq!(line(&mut log, LEVEL::Fatal), "[CRITICAL] m\n");
+    assert_eq!(line(&mut log, LEVEL::Info), "[INFO] m\n");
+
+    log.set_formatter("{level}|{message}\n");
+    assert_eq!(line(&mut log, LEVEL::Warn), "[WARNING]|m\n");
+
+    log.set_attr_format(|fmt| fmt.set_level_fmt(|level| format!("<{:?}>", level)));
+    assert_eq!(line(&mut log, LEVEL::Warn), "<Warn>|m\n");
+
+    log.set_format_json(true);
+    assert!(line(&mut log, LEVEL::Warn).starts_with("{\"level\":\"warn\""));
+
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_level_label(LEVEL::Warn, "WARNING").clear_level_labels();
+    assert_eq!(line(&mut log, LEVEL::Warn), "[WARN] m\n");
+}
+
+#[test]
+fn test_level_labels_multi_macros() {
+    let path = logfile("multi");
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).set_level_label(LEVEL::Warn, "WARNING");
+    let mut log = Arc::new(Mutex::new(log));
+    let log = &mut log;
+    warns!(log, "disk at 91%");
+    infos!(log, "ok");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[WARNING] disk at 91%\n[INFO] ok\n");
+    let _ = fs::remove_file(&path);
+}
+
+// Lines formatted on the consumer keep the labels of when they were queued.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_level_labels_captured_when_queued() {
+    let path = logfile("queued");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_format_stage(FormatStage::Worker).set_cutmode_by_size(&path, 0, 0, false).await;
+    log.set_level_label(LEVEL::Warn, "WARNING");
+    for i in 0..100 {
+        log.enqueue(LEVEL::Warn, "app", "", 0, format!("first {}", i));
+    }
+    log.set_level_label(LEVEL::Warn, "W");
+    log.enqueue(LEVEL::Warn, "app", "", 0, "second".to_string());
+    log.flush().await;
+
+    let mut expected: Vec<String> = (0..100).map(|i| format!("[WARNING] first {}", i)).collect();
+    expected.push("[W] second".to_string());
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().collect::<Vec<_>>(), expected);
+    let _ = fs::remove_file(&path);
+}
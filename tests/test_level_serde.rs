@@ -0,0 +1,18 @@
+#![cfg(feature = "serde")]
+
+use tklog::LEVEL;
+
+const LEVELS: [LEVEL; 7] = [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal, LEVEL::Off];
+
+#[test]
+fn test_level_serde_round_trip() {
+    for level in LEVELS {
+        let json = serde_json::to_string(&level).unwrap();
+        assert_eq!(json, format!("\"{}\"", level));
+        assert_eq!(serde_json::from_str::<LEVEL>(&json).unwrap(), level);
+    }
+    assert_eq!(serde_json::from_str::<LEVEL>("\"warning\"").unwrap(), LEVEL::Warn);
+    assert_eq!(serde_json::from_str::<Vec<LEVEL>>("[\"info\", \"3\"]").unwrap(), [LEVEL::Info, LEVEL::Info]);
+    let err = serde_json::from_str::<LEVEL>("\"verbose\"").unwrap_err();
+    assert!(err.to_string().contains("unknown level `verbose`"), "{}", err);
+}
@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use tklog::{sync::Logger, Format, ParseLevelError, LEVEL};
+
+const LEVELS: [LEVEL; 7] = [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal, LEVEL::Off];
+
+#[test]
+fn test_level_from_str() {
+    assert_eq!("trace".parse(), Ok(LEVEL::Trace));
+    assert_eq!("Debug".parse(), Ok(LEVEL::Debug));
+    assert_eq!("INFO".parse(), Ok(LEVEL::Info));
+    assert_eq!("warn".parse(), Ok(LEVEL::Warn));
+    assert_eq!("Warning".parse(), Ok(LEVEL::Warn));
+    assert_eq!("error".parse(), Ok(LEVEL::Error));
+    assert_eq!("fatal".parse(), Ok(LEVEL::Fatal));
+    assert_eq!("off".parse(), Ok(LEVEL::Off));
+    for (i, level) in LEVELS.into_iter().enumerate() {
+        assert_eq!(LEVEL::from_str(&(i + 1).to_string()), Ok(level));
+    }
+    for bad in ["", "0", "8", "verbose", " info"] {
+        let err = LEVEL::from_str(bad).unwrap_err();
+        assert_eq!(err, ParseLevelError { input: bad.to_string() });
+        assert!(err.to_string().contains("expected trace"), "{}", err);
+    }
+}
+
+#[test]
+fn test_level_try_from_u8() {
+    for level in LEVELS {
+        assert_eq!(LEVEL::try_from(level as u8), Ok(level));
+    }
+    assert_eq!(LEVEL::try_from(0).unwrap_err().input, "0");
+    assert!(LEVEL::try_from(8).is_err());
+}
+
+// The name is the one in the level flag of a line, and parses back.
+#[test]
+fn test_level_display_round_trip() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    for level in LEVELS.into_iter().filter(|l| *l != LEVEL::Off) {
+        let s = log.fmt("app", level, "", 0, "x".to_string());
+        assert_eq!(s.file_body, format!("[{}] x\n", level));
+    }
+    for level in LEVELS {
+        assert_eq!(level.to_string().parse(), Ok(level));
+    }
+    assert_eq!(format!("{:>5}|", LEVEL::Info), " INFO|");
+}
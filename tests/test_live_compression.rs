@@ -0,0 +1,126 @@
+mod common;
+
+use std::{
+    fs,
+    io::{BufRead, Read},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use flate2::read::MultiGzDecoder;
+use tklog::{compress, CompressType, Format, LEVEL};
+
+use common::{dir, files};
+
+/// The lines of a live file up to its last flush, and whether its gzip
+/// member was still open.
+fn read_live(path: &Path) -> (Vec<String>, bool) {
+    let mut lines = Vec::new();
+    for line in compress::open_any(path).unwrap().lines() {
+        match line {
+            Ok(line) => lines.push(line),
+            Err(e) if compress::Truncated::of(&e).is_some() => return (lines, true),
+            Err(e) => panic!("{}", e),
+        }
+    }
+    (lines, false)
+}
+
+/// A backup must be a complete archive.
+fn read_backup(path: &Path) -> String {
+    let mut s = String::new();
+    MultiGzDecoder::new(fs::File::open(path).unwrap()).read_to_string(&mut s).unwrap();
+    s
+}
+
+#[test]
+fn test_live_compression_sync() {
+    let dir = dir("sync");
+    let path = dir.join("app.log");
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 40, 0, false);
+    log.set_live_compression(CompressType::Gzip, Duration::ZERO);
+
+    for i in 0..10 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {}", i));
+        log.print(LEVEL::Info, "app", s);
+    }
+    let names = files(&dir);
+    assert!(!names.contains(&"app.log".to_string()), "{:?}", names);
+    assert!(names.contains(&"app.log.gz".to_string()), "{:?}", names);
+
+    // Every line so far is readable from the open member.
+    let live = dir.join("app.log.gz");
+    let (tail, open) = read_live(&live);
+    assert!(open);
+    thread::sleep(Duration::from_millis(100));
+    let mut backups: Vec<String> = files(&dir).into_iter().filter(|n| n.starts_with("app_")).collect();
+    backups.sort_by_key(|n| n[4..n.len() - 7].parse::<u32>().unwrap());
+    assert!(backups.len() >= 2 && backups.iter().all(|n| n.ends_with(".log.gz")), "{:?}", backups);
+    let mut text: String = backups.iter().map(|n| read_backup(&dir.join(n))).collect();
+    for line in &tail {
+        text.push_str(line);
+        text.push('\n');
+    }
+    let expected: String = (0..10).map(|i| format!("[INFO] line {}\n", i)).collect();
+    assert_eq!(text, expected);
+
+    // Dropping the logger closes the member.
+    drop(log);
+    let (lines, open) = read_live(&live);
+    assert!(!open);
+    assert_eq!(lines, tail);
+    assert_eq!(tklog::postmortem::previous_tail(&path, 1).unwrap(), ["[INFO] line 9"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_live_compression_waits_for_interval() {
+    let dir = dir("interval");
+    let path = dir.join("app.log");
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 0, 0, false);
+    log.set_live_compression(CompressType::Gzip, Duration::from_millis(200));
+
+    let s = log.fmt("app", LEVEL::Info, "", 0, "first".to_string());
+    log.print(LEVEL::Info, "app", s);
+    let s = log.fmt("app", LEVEL::Info, "", 0, "second".to_string());
+    log.print(LEVEL::Info, "app", s);
+    let live = dir.join("app.log.gz");
+    assert_eq!(read_live(&live), (vec![], true));
+
+    thread::sleep(Duration::from_millis(250));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "third".to_string());
+    log.print(LEVEL::Info, "app", s);
+    assert_eq!(read_live(&live), (vec!["[INFO] first".to_string(), "[INFO] second".to_string(), "[INFO] third".to_string()], true));
+
+    // Turned off, the member is closed and the raw file is written again.
+    log.clear_live_compression();
+    let s = log.fmt("app", LEVEL::Info, "", 0, "raw".to_string());
+    log.print(LEVEL::Info, "app", s);
+    assert_eq!(read_live(&live).1, false);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] raw\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_live_compression_async() {
+    let dir = dir("async");
+    let path = dir.join("app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 0, 0, false).await;
+    log.set_live_compression(CompressType::Gzip, Duration::from_secs(3600));
+
+    for i in 0..100 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("queued {}", i));
+        log.log(LEVEL::Info, "app", s);
+    }
+    log.flush().await;
+    let (lines, open) = read_live(&dir.join("app.log.gz"));
+    assert!(open);
+    assert_eq!(lines.len(), 100);
+    assert_eq!(lines[99], "[INFO] queued 99");
+    assert!(!path.exists(), "{:?}", files(&dir));
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{async_infos, infos, sync::Logger, Format, LocationStrategy, LEVEL, PRINTMODE};
+
+use common::logfile;
+
+#[track_caller]
+fn helper(log: &mut Logger, msg: &str) {
+    log.log_record(LEVEL::Warn, "loc", None, msg.to_string());
+}
+
+#[track_caller]
+fn macro_helper(log: &mut Arc<Mutex<Logger>>, msg: &str) -> u32 {
+    infos!(log, msg);
+    line!() - 1
+}
+
+macro_rules! wrapped {
+    ($log:expr, $msg:expr) => {
+        infos!($log, "wrapped ", $msg)
+    };
+}
+
+macro_rules! async_wrapped {
+    ($log:expr, $msg:expr) => {
+        async_infos!($log, "wrapped ", $msg)
+    };
+}
+
+#[test]
+fn test_log_record_location() {
+    let path = std::env::temp_dir().join(format!("tklog_location_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_format(Format::LevelFlag | Format::LongFileName)
+        .set_formatter("{file}|{message}\n")
+        .set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    log.set_printmode(PRINTMODE::PUNCTUAL);
+
+    let direct = line!() + 1;
+    log.log_record(LEVEL::Info, "loc", None, "direct".to_string());
+    let nested = line!() + 1;
+    helper(&mut log, "nested");
+    log.log_record(LEVEL::Info, "loc", Some(("src/explicit.rs", 7)), "explicit".to_string());
+    log.log_record(LEVEL::Trace, "loc", None, "filtered".to_string());
+
+    let expect = format!("tests/test_location.rs {}|direct\ntests/test_location.rs {}|nested\nsrc/explicit.rs 7|explicit\n", direct, nested);
+    assert_eq!(fs::read_to_string(&path).unwrap(), expect);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_macro_location_strategy() {
+    let path = logfile("strategy");
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::ShortFileName).set_formatter("{file}|{message}\n").set_cutmode_by_size(&path, 0, 0, false);
+    assert_eq!(log.location_strategy(), LocationStrategy::Caller);
+    let mut log = Arc::new(Mutex::new(log));
+    let log = &mut log;
+
+    let caller = line!() + 1;
+    macro_helper(log, "in a helper");
+    let outer = line!() + 1;
+    wrapped!(log, "by a macro");
+    log.lock().unwrap().set_location_strategy(LocationStrategy::MacroExpansion);
+    let helper = macro_helper(log, "in a helper");
+    wrapped!(log, "by a macro");
+
+    let expect = format!(
+        "test_location.rs {}|in a helper\ntest_location.rs {}|wrapped by a macro\ntest_location.rs {}|in a helper\ntest_location.rs {}|wrapped by a macro\n",
+        caller,
+        outer,
+        helper,
+        outer + 3
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), expect);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_async_macro_location() {
+    let path = logfile("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::ShortFileName).set_formatter("{file}|{message}\n").set_cutmode_by_size(&path, 0, 0, false).await;
+    let log = Arc::new(log);
+    let outer = line!() + 1;
+    async_wrapped!(&log, "by a macro");
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), format!("test_location.rs {}|wrapped by a macro\n", outer));
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,50 @@
+use std::fs;
+
+use tklog::{bridge::LogBridge, Format, LogOption, LEVEL, LOG, PRINTMODE};
+
+fn module(level: LEVEL) -> LogOption {
+    LogOption { level: Some(level), format: None, formatter: None, console: None, fileoption: None }
+}
+
+// The facade has one logger per process, so this is the only test here.
+#[test]
+fn test_log_bridge() {
+    let path = std::env::temp_dir().join(format!("tklog_log_bridge_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    LOG.set_console(false)
+        .set_printmode(PRINTMODE::PUNCTUAL)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag | Format::ShortFileName)
+        .set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false)
+        .set_mod_option("dep::quiet", module(LEVEL::Error));
+    tklog::install_log_bridge(LogBridge::Sync).unwrap();
+    assert!(tklog::install_log_bridge(LogBridge::Async).is_err());
+    assert_eq!(log::max_level(), log::LevelFilter::Info);
+
+    log::info!(target: "dep", "from the facade");
+    log::debug!(target: "dep", "below the level");
+    log::warn!(target: "dep::quiet", "below the module level");
+    log::error!(target: "dep::quiet::inner", "module {}", "error");
+
+    // A module level below the global one lowers the facade's max level.
+    LOG.set_mod_option("dep::chatty", module(LEVEL::Debug));
+    assert_eq!(log::max_level(), log::LevelFilter::Debug);
+    assert!(log::log_enabled!(target: "dep::chatty", log::Level::Debug));
+    assert!(!log::log_enabled!(target: "dep", log::Level::Debug));
+    log::debug!(target: "dep::chatty", "chatty");
+    log::debug!(target: "dep", "still below the level");
+    assert!(LOG.clear_module_level("dep::chatty"));
+    assert_eq!(log::max_level(), log::LevelFilter::Info);
+
+    let line = line!();
+    let lines: Vec<String> = fs::read_to_string(&path).unwrap().lines().map(str::to_string).collect();
+    assert_eq!(
+        lines,
+        [
+            format!("[INFO] test_log_bridge.rs {}:from the facade", line - 15),
+            format!("[ERROR] test_log_bridge.rs {}:module error", line - 12),
+            format!("[DEBUG] test_log_bridge.rs {}:chatty", line - 5),
+        ]
+    );
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{logerror::LogError, sync::Logger, Format, LEVEL, PRINTMODE};
+
+type Seen = Arc<Mutex<Vec<(String, PathBuf, Option<i32>)>>>;
+
+fn recorder() -> (Seen, tklog::logerror::ErrorHandler) {
+    let seen: Seen = Arc::default();
+    let s = seen.clone();
+    let handler = Box::new(move |e: LogError| {
+        let kind = format!("{:?}", e).split('(').next().unwrap().to_string();
+        s.lock().unwrap().push((kind, e.path().to_path_buf(), e.error().raw_os_error()));
+    });
+    (seen, handler)
+}
+
+fn write(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_failed() {
+    let (seen, handler) = recorder();
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size("/dev/full", 0, 0, false);
+    log.set_error_handler(handler);
+    write(&mut log, "lost");
+    write(&mut log, "lost too");
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    // ENOSPC.
+    assert_eq!(seen[0], ("WriteFailed".to_string(), PathBuf::from("/dev/full"), Some(28)));
+}
+
+#[test]
+fn test_rotate_failed() {
+    let dir = std::env::temp_dir().join(format!("tklog_log_error_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let file = dir.join("app.log");
+    let (seen, handler) = recorder();
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(file.to_str().unwrap(), 10, 0, false);
+    log.set_error_handler(handler);
+    write(&mut log, "first line");
+    fs::remove_dir_all(&dir).unwrap();
+    write(&mut log, "second line");
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.first().map(|(kind, path, _)| (kind.as_str(), path.clone())), Some(("RotateFailed", file.clone())));
+    drop(seen);
+
+    // A handler that panics is disabled and the failures go to stderr.
+    log.set_error_handler(Box::new(|_| panic!("handler")));
+    for _ in 0..3 {
+        write(&mut log, "third line");
+    }
+    assert_eq!(log.health().degraded_reasons, ["the error handler panicked 3 times in a row and is disabled"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_write_failed_async() {
+    let (seen, handler) = recorder();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size("/dev/full", 0, 0, false).await;
+    log.set_error_handler(handler);
+    let s = log.fmt("app", LEVEL::Info, "", 0, "lost".to_string());
+    log.log(LEVEL::Info, "app", s);
+    log.flush().await;
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].0, "WriteFailed");
+    assert_eq!(seen[0].1, PathBuf::from("/dev/full"));
+}
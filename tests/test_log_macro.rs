@@ -0,0 +1,74 @@
+use std::{cell::Cell, fs};
+
+use tklog::{async_log, async_logf, log, logf, Format, LEVEL, LOG, PRINTMODE};
+
+fn level_of(status: u16, calls: &Cell<u32>) -> LEVEL {
+    calls.set(calls.get() + 1);
+    match status {
+        500.. => LEVEL::Error,
+        400.. => LEVEL::Warn,
+        _ => LEVEL::Debug,
+    }
+}
+
+#[test]
+fn test_log_macro() {
+    let file = std::env::temp_dir().join(format!("tklog_log_macro_{}.log", std::process::id()));
+    let _ = fs::remove_file(&file);
+    LOG.set_console(false)
+        .set_printmode(PRINTMODE::PUNCTUAL)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(file.to_str().unwrap(), 0, 0, false);
+    let calls = Cell::new(0);
+    log!(level_of(503, &calls), "status", 503);
+    log!(level_of(404, &calls), "not found");
+    log!(level_of(200, &calls), "ok");
+    logf!(level_of(500, &calls), "took {}ms", 12);
+    log!(level_of(404, &calls), || "lazy");
+    log!(LEVEL::Warn, "user", "joe"; id = 7);
+    assert_eq!(calls.get(), 5);
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "[ERROR] status503\n[WARN] not found\n[ERROR] took 12ms\n[WARN] lazy\n[WARN] userjoe id=7\n"
+    );
+    let _ = fs::remove_file(&file);
+}
+
+#[tokio::test]
+async fn test_log_macro_async() {
+    let file = std::env::temp_dir().join(format!("tklog_log_macro_async_{}.log", std::process::id()));
+    let _ = fs::remove_file(&file);
+    tklog::ASYNC_LOG
+        .set_console(false)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(file.to_str().unwrap(), 0, 0, false)
+        .await;
+    let calls = Cell::new(0);
+    async_log!(level_of(503, &calls), "status", 503);
+    async_log!(level_of(200, &calls), "ok");
+    async_logf!(level_of(404, &calls), "took {}ms", 12);
+    assert_eq!(calls.get(), 3);
+    tklog::global_async().await.flush().await;
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[ERROR] status503\n[WARN] took 12ms\n");
+    let _ = fs::remove_file(&file);
+}
+
+// The level of `formats!` and `logs_common!` is evaluated once, for the
+// check and for the line alike.
+#[test]
+fn test_formats_level_once() {
+    let file = std::env::temp_dir().join(format!("tklog_formats_level_{}.log", std::process::id()));
+    let _ = fs::remove_file(&file);
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_cutmode_by_size(file.to_str().unwrap(), 0, 0, false);
+    let mut log = std::sync::Arc::new(std::sync::Mutex::new(log));
+    let calls = Cell::new(0);
+    tklog::formats!(&mut log, level_of(503, &calls), "took {}ms", 12);
+    tklog::formats!(&mut log, level_of(200, &calls), "ok");
+    tklog::logs_common!(&mut log, level_of(404, &calls), "not found");
+    assert_eq!(calls.get(), 3);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[ERROR] took 12ms\n[WARN] not found\n");
+    let _ = fs::remove_file(&file);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::fs;
+
+use tklog::{sync::Logger, Error, Format, LEVEL, PRINTMODE};
+
+use common::{missing_dir, on_rotate, wait_backups};
+
+fn write(log: &mut Logger, msg: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_log_path() {
+    let dir = missing_dir("sync");
+    let first = dir.join("a/app.log");
+    let second = dir.join("b/c/app.log");
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_printmode(PRINTMODE::PUNCTUAL)
+        .set_format(Format::Nano)
+        .set_rotation_handler(on_rotate);
+    assert!(matches!(log.set_log_path(second.to_str().unwrap(), false), Err(Error::NoLogFile)));
+    log.set_cutmode_by_size(first.to_str().unwrap(), 1 << 20, 0, false);
+    write(&mut log, "one\n");
+    log.set_log_path(first.to_str().unwrap(), true).unwrap();
+    log.set_log_path(second.to_str().unwrap(), false).unwrap();
+    write(&mut log, "two\n");
+    assert_eq!(fs::read_to_string(&first).unwrap(), "one\n");
+    assert_eq!(fs::read_to_string(&second).unwrap(), "two\n");
+    assert_eq!(log.config().file.unwrap().maxsize, 1 << 20);
+
+    // The old file rotated one last time; the new one holds what was there.
+    fs::write(&first, "before\n").unwrap();
+    log.set_log_path(first.to_str().unwrap(), true).unwrap();
+    let backup = &wait_backups(second.to_str().unwrap(), 1)[0];
+    assert_eq!(fs::read_to_string(backup).unwrap(), "two\n");
+    assert!(!second.exists());
+    write(&mut log, "three\n");
+    assert_eq!(fs::read_to_string(&first).unwrap(), "before\nthree\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_log_path_unopenable() {
+    let dir = missing_dir("unopenable");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.log");
+    // A file where the directory should be.
+    let blocker = dir.join("blocker");
+    fs::write(&blocker, "").unwrap();
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_printmode(PRINTMODE::PUNCTUAL)
+        .set_format(Format::Nano)
+        .set_error_handler(Box::new(|_| {}));
+    log.set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    let refused = log.set_log_path(blocker.join("app.log").to_str().unwrap(), true);
+    assert!(matches!(refused, Err(Error::FileUnopenable(..))));
+    write(&mut log, "kept\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "kept\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_log_path_async() {
+    let dir = missing_dir("async");
+    let first = dir.join("app.log");
+    let second = dir.join("moved/app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano);
+    log.set_cutmode_by_size(first.to_str().unwrap(), 0, 0, false).await;
+    for msg in ["one\n", "two\n"] {
+        let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "app", s).await;
+    }
+    log.set_log_path(second.to_str().unwrap(), false).await.unwrap();
+    let s = log.fmt("app", LEVEL::Info, "", 0, "three\n".to_string());
+    log.print(LEVEL::Info, "app", s).await;
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&first).unwrap(), "one\ntwo\n");
+    assert_eq!(fs::read_to_string(&second).unwrap(), "three\n");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,59 @@
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{sync::Logger, timer, timers, Format, LEVEL, LOG, PRINTMODE};
+
+/// `<file>:<line>` and the label of each line, with the duration left out.
+fn lines(path: &str) -> Vec<String> {
+    fs::read_to_string(path).unwrap().lines().map(|l| l.split(" took ").next().unwrap().to_string()).collect()
+}
+
+#[test]
+fn test_log_timer_global() {
+    let path = std::env::temp_dir().join(format!("tklog_log_timer_global_{}.log", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    LOG.set_console(false)
+        .set_printmode(PRINTMODE::PUNCTUAL)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag | Format::ShortFileName)
+        .set_cutmode_by_size(path, 0, 0, false);
+    let line = line!() + 2;
+    {
+        let _t = timer!(LEVEL::Info, "load users");
+        let _off = timer!(LEVEL::Debug, "below the level");
+    }
+    let t = timer!(LEVEL::Warn, String::from("flush"));
+    t.finish();
+    assert_eq!(
+        lines(path),
+        [
+            format!("[INFO] test_log_timer.rs {}:load users", line),
+            format!("[WARN] test_log_timer.rs {}:flush", line + 3)
+        ]
+    );
+    let s = fs::read_to_string(path).unwrap();
+    assert!(s.lines().all(|l| l.ends_with('s')), "{}", s);
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn test_log_timer_multi() {
+    let path = std::env::temp_dir().join(format!("tklog_log_timer_multi_{}.log", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Debug)
+        .set_format(Format::LevelFlag)
+        .set_cutmode_by_size(path, 0, 0, false);
+    let mut log = Arc::new(Mutex::new(log));
+    {
+        let _t = timers!(&mut log, LEVEL::Debug, "query");
+        let _off = timers!(&mut log, LEVEL::Trace, "below the level");
+    }
+    assert_eq!(lines(path), ["[DEBUG] query"]);
+    let _ = fs::remove_file(path);
+}
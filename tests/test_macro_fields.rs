@@ -0,0 +1,104 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tklog::{async_infos, async_warns, debugs, info, infos, logsink::LogSink, sync::Logger, warns, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+static EVALUATED: AtomicUsize = AtomicUsize::new(0);
+
+fn user_id() -> u32 {
+    EVALUATED.fetch_add(1, Ordering::Relaxed);
+    42
+}
+
+#[test]
+fn test_macro_fields_text() {
+    let path = logfile("text");
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_separator(" ").set_cutmode_by_size(&path, 0, 0, false);
+    log.set_dynamic_fields(Box::new(|fields| {
+        fields.insert("tenant", "acme").insert("req", "dynamic");
+    }));
+    let mut logger = Arc::new(Mutex::new(log));
+    let log = &mut logger;
+
+    let request_id = String::from("abc");
+    let retry: Option<u32> = Some(3);
+    infos!(log, "request", "done"; user_id = user_id(), req = %request_id, retry = ?retry, "http.status" = 200);
+    debugs!(log, "filtered"; user_id = user_id());
+    warns!(log, "no fields");
+    warns!(log, "trailing comma"; note = "two words",);
+    assert_eq!(EVALUATED.load(Ordering::Relaxed), 1, "filtered lines must not evaluate their fields");
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "[INFO] request done user_id=42 req=abc retry=Some(3) http.status=200 tenant=acme\n\
+         [WARN] no fields tenant=acme req=dynamic\n\
+         [WARN] trailing comma note=\"two words\" tenant=acme req=dynamic\n"
+    );
+    let _ = fs::remove_file(&path);
+}
+
+#[derive(Clone, Default)]
+struct Lines(Arc<Mutex<Vec<String>>>);
+
+impl LogSink for Lines {
+    fn write(&mut self, _: LEVEL, formatted: &str) {
+        self.0.lock().unwrap().push(formatted.to_string());
+    }
+
+    fn flush(&mut self) {}
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_macro_fields_global() {
+    let lines = Lines::default();
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_custom_sink(Box::new(lines.clone())).set_custom_sink_only(true);
+    info!("started"; port = 8080);
+    info!("ready", " now");
+    LOG.clear_custom_sink();
+    assert_eq!(*lines.0.lock().unwrap(), ["[INFO] started port=8080\n", "[INFO] ready now\n"]);
+}
+
+#[test]
+fn test_macro_fields_json() {
+    let path = logfile("json");
+    let mut log = Logger::new();
+    log.set_console(false).set_format_json(true).set_cutmode_by_size(&path, 0, 0, false);
+    let mut logger = Arc::new(Mutex::new(log));
+    let log = &mut logger;
+
+    infos!(log, "login"; user_id = 42, name = ?"ann");
+    let s = fs::read_to_string(&path).unwrap();
+    assert!(s.contains("\"message\":\"login\""), "{}", s);
+    assert!(s.contains("\"user_id\":\"42\",\"name\":\"\\\"ann\\\"\""), "{}", s);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_macro_fields_async() {
+    let path = logfile("async");
+    for mode in [PRINTMODE::DELAY, PRINTMODE::PUNCTUAL] {
+        let _ = fs::remove_file(&path);
+        let mut log = tklog::Async::Logger::new();
+        log.set_console(false).set_printmode(mode).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false).await;
+        let log = Arc::new(log);
+        async_infos!(log, "queued"; job = 7, state = ?LEVEL::Warn);
+        let mut other = tklog::Async::Logger::new();
+        other.set_console(false);
+        let shared = Arc::new(tokio::sync::Mutex::new(other));
+        async_warns!(shared, "not in the file"; job = 8);
+        log.flush().await;
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] queued job=7 state=Warn\n");
+    }
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use std::{
+    fs::{self, File},
+    path::Path,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use tklog::{syncfile::prune_backups, Format, PrunePolicy, LEVEL};
+
+use common::{dir, files};
+
+/// Creates `names` in `dir`, the first the newest on disk, so that only
+/// their names tell the order they were cut in.
+fn seed(dir: &Path, names: &[&str]) {
+    let now = SystemTime::now();
+    for (i, n) in names.iter().enumerate() {
+        let f = File::create(dir.join(n)).unwrap();
+        f.set_modified(now - Duration::from_secs(60 * (i as u64 + 1))).unwrap();
+    }
+}
+
+#[test]
+fn test_prune_goes_by_names() {
+    let dir = dir("names");
+    seed(&dir, &["app_20240101_1.log.gz", "app_20240102_1.log", "app_20240102_2.log.gz", "app_20240102_10.log", "app.log"]);
+    let deleted = prune_backups(&dir.join("app.log"), 2, PrunePolicy::ByFile).unwrap();
+    assert_eq!(deleted, vec![dir.join("app_20240101_1.log.gz"), dir.join("app_20240102_1.log")]);
+    assert_eq!(files(&dir), ["app.log", "app_20240102_10.log", "app_20240102_2.log.gz"]);
+    let _ = fs::remove_dir_all(&dir);
+
+    let dir = self::dir("sizes");
+    seed(&dir, &["app_1.log.gz", "app_2.log", "app_9.log.gz", "app_11.log", "app.log"]);
+    let deleted = prune_backups(&dir.join("app.log"), 1, PrunePolicy::ByFile).unwrap();
+    assert_eq!(deleted, vec![dir.join("app_1.log.gz"), dir.join("app_2.log"), dir.join("app_9.log.gz")]);
+    assert_eq!(files(&dir), ["app.log", "app_11.log"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_set_max_backups_size_cut() {
+    let dir = dir("size");
+    let app = dir.join("app.log");
+    seed(&dir, &["app_1.log", "app_2.log.gz"]);
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&app.to_string_lossy(), 40, 0, true).set_max_backups(2);
+    assert_eq!(log.config().file.unwrap().maxbackups, 2);
+
+    for i in 0..12 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {} of the size cut", i));
+        log.print(LEVEL::Info, "app", s);
+    }
+    // The last backup is compressed and pruned on the rotation thread.
+    let backups_of = |names: &[String]| names.iter().filter(|n| n.starts_with("app_")).cloned().collect::<Vec<_>>();
+    for _ in 0..200 {
+        let backups = backups_of(&files(&dir));
+        if backups.len() == 2 && backups.iter().all(|n| n.ends_with(".log.gz")) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let names = files(&dir);
+    assert!(names.contains(&"app.log".to_string()), "{:?}", names);
+    let backups: Vec<&String> = names.iter().filter(|n| n.starts_with("app_")).collect();
+    assert_eq!(backups.len(), 2, "{:?}", names);
+    assert!(backups.iter().all(|n| n.ends_with(".log.gz")), "{:?}", names);
+    assert!(!names.contains(&"app_1.log".to_string()) && !names.contains(&"app_2.log.gz".to_string()), "{:?}", names);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_set_max_backups_zero_keeps_all() {
+    let dir = dir("zero");
+    let app = dir.join("app.log");
+    let mut log = tklog::sync::Logger::new();
+    log.set_max_backups(0).set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&app.to_string_lossy(), 40, 1, false);
+    for i in 0..8 {
+        let s = log.fmt("app", LEVEL::Info, "", 0, format!("line {} kept in a backup", i));
+        log.print(LEVEL::Info, "app", s);
+    }
+    assert!(files(&dir).iter().filter(|n| n.starts_with("app_")).count() > 1, "{:?}", files(&dir));
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,67 @@
+// Passes with and without a `max_level_*` feature: run it with
+// `--features max_level_info` too.
+
+use std::{
+    cell::Cell,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{async_debug, async_debugs, async_infos, debug, debugs, info, infos, sync::Logger, LEVEL, MAX_COMPILED_LEVEL};
+
+fn count(calls: &Cell<u32>) -> u32 {
+    calls.set(calls.get() + 1);
+    calls.get()
+}
+
+/// The calls expected of one line at `level` per call of `count`.
+fn expected(level: LEVEL) -> u32 {
+    if level >= MAX_COMPILED_LEVEL {
+        1
+    } else {
+        0
+    }
+}
+
+#[test]
+fn test_max_level_global() {
+    tklog::LOG.set_console(false).set_level(LEVEL::Trace);
+    let calls = Cell::new(0);
+    debug!("debug", count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Debug));
+    calls.set(0);
+    info!("info", count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Info));
+    calls.set(0);
+    tklog::trace!(|| count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Trace));
+}
+
+#[test]
+fn test_max_level_multi() {
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Trace);
+    let mut log = Arc::new(Mutex::new(log));
+    let calls = Cell::new(0);
+    debugs!(&mut log, "debug", count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Debug));
+    calls.set(0);
+    infos!(&mut log, "info", count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Info));
+}
+
+#[tokio::test]
+async fn test_max_level_async() {
+    tklog::ASYNC_LOG.set_console(false).set_level(LEVEL::Trace);
+    let calls = Cell::new(0);
+    async_debug!("debug", count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Debug));
+
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_level(LEVEL::Trace);
+    calls.set(0);
+    async_debugs!(&log, "debug", count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Debug));
+    calls.set(0);
+    async_infos!(&log, "info", count(&calls));
+    assert_eq!(calls.get(), expected(LEVEL::Info));
+}
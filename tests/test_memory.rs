@@ -0,0 +1,41 @@
+use std::fs;
+
+use tklog::{Async::Logger, Format, LEVEL};
+
+const BUDGET: u64 = 64 << 10;
+
+// A current-thread runtime doesn't run the queue consumer until the test
+// awaits, which stalls the sink while the lines pile up.
+#[tokio::test]
+async fn test_memory_budget_with_stalled_sink() {
+    let path = std::env::temp_dir().join(format!("tklog_memory_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Trace).set_format(Format::LevelFlag).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false).await;
+    tklog::set_memory_budget(BUDGET);
+
+    let payload = "x".repeat(100);
+    for (level, n) in [(LEVEL::Debug, 1000), (LEVEL::Info, 1000), (LEVEL::Warn, 100)] {
+        for i in 0..n {
+            log.enqueue(level, "app", "", 0, format!("{} {}", i, payload));
+            assert!(log.stats().memory.used <= BUDGET);
+        }
+    }
+    let memory = log.stats().memory;
+    assert_eq!(memory.shed[0], 1000, "every Debug line goes before any Info line");
+    assert!(memory.shed[1] > 0);
+    assert_eq!(memory.shed[2], 0);
+
+    log.flush().await;
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(content.matches("[DEBUG]").count(), 0);
+    assert_eq!(content.matches("[WARN]").count(), 100);
+    assert_eq!(content.matches("[INFO]").count() as u64, 1000 - memory.shed[1]);
+    assert!(content.contains("[INFO] 999 x"), "the newest Info lines are kept");
+    assert_eq!(log.stats().memory.used, 0);
+    assert_eq!(log.stats().sinks[0].queue_depth, 0);
+    assert!(log.stats().render_prometheus().contains("tklog_memory_shed_total{class=\"debug\"} 1000\n"));
+
+    tklog::set_memory_budget(0);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tklog::{async_errors, async_infos, debugs, errors, infos, metrics::LogMetrics, sync::Logger, warns, Format, LEVEL, PRINTMODE};
+
+use common::missing_dir;
+
+#[test]
+fn test_metrics() {
+    let dir = missing_dir("sync");
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_level(LEVEL::Info);
+    log.set_format(Format::LevelFlag).set_cutmode_by_size(path.to_str().unwrap(), 40, 0, true);
+    let mut log = Arc::new(Mutex::new(log));
+    let mut clone = log.clone();
+    debugs!(&mut log, "below the level");
+    infos!(&mut log, "one");
+    infos!(&mut clone, "two");
+    warns!(&mut clone, "three");
+    errors!(&mut log, "four");
+
+    let metrics = log.lock().unwrap().metrics();
+    assert_eq!((metrics.debug, metrics.info, metrics.warn, metrics.error), (0, 2, 1, 1));
+    assert_eq!(metrics.records(LEVEL::Info), 2);
+    assert_eq!(metrics.bytes_written, "[INFO] one\n[INFO] two\n[WARN] three\n[ERROR] four\n".len() as u64);
+    assert_eq!(metrics.rotations, 1);
+    let start = Instant::now();
+    while log.lock().unwrap().metrics().compressions < 1 {
+        assert!(start.elapsed() < Duration::from_secs(10), "no compression");
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    log.lock().unwrap().reset_metrics();
+    assert_eq!(clone.lock().unwrap().metrics(), LogMetrics::default());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_metrics_async() {
+    let dir = missing_dir("async");
+    let path = dir.join("app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag);
+    log.set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false).await;
+    let log = Arc::new(tokio::sync::Mutex::new(log));
+    async_infos!(&log, "one");
+    async_errors!(&log, "two");
+    let log = log.lock().await;
+    log.flush().await;
+    let metrics = log.metrics();
+    assert_eq!((metrics.info, metrics.error), (1, 1));
+    assert_eq!(metrics.bytes_written, fs::metadata(&path).unwrap().len());
+    assert_eq!(metrics.rotations, 0);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{infos, sync::Logger, Format, LogOption, LEVEL, PRINTMODE};
+
+use common::dir;
+
+mod proto {
+    use std::sync::{Arc, Mutex};
+
+    pub fn log(log: &mut Arc<Mutex<tklog::sync::Logger>>) {
+        tklog::infos!(log, "frame", 7, "sent");
+    }
+}
+
+// The options of `proto` replace the global ones they set; the rest fall
+// through.
+#[test]
+fn test_mod_options_override() {
+    let dir = dir("override");
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag | Format::ShortFileName).set_separator(" ");
+    log.set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false);
+    let mut terse = LogOption::new();
+    terse.set_format(Format::LevelFlag);
+    log.set_mod_option("test_mod_options::proto", terse).set_mod_separator("test_mod_options::proto", ",");
+    let mut log = Arc::new(Mutex::new(log));
+
+    proto::log(&mut log);
+    infos!(&mut log, "frame", 8, "sent");
+    let line = line!() - 1;
+    assert_eq!(fs::read_to_string(&path).unwrap(), format!("[INFO] frame,7,sent\n[INFO] test_mod_options.rs {}:frame 8 sent\n", line));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_mod_separator_keeps_options() {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Info).set_mod_level("app::db", LEVEL::Warn).set_mod_separator("app::db", "|");
+    assert_eq!(log.get_level("app::db::pool"), LEVEL::Warn);
+    assert_eq!(log.separator("app::db::pool"), "|");
+    assert_eq!(log.separator("app::http"), "");
+
+    // `set_mod_option` replaces the level but keeps the separator.
+    let mut option = LogOption::new();
+    option.set_level(LEVEL::Debug);
+    log.set_mod_option("app::db", option);
+    assert_eq!(log.get_level("app::db"), LEVEL::Debug);
+    assert_eq!(log.separator("app::db"), "|");
+    assert!(log.clear_module_level("app::db"));
+    assert_eq!(log.separator("app::db"), "|");
+}
@@ -0,0 +1,75 @@
+use tklog::{sync::Logger, Format, LogOption, LEVEL};
+
+fn option(level: Option<LEVEL>, console: Option<bool>) -> LogOption {
+    LogOption { level, format: None, formatter: None, console, fileoption: None }
+}
+
+#[test]
+fn test_module_levels() {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Info)
+        .set_mod_option("app::db", option(Some(LEVEL::Debug), None))
+        .set_mod_option("app::http::*", option(Some(LEVEL::Warn), Some(false)))
+        .set_mod_option("app::cache", option(None, Some(true)));
+    assert_eq!(log.module_levels(), vec![("app::db".to_string(), LEVEL::Debug), ("app::http::*".to_string(), LEVEL::Warn)]);
+    assert_eq!(log.get_level("app::http::server"), LEVEL::Warn);
+
+    assert!(log.clear_module_level("app::http::*"));
+    assert!(!log.clear_module_level("app::http::*"));
+    assert!(!log.clear_module_level("app::cache"));
+    assert!(!log.clear_module_level("app::unknown"));
+    assert_eq!(log.get_level("app::http::server"), LEVEL::Info);
+
+    assert!(log.clear_module_level("app::db"));
+    assert_eq!(log.get_level("app::db"), LEVEL::Info);
+    assert!(log.module_levels().is_empty());
+
+    log.set_mod_option("app::db", option(Some(LEVEL::Trace), None));
+    assert_eq!(log.get_level("app::db"), LEVEL::Trace);
+}
+
+#[test]
+fn test_module_levels_nested() {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Info).set_mod_level("my_app::db", LEVEL::Warn).set_mod_level("my_app::db::pool::*", LEVEL::Debug);
+    // The longest configured prefix wins.
+    assert_eq!(log.get_level("my_app::db"), LEVEL::Warn);
+    assert_eq!(log.get_level("my_app::db::query"), LEVEL::Warn);
+    assert_eq!(log.get_level("my_app::db::pool"), LEVEL::Warn);
+    assert_eq!(log.get_level("my_app::db::pool::conn"), LEVEL::Debug);
+    assert_eq!(log.get_level("my_app::dbx"), LEVEL::Info);
+    assert_eq!(log.get_level("my_app"), LEVEL::Info);
+
+    // The cache follows changes.
+    log.set_mod_level("my_app::db::pool", LEVEL::Error);
+    assert_eq!(log.get_level("my_app::db::pool"), LEVEL::Error);
+    assert!(log.clear_module_level("my_app::db"));
+    assert_eq!(log.get_level("my_app::db::query"), LEVEL::Info);
+    assert_eq!(log.get_level("my_app::db::pool::conn"), LEVEL::Debug);
+}
+
+#[test]
+fn test_module_levels_file_line() {
+    let mut log = Logger::new();
+    log.set_format(Format::LevelFlag);
+    log.set_mod_option("my_app::db", LogOption { level: None, format: Some(Format::LevelFlag | Format::ShortFileName), formatter: None, console: None, fileoption: None });
+    assert!(!log.is_file_line(LEVEL::Info, "my_app"));
+    assert!(log.is_file_line(LEVEL::Info, "my_app::db"));
+    assert!(log.is_file_line(LEVEL::Info, "my_app::db::pool::conn"));
+
+    // A level set later keeps the module's format.
+    log.set_mod_level("my_app::db", LEVEL::Trace);
+    assert!(log.is_file_line(LEVEL::Trace, "my_app::db::pool"));
+    assert_eq!(log.get_level("my_app::db::pool"), LEVEL::Trace);
+}
+
+#[tokio::test]
+async fn test_module_levels_async() {
+    let mut log = tklog::Async::Logger::new();
+    log.set_level(LEVEL::Info).set_mod_level("my_app::db", LEVEL::Debug);
+    assert_eq!(log.get_level("my_app::db::pool::conn"), LEVEL::Debug);
+    log.set_mod_level("my_app::db::pool::*", LEVEL::Error);
+    assert_eq!(log.get_level("my_app::db::pool::conn"), LEVEL::Error);
+    log.clear_module_level("my_app::db::pool::*");
+    assert_eq!(log.get_level("my_app::db::pool::conn"), LEVEL::Debug);
+}
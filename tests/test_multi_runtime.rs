@@ -0,0 +1,70 @@
+mod common;
+
+use std::{fs, thread, time::Duration};
+
+use tklog::{Async::Logger, Format, LEVEL};
+
+use common::logfile;
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build().unwrap()
+}
+
+fn line(log: &Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.log(LEVEL::Info, "app", s);
+}
+
+fn lines(path: &str) -> Vec<String> {
+    fs::read_to_string(path).unwrap_or_default().lines().map(str::to_string).collect()
+}
+
+// The consumer runs on the background runtime, which shuts down before the
+// runtime that still logs.
+#[test]
+fn test_worker_runtime_shuts_down_first() {
+    let path = logfile("first");
+    let (io, background) = (runtime(), runtime());
+    let mut log = Logger::with_runtime(background.handle());
+    assert!(log.is_started());
+    log.set_console(false).set_format(Format::LevelFlag);
+    io.block_on(log.set_cutmode_by_size(&path, 0, 0, false));
+
+    io.block_on(async {
+        line(&log, "before");
+        log.flush().await;
+    });
+    drop(background);
+    io.block_on(async {
+        line(&log, "after");
+        log.flush().await;
+    });
+    assert_eq!(lines(&path), ["[INFO] before", "[INFO] after"]);
+    assert!(io.block_on(log.health()).ok);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_worker_outlives_every_runtime() {
+    let path = logfile("none");
+    let rt = runtime();
+    let mut log = Logger::with_runtime(rt.handle());
+    log.set_console(false).set_format(Format::LevelFlag);
+    rt.block_on(log.set_cutmode_by_size(&path, 0, 0, false));
+    rt.block_on(async {
+        line(&log, "in the runtime");
+        log.flush().await;
+    });
+    drop(rt);
+
+    // No runtime at all: the consumer gets a thread of its own.
+    line(&log, "after the runtime");
+    for _ in 0..200 {
+        if lines(&path).len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(lines(&path), ["[INFO] in the runtime", "[INFO] after the runtime"]);
+    let _ = fs::remove_file(&path);
+}
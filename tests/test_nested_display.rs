@@ -0,0 +1,55 @@
+mod common;
+
+use std::{
+    fmt, fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{formats, info, infos, sync::Logger, Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+/// A value whose `Display` logs on `LOG`, as a lazily loaded value might.
+struct Loaded(&'static str);
+
+impl fmt::Display for Loaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        info!("loading ", self.0);
+        f.write_str(self.0)
+    }
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_nested_display_global() {
+    let path = logfile("global");
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+    info!("value ", Loaded("a"));
+    info!("with a field"; value = Loaded("b"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] loading a\n[INFO] value a\n[INFO] loading b\n[INFO] with a field value=b\n");
+    let _ = fs::remove_file(&path);
+}
+
+/// A value whose `Display` logs on the logger it was shown with.
+struct Shown(Arc<Mutex<Logger>>, &'static str);
+
+impl fmt::Display for Shown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut log = self.0.clone();
+        infos!(&mut log, "showing ", self.1);
+        f.write_str(self.1)
+    }
+}
+
+#[test]
+fn test_nested_display_multi() {
+    let path = logfile("multi");
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Info).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+    let mut logger = Arc::new(Mutex::new(log));
+    let (a, b) = (Shown(logger.clone(), "a"), Shown(logger.clone(), "b"));
+    infos!(&mut logger, "value ", a);
+    formats!(&mut logger, LEVEL::Warn, "formatted {}", b);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] showing a\n[INFO] value a\n[INFO] showing b\n[WARN] formatted b\n");
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::{
+    fs,
+    io::{self, BufRead, Read, Write},
+    path::PathBuf,
+};
+
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use tklog::compress::{open_any, Truncated};
+
+use common::dir;
+
+fn numbered(n: usize) -> String {
+    (0..n).map(|i| format!("line {}\n", i)).collect()
+}
+
+fn gz(data: &str) -> Vec<u8> {
+    let mut e = GzEncoder::new(Vec::new(), Compression::default());
+    e.write_all(data.as_bytes()).unwrap();
+    e.finish().unwrap()
+}
+
+fn read(path: &PathBuf) -> io::Result<String> {
+    let mut s = String::new();
+    open_any(path)?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+#[test]
+fn test_open_any_sniffs_by_content() {
+    let dir = dir("sniff");
+    let data = numbered(1000);
+    let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+    zlib.write_all(data.as_bytes()).unwrap();
+    // The names lie on purpose: the first bytes decide.
+    fs::write(dir.join("a.log"), gz(&data)).unwrap();
+    fs::write(dir.join("b.log.gz"), zlib.finish().unwrap()).unwrap();
+    fs::write(dir.join("c.log.gz"), &data).unwrap();
+    fs::write(dir.join("empty.log"), "").unwrap();
+    fs::write(dir.join("d.log.zst"), [0x28, 0xb5, 0x2f, 0xfd, 0, 0]).unwrap();
+
+    for name in ["a.log", "b.log.gz", "c.log.gz"] {
+        assert_eq!(read(&dir.join(name)).unwrap(), data, "{}", name);
+    }
+    assert_eq!(read(&dir.join("empty.log")).unwrap(), "");
+    assert_eq!(open_any(dir.join("d.log.zst")).err().unwrap().kind(), io::ErrorKind::Unsupported);
+    assert_eq!(open_any(dir.join("missing.log")).err().unwrap().kind(), io::ErrorKind::NotFound);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_truncated_archive_yields_prefix() {
+    let dir = dir("truncated");
+    let path = dir.join("app_1.log.gz");
+    let data = numbered(20000);
+    let archive = gz(&data);
+    fs::write(&path, &archive[..archive.len() / 2]).unwrap();
+
+    let mut lines = Vec::new();
+    let mut truncated = None;
+    for line in open_any(&path).unwrap().lines() {
+        match line {
+            Ok(line) => lines.push(line),
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+                truncated = Truncated::of(&e).cloned();
+                break;
+            }
+        }
+    }
+    let truncated = truncated.expect("no Truncated error");
+    assert_eq!(truncated.path, path);
+    assert!(lines.len() > 1000 && lines.len() < 20000, "{}", lines.len());
+    assert_eq!(lines.join("\n"), data.lines().take(lines.len()).collect::<Vec<_>>().join("\n"));
+    assert!(truncated.offset as usize >= lines.iter().map(|l| l.len() + 1).sum::<usize>());
+
+    // The tail of a previous run reads up to the cut.
+    fs::write(dir.join("app.log"), "").unwrap();
+    let tail = tklog::postmortem::previous_tail(dir.join("app.log"), 2).unwrap();
+    assert_eq!(tail, lines[lines.len() - 2..]);
+    let _ = fs::remove_dir_all(&dir);
+}
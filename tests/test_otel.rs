@@ -0,0 +1,38 @@
+#![cfg(feature = "otel")]
+
+use opentelemetry::{
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use tklog::{otel::current_ids, sync::Logger, Format, LEVEL};
+
+fn span(trace_id: u128, span_id: u64) -> Context {
+    let sc = SpanContext::new(TraceId::from(trace_id), SpanId::from(span_id), TraceFlags::SAMPLED, true, TraceState::default());
+    Context::current().with_remote_span_context(sc)
+}
+
+#[test]
+fn test_current_ids() {
+    assert_eq!(current_ids(), None);
+    {
+        let _guard = span(0x4bf92f3577b34da6a3ce929d0e0e4736, 0x00f067aa0ba902b7).attach();
+        assert_eq!(current_ids(), Some(("4bf92f3577b34da6a3ce929d0e0e4736".to_string(), "00f067aa0ba902b7".to_string())));
+        let _inner = span(0x4bf92f3577b34da6a3ce929d0e0e4736, 0x1).attach();
+        assert_eq!(current_ids().unwrap().1, "0000000000000001");
+    }
+    assert_eq!(current_ids(), None);
+}
+
+#[test]
+fn test_auto_trace_ids() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_auto_trace_ids(true);
+    assert_eq!(log.fmt("otel", LEVEL::Info, "", 0, "outside\n".to_string()).file_body, "outside\n");
+    let _guard = span(0xabc, 0xdef).attach();
+    assert_eq!(
+        log.fmt("otel", LEVEL::Info, "", 0, "inside\n".to_string()).file_body,
+        "inside trace_id=00000000000000000000000000000abc span_id=0000000000000def\n"
+    );
+    log.set_auto_trace_ids(false);
+    assert_eq!(log.fmt("otel", LEVEL::Info, "", 0, "off\n".to_string()).file_body, "off\n");
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::fs;
+
+use tklog::{output::OutputSink, sync::Logger, Format, LEVEL, PRINTMODE};
+
+use common::logfile;
+
+#[test]
+fn test_output_level_gate() {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Info).set_output_level(OutputSink::Console, LEVEL::Warn).set_output_level(OutputSink::File, LEVEL::Trace);
+    assert_eq!(log.get_level("app"), LEVEL::Trace);
+    assert_eq!(log.output_level(OutputSink::Console), Some(LEVEL::Warn));
+
+    // A console that is off doesn't count.
+    log.set_output_level(OutputSink::Console, LEVEL::Trace).set_output_level(OutputSink::File, LEVEL::Error).set_console(false);
+    assert_eq!(log.get_level("app"), LEVEL::Error);
+
+    // A sink without a level follows the module.
+    log.clear_output_level(OutputSink::File);
+    assert_eq!(log.get_level("app"), LEVEL::Info);
+    log.clear_output_level(OutputSink::Console).set_console(true);
+    assert_eq!(log.get_level("app"), LEVEL::Info);
+}
+
+#[test]
+fn test_output_level_file() {
+    for mode in [PRINTMODE::PUNCTUAL, PRINTMODE::DELAY] {
+        let file = logfile(&format!("{:?}", mode));
+        let mut log = Logger::new();
+        log.set_console(false).set_printmode(mode).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false);
+        log.set_level(LEVEL::Warn).set_output_level(OutputSink::Console, LEVEL::Error).set_output_level(OutputSink::File, LEVEL::Debug);
+        for level in [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Error] {
+            if log.get_level("app") <= level {
+                let s = log.fmt("app", level, "", 0, format!("{:?}", level));
+                log.print(level, "app", s);
+            }
+        }
+        log.flush();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "[DEBUG] Debug\n[INFO] Info\n[ERROR] Error\n", "{:?}", mode);
+        let _ = fs::remove_file(&file);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_output_level_async() {
+    let file = logfile("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(&file, 0, 0, false).await;
+    log.set_output_level(OutputSink::File, LEVEL::Error);
+    for level in [LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal] {
+        if log.get_level("app") <= level {
+            let s = log.fmt("app", level, "", 0, format!("{:?}", level));
+            log.log(level, "app", s);
+        }
+    }
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[ERROR] Error\n[FATAL] Fatal\n");
+    let _ = fs::remove_file(&file);
+}
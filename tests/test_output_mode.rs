@@ -0,0 +1,52 @@
+use tklog::{
+    output::{OutputMode, OutputSink},
+    sync::Logger,
+    Error, LEVEL,
+};
+
+const MODES: [OutputMode; 4] = [OutputMode::Text, OutputMode::Json, OutputMode::Logfmt, OutputMode::Delimited];
+
+#[test]
+fn test_conflicting_pairs() {
+    for active in MODES {
+        for requested in MODES {
+            let mut log = Logger::new();
+            log.set_output_mode(OutputSink::Console, active).unwrap();
+            let r = log.set_output_mode(OutputSink::Console, requested).map(|_| ());
+            if active == requested {
+                assert!(r.is_ok(), "{} then {}", active, requested);
+            } else {
+                assert!(matches!(r, Err(Error::ConflictingMode { active: a, requested: q }) if a == active && q == requested), "{} then {}", active, requested);
+            }
+            // The file is a sink of its own.
+            assert!(log.set_output_mode(OutputSink::File, requested).is_ok());
+            log.clear_output_mode(OutputSink::Console);
+            assert!(log.set_output_mode(OutputSink::Console, requested).is_ok());
+        }
+    }
+}
+
+#[test]
+fn test_presets_set_modes() {
+    let mut log = Logger::new();
+    log.preset_k8s();
+    assert_eq!(log.validate().unwrap(), vec![(OutputSink::Console, OutputMode::Json), (OutputSink::File, OutputMode::Json)]);
+    assert!(log.describe().contains("output: console json, file json\n"));
+    let e = log.set_output_mode(OutputSink::Console, OutputMode::Delimited).map(|_| ()).unwrap_err();
+    assert_eq!(e.to_string(), "output mode refused: delimited requested while json is active");
+
+    log.preset_systemd();
+    assert_eq!(log.validate().unwrap()[0], (OutputSink::Console, OutputMode::Text));
+}
+
+#[test]
+fn test_validate_text_settings() {
+    let mut log = Logger::new();
+    log.set_formatter("{level} {message}\n").set_attr_format(|fmt| fmt.set_console_body_fmt(|_, body| format!("\x1b[31m{}\x1b[0m", body)));
+    assert_eq!(log.validate().unwrap(), vec![(OutputSink::Console, OutputMode::Text), (OutputSink::File, OutputMode::Text)]);
+
+    log.preset_k8s();
+    assert!(log.validate().is_ok(), "the preset drops the text settings");
+    log.set_attr_format(|fmt| fmt.set_console_body_fmt(|level, body| if level == LEVEL::Error { format!("\x1b[31m{}\x1b[0m", body) } else { body }));
+    assert!(matches!(log.validate(), Err(Error::ConflictingMode { active: OutputMode::Json, requested: OutputMode::Text })));
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use std::{fs, thread};
+
+use tklog::{Format, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+fn panic_on(name: &str, f: impl FnOnce() + Send + 'static) {
+    assert!(thread::Builder::new().name(name.to_string()).spawn(f).unwrap().join().is_err());
+}
+
+// One test: the hook is the process's.
+#[test]
+fn test_panic_hook() {
+    let path = logfile("sync");
+    LOG.set_console(false)
+        .set_printmode(PRINTMODE::DELAY)
+        .set_format(Format::LevelFlag | Format::ShortFileName)
+        .set_cutmode_by_size(&path, 0, 0, false);
+    tklog::log_panics();
+    tklog::info!("before");
+    panic_on("worker", || panic!("boom {}", 7));
+    let lines = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert!(lines[0].starts_with("[INFO] ") && lines[0].ends_with(":before"), "{}", lines[0]);
+    assert!(lines[1].starts_with("[FATAL] test_panic_hook.rs "), "{}", lines[1]);
+    assert!(lines[1].contains(":thread 'worker' panicked at tests/test_panic_hook.rs:"), "{}", lines[1]);
+    assert!(lines[1].ends_with(": boom 7"), "{}", lines[1]);
+
+    // With the logger held by the panicking thread the line goes to stderr.
+    panic_on("holder", || {
+        let _logger = tklog::global();
+        panic!("held");
+    });
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+    let path = logfile("async");
+    let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build().unwrap();
+    rt.block_on(async {
+        tklog::ASYNC_LOG
+            .set_console(false)
+            .set_level(LEVEL::Info)
+            .set_format(Format::LevelFlag)
+            .set_cutmode_by_size(&path, 0, 0, false)
+            .await;
+        tklog::log_panics_async();
+        assert!(tokio::spawn(async { panic!("async boom") }).await.is_err());
+    });
+    let lines = fs::read_to_string(&path).unwrap();
+    assert!(lines.starts_with("[FATAL] thread 'tokio-rt-worker' panicked at tests/test_panic_hook.rs:"), "{}", lines);
+    assert!(lines.ends_with(": async boom\n"), "{}", lines);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,153 @@
+use std::{
+    fmt, fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tklog::{infos, sync::Logger, Format, LogContext, RotationEvent, LEVEL};
+
+fn logger() -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    log
+}
+
+fn line(log: &mut Logger, i: usize) -> String {
+    log.fmt("panics", LEVEL::Info, "", 0, format!("line {}", i)).file_body
+}
+
+static FILTER_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn panicking_filter(_: &LogContext) -> bool {
+    FILTER_CALLS.fetch_add(1, Ordering::SeqCst);
+    panic!("filter bug");
+}
+
+#[test]
+fn test_panicking_custom_handler() {
+    let mut log = logger();
+    log.set_custom_handler(panicking_filter);
+    for i in 0..5 {
+        assert_eq!(line(&mut log, i), format!("[INFO] line {}\n", i));
+    }
+    assert_eq!(FILTER_CALLS.load(Ordering::SeqCst), 3, "disabled after three panics in a row");
+    log.set_custom_handler(panicking_filter);
+    line(&mut log, 5);
+    assert_eq!(FILTER_CALLS.load(Ordering::SeqCst), 4, "setting it again re-enables it");
+}
+
+#[test]
+fn test_panicking_formatters() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut log = logger();
+    log.set_format(Format::LevelFlag | Format::Date);
+    let c = calls.clone();
+    log.set_attr_format(move |fmt| {
+        let c1 = c.clone();
+        fmt.set_level_fmt(move |_| {
+            c1.fetch_add(1, Ordering::SeqCst);
+            panic!("level format bug")
+        });
+        let c2 = c.clone();
+        fmt.set_time_fmt(move || {
+            c2.fetch_add(1, Ordering::SeqCst);
+            panic!("time format bug")
+        });
+        let c3 = c.clone();
+        fmt.set_file_body_fmt(move |_, _| {
+            c3.fetch_add(1, Ordering::SeqCst);
+            panic!("file body format bug")
+        });
+        let c4 = c.clone();
+        fmt.set_console_body_fmt(move |_, _| {
+            c4.fetch_add(1, Ordering::SeqCst);
+            panic!("console body format bug")
+        });
+    });
+    for i in 0..5 {
+        let s = log.fmt("panics", LEVEL::Warn, "", 0, format!("line {}", i));
+        assert!(s.file_body.starts_with("[WARN] 20") && s.file_body.ends_with(&format!(" line {}\n", i)), "{}", s.file_body);
+        assert_eq!(s.console_body.as_deref(), Some(s.file_body.as_str()));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 4 * 3);
+}
+
+#[test]
+fn test_panicking_record_formatter_and_fields() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut log = logger();
+    let c = calls.clone();
+    log.set_record_formatter(Box::new(move |_| {
+        c.fetch_add(1, Ordering::SeqCst);
+        panic!("record formatter bug")
+    }));
+    let c = calls.clone();
+    log.set_dynamic_fields(Box::new(move |_| {
+        c.fetch_add(1, Ordering::SeqCst);
+        panic!("fields bug")
+    }));
+    for i in 0..5 {
+        assert_eq!(line(&mut log, i), format!("[INFO] line {}\n", i));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2 * 3);
+}
+
+struct PanicsOnDisplay;
+
+impl fmt::Display for PanicsOnDisplay {
+    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        panic!("display bug")
+    }
+}
+
+#[test]
+fn test_poisoned_logger_keeps_logging() {
+    let path = std::env::temp_dir().join(format!("tklog_panics_poison_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = logger();
+    log.set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    let mut logger = Arc::new(Mutex::new(log));
+    let mut inner = logger.clone();
+    assert!(catch_unwind(AssertUnwindSafe(|| infos!(&mut inner, PanicsOnDisplay))).is_err());
+    assert!(!logger.is_poisoned(), "the message is built without the lock");
+    assert!(catch_unwind(AssertUnwindSafe(|| {
+        let _held = inner.lock().unwrap();
+        panic!("panicked holding the logger")
+    }))
+    .is_err());
+    assert!(logger.is_poisoned());
+    infos!(&mut logger, "still logging\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "still logging\n");
+    let _ = fs::remove_file(&path);
+}
+
+static ROTATIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn panicking_rotation(_: &RotationEvent) {
+    ROTATIONS.fetch_add(1, Ordering::SeqCst);
+    panic!("rotation hook bug");
+}
+
+#[test]
+fn test_panicking_rotation_handler() {
+    let dir = std::env::temp_dir().join(format!("tklog_panics_rotation_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("rotate.log");
+    let mut log = logger();
+    log.set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 16, 0, false);
+    log.set_rotation_handler(panicking_rotation);
+    let mut logger = Arc::new(Mutex::new(log));
+    for i in 0..6 {
+        infos!(&mut logger, "sixteen bytes ", i, "\n");
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(ROTATIONS.load(Ordering::SeqCst), 3, "disabled after three panics in a row");
+    let backups = fs::read_dir(&dir).unwrap().count() - 1;
+    assert!(backups >= 5, "rotation stopped after the hook panicked: {} backups", backups);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{logerror::LogError, sync::Logger, Format, LEVEL, MODE, PRINTMODE};
+
+use common::missing_dir;
+
+fn logger() -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano);
+    log
+}
+
+fn write(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_parent_dirs_created() {
+    let dir = missing_dir("sync");
+    let path = dir.join("logs/app/app.log");
+    let mut log = logger();
+    log.set_cutmode_by_time(path.to_str().unwrap(), MODE::DAY, 0, false);
+    write(&mut log, "first");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// A file without a directory is opened where the process runs.
+#[test]
+fn test_parent_dirs_relative() {
+    let name = format!("tklog_parent_dirs_{}.log", std::process::id());
+    let mut log = logger();
+    log.set_cutmode_by_size(&name, 0, 0, false);
+    write(&mut log, "here");
+    assert_eq!(fs::read_to_string(&name).unwrap(), "here");
+    let _ = fs::remove_file(&name);
+}
+
+#[test]
+fn test_parent_dirs_failed() {
+    let dir = missing_dir("failed");
+    fs::create_dir_all(&dir).unwrap();
+    // A file where the directory should be.
+    let blocker = dir.join("blocker");
+    fs::write(&blocker, "").unwrap();
+    let path = blocker.join("sub/app.log");
+
+    let seen: Arc<Mutex<Vec<String>>> = Arc::default();
+    let s = seen.clone();
+    let mut log = logger();
+    log.set_error_handler(Box::new(move |e: LogError| {
+        assert!(matches!(e, LogError::OpenFailed(..)));
+        s.lock().unwrap().push(e.to_string());
+    }));
+    log.set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    write(&mut log, "lost");
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    let expected = format!("cannot open {}: cannot create the directory {}:", path.display(), blocker.join("sub").display());
+    assert!(seen[0].starts_with(&expected), "{}", seen[0]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_parent_dirs_async() {
+    let dir = missing_dir("async");
+    let path = dir.join("logs/app/app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano);
+    log.set_cutmode_by_time(path.to_str().unwrap(), MODE::DAY, 0, false).await;
+    let s = log.fmt("app", LEVEL::Info, "", 0, "first".to_string());
+    log.print(LEVEL::Info, "app", s).await;
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+    let _ = fs::remove_dir_all(&dir);
+}
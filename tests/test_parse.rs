@@ -0,0 +1,113 @@
+use chrono::{Local, TimeZone};
+use tklog::{
+    parse::{parse_line, Parser},
+    sync::Logger,
+    Format, TestMode, LEVEL,
+};
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn string(&mut self, alphabet: &[u8], max: u64) -> String {
+        let len = 1 + self.below(max);
+        (0..len).map(|_| alphabet[self.below(alphabet.len() as u64) as usize] as char).collect()
+    }
+}
+
+const LEVELS: [LEVEL; 6] = [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal];
+const MSG_CHARS: &[u8] = b"abcXYZ019 :[]{}|,.-_";
+const FILE_CHARS: &[u8] = b"abcdef_019";
+
+fn formats() -> Vec<u8> {
+    vec![
+        Format::Nano,
+        Format::LevelFlag,
+        Format::LevelFlag | Format::Date,
+        Format::LevelFlag | Format::Time | Format::ShortFileName,
+        Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName,
+        Format::LevelFlag | Format::Date | Format::Microseconds | Format::LongFileName,
+        Format::Date | Format::Time,
+        Format::Microseconds | Format::ShortFileName,
+    ]
+}
+
+fn check(format: u8, formatter: Option<&str>, rng: &mut XorShift) {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(format);
+    if let Some(f) = formatter {
+        log.set_formatter(f);
+    }
+    log.set_test_mode(TestMode {
+        fixed_time: Local.with_ymd_and_hms(2024, 5, 1, 12, 30, 45).unwrap(),
+        fixed_seq_start: 1,
+    })
+    .unwrap();
+    let parser = match formatter {
+        Some(f) => Parser::new((format, f)),
+        None => Parser::new(format),
+    };
+
+    for _ in 0..200 {
+        let level = LEVELS[rng.below(6) as usize];
+        let file = format!("src/{}/{}.rs", rng.string(FILE_CHARS, 6), rng.string(FILE_CHARS, 10));
+        let line = rng.below(10000) as u32;
+        let message = rng.string(MSG_CHARS, 40);
+        let rendered = log.fmt("parse", level, &file, line, message.clone()).file_body;
+        let r = parser.parse(&rendered).unwrap_or_else(|| panic!("{:?} did not parse: {:?}", formatter, rendered));
+
+        let has = |name: &str| formatter.map_or(true, |f| f.contains(name));
+        assert_eq!(r.message, message, "{:?}", rendered);
+        if format & Format::LevelFlag != 0 && has("{level}") {
+            assert_eq!(r.level, Some(level));
+        }
+        if format & (Format::LongFileName | Format::ShortFileName) != 0 && has("{file}") {
+            let expect = if format & Format::ShortFileName != 0 { file.rsplit('/').next().unwrap().to_string() } else { file.clone() };
+            assert_eq!(r.file, Some(expect));
+            assert_eq!(r.line, Some(line));
+        }
+        if format & Format::Date != 0 && has("{time}") {
+            assert!(r.time.as_ref().unwrap().starts_with("2024-05-01"));
+        }
+    }
+}
+
+#[test]
+fn test_roundtrip_default_layout() {
+    let mut rng = XorShift(0x9E3779B97F4A7C15);
+    for format in formats() {
+        check(format, None, &mut rng);
+    }
+}
+
+#[test]
+fn test_roundtrip_templates() {
+    let mut rng = XorShift(0xD1B54A32D192ED03);
+    let templates = ["{level}{time} {file}:{message}\n", "{message} | {time} {file}{level}\n", "{seq} {level} {time} {message}\n", "{time}|{level}|{file}|{message}"];
+    for format in formats() {
+        for t in templates {
+            check(format, Some(t), &mut rng);
+        }
+    }
+}
+
+#[test]
+fn test_parse_line() {
+    let r = parse_line("[WARN] 2024-05-01 12:00:00.123456 main.rs 7:disk 90% full", Format::LevelFlag | Format::Date | Format::Microseconds | Format::ShortFileName).unwrap();
+    assert_eq!(r.level, Some(LEVEL::Warn));
+    assert_eq!(r.time.as_deref(), Some("2024-05-01 12:00:00.123456"));
+    assert_eq!(r.file.as_deref(), Some("main.rs"));
+    assert_eq!(r.line, Some(7));
+    assert_eq!(r.message, "disk 90% full");
+    assert!(parse_line("garbage", Format::LevelFlag | Format::Date).is_none());
+}
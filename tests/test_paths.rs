@@ -0,0 +1,84 @@
+mod common;
+
+use std::fs;
+
+use tklog::{
+    handle::{FileSizeMode, FileTimeMode},
+    sync::Logger,
+    Error, Format, LogOption, LEVEL, LOG, MODE,
+};
+
+use common::dir;
+
+fn module(option: FileSizeMode) -> LogOption {
+    LogOption { level: None, format: None, formatter: None, console: None, fileoption: Some(Box::new(option)) }
+}
+
+fn emit(log: &mut Logger, module: &str, msg: &str) {
+    let s = log.fmt(module, LEVEL::Info, "", 0, msg.to_string());
+    log.print(LEVEL::Info, module, s);
+}
+
+#[test]
+fn test_matching_settings_share_a_handler() {
+    let dir = dir("match");
+    let a = dir.join("shared.log");
+    let b = format!("{}/./shared.log", dir.display());
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_cutmode_by_size(dir.join("app.log").to_str().unwrap(), 0, 0, false);
+    log.set_mod_option("a", module(FileSizeMode::new(a.to_str().unwrap(), 1 << 20, 3, false)));
+    log.set_mod_option("b", module(FileSizeMode::new(&b, 1 << 20, 3, false)));
+    emit(&mut log, "a", "from a\n");
+    emit(&mut log, "b", "from b\n");
+    assert!(log.check_paths().is_ok());
+    assert_eq!(fs::read_to_string(&a).unwrap(), "from a\nfrom b\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_mismatched_settings_conflict() {
+    let dir = dir("mismatch");
+    let shared = dir.join("shared.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_cutmode_by_size(dir.join("app.log").to_str().unwrap(), 0, 0, false);
+    log.set_mod_option("a", module(FileSizeMode::new(shared.to_str().unwrap(), 1 << 20, 3, false)));
+    log.set_level_option(LEVEL::Error, &LogOption { level: None, format: None, formatter: None, console: None, fileoption: Some(Box::new(FileTimeMode::new(shared.to_str().unwrap(), MODE::DAY, 3, false))) });
+    match log.check_paths() {
+        Err(Error::PathConflict { path, first, second }) => {
+            assert_eq!(path, fs::canonicalize(&shared).unwrap());
+            assert_eq!(first, "module `a`");
+            assert_eq!(second, "level Error");
+        }
+        r => panic!("{:?}", r),
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_to_the_same_file() {
+    let dir = dir("symlink");
+    let target = dir.join("real.log");
+    fs::write(&target, "").unwrap();
+    let link = dir.join("link.log");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_cutmode_by_size(target.to_str().unwrap(), 1 << 20, 0, false);
+    log.add_file_sink("audit", FileSizeMode::new(link.to_str().unwrap(), 1 << 10, 0, false));
+    assert!(matches!(log.check_paths(), Err(Error::PathConflict { first, .. }) if first == "default file"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_global_loggers_check_each_other() {
+    let dir = dir("global");
+    let path = dir.join("both.log");
+    LOG.set_console(false).set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false);
+    tklog::ASYNC_LOG.set_console(false).set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false).await;
+    assert!(LOG.check_paths().is_ok());
+    match tklog::ASYNC_LOG.check_paths() {
+        Err(Error::PathConflict { first, second, .. }) => assert_eq!((first.as_str(), second.as_str()), ("global sync logger, default file", "default file")),
+        r => panic!("{:?}", r),
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,148 @@
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, TimeZone};
+use tklog::{
+    clear,
+    clock::ManualClock,
+    logsink::LogSink,
+    persist::MAX_PENDING,
+    sync::Logger,
+    warn_if_persists, Format, LEVEL, LOG, PRINTMODE,
+};
+
+#[derive(Clone, Default)]
+struct Lines(Arc<Mutex<Vec<String>>>);
+
+impl LogSink for Lines {
+    fn write(&mut self, _: LEVEL, formatted: &str) {
+        self.0.lock().unwrap().push(formatted.to_string());
+    }
+
+    fn flush(&mut self) {}
+}
+
+impl Lines {
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+fn start() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()
+}
+
+fn logger() -> (Logger, Arc<ManualClock>, Lines) {
+    let clock = Arc::new(ManualClock::at(start()));
+    let lines = Lines::default();
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_separator(" ");
+    log.set_clock(clock.clone()).set_custom_sink(Box::new(lines.clone())).set_custom_sink_only(true);
+    (log, clock, lines)
+}
+
+fn arm(log: &mut Logger, key: &str, ms: u32) {
+    log.log_if_persists(key, Duration::from_secs(5), LEVEL::Warn, "app", "", 0, || format!("db latency {}ms", ms));
+}
+
+#[test]
+fn test_persist_expires() {
+    let (mut log, clock, lines) = logger();
+    arm(&mut log, "db_slow", 900);
+    clock.advance(Duration::from_secs(3));
+    // Armed already: the first message is kept.
+    arm(&mut log, "db_slow", 1200);
+    assert_eq!(log.expire_persisting(), Some(Duration::from_secs(2)));
+    assert!(lines.take().is_empty());
+
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(log.expire_persisting(), None);
+    assert_eq!(lines.take(), [format!("[WARN] db latency 900ms since={}\n", start().format("%Y-%m-%dT%H:%M:%S%.3f%:z"))]);
+
+    // Expired, the key can be armed again.
+    arm(&mut log, "db_slow", 1500);
+    assert_eq!(log.expire_persisting(), Some(Duration::from_secs(5)));
+}
+
+#[test]
+fn test_persist_cleared() {
+    let (mut log, clock, lines) = logger();
+    arm(&mut log, "db_slow", 900);
+    arm(&mut log, "disk_full", 0);
+    clock.advance(Duration::from_secs(4));
+    assert!(log.clear_persisting("db_slow"));
+    assert!(!log.clear_persisting("db_slow"));
+    assert!(!log.clear_persisting("never_armed"));
+
+    // Due, the other key is logged by the next line.
+    clock.advance(Duration::from_secs(1));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "next".to_string());
+    log.print(LEVEL::Info, "app", s);
+    let got = lines.take();
+    assert_eq!(got.len(), 2, "{:?}", got);
+    assert!(got[0].starts_with("[WARN] db latency 0ms since="), "{:?}", got);
+    assert_eq!(got[1], "[INFO] next\n");
+    assert_eq!(log.expire_persisting(), None);
+}
+
+#[test]
+fn test_persist_bounded() {
+    let (mut log, _clock, lines) = logger();
+    for i in 0..MAX_PENDING {
+        arm(&mut log, &format!("key{}", i), 0);
+    }
+    assert!(lines.take().is_empty());
+    // No room left: logged right away.
+    arm(&mut log, "one_more", 7);
+    assert_eq!(lines.take().len(), 1);
+    assert!(log.clear_persisting("key0"));
+    arm(&mut log, "one_more", 7);
+    assert!(lines.take().is_empty());
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_persist_global() {
+    let path = std::env::temp_dir().join(format!("tklog_persist_global_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&path.to_string_lossy(), 0, 0, false);
+    let ms = 900;
+    warn_if_persists!(Duration::from_millis(50), key: "db_slow", "db latency {}ms", ms);
+    warn_if_persists!(Duration::from_secs(60), key: "cleared", "never logged");
+    assert!(clear!("cleared"));
+    assert!(!clear!("cleared"));
+
+    // The ticker logs it without another line.
+    let mut s = String::new();
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(20));
+        s = fs::read_to_string(&path).unwrap_or_default();
+        if !s.is_empty() {
+            break;
+        }
+    }
+    assert!(s.starts_with("[WARN] db latency 900ms since="), "{}", s);
+    assert!(!s.contains("never logged"));
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_persist_async() {
+    let clock = Arc::new(ManualClock::at(start()));
+    let lines = Lines::default();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_clock(clock.clone()).set_custom_sink(Box::new(lines.clone())).set_custom_sink_only(true);
+    log.log_if_persists("db_slow", Duration::from_secs(5), LEVEL::Warn, "app", "", 0, || "db slow".to_string());
+    log.log_if_persists("cleared", Duration::from_secs(5), LEVEL::Warn, "app", "", 0, || "never logged".to_string());
+    assert!(log.clear_persisting("cleared"));
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(log.expire_persisting(), None);
+    log.flush().await;
+    let got = lines.take();
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert!(got[0].starts_with("[WARN] db slow since="), "{:?}", got);
+}
@@ -0,0 +1,47 @@
+use tklog::{sync::Logger, Format, LEVEL};
+
+fn line(formatter: &str, level: LEVEL, file: &str) -> String {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::LongFileName).set_formatter(formatter);
+    log.fmt("app", level, file, 3, "m".to_string()).file_body
+}
+
+#[test]
+fn test_placeholder_spec() {
+    assert_eq!(line("{level:<8}|{message}", LEVEL::Info, "a.rs"), "[INFO]  |m");
+    assert_eq!(line("{level:<8}|{message}", LEVEL::Error, "a.rs"), "[ERROR] |m");
+    assert_eq!(line("{level:>8}|", LEVEL::Info, "a.rs"), "  [INFO]|");
+    assert_eq!(line("{level:*^10}|", LEVEL::Info, "a.rs"), "**[INFO]**|");
+    assert_eq!(line("{level:8}|", LEVEL::Info, "a.rs"), "[INFO]  |");
+    // Truncating a file keeps its tail, so that the file name and line survive.
+    assert_eq!(line("{file:.12}|", LEVEL::Info, "src/server/handler.rs"), "handler.rs 3|");
+    assert_eq!(line("{file:>14.12}|", LEVEL::Info, "src/server/handler.rs"), "  handler.rs 3|");
+    assert_eq!(line("{message:.0}|{level:.2}|", LEVEL::Info, "a.rs"), "|[I|");
+    // Width and precision count chars.
+    let mut log = Logger::new();
+    log.set_console(false).set_formatter("{message:-<6.4}|");
+    assert_eq!(log.fmt("app", LEVEL::Info, "", 0, "ßßßßß".to_string()).file_body, "ßßßß--|");
+}
+
+#[test]
+fn test_placeholder_spec_malformed() {
+    // A modifier tklog can't read leaves the value as it is.
+    for spec in ["title", "<x", ".", "5.x", "<<<", "-5", "+5"] {
+        assert_eq!(line(&format!("{{level:{}}}|", spec), LEVEL::Info, "a.rs"), "[INFO]|", "{}", spec);
+    }
+    assert_eq!(line("{level:}|", LEVEL::Info, "a.rs"), "[INFO]|");
+}
+
+#[test]
+fn test_placeholder_braces() {
+    assert_eq!(line("{{level}}|{{{level}}}", LEVEL::Info, "a.rs"), "{level}|{[INFO]}");
+    assert_eq!(line("{{", LEVEL::Info, "a.rs"), "{");
+    // A brace inside a placeholder starts it again.
+    assert_eq!(line("{a{level}|{x{y}", LEVEL::Info, "a.rs"), "{a[INFO]|{x{y}");
+    // Placeholders at the ends of the template, and one left open.
+    assert_eq!(line("{level}", LEVEL::Info, "a.rs"), "[INFO]");
+    assert_eq!(line("{message}{level}", LEVEL::Info, "a.rs"), "m[INFO]");
+    assert_eq!(line("{level}|{mess", LEVEL::Info, "a.rs"), "[INFO]|{mess");
+    assert_eq!(line("{", LEVEL::Info, "a.rs"), "{");
+    assert_eq!(line("}|{unknown:<5}", LEVEL::Info, "a.rs"), "}|{unknown:<5}");
+}
@@ -0,0 +1,53 @@
+use std::thread;
+
+use tklog::{sync::Logger, Format, LEVEL};
+
+fn logger(formatter: &str) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::ShortFileName).set_formatter(formatter);
+    log
+}
+
+#[test]
+fn test_module_line_pid() {
+    let mut log = logger("{level} {module} {file}|{line} pid={pid}: {message}\n");
+    let s = log.fmt("app::db", LEVEL::Info, "src/db.rs", 42, "connected".to_string()).file_body;
+    assert_eq!(s, format!("[INFO] app::db db.rs|42 pid={}: connected\n", std::process::id()));
+
+    // Without `{line}`, `{file}` keeps the line.
+    let mut log = logger("{file}:{message}\n");
+    assert_eq!(log.fmt("app", LEVEL::Info, "src/db.rs", 42, "m".to_string()).file_body, "db.rs 42:m\n");
+
+    // No file name in the format, no line either.
+    let mut log = logger("[{file}|{line}] {message}\n");
+    log.set_format(Format::LevelFlag);
+    assert_eq!(log.fmt("app", LEVEL::Info, "src/db.rs", 42, "m".to_string()).file_body, "[|] m\n");
+}
+
+#[test]
+fn test_thread() {
+    let s = thread::Builder::new()
+        .name("worker-7".to_string())
+        .spawn(|| logger("{thread} {message}\n").fmt("app", LEVEL::Info, "", 0, "m".to_string()).file_body)
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(s, "worker-7 m\n");
+
+    let s = thread::spawn(|| logger("{thread}").fmt("app", LEVEL::Info, "", 0, "m".to_string()).file_body).join().unwrap();
+    assert!(!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()), "{}", s);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_thread_async() {
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_formatter("{thread}|{module}|{message}\n");
+    let s = thread::Builder::new().name("caller".to_string()).spawn(move || log.fmt("app::jobs", LEVEL::Info, "", 0, "m".to_string()).file_body).unwrap().join().unwrap();
+    assert_eq!(s, "caller|app::jobs|m\n");
+}
+
+#[test]
+fn test_unknown_placeholder_kept() {
+    let mut log = logger("{level} {mesage} {message} {}\n");
+    assert_eq!(log.fmt("app", LEVEL::Warn, "", 0, "m".to_string()).file_body, "[WARN] {mesage} m {}\n");
+}
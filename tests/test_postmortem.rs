@@ -0,0 +1,51 @@
+mod common;
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    time::{Duration, SystemTime},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use tklog::postmortem::previous_tail;
+
+use common::dir;
+
+fn numbered(range: std::ops::Range<usize>) -> String {
+    range.map(|i| format!("line {} {}\n", i, "x".repeat(i % 50))).collect()
+}
+
+#[test]
+fn test_tail_of_live_file() {
+    let dir = dir("live");
+    let path = dir.join("app.log");
+    fs::write(&path, numbered(0..5000)).unwrap();
+    let tail = previous_tail(&path, 3).unwrap();
+    assert_eq!(tail, vec![format!("line 4997 {}", "x".repeat(47)), format!("line 4998 {}", "x".repeat(48)), format!("line 4999 {}", "x".repeat(49))]);
+    assert_eq!(previous_tail(&path, 10000).unwrap().len(), 5000);
+
+    fs::write(&path, "no trailing break\nlast").unwrap();
+    assert_eq!(previous_tail(&path, 1).unwrap(), vec!["last"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_tail_falls_back_to_newest_backup() {
+    let dir = dir("backup");
+    let path = dir.join("app.log");
+    File::create(&path).unwrap();
+    let old = dir.join("app_1.log");
+    fs::write(&old, numbered(0..10)).unwrap();
+    File::options().write(true).open(&old).unwrap().set_modified(SystemTime::now() - Duration::from_secs(60)).unwrap();
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(numbered(10..20).as_bytes()).unwrap();
+    fs::write(dir.join("app_2.log.gz"), gz.finish().unwrap()).unwrap();
+
+    assert_eq!(previous_tail(&path, 2).unwrap(), vec![format!("line 18 {}", "x".repeat(18)), format!("line 19 {}", "x".repeat(19))]);
+    fs::remove_file(dir.join("app_2.log.gz")).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(previous_tail(&path, 1).unwrap(), vec![format!("line 9 {}", "x".repeat(9))]);
+    fs::remove_file(&old).unwrap();
+    assert!(previous_tail(&path, 1).unwrap().is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,70 @@
+use chrono::{TimeZone, Utc};
+use tklog::{Format, TestMode, LEVEL};
+
+#[test]
+fn test_preset_k8s() {
+    std::env::set_var("POD_NAME", "api-7d4b9");
+    std::env::set_var("POD_NAMESPACE", "shop");
+    let file = std::env::temp_dir().join(format!("tklog_preset_{}.log", std::process::id()));
+    let mut log = tklog::sync::Logger::new();
+    log.set_cutmode_by_size(file.to_str().unwrap(), 0, 0, false).set_level(LEVEL::Trace).set_format(Format::Nano);
+    log.preset_k8s();
+    log.set_test_mode(TestMode {
+        fixed_time: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap().into(),
+        fixed_seq_start: 1,
+    })
+    .unwrap();
+
+    assert_eq!(log.get_level("app::db"), LEVEL::Info);
+    assert_eq!(
+        log.fmt("app::db", LEVEL::Warn, "src/db.rs", 42, "slow \"query\"\n".to_string()).file_body,
+        "{\"v\":2,\"ts\":\"2024-05-01T12:00:00.000000Z\",\"level\":\"warn\",\"msg\":\"slow \\\"query\\\"\",\"caller\":\"src/db.rs:42\",\"logger\":\"app::db\",\"pod\":\"api-7d4b9\",\"namespace\":\"shop\"}\n"
+    );
+
+    let described = log.describe();
+    assert!(described.contains("preset: k8s\n"), "{}", described);
+    assert!(described.contains("console: true"), "{}", described);
+    assert!(described.contains("file: none\n"), "{}", described);
+    let _ = std::fs::remove_file(&file);
+}
+
+fn systemd_logger() -> tklog::sync::Logger {
+    let mut log = tklog::sync::Logger::new();
+    log.set_level(LEVEL::Trace).set_format(Format::LevelFlag | Format::Date | Format::Time).set_formatter("{level}{time} {message}\n");
+    log.preset_systemd();
+    log.set_test_mode(TestMode {
+        fixed_time: chrono::Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+        fixed_seq_start: 1,
+    })
+    .unwrap();
+    log
+}
+
+#[test]
+fn test_preset_systemd_priorities() {
+    let mut log = systemd_logger();
+    for (level, console) in [
+        (LEVEL::Trace, "<7>[TRACE] up\n"),
+        (LEVEL::Debug, "<7>[DEBUG] up\n"),
+        (LEVEL::Info, "<6>[INFO] up\n"),
+        (LEVEL::Warn, "<4>[WARN] up\n"),
+        (LEVEL::Error, "<3>[ERROR] up\n"),
+        (LEVEL::Fatal, "<2>[FATAL] up\n"),
+    ] {
+        let s = log.fmt("svc", level, "", 0, "up".to_string());
+        assert_eq!(s.console_body.as_deref(), Some(console));
+        // The file keeps its timestamp and has no prefix.
+        assert!(s.file_body.contains("2024-05-01 12:00:00 up"), "{}", s.file_body);
+        assert!(!s.file_body.starts_with('<'), "{}", s.file_body);
+    }
+    assert!(log.describe().contains("preset: systemd\n"));
+}
+
+#[test]
+fn test_preset_systemd_prefix_before_color() {
+    let mut log = systemd_logger();
+    log.set_attr_format(|fmt| fmt.set_console_body_fmt(|level, body| if level == LEVEL::Error { format!("\x1b[31m{}\x1b[0m", body) } else { body }));
+    let s = log.fmt("svc", LEVEL::Error, "", 0, "down".to_string());
+    assert_eq!(s.console_body.as_deref(), Some("<3>\x1b[31m[ERROR] down\n\x1b[0m"));
+    assert!(!s.file_body.contains('\x1b'));
+}
@@ -0,0 +1,75 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tklog::{syncfile::prune_backups, PrunePolicy};
+
+/// Yesterday's plain backup plus today's nine compressed ones, each a minute
+/// newer than the one before.
+fn synthetic_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tklog_prune_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let mut names = vec!["app_20240430_1.log".to_string()];
+    names.extend((1..=9).map(|i| format!("app_20240501_{}.log.gz", i)));
+    names.push("app.log".to_string());
+    names.push("other_20240401_1.log".to_string());
+    let start = SystemTime::now() - Duration::from_secs(3600);
+    for (i, n) in names.iter().enumerate() {
+        let f = File::create(dir.join(n)).unwrap();
+        f.set_modified(start + Duration::from_secs(60 * i as u64)).unwrap();
+    }
+    dir
+}
+
+fn survivors(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_prune_by_file_splits_period() {
+    let dir = synthetic_dir("byfile");
+    let deleted = prune_backups(&dir.join("app.log"), 7, PrunePolicy::ByFile).unwrap();
+    assert_eq!(deleted.len(), 3);
+    let left = survivors(&dir);
+    assert!(!left.contains(&"app_20240430_1.log".to_string()));
+    assert!(!left.contains(&"app_20240501_1.log.gz".to_string()));
+    assert!(!left.contains(&"app_20240501_2.log.gz".to_string()));
+    assert!(left.contains(&"app_20240501_3.log.gz".to_string()));
+    assert!(left.contains(&"other_20240401_1.log".to_string()));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_prune_by_period_keeps_active_chain() {
+    let dir = synthetic_dir("byperiod");
+    let deleted = prune_backups(&dir.join("app.log"), 1, PrunePolicy::ByPeriod).unwrap();
+    assert_eq!(deleted, vec![dir.join("app_20240430_1.log")]);
+    let mut expect: Vec<String> = (1..=9).map(|i| format!("app_20240501_{}.log.gz", i)).collect();
+    expect.push("app.log".to_string());
+    expect.push("other_20240401_1.log".to_string());
+    expect.sort();
+    assert_eq!(survivors(&dir), expect);
+
+    assert!(prune_backups(&dir.join("app.log"), 1, PrunePolicy::ByPeriod).unwrap().is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_prune_by_period_without_stamps_counts_files() {
+    let dir = std::env::temp_dir().join(format!("tklog_prune_size_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let start = SystemTime::now() - Duration::from_secs(3600);
+    for i in 1..=5 {
+        let f = File::create(dir.join(format!("app_{}.log", i))).unwrap();
+        f.set_modified(start + Duration::from_secs(60 * i)).unwrap();
+    }
+    let deleted = prune_backups(&dir.join("app.log"), 2, PrunePolicy::ByPeriod).unwrap();
+    assert_eq!(deleted, vec![dir.join("app_1.log"), dir.join("app_2.log"), dir.join("app_3.log")]);
+    let _ = fs::remove_dir_all(&dir);
+}
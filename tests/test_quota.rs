@@ -0,0 +1,47 @@
+use std::fs;
+
+use tklog::{handle::FileSizeMode, sync::Logger, Format, LogOption, LEVEL};
+
+fn emit(log: &mut Logger, module: &str, level: LEVEL, msg: &str) {
+    let s = log.fmt(module, level, "", 0, msg.to_string());
+    if !s.is_empty() {
+        log.print(level, module, s);
+    }
+}
+
+#[test]
+fn test_handler_quota() {
+    let dir = std::env::temp_dir().join(format!("tklog_quota_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let (main, noisy) = (dir.join("main.log"), dir.join("noisy.log"));
+    let noisy_name = noisy.to_str().unwrap().to_string();
+
+    let mut option = LogOption::new();
+    option.set_fileoption(FileSizeMode::new(&noisy_name, 0, 0, false));
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_format(Format::LevelFlag)
+        .set_formatter("{level} {message}\n")
+        .set_cutmode_by_size(main.to_str().unwrap(), 0, 0, false)
+        .set_mod_option("noisy", option)
+        .set_handler_quota(&noisy_name, 40);
+
+    for i in 0..5 {
+        emit(&mut log, "noisy", LEVEL::Info, &format!("flood {}", i));
+    }
+    emit(&mut log, "quiet", LEVEL::Info, "still flowing");
+
+    assert_eq!(fs::read_to_string(&noisy).unwrap(), "[INFO] flood 0\n[INFO] flood 1\n");
+    assert_eq!(
+        fs::read_to_string(&main).unwrap(),
+        format!("[WARN] quota exceeded for handler `{}`: 40 bytes per day; dropping its lines until the next day\n[INFO] still flowing\n", noisy_name)
+    );
+
+    let stats = log.stats();
+    let q = stats.quota(&noisy_name).unwrap();
+    assert_eq!((q.bytes_per_day, q.used, q.dropped), (40, 30, 3));
+    assert!(stats.render_prometheus().contains(&format!("tklog_quota_dropped_total{{handler=\"{}\"}} 3\n", noisy_name)));
+    assert!(log.describe().contains(&format!("quota: {} 30/40 bytes today, 3 lines dropped\n", noisy_name)));
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use std::{cell::RefCell, fs};
+
+use tklog::{record::FormatStage, sync, Async, Format, LEVEL};
+
+use common::testmode;
+
+thread_local! {
+    static TENANT: RefCell<&'static str> = const { RefCell::new("") };
+}
+
+#[test]
+fn test_record_formatter() {
+    let mut log = sync::Logger::new();
+    log.set_console(false).set_test_mode(testmode()).unwrap();
+    log.set_record_formatter(Box::new(|r| format!("{} {:?} {} {}:{} [{}] {}\n", r.time.format("%H:%M"), r.level, r.module, r.file, r.line, r.fields.len(), r.message)));
+    log.set_attr_format(|fmt| fmt.set_file_body_fmt(|_, body| format!("> {}", body)));
+    log.set_format(Format::LevelFlag | Format::ShortFileName);
+    let s = log.fmt("app", LEVEL::Warn, "src/main.rs", 7, "disk low".to_string());
+    assert_eq!(s.file_body, "> 12:00 Warn app src/main.rs:7 [0] disk low\n");
+    log.clear_record_formatter();
+    let s = log.fmt("app", LEVEL::Warn, "src/main.rs", 7, "disk low".to_string());
+    assert_eq!(s.file_body, "> [WARN] main.rs 7:disk low\n");
+}
+
+async fn write_lines(stage: FormatStage, path: &std::path::Path) -> String {
+    let _ = fs::remove_file(path);
+    let mut log = Async::Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Info)
+        .set_format(Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName)
+        .set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false)
+        .await;
+    log.set_test_mode(testmode()).unwrap();
+    log.set_format_stage(stage).set_dynamic_fields(Box::new(|fields| {
+        TENANT.with(|t| {
+            fields.insert("tenant", *t.borrow());
+        });
+    }));
+    for (i, tenant) in ["acme", "globex", "initech"].into_iter().enumerate() {
+        TENANT.with(|t| *t.borrow_mut() = tenant);
+        log.enqueue(LEVEL::Info, "billing", "src/billing.rs", 10 + i as u32, format!("invoice {}", i));
+    }
+    log.enqueue(LEVEL::Debug, "billing", "src/billing.rs", 20, "filtered".to_string());
+    log.flush().await;
+    let content = fs::read_to_string(path).unwrap();
+    let _ = fs::remove_file(path);
+    content
+}
+
+#[tokio::test]
+async fn test_worker_stage_matches_call_site() {
+    let dir = std::env::temp_dir();
+    let call_site = write_lines(FormatStage::CallSite, &dir.join(format!("tklog_record_call_site_{}.log", std::process::id()))).await;
+    let worker = write_lines(FormatStage::Worker, &dir.join(format!("tklog_record_worker_{}.log", std::process::id()))).await;
+    assert_eq!(
+        call_site,
+        "[INFO] 2024-05-01 12:00:00 billing.rs 10:invoice 0 tenant=acme\n\
+         [INFO] 2024-05-01 12:00:00 billing.rs 11:invoice 1 tenant=globex\n\
+         [INFO] 2024-05-01 12:00:00 billing.rs 12:invoice 2 tenant=initech\n"
+    );
+    assert_eq!(worker, call_site);
+}
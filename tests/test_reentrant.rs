@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tklog::{error, info, infos, sync::Logger, warn, Format, LogContext, RotationEvent, LEVEL, LOG, PRINTMODE};
+
+/// Runs `f` on its own thread and fails instead of hanging on a deadlock.
+fn within_deadline<F: FnOnce() + Send + 'static>(f: F) {
+    let (done, wait) = mpsc::channel();
+    thread::spawn(move || {
+        f();
+        let _ = done.send(());
+    });
+    wait.recv_timeout(Duration::from_secs(10)).expect("deadlocked");
+}
+
+fn temp_log(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("tklog_reentrant_{}_{}.log", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path.to_str().unwrap().to_string()
+}
+
+static AUDIT: Mutex<Option<Arc<Mutex<Logger>>>> = Mutex::new(None);
+
+fn audit_handler(ctx: &LogContext) -> bool {
+    if let Some(log) = AUDIT.lock().unwrap().clone() {
+        let mut log = log;
+        infos!(&mut log, "audited ", ctx.log_body.trim_end());
+    }
+    true
+}
+
+#[test]
+fn test_reentrant_callbacks() {
+    within_deadline(|| {
+        let filename = temp_log("callbacks");
+        let mut log = Logger::new();
+        log.set_console(false).set_format(Format::Nano).set_cutmode_by_size(&filename, 0, 0, false);
+        log.set_custom_handler(audit_handler);
+        let mut logger = Arc::new(Mutex::new(log));
+        *AUDIT.lock().unwrap() = Some(logger.clone());
+
+        let inner = logger.clone();
+        logger.lock().unwrap().set_dynamic_fields(Box::new(move |fields| {
+            let mut inner = inner.clone();
+            infos!(&mut inner, "from the fields callback");
+            fields.insert("k", "v");
+        }));
+        let inner = logger.clone();
+        logger.lock().unwrap().set_attr_format(move |fmt| {
+            let inner = inner.clone();
+            fmt.set_file_body_fmt(move |_, body| {
+                let mut inner = inner.clone();
+                infos!(&mut inner, "from the body format");
+                body
+            });
+        });
+        infos!(&mut logger, "outer\n");
+        *AUDIT.lock().unwrap() = None;
+        logger.lock().unwrap().clear_dynamic_fields();
+        assert_eq!(fs::read_to_string(&filename).unwrap(), "outer k=v\n");
+        let _ = fs::remove_file(&filename);
+    });
+}
+
+struct LogsOnDrop;
+
+impl Drop for LogsOnDrop {
+    fn drop(&mut self) {
+        warn!("dropped while tklog held the logger");
+        info!("dropped quietly");
+    }
+}
+
+static ROTATED: AtomicBool = AtomicBool::new(false);
+
+fn on_rotate(e: &RotationEvent) {
+    error!("rotated", e.filename.as_str());
+    ROTATED.store(true, Ordering::SeqCst);
+}
+
+#[test]
+fn test_reentrant_global_logger() {
+    within_deadline(|| {
+        let filename = temp_log("global");
+        LOG.set_console(false).set_level(LEVEL::Info).set_format(Format::Nano).set_cutmode_by_size(&filename, 64, 0, false);
+        LOG.set_printmode(PRINTMODE::PUNCTUAL).set_rotation_handler(on_rotate);
+
+        let guard = LogsOnDrop;
+        LOG.set_dynamic_fields(Box::new(move |_| {
+            let _ = &guard;
+        }));
+        info!("before\n");
+        LOG.clear_dynamic_fields();
+        info!("after\n");
+        assert_eq!(fs::read_to_string(&filename).unwrap(), "before\nafter\n");
+
+        for i in 0..8 {
+            info!("filling the file up to its size limit ", i, "\n");
+            thread::sleep(Duration::from_millis(20));
+        }
+        for _ in 0..100 {
+            if ROTATED.load(Ordering::SeqCst) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(ROTATED.load(Ordering::SeqCst));
+    });
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader},
+    net::{TcpListener, UdpSocket},
+    time::Duration,
+};
+
+use tklog::{
+    remote::{RemoteConfig, RemoteTransport},
+    sync::Logger,
+    Format, LEVEL, PRINTMODE,
+};
+
+use common::logfile;
+
+fn logger(name: &str) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&logfile(name), 0, 0, false);
+    log
+}
+
+fn write(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_remote_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut log = logger("tcp");
+    log.set_remote(RemoteConfig::new(RemoteTransport::Tcp(listener.local_addr().unwrap().to_string())));
+    for i in 0..3 {
+        write(&mut log, &format!("line {}", i));
+    }
+    log.flush();
+
+    let (stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let lines: Vec<String> = BufReader::new(stream).lines().take(3).map(Result::unwrap).collect();
+    assert_eq!(lines, ["[INFO] line 0", "[INFO] line 1", "[INFO] line 2"]);
+    assert_eq!(log.remote_dropped(), 0);
+}
+
+#[test]
+fn test_remote_udp() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut log = logger("udp");
+    log.set_remote(RemoteConfig::new(RemoteTransport::Udp(server.local_addr().unwrap().to_string())));
+    write(&mut log, "one datagram");
+
+    let mut buf = [0u8; 1024];
+    let n = server.recv(&mut buf).unwrap();
+    assert_eq!(std::str::from_utf8(&buf[..n]).unwrap().trim_end(), "[INFO] one datagram");
+}
+
+#[test]
+fn test_remote_drop_oldest_and_reconnect() {
+    // A port nothing listens on yet.
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let mut log = logger("reconnect");
+    let mut config = RemoteConfig::new(RemoteTransport::Tcp(addr.to_string()));
+    config.capacity = 2;
+    config.flush_timeout = Duration::from_millis(100);
+    log.set_remote(config);
+    // Once the sender holds its first batch, the queue keeps the newest two.
+    write(&mut log, "first");
+    std::thread::sleep(Duration::from_millis(50));
+    for m in ["second", "third", "fourth"] {
+        write(&mut log, m);
+    }
+    assert_eq!(log.remote_dropped(), 1);
+
+    let listener = TcpListener::bind(addr).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let lines: Vec<String> = BufReader::new(stream).lines().take(3).map(Result::unwrap).collect();
+    assert_eq!(lines, ["[INFO] first", "[INFO] third", "[INFO] fourth"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_remote_async() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    log.set_remote(RemoteConfig::new(RemoteTransport::Tcp(listener.local_addr().unwrap().to_string())));
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "from a task".to_string());
+    log.log(LEVEL::Warn, "app", s);
+    let (stream, _) = listener.accept().await.unwrap();
+    log.flush().await;
+
+    let mut lines = tokio::io::AsyncBufReadExt::lines(tokio::io::BufReader::new(stream));
+    let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await.unwrap().unwrap();
+    assert_eq!(line.as_deref(), Some("[WARN] from a task"));
+}
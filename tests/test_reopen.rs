@@ -0,0 +1,83 @@
+mod common;
+
+use std::fs;
+
+use tklog::{sync::Logger, Format, LEVEL, PRINTMODE};
+
+use common::dir;
+
+fn write(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+// As logrotate does it: the file is renamed, then the logger told.
+#[test]
+fn test_reopen() {
+    let dir = dir("sync");
+    let path = dir.join("app.log");
+    let rotated = dir.join("app.log.1");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano);
+    log.set_cutmode_by_size(path.to_str().unwrap(), 30, 0, false);
+    write(&mut log, "twenty bytes of text");
+    fs::rename(&path, &rotated).unwrap();
+    log.reopen().unwrap();
+    // The size cut counts from the new file: no rotation.
+    write(&mut log, "twenty more of them.");
+    assert_eq!(fs::read_to_string(&rotated).unwrap(), "twenty bytes of text");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "twenty more of them.");
+    assert_eq!(log.metrics().rotations, 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// The lines queued before the reopen go to the renamed file.
+#[tokio::test]
+async fn test_reopen_async() {
+    let dir = dir("async");
+    let path = dir.join("app.log");
+    let rotated = dir.join("app.log.1");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano);
+    log.set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false).await;
+    log.enqueue(LEVEL::Info, "app", "", 0, "before".to_string());
+    log.flush().await;
+    fs::rename(&path, &rotated).unwrap();
+    log.enqueue(LEVEL::Info, "app", "", 0, " queued".to_string());
+    log.reopen().await.unwrap();
+    log.enqueue(LEVEL::Info, "app", "", 0, "after".to_string());
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&rotated).unwrap(), "before queued");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "after");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(all(unix, feature = "signal"))]
+#[test]
+fn test_reopen_on_sighup() {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    let dir = dir("sighup");
+    let path = dir.join("app.log");
+    let rotated = dir.join("app.log.1");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano);
+    log.set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    log.enable_reopen_on_sighup().unwrap();
+    write(&mut log, "before");
+    fs::rename(&path, &rotated).unwrap();
+    unsafe { libc::kill(libc::getpid(), libc::SIGHUP) };
+    // The signal is counted on a thread of its own.
+    let start = Instant::now();
+    while !path.exists() {
+        assert!(start.elapsed() < Duration::from_secs(10), "no reopen");
+        thread::sleep(Duration::from_millis(20));
+        write(&mut log, ".");
+    }
+    assert!(fs::read_to_string(&rotated).unwrap().starts_with("before"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), ".");
+    let _ = fs::remove_dir_all(&dir);
+}
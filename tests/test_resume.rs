@@ -0,0 +1,70 @@
+mod common;
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use tklog::{sync::Logger, Format, LEVEL, MODE, PRINTMODE};
+
+use common::{dir, files};
+
+const DAY: Duration = Duration::from_secs(86400);
+
+/// An `app.log` left by an earlier run, `len` bytes long and last written
+/// `age` ago.
+fn leftover(dir: &Path, len: usize, age: Duration) -> PathBuf {
+    let path = dir.join("app.log");
+    fs::write(&path, "x".repeat(len)).unwrap();
+    File::options().append(true).open(&path).unwrap().set_modified(SystemTime::now() - age).unwrap();
+    path
+}
+
+fn write(log: &mut Logger) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, "after restart".to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_resume_size_cut() {
+    let dir = dir("size");
+    let path = leftover(&dir, 4096, Duration::ZERO);
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 1024, 0, false);
+    write(&mut log);
+    assert_eq!(files(&dir), ["app.log", "app_1.log"]);
+    assert_eq!(fs::metadata(dir.join("app_1.log")).unwrap().len(), 4096);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "after restart");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_resume_time_cut() {
+    let dir = dir("time");
+    let path = leftover(&dir, 16, 2 * DAY);
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano).set_cutmode_by_time(path.to_str().unwrap(), MODE::DAY, 0, false);
+    write(&mut log);
+    // The backup carries the day the file was last written.
+    let day = DateTime::<Local>::from(SystemTime::now() - 2 * DAY).format("%Y%m%d");
+    assert_eq!(files(&dir), ["app.log".to_string(), format!("app_{}_1.log", day)]);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "after restart");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_resume_async() {
+    let dir = dir("async");
+    let path = leftover(&dir, 4096, 2 * DAY);
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano);
+    log.set_cutmode_by_mixed(path.to_str().unwrap(), MODE::DAY, 1 << 20, 0, false).await;
+    let s = log.fmt("app", LEVEL::Info, "", 0, "after restart".to_string());
+    log.log(LEVEL::Info, "app", s);
+    log.flush().await;
+    let day = DateTime::<Local>::from(SystemTime::now() - 2 * DAY).format("%Y%m%d");
+    assert_eq!(files(&dir), ["app.log".to_string(), format!("app_{}_1.log", day)]);
+    let _ = fs::remove_dir_all(&dir);
+}
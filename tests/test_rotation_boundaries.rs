@@ -0,0 +1,61 @@
+mod common;
+
+use std::{fs, sync::Arc, time::Duration};
+
+use chrono::{Local, TimeZone};
+use tklog::{clock::ManualClock, sync::Logger, Format, LEVEL, MODE};
+
+use common::{dir, files};
+
+const MINUTE: u64 = 60;
+
+fn line(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+/// Logs a line at `start`, one `before` later, still in the period, and one
+/// `after` that, past its end; returns the backups.
+fn rotate(name: &str, mode: MODE, start: (i32, u32, u32, u32, u32), before: u64, after: u64) -> Vec<String> {
+    let dir = dir(name);
+    let path = dir.join("app.log").to_string_lossy().into_owned();
+    let (y, mo, d, h, mi) = start;
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()));
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_clock(clock.clone()).set_cutmode_by_time(&path, mode, 0, false);
+    log.set_rotation_group(&[&path]).unwrap();
+
+    line(&mut log, "start");
+    clock.advance(Duration::from_secs(before * MINUTE));
+    line(&mut log, "before");
+    assert_eq!(files(&dir), ["app.log"], "{} rotated early", name);
+    clock.advance(Duration::from_secs(after * MINUTE));
+    line(&mut log, "after");
+
+    let backups: Vec<String> = files(&dir).into_iter().filter(|n| n != "app.log").collect();
+    for backup in &backups {
+        assert_eq!(fs::read_to_string(dir.join(backup)).unwrap(), "[INFO] start\n[INFO] before\n");
+    }
+    assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "[INFO] after\n");
+    let _ = fs::remove_dir_all(&dir);
+    backups
+}
+
+#[test]
+fn test_hour_across_midnight() {
+    assert_eq!(rotate("hour", MODE::HOUR, (2024, 5, 1, 23, 10), 49, 2), ["app_2024050123_1.log"]);
+}
+
+#[test]
+fn test_day_across_month_end() {
+    assert_eq!(rotate("day_month", MODE::DAY, (2024, 1, 31, 8, 0), 15 * 60 + 59, 2), ["app_20240131_1.log"]);
+    // A leap day.
+    assert_eq!(rotate("day_leap", MODE::DAY, (2024, 2, 29, 23, 0), 59, 2), ["app_20240229_1.log"]);
+}
+
+#[test]
+fn test_across_year_end() {
+    assert_eq!(rotate("day_year", MODE::DAY, (2023, 12, 31, 23, 30), 29, 2), ["app_20231231_1.log"]);
+    assert_eq!(rotate("month_year", MODE::MONTH, (2023, 12, 15, 12, 0), (16 * 24 + 11) * 60 + 59, 2), ["app_202312_1.log"]);
+    assert_eq!(rotate("hour_year", MODE::HOUR, (2023, 12, 31, 23, 0), 59, 2), ["app_2023123123_1.log"]);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+
+use chrono::{Local, TimeZone};
+use tklog::{clock::ManualClock, handle::FileTimeMode, sync::Logger, Error, Format, LEVEL, MODE};
+
+use common::{dir, files};
+
+fn grouped_logger(dir: &PathBuf, clock: Arc<ManualClock>) -> Logger {
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_format(Format::LevelFlag)
+        .set_clock(clock)
+        .set_cutmode_by_time(&path("app.log"), MODE::DAY, 0, false)
+        .add_file_sink("errors", FileTimeMode::new(&path("error.log"), MODE::DAY, 0, false))
+        .add_file_sink("audit", FileTimeMode::new(&path("audit.log"), MODE::DAY, 0, false));
+    log
+}
+
+fn line(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_group_rotates_together() {
+    let dir = dir("sync");
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 5, 1, 23, 59, 0).unwrap()));
+    let mut log = grouped_logger(&dir, clock.clone());
+    let ids = ["app.log", "error.log", "audit.log"].map(|f| dir.join(f).to_string_lossy().into_owned());
+    log.set_rotation_group(&ids.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+
+    line(&mut log, "before midnight");
+    assert_eq!(files(&dir), ["app.log", "audit.log", "error.log"]);
+    clock.advance(Duration::from_secs(120));
+    line(&mut log, "after midnight");
+
+    assert_eq!(files(&dir), ["app.log", "app_20240501_1.log", "audit.log", "audit_20240501_1.log", "error.log", "error_20240501_1.log"]);
+    assert_eq!(fs::read_to_string(dir.join("app_20240501_1.log")).unwrap(), "[INFO] before midnight\n");
+    assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "[INFO] after midnight\n");
+    assert_eq!(fs::read_to_string(dir.join("error_20240501_1.log")).unwrap(), "");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_group_without_empty_backups() {
+    let dir = dir("nonempty");
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 5, 1, 23, 59, 0).unwrap()));
+    let mut log = grouped_logger(&dir, clock.clone());
+    let ids = ["app.log", "error.log", "audit.log"].map(|f| dir.join(f).to_string_lossy().into_owned());
+    log.set_rotation_group_with(&ids.iter().map(String::as_str).collect::<Vec<_>>(), false).unwrap();
+
+    line(&mut log, "before midnight");
+    clock.advance(Duration::from_secs(120));
+    line(&mut log, "after midnight");
+    assert_eq!(files(&dir), ["app.log", "app_20240501_1.log", "audit.log", "error.log"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_group_refusals() {
+    let dir = dir("refused");
+    let mut log = grouped_logger(&dir, Arc::new(ManualClock::new()));
+    let app = dir.join("app.log").to_string_lossy().into_owned();
+    let size = dir.join("size.log").to_string_lossy().into_owned();
+    log.add_file_sink("size", tklog::handle::FileSizeMode::new(&size, 1 << 20, 0, false));
+
+    let reason = |r: Result<&mut Logger, Error>| match r {
+        Err(Error::InvalidRotationGroup { reason, .. }) => reason,
+        r => panic!("expected InvalidRotationGroup, got {}", r.is_ok()),
+    };
+    assert_eq!(reason(log.set_rotation_group(&[&app, "nope.log"])), "is not a file handler");
+    assert_eq!(reason(log.set_rotation_group(&[&app, &size])), "does not rotate by time");
+    assert_eq!(reason(log.set_rotation_group(&[&app, &app])), "is given twice");
+    assert_eq!(reason(log.set_rotation_group(&[])), "no members");
+    log.set_rotation_group(&[&app]).unwrap();
+    assert_eq!(reason(log.set_rotation_group(&[&app])), "is in another rotation group");
+    log.clear_rotation_groups();
+    assert!(log.set_rotation_group(&[&app]).is_ok());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_async_group_rotates_together() {
+    let dir = dir("async");
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    let clock = Arc::new(ManualClock::at(Local.with_ymd_and_hms(2024, 5, 1, 23, 59, 0).unwrap()));
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_clock(clock.clone());
+    log.set_cutmode_by_time(&path("app.log"), MODE::DAY, 0, false).await;
+    log.add_file_sink("audit", FileTimeMode::new(&path("audit.log"), MODE::DAY, 0, false)).await;
+    log.set_rotation_group(&[&path("app.log"), &path("audit.log")]).await.unwrap();
+
+    let s = log.fmt("app", LEVEL::Info, "", 0, "before midnight".to_string());
+    log.log(LEVEL::Info, "app", s);
+    clock.advance(Duration::from_secs(120));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "after midnight".to_string());
+    log.log(LEVEL::Info, "app", s);
+    log.flush().await;
+
+    assert_eq!(files(&dir), ["app.log", "app_20240501_1.log", "audit.log", "audit_20240501_1.log"]);
+    assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "[INFO] after midnight\n");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use std::{fs, sync::Mutex};
+
+use tklog::{CompressDecision, Format, RotationEvent, LOG, PRINTMODE};
+
+use common::dir;
+
+static EVENTS: Mutex<Vec<(String, RotationEvent)>> = Mutex::new(Vec::new());
+
+fn on_rotate(e: &RotationEvent) {
+    let thread = std::thread::current().name().unwrap_or("").to_string();
+    EVENTS.lock().unwrap().push((thread, e.clone()));
+}
+
+// The rotating line only renames; the guard waits for the compressions.
+#[test]
+fn test_rotation_worker_drained_by_guard() {
+    let dir = dir("worker");
+    let path = dir.join("app.log");
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, true);
+    LOG.set_rotation_handler(on_rotate);
+    {
+        let _flush = tklog::flush_guard();
+        let line = "x".repeat(1 << 16);
+        for _ in 0..80 {
+            tklog::info!(line);
+        }
+    }
+    let events = EVENTS.lock().unwrap();
+    assert_eq!(events.len(), 4, "{:?}", events);
+    for (thread, event) in events.iter() {
+        assert_eq!(thread, "tklog-rotation");
+        assert_eq!(event.compression, CompressDecision::Compressed);
+        assert!(event.backup.exists(), "{}", event.backup.display());
+    }
+    let mut names: Vec<String> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    names.sort();
+    assert_eq!(names, ["app.log", "app_1.log.gz", "app_2.log.gz", "app_3.log.gz", "app_4.log.gz"]);
+    let _ = fs::remove_dir_all(&dir);
+}
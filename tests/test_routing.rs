@@ -0,0 +1,75 @@
+mod common;
+
+use std::fs;
+
+use tklog::{handle::FileSizeMode, Error, Format, LEVEL};
+
+use common::missing_dir;
+
+#[test]
+fn test_sync_routing_matrix() {
+    let dir = missing_dir("sync");
+    let (app, error) = (dir.join("app.log"), dir.join("error.log"));
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false)
+        .set_format(Format::Nano)
+        .set_cutmode_by_size(app.to_str().unwrap(), 0, 0, false)
+        .add_file_sink("error", FileSizeMode::new(error.to_str().unwrap(), 0, 0, false));
+    log.set_routing(|route| {
+        route.level(LEVEL::Error..).to(["file:error", "file"]);
+        route.level(..=LEVEL::Warn).to(["file"]);
+    })
+    .unwrap();
+
+    for (level, msg) in [(LEVEL::Info, "served\n"), (LEVEL::Error, "failed\n"), (LEVEL::Fatal, "crashed\n")] {
+        let s = log.fmt("routing", level, "", 0, msg.to_string());
+        log.print(level, "routing", s);
+    }
+    assert_eq!(fs::read_to_string(&app).unwrap(), "served\nfailed\ncrashed\n");
+    assert_eq!(fs::read_to_string(&error).unwrap(), "failed\ncrashed\n");
+
+    let described = log.describe();
+    assert!(described.contains("route: Info -> file\n"), "{}", described);
+    assert!(described.contains("route: Error -> file:error, file\n"), "{}", described);
+
+    log.clear_routing();
+    let s = log.fmt("routing", LEVEL::Error, "", 0, "unrouted\n".to_string());
+    log.print(LEVEL::Error, "routing", s);
+    assert_eq!(fs::read_to_string(&error).unwrap(), "failed\ncrashed\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_routing_rejects_unknown_sink() {
+    let mut log = tklog::sync::Logger::new();
+    let err = log.set_routing(|route| route.level(LEVEL::Error).to(["console", "remote"])).err();
+    assert!(matches!(err, Some(Error::UnknownSink(ref s)) if s == "remote"), "{:?}", err.map(|e| e.to_string()));
+    // No file configured: `file` names nothing either.
+    assert!(log.set_routing(|route| route.level(LEVEL::Trace..).to(["file"])).is_err());
+    assert!(log.set_routing(|route| route.level(LEVEL::Trace..).to(["console"])).is_ok());
+}
+
+#[tokio::test]
+async fn test_async_routing_matrix() {
+    let dir = missing_dir("async");
+    let (app, error) = (dir.join("app.log"), dir.join("error.log"));
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_cutmode_by_size(app.to_str().unwrap(), 0, 0, false).await;
+    log.add_file_sink("error", FileSizeMode::new(error.to_str().unwrap(), 0, 0, false)).await;
+    log.set_routing(|route| {
+        route.level(LEVEL::Warn..).to(["file:error"]);
+        route.level(..LEVEL::Error).to(["file"]);
+    })
+    .unwrap();
+
+    for (level, msg) in [(LEVEL::Info, "served\n"), (LEVEL::Warn, "slow\n"), (LEVEL::Error, "failed\n")] {
+        log.enqueue(level, "routing", "", 0, msg.to_string());
+    }
+    log.flush().await;
+    assert_eq!(fs::read_to_string(&app).unwrap(), "served\nslow\n");
+    assert_eq!(fs::read_to_string(&error).unwrap(), "slow\nfailed\n");
+    let stats = log.stats();
+    assert_eq!(stats.sink(app.to_str().unwrap()).unwrap().lines, 2);
+    assert_eq!(stats.sink(error.to_str().unwrap()).unwrap().lines, 2);
+    let _ = fs::remove_dir_all(&dir);
+}
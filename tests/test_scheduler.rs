@@ -0,0 +1,56 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use tklog::{Format, LEVEL, MODE};
+
+/// A log file last written two hours ago, and the name its hourly backup gets.
+fn stale_log(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("tklog_scheduler_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.log");
+    let modified = SystemTime::now() - Duration::from_secs(7200);
+    File::create(&path).unwrap().set_modified(modified).unwrap();
+    let stamp = DateTime::<Local>::from(modified).format("%Y%m%d%H").to_string();
+    let backup = dir.join(format!("app_{}_1.log", stamp));
+    (dir, path, backup)
+}
+
+#[test]
+fn test_sync_rotation_due_on_first_write() {
+    let (dir, path, backup) = stale_log("sync");
+    let mut log = tklog::sync::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_cutmode_by_time(path.to_str().unwrap(), MODE::HOUR, 0, false);
+    for msg in ["first\n", "second\n"] {
+        let s = log.fmt("scheduler", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "scheduler", s);
+    }
+    assert!(backup.exists(), "{:?}", fs::read_dir(&dir).unwrap().collect::<Vec<_>>());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    drop(log);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_async_rotation_due_on_first_write() {
+    let (dir, path, backup) = stale_log("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_cutmode_by_time(path.to_str().unwrap(), MODE::HOUR, 0, false).await;
+    for msg in ["first\n", "second\n"] {
+        let s = log.fmt("scheduler", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "scheduler", s).await;
+    }
+    assert!(backup.exists(), "{:?}", fs::read_dir(&dir).unwrap().collect::<Vec<_>>());
+    for _ in 0..100 {
+        if fs::read_to_string(&path).unwrap() == "first\nsecond\n" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    let _ = fs::remove_dir_all(&dir);
+}
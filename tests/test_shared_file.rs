@@ -0,0 +1,113 @@
+mod common;
+
+use std::{fs, path::PathBuf, thread};
+
+use tklog::{sync::Logger, Format, LEVEL, PRINTMODE};
+
+use common::missing_dir;
+
+/// A logger as one of the processes sharing `path` would have it.
+fn process(path: &str, maxsize: u64) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano).set_shared_file(true);
+    log.set_cutmode_by_size(path, maxsize, 0, false);
+    log
+}
+
+fn write(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+/// The contents of every file in `dir`, backups included, by name.
+fn contents(dir: &PathBuf) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .map(|p| (p.file_name().unwrap().to_string_lossy().into_owned(), fs::read_to_string(&p).unwrap()))
+        .collect();
+    files.sort();
+    files
+}
+
+// Each cut counts what the others wrote, and a file rotated by another is
+// not rotated again.
+#[test]
+fn test_shared_file_cuts() {
+    let dir = missing_dir("cuts");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let mut a = process(filename, 30);
+    let mut b = process(filename, 30);
+    write(&mut a, "a line of twenty ...\n");
+    write(&mut b, "b line of twenty ...\n");
+    assert_eq!(b.metrics().rotations, 1);
+    write(&mut a, "another twenty .....\n");
+    assert_eq!(a.metrics().rotations, 1);
+    assert_eq!(
+        contents(&dir),
+        vec![
+            ("app.log".to_string(), "another twenty .....\n".to_string()),
+            ("app_1.log".to_string(), "a line of twenty ...\n".to_string()),
+            ("app_2.log".to_string(), "b line of twenty ...\n".to_string()),
+        ]
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// Lines written at once by several writers come out whole, none lost.
+#[test]
+fn test_shared_file_concurrent() {
+    let dir = missing_dir("concurrent");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap().to_string();
+    let writers: Vec<_> = (0..4)
+        .map(|w| {
+            let filename = filename.clone();
+            thread::spawn(move || {
+                let mut log = process(&filename, 2000);
+                for i in 0..200 {
+                    write(&mut log, &format!("writer {} line {:03} {}\n", w, i, "x".repeat(40)));
+                }
+            })
+        })
+        .collect();
+    for w in writers {
+        w.join().unwrap();
+    }
+    let mut lines: Vec<String> = contents(&dir).into_iter().flat_map(|(_, s)| s.lines().map(str::to_string).collect::<Vec<_>>()).collect();
+    lines.sort();
+    let mut expected: Vec<String> = (0..4)
+        .flat_map(|w| (0..200).map(move |i| format!("writer {} line {:03} {}", w, i, "x".repeat(40))))
+        .collect();
+    expected.sort();
+    assert_eq!(lines, expected);
+    for (name, s) in contents(&dir) {
+        assert!(s.len() <= 2000, "{} has {} bytes", name, s.len());
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_shared_file_async() {
+    let dir = missing_dir("async");
+    let path = dir.join("app.log");
+    let filename = path.to_str().unwrap();
+    let mut a = process(filename, 30);
+    let mut b = tklog::Async::Logger::new();
+    b.set_console(false).set_format(Format::Nano).set_shared_file(true);
+    b.set_cutmode_by_size(filename, 30, 0, false).await;
+    write(&mut a, "a line of twenty ...\n");
+    b.enqueue(LEVEL::Info, "app", "", 0, "b line of twenty ...\n".to_string());
+    b.flush().await;
+    write(&mut a, "another twenty .....\n");
+    assert_eq!(
+        contents(&dir),
+        vec![
+            ("app.log".to_string(), "another twenty .....\n".to_string()),
+            ("app_1.log".to_string(), "a line of twenty ...\n".to_string()),
+            ("app_2.log".to_string(), "b line of twenty ...\n".to_string()),
+        ]
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::fs;
+
+use tklog::{parse_size, sync::Logger, Format, ParseSizeError, LEVEL};
+
+use common::dir;
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert_eq!(parse_size("512B"), Ok(512));
+    assert_eq!(parse_size("128MB"), Ok(128_000_000));
+    assert_eq!(parse_size("10 mb"), Ok(10_000_000));
+    assert_eq!(parse_size(" 64KiB "), Ok(64 << 10));
+    assert_eq!(parse_size("2gib"), Ok(2 << 30));
+    assert_eq!(parse_size("1.5GB"), Ok(1_500_000_000));
+    assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+    assert_eq!(parse_size("0.5 KB"), Ok(500));
+    // The decimal units and the binary ones differ.
+    assert_eq!((parse_size("1KB"), parse_size("1KiB")), (Ok(1000), Ok(1024)));
+    assert_eq!((parse_size("1MB"), parse_size("1MiB")), (Ok(1_000_000), Ok(1 << 20)));
+    assert_eq!((parse_size("1GB"), parse_size("1GiB")), (Ok(1_000_000_000), Ok(1 << 30)));
+}
+
+#[test]
+fn test_parse_size_error() {
+    let err = |s: &str| parse_size(s).unwrap_err().to_string();
+    assert_eq!(err(""), "invalid size ``: no number");
+    assert_eq!(err("MB"), "invalid size `MB`: no number");
+    assert_eq!(err("10 XB"), "invalid size `10 XB`: an unknown unit, expected B, KB, MB, GB, KiB, MiB or GiB");
+    assert_eq!(err("1.2.3MB"), "invalid size `1.2.3MB`: not a number");
+    assert_eq!(err("99999999999GB"), "invalid size `99999999999GB`: too large");
+    assert_eq!(
+        parse_size("-1"),
+        Err(ParseSizeError {
+            input: "-1".to_string(),
+            reason: "a signed number, expected a size of at least 1 byte"
+        })
+    );
+    for zero in ["0", "0MB", "0.0001KB"] {
+        assert_eq!(err(zero), format!("invalid size `{}`: zero, a file would rotate on every line", zero));
+    }
+}
+
+#[test]
+fn test_cutmode_by_size_str() {
+    let dir = dir("size_str");
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Nano);
+    assert!(log.set_cutmode_by_size_str(path.to_str().unwrap(), "ten", 0, false).is_err());
+    assert!(log.set_cutmode_by_size_str(path.to_str().unwrap(), "0KB", 0, false).is_err());
+    log.set_cutmode_by_size_str(path.to_str().unwrap(), "0.01 KB", 0, false).unwrap();
+    for line in ["0123456789", "abc"] {
+        let s = log.fmt("app", LEVEL::Info, "", 0, line.to_string());
+        log.print(LEVEL::Info, "app", s);
+    }
+    assert_eq!(fs::read_to_string(dir.join("app_1.log")).unwrap(), "0123456789");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "abc");
+    let _ = fs::remove_dir_all(&dir);
+}
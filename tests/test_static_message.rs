@@ -0,0 +1,71 @@
+mod common;
+
+use std::fs;
+
+use chrono::{Local, TimeZone};
+use tklog::{info, sync::Logger, Format, TestMode, LEVEL, LOG, PRINTMODE};
+
+use common::logfile;
+
+fn logger(path: &str) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_cutmode_by_size(path, 0, 0, false);
+    log.set_test_mode(TestMode { fixed_time: Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(), fixed_seq_start: 1 }).unwrap();
+    log
+}
+
+#[test]
+fn test_print_static_matches_fmt() {
+    let path = logfile("plain");
+    let mut log = logger(&path);
+    let formats = [Format::LevelFlag | Format::Date | Format::Time, Format::LevelFlag | Format::Time | Format::Microseconds, Format::Time, Format::LevelFlag];
+    for fmat in formats {
+        log.set_format(fmat);
+        for level in [LEVEL::Info, LEVEL::Error, LEVEL::Info] {
+            log.print_static(level, "app", "cache miss");
+            let s = log.fmt("app", level, "", 0, "cache miss".to_string());
+            log.print(level, "app", s);
+        }
+    }
+    let text = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), formats.len() * 6);
+    for pair in lines.chunks(2) {
+        assert_eq!(pair[0], pair[1]);
+    }
+    assert_eq!(lines[0], "[INFO] 2024-05-01 12:00:00 cache miss");
+    assert_eq!(lines[6], "[INFO] 12:00:00.000000 cache miss");
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_print_static_falls_back() {
+    let path = logfile("fallback");
+    let mut log = logger(&path);
+    log.set_format(Format::LevelFlag).set_formatter("{level}|{message}\n");
+    log.print_static(LEVEL::Warn, "app", "with a formatter");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[WARN]|with a formatter\n");
+
+    // ANSI escapes are still stripped from the file.
+    let path = logfile("ansi");
+    let mut log = logger(&path);
+    log.set_format(Format::LevelFlag).print_static(LEVEL::Warn, "app", "\x1b[31mred\x1b[0m");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[WARN] red\n");
+    let _ = fs::remove_file(&path);
+}
+
+// The only test of this file on `LOG`.
+#[test]
+fn test_static_macro_arm() {
+    let path = logfile("macro");
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+    info!("cache miss");
+    info!(1.50);
+    LOG.set_format(Format::LevelFlag | Format::ShortFileName);
+    info!("with its file");
+    let text = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[..2], ["[INFO] cache miss", "[INFO] 1.5"]);
+    assert!(lines[2].starts_with("[INFO] test_static_message.rs ") && lines[2].ends_with(":with its file"), "{}", lines[2]);
+    let _ = fs::remove_file(&path);
+}
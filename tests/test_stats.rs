@@ -0,0 +1,59 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use tklog::{
+    stats::{LatencyHistogram, LogStats, SinkStats},
+    sync::Logger,
+    LEVEL,
+};
+
+#[test]
+fn test_queue_stats() {
+    tklog::global().set_console(false);
+    let mut log = Logger::new();
+    log.set_console(false).set_latency_sampling(5);
+    for i in 0..50 {
+        let s = log.fmt("stats", LEVEL::Info, "", 0, format!("line {}", i));
+        log.log(LEVEL::Info, "stats".to_string(), s);
+    }
+    let start = Instant::now();
+    while log.stats().sink("console").map_or(0, |s| s.lines) < 50 {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        thread::sleep(Duration::from_millis(10));
+    }
+    let stats = log.stats();
+    let console = stats.sink("console").unwrap();
+    assert_eq!(console.queue_depth, 0);
+    assert!(console.max_queue_depth >= 1);
+    assert_eq!(console.latency.count(), 10);
+}
+
+#[test]
+fn test_render_prometheus() {
+    let mut latency = LatencyHistogram::default();
+    for ms in [0, 5, 5, 50, 500] {
+        latency.record(Duration::from_millis(ms));
+    }
+    let stats = LogStats {
+        sinks: vec![SinkStats {
+            sink: "logs/app.log".to_string(),
+            lines: 5,
+            queue_depth: 1,
+            max_queue_depth: 3,
+            latency,
+        }],
+        ..Default::default()
+    };
+    let text = stats.render_prometheus();
+    assert!(text.contains("tklog_queue_lines_total{sink=\"logs/app.log\"} 5\n"));
+    assert!(text.contains("tklog_queue_depth{sink=\"logs/app.log\"} 1\n"));
+    assert!(text.contains("tklog_queue_depth_max{sink=\"logs/app.log\"} 3\n"));
+    assert!(text.contains("tklog_write_latency_seconds_bucket{sink=\"logs/app.log\",le=\"0.001\"} 1\n"));
+    assert!(text.contains("tklog_write_latency_seconds_bucket{sink=\"logs/app.log\",le=\"0.01\"} 3\n"));
+    assert!(text.contains("tklog_write_latency_seconds_bucket{sink=\"logs/app.log\",le=\"0.1\"} 4\n"));
+    assert!(text.contains("tklog_write_latency_seconds_bucket{sink=\"logs/app.log\",le=\"+Inf\"} 5\n"));
+    assert!(text.contains("tklog_write_latency_seconds_sum{sink=\"logs/app.log\"} 0.56\n"));
+    assert!(text.contains("tklog_write_latency_seconds_count{sink=\"logs/app.log\"} 5\n"));
+}
@@ -0,0 +1,82 @@
+use std::{fs, sync::Arc, time::Duration};
+
+use tklog::{
+    clock::ManualClock,
+    storm::{StormConfig, StormScope},
+    sync::Logger,
+    Format, LEVEL,
+};
+
+fn emit(log: &mut Logger, module: &str, level: LEVEL, msg: &str) {
+    let s = log.fmt(module, level, "", 0, msg.to_string());
+    if !s.is_empty() {
+        log.print(level, module, s);
+    }
+}
+
+#[test]
+fn test_storm_per_module() {
+    let path = std::env::temp_dir().join(format!("tklog_storm_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let clock = Arc::new(ManualClock::new());
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Trace)
+        .set_format(Format::LevelFlag)
+        .set_formatter("{level} {message}\n")
+        .set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false)
+        .set_clock(clock.clone())
+        .set_storm_control(StormConfig {
+            threshold: 3,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+            scope: StormScope::PerModule,
+        });
+
+    emit(&mut log, "a", LEVEL::Error, "e1");
+    clock.advance(Duration::from_secs(11));
+    emit(&mut log, "a", LEVEL::Error, "e2");
+    emit(&mut log, "a", LEVEL::Info, "still flowing");
+    emit(&mut log, "a", LEVEL::Error, "e3");
+    emit(&mut log, "a", LEVEL::Error, "e4");
+    emit(&mut log, "a", LEVEL::Info, "dropped");
+    emit(&mut log, "a", LEVEL::Debug, "dropped");
+    emit(&mut log, "a", LEVEL::Warn, "warn passes");
+    emit(&mut log, "b", LEVEL::Info, "other module");
+    clock.advance(Duration::from_secs(30));
+    emit(&mut log, "a", LEVEL::Info, "back");
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "[ERROR] e1\n\
+         [ERROR] e2\n\
+         [INFO] still flowing\n\
+         [ERROR] e3\n\
+         [WARN] storm control: 3 Error lines within 10s from module `a`; suppressing lines below Warn for 30s\n\
+         [ERROR] e4\n\
+         [WARN] warn passes\n\
+         [INFO] other module\n\
+         [WARN] storm control lifted for module `a`: 2 lines suppressed\n\
+         [INFO] back\n"
+    );
+    assert_eq!(log.stats().storm_suppressed, 2);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_storm_global_scope() {
+    let clock = Arc::new(ManualClock::new());
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Trace).set_clock(clock.clone()).set_storm_control(StormConfig {
+        threshold: 2,
+        window: Duration::from_secs(10),
+        cooldown: Duration::from_secs(5),
+        scope: StormScope::Global,
+    });
+    emit(&mut log, "a", LEVEL::Error, "e1");
+    emit(&mut log, "b", LEVEL::Error, "e2");
+    assert!(log.fmt("c", LEVEL::Info, "", 0, "x".to_string()).is_empty());
+    clock.advance(Duration::from_secs(5));
+    assert!(!log.fmt("c", LEVEL::Info, "", 0, "x".to_string()).is_empty());
+    assert_eq!(log.stats().storm_suppressed, 1);
+}
@@ -0,0 +1,25 @@
+use chrono::{Local, TimeZone};
+use tklog::{sync::Logger, Format, TestMode, LEVEL};
+
+fn at(y: i32) -> TestMode {
+    TestMode { fixed_time: Local.with_ymd_and_hms(y, 5, 1, 12, 0, 0).unwrap(), fixed_seq_start: 1 }
+}
+
+#[test]
+fn test_time_subseq() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Time).set_test_mode(at(2090)).unwrap();
+    log.fmt("", LEVEL::Info, "", 0, "off".to_string());
+    log.set_time_subseq(true);
+    let lines: Vec<String> = (0..3).map(|_| log.fmt("", LEVEL::Info, "", 0, "tie".to_string()).file_body).collect();
+    assert_eq!(lines, [" 12:00:00#0 tie\n", " 12:00:00#1 tie\n", " 12:00:00#2 tie\n"]);
+
+    log.set_formatter("{time} {subseq}:{message}\n");
+    assert_eq!(log.fmt("", LEVEL::Info, "", 0, "own".to_string()).file_body, "12:00:00 3:own\n");
+
+    // A clock stepping back restarts at 0 and leaves the later time's count alone.
+    log.set_test_mode(at(2089)).unwrap();
+    assert_eq!(log.fmt("", LEVEL::Info, "", 0, "back".to_string()).file_body, "12:00:00 0:back\n");
+    log.set_test_mode(at(2090)).unwrap();
+    assert_eq!(log.fmt("", LEVEL::Info, "", 0, "again".to_string()).file_body, "12:00:00 4:again\n");
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "syslog")]
+
+mod common;
+
+use std::{fs, net::UdpSocket, time::Duration};
+
+use tklog::{
+    syslog::{severity, Facility, SyslogConfig, Transport},
+    sync::Logger,
+    Format, LEVEL, PRINTMODE,
+};
+
+use common::logfile;
+
+fn logger(transport: Transport, file: &str) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::LevelFlag).set_cutmode_by_size(file, 0, 0, false);
+    log.set_syslog(SyslogConfig { transport, facility: Facility::Local0, app_name: "shop".to_string() });
+    log
+}
+
+#[test]
+fn test_syslog_udp() {
+    let daemon = UdpSocket::bind("127.0.0.1:0").unwrap();
+    daemon.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let file = logfile("udp");
+    let mut log = logger(Transport::Udp(daemon.local_addr().unwrap()), &file);
+    let s = log.fmt("app", LEVEL::Warn, "", 0, "disk low".to_string());
+    log.print(LEVEL::Warn, "app", s);
+
+    let mut buf = [0u8; 1024];
+    let n = daemon.recv(&mut buf).unwrap();
+    let message = std::str::from_utf8(&buf[..n]).unwrap();
+    // Local0 is 16, Warn is severity 4: 16 * 8 + 4.
+    assert!(message.starts_with("<132>1 "), "{}", message);
+    let parts: Vec<&str> = message.splitn(8, ' ').collect();
+    assert!(parts[1].contains('T') && parts[1].len() >= 26, "{}", message);
+    assert_eq!(parts[3..], ["shop", &std::process::id().to_string(), "-", "-", "[WARN] disk low"]);
+    assert_eq!(fs::read_to_string(&file).unwrap_or_default(), "");
+    let _ = fs::remove_file(&file);
+}
+
+#[test]
+fn test_syslog_fallback() {
+    let file = logfile("fallback");
+    let socket = std::env::temp_dir().join(format!("tklog_syslog_missing_{}.sock", std::process::id()));
+    let mut log = logger(Transport::UnixSocket(socket), &file);
+    for m in ["first", "second"] {
+        let s = log.fmt("app", LEVEL::Error, "", 0, m.to_string());
+        log.print(LEVEL::Error, "app", s);
+    }
+    assert_eq!(fs::read_to_string(&file).unwrap(), "[ERROR] first\n[ERROR] second\n");
+    let _ = fs::remove_file(&file);
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_syslog_unix_async() {
+    let path = std::env::temp_dir().join(format!("tklog_syslog_{}.sock", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let daemon = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+    daemon.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag);
+    log.set_syslog(SyslogConfig { transport: Transport::UnixSocket(path.clone()), facility: Facility::User, app_name: String::new() });
+    let s = log.fmt("app", LEVEL::Info, "", 0, "queued".to_string());
+    log.log(LEVEL::Info, "app", s);
+
+    let mut buf = [0u8; 1024];
+    let n = daemon.recv(&mut buf).unwrap();
+    let message = std::str::from_utf8(&buf[..n]).unwrap();
+    assert!(message.starts_with(&format!("<{}>1 ", 8 + severity(LEVEL::Info))), "{}", message);
+    assert!(message.ends_with(&format!(" - {} - - [INFO] queued", std::process::id())), "{}", message);
+    let _ = fs::remove_file(&path);
+}
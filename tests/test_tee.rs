@@ -0,0 +1,79 @@
+mod common;
+
+use std::fs;
+
+use tklog::{cut::CutSize, output::OutputMode, sync::Logger, Error, Format, LEVEL};
+
+use common::dir;
+
+#[test]
+fn test_tee_writes_text_and_json() {
+    let dir = dir("sync");
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(dir.join("app.log").to_str().unwrap(), 1 << 20, 0, false);
+    log.add_tee_file(OutputMode::Json, CutSize::builder().file(dir.join("app.json.log")).max_size(1 << 20).build().unwrap()).unwrap();
+    for (level, module) in [(LEVEL::Info, "app"), (LEVEL::Warn, "app::db")] {
+        let s = log.fmt(module, level, "", 0, format!("{:?} line", level));
+        log.print(level, module, s);
+    }
+
+    assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "[INFO] Info line\n[WARN] Warn line\n");
+    let json = fs::read_to_string(dir.join("app.json.log")).unwrap();
+    let lines: Vec<&str> = json.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"level\":\"info\",\"msg\":\"Info line\",\"logger\":\"app\""), "{}", lines[0]);
+    assert!(lines[1].contains("\"level\":\"warn\",\"msg\":\"Warn line\",\"logger\":\"app::db\""), "{}", lines[1]);
+
+    let stats = log.stats();
+    let tee = stats.tee(&dir.join("app.json.log").to_string_lossy()).unwrap();
+    assert_eq!((tee.mode, tee.lines, tee.bytes), (OutputMode::Json, 2, json.len() as u64));
+
+    log.clear_tee_files();
+    let s = log.fmt("app", LEVEL::Info, "", 0, "after".to_string());
+    log.print(LEVEL::Info, "app", s);
+    assert_eq!(fs::read_to_string(dir.join("app.json.log")).unwrap(), json);
+    assert!(log.stats().tees.is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_tee_text_of_json_logger() {
+    let dir = dir("text");
+    let mut log = Logger::new();
+    log.set_console(false).preset_k8s().set_format(Format::LevelFlag);
+    log.add_tee_file(OutputMode::Text, CutSize::builder().file(dir.join("app.txt")).max_size(1 << 20).build().unwrap()).unwrap();
+    let s = log.fmt("app", LEVEL::Error, "", 0, "plain".to_string());
+    assert!(s.file_body.starts_with('{'));
+    log.print(LEVEL::Error, "app", s);
+    assert_eq!(fs::read_to_string(dir.join("app.txt")).unwrap(), "[ERROR] plain\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_tee_refusals() {
+    let dir = dir("refused");
+    let file = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_cutmode_by_size(file.to_str().unwrap(), 1 << 20, 0, false);
+    let cut = CutSize::builder().file(&file).max_size(1 << 20).build().unwrap();
+    assert!(matches!(log.add_tee_file(OutputMode::Logfmt, cut.clone()), Err(Error::UnsupportedTeeMode(OutputMode::Logfmt))));
+    assert!(matches!(log.add_tee_file(OutputMode::Json, cut), Err(Error::PathConflict { .. })));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_async_tee_flushes_both() {
+    let dir = dir("async");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag).set_cutmode_by_size(dir.join("app.log").to_str().unwrap(), 1 << 20, 0, false).await;
+    log.add_tee_file(OutputMode::Json, CutSize::builder().file(dir.join("app.json.log")).max_size(1 << 20).build().unwrap()).await.unwrap();
+    let s = log.fmt("app", LEVEL::Info, "", 0, "queued".to_string());
+    log.log(LEVEL::Info, "app", s);
+    log.flush().await;
+
+    assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "[INFO] queued\n");
+    let json = fs::read_to_string(dir.join("app.json.log")).unwrap();
+    assert!(json.contains("\"msg\":\"queued\""), "{}", json);
+    assert_eq!(log.stats().tee(&dir.join("app.json.log").to_string_lossy()).unwrap().bytes, json.len() as u64);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{debugs, infos, logerror::LogError, sync::Logger, warns, Error, Format, TestMode, LEVEL, MODE};
+
+/// `common::testmode`, its sequence from 100.
+fn testmode() -> TestMode {
+    TestMode { fixed_seq_start: 100, ..common::testmode() }
+}
+
+#[test]
+fn test_mode_exact_bytes() {
+    let path = std::env::temp_dir().join(format!("tklog_testmode_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    log.set_console(false)
+        .set_level(LEVEL::Trace)
+        .set_format(Format::LevelFlag | Format::Date | Format::Microseconds)
+        .set_formatter("{seq} {level} {time} {message}\n")
+        .set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+    log.set_test_mode(testmode()).unwrap();
+
+    let mut logger = Arc::new(Mutex::new(log));
+    let log = &mut logger;
+    debugs!(log, "first");
+    infos!(log, "second");
+    warns!(log, "third");
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        "100 [DEBUG] 2024-05-01 12:00:00.000000 first\n\
+         101 [INFO] 2024-05-01 12:00:00.000000 second\n\
+         102 [WARN] 2024-05-01 12:00:00.000000 third\n"
+    );
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_mode_refused_with_rotation() {
+    let path = std::env::temp_dir().join(format!("tklog_testmode_rotate_{}.log", std::process::id()));
+    let mut log = Logger::new();
+    log.set_console(false).set_cutmode_by_time(path.to_str().unwrap(), MODE::DAY, 0, false);
+    match log.set_test_mode(testmode()) {
+        Err(Error::TestModeWithRotation(filename)) => assert_eq!(filename, path.to_str().unwrap()),
+        _ => panic!("test mode must be refused while a rotating file handler is active"),
+    }
+    let _ = fs::remove_file(&path);
+}
+
+// In test mode a rotating file is refused in turn, by every cut setter.
+#[test]
+fn test_mode_refuses_later_rotation() {
+    let path = std::env::temp_dir().join(format!("tklog_testmode_later_{}.log", std::process::id()));
+    let filename = path.to_str().unwrap();
+    let _ = fs::remove_file(&path);
+    let refused = Arc::new(Mutex::new(Vec::new()));
+    let mut log = Logger::new();
+    log.set_console(false).set_error_handler({
+        let refused = refused.clone();
+        Box::new(move |e: LogError| refused.lock().unwrap().push(e.path().to_path_buf()))
+    });
+    log.set_test_mode(testmode()).unwrap();
+
+    log.set_cutmode_by_time(filename, MODE::DAY, 0, false);
+    log.set_cutmode_by_size(filename, 1024, 0, false);
+    log.set_cutmode_by_mixed(filename, MODE::HOUR, 1024, 0, false);
+    assert!(log.set_cutmode_by_size_str(filename, "1KB", 0, false).is_ok());
+    match log.set_cutmode_by_time_pattern("tklog-testmode-%Y%m%d.log", MODE::DAY, 0, false) {
+        Err(Error::TestModeWithRotation(pattern)) => assert_eq!(pattern, "tklog-testmode-%Y%m%d.log"),
+        _ => panic!("a file pattern must be refused in test mode"),
+    }
+    assert_eq!(*refused.lock().unwrap(), vec![path.clone(); 4]);
+    assert!(!path.exists());
+
+    // A file that doesn't rotate is still taken.
+    log.set_cutmode_by_size(filename, 0, 0, false);
+    let mut logger = Arc::new(Mutex::new(log));
+    infos!(&mut logger, "kept");
+    assert!(fs::read_to_string(&path).unwrap().ends_with("kept\n"));
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_mode_refuses_later_rotation_async() {
+    let path = std::env::temp_dir().join(format!("tklog_testmode_later_async_{}.log", std::process::id()));
+    let filename = path.to_str().unwrap();
+    let _ = fs::remove_file(&path);
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_error_handler(Box::new(|_| {}));
+    log.set_test_mode(testmode()).unwrap();
+    log.set_cutmode_by_time(filename, MODE::DAY, 0, false).await;
+    assert!(matches!(
+        log.set_cutmode_by_time_pattern("tklog-testmode-%Y%m%d.log", MODE::DAY, 0, false).await,
+        Err(Error::TestModeWithRotation(_))
+    ));
+    assert!(!path.exists());
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use std::{fs, thread};
+
+use tklog::{parse::parse_line, sync::Logger, Format, LEVEL, PRINTMODE};
+
+use common::logfile;
+
+fn write(log: &mut Logger, message: &str) {
+    let s = log.fmt("app", LEVEL::Info, "src/pool.rs", 7, message.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_thread_id_column() {
+    let file = logfile("column");
+    let format = Format::LevelFlag | Format::ShortFileName | Format::ThreadId;
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(format).set_cutmode_by_size(&file, 0, 0, false);
+    thread::scope(|s| {
+        thread::Builder::new().name("worker-1".to_string()).spawn_scoped(s, || write(&mut log, "named")).unwrap().join().unwrap();
+    });
+    thread::scope(|s| {
+        s.spawn(|| write(&mut log, "unnamed"));
+    });
+    let text = fs::read_to_string(&file).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "[INFO] worker-1 pool.rs 7:named");
+
+    // An unnamed thread shows its ID, which the parser gets back.
+    let parsed = parse_line(lines[1], format).unwrap();
+    let id = parsed.thread.unwrap();
+    assert!(id.parse::<u64>().is_ok(), "{}", id);
+    assert_eq!((parsed.file.as_deref(), parsed.message.as_str()), (Some("pool.rs"), "unnamed"));
+    let _ = fs::remove_file(&file);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_thread_id_async_task() {
+    let file = logfile("task");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::ThreadId).set_formatter("{level} {thread} task={task} {message}\n");
+    log.set_cutmode_by_size(&file, 0, 0, false).await;
+    let task = tokio::spawn(async move {
+        let s = log.fmt("app", LEVEL::Info, "", 0, "in a task".to_string());
+        log.log(LEVEL::Info, "app", s);
+        log.flush().await;
+        tokio::task::id()
+    })
+    .await
+    .unwrap();
+    let text = fs::read_to_string(&file).unwrap();
+    // The worker thread is named by tokio.
+    assert!(text.starts_with("[INFO] tokio-"), "{}", text);
+    assert!(text.ends_with(&format!(" task={} in a task\n", task)), "{}", text);
+    let _ = fs::remove_file(&file);
+}
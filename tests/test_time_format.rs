@@ -0,0 +1,36 @@
+use chrono::{Local, TimeZone};
+use tklog::{sync::Logger, Error, Format, TestMode, LEVEL};
+
+fn line(log: &mut Logger) -> String {
+    log.fmt("app", LEVEL::Info, "src/main.rs", 3, "m".to_string()).file_body
+}
+
+#[test]
+fn test_time_format() {
+    let time = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::LevelFlag | Format::Date | Format::Time | Format::ShortFileName);
+    log.set_test_mode(TestMode { fixed_time: time, fixed_seq_start: 1 }).unwrap();
+    log.set_time_format("%d/%m/%Y %Hh").unwrap();
+    assert_eq!(line(&mut log), "[INFO] 01/05/2024 12h main.rs 3:m\n");
+
+    log.set_formatter("{time}|{message}\n");
+    assert_eq!(line(&mut log), "01/05/2024 12h|m\n");
+
+    // Without any time flag, there is no time to render.
+    log.set_format(Format::LevelFlag);
+    assert_eq!(line(&mut log), "|m\n");
+
+    log.set_format(Format::Time).set_time_rfc3339();
+    assert_eq!(line(&mut log), format!("{}|m\n", time.format("%Y-%m-%dT%H:%M:%S.000000%:z")));
+}
+
+#[test]
+fn test_time_format_invalid() {
+    let mut log = Logger::new();
+    log.set_console(false).set_format(Format::Time).set_formatter("{time}|{message}\n");
+    assert!(matches!(log.set_time_format("%Y-%Q"), Err(Error::InvalidTimeFormat(f)) if f == "%Y-%Q"));
+    // The logger keeps its layout and doesn't panic.
+    let s = line(&mut log);
+    assert!(s.ends_with("|m\n") && s.len() == "00:00:00|m\n".len(), "{}", s);
+}
@@ -0,0 +1,39 @@
+use std::fs;
+
+use chrono::{FixedOffset, Utc};
+use tklog::{parse::parse_line, set_time_zone, sync::Logger, Format, LEVEL};
+
+const FORMAT: u8 = Format::LevelFlag | Format::Date | Format::Time;
+
+/// The minute of the last line of `path`, which must be one of the minutes
+/// `offset` read while the line was written.
+fn assert_last_minute(path: &std::path::Path, offset: FixedOffset, write: impl FnOnce()) {
+    let minute = || Utc::now().with_timezone(&offset).format("%Y-%m-%d %H:%M").to_string();
+    let before = minute();
+    write();
+    let after = minute();
+    let content = fs::read_to_string(path).unwrap();
+    let r = parse_line(content.lines().last().unwrap(), FORMAT).unwrap();
+    let time = r.time.unwrap();
+    assert!(time.starts_with(&before) || time.starts_with(&after), "{} not in {}..{}", time, before, after);
+}
+
+#[test]
+fn test_invalid_tz_falls_back_to_utc() {
+    std::env::set_var("TZ", "Invalid/Nowhere");
+    let path = std::env::temp_dir().join(format!("tklog_tz_{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut log = Logger::new();
+    log.set_console(false).set_format(FORMAT).set_cutmode_by_size(path.to_str().unwrap(), 0, 0, false);
+
+    let mut emit = |msg: &str| {
+        let s = log.fmt("tz", LEVEL::Info, "", 0, msg.to_string());
+        log.print(LEVEL::Info, "tz", s);
+    };
+    assert_last_minute(&path, FixedOffset::east_opt(0).unwrap(), || emit("in utc"));
+
+    let ist = FixedOffset::east_opt(5 * 3600 + 1800).unwrap();
+    set_time_zone(ist).unwrap();
+    assert_last_minute(&path, ist, || emit("in ist"));
+    let _ = fs::remove_file(&path);
+}
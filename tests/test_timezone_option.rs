@@ -0,0 +1,51 @@
+use std::{fs, thread, time::Duration};
+
+use chrono::{FixedOffset, Utc};
+use tklog::{parse::parse_line, set_time_zone, sync::Logger, Error, Format, TimeZoneOption, LEVEL, PRINTMODE};
+
+const FORMAT: u8 = Format::LevelFlag | Format::Date | Format::Time;
+
+/// The minutes `offset` read around `write`.
+fn minutes(offset: FixedOffset, pattern: &str, write: impl FnOnce()) -> [String; 2] {
+    let minute = || Utc::now().with_timezone(&offset).format(pattern).to_string();
+    let before = minute();
+    write();
+    [before, minute()]
+}
+
+// The zone is process-wide: one test for the whole file.
+#[test]
+fn test_timezone_applies_to_lines_and_backups() {
+    assert!(matches!(set_time_zone(TimeZoneOption::FixedOffset(86400)), Err(Error::InvalidTimeZone(86400))));
+
+    let dir = std::env::temp_dir().join(format!("tklog_tz_option_{}", std::process::id()));
+    for (zone, offset) in [(TimeZoneOption::Utc, 0), (TimeZoneOption::FixedOffset(-(9 * 3600 + 1800)), -(9 * 3600 + 1800))] {
+        set_time_zone(zone).unwrap();
+        let offset = FixedOffset::east_opt(offset).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        let mut log = Logger::new();
+        log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(FORMAT);
+        log.set_cutmode_by_interval(&path.to_string_lossy(), Duration::from_secs(1), 0, false);
+        let mut emit = |msg: &str| {
+            let s = log.fmt("tz", LEVEL::Info, "", 0, msg.to_string());
+            log.print(LEVEL::Info, "tz", s);
+        };
+
+        let seen = minutes(offset, "%Y-%m-%d %H:%M", || emit("first"));
+        let content = fs::read_to_string(&path).unwrap();
+        let time = parse_line(content.lines().last().unwrap(), FORMAT).unwrap().time.unwrap();
+        assert!(seen.iter().any(|m| time.starts_with(m)), "{} not in {:?}", time, seen);
+
+        // The backup of the first period is stamped in the same zone.
+        thread::sleep(Duration::from_millis(1100));
+        let seen = minutes(offset, "%Y%m%d%H%M", || emit("second"));
+        let backups: Vec<String> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).filter(|n| n != "app.log").collect();
+        assert_eq!(backups.len(), 1, "{:?}", backups);
+        assert!(seen.iter().any(|m| backups[0].starts_with(&format!("app_{}", m))), "{:?} not in {:?}", backups, seen);
+    }
+
+    set_time_zone(TimeZoneOption::Local).unwrap();
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use tklog::{
+    timer,
+    timing::{self, Timer, MAX_LABELS},
+    LEVEL,
+};
+
+#[test]
+fn test_timing_report() {
+    timing::record("before", Duration::from_millis(1));
+    tklog::set_timing_report(true);
+    for ms in [3, 5, 1] {
+        timing::record("load", Duration::from_millis(ms));
+    }
+    timing::record("parse", Duration::from_millis(20));
+    drop(Timer::start(format!("label {}", 0)));
+    // Recorded whether the line is logged or not.
+    timer!(LEVEL::Trace, "timed").finish();
+    for i in 0..MAX_LABELS + 10 {
+        timing::record(&format!("label {}", i), Duration::from_micros(1));
+    }
+    tklog::set_timing_report(false);
+    timing::record("after", Duration::from_millis(1));
+
+    let report = tklog::timing_report();
+    assert_eq!(report.labels.len(), MAX_LABELS + 1);
+    assert_eq!(report.labels[0].0, "parse");
+    let (label, load) = &report.labels[1];
+    assert_eq!((label.as_str(), load.count, load.total, load.max), ("load", 3, Duration::from_millis(9), Duration::from_millis(5)));
+    let other = report.labels.iter().find(|(l, _)| l == "other").unwrap().1;
+    assert_eq!(other.count, 13, "the labels past the cap");
+    assert_eq!(report.labels.iter().find(|(l, _)| l == "label 0").unwrap().1.count, 2);
+    assert_eq!(report.labels.iter().find(|(l, _)| l == "timed").unwrap().1.count, 1);
+    assert!(!report.labels.iter().any(|(l, _)| l == "before" || l == "after"));
+
+    let table = report.to_string();
+    assert!(table.starts_with("label "), "{}", table);
+    assert!(table.lines().nth(1).unwrap().starts_with("parse "), "{}", table);
+    assert!(report.to_json().starts_with("{\"parse\":{\"count\":1,\"total_us\":20000,\"max_us\":20000},\"load\":{\"count\":3,"));
+}
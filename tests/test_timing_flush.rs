@@ -0,0 +1,28 @@
+mod common;
+
+use std::{fs, time::Duration};
+
+use tklog::{timing, Format, LOG, PRINTMODE};
+
+use common::logfile;
+
+// The only test of this file on `LOG` and the timing report.
+#[test]
+fn test_timing_report_on_flush_guard() {
+    let path = logfile("report");
+    LOG.set_console(false).set_printmode(PRINTMODE::DELAY).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 0, 0, false);
+
+    // Off, the guard logs no report.
+    timing::record("off", Duration::from_millis(1));
+    drop(tklog::flush_guard());
+    assert_eq!(fs::read_to_string(&path).unwrap_or_default(), "");
+
+    tklog::set_timing_report(true);
+    timing::record("load", Duration::from_millis(3));
+    drop(tklog::flush_guard());
+    let written = fs::read_to_string(&path).unwrap();
+    assert!(written.starts_with("[INFO] timing report\nlabel "), "{}", written);
+    assert!(written.lines().any(|l| l.starts_with("load ")), "{}", written);
+    assert!(!written.contains("off"), "{}", written);
+    let _ = fs::remove_file(&path);
+}
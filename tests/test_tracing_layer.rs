@@ -0,0 +1,55 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use tklog::{sync::Logger, tracing_layer::TklogLayer, Format, LogOption, LEVEL, LOG, PRINTMODE};
+use tracing_subscriber::layer::SubscriberExt;
+
+use common::logfile;
+
+#[test]
+fn test_layer_with_own_logger() {
+    let path = logfile("own");
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Debug).set_format(Format::LevelFlag).set_cutmode_by_size(&path, 1 << 20, 3, true);
+    log.set_mod_option("app::db", LogOption { level: Some(LEVEL::Warn), format: None, formatter: None, console: None, fileoption: None });
+    let logger = Arc::new(Mutex::new(log));
+
+    let subscriber = tracing_subscriber::registry().with(TklogLayer::new(logger.clone()).with_span_names(true));
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(target: "app", user = "ann", attempts = 3, "signed in");
+        tracing::trace!(target: "app", "below the level");
+        let request = tracing::info_span!("request", id = 7);
+        let _request = request.enter();
+        let db = tracing::debug_span!("db");
+        let _db = db.enter();
+        tracing::warn!(target: "app::db", query = "select 1", "slow");
+        tracing::info!(target: "app::db", "below the module level");
+    });
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] signed in user=ann attempts=3\n[WARN] request:db: slow query=\"select 1\"\n");
+    let file = logger.lock().unwrap().config().file.unwrap();
+    assert_eq!((file.maxsize, file.maxbackups, file.compress), (1 << 20, 3, true));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_layer_with_global_log() {
+    let path = logfile("global");
+    LOG.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_level(LEVEL::Info).set_format(Format::LevelFlag | Format::ShortFileName).set_cutmode_by_size(&path, 1 << 20, 0, false);
+
+    let line = line!() + 3;
+    let subscriber = tracing_subscriber::registry().with(TklogLayer::global());
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::error!(code = 503, "upstream down");
+        tracing::debug!("below the level");
+    });
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), format!("[ERROR] test_tracing_layer.rs {}:upstream down code=503\n", line));
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use tklog::{sync::Logger, verify, Format, LEVEL};
+
+use common::dir;
+
+const KEY: &[u8] = b"audit key";
+
+fn logger(path: &Path, max_size: u64, compress: bool) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_level(LEVEL::Trace).set_format(Format::Nano).set_tamper_evidence(KEY).set_cutmode_by_size(path.to_str().unwrap(), max_size, 0, compress);
+    log
+}
+
+fn write(log: &mut Logger, msg: &str) {
+    let s = log.fmt("verify", LEVEL::Info, "", 0, msg.to_string());
+    log.print(LEVEL::Info, "verify", s);
+}
+
+#[test]
+fn test_chain_across_rotations_and_runs() {
+    let dir = dir("rotate");
+    let path = dir.join("app.log");
+    let mut log = logger(&path, 300, false);
+    for i in 0..12 {
+        write(&mut log, &format!("request {} served\n", i));
+    }
+    write(&mut log, "two\nlines\n");
+    drop(log);
+    // A second run appends to the live file and picks the chain up.
+    let mut log = logger(&path, 300, false);
+    write(&mut log, "restarted\n");
+
+    let report = verify::chain(&path, KEY).unwrap();
+    assert!(report.is_intact(), "{:?}", report.broken);
+    assert_eq!(report.lines, 14);
+    assert!(report.files.len() > 2, "{:?}", report.files);
+    assert_eq!(report.files.last().unwrap(), &path);
+    let live = fs::read_to_string(&path).unwrap();
+    assert!(live.lines().all(|l| l.len() > 37 && l[l.len() - 37..].starts_with(" mac=")), "{}", live);
+
+    assert!(!verify::chain(&path, b"other key").unwrap().is_intact());
+
+    // Editing a line breaks the chain right there.
+    let backup = report.files[1].clone();
+    let content = fs::read_to_string(&backup).unwrap();
+    fs::write(&backup, content.replacen("served", "failed", 1)).unwrap();
+    let broken = verify::chain(&path, KEY).unwrap().broken.unwrap();
+    assert_eq!((broken.file, broken.line, broken.reason.as_str()), (backup.clone(), 2, "MAC mismatch"));
+
+    // Removing a whole backup leaves the older ones unlinked.
+    fs::remove_file(&backup).unwrap();
+    let broken = verify::chain(&path, KEY).unwrap().broken.unwrap();
+    assert_eq!((broken.file, broken.line), (report.files[0].clone(), 0));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_chain_through_compressed_backups() {
+    let dir = dir("gzip");
+    let path = dir.join("app.log");
+    let mut log = logger(&path, 200, true);
+    for i in 0..10 {
+        write(&mut log, &format!("line {}\n", i));
+    }
+    // Compression runs in the background; wait for the plain backups to go.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !backups_compressed(&dir) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let report = verify::chain(&path, KEY).unwrap();
+    assert!(report.is_intact(), "{:?}", report.broken);
+    assert_eq!(report.lines, 10);
+    assert!(report.files.iter().any(|f| f.extension().is_some_and(|e| e == "gz")), "{:?}", report.files);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn backups_compressed(dir: &Path) -> bool {
+    let names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+    names.iter().filter(|n| n.starts_with("app_")).all(|n| n.ends_with(".gz"))
+}
+
+#[tokio::test]
+async fn test_async_chain() {
+    let dir = dir("async");
+    let path = dir.join("app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_format(Format::Nano).set_tamper_evidence(KEY).set_cutmode_by_size(path.to_str().unwrap(), 200, 0, false).await;
+    for i in 0..10 {
+        log.enqueue(LEVEL::Info, "verify", "", 0, format!("line {}\n", i));
+    }
+    log.flush().await;
+    let report = verify::chain(&path, KEY).unwrap();
+    assert!(report.is_intact(), "{:?}", report.broken);
+    assert_eq!(report.lines, 10);
+    let _ = fs::remove_dir_all(&dir);
+}
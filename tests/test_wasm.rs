@@ -0,0 +1,63 @@
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use js_sys::{Array, Function};
+use tklog::{sync::Logger, wasm::Console, ConsoleStream, Format, LEVEL};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const LEVELS: [LEVEL; 6] = [LEVEL::Trace, LEVEL::Debug, LEVEL::Info, LEVEL::Warn, LEVEL::Error, LEVEL::Fatal];
+
+/// The lines `f` gives the console, as `method line`.
+fn console_lines(f: impl FnOnce()) -> Vec<String> {
+    let capture = "const lines = [], kept = {}; \
+                   for (const m of ['log', 'debug', 'info', 'warn', 'error']) { kept[m] = console[m]; console[m] = (l) => lines.push(m + ' ' + l); } \
+                   return () => { Object.assign(console, kept); return lines; };";
+    let restore: Function = Function::new_no_args(capture).call0(&JsValue::NULL).unwrap().into();
+    f();
+    let lines: Array = restore.call0(&JsValue::NULL).unwrap().into();
+    lines.iter().map(|l| l.as_string().unwrap()).collect()
+}
+
+fn log_each_level(log: &mut Logger) {
+    for level in LEVELS {
+        let s = log.fmt("app", level, "", 0, "started".to_string());
+        log.print(level, "app", s);
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_wasm_console_sink() {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Trace).set_format(Format::LevelFlag).set_custom_sink(Box::new(Console)).set_custom_sink_only(true);
+    assert_eq!(
+        console_lines(|| log_each_level(&mut log)),
+        [
+            "debug [TRACE] started",
+            "debug [DEBUG] started",
+            "info [INFO] started",
+            "warn [WARN] started",
+            "error [ERROR] started",
+            "error [FATAL] started",
+        ]
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_wasm_console_stream() {
+    let mut log = Logger::new();
+    log.set_level(LEVEL::Trace).set_format(Format::LevelFlag).set_console_stream(ConsoleStream::SplitAt(LEVEL::Warn));
+    assert_eq!(
+        console_lines(|| log_each_level(&mut log)),
+        ["log [TRACE] started", "log [DEBUG] started", "log [INFO] started", "error [WARN] started", "error [ERROR] started", "error [FATAL] started"]
+    );
+    // The time of the lines, by `js_sys::Date`.
+    log.set_format(Format::Date);
+    let lines = console_lines(|| {
+        let s = log.fmt("app", LEVEL::Info, "", 0, String::new());
+        log.print(LEVEL::Info, "app", s);
+    });
+    let today = js_sys::Date::new_0();
+    let date = format!("{}-{:02}-{:02}", today.get_full_year(), today.get_month() + 1, today.get_date());
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(), ["log", date.as_str()]);
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use tklog::{sync::Logger, Format, LEVEL, PRINTMODE};
+
+use common::dir;
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+fn logger(path: &Path) -> Logger {
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false);
+    log
+}
+
+fn write(log: &mut Logger, msg: &str) {
+    let s = log.fmt("app", LEVEL::Info, "", 0, msg.to_string());
+    log.print(LEVEL::Info, "app", s);
+}
+
+#[test]
+fn test_write_buffer_size() {
+    let dir = dir("size");
+    let path = dir.join("app.log");
+    let mut log = logger(&path);
+    log.set_buffer_size(8);
+    write(&mut log, "abc");
+    assert_eq!(read(&path), "");
+    // The buffer is written out once it holds 8 bytes.
+    write(&mut log, "defgh");
+    assert_eq!(read(&path), "abcdefgh");
+    write(&mut log, "ij");
+    log.flush();
+    assert_eq!(read(&path), "abcdefghij");
+    // Back to writing every line.
+    write(&mut log, "k");
+    log.set_buffer_size(0);
+    assert_eq!(read(&path), "abcdefghijk");
+    write(&mut log, "l");
+    assert_eq!(read(&path), "abcdefghijkl");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_write_buffer_interval() {
+    let dir = dir("interval");
+    let path = dir.join("app.log");
+    let mut log = logger(&path);
+    log.set_buffer_size(1 << 16).set_flush_interval(Duration::from_millis(50));
+    write(&mut log, "early");
+    assert_eq!(read(&path), "");
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(read(&path), "early");
+
+    // Zero turns the periodic flush off at once.
+    log.set_flush_interval(Duration::ZERO);
+    write(&mut log, "late");
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(read(&path), "early");
+    drop(log);
+    assert_eq!(read(&path), "earlylate");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// The buffer is written out before each cut, so no line lands in the wrong file.
+#[test]
+fn test_write_buffer_rotation() {
+    let dir = dir("rotation");
+    let path = dir.join("app.log");
+    let mut log = Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano).set_cutmode_by_size(path.to_str().unwrap(), 10, 0, false);
+    log.set_buffer_size(1 << 16).set_flush_interval(Duration::from_millis(1));
+    for i in 0..3 {
+        write(&mut log, &format!("line {:04}", i));
+        thread::sleep(Duration::from_millis(20));
+    }
+    log.flush();
+    assert_eq!(read(&dir.join("app_1.log")), "line 0000");
+    assert_eq!(read(&dir.join("app_2.log")), "line 0001");
+    assert_eq!(read(&path), "line 0002");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_write_buffer_async() {
+    let dir = dir("async");
+    let path = dir.join("app.log");
+    let mut log = tklog::Async::Logger::new();
+    log.set_console(false).set_printmode(PRINTMODE::PUNCTUAL).set_format(Format::Nano);
+    log.set_cutmode_by_size(path.to_str().unwrap(), 1 << 20, 0, false).await;
+    log.set_buffer_size(1 << 16).set_flush_interval(Duration::from_millis(50));
+    let s = log.fmt("app", LEVEL::Info, "", 0, "before".to_string());
+    log.log(LEVEL::Info, "app", s);
+    log.flush().await;
+    assert_eq!(read(&path), "before");
+    let s = log.fmt("app", LEVEL::Info, "", 0, "after".to_string());
+    log.log(LEVEL::Info, "app", s);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(read(&path), "beforeafter");
+    let _ = fs::remove_dir_all(&dir);
+}